@@ -0,0 +1,18 @@
+//! The output of `discover --json`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/parser.rs"]
+mod parser;
+
+fuzz_target!(|data: &[u8]| {
+    let stdout = String::from_utf8_lossy(data);
+    if let Ok(devices) = parser::parse_discovered_devices(&stdout) {
+        for device in devices {
+            assert_eq!(parser::clean_text(&device.name), device.name);
+        }
+    }
+});
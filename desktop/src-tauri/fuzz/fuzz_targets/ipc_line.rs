@@ -0,0 +1,23 @@
+//! One stdout line of a listener or send, as `cli_ipc::parse_line` sees it.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/parser.rs"]
+mod parser;
+
+fuzz_target!(|data: &[u8]| {
+    let line = String::from_utf8_lossy(data);
+    let Some(value) = parser::ipc_value(&line) else {
+        return;
+    };
+    if let Some(request) = parser::parse_confirm_request(value.clone()) {
+        assert_eq!(parser::clean_text(&request.path), request.path);
+        assert!(request.sha256.is_none_or(|sha256| sha256.len() == 64));
+    }
+    if let Some(progress) = parser::parse_progress(value) {
+        assert!(progress.total.is_none_or(|total| progress.bytes <= total));
+    }
+});
@@ -0,0 +1,111 @@
+use crate::activity_search::capture;
+use crate::cli_ipc::SentFile;
+use crate::events::{emit_app_event, AppEvent};
+use crate::log_queue::enqueue_activity;
+use crate::AppState;
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Manager, State};
+
+/// One entry of the combined receive/send activity stream. Receiving and
+/// sending run in separate CLI children, so entries from both can interleave;
+/// `send_id` tells concurrent sends apart.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub(crate) enum ActivityEntry {
+    System {
+        line: String,
+    },
+    ReceiveLog {
+        stream: String,
+        line: String,
+    },
+    SendStarted {
+        send_id: u64,
+        path: String,
+        target: String,
+    },
+    SendOutput {
+        send_id: u64,
+        stream: String,
+        chunk: String,
+    },
+    SendFinished {
+        send_id: u64,
+        success: bool,
+    },
+    /// Every file of a finished send, for the per-file list.
+    SendManifest {
+        send_id: u64,
+        files: Vec<SentFile>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActivityStatusPayload {
+    receiving: bool,
+    active_sends: Vec<u64>,
+}
+
+/// Child output goes through the bounded log queue so chatty children cannot
+/// flood the event bus. System lines, send lifecycle and manifests are rare
+/// and must not be dropped with the oldest output, so they are emitted
+/// directly.
+pub(crate) fn emit_activity(app: &AppHandle, entry: ActivityEntry) {
+    capture(app, &entry);
+    if matches!(
+        entry,
+        ActivityEntry::ReceiveLog { .. } | ActivityEntry::SendOutput { .. }
+    ) {
+        enqueue_activity(app, entry);
+    } else {
+        emit_app_event(app, AppEvent::Activity(entry));
+    }
+}
+
+pub(crate) fn begin_send(app: &AppHandle, path: &str, target: String) -> u64 {
+    let state = app.state::<AppState>();
+    let send_id = state.next_send_id.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Ok(mut guard) = state.active_sends.lock() {
+        guard.insert(send_id);
+    }
+    emit_activity(
+        app,
+        ActivityEntry::SendStarted {
+            send_id,
+            path: path.to_string(),
+            target,
+        },
+    );
+    send_id
+}
+
+pub(crate) fn finish_send(app: &AppHandle, send_id: u64, success: bool) {
+    let state = app.state::<AppState>();
+    if let Ok(mut guard) = state.active_sends.lock() {
+        guard.remove(&send_id);
+    }
+    emit_activity(app, ActivityEntry::SendFinished { send_id, success });
+}
+
+#[tauri::command]
+pub(crate) fn activity_status(state: State<AppState>) -> Result<ActivityStatusPayload, String> {
+    let receiving = crate::inspect_listen_state(&state)?.running;
+    let mut active_sends = state
+        .active_sends
+        .lock()
+        .map_err(|_| "failed to lock active sends".to_string())?
+        .iter()
+        .copied()
+        .collect::<Vec<u64>>();
+    active_sends.sort_unstable();
+    Ok(ActivityStatusPayload {
+        receiving,
+        active_sends,
+    })
+}
@@ -0,0 +1,539 @@
+use crate::activity::ActivityEntry;
+use crate::history::{transfer_items, TransferDirection};
+use crate::history_retention::HistoryRetention;
+use crate::storage::{data_file, now_ms};
+use crate::AppState;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const ACTIVITY_INDEX_FILE: &str = "activity-index.sqlite";
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+/// Log lines waiting for the next flush; the oldest are dropped beyond this.
+const PENDING_LOG_LIMIT: usize = 5000;
+/// Log lines kept in the index. History entries are never pruned, so the
+/// index outlives the capped history files.
+const INDEXED_LOG_LIMIT: i64 = 50_000;
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 500;
+
+/// Deleted rows are zeroed, in the tables and in the FTS5 index, so pruned
+/// history does not linger in the file.
+const SCHEMA: &str = "
+    PRAGMA secure_delete = ON;
+    CREATE VIRTUAL TABLE IF NOT EXISTS activity USING fts5(
+        kind UNINDEXED,
+        at_ms UNINDEXED,
+        source_id UNINDEXED,
+        success UNINDEXED,
+        path,
+        peer,
+        text,
+        tokenize = 'unicode61 remove_diacritics 2'
+    );
+    CREATE TABLE IF NOT EXISTS indexed_history (
+        direction TEXT PRIMARY KEY,
+        last_id INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS transfers (
+        direction TEXT NOT NULL,
+        id INTEGER NOT NULL,
+        at_ms INTEGER NOT NULL,
+        peer TEXT,
+        success INTEGER NOT NULL,
+        bytes INTEGER,
+        duration_ms INTEGER,
+        PRIMARY KEY (direction, id)
+    );
+    CREATE INDEX IF NOT EXISTS transfers_at_ms ON transfers (at_ms);
+    INSERT INTO activity (activity, rank) VALUES ('secure-delete', 1);
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ActivityKind {
+    Send,
+    Receive,
+    Log,
+}
+
+impl ActivityKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ActivityKind::Send => "send",
+            ActivityKind::Receive => "receive",
+            ActivityKind::Log => "log",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "send" => ActivityKind::Send,
+            "receive" => ActivityKind::Receive,
+            _ => ActivityKind::Log,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ActivityFilters {
+    /// Empty means every kind.
+    kinds: Vec<ActivityKind>,
+    /// Part of a host, device name or address.
+    peer: Option<String>,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActivityMatch {
+    kind: ActivityKind,
+    at_ms: u64,
+    /// The send or receive history id; `None` for log lines.
+    history_id: Option<u64>,
+    path: Option<String>,
+    peer: Option<String>,
+    success: Option<bool>,
+    /// The matching text with hits in `[` `]`.
+    snippet: String,
+}
+
+struct PendingLog {
+    at_ms: u64,
+    line: String,
+}
+
+/// The open index of the active profile and log lines not yet written to it.
+#[derive(Default)]
+pub(crate) struct ActivityIndex {
+    connection: Option<(PathBuf, Connection)>,
+    pending: Vec<PendingLog>,
+    /// Set while history retention is `nothing`.
+    paused: bool,
+}
+
+impl ActivityIndex {
+    /// Opens the index at `path`, reopening it after a profile switch.
+    fn connection(&mut self, path: PathBuf) -> Result<&mut Connection, String> {
+        if self
+            .connection
+            .as_ref()
+            .is_none_or(|(open_path, _)| *open_path != path)
+        {
+            let connection = Connection::open(&path)
+                .map_err(|err| format!("failed to open {}: {err}", path.display()))?;
+            connection
+                .busy_timeout(Duration::from_secs(5))
+                .and_then(|_| connection.execute_batch(SCHEMA))
+                .map_err(|err| format!("failed to prepare activity index: {err}"))?;
+            self.connection = Some((path, connection));
+        }
+        Ok(&mut self.connection.as_mut().expect("index was just opened").1)
+    }
+}
+
+fn lock_index(state: &AppState) -> Result<std::sync::MutexGuard<'_, ActivityIndex>, String> {
+    state
+        .activity_index
+        .lock()
+        .map_err(|_| "failed to lock activity index".to_string())
+}
+
+/// Lines worth finding later; send output keeps only the final state of
+/// `\r`-redrawn progress lines.
+fn log_lines(entry: &ActivityEntry) -> Vec<String> {
+    match entry {
+        ActivityEntry::System { line } | ActivityEntry::ReceiveLog { line, .. } => {
+            vec![line.trim().to_string()]
+        }
+        ActivityEntry::SendOutput { chunk, .. } => chunk
+            .lines()
+            .filter_map(|line| line.rsplit('\r').next())
+            .map(|line| line.trim().to_string())
+            .collect(),
+        ActivityEntry::SendStarted { .. }
+        | ActivityEntry::SendFinished { .. }
+        | ActivityEntry::SendManifest { .. } => Vec::new(),
+    }
+}
+
+/// Queues the log lines of `entry` for indexing. Called for every activity
+/// entry, so it only appends; the index task writes them in batches.
+pub(crate) fn capture(app: &AppHandle, entry: &ActivityEntry) {
+    let lines = log_lines(entry);
+    if lines.iter().all(String::is_empty) {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let Ok(mut index) = lock_index(state.inner()) else {
+        return;
+    };
+    if index.paused {
+        return;
+    }
+    let at_ms = now_ms();
+    index.pending.extend(
+        lines
+            .into_iter()
+            .filter(|line| !line.is_empty())
+            .map(|line| PendingLog { at_ms, line }),
+    );
+    let overflow = index.pending.len().saturating_sub(PENDING_LOG_LIMIT);
+    index.pending.drain(..overflow);
+}
+
+fn flush_logs(app: &AppHandle, index: &mut ActivityIndex) -> Result<(), String> {
+    if index.pending.is_empty() {
+        return Ok(());
+    }
+    let pending = std::mem::take(&mut index.pending);
+    let connection = index.connection(data_file(app, ACTIVITY_INDEX_FILE)?)?;
+    let transaction = connection
+        .transaction()
+        .map_err(|err| format!("failed to write activity index: {err}"))?;
+    for log in &pending {
+        transaction
+            .execute(
+                "INSERT INTO activity (kind, at_ms, text) VALUES ('log', ?1, ?2)",
+                params![log.at_ms as i64, log.line],
+            )
+            .map_err(|err| format!("failed to write activity index: {err}"))?;
+    }
+    transaction
+        .execute(
+            "DELETE FROM activity WHERE kind = 'log' AND rowid <= (
+                SELECT rowid FROM activity WHERE kind = 'log'
+                ORDER BY rowid DESC LIMIT 1 OFFSET ?1
+            )",
+            params![INDEXED_LOG_LIMIT],
+        )
+        .and_then(|_| transaction.commit())
+        .map_err(|err| format!("failed to write activity index: {err}"))
+}
+
+/// Adds sends and receives recorded since the last search, to the search
+/// table and to `transfers` for usage statistics. The history files keep
+/// their ids when they drop old entries, so the highest indexed id is
+/// enough to find the new ones.
+fn sync_history(app: &AppHandle, index: &mut ActivityIndex) -> Result<(), String> {
+    let items = transfer_items(app)?;
+    let connection = index.connection(data_file(app, ACTIVITY_INDEX_FILE)?)?;
+    let transaction = connection
+        .transaction()
+        .map_err(|err| format!("failed to write activity index: {err}"))?;
+    for (direction, kind) in [
+        (TransferDirection::Send, ActivityKind::Send),
+        (TransferDirection::Receive, ActivityKind::Receive),
+    ] {
+        for item in items.iter().filter(|item| item.direction == direction) {
+            transaction
+                .execute(
+                    "INSERT OR IGNORE INTO transfers
+                     (direction, id, at_ms, peer, success, bytes, duration_ms)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        kind.as_str(),
+                        item.id as i64,
+                        item.at_ms as i64,
+                        item.peer,
+                        item.success,
+                        item.bytes.map(|bytes| bytes as i64),
+                        item.duration_ms.map(|duration| duration as i64),
+                    ],
+                )
+                .map_err(|err| format!("failed to write activity index: {err}"))?;
+        }
+        let last_id: i64 = transaction
+            .query_row(
+                "SELECT COALESCE(MAX(last_id), 0) FROM indexed_history WHERE direction = ?1",
+                params![kind.as_str()],
+                |row| row.get(0),
+            )
+            .map_err(|err| format!("failed to read activity index: {err}"))?;
+        let mut newest = last_id;
+        for item in items
+            .iter()
+            .filter(|item| item.direction == direction && item.id as i64 > last_id)
+        {
+            let text = item
+                .note
+                .iter()
+                .cloned()
+                .chain(item.tags.iter().map(|tag| format!("#{tag}")))
+                .collect::<Vec<_>>()
+                .join(" ");
+            transaction
+                .execute(
+                    "INSERT INTO activity (kind, at_ms, source_id, success, path, peer, text)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        kind.as_str(),
+                        item.at_ms as i64,
+                        item.id as i64,
+                        item.success,
+                        item.path,
+                        item.peer,
+                        text,
+                    ],
+                )
+                .map_err(|err| format!("failed to write activity index: {err}"))?;
+            newest = newest.max(item.id as i64);
+        }
+        transaction
+            .execute(
+                "INSERT INTO indexed_history (direction, last_id) VALUES (?1, ?2)
+                 ON CONFLICT (direction) DO UPDATE SET last_id = excluded.last_id",
+                params![kind.as_str(), newest],
+            )
+            .map_err(|err| format!("failed to write activity index: {err}"))?;
+    }
+    transaction
+        .commit()
+        .map_err(|err| format!("failed to write activity index: {err}"))
+}
+
+/// Applies history retention to the index. `Entries` keeps as many sends
+/// and receives as the history files, and the log lines since the oldest
+/// of them.
+pub(crate) fn prune_index(app: &AppHandle, retention: HistoryRetention) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut index = lock_index(state.inner())?;
+    index.paused = !retention.records();
+    if index.paused {
+        index.pending.clear();
+    }
+    let statements: Vec<(&str, Vec<Value>)> = match retention {
+        HistoryRetention::KeepAll => return Ok(()),
+        HistoryRetention::Nothing => vec![
+            ("DELETE FROM activity", Vec::new()),
+            ("DELETE FROM transfers", Vec::new()),
+        ],
+        HistoryRetention::Days { .. } => {
+            let cutoff = Value::Integer(retention.cutoff_ms().unwrap_or(0) as i64);
+            vec![
+                (
+                    "DELETE FROM activity WHERE at_ms < ?1",
+                    vec![cutoff.clone()],
+                ),
+                ("DELETE FROM transfers WHERE at_ms < ?1", vec![cutoff]),
+            ]
+        }
+        HistoryRetention::Entries { entries } => {
+            let entries = Value::Integer(i64::from(entries));
+            vec![
+                (
+                    "DELETE FROM activity WHERE rowid IN (
+                        SELECT rowid FROM (
+                            SELECT rowid, ROW_NUMBER() OVER (
+                                PARTITION BY kind ORDER BY at_ms DESC
+                            ) AS position
+                            FROM activity WHERE kind != 'log'
+                        ) WHERE position > ?1
+                    )",
+                    vec![entries.clone()],
+                ),
+                (
+                    "DELETE FROM transfers WHERE rowid IN (
+                        SELECT rowid FROM (
+                            SELECT rowid, ROW_NUMBER() OVER (
+                                PARTITION BY direction ORDER BY at_ms DESC
+                            ) AS position
+                            FROM transfers
+                        ) WHERE position > ?1
+                    )",
+                    vec![entries],
+                ),
+                (
+                    "DELETE FROM activity WHERE kind = 'log'
+                     AND at_ms < (SELECT COALESCE(MIN(at_ms), 0) FROM transfers)",
+                    Vec::new(),
+                ),
+            ]
+        }
+    };
+    let connection = index.connection(data_file(app, ACTIVITY_INDEX_FILE)?)?;
+    let transaction = connection
+        .transaction()
+        .map_err(|err| format!("failed to prune activity index: {err}"))?;
+    for (sql, values) in statements {
+        transaction
+            .execute(sql, params_from_iter(values))
+            .map_err(|err| format!("failed to prune activity index: {err}"))?;
+    }
+    transaction
+        .commit()
+        .map_err(|err| format!("failed to prune activity index: {err}"))
+}
+
+/// Empties the index and vacuums it, so nothing of it is left in the file.
+pub(crate) fn wipe_index(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut index = lock_index(state.inner())?;
+    index.pending.clear();
+    let connection = index.connection(data_file(app, ACTIVITY_INDEX_FILE)?)?;
+    connection
+        .execute_batch(
+            "DELETE FROM activity;
+             DELETE FROM transfers;
+             DELETE FROM indexed_history;
+             VACUUM;",
+        )
+        .map_err(|err| format!("failed to wipe activity index: {err}"))
+}
+
+/// Writes pending log lines and new history entries, for when the index
+/// task has stopped at exit.
+pub(crate) fn flush(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut index = lock_index(state.inner())?;
+    flush_logs(app, &mut index)?;
+    sync_history(app, &mut index)
+}
+
+pub(crate) fn spawn_index_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Ok(mut index) = lock_index(state.inner()) {
+            // Not reported: the report would be a log line to index again.
+            let _ = flush_logs(&app, &mut index);
+        }
+        thread::sleep(FLUSH_INTERVAL);
+    });
+}
+
+/// Every word is matched as a prefix, so "contr" finds "contract.pdf".
+/// Quoting keeps FTS5 operators in the input from being interpreted.
+fn match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Searches sends, receives (paths, devices, notes and tags) and captured
+/// log lines, best matches first. Without a query the newest entries that
+/// pass `filters` are returned.
+#[tauri::command]
+pub(crate) fn search_activity(
+    app: AppHandle,
+    query: Option<String>,
+    filters: Option<ActivityFilters>,
+) -> Result<Vec<ActivityMatch>, String> {
+    let filters = filters.unwrap_or_default();
+    with_index(&app, |connection| {
+        search(connection, query.as_deref(), &filters)
+    })
+}
+
+/// Runs `f` on the active profile's index, brought up to date first.
+pub(crate) fn with_index<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let state = app.state::<AppState>();
+    let mut index = lock_index(state.inner())?;
+    flush_logs(app, &mut index)?;
+    sync_history(app, &mut index)?;
+    f(index.connection(data_file(app, ACTIVITY_INDEX_FILE)?)?)
+}
+
+fn search(
+    connection: &Connection,
+    query: Option<&str>,
+    filters: &ActivityFilters,
+) -> Result<Vec<ActivityMatch>, String> {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    let expression = match_expression(query.unwrap_or_default());
+    if let Some(expression) = &expression {
+        values.push(Value::Text(expression.clone()));
+        conditions.push(format!("activity MATCH ?{}", values.len()));
+    }
+    if !filters.kinds.is_empty() {
+        let mut placeholders = Vec::new();
+        for kind in &filters.kinds {
+            values.push(Value::Text(kind.as_str().to_string()));
+            placeholders.push(format!("?{}", values.len()));
+        }
+        conditions.push(format!("kind IN ({})", placeholders.join(", ")));
+    }
+    if let Some(peer) = filters
+        .peer
+        .as_deref()
+        .map(str::trim)
+        .filter(|peer| !peer.is_empty())
+    {
+        let escaped = peer
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        values.push(Value::Text(format!("%{escaped}%")));
+        conditions.push(format!("peer LIKE ?{} ESCAPE '\\'", values.len()));
+    }
+    if let Some(since_ms) = filters.since_ms {
+        values.push(Value::Integer(since_ms as i64));
+        conditions.push(format!("at_ms >= ?{}", values.len()));
+    }
+    if let Some(until_ms) = filters.until_ms {
+        values.push(Value::Integer(until_ms as i64));
+        conditions.push(format!("at_ms <= ?{}", values.len()));
+    }
+    let limit = filters
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    values.push(Value::Integer(limit as i64));
+
+    let sql = format!(
+        "SELECT kind, at_ms, source_id, success, path, peer, {snippet}
+         FROM activity {where_clause}
+         ORDER BY {order} LIMIT ?{limit}",
+        snippet = if expression.is_some() {
+            "snippet(activity, -1, '[', ']', '…', 16)"
+        } else {
+            "text"
+        },
+        where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        },
+        order = if expression.is_some() {
+            "rank, at_ms DESC"
+        } else {
+            "at_ms DESC"
+        },
+        limit = values.len(),
+    );
+    let mut statement = connection
+        .prepare(&sql)
+        .map_err(|err| format!("failed to search activity: {err}"))?;
+    let rows = statement
+        .query_map(params_from_iter(values), |row| {
+            let kind: String = row.get(0)?;
+            Ok(ActivityMatch {
+                kind: ActivityKind::parse(&kind),
+                at_ms: row.get::<_, i64>(1)?.max(0) as u64,
+                history_id: row.get::<_, Option<i64>>(2)?.map(|id| id.max(0) as u64),
+                success: row.get(3)?,
+                path: row.get(4)?,
+                peer: row.get(5)?,
+                snippet: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+            })
+        })
+        .map_err(|err| format!("failed to search activity: {err}"))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to search activity: {err}"))
+}
@@ -0,0 +1,294 @@
+use crate::app_profiles::{active_profile_dir, KNOWN_HOSTS_FILE};
+use crate::device_groups::{DeviceGroup, DEVICE_GROUPS_FILE};
+use crate::network_profiles::NetworkProfile;
+use crate::presets::{SendPreset, SEND_PRESETS_FILE};
+use crate::settings::{AppSettings, APP_SETTINGS_FILE};
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{network_profiles, paths, secrets, AppState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SECRETS_ENTRY: &str = "secrets.json";
+const ARCHIVE_VERSION: u32 = 1;
+/// Exported files are small JSON documents; anything bigger is not ours.
+const MAX_ENTRY_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: u32,
+    exported_at_ms: u64,
+    files: Vec<String>,
+    includes_secrets: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppDataSummary {
+    files: Vec<String>,
+    secrets: usize,
+}
+
+/// Where `--tls-tofu` sends of the active profile remember fingerprints.
+pub(crate) fn known_hosts_path(app: &AppHandle) -> Result<PathBuf, String> {
+    if active_profile_dir(app.state::<AppState>().inner()).is_some() {
+        return data_file(app, KNOWN_HOSTS_FILE);
+    }
+    let home = app
+        .path()
+        .home_dir()
+        .map_err(|err| format!("failed to resolve home directory: {err}"))?;
+    Ok(home.join(".local-sent").join(KNOWN_HOSTS_FILE))
+}
+
+/// The exported documents, each parsed so a corrupt file fails the export
+/// rather than producing an archive that cannot be imported.
+fn collect_documents(app: &AppHandle) -> Result<Vec<(&'static str, Value)>, String> {
+    let state = app.state::<AppState>();
+    let settings: AppSettings = {
+        let _guard = state
+            .settings_lock
+            .lock()
+            .map_err(|_| "failed to lock app settings".to_string())?;
+        read_json(&data_file(app, APP_SETTINGS_FILE)?)?
+    };
+    let presets: Vec<SendPreset> = {
+        let _guard = state
+            .presets_lock
+            .lock()
+            .map_err(|_| "failed to lock send presets".to_string())?;
+        read_json(&data_file(app, SEND_PRESETS_FILE)?)?
+    };
+    let device_groups: Vec<DeviceGroup> = {
+        let _guard = state
+            .device_groups_lock
+            .lock()
+            .map_err(|_| "failed to lock device groups".to_string())?;
+        read_json(&data_file(app, DEVICE_GROUPS_FILE)?)?
+    };
+    let network_profiles: Vec<NetworkProfile> = {
+        let _guard = state
+            .network_profiles_lock
+            .lock()
+            .map_err(|_| "failed to lock network profiles".to_string())?;
+        read_json(&data_file(app, network_profiles::NETWORK_PROFILES_FILE)?)?
+    };
+    let known_hosts: BTreeMap<String, String> = read_json(&known_hosts_path(app)?)?;
+
+    let to_value = |value: Result<Value, serde_json::Error>| {
+        value.map_err(|err| format!("failed to encode app data: {err}"))
+    };
+    Ok(vec![
+        (APP_SETTINGS_FILE, to_value(serde_json::to_value(settings))?),
+        (SEND_PRESETS_FILE, to_value(serde_json::to_value(presets))?),
+        (
+            DEVICE_GROUPS_FILE,
+            to_value(serde_json::to_value(device_groups))?,
+        ),
+        (
+            network_profiles::NETWORK_PROFILES_FILE,
+            to_value(serde_json::to_value(network_profiles))?,
+        ),
+        (
+            KNOWN_HOSTS_FILE,
+            to_value(serde_json::to_value(known_hosts))?,
+        ),
+    ])
+}
+
+/// Pair codes the presets refer to, read back from the OS keychain.
+fn collect_secrets(app: &AppHandle) -> Result<BTreeMap<String, String>, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .presets_lock
+        .lock()
+        .map_err(|_| "failed to lock send presets".to_string())?;
+    let presets: Vec<SendPreset> = read_json(&data_file(app, SEND_PRESETS_FILE)?)?;
+    let mut found = BTreeMap::new();
+    for preset in presets {
+        let key = secrets::pair_code_target_key(&preset.request);
+        if let Some(code) = secrets::get_secret(&secrets::profile_key(app, &key))? {
+            found.insert(key, code);
+        }
+    }
+    Ok(found)
+}
+
+fn write_entry(
+    zip: &mut ZipWriter<File>,
+    name: &str,
+    value: &impl Serialize,
+) -> Result<(), String> {
+    let raw = serde_json::to_vec_pretty(value)
+        .map_err(|err| format!("failed to encode {name}: {err}"))?;
+    zip.start_file(
+        name,
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    )
+    .map_err(|err| format!("failed to add {name} to archive: {err}"))?;
+    zip.write_all(&raw)
+        .map_err(|err| format!("failed to add {name} to archive: {err}"))
+}
+
+/// Writes settings, send presets, device groups, network profiles (the
+/// auto-accept rules) and the TLS known hosts of the active profile to a zip
+/// archive at `path`. Pair codes stay in the keychain unless
+/// `include_secrets` is set, in which case they are written in plain text.
+#[tauri::command]
+pub(crate) fn export_app_data(
+    app: AppHandle,
+    path: String,
+    include_secrets: bool,
+) -> Result<AppDataSummary, String> {
+    let target = paths::validate_output_file(&path, "export archive")?;
+    let documents = collect_documents(&app)?;
+    let secrets = if include_secrets {
+        collect_secrets(&app)?
+    } else {
+        BTreeMap::new()
+    };
+
+    let temp_path = target.with_extension("zip.tmp");
+    let file = File::create(&temp_path)
+        .map_err(|err| format!("failed to create {}: {err}", temp_path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let files: Vec<String> = documents.iter().map(|(name, _)| name.to_string()).collect();
+    write_entry(
+        &mut zip,
+        MANIFEST_ENTRY,
+        &Manifest {
+            version: ARCHIVE_VERSION,
+            exported_at_ms: now_ms(),
+            files: files.clone(),
+            includes_secrets: include_secrets,
+        },
+    )?;
+    for (name, value) in &documents {
+        write_entry(&mut zip, name, value)?;
+    }
+    if include_secrets {
+        write_entry(&mut zip, SECRETS_ENTRY, &secrets)?;
+    }
+    zip.finish()
+        .map_err(|err| format!("failed to finish archive: {err}"))?;
+    fs::rename(&temp_path, &target)
+        .map_err(|err| format!("failed to replace {}: {err}", target.display()))?;
+
+    Ok(AppDataSummary {
+        files,
+        secrets: secrets.len(),
+    })
+}
+
+fn read_entry<T>(zip: &mut ZipArchive<File>, name: &str) -> Result<Option<T>, String>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let entry = match zip.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(err) => return Err(format!("failed to read {name} from archive: {err}")),
+    };
+    if entry.size() > MAX_ENTRY_BYTES {
+        return Err(format!("{name} in archive is too large"));
+    }
+    let mut raw = Vec::new();
+    entry
+        .take(MAX_ENTRY_BYTES)
+        .read_to_end(&mut raw)
+        .map_err(|err| format!("failed to read {name} from archive: {err}"))?;
+    serde_json::from_slice(&raw)
+        .map(Some)
+        .map_err(|err| format!("invalid {name} in archive: {err}"))
+}
+
+/// Replaces the active profile's settings, presets, network profiles and
+/// known hosts with those in an archive from `export_app_data`. Every file
+/// is parsed before anything is written, so a bad archive changes nothing.
+#[tauri::command]
+pub(crate) fn import_app_data(app: AppHandle, path: String) -> Result<AppDataSummary, String> {
+    let source = paths::validate_input_file(&path, "import archive")?;
+    let file =
+        File::open(&source).map_err(|err| format!("failed to open {}: {err}", source.display()))?;
+    let mut zip = ZipArchive::new(file).map_err(|err| format!("failed to read archive: {err}"))?;
+    let manifest: Manifest = read_entry(&mut zip, MANIFEST_ENTRY)?
+        .ok_or_else(|| "not a local-sent app data archive".to_string())?;
+    if manifest.version > ARCHIVE_VERSION {
+        return Err(format!(
+            "archive version {} is newer than this app supports",
+            manifest.version
+        ));
+    }
+
+    let settings: Option<AppSettings> = read_entry(&mut zip, APP_SETTINGS_FILE)?;
+    let presets: Option<Vec<SendPreset>> = read_entry(&mut zip, SEND_PRESETS_FILE)?;
+    let device_groups: Option<Vec<DeviceGroup>> = read_entry(&mut zip, DEVICE_GROUPS_FILE)?;
+    let network_profiles: Option<Vec<NetworkProfile>> =
+        read_entry(&mut zip, network_profiles::NETWORK_PROFILES_FILE)?;
+    let known_hosts: Option<BTreeMap<String, String>> = read_entry(&mut zip, KNOWN_HOSTS_FILE)?;
+    let secrets: BTreeMap<String, String> =
+        read_entry(&mut zip, SECRETS_ENTRY)?.unwrap_or_default();
+
+    let state = app.state::<AppState>();
+    let mut files = Vec::new();
+    if let Some(settings) = settings {
+        let _guard = state
+            .settings_lock
+            .lock()
+            .map_err(|_| "failed to lock app settings".to_string())?;
+        write_json(&data_file(&app, APP_SETTINGS_FILE)?, &settings)?;
+        files.push(APP_SETTINGS_FILE.to_string());
+    }
+    if let Some(presets) = presets {
+        let _guard = state
+            .presets_lock
+            .lock()
+            .map_err(|_| "failed to lock send presets".to_string())?;
+        write_json(&data_file(&app, SEND_PRESETS_FILE)?, &presets)?;
+        files.push(SEND_PRESETS_FILE.to_string());
+    }
+    if let Some(device_groups) = device_groups {
+        let _guard = state
+            .device_groups_lock
+            .lock()
+            .map_err(|_| "failed to lock device groups".to_string())?;
+        write_json(&data_file(&app, DEVICE_GROUPS_FILE)?, &device_groups)?;
+        files.push(DEVICE_GROUPS_FILE.to_string());
+    }
+    if let Some(network_profiles) = network_profiles {
+        let _guard = state
+            .network_profiles_lock
+            .lock()
+            .map_err(|_| "failed to lock network profiles".to_string())?;
+        write_json(
+            &data_file(&app, network_profiles::NETWORK_PROFILES_FILE)?,
+            &network_profiles,
+        )?;
+        files.push(network_profiles::NETWORK_PROFILES_FILE.to_string());
+    }
+    if let Some(known_hosts) = known_hosts {
+        let path = known_hosts_path(&app)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+        }
+        write_json(&path, &known_hosts)?;
+        files.push(KNOWN_HOSTS_FILE.to_string());
+    }
+    for (key, code) in &secrets {
+        secrets::store_secret(&secrets::profile_key(&app, key), code)?;
+    }
+
+    Ok(AppDataSummary {
+        files,
+        secrets: secrets.len(),
+    })
+}
@@ -0,0 +1,143 @@
+use crate::{
+    activity_search, cleanup_child_processes_from_app, cli_control, emit_system_log, scheduler,
+    stop_listener, AppState,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// How long closing waits for running sends and receives to finish before
+/// it stops them.
+const DRAIN_GRACE: Duration = Duration::from_secs(15);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// The app exits after this even if the shutdown sequence is stuck; exit
+/// cleanup still kills the children.
+const EXIT_HARD_LIMIT: Duration = Duration::from_secs(40);
+
+#[derive(Debug, Default)]
+pub(crate) struct ExitState {
+    requested: AtomicBool,
+    /// Set once the shutdown sequence is done and the exit may go ahead.
+    ready: AtomicBool,
+    children_stopped: AtomicBool,
+}
+
+/// True for the first caller only: children are stopped once, by the
+/// shutdown sequence or by exit cleanup if the sequence never got there.
+pub(crate) fn claim_child_cleanup(state: &AppState) -> bool {
+    !state.app_exit.children_stopped.swap(true, Ordering::SeqCst)
+}
+
+pub(crate) fn ready(app: &AppHandle) -> bool {
+    app.state::<AppState>()
+        .app_exit
+        .ready
+        .load(Ordering::SeqCst)
+}
+
+fn allow_exit(app: &AppHandle) {
+    app.state::<AppState>()
+        .app_exit
+        .ready
+        .store(true, Ordering::SeqCst);
+    app.exit(0);
+}
+
+/// Receives the listener is writing right now; 0 without a listener.
+fn receives_in_progress(state: &AppState) -> usize {
+    cli_control::request(state, "transfers", json!({}))
+        .ok()
+        .and_then(|result| result.get("transfers")?.as_array().map(Vec::len))
+        .unwrap_or(0)
+}
+
+/// Sends still moving data. Paused low-priority sends would not finish in
+/// time and are not waited for.
+fn sends_in_progress(app: &AppHandle) -> usize {
+    let state = app.state::<AppState>();
+    let paused = scheduler::paused_sends(app);
+    state
+        .active_sends
+        .lock()
+        .map(|sends| sends.iter().filter(|id| !paused.contains(id)).count())
+        .unwrap_or(0)
+}
+
+/// Gives running transfers `DRAIN_GRACE` to finish, so files are not cut
+/// off mid-write when the app closes.
+fn drain(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let deadline = Instant::now() + DRAIN_GRACE;
+    let mut reported = None;
+    loop {
+        let counts = (sends_in_progress(app), receives_in_progress(state.inner()));
+        if counts == (0, 0) {
+            return;
+        }
+        if Instant::now() >= deadline {
+            emit_system_log(
+                app,
+                format!(
+                    "关闭时仍有 {} 个发送、{} 个接收未完成，将中止（接收方会保留已收部分以便续传）",
+                    counts.0, counts.1
+                ),
+            );
+            return;
+        }
+        if reported != Some(counts) {
+            emit_system_log(
+                app,
+                format!("等待 {} 个发送、{} 个接收完成后关闭...", counts.0, counts.1),
+            );
+            reported = Some(counts);
+        }
+        thread::sleep(DRAIN_POLL_INTERVAL);
+    }
+}
+
+fn shut_down(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    // Stops the background tasks and keeps new CLI children from starting.
+    state.shutdown_cleanup_started.store(true, Ordering::SeqCst);
+
+    let paused = scheduler::paused_sends(app).len();
+    if paused > 0 {
+        emit_system_log(app, format!("取消 {paused} 个排队中的发送"));
+    }
+    drain(app);
+
+    // The listener rolls back open batches and closes its files.
+    let listening = state.listen_child.lock().is_ok_and(|child| child.is_some());
+    if listening {
+        if let Err(err) = stop_listener(app) {
+            emit_system_log(app, format!("failed to stop listener: {err}"));
+        }
+    }
+    cleanup_child_processes_from_app(app);
+
+    // The index task has stopped; write what it had not picked up yet.
+    if let Err(err) = activity_search::flush(app) {
+        emit_system_log(app, format!("failed to flush activity index: {err}"));
+    }
+}
+
+/// Runs the shutdown sequence off the event loop, then exits. Further
+/// close requests while it runs are ignored.
+pub(crate) fn request_exit(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if state.app_exit.requested.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let timer_app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(EXIT_HARD_LIMIT);
+        allow_exit(&timer_app);
+    });
+    let exit_app = app.clone();
+    thread::spawn(move || {
+        shut_down(&exit_app);
+        allow_exit(&exit_app);
+    });
+}
@@ -0,0 +1,171 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::storage::{read_json, shared_file, write_json};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+const APP_PROFILES_FILE: &str = "profiles.json";
+/// Lives directly in the app data folder, where every file was kept before
+/// profiles existed.
+pub(crate) const DEFAULT_PROFILE: &str = "Default";
+const MAX_PROFILE_NAME_CHARS: usize = 40;
+/// TLS trust-on-first-use store of non-default profiles; the default one
+/// keeps using the CLI's `~/.local-sent/known_hosts.json`.
+pub(crate) const KNOWN_HOSTS_FILE: &str = "known_hosts.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AppProfile {
+    name: String,
+    /// Folder under `profiles/` holding the profile's data files.
+    dir: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct AppProfiles {
+    /// `None` is the default profile.
+    active: Option<String>,
+    profiles: Vec<AppProfile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppProfilesPayload {
+    active: String,
+    profiles: Vec<String>,
+}
+
+fn load_profiles(app: &AppHandle) -> Result<AppProfiles, String> {
+    read_json(&shared_file(app, APP_PROFILES_FILE)?)
+}
+
+fn payload(profiles: &AppProfiles) -> AppProfilesPayload {
+    let active = profiles
+        .active
+        .as_ref()
+        .and_then(|dir| profiles.profiles.iter().find(|profile| &profile.dir == dir))
+        .map_or(DEFAULT_PROFILE, |profile| profile.name.as_str());
+    AppProfilesPayload {
+        active: active.to_string(),
+        profiles: std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(profiles.profiles.iter().map(|profile| profile.name.clone()))
+            .collect(),
+    }
+}
+
+/// A folder name for `name`: lowercase letters, digits and dashes.
+fn profile_dir_name(name: &str) -> String {
+    let mut dir = String::new();
+    for ch in name.chars().flat_map(char::to_lowercase) {
+        if ch.is_ascii_alphanumeric() {
+            dir.push(ch);
+        } else if !dir.ends_with('-') {
+            dir.push('-');
+        }
+    }
+    let dir = dir.trim_matches('-');
+    if dir.is_empty() {
+        "profile".to_string()
+    } else {
+        dir.to_string()
+    }
+}
+
+/// Reads the saved active profile into `AppState`; called once at startup.
+pub(crate) fn load_active_profile(app: &AppHandle) {
+    let active = load_profiles(app).ok().and_then(|profiles| profiles.active);
+    if let Ok(mut guard) = app.state::<AppState>().active_profile.lock() {
+        *guard = active;
+    }
+}
+
+/// Folder of the active profile relative to the app data folder.
+pub(crate) fn active_profile_dir(state: &AppState) -> Option<String> {
+    state
+        .active_profile
+        .lock()
+        .ok()
+        .and_then(|guard| guard.clone())
+        .map(|dir| format!("profiles/{dir}"))
+}
+
+#[tauri::command]
+pub(crate) fn list_profiles(app: AppHandle) -> Result<AppProfilesPayload, String> {
+    load_profiles(&app).map(|profiles| payload(&profiles))
+}
+
+/// Makes `name` the active profile, creating it when it does not exist yet.
+/// Settings, presets, network profiles, send history, the received-files
+/// index and the TLS trust store are all read from the profile's folder
+/// from then on; the device identity and the audit log stay shared.
+#[tauri::command]
+pub(crate) fn switch_profile(
+    app: AppHandle,
+    state: State<AppState>,
+    name: String,
+) -> Result<AppProfilesPayload, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("profile name is required".to_string());
+    }
+    if name.chars().count() > MAX_PROFILE_NAME_CHARS {
+        return Err(format!(
+            "profile name is longer than {MAX_PROFILE_NAME_CHARS} characters"
+        ));
+    }
+    if state
+        .listen_child
+        .lock()
+        .map_err(|_| "failed to lock listen process state".to_string())?
+        .is_some()
+    {
+        return Err("stop the receiver before switching profiles".to_string());
+    }
+    if !state
+        .active_sends
+        .lock()
+        .map_err(|_| "failed to lock active sends".to_string())?
+        .is_empty()
+    {
+        return Err("wait for running sends to finish before switching profiles".to_string());
+    }
+
+    let _guard = state
+        .settings_lock
+        .lock()
+        .map_err(|_| "failed to lock app settings".to_string())?;
+    let path = shared_file(&app, APP_PROFILES_FILE)?;
+    let mut profiles: AppProfiles = read_json(&path)?;
+    profiles.active = if name.eq_ignore_ascii_case(DEFAULT_PROFILE) {
+        None
+    } else if let Some(profile) = profiles
+        .profiles
+        .iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(&name))
+    {
+        Some(profile.dir.clone())
+    } else {
+        let base = profile_dir_name(&name);
+        let mut dir = base.clone();
+        let mut suffix = 2;
+        while profiles.profiles.iter().any(|profile| profile.dir == dir) {
+            dir = format!("{base}-{suffix}");
+            suffix += 1;
+        }
+        profiles.profiles.push(AppProfile {
+            name,
+            dir: dir.clone(),
+        });
+        Some(dir)
+    };
+    write_json(&path, &profiles)?;
+    *state
+        .active_profile
+        .lock()
+        .map_err(|_| "failed to lock active profile".to_string())? = profiles.active.clone();
+
+    let payload = payload(&profiles);
+    emit_app_event(&app, AppEvent::ProfileSwitched(payload.clone()));
+    Ok(payload)
+}
@@ -0,0 +1,484 @@
+use crate::emit_system_log;
+use crate::events::{emit_app_event, AppEvent};
+use crate::paths::{long_path, windows_safe_name};
+use crate::settings::update_settings;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// Refuse archives that expand beyond this multiple of their own size, or to
+/// more entries than this; both guard against decompression bombs.
+const MAX_EXPANSION_RATIO: u64 = 200;
+const MAX_ENTRIES: usize = 200_000;
+const MAX_SUFFIX: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct AutoExtract {
+    pub(crate) enabled: bool,
+    /// Delete the archive once it was extracted completely.
+    pub(crate) delete_archive: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExtractProgressPayload {
+    archive: String,
+    destination: String,
+    /// `running`, `done` or `failed`.
+    state: &'static str,
+    entries_done: usize,
+    /// Known up front for zip files only.
+    entries_total: Option<usize>,
+    bytes_written: u64,
+    error: Option<String>,
+}
+
+struct Progress<'a> {
+    emit: &'a dyn Fn(ExtractProgressPayload),
+    payload: ExtractProgressPayload,
+    byte_budget: u64,
+    last_emit: Instant,
+}
+
+impl Progress<'_> {
+    fn entry_done(&mut self, bytes: u64) -> Result<(), String> {
+        self.payload.entries_done += 1;
+        self.payload.bytes_written += bytes;
+        if self.payload.entries_done > MAX_ENTRIES {
+            return Err(format!("archive has more than {MAX_ENTRIES} entries"));
+        }
+        if self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.last_emit = Instant::now();
+            (self.emit)(self.payload.clone());
+        }
+        Ok(())
+    }
+
+    fn remaining_bytes(&self) -> u64 {
+        self.byte_budget.saturating_sub(self.payload.bytes_written)
+    }
+
+    fn finish(mut self, error: Option<String>) {
+        self.payload.state = if error.is_some() { "failed" } else { "done" };
+        self.payload.error = error;
+        (self.emit)(self.payload);
+    }
+}
+
+fn archive_kind(path: &Path) -> Option<(ArchiveKind, String)> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let lower = name.to_ascii_lowercase();
+    [
+        (".tar.gz", ArchiveKind::TarGz),
+        (".tgz", ArchiveKind::TarGz),
+        (".tar", ArchiveKind::Tar),
+        (".zip", ArchiveKind::Zip),
+    ]
+    .into_iter()
+    .find(|(suffix, _)| lower.ends_with(suffix) && lower.len() > suffix.len())
+    .map(|(suffix, kind)| (kind, name[..name.len() - suffix.len()].to_string()))
+}
+
+/// Joins an archive entry name onto `root`, rejecting absolute paths, drive
+/// prefixes and `..` so nothing can be written outside `root` (zip-slip).
+/// On Windows, names like `CON` are rewritten and deep paths prefixed so
+/// `node_modules`-style archives extract completely.
+fn safe_entry_path(root: &Path, entry: &Path) -> Result<Option<PathBuf>, String> {
+    let mut target = root.to_path_buf();
+    let mut depth = 0;
+    for component in entry.components() {
+        match component {
+            Component::Normal(part) if cfg!(windows) => {
+                target.push(windows_safe_name(&part.to_string_lossy()));
+                depth += 1;
+            }
+            Component::Normal(part) => {
+                target.push(part);
+                depth += 1;
+            }
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(format!(
+                    "archive entry `{}` escapes the extraction folder",
+                    entry.display()
+                ));
+            }
+        }
+    }
+    Ok((depth > 0).then(|| long_path(&target)))
+}
+
+fn write_entry(
+    reader: &mut impl Read,
+    target: &Path,
+    progress: &mut Progress<'_>,
+) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+    }
+    let mut file = File::create(target)
+        .map_err(|err| format!("failed to create {}: {err}", target.display()))?;
+    // One byte over the budget is enough to tell that it was exceeded.
+    let budget = progress.remaining_bytes();
+    let written = io::copy(&mut reader.take(budget + 1), &mut file)
+        .map_err(|err| format!("failed to extract {}: {err}", target.display()))?;
+    if written > budget {
+        return Err(format!(
+            "archive expands to more than {MAX_EXPANSION_RATIO}x its size"
+        ));
+    }
+    progress.entry_done(written)
+}
+
+fn extract_zip(archive: &Path, root: &Path, progress: &mut Progress<'_>) -> Result<(), String> {
+    let file = File::open(archive).map_err(|err| format!("failed to open archive: {err}"))?;
+    let mut zip =
+        zip::ZipArchive::new(file).map_err(|err| format!("failed to read zip archive: {err}"))?;
+    progress.payload.entries_total = Some(zip.len());
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|err| format!("failed to read zip entry: {err}"))?;
+        let name = PathBuf::from(entry.name());
+        let Some(target) = safe_entry_path(root, &name)? else {
+            continue;
+        };
+        let is_symlink = entry
+            .unix_mode()
+            .is_some_and(|mode| mode & 0o170000 == 0o120000);
+        if is_symlink {
+            // Links could point anywhere; skip them rather than follow.
+            progress.entry_done(0)?;
+        } else if entry.is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|err| format!("failed to create {}: {err}", target.display()))?;
+            progress.entry_done(0)?;
+        } else {
+            write_entry(&mut entry, &target, progress)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar(reader: impl Read, root: &Path, progress: &mut Progress<'_>) -> Result<(), String> {
+    let mut tar = tar::Archive::new(reader);
+    let entries = tar
+        .entries()
+        .map_err(|err| format!("failed to read tar archive: {err}"))?;
+    for entry in entries {
+        let mut entry = entry.map_err(|err| format!("failed to read tar entry: {err}"))?;
+        let name = entry
+            .path()
+            .map_err(|err| format!("invalid tar entry name: {err}"))?
+            .into_owned();
+        let Some(target) = safe_entry_path(root, &name)? else {
+            continue;
+        };
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_dir() {
+            fs::create_dir_all(&target)
+                .map_err(|err| format!("failed to create {}: {err}", target.display()))?;
+            progress.entry_done(0)?;
+        } else if entry_type.is_file() {
+            write_entry(&mut entry, &target, progress)?;
+        } else {
+            // Links, devices and FIFOs are skipped.
+            progress.entry_done(0)?;
+        }
+    }
+    Ok(())
+}
+
+fn free_destination(parent: &Path, stem: &str) -> Option<PathBuf> {
+    (0..MAX_SUFFIX)
+        .map(|index| match index {
+            0 => parent.join(stem),
+            _ => parent.join(format!("{stem}({index})")),
+        })
+        .find(|candidate| !candidate.exists())
+}
+
+/// Extracts a received zip/tar/tar.gz next to itself, into a folder named after
+/// the archive. Work happens in a hidden staging folder that is renamed into
+/// place only when extraction succeeded, so a failed or rejected archive
+/// leaves nothing half-written behind. Other files are left alone.
+pub(crate) fn extract_received(app: &AppHandle, archive: &Path, settings: AutoExtract) {
+    let Some((kind, stem)) = archive_kind(archive) else {
+        return;
+    };
+    let Some(parent) = archive.parent() else {
+        return;
+    };
+    let Some(destination) = free_destination(parent, &stem) else {
+        emit_system_log(
+            app,
+            format!("no free folder name to extract {}", archive.display()),
+        );
+        return;
+    };
+    let staging = parent.join(format!(
+        ".{stem}.{}.local-sent-extracting",
+        uuid::Uuid::new_v4().simple()
+    ));
+    let archive_size = fs::metadata(archive).map(|meta| meta.len()).unwrap_or(0);
+
+    let emit = |payload| emit_app_event(app, AppEvent::ExtractProgress(payload));
+    let mut progress = Progress {
+        emit: &emit,
+        payload: ExtractProgressPayload {
+            archive: archive.to_string_lossy().to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            state: "running",
+            entries_done: 0,
+            entries_total: None,
+            bytes_written: 0,
+            error: None,
+        },
+        byte_budget: archive_size.max(1).saturating_mul(MAX_EXPANSION_RATIO),
+        last_emit: Instant::now(),
+    };
+    emit(progress.payload.clone());
+
+    let result = (|| -> Result<(), String> {
+        fs::create_dir_all(&staging)
+            .map_err(|err| format!("failed to create {}: {err}", staging.display()))?;
+        match kind {
+            ArchiveKind::Zip => extract_zip(archive, &staging, &mut progress)?,
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                let file =
+                    File::open(archive).map_err(|err| format!("failed to open archive: {err}"))?;
+                if kind == ArchiveKind::TarGz {
+                    extract_tar(GzDecoder::new(file), &staging, &mut progress)?
+                } else {
+                    extract_tar(file, &staging, &mut progress)?
+                }
+            }
+        }
+        fs::rename(&staging, &destination)
+            .map_err(|err| format!("failed to move extracted files into place: {err}"))
+    })();
+
+    match result {
+        Ok(()) => {
+            let files = progress.payload.entries_done;
+            progress.finish(None);
+            if settings.delete_archive {
+                if let Err(err) = fs::remove_file(archive) {
+                    emit_system_log(
+                        app,
+                        format!("failed to delete {}: {err}", archive.display()),
+                    );
+                }
+            }
+            emit_system_log(
+                app,
+                format!(
+                    "extracted {} ({files} entries) into {}",
+                    archive.display(),
+                    destination.display()
+                ),
+            );
+        }
+        Err(err) => {
+            let _ = fs::remove_dir_all(&staging);
+            emit_system_log(
+                app,
+                format!("failed to extract {}: {err}", archive.display()),
+            );
+            progress.finish(Some(err));
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) fn set_auto_extract(
+    app: AppHandle,
+    settings: AutoExtract,
+) -> Result<AutoExtract, String> {
+    update_settings(&app, |current| {
+        current.auto_extract = settings;
+        Ok(())
+    })
+    .map(|current| current.auto_extract)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "local-sent-archive-test-{}",
+                uuid::Uuid::new_v4().simple()
+            ));
+            fs::create_dir_all(dir.join("out")).unwrap();
+            Scratch(dir)
+        }
+
+        fn root(&self) -> PathBuf {
+            self.0.join("out")
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn progress(emit: &dyn Fn(ExtractProgressPayload), archive_size: u64) -> Progress<'_> {
+        Progress {
+            emit,
+            payload: ExtractProgressPayload {
+                archive: String::new(),
+                destination: String::new(),
+                state: "running",
+                entries_done: 0,
+                entries_total: None,
+                bytes_written: 0,
+                error: None,
+            },
+            byte_budget: archive_size.max(1).saturating_mul(MAX_EXPANSION_RATIO),
+            last_emit: Instant::now(),
+        }
+    }
+
+    fn build_zip(entries: &[(&str, &[u8])], symlinks: &[(&str, &str)]) -> Vec<u8> {
+        let options = zip::write::SimpleFileOptions::default();
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        for (name, target) in symlinks {
+            zip.add_symlink(*name, *target, options).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// Writes `name` straight into the header, since `tar::Builder` refuses
+    /// the names these tests need.
+    fn tar_header(name: &str, entry_type: tar::EntryType, size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_cksum();
+        header
+    }
+
+    fn run_zip(scratch: &Scratch, archive: &[u8]) -> Result<(), String> {
+        let path = scratch.0.join("test.zip");
+        fs::write(&path, archive).unwrap();
+        let emit = |_| {};
+        let mut progress = progress(&emit, archive.len() as u64);
+        extract_zip(&path, &scratch.root(), &mut progress)
+    }
+
+    fn run_tar(scratch: &Scratch, archive: &[u8]) -> Result<(), String> {
+        let emit = |_| {};
+        let mut progress = progress(&emit, archive.len() as u64);
+        extract_tar(archive, &scratch.root(), &mut progress)
+    }
+
+    #[test]
+    fn entry_paths_cannot_leave_the_root() {
+        let root = Path::new("/srv/out");
+        for name in ["../x", "a/../../x", "/etc/passwd"] {
+            assert!(safe_entry_path(root, Path::new(name)).is_err(), "{name}");
+        }
+        assert_eq!(safe_entry_path(root, Path::new("./")).unwrap(), None);
+        let inside = safe_entry_path(root, Path::new("a/./b.txt"))
+            .unwrap()
+            .unwrap();
+        assert!(inside.ends_with("a/b.txt"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn drive_prefixed_entries_are_rejected() {
+        let root = Path::new(r"C:\srv\out");
+        for name in [r"C:\x", "C:x", r"\\server\share\x"] {
+            assert!(safe_entry_path(root, Path::new(name)).is_err(), "{name}");
+        }
+    }
+
+    /// Elsewhere `C:` is an ordinary name and must stay inside the root.
+    #[cfg(not(windows))]
+    #[test]
+    fn drive_prefixed_entries_stay_inside_the_root() {
+        let scratch = Scratch::new();
+        run_zip(&scratch, &build_zip(&[("C:/x", b"data")], &[])).unwrap();
+        assert!(scratch.root().join("C:").join("x").is_file());
+    }
+
+    #[test]
+    fn zip_entries_escaping_the_root_fail_the_extraction() {
+        for name in ["../x", "/x"] {
+            let scratch = Scratch::new();
+            let err = run_zip(&scratch, &build_zip(&[(name, b"data")], &[])).unwrap_err();
+            assert!(err.contains("escapes the extraction folder"), "{err}");
+            assert!(!scratch.0.join("x").exists());
+        }
+    }
+
+    #[test]
+    fn tar_entries_escaping_the_root_fail_the_extraction() {
+        for name in ["../x", "/x"] {
+            let scratch = Scratch::new();
+            let mut builder = tar::Builder::new(Vec::new());
+            builder
+                .append(&tar_header(name, tar::EntryType::Regular, 4), &b"data"[..])
+                .unwrap();
+            let archive = builder.into_inner().unwrap();
+            let err = run_tar(&scratch, &archive).unwrap_err();
+            assert!(err.contains("escapes the extraction folder"), "{err}");
+            assert!(!scratch.0.join("x").exists());
+        }
+    }
+
+    #[test]
+    fn symlinks_are_skipped() {
+        let scratch = Scratch::new();
+        run_zip(&scratch, &build_zip(&[], &[("link", "/etc/passwd")])).unwrap();
+        assert!(fs::symlink_metadata(scratch.root().join("link")).is_err());
+
+        let scratch = Scratch::new();
+        let mut header = tar_header("link", tar::EntryType::Symlink, 0);
+        header.set_link_name("/etc/passwd").unwrap();
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append(&header, io::empty()).unwrap();
+        run_tar(&scratch, &builder.into_inner().unwrap()).unwrap();
+        assert!(fs::symlink_metadata(scratch.root().join("link")).is_err());
+    }
+
+    #[test]
+    fn archives_expanding_past_the_ratio_are_refused() {
+        let scratch = Scratch::new();
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        let archive = build_zip(&[("bomb.bin", &zeros)], &[]);
+        assert!((archive.len() as u64) * MAX_EXPANSION_RATIO < zeros.len() as u64);
+        let err = run_zip(&scratch, &archive).unwrap_err();
+        assert!(err.contains("expands to more than"), "{err}");
+    }
+}
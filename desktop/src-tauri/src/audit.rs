@@ -0,0 +1,279 @@
+use crate::secrets::{get_secret, store_secret};
+use crate::storage::{now_ms, shared_file};
+use crate::AppState;
+use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+
+const AUDIT_LOG_FILE: &str = "audit.jsonl";
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// `<seq>:<hash>` of the last record appended, kept in the OS keychain where
+/// whoever rewrites `audit.jsonl` cannot follow.
+const AUDIT_HEAD_SECRET: &str = "audit-head";
+
+/// Security-relevant actions written to the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum AuditAction {
+    TransferAccepted,
+    TransferRejected,
+    TransferAutoAccepted,
+    NetworkProfileSaved,
+    NetworkProfileDeleted,
+    PairCodeSet,
+    TlsFingerprintPinned,
+    TlsFingerprintChanged,
+    SharedFolderAdded,
+    SharedFolderRemoved,
+    ShareAccessed,
+    ShareDenied,
+    ClipboardSyncChanged,
+    FilePrinted,
+}
+
+/// One line of `audit.jsonl`. `hash` covers `prev_hash` plus every other
+/// field, so editing, reordering or deleting a line breaks the chain from
+/// that point on. Truncating the log, or rebuilding the whole chain, leaves
+/// every link intact; the keychain head catches those.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditRecord {
+    seq: u64,
+    timestamp_ms: u64,
+    action: AuditAction,
+    detail: Value,
+    prev_hash: String,
+    hash: String,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AuditChain {
+    initialized: bool,
+    last_seq: u64,
+    last_hash: String,
+    file: Option<File>,
+    /// Last pinned TLS fingerprint per send target, rebuilt from the log.
+    tls_pins: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditExportPayload {
+    path: String,
+    entries: usize,
+    chain_valid: bool,
+    /// First record whose hash or link does not match, if any.
+    first_invalid_seq: Option<u64>,
+    /// Whether the last record is the head held in the keychain; `None` when
+    /// there is no head to compare, e.g. a log from before it was kept.
+    head_verified: Option<bool>,
+}
+
+fn record_hash(
+    prev_hash: &str,
+    seq: u64,
+    timestamp_ms: u64,
+    action: AuditAction,
+    detail: &Value,
+) -> String {
+    let material = json!([prev_hash, seq, timestamp_ms, action, detail]).to_string();
+    Sha256::digest(material.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn read_records(path: &Path) -> Vec<AuditRecord> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<AuditRecord>(&line).ok())
+        .collect()
+}
+
+/// Returns the seq of the first record that fails verification.
+fn first_invalid_record(records: &[AuditRecord]) -> Option<u64> {
+    let mut expected_prev = GENESIS_HASH.to_string();
+    for record in records {
+        let hash = record_hash(
+            &record.prev_hash,
+            record.seq,
+            record.timestamp_ms,
+            record.action,
+            &record.detail,
+        );
+        if record.prev_hash != expected_prev || record.hash != hash {
+            return Some(record.seq);
+        }
+        expected_prev = hash;
+    }
+    None
+}
+
+fn head_verified(records: &[AuditRecord]) -> Option<bool> {
+    let head = get_secret(AUDIT_HEAD_SECRET).ok().flatten()?;
+    let last = records
+        .last()
+        .map(|record| format!("{}:{}", record.seq, record.hash));
+    Some(last.as_deref() == Some(head.as_str()))
+}
+
+fn remember_tls_pin(chain: &mut AuditChain, record: &AuditRecord) {
+    if !matches!(
+        record.action,
+        AuditAction::TlsFingerprintPinned | AuditAction::TlsFingerprintChanged
+    ) {
+        return;
+    }
+    let target = record.detail.get("target").and_then(Value::as_str);
+    let fingerprint = record.detail.get("fingerprint").and_then(Value::as_str);
+    if let (Some(target), Some(fingerprint)) = (target, fingerprint) {
+        chain
+            .tls_pins
+            .insert(target.to_string(), fingerprint.to_string());
+    }
+}
+
+fn initialize_chain(app: &AppHandle, chain: &mut AuditChain) {
+    chain.initialized = true;
+    chain.last_hash = GENESIS_HASH.to_string();
+    let Ok(path) = shared_file(app, AUDIT_LOG_FILE) else {
+        return;
+    };
+    for record in read_records(&path) {
+        remember_tls_pin(chain, &record);
+        chain.last_seq = record.seq;
+        chain.last_hash = record.hash;
+    }
+    chain.file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .ok();
+}
+
+fn append_record(app: &AppHandle, chain: &mut AuditChain, action: AuditAction, detail: Value) {
+    if !chain.initialized {
+        initialize_chain(app, chain);
+    }
+    let seq = chain.last_seq + 1;
+    let timestamp_ms = now_ms();
+    let hash = record_hash(&chain.last_hash, seq, timestamp_ms, action, &detail);
+    let record = AuditRecord {
+        seq,
+        timestamp_ms,
+        action,
+        detail,
+        prev_hash: chain.last_hash.clone(),
+        hash,
+    };
+    let Ok(line) = serde_json::to_string(&record) else {
+        return;
+    };
+    let Some(file) = chain.file.as_mut() else {
+        return;
+    };
+    if writeln!(file, "{line}")
+        .and_then(|()| file.sync_data())
+        .is_err()
+    {
+        chain.file = None;
+        return;
+    }
+    // Best effort like the rest of auditing; verification then reports the
+    // head as stale rather than the action failing.
+    let _ = store_secret(
+        AUDIT_HEAD_SECRET,
+        &format!("{}:{}", record.seq, record.hash),
+    );
+    remember_tls_pin(chain, &record);
+    chain.last_seq = record.seq;
+    chain.last_hash = record.hash;
+}
+
+/// Appends `action` to the audit log. Failures are swallowed: auditing must
+/// never block the action itself.
+pub(crate) fn record_audit(app: &AppHandle, action: AuditAction, detail: Value) {
+    let state = app.state::<AppState>();
+    let Ok(mut chain) = state.audit_chain.lock() else {
+        return;
+    };
+    append_record(app, &mut chain, action, detail);
+}
+
+/// Records a pin the first time a fingerprint is used for `target` and a
+/// change whenever it differs from the previous one.
+pub(crate) fn note_tls_fingerprint(app: &AppHandle, target: &str, fingerprint: &str) {
+    let state = app.state::<AppState>();
+    let Ok(mut chain) = state.audit_chain.lock() else {
+        return;
+    };
+    if !chain.initialized {
+        initialize_chain(app, &mut chain);
+    }
+    let fingerprint = fingerprint.trim().to_ascii_lowercase();
+    let (action, detail) = match chain.tls_pins.get(target) {
+        Some(previous) if *previous == fingerprint => return,
+        Some(previous) => (
+            AuditAction::TlsFingerprintChanged,
+            json!({ "target": target, "fingerprint": fingerprint, "previous": previous }),
+        ),
+        None => (
+            AuditAction::TlsFingerprintPinned,
+            json!({ "target": target, "fingerprint": fingerprint }),
+        ),
+    };
+    append_record(app, &mut chain, action, detail);
+}
+
+/// Copies the audit log to a user-chosen file and reports whether the hash
+/// chain is intact. Returns `None` when the save dialog is cancelled.
+#[tauri::command]
+pub(crate) fn export_audit_log(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Option<AuditExportPayload>, String> {
+    let Some(destination) = FileDialog::new()
+        .set_file_name(format!("local_sent-audit-{}.jsonl", now_ms()))
+        .save_file()
+    else {
+        return Ok(None);
+    };
+
+    let source = shared_file(&app, AUDIT_LOG_FILE)?;
+    let (records, head_verified) = {
+        let _chain = state
+            .audit_chain
+            .lock()
+            .map_err(|_| "failed to lock audit log".to_string())?;
+        if source.exists() {
+            fs::copy(&source, &destination)
+                .map_err(|err| format!("failed to export audit log: {err}"))?;
+        } else {
+            File::create(&destination)
+                .map_err(|err| format!("failed to export audit log: {err}"))?;
+        }
+        let records = read_records(&source);
+        let head_verified = head_verified(&records);
+        (records, head_verified)
+    };
+
+    let first_invalid_seq = first_invalid_record(&records);
+    Ok(Some(AuditExportPayload {
+        path: destination.to_string_lossy().to_string(),
+        entries: records.len(),
+        chain_valid: first_invalid_seq.is_none() && head_verified != Some(false),
+        first_invalid_seq,
+        head_verified,
+    }))
+}
@@ -0,0 +1,163 @@
+use crate::audit::{record_audit, AuditAction};
+use crate::settings::update_settings;
+use crate::{emit_system_log, listen_session, AppState};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs::File;
+use std::io::Read;
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, Manager};
+
+/// Sends received PDFs to a printer, turning this device into a print target
+/// for the ones listed in `trusted_senders`. Addresses can be spoofed on the
+/// LAN, so printing also needs the listener to require a pair code; one is
+/// generated when listening starts with printing on.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct AutoPrint {
+    pub(crate) enabled: bool,
+    /// Peer addresses whose PDFs are printed; files from anyone else are
+    /// only saved.
+    pub(crate) trusted_senders: Vec<String>,
+    /// Printer name; the system default when unset.
+    pub(crate) printer: Option<String>,
+}
+
+impl AutoPrint {
+    fn trusts(&self, from: &str) -> bool {
+        self.trusted_senders.iter().any(|sender| sender == from)
+    }
+}
+
+/// Checks the content too, so a renamed file never reaches the printer.
+fn is_pdf(path: &Path) -> bool {
+    let has_extension = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"));
+    let mut magic = [0u8; 5];
+    has_extension
+        && File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok()
+        && &magic == b"%PDF-"
+}
+
+#[cfg(not(target_os = "windows"))]
+fn print_command(path: &Path, printer: Option<&str>) -> Command {
+    // CUPS, on both macOS and Linux.
+    let mut command = Command::new("lp");
+    if let Some(printer) = printer {
+        command.args(["-d", printer]);
+    }
+    command.arg(path);
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn print_command(path: &Path, printer: Option<&str>) -> Command {
+    // Prints through the registered PDF handler. Values go through the
+    // environment to avoid quoting them in the script.
+    let script = if printer.is_some() {
+        "Start-Process -FilePath $env:LOCAL_SENT_PRINT_FILE -Verb PrintTo \
+         -ArgumentList ('\"' + $env:LOCAL_SENT_PRINTER + '\"') -WindowStyle Hidden"
+    } else {
+        "Start-Process -FilePath $env:LOCAL_SENT_PRINT_FILE -Verb Print -WindowStyle Hidden"
+    };
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("LOCAL_SENT_PRINT_FILE", path)
+        .env("LOCAL_SENT_PRINTER", printer.unwrap_or_default());
+    crate::configure_cli_command_for_platform(&mut command);
+    command
+}
+
+fn print_file(path: &Path, printer: Option<&str>) -> Result<(), String> {
+    let output = print_command(path, printer)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("failed to start the print command: {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("printing failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+/// Post-receive stage: prints `path` if it is a PDF from a trusted sender.
+pub(crate) fn print_received(app: &AppHandle, path: &Path, from: Option<&str>, config: &AutoPrint) {
+    let Some(from) = from.filter(|from| config.trusts(from)) else {
+        return;
+    };
+    if !is_pdf(path) {
+        return;
+    }
+    if !listen_session::requires_pair_code(app.state::<AppState>().inner()) {
+        // Without a pair code the sender address is all there is to trust.
+        emit_system_log(
+            app,
+            format!(
+                "not printing {}: the listener does not require a pair code",
+                path.display()
+            ),
+        );
+        return;
+    }
+    let printer = config
+        .printer
+        .as_deref()
+        .filter(|name| !name.trim().is_empty());
+    let result = print_file(path, printer);
+    record_audit(
+        app,
+        AuditAction::FilePrinted,
+        json!({
+            "path": path.to_string_lossy(),
+            "from": from,
+            "printer": printer,
+            "error": result.as_ref().err(),
+        }),
+    );
+    match result {
+        Ok(()) => emit_system_log(
+            app,
+            format!(
+                "printing {} from {from} on {}",
+                path.display(),
+                printer.unwrap_or("the default printer")
+            ),
+        ),
+        Err(err) => emit_system_log(app, format!("failed to print {}: {err}", path.display())),
+    }
+}
+
+/// Opt-in: only PDFs from `trustedSenders` (peer addresses) are printed.
+#[tauri::command]
+pub(crate) fn set_auto_print(app: AppHandle, auto_print: AutoPrint) -> Result<AutoPrint, String> {
+    let mut trusted_senders = Vec::new();
+    for sender in &auto_print.trusted_senders {
+        let address = sender
+            .trim()
+            .parse::<IpAddr>()
+            .map_err(|_| format!("`{}` is not an IP address", sender.trim()))?;
+        trusted_senders.push(address.to_string());
+    }
+    if auto_print.enabled && trusted_senders.is_empty() {
+        return Err("add at least one trusted sender to print from".to_string());
+    }
+    let auto_print = AutoPrint {
+        trusted_senders,
+        printer: auto_print
+            .printer
+            .map(|printer| printer.trim().to_string())
+            .filter(|printer| !printer.is_empty()),
+        ..auto_print
+    };
+    update_settings(&app, |settings| {
+        settings.auto_print = auto_print;
+        Ok(())
+    })
+    .map(|settings| settings.auto_print)
+}
@@ -0,0 +1,226 @@
+use crate::settings::{load_settings, update_settings};
+use crate::{emit_system_log, scheduler, AppState};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum BatteryAction {
+    /// Low-priority sends are paused until power returns or the charge is
+    /// back above the threshold; they show as paused in the queue.
+    #[default]
+    Defer,
+    /// Sends use a single connection instead of parallel streams.
+    LowerParallelism,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct BatteryPolicy {
+    pub(crate) enabled: bool,
+    /// Applies on battery at or below this charge.
+    pub(crate) threshold_percent: u8,
+    pub(crate) action: BatteryAction,
+}
+
+impl Default for BatteryPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_percent: 30,
+            action: BatteryAction::Defer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PowerSource {
+    pub(crate) on_battery: bool,
+    pub(crate) percent: Option<u8>,
+}
+
+/// The power source at the last check and whether the policy applied.
+#[derive(Debug, Default)]
+pub(crate) struct BatteryState {
+    power: Option<PowerSource>,
+    constrained: Option<BatteryAction>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BatteryStatusPayload {
+    /// `None` on machines without a battery or when it cannot be read.
+    power: Option<PowerSource>,
+    policy: BatteryPolicy,
+    /// The action in effect right now, if any.
+    active_action: Option<BatteryAction>,
+}
+
+#[cfg(target_os = "linux")]
+fn read_power_source() -> Option<PowerSource> {
+    let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |dir: &std::path::Path, name: &str| {
+        std::fs::read_to_string(dir.join(name))
+            .ok()
+            .map(|value| value.trim().to_string())
+    };
+    entries.flatten().find_map(|entry| {
+        let dir = entry.path();
+        if read(&dir, "type").as_deref() != Some("Battery") {
+            return None;
+        }
+        Some(PowerSource {
+            on_battery: read(&dir, "status").as_deref() == Some("Discharging"),
+            percent: read(&dir, "capacity").and_then(|value| value.parse().ok()),
+        })
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn read_power_source() -> Option<PowerSource> {
+    let output = crate::network::command_stdout("pmset", &["-g", "batt"])?;
+    if !output.contains("InternalBattery") {
+        return None;
+    }
+    let percent = output
+        .split(|ch: char| ch.is_whitespace() || ch == ';')
+        .find_map(|token| token.strip_suffix('%')?.parse().ok());
+    Some(PowerSource {
+        on_battery: output.contains("'Battery Power'"),
+        percent,
+    })
+}
+
+#[cfg(target_os = "windows")]
+fn read_power_source() -> Option<PowerSource> {
+    let output = crate::network::command_stdout(
+        "powershell",
+        &[
+            "-NoProfile",
+            "-Command",
+            "Get-CimInstance Win32_Battery | Select-Object -First 1 | \
+             ForEach-Object { \"$($_.BatteryStatus) $($_.EstimatedChargeRemaining)\" }",
+        ],
+    )?;
+    let mut fields = output.split_whitespace();
+    let status: u32 = fields.next()?.parse().ok()?;
+    Some(PowerSource {
+        // 1 is "discharging"; the other codes mean it is on AC power.
+        on_battery: status == 1,
+        percent: fields.next().and_then(|value| value.parse().ok()),
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn read_power_source() -> Option<PowerSource> {
+    None
+}
+
+fn active_action(policy: &BatteryPolicy, power: Option<PowerSource>) -> Option<BatteryAction> {
+    let power = power?;
+    let low = power
+        .percent
+        .is_some_and(|percent| percent <= policy.threshold_percent);
+    (policy.enabled && power.on_battery && low).then_some(policy.action)
+}
+
+/// Re-reads the power source and applies the policy when its outcome
+/// changes.
+fn check(app: &AppHandle) {
+    let policy = load_settings(app)
+        .map(|settings| settings.battery)
+        .unwrap_or_default();
+    let power = read_power_source();
+    let action = active_action(&policy, power);
+    let state = app.state::<AppState>();
+    {
+        let Ok(mut battery) = state.battery.lock() else {
+            return;
+        };
+        battery.power = power;
+        if battery.constrained == action {
+            return;
+        }
+        battery.constrained = action;
+    }
+
+    match action {
+        Some(BatteryAction::Defer) => emit_system_log(
+            app,
+            format!(
+                "battery at or below {}%, deferring low-priority sends",
+                policy.threshold_percent
+            ),
+        ),
+        Some(BatteryAction::LowerParallelism) => emit_system_log(
+            app,
+            format!(
+                "battery at or below {}%, new sends use a single connection",
+                policy.threshold_percent
+            ),
+        ),
+        None => emit_system_log(app, "battery policy no longer applies"),
+    }
+    scheduler::set_battery_deferred(app, action == Some(BatteryAction::Defer));
+}
+
+/// Whether new sends should give up parallel streams to save power.
+pub(crate) fn limits_parallelism(app: &AppHandle) -> bool {
+    app.state::<AppState>()
+        .battery
+        .lock()
+        .is_ok_and(|battery| battery.constrained == Some(BatteryAction::LowerParallelism))
+}
+
+pub(crate) fn spawn_watch_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        check(&app);
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+fn status(app: &AppHandle) -> Result<BatteryStatusPayload, String> {
+    let policy = load_settings(app)?.battery;
+    let (power, active_action) = app
+        .state::<AppState>()
+        .battery
+        .lock()
+        .map_err(|_| "failed to lock battery state".to_string())
+        .map(|battery| (battery.power, battery.constrained))?;
+    Ok(BatteryStatusPayload {
+        power,
+        policy,
+        active_action,
+    })
+}
+
+#[tauri::command]
+pub(crate) fn get_battery_status(app: AppHandle) -> Result<BatteryStatusPayload, String> {
+    status(&app)
+}
+
+#[tauri::command]
+pub(crate) fn set_battery_policy(
+    app: AppHandle,
+    policy: BatteryPolicy,
+) -> Result<BatteryStatusPayload, String> {
+    if !(1..=100).contains(&policy.threshold_percent) {
+        return Err("threshold must be between 1 and 100 percent".to_string());
+    }
+    update_settings(&app, |settings| {
+        settings.battery = policy;
+        Ok(())
+    })?;
+    check(&app);
+    status(&app)
+}
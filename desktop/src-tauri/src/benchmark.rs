@@ -0,0 +1,363 @@
+use crate::load_simulation::fill;
+use crate::settings::load_settings;
+use crate::storage::{now_ms, shared_dir};
+use crate::{emit_system_log, run_send_request, zero_copy, SendRequest};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const BENCHMARK_DIR: &str = "benchmarks";
+/// Bumped when fields change meaning, so old reports are not compared
+/// against new ones by mistake.
+const REPORT_SCHEMA: u32 = 1;
+const MAX_SAVED_REPORTS: usize = 50;
+const WRITE_CHUNK: usize = 1024 * 1024;
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum BenchmarkProfile {
+    /// About 40 MiB; a quick before/after check.
+    Quick,
+    /// About 500 MiB.
+    Standard,
+    /// About 2.5 GiB, including a 1 GiB file.
+    Large,
+}
+
+#[derive(Clone, Copy)]
+struct SizeClass {
+    label: &'static str,
+    file_size: u64,
+    files: u32,
+}
+
+impl BenchmarkProfile {
+    fn classes(self) -> Vec<SizeClass> {
+        let class = |label, file_size, files| SizeClass {
+            label,
+            file_size,
+            files,
+        };
+        match self {
+            BenchmarkProfile::Quick => vec![
+                class("4KiB", 4 * KIB, 20),
+                class("1MiB", MIB, 5),
+                class("32MiB", 32 * MIB, 1),
+            ],
+            BenchmarkProfile::Standard => vec![
+                class("4KiB", 4 * KIB, 100),
+                class("1MiB", MIB, 20),
+                class("32MiB", 32 * MIB, 4),
+                class("256MiB", 256 * MIB, 1),
+            ],
+            BenchmarkProfile::Large => vec![
+                class("4KiB", 4 * KIB, 200),
+                class("1MiB", MIB, 50),
+                class("32MiB", 32 * MIB, 8),
+                class("256MiB", 256 * MIB, 2),
+                class("1GiB", 1024 * MIB, 1),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ClassResult {
+    label: String,
+    file_size: u64,
+    files: u32,
+    succeeded: u32,
+    elapsed_ms: u64,
+    bytes_per_sec: f64,
+    files_per_sec: f64,
+    /// The first failure, if any send of the class failed.
+    error: Option<String>,
+}
+
+/// CPU time of the app and its CLI children over the whole run.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CpuUsage {
+    user_ms: u64,
+    system_ms: u64,
+    /// Of one core; above 100 when several cores were busy.
+    percent: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BenchmarkReport {
+    schema: u32,
+    app_version: String,
+    os: String,
+    arch: String,
+    profile: BenchmarkProfile,
+    /// `host:port`, or `device:port` for a discovered device.
+    target: String,
+    started_at_ms: u64,
+    /// `sendfile` when sends skip the CLI, `cli` otherwise.
+    send_path: String,
+    tls: bool,
+    streams: Option<u8>,
+    classes: Vec<ClassResult>,
+    bytes: u64,
+    elapsed_ms: u64,
+    bytes_per_sec: f64,
+    /// `None` where the platform does not report it.
+    cpu: Option<CpuUsage>,
+}
+
+#[cfg(unix)]
+fn cpu_time() -> Option<(Duration, Duration)> {
+    let to_duration = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec.max(0) as u64)
+            + Duration::from_micros(time.tv_usec.max(0) as u64)
+    };
+    let mut total = (Duration::ZERO, Duration::ZERO);
+    for who in [libc::RUSAGE_SELF, libc::RUSAGE_CHILDREN] {
+        // SAFETY: `usage` is a plain struct the call fills in.
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        if unsafe { libc::getrusage(who, &mut usage) } != 0 {
+            return None;
+        }
+        total.0 += to_duration(usage.ru_utime);
+        total.1 += to_duration(usage.ru_stime);
+    }
+    Some(total)
+}
+
+#[cfg(not(unix))]
+fn cpu_time() -> Option<(Duration, Duration)> {
+    None
+}
+
+fn per_sec(amount: f64, elapsed: Duration) -> f64 {
+    amount / elapsed.as_secs_f64().max(0.001)
+}
+
+/// Writes the files of one size class. Every run gets new content so the
+/// receiver's duplicate detection never skips a file.
+fn write_class_files(dir: &Path, class: &SizeClass, seed: u64) -> Result<Vec<PathBuf>, String> {
+    let mut buf = vec![0; WRITE_CHUNK];
+    (0..class.files)
+        .map(|index| {
+            let path = dir.join(format!("{}-{index}.bin", class.label));
+            let file = File::create(&path)
+                .map_err(|err| format!("failed to create benchmark file: {err}"))?;
+            let mut writer = BufWriter::new(file);
+            let file_seed = seed ^ (u64::from(index) << 40) ^ class.file_size;
+            let mut offset = 0;
+            while offset < class.file_size {
+                let len = (class.file_size - offset).min(WRITE_CHUNK as u64) as usize;
+                fill(file_seed, offset, &mut buf[..len]);
+                writer
+                    .write_all(&buf[..len])
+                    .map_err(|err| format!("failed to write benchmark file: {err}"))?;
+                offset += len as u64;
+            }
+            writer
+                .flush()
+                .map_err(|err| format!("failed to write benchmark file: {err}"))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn reports_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = shared_dir(app)?.join(BENCHMARK_DIR);
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("failed to create benchmark directory: {err}"))?;
+    Ok(dir)
+}
+
+fn saved_report_paths(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|err| format!("failed to read benchmark directory: {err}"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    // `benchmark-<started_at_ms>.json`: oldest first.
+    paths.sort();
+    Ok(paths)
+}
+
+fn save_report(app: &AppHandle, report: &BenchmarkReport) -> Result<(), String> {
+    let dir = reports_dir(app)?;
+    let path = dir.join(format!("benchmark-{:015}.json", report.started_at_ms));
+    let json = serde_json::to_vec_pretty(report)
+        .map_err(|err| format!("failed to serialize benchmark report: {err}"))?;
+    fs::write(&path, json).map_err(|err| format!("failed to save benchmark report: {err}"))?;
+    let paths = saved_report_paths(&dir)?;
+    for old in &paths[..paths.len().saturating_sub(MAX_SAVED_REPORTS)] {
+        let _ = fs::remove_file(old);
+    }
+    Ok(())
+}
+
+async fn run_classes(
+    app: &AppHandle,
+    target: &SendRequest,
+    profile: BenchmarkProfile,
+    dir: &Path,
+    seed: u64,
+) -> Result<Vec<ClassResult>, String> {
+    let mut results = Vec::new();
+    for class in profile.classes() {
+        let class_dir = dir.to_path_buf();
+        let files = tauri::async_runtime::spawn_blocking(move || {
+            write_class_files(&class_dir, &class, seed)
+        })
+        .await
+        .map_err(|err| format!("failed to join benchmark task: {err}"))??;
+        let mut result = ClassResult {
+            label: class.label.to_string(),
+            file_size: class.file_size,
+            files: class.files,
+            succeeded: 0,
+            elapsed_ms: 0,
+            bytes_per_sec: 0.0,
+            files_per_sec: 0.0,
+            error: None,
+        };
+        let started = Instant::now();
+        for path in &files {
+            let mut request = target.clone();
+            request.path = path.to_string_lossy().to_string();
+            request.dry_run = None;
+            request.note = Some(format!("benchmark ({})", class.label));
+            request.tags = vec!["benchmark".to_string()];
+            match run_send_request(app.clone(), request).await {
+                Ok(_) => result.succeeded += 1,
+                Err(err) => {
+                    result.error.get_or_insert(err);
+                }
+            }
+        }
+        let elapsed = started.elapsed();
+        for path in &files {
+            let _ = fs::remove_file(path);
+        }
+        if result.succeeded == 0 {
+            return Err(format!(
+                "benchmark stopped: every {} send failed: {}",
+                class.label,
+                result.error.unwrap_or_default()
+            ));
+        }
+        result.elapsed_ms = elapsed.as_millis() as u64;
+        result.bytes_per_sec = per_sec(
+            (u64::from(result.succeeded) * class.file_size) as f64,
+            elapsed,
+        );
+        result.files_per_sec = per_sec(f64::from(result.succeeded), elapsed);
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Sends generated files of a fixed set of sizes to `target` through the
+/// normal send path and saves a report under `benchmarks/` in the app data
+/// folder. Reports from the same profile and target are comparable across
+/// app versions; sends run one at a time and are tagged `benchmark` in the
+/// history. `target.path` is ignored.
+#[tauri::command]
+pub(crate) async fn run_benchmark(
+    app: AppHandle,
+    target: SendRequest,
+    profile: BenchmarkProfile,
+) -> Result<BenchmarkReport, String> {
+    let settings = load_settings(&app)?;
+    let started_at_ms = now_ms();
+    let dir = std::env::temp_dir().join(format!("local-sent-benchmark-{started_at_ms}"));
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("failed to create benchmark directory: {err}"))?;
+    // The path only matters for whether `sendfile` applies to single files.
+    let mut probe = target.clone();
+    probe.path = dir.join("probe").to_string_lossy().to_string();
+    let _ = fs::write(&probe.path, b"");
+    let send_path = if zero_copy::takes_native_path(&app, &probe, &settings) {
+        "sendfile"
+    } else {
+        "cli"
+    };
+    let _ = fs::remove_file(&probe.path);
+
+    emit_system_log(
+        &app,
+        format!("benchmark started ({profile:?}, {send_path})"),
+    );
+    let cpu_before = cpu_time();
+    let started = Instant::now();
+    let classes = run_classes(&app, &target, profile, &dir, started_at_ms).await;
+    let elapsed = started.elapsed();
+    let cpu_after = cpu_time();
+    let _ = fs::remove_dir_all(&dir);
+    let classes = classes?;
+
+    let bytes = classes
+        .iter()
+        .map(|class| u64::from(class.succeeded) * class.file_size)
+        .sum();
+    let cpu = cpu_before.zip(cpu_after).map(
+        |((user_before, system_before), (user_after, system_after))| {
+            let user = user_after.saturating_sub(user_before);
+            let system = system_after.saturating_sub(system_before);
+            CpuUsage {
+                user_ms: user.as_millis() as u64,
+                system_ms: system.as_millis() as u64,
+                percent: (user + system).as_secs_f64() / elapsed.as_secs_f64().max(0.001) * 100.0,
+            }
+        },
+    );
+    let report = BenchmarkReport {
+        schema: REPORT_SCHEMA,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        profile,
+        target: format!(
+            "{}:{}",
+            target
+                .host
+                .clone()
+                .or_else(|| target.device.clone())
+                .unwrap_or_else(|| "auto".to_string()),
+            target.port
+        ),
+        started_at_ms,
+        send_path: send_path.to_string(),
+        tls: target.tls.unwrap_or(false),
+        streams: target.streams,
+        classes,
+        bytes,
+        elapsed_ms: elapsed.as_millis() as u64,
+        bytes_per_sec: per_sec(bytes as f64, elapsed),
+        cpu,
+    };
+    save_report(&app, &report)?;
+    emit_system_log(
+        &app,
+        format!(
+            "benchmark finished: {}/s overall",
+            zero_copy::format_bytes(report.bytes_per_sec)
+        ),
+    );
+    Ok(report)
+}
+
+/// Saved reports, oldest first, for comparing runs.
+#[tauri::command]
+pub(crate) fn list_benchmarks(app: AppHandle) -> Result<Vec<BenchmarkReport>, String> {
+    let paths = saved_report_paths(&reports_dir(&app)?)?;
+    Ok(paths
+        .iter()
+        .filter_map(|path| fs::read(path).ok())
+        .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+        .collect())
+}
@@ -0,0 +1,125 @@
+//! Stand-in for the `local-sent` CLI in the desktop app's tests. It speaks
+//! just enough of the CLI's output and `--ipc json` protocol for the command
+//! layer, and `LOCAL_SENT_MOCK_SCENARIO` makes it misbehave on purpose:
+//!
+//! - `ok` (default): well-formed output
+//! - `crash`: writes to stderr and exits with status 3
+//! - `hang`: never exits
+//! - `malformed`: truncated JSON and garbage lines
+//! - `unicode`: CJK and emoji text, then bytes that are not UTF-8
+
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+const SCENARIO_ENV: &str = "LOCAL_SENT_MOCK_SCENARIO";
+
+fn print_line(out: &mut impl Write, value: Value) {
+    let _ = writeln!(out, "{value}");
+    let _ = out.flush();
+}
+
+fn discover(out: &mut impl Write) {
+    print_line(
+        out,
+        json!([
+            { "name": "office-pc", "host": "office-pc.local", "port": 39393, "addresses": ["192.168.1.20"] },
+            { "name": "laptop", "host": "laptop.local", "port": 39393, "addresses": ["192.168.1.21", "fe80::1"] }
+        ]),
+    );
+}
+
+fn send(out: &mut impl Write, ipc: bool, args: &[String]) {
+    let path = args.first().map(String::as_str).unwrap_or("file.bin");
+    if !ipc {
+        let _ = writeln!(out, "sent {path} ({})", args.join(" "));
+        return;
+    }
+    for bytes in [0, 512, 1024] {
+        print_line(
+            out,
+            json!({ "v": 1, "type": "progress", "direction": "send", "path": path,
+                    "bytes": bytes, "total": 1024, "bytesPerSecond": 2048 }),
+        );
+    }
+    print_line(out, json!({ "v": 1, "type": "sendDone" }));
+}
+
+/// Asks to confirm one file, then answers control requests from stdin
+/// until it closes.
+fn listen(out: &mut impl Write) {
+    print_line(
+        out,
+        json!({ "v": 1, "type": "confirmRequest", "id": 1, "from": "192.168.1.20",
+                "path": "report.pdf", "size": 1024 }),
+    );
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let method = request["method"].as_str().unwrap_or_default();
+        let approved = method == "approve";
+        print_line(
+            out,
+            json!({ "v": 1, "type": "response", "id": request["id"],
+                    "ok": approved || method == "reject" }),
+        );
+        if approved {
+            print_line(
+                out,
+                json!({ "v": 1, "type": "saved", "path": "report.pdf",
+                        "alreadyHad": false, "from": "192.168.1.20" }),
+            );
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let ipc = args.starts_with(&["--ipc".to_string(), "json".to_string()]);
+    if ipc {
+        args.drain(..2);
+    }
+    let scenario = std::env::var(SCENARIO_ENV).unwrap_or_else(|_| "ok".to_string());
+    let mut out = io::stdout().lock();
+
+    match scenario.as_str() {
+        "crash" => {
+            let _ = writeln!(out, "starting {}", args.join(" "));
+            eprintln!("simulated crash: connection reset by peer");
+            return ExitCode::from(3);
+        }
+        "hang" => loop {
+            thread::sleep(Duration::from_secs(60));
+        },
+        "malformed" => {
+            let _ = out.write_all(b"[{\"name\": \"office-pc\", \"host\"\n");
+            let _ = out.write_all(b"{\"v\":1,\"type\":\"progress\",\"bytes\":\n");
+            let _ = out.write_all(b"{\"v\":1,\"type\":\"saved\"}\n");
+            let _ = out.write_all(b"not json at all\n");
+            return ExitCode::SUCCESS;
+        }
+        "unicode" => {
+            let _ = writeln!(out, "已发送 报告.pdf 📦 → Ünïcödé");
+            let _ = out.write_all(b"broken \xff\xfe bytes\n");
+            return ExitCode::SUCCESS;
+        }
+        _ => {}
+    }
+
+    match args.first().map(String::as_str) {
+        Some("discover") => discover(&mut out),
+        Some("send") => send(&mut out, ipc, &args[1..]),
+        Some("listen") => listen(&mut out),
+        _ => {
+            eprintln!("unknown command: {}", args.join(" "));
+            return ExitCode::from(2);
+        }
+    }
+    ExitCode::SUCCESS
+}
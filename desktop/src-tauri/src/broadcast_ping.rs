@@ -0,0 +1,64 @@
+//! The CLI's "ping all listeners" probe, a last-resort discovery path for
+//! networks where nothing else finds a device. Answering and probing are
+//! separate opt-ins; the listener stops answering while hidden from
+//! discovery, like it stops announcing.
+
+use crate::settings::{load_settings, update_settings};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct BroadcastPingSettings {
+    /// Answer pings while receiving; applies from the next `start_listen`.
+    pub(crate) answer: bool,
+    /// Ping as part of every discovery.
+    pub(crate) probe: bool,
+    /// Port replies are sent to, e.g. one the firewall lets in; any free
+    /// port when unset.
+    pub(crate) reply_port: Option<u16>,
+}
+
+fn load(app: &AppHandle) -> BroadcastPingSettings {
+    load_settings(app)
+        .map(|settings| settings.broadcast_ping)
+        .unwrap_or_default()
+}
+
+/// `--answer-pings` for `listen` while answering is on.
+pub(crate) fn listen_args(app: &AppHandle) -> Vec<String> {
+    if load(app).answer {
+        vec!["--answer-pings".to_string()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// `--ping` and `--ping-reply-port` for `discover` while probing is on.
+pub(crate) fn discover_args(app: &AppHandle) -> Vec<String> {
+    let ping = load(app);
+    if !ping.probe {
+        return Vec::new();
+    }
+    let mut args = vec!["--ping".to_string()];
+    if let Some(port) = ping.reply_port.filter(|port| *port > 0) {
+        args.push("--ping-reply-port".to_string());
+        args.push(port.to_string());
+    }
+    args
+}
+
+#[tauri::command]
+pub(crate) fn set_broadcast_ping(
+    app: AppHandle,
+    ping: BroadcastPingSettings,
+) -> Result<BroadcastPingSettings, String> {
+    update_settings(&app, |settings| {
+        settings.broadcast_ping = BroadcastPingSettings {
+            reply_port: ping.reply_port.filter(|port| *port > 0),
+            ..ping
+        };
+        Ok(())
+    })
+    .map(|settings| settings.broadcast_ping)
+}
@@ -0,0 +1,143 @@
+use crate::parser::ThroughputRegime;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// How long a request waits for the listener's `response`.
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Outcome = Result<Value, String>;
+
+/// Control requests written to the listener's stdin that still wait for the
+/// `response` message with their correlation id.
+#[derive(Debug, Default)]
+pub(crate) struct ControlChannel {
+    next_id: u64,
+    pending: HashMap<u64, Sender<Outcome>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActiveTransfer {
+    path: String,
+    from: String,
+    bytes: u64,
+    /// `None` while streaming content of unknown length.
+    total: Option<u64>,
+    started_at: u64,
+    #[serde(default)]
+    bytes_per_second: u64,
+    #[serde(default)]
+    eta_seconds: Option<u64>,
+    #[serde(default)]
+    stalled: bool,
+    #[serde(default)]
+    regime: ThroughputRegime,
+}
+
+fn next_id(state: &AppState, waiter: Option<Sender<Outcome>>) -> Result<u64, String> {
+    let mut channel = state
+        .listen_control
+        .lock()
+        .map_err(|_| "failed to lock listen control channel".to_string())?;
+    channel.next_id += 1;
+    let id = channel.next_id;
+    if let Some(waiter) = waiter {
+        channel.pending.insert(id, waiter);
+    }
+    Ok(id)
+}
+
+fn forget(state: &AppState, id: u64) {
+    if let Ok(mut channel) = state.listen_control.lock() {
+        channel.pending.remove(&id);
+    }
+}
+
+fn write_request(state: &AppState, id: u64, method: &str, params: Value) -> Result<(), String> {
+    let line = json!({ "id": id, "method": method, "params": params }).to_string();
+    let mut stdin_guard = state
+        .listen_stdin
+        .lock()
+        .map_err(|_| "failed to lock listen stdin state".to_string())?;
+    let stdin = stdin_guard
+        .as_mut()
+        .ok_or_else(|| "listen process is not running".to_string())?;
+    writeln!(stdin, "{line}").map_err(|err| format!("failed to write listen control: {err}"))?;
+    stdin
+        .flush()
+        .map_err(|err| format!("failed to flush listen control: {err}"))
+}
+
+/// Sends `method` to the listener and waits for its answer. Must not be
+/// called from the listener's output reader, which delivers that answer.
+pub(crate) fn request(state: &AppState, method: &str, params: Value) -> Result<Value, String> {
+    let (sender, receiver) = mpsc::channel();
+    let id = next_id(state, Some(sender))?;
+    if let Err(err) = write_request(state, id, method, params) {
+        forget(state, id);
+        return Err(err);
+    }
+    match receiver.recv_timeout(CONTROL_TIMEOUT) {
+        Ok(outcome) => outcome,
+        Err(RecvTimeoutError::Timeout) => {
+            forget(state, id);
+            Err(format!(
+                "listener did not answer `{method}` within {}s",
+                CONTROL_TIMEOUT.as_secs()
+            ))
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            Err(format!("listener stopped before answering `{method}`"))
+        }
+    }
+}
+
+/// Sends `method` without waiting; a failure comes back as an unclaimed
+/// response. Used where blocking would stall the output reader.
+pub(crate) fn notify(state: &AppState, method: &str, params: Value) -> Result<(), String> {
+    let id = next_id(state, None)?;
+    write_request(state, id, method, params)
+}
+
+/// Hands a `response` to the request waiting for it. Returns false when
+/// nothing waits, e.g. for a `notify` or a request that timed out.
+pub(crate) fn resolve(state: &AppState, id: u64, outcome: Outcome) -> bool {
+    let waiter = state
+        .listen_control
+        .lock()
+        .ok()
+        .and_then(|mut channel| channel.pending.remove(&id));
+    match waiter {
+        Some(waiter) => {
+            let _ = waiter.send(outcome);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Fails every waiting request; called when the listener goes away.
+pub(crate) fn reset(state: &AppState) {
+    if let Ok(mut channel) = state.listen_control.lock() {
+        channel.pending.clear();
+    }
+}
+
+/// Receives the running listener is writing right now, with their progress.
+#[tauri::command]
+pub(crate) async fn list_receive_transfers(app: AppHandle) -> Result<Vec<ActiveTransfer>, String> {
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        request(app.state::<AppState>().inner(), "transfers", json!({}))
+    })
+    .await
+    .map_err(|err| format!("failed to join listen control task: {err}"))??;
+    let transfers = result.get("transfers").cloned().unwrap_or_default();
+    serde_json::from_value(transfers)
+        .map_err(|err| format!("failed to parse active transfers: {err}"))
+}
@@ -0,0 +1,185 @@
+//! Runs the command layer against `mock_cli` (`src/bin/mock_cli.rs`)
+//! instead of the bundled CLI, so tests do not need Node or a network.
+//! A test picks the mock's behavior with `use_mock`; everything else still
+//! resolves the real runtime.
+
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::process::Command;
+
+const SCENARIO_ENV: &str = "LOCAL_SENT_MOCK_SCENARIO";
+
+thread_local! {
+    // Per thread: the command layer builds the command on the caller's
+    // thread, and tests run in parallel.
+    static SCENARIO: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Makes `build_cli_command` on this thread start `mock_cli` with
+/// `scenario`; see the mock for the scenarios it knows.
+pub(crate) fn use_mock(scenario: &'static str) {
+    SCENARIO.with(|current| current.set(Some(scenario)));
+}
+
+/// `mock_cli` next to the test binary: cargo builds binaries into the
+/// parent of the `deps` folder tests run from.
+fn mock_cli_path() -> PathBuf {
+    let exe = std::env::current_exe().expect("test binary path");
+    let dir = exe
+        .parent()
+        .and_then(|deps| deps.parent())
+        .expect("target directory");
+    dir.join(format!("mock_cli{}", std::env::consts::EXE_SUFFIX))
+}
+
+pub(crate) fn mock_command(args: &[String]) -> Option<Command> {
+    let scenario = SCENARIO.with(Cell::get)?;
+    let mut command = Command::new(mock_cli_path());
+    command.args(args).env(SCENARIO_ENV, scenario);
+    Some(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_ipc::{self, CliMessage};
+    use crate::process_pool::{self, ChildKind};
+    use crate::{
+        answer_confirm, build_cli_command, capture_cli, parse_discovered_devices, render_cli_error,
+        AppState, CommandResult,
+    };
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    const DEADLINE: Option<Duration> = Some(Duration::from_secs(20));
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn run(scenario: &'static str, cli_args: &[&str], kind: ChildKind) -> CommandResult {
+        use_mock(scenario);
+        capture_cli(&AppState::default(), &args(cli_args), kind, DEADLINE, None)
+            .expect("mock CLI runs")
+    }
+
+    #[test]
+    fn send_output_and_status_are_captured() {
+        let output = run(
+            "ok",
+            &["send", "a.txt", "--host", "laptop.local"],
+            ChildKind::Send,
+        );
+        assert!(output.success);
+        assert_eq!(output.code, 0);
+        assert!(output.stdout.contains("sent a.txt"), "{}", output.stdout);
+    }
+
+    #[test]
+    fn ipc_send_output_parses() {
+        let output = run("ok", &["--ipc", "json", "send", "a.txt"], ChildKind::Send);
+        let messages: Vec<CliMessage> = output
+            .stdout
+            .lines()
+            .filter_map(cli_ipc::parse_line)
+            .collect();
+        assert_eq!(messages.len(), 4);
+        assert!(messages[2].completes());
+        assert!(matches!(messages[3], CliMessage::SendDone(_)));
+    }
+
+    #[test]
+    fn discovered_devices_parse() {
+        let output = run(
+            "ok",
+            &["discover", "-t", "1", "--json"],
+            ChildKind::Discover,
+        );
+        let devices = parse_discovered_devices(&output.stdout).expect("device list");
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[1].addresses, ["192.168.1.21", "fe80::1"]);
+        assert!(parse_discovered_devices("  \n").expect("empty").is_empty());
+    }
+
+    #[test]
+    fn crash_is_reported_with_stderr() {
+        let output = run("crash", &["send", "a.txt"], ChildKind::Send);
+        assert!(!output.success);
+        assert_eq!(output.code, 3);
+        let error = render_cli_error("send", &output);
+        assert!(error.starts_with("send failed (exit code 3)"), "{error}");
+        assert!(error.contains("simulated crash"), "{error}");
+    }
+
+    #[test]
+    fn hang_is_killed_at_the_deadline() {
+        use_mock("hang");
+        let state = AppState::default();
+        let result = capture_cli(
+            &state,
+            &args(&["send", "a.txt"]),
+            ChildKind::Send,
+            Some(Duration::from_millis(300)),
+            None,
+        );
+        let error = result.expect_err("hang must time out");
+        assert!(error.contains("timed out"), "{error}");
+        assert_eq!(process_pool::len(&state), 0);
+    }
+
+    #[test]
+    fn malformed_output_is_rejected_not_misread() {
+        let output = run("malformed", &["discover", "--json"], ChildKind::Discover);
+        assert!(output.success);
+        let error = parse_discovered_devices(&output.stdout).expect_err("malformed list");
+        assert!(
+            error.starts_with("failed to parse discovery JSON"),
+            "{error}"
+        );
+        for line in output.stdout.lines() {
+            assert!(cli_ipc::parse_line(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn unicode_output_survives_and_invalid_bytes_are_replaced() {
+        let output = run("unicode", &["send", "报告.pdf"], ChildKind::Send);
+        assert!(output.stdout.contains("已发送 报告.pdf 📦 → Ünïcödé"));
+        assert!(output.stdout.contains("broken \u{fffd}\u{fffd} bytes"));
+    }
+
+    #[test]
+    fn listen_confirmation_round_trip() {
+        use_mock("ok");
+        let state = AppState::default();
+        let mut child = build_cli_command(&args(&["--ipc", "json", "listen"]))
+            .expect("mock command")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("mock listener starts");
+        *state.listen_stdin.lock().expect("listen stdin") = child.stdin.take();
+        let mut lines = BufReader::new(child.stdout.take().expect("stdout")).lines();
+        let mut next = || {
+            let line = lines.next().expect("line").expect("readable line");
+            cli_ipc::parse_line(&line).expect("IPC message")
+        };
+
+        let CliMessage::ConfirmRequest(request) = next() else {
+            panic!("expected a confirm request");
+        };
+        assert_eq!(request.path, "report.pdf");
+        answer_confirm(&state, "approve", request.id).expect("answer written");
+        assert!(matches!(next(), CliMessage::Response { ok: true, .. }));
+        let CliMessage::Saved { path, from, .. } = next() else {
+            panic!("expected the saved file");
+        };
+        assert_eq!(path, PathBuf::from("report.pdf"));
+        assert_eq!(from.as_deref(), Some("192.168.1.20"));
+
+        // Closing stdin is how the app stops a listener.
+        *state.listen_stdin.lock().expect("listen stdin") = None;
+        assert!(child.wait().expect("listener exits").success());
+    }
+}
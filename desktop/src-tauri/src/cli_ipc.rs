@@ -0,0 +1,211 @@
+use crate::history::TransferNote;
+use crate::parser;
+pub(crate) use crate::parser::{ConfirmRequest, Direction, Progress};
+use crate::progress_stats::{self, ProgressSnapshot};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Puts the CLI into JSON-lines output; global options go before the
+/// subcommand.
+pub(crate) const IPC_ARGS: [&str; 2] = ["--ipc", "json"];
+
+/// One stdout line of a CLI started with `IPC_ARGS` (`src/ipc.ts`).
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub(crate) enum CliMessage {
+    /// Human-oriented text, shown as is.
+    Log {
+        line: String,
+    },
+    Progress(Progress),
+    ConfirmRequest(ConfirmRequest),
+    /// A received file is verified and in its final place.
+    Saved {
+        path: PathBuf,
+        already_had: bool,
+        /// The sender's address; older CLIs leave it out.
+        #[serde(default)]
+        from: Option<String>,
+        #[serde(default)]
+        note: Option<TransferNote>,
+        /// From an unpaired Quick Share sender, whose address proves nothing.
+        #[serde(default)]
+        quick_share: bool,
+    },
+    ReceiveFailed {
+        message: String,
+    },
+    /// A text message from a peer; `from` is its address. The CLI also
+    /// prints it as a `log` line.
+    Message {
+        from: String,
+        name: String,
+        text: String,
+    },
+    /// Clipboard text from the peer allowed by `--clipboard-from`.
+    Clipboard {
+        from: String,
+        text: String,
+    },
+    /// A peer browsed or pulled from a shared folder; the CLI prints the
+    /// same event as a `log` line.
+    ShareAccess {
+        from: String,
+        action: String,
+        path: String,
+        allowed: bool,
+        bytes: Option<u64>,
+    },
+    /// The files a send is about to transfer.
+    SendPlan {
+        files: Vec<PlannedFile>,
+    },
+    /// One file of a send is safely on the receiver; `source` is the local
+    /// file it was read from.
+    FileSent {
+        path: String,
+        source: PathBuf,
+        #[serde(default)]
+        sha256: Option<String>,
+    },
+    /// A send finished. The CLI also prints a summary as a `log` line.
+    SendDone(SendDone),
+    /// `send --dry-run` passed: the receiver accepts the pair code and has
+    /// room for `bytes`. `free_bytes` is absent when it cannot tell.
+    DryRun {
+        files: u64,
+        bytes: u64,
+        #[serde(default)]
+        free_bytes: Option<u64>,
+    },
+    /// The command failed; the CLI also prints the message to stderr.
+    Error {
+        message: String,
+    },
+    /// Answer to a `cli_control` request with the same `id`.
+    Response {
+        id: u64,
+        ok: bool,
+        #[serde(default)]
+        result: Value,
+        error: Option<String>,
+    },
+    /// `hello`, `listening` and anything newer: informational,
+    /// the same facts also arrive as `log` lines.
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct PlannedFile {
+    pub(crate) path: String,
+    pub(crate) size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct SendDone {
+    /// Absent from older CLIs.
+    #[serde(default)]
+    pub(crate) manifest: Vec<SentFile>,
+}
+
+/// One file of a finished send, in the order the files were sent.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SentFile {
+    pub(crate) path: String,
+    pub(crate) bytes: u64,
+    #[serde(default)]
+    pub(crate) resumed_bytes: u64,
+    /// The receiver already had the content and kept its copy.
+    #[serde(default)]
+    pub(crate) deduplicated: bool,
+    /// Reported by the receiver.
+    #[serde(default)]
+    pub(crate) saved_path: Option<String>,
+}
+
+impl SendDone {
+    /// Saved paths come from the receiver and are shown in the UI.
+    fn cleaned(mut self) -> Self {
+        for file in &mut self.manifest {
+            file.path = parser::clean_text(&file.path);
+            file.saved_path = file.saved_path.as_deref().map(parser::clean_text);
+        }
+        self
+    }
+}
+
+impl CliMessage {
+    /// Whether a progress message reports the last byte of its file.
+    pub(crate) fn completes(&self) -> bool {
+        matches!(self, CliMessage::Progress(progress) if progress.completes())
+    }
+}
+
+/// `None` for lines that are not IPC messages; see `parser::ipc_value`.
+/// Confirm requests and progress carry what a peer sent and go through
+/// the parser's checks.
+pub(crate) fn parse_line(line: &str) -> Option<CliMessage> {
+    let value = parser::ipc_value(line)?;
+    match parser::message_type(&value) {
+        Some("confirmRequest") => {
+            parser::parse_confirm_request(value).map(CliMessage::ConfirmRequest)
+        }
+        Some("progress") => parser::parse_progress(value).map(CliMessage::Progress),
+        Some("sendDone") => serde_json::from_value(value)
+            .ok()
+            .map(|done: SendDone| CliMessage::SendDone(done.cleaned())),
+        _ => serde_json::from_value(value).ok(),
+    }
+}
+
+/// Renders `message` the way the CLI prints it without `--ipc`, which is
+/// what the activity feed and the frontend's progress bars expect. `None`
+/// for messages that are acted on rather than shown.
+pub(crate) fn display_text(message: &CliMessage) -> Option<String> {
+    match message {
+        CliMessage::Log { line } => Some(line.clone()),
+        CliMessage::Progress(progress) => {
+            let prefix = match progress.direction {
+                Direction::Send => format!("[send {}]", progress.path),
+                Direction::Recv => format!("[recv {}]", progress.path),
+            };
+            let snapshot = ProgressSnapshot {
+                bytes_per_second: progress.bytes_per_second,
+                eta_seconds: progress.eta_seconds,
+                stalled: progress.stalled,
+                regime: progress.regime,
+            };
+            let mut line =
+                progress_stats::render_line(&prefix, progress.bytes, progress.total, &snapshot);
+            if let Some(batch) = &progress.batch {
+                line.push_str(&progress_stats::render_batch(batch));
+            }
+            Some(line)
+        }
+        CliMessage::Response {
+            ok: false,
+            error: Some(error),
+            ..
+        } => Some(format!("[control] {error}")),
+        CliMessage::ConfirmRequest(_)
+        | CliMessage::Response { .. }
+        | CliMessage::Saved { .. }
+        | CliMessage::ReceiveFailed { .. }
+        | CliMessage::Message { .. }
+        | CliMessage::Clipboard { .. }
+        | CliMessage::ShareAccess { .. }
+        | CliMessage::SendPlan { .. }
+        | CliMessage::FileSent { .. }
+        | CliMessage::SendDone(_)
+        | CliMessage::DryRun { .. }
+        | CliMessage::Error { .. }
+        | CliMessage::Other => None,
+    }
+}
@@ -0,0 +1,334 @@
+use crate::audit::{record_audit, AuditAction};
+use crate::device_groups::match_device;
+use crate::events::{emit_app_event, AppEvent};
+use crate::process_pool::ChildKind;
+use crate::remote_shares::RemoteDevice;
+use crate::settings::{load_settings, update_settings};
+use crate::storage::now_ms;
+use crate::{
+    cli_control, discover_devices, emit_system_log, inspect_listen_state, kill_switch,
+    listen_state_payload, outbound_policy, render_cli_error, run_cli_capture_with_input, AppState,
+};
+use arboard::Clipboard;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::net::{IpAddr, ToSocketAddrs};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Matches `MAX_CLIPBOARD_BYTES` in `src/protocol.ts`.
+const MAX_CLIPBOARD_BYTES: usize = 256 * 1024;
+const PEER_DISCOVERY_TIMEOUT_MS: u64 = 2000;
+
+/// Continuous clipboard sync with one device. Both sides have to turn it on
+/// for each other: a listener only takes clipboard text from the addresses
+/// of its own peer.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ClipboardSync {
+    pub(crate) enabled: bool,
+    pub(crate) peer: Option<RemoteDevice>,
+    /// Where the peer's pushes come from, resolved when sync is turned on.
+    pub(crate) peer_addresses: Vec<String>,
+}
+
+/// The system clipboard handle and what was last synced through it.
+#[derive(Default)]
+pub(crate) struct ClipboardSyncState {
+    /// Kept open: on X11 the clipboard is served by its owner, so dropping
+    /// the handle right after writing would lose the text.
+    clipboard: Option<Clipboard>,
+    /// Digest of the text last read locally or applied from the peer. Text
+    /// applied from the peer is never pushed back, which prevents loops.
+    last_digest: Option<[u8; 32]>,
+    last_synced_at_ms: Option<u64>,
+    last_error: Option<String>,
+}
+
+/// Part of `listen_status`, so the on/off state shows next to the listener.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ClipboardSyncStatus {
+    enabled: bool,
+    peer: Option<String>,
+    /// Syncing needs the listener running to receive the peer's changes.
+    active: bool,
+    last_synced_at_ms: Option<u64>,
+    last_error: Option<String>,
+}
+
+fn digest(text: &str) -> [u8; 32] {
+    Sha256::digest(text.as_bytes()).into()
+}
+
+fn lock_state(state: &AppState) -> Result<std::sync::MutexGuard<'_, ClipboardSyncState>, String> {
+    state
+        .clipboard_sync
+        .lock()
+        .map_err(|_| "failed to lock clipboard sync".to_string())
+}
+
+impl ClipboardSyncState {
+    fn clipboard(&mut self) -> Result<&mut Clipboard, String> {
+        if self.clipboard.is_none() {
+            self.clipboard =
+                Some(Clipboard::new().map_err(|err| format!("failed to open clipboard: {err}"))?);
+        }
+        Ok(self.clipboard.as_mut().expect("clipboard was just opened"))
+    }
+}
+
+pub(crate) fn status(app: &AppHandle, running: bool) -> ClipboardSyncStatus {
+    let sync = load_settings(app)
+        .map(|settings| settings.clipboard_sync)
+        .unwrap_or_default();
+    let state = app.state::<AppState>();
+    let (last_synced_at_ms, last_error) = lock_state(state.inner())
+        .map(|state| (state.last_synced_at_ms, state.last_error.clone()))
+        .unwrap_or_default();
+    ClipboardSyncStatus {
+        enabled: sync.enabled,
+        peer: sync.peer.as_ref().map(RemoteDevice::label),
+        active: sync.enabled && running,
+        last_synced_at_ms,
+        last_error: last_error.filter(|_| sync.enabled),
+    }
+}
+
+fn emit_status(app: &AppHandle) {
+    if let Ok(snapshot) = inspect_listen_state(&app.state::<AppState>()) {
+        emit_app_event(
+            app,
+            AppEvent::ListenState(listen_state_payload(app, snapshot.running, snapshot.pid)),
+        );
+    }
+}
+
+/// Records the outcome of a sync; the status is re-emitted and logged only
+/// when the error changes, so an offline peer does not flood the log.
+fn record_outcome(app: &AppHandle, error: Option<String>) {
+    let changed = {
+        let state = app.state::<AppState>();
+        let Ok(mut sync) = lock_state(state.inner()) else {
+            return;
+        };
+        if error.is_none() {
+            sync.last_synced_at_ms = Some(now_ms());
+        }
+        let changed = sync.last_error != error;
+        sync.last_error = error.clone();
+        changed
+    };
+    if changed {
+        if let Some(error) = error {
+            emit_system_log(app, format!("clipboard sync: {error}"));
+        }
+        emit_status(app);
+    }
+}
+
+/// `--clipboard-from` for `listen` while sync is on.
+pub(crate) fn listen_args(app: &AppHandle) -> Vec<String> {
+    match load_settings(app) {
+        Ok(settings)
+            if settings.clipboard_sync.enabled
+                && !settings.clipboard_sync.peer_addresses.is_empty() =>
+        {
+            vec![
+                "--clipboard-from".to_string(),
+                settings.clipboard_sync.peer_addresses.join(","),
+            ]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Applies clipboard text pushed by the peer.
+pub(crate) fn received(app: &AppHandle, from: &str, text: &str) {
+    let allowed = load_settings(app).is_ok_and(|settings| {
+        settings.clipboard_sync.enabled
+            && settings
+                .clipboard_sync
+                .peer_addresses
+                .iter()
+                .any(|address| address == from)
+    });
+    if !allowed || text.len() > MAX_CLIPBOARD_BYTES {
+        return;
+    }
+    let result = {
+        let state = app.state::<AppState>();
+        let Ok(mut sync) = lock_state(state.inner()) else {
+            return;
+        };
+        // Set first: the next poll then sees this text as already synced.
+        sync.last_digest = Some(digest(text));
+        sync.clipboard().and_then(|clipboard| {
+            clipboard
+                .set_text(text)
+                .map_err(|err| format!("failed to write clipboard: {err}"))
+        })
+    };
+    record_outcome(app, result.err());
+}
+
+/// Reads the clipboard and returns its text if it changed since the last
+/// look and should be pushed.
+fn take_local_change(state: &AppState) -> Option<Result<String, String>> {
+    let mut sync = lock_state(state).ok()?;
+    // Images and files have no text; they are left alone.
+    let text = sync.clipboard().ok()?.get_text().ok()?;
+    let current = digest(&text);
+    let previous = sync.last_digest.replace(current);
+    // The first read after starting only sets the baseline, so turning sync
+    // on never overwrites the peer's clipboard with something old.
+    if previous.is_none() || previous == Some(current) || text.is_empty() {
+        return None;
+    }
+    if text.len() > MAX_CLIPBOARD_BYTES {
+        return Some(Err(format!(
+            "copied text is larger than {} KiB and was not synced",
+            MAX_CLIPBOARD_BYTES / 1024
+        )));
+    }
+    Some(Ok(text))
+}
+
+fn push(app: &AppHandle, peer: &RemoteDevice, text: String) -> Result<(), String> {
+    kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+    let mut args = vec!["clipboard".to_string()];
+    args.extend(peer.args()?);
+    outbound_policy::enforce_destination(app, &peer.destination())?;
+    let output =
+        run_cli_capture_with_input(app.clone(), args, ChildKind::Discover, Some(text.into()))?;
+    if !output.success {
+        return Err(render_cli_error("clipboard", &output));
+    }
+    Ok(())
+}
+
+fn poll(app: &AppHandle) {
+    let Ok(settings) = load_settings(app) else {
+        return;
+    };
+    let sync = settings.clipboard_sync;
+    let state = app.state::<AppState>();
+    let running = inspect_listen_state(&state).is_ok_and(|snapshot| snapshot.running);
+    let Some(peer) = sync.peer.filter(|_| sync.enabled && running) else {
+        // Forget the baseline so resuming does not push what was copied
+        // while sync was off.
+        if let Ok(mut sync) = lock_state(state.inner()) {
+            sync.last_digest = None;
+        }
+        return;
+    };
+    match take_local_change(state.inner()) {
+        Some(Ok(text)) => record_outcome(app, push(app, &peer, text).err()),
+        Some(Err(err)) => record_outcome(app, Some(err)),
+        None => {}
+    }
+}
+
+pub(crate) fn spawn_watch_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        poll(&app);
+        thread::sleep(POLL_INTERVAL);
+    });
+}
+
+/// Addresses the peer's pushes can come from. Device names are looked up
+/// in discovery once, now.
+async fn resolve_peer_addresses(
+    app: &AppHandle,
+    peer: &RemoteDevice,
+) -> Result<Vec<String>, String> {
+    if let Some(host) = peer.host() {
+        if let Ok(address) = host.parse::<IpAddr>() {
+            return Ok(vec![address.to_string()]);
+        }
+        let addresses: Vec<String> = (host, peer.port())
+            .to_socket_addrs()
+            .map_err(|err| format!("failed to resolve {host}: {err}"))?
+            .map(|address| address.ip().to_string())
+            .collect();
+        return Ok(addresses);
+    }
+    let name = peer.device().unwrap_or_default();
+    let devices = discover_devices(app.clone(), PEER_DISCOVERY_TIMEOUT_MS).await?;
+    let device = match_device(&devices, name)
+        .ok_or_else(|| format!("device `{name}` was not found on the network"))?;
+    let mut addresses = device.addresses.clone();
+    if !addresses.contains(&device.host) {
+        addresses.push(device.host.clone());
+    }
+    Ok(addresses)
+}
+
+/// Turns clipboard sync with `peer` on or off. Text copied on either device
+/// is then pushed to the other while both are listening; the other device
+/// must turn sync on for this one as well.
+#[tauri::command]
+pub(crate) async fn set_clipboard_sync(
+    app: AppHandle,
+    enabled: bool,
+    peer: Option<RemoteDevice>,
+) -> Result<ClipboardSyncStatus, String> {
+    let sync = if enabled {
+        let peer = peer.ok_or_else(|| "select the device to sync with".to_string())?;
+        peer.args()?;
+        let peer_addresses = resolve_peer_addresses(&app, &peer).await?;
+        if peer_addresses.is_empty() {
+            return Err(format!("no address found for {}", peer.label()));
+        }
+        ClipboardSync {
+            enabled: true,
+            peer: Some(peer),
+            peer_addresses,
+        }
+    } else {
+        ClipboardSync {
+            enabled: false,
+            ..load_settings(&app)?.clipboard_sync
+        }
+    };
+    update_settings(&app, |settings| {
+        settings.clipboard_sync = sync.clone();
+        Ok(())
+    })?;
+    {
+        let state = app.state::<AppState>();
+        let mut state = lock_state(state.inner())?;
+        state.last_digest = None;
+        state.last_error = None;
+    }
+
+    let state = app.state::<AppState>();
+    let running = inspect_listen_state(&state)?.running;
+    if running {
+        let from = if sync.enabled {
+            sync.peer_addresses.clone()
+        } else {
+            Vec::new()
+        };
+        cli_control::request(state.inner(), "update", json!({ "clipboardFrom": from }))?;
+    }
+    record_audit(
+        &app,
+        AuditAction::ClipboardSyncChanged,
+        json!({
+            "enabled": sync.enabled,
+            "peer": sync.peer.as_ref().map(RemoteDevice::label),
+            "addresses": sync.peer_addresses,
+        }),
+    );
+    emit_status(&app);
+    Ok(status(&app, running))
+}
@@ -0,0 +1,328 @@
+use crate::settings::update_settings;
+use crate::storage::{data_file, now_ms, read_json, secure_remove, write_json};
+use crate::{emit_system_log, AppState};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+const RECEIVED_INDEX_FILE: &str = "received-index.json";
+/// Mirrors the CLI's `name(N).ext` collision suffixes when locating the file
+/// a finished receive was saved as.
+const MAX_DUPLICATE_SUFFIX: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DuplicatePolicy {
+    /// Prompt even when the network profile would auto-accept.
+    #[default]
+    Ask,
+    Skip,
+    KeepBoth,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReceivedFile {
+    path: String,
+    size: u64,
+    received_at_ms: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ReceivedIndex {
+    /// Received files keyed by SHA-256, as announced by the sender and
+    /// verified by the CLI before the file is kept.
+    files: HashMap<String, ReceivedFile>,
+    skipped_duplicates: u64,
+    bytes_saved: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DedupStats {
+    indexed_files: usize,
+    skipped_duplicates: u64,
+    bytes_saved: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IndexVerifyReport {
+    checked: usize,
+    /// Entries dropped because the file is gone or its content changed.
+    removed: usize,
+    hashed_bytes: u64,
+}
+
+/// Hash and size of an approved receive, keyed by relative path until the
+/// CLI reports it finished.
+#[derive(Debug, Clone)]
+pub(crate) struct IncomingFile {
+    sha256: String,
+    size: u64,
+}
+
+fn with_index<T>(
+    app: &AppHandle,
+    change: impl FnOnce(&mut ReceivedIndex) -> T,
+) -> Result<T, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .received_index_lock
+        .lock()
+        .map_err(|_| "failed to lock received index".to_string())?;
+    let path = data_file(app, RECEIVED_INDEX_FILE)?;
+    let mut index: ReceivedIndex = read_json(&path)?;
+    let result = change(&mut index);
+    write_json(&path, &index)?;
+    Ok(result)
+}
+
+/// Returns the path of an already-received file with the same content, if it
+/// still exists with the expected size.
+pub(crate) fn find_duplicate(app: &AppHandle, sha256: &str, size: u64) -> Option<String> {
+    let state = app.state::<AppState>();
+    let _guard = state.received_index_lock.lock().ok()?;
+    let index: ReceivedIndex = read_json(&data_file(app, RECEIVED_INDEX_FILE).ok()?).ok()?;
+    let existing = index.files.get(&sha256.to_ascii_lowercase())?;
+    let still_there = fs::metadata(&existing.path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.len() == size);
+    still_there.then(|| existing.path.clone())
+}
+
+pub(crate) fn receive_approved(state: &AppState, relative_path: &str, sha256: &str, size: u64) {
+    if let Ok(mut incoming) = state.incoming_files.lock() {
+        incoming.insert(
+            relative_path.to_string(),
+            IncomingFile {
+                sha256: sha256.to_ascii_lowercase(),
+                size,
+            },
+        );
+    }
+}
+
+fn suffixed_path(base: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return base.to_path_buf();
+    }
+    let stem = base
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match base.extension() {
+        Some(extension) => format!("{stem}({index}).{}", extension.to_string_lossy()),
+        None => format!("{stem}({index})"),
+    };
+    base.with_file_name(name)
+}
+
+/// Picks the newest file among `relative_path` and its collision variants
+/// that has the expected size: that is where the CLI just saved it.
+fn locate_saved_file(output_dir: &Path, relative_path: &str, size: u64) -> Option<PathBuf> {
+    let base = output_dir.join(relative_path);
+    let mut best: Option<(PathBuf, std::time::SystemTime)> = None;
+    for index in 0..MAX_DUPLICATE_SUFFIX {
+        let candidate = suffixed_path(&base, index);
+        let Ok(metadata) = fs::metadata(&candidate) else {
+            if index > 0 {
+                break;
+            }
+            continue;
+        };
+        if !metadata.is_file() || metadata.len() != size {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+        if best.as_ref().is_none_or(|(_, newest)| modified > *newest) {
+            best = Some((candidate, modified));
+        }
+    }
+    best.map(|(path, _)| path)
+}
+
+/// Adds a finished receive to the index so later copies are recognized.
+pub(crate) fn record_received(app: &AppHandle, relative_path: &str) {
+    let state = app.state::<AppState>();
+    let Some(incoming) = state
+        .incoming_files
+        .lock()
+        .ok()
+        .and_then(|mut incoming| incoming.remove(relative_path))
+    else {
+        return;
+    };
+    let Some(output_dir) = state
+        .listen_output_dir
+        .lock()
+        .ok()
+        .and_then(|guard| guard.clone())
+    else {
+        return;
+    };
+    let Some(saved) = locate_saved_file(&output_dir, relative_path, incoming.size) else {
+        return;
+    };
+    let result = with_index(app, |index| {
+        index.files.insert(
+            incoming.sha256,
+            ReceivedFile {
+                path: saved.to_string_lossy().to_string(),
+                size: incoming.size,
+                received_at_ms: now_ms(),
+            },
+        );
+    });
+    if let Err(err) = result {
+        emit_system_log(app, format!("failed to update received index: {err}"));
+    }
+}
+
+/// Every indexed file with the time it was received.
+pub(crate) fn received_paths(app: &AppHandle) -> Result<Vec<(PathBuf, u64)>, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .received_index_lock
+        .lock()
+        .map_err(|_| "failed to lock received index".to_string())?;
+    let index: ReceivedIndex = read_json(&data_file(app, RECEIVED_INDEX_FILE)?)?;
+    Ok(index
+        .files
+        .into_values()
+        .map(|file| (PathBuf::from(file.path), file.received_at_ms))
+        .collect())
+}
+
+/// Overwrites and deletes the received index; later copies of files
+/// received before are no longer recognized.
+pub(crate) fn wipe_received_index(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .received_index_lock
+        .lock()
+        .map_err(|_| "failed to lock received index".to_string())?;
+    secure_remove(&data_file(app, RECEIVED_INDEX_FILE)?)
+}
+
+/// Counts a skipped duplicate towards the savings and logs it to the
+/// activity feed.
+pub(crate) fn note_skipped(app: &AppHandle, relative_path: &str, size: u64, existing: &str) {
+    let _ = with_index(app, |index| {
+        index.skipped_duplicates += 1;
+        index.bytes_saved += size;
+    });
+    emit_system_log(
+        app,
+        format!("skipped duplicate {relative_path} (already received as {existing}, saved {size} bytes)"),
+    );
+}
+
+/// Follows a received file that a post-receive stage moved elsewhere.
+pub(crate) fn note_moved(app: &AppHandle, from: &Path, to: &Path) {
+    let from = from.to_string_lossy();
+    let _ = with_index(app, |index| {
+        for file in index.files.values_mut() {
+            if file.path == from {
+                file.path = to.to_string_lossy().to_string();
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub(crate) fn get_dedup_stats(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<DedupStats, String> {
+    let _guard = state
+        .received_index_lock
+        .lock()
+        .map_err(|_| "failed to lock received index".to_string())?;
+    let index: ReceivedIndex = read_json(&data_file(&app, RECEIVED_INDEX_FILE)?)?;
+    Ok(DedupStats {
+        indexed_files: index.files.len(),
+        skipped_duplicates: index.skipped_duplicates,
+        bytes_saved: index.bytes_saved,
+    })
+}
+
+fn sha256_file(path: &Path) -> io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)?;
+    let digest = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok((digest, size))
+}
+
+/// The index is keyed by the SHA-256 the CLI verified while writing the
+/// file, so checking an entry needs no hash of its own from receive time.
+fn verify_entry(sha256: &str, file: &ReceivedFile, report: &mut IndexVerifyReport) -> bool {
+    let size_matches = fs::metadata(&file.path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.len() == file.size);
+    if !size_matches {
+        return false;
+    }
+    match sha256_file(Path::new(&file.path)) {
+        Ok((digest, size)) => {
+            report.hashed_bytes += size;
+            digest == sha256
+        }
+        Err(_) => false,
+    }
+}
+
+/// Re-hashes every indexed file and drops entries whose file disappeared or
+/// changed since it was received, so duplicates are never skipped in favour
+/// of a copy that no longer matches.
+#[tauri::command]
+pub(crate) async fn verify_received_index(app: AppHandle) -> Result<IndexVerifyReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let files = with_index(&app, |index| index.files.clone())?;
+        let mut report = IndexVerifyReport::default();
+        let mut stale = Vec::new();
+        for (sha256, file) in &files {
+            report.checked += 1;
+            if !verify_entry(sha256, file, &mut report) {
+                stale.push((sha256.clone(), file.path.clone()));
+            }
+        }
+        report.removed = with_index(&app, |index| {
+            let before = index.files.len();
+            for (sha256, path) in &stale {
+                // Skip entries that were replaced while hashing.
+                if index
+                    .files
+                    .get(sha256)
+                    .is_some_and(|file| &file.path == path)
+                {
+                    index.files.remove(sha256);
+                }
+            }
+            before - index.files.len()
+        })?;
+        Ok(report)
+    })
+    .await
+    .map_err(|err| format!("failed to join verification task: {err}"))?
+}
+
+#[tauri::command]
+pub(crate) fn set_duplicate_policy(
+    app: AppHandle,
+    policy: DuplicatePolicy,
+) -> Result<DuplicatePolicy, String> {
+    update_settings(&app, |settings| {
+        settings.duplicate_policy = policy;
+        Ok(())
+    })
+    .map(|settings| settings.duplicate_policy)
+}
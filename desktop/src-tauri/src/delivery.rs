@@ -0,0 +1,256 @@
+use crate::kde_connect::{self, KdeConnectTarget};
+use crate::s3::{self, S3Target};
+use crate::sftp::{self, SftpTarget};
+use crate::smb::{self, SmbTarget};
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::syncthing::{self, SyncthingTarget};
+use crate::{AppState, CommandResult, SendRequest};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const DELIVERY_TARGETS_FILE: &str = "delivery-targets.json";
+
+/// How a delivery target is reached.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum DeliveryBackend {
+    Sftp(SftpTarget),
+    Smb(SmbTarget),
+    S3(S3Target),
+    Syncthing(SyncthingTarget),
+    KdeConnect(KdeConnectTarget),
+}
+
+/// A destination that does not run this app, such as a NAS reached over
+/// SSH, a file server's share or an object store bucket. Sends naming it
+/// as their `device` are delivered by the app itself and go through the
+/// same queue, progress and history as other sends.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeliveryTarget {
+    name: String,
+    #[serde(flatten)]
+    backend: DeliveryBackend,
+    #[serde(default)]
+    updated_at_ms: u64,
+}
+
+impl DeliveryTarget {
+    /// Shown as the send's target in activity and history.
+    pub(crate) fn label(&self) -> String {
+        match &self.backend {
+            DeliveryBackend::Sftp(target) => target.label(),
+            DeliveryBackend::Smb(target) => target.label(),
+            DeliveryBackend::S3(target) => target.label(),
+            DeliveryBackend::Syncthing(target) => target.label(),
+            DeliveryBackend::KdeConnect(target) => target.label(),
+        }
+    }
+
+    /// Object stores, and Syncthing, whose devices may connect through
+    /// public relays.
+    pub(crate) fn is_internet_service(&self) -> bool {
+        matches!(
+            self.backend,
+            DeliveryBackend::S3(_) | DeliveryBackend::Syncthing(_)
+        )
+    }
+}
+
+/// A file to deliver and its path under the destination folder, with `/`
+/// separators.
+pub(crate) struct SourceFile {
+    pub(crate) path: PathBuf,
+    pub(crate) relative_path: String,
+    pub(crate) size: u64,
+}
+
+/// The files of a send: the file itself, or every file under a folder,
+/// kept under the folder's name like the CLI does.
+pub(crate) fn source_files(path: &Path) -> Result<Vec<SourceFile>, String> {
+    let read_error = |err: std::io::Error| format!("failed to read {}: {err}", path.display());
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "path has no file name".to_string())?;
+    let metadata = fs::metadata(path).map_err(read_error)?;
+    if metadata.is_file() {
+        return Ok(vec![SourceFile {
+            path: path.to_path_buf(),
+            relative_path: name,
+            size: metadata.len(),
+        }]);
+    }
+    let mut files = Vec::new();
+    let mut pending = vec![(path.to_path_buf(), name)];
+    while let Some((dir, prefix)) = pending.pop() {
+        for entry in fs::read_dir(&dir).map_err(read_error)? {
+            let entry = entry.map_err(read_error)?;
+            let file_type = entry.file_type().map_err(read_error)?;
+            let relative_path = format!("{prefix}/{}", entry.file_name().to_string_lossy());
+            if file_type.is_dir() {
+                pending.push((entry.path(), relative_path));
+            } else if file_type.is_file() {
+                files.push(SourceFile {
+                    size: entry.metadata().map_err(read_error)?.len(),
+                    path: entry.path(),
+                    relative_path,
+                });
+            }
+        }
+    }
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    Ok(files)
+}
+
+fn load_targets(app: &AppHandle) -> Result<Vec<DeliveryTarget>, String> {
+    read_json(&data_file(app, DELIVERY_TARGETS_FILE)?)
+}
+
+fn save_targets(app: &AppHandle, targets: &[DeliveryTarget]) -> Result<(), String> {
+    write_json(&data_file(app, DELIVERY_TARGETS_FILE)?, targets)
+}
+
+fn same_target_name(left: &str, right: &str) -> bool {
+    left.trim().eq_ignore_ascii_case(right.trim())
+}
+
+/// The delivery target a send names as its device, if any. Syncthing
+/// folders and paired KDE Connect devices need no saved target.
+pub(crate) fn find_target(app: &AppHandle, request: &SendRequest) -> Option<DeliveryTarget> {
+    let device = request
+        .device
+        .as_deref()
+        .filter(|device| !device.trim().is_empty())?;
+    if request
+        .host
+        .as_deref()
+        .is_some_and(|host| !host.trim().is_empty())
+    {
+        return None;
+    }
+    if let Some(folder_id) = device.trim().strip_prefix(syncthing::DEVICE_PREFIX) {
+        return Some(DeliveryTarget {
+            name: device.trim().to_string(),
+            backend: DeliveryBackend::Syncthing(SyncthingTarget {
+                folder_id: folder_id.to_string(),
+            }),
+            updated_at_ms: 0,
+        });
+    }
+    if let Some(device_id) = device.trim().strip_prefix(kde_connect::DEVICE_PREFIX) {
+        return Some(DeliveryTarget {
+            name: device.trim().to_string(),
+            backend: DeliveryBackend::KdeConnect(KdeConnectTarget {
+                device_id: device_id.to_string(),
+            }),
+            updated_at_ms: 0,
+        });
+    }
+    let state = app.state::<AppState>();
+    let _guard = state.delivery_targets_lock.lock().ok()?;
+    load_targets(app)
+        .ok()?
+        .into_iter()
+        .find(|target| same_target_name(&target.name, device))
+}
+
+/// Delivers `request` to `target`; blocking. Output mimics the CLI so
+/// progress and error rendering treat it like any other send.
+pub(crate) fn send(
+    app: &AppHandle,
+    request: &SendRequest,
+    target: &DeliveryTarget,
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let files = source_files(Path::new(&request.path))?;
+    match &target.backend {
+        DeliveryBackend::Sftp(sftp_target) => sftp::send(app, sftp_target, &files, send_id),
+        DeliveryBackend::Smb(smb_target) => smb::send(app, smb_target, &files, send_id),
+        DeliveryBackend::S3(s3_target) => s3::send(app, s3_target, &target.name, &files, send_id),
+        DeliveryBackend::Syncthing(syncthing_target) => {
+            syncthing::send(app, syncthing_target, &files, send_id)
+        }
+        DeliveryBackend::KdeConnect(kde_connect_target) => {
+            kde_connect::send(app, kde_connect_target, &files, send_id)
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) fn list_delivery_targets(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<DeliveryTarget>, String> {
+    let _guard = state
+        .delivery_targets_lock
+        .lock()
+        .map_err(|_| "failed to lock delivery targets".to_string())?;
+    load_targets(&app)
+}
+
+/// Creates or replaces the target with the same name.
+#[tauri::command]
+pub(crate) fn save_delivery_target(
+    app: AppHandle,
+    state: State<AppState>,
+    target: DeliveryTarget,
+) -> Result<DeliveryTarget, String> {
+    let name = target.name.trim().to_string();
+    if name.is_empty() {
+        return Err("target name is required".to_string());
+    }
+    let backend = match target.backend {
+        DeliveryBackend::Sftp(sftp_target) => DeliveryBackend::Sftp(sftp_target.validated()?),
+        DeliveryBackend::Smb(smb_target) => DeliveryBackend::Smb(smb_target.validated()?),
+        DeliveryBackend::S3(s3_target) => DeliveryBackend::S3(s3_target.validated(&name)?),
+        DeliveryBackend::Syncthing(syncthing_target) => {
+            DeliveryBackend::Syncthing(syncthing_target.validated()?)
+        }
+        DeliveryBackend::KdeConnect(kde_connect_target) => {
+            DeliveryBackend::KdeConnect(kde_connect_target.validated()?)
+        }
+    };
+
+    let _guard = state
+        .delivery_targets_lock
+        .lock()
+        .map_err(|_| "failed to lock delivery targets".to_string())?;
+    let mut targets = load_targets(&app)?;
+    let target = DeliveryTarget {
+        name,
+        backend,
+        updated_at_ms: now_ms(),
+    };
+    match targets
+        .iter_mut()
+        .find(|item| same_target_name(&item.name, &target.name))
+    {
+        Some(existing) => *existing = target.clone(),
+        None => targets.push(target.clone()),
+    }
+    save_targets(&app, &targets)?;
+    Ok(target)
+}
+
+#[tauri::command]
+pub(crate) fn delete_delivery_target(
+    app: AppHandle,
+    state: State<AppState>,
+    name: String,
+) -> Result<bool, String> {
+    let _guard = state
+        .delivery_targets_lock
+        .lock()
+        .map_err(|_| "failed to lock delivery targets".to_string())?;
+    let mut targets = load_targets(&app)?;
+    let before = targets.len();
+    targets.retain(|target| !same_target_name(&target.name, &name));
+    if targets.len() == before {
+        return Ok(false);
+    }
+    save_targets(&app, &targets)?;
+    Ok(true)
+}
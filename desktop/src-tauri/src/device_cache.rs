@@ -0,0 +1,159 @@
+//! The last discovery results, kept in memory and on disk so the device
+//! list can be shown at once while a fresh discovery runs.
+
+use crate::events::{emit_app_event, AppEvent};
+use crate::parser::DiscoverDevice;
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{
+    discover_devices, emit_system_log, interfaces, kill_switch, managed_config, settings, AppState,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const DEVICE_CACHE_FILE: &str = "device-cache.json";
+const REFRESH_TIMEOUT_MS: u64 = 3000;
+
+#[derive(Debug, Default)]
+pub(crate) struct DeviceCache {
+    /// Whether the copy on disk was read yet.
+    loaded: bool,
+    devices: Vec<DiscoverDevice>,
+    updated_at_ms: Option<u64>,
+    refreshing: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct StoredDevices {
+    devices: Vec<DiscoverDevice>,
+    updated_at_ms: Option<u64>,
+}
+
+/// Returned by `get_cached_devices` and sent as the `devicesRefreshed` app
+/// event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CachedDevicesPayload {
+    devices: Vec<DiscoverDevice>,
+    /// When the devices were discovered; `None` before the first discovery.
+    updated_at_ms: Option<u64>,
+    age_ms: Option<u64>,
+    /// A discovery is running and `devicesRefreshed` follows when it ends.
+    refreshing: bool,
+}
+
+impl DeviceCache {
+    fn payload(&self) -> CachedDevicesPayload {
+        CachedDevicesPayload {
+            devices: self.devices.clone(),
+            updated_at_ms: self.updated_at_ms,
+            age_ms: self
+                .updated_at_ms
+                .map(|updated| now_ms().saturating_sub(updated)),
+            refreshing: self.refreshing,
+        }
+    }
+}
+
+/// Reads the copy on disk once. Routes are not stored; they are worked out
+/// again against the interfaces this machine has now.
+fn ensure_loaded(app: &AppHandle, cache: &mut DeviceCache) {
+    if cache.loaded {
+        return;
+    }
+    cache.loaded = true;
+    let Ok(stored) =
+        data_file(app, DEVICE_CACHE_FILE).and_then(|path| read_json::<StoredDevices>(&path))
+    else {
+        return;
+    };
+    let mut devices = stored.devices;
+    let policy = settings::load_settings(app)
+        .map(|settings| settings.interfaces)
+        .unwrap_or_default();
+    interfaces::apply_policy(&mut devices, &policy);
+    if let Ok(managed) = managed_config::load() {
+        devices.retain(|device| managed.allows_peer(&device.host));
+    }
+    cache.devices = devices;
+    cache.updated_at_ms = stored.updated_at_ms;
+}
+
+/// Keeps the result of a finished discovery and announces it.
+pub(crate) fn store(app: &AppHandle, devices: &[DiscoverDevice]) {
+    let state = app.state::<AppState>();
+    let payload = {
+        let Ok(mut cache) = state.device_cache.lock() else {
+            return;
+        };
+        cache.loaded = true;
+        cache.devices = devices.to_vec();
+        cache.updated_at_ms = Some(now_ms());
+        cache.refreshing = false;
+        cache.payload()
+    };
+    let stored = StoredDevices {
+        devices: devices.to_vec(),
+        updated_at_ms: payload.updated_at_ms,
+    };
+    if let Err(err) = data_file(app, DEVICE_CACHE_FILE).and_then(|path| write_json(&path, &stored))
+    {
+        emit_system_log(app, format!("failed to save discovered devices: {err}"));
+    }
+    emit_app_event(app, AppEvent::DevicesRefreshed(payload));
+}
+
+/// Whether the last discovery found a stealth listener at `host` and
+/// `port`, one that only opens its port to senders that knock.
+pub(crate) fn announces_knock(app: &AppHandle, host: &str, port: u16) -> bool {
+    let state = app.state::<AppState>();
+    let Ok(mut cache) = state.device_cache.lock() else {
+        return false;
+    };
+    ensure_loaded(app, &mut cache);
+    cache.devices.iter().any(|device| {
+        device.port == port
+            && (device.host == host || device.addresses.iter().any(|address| address == host))
+            && device
+                .capabilities
+                .iter()
+                .any(|capability| capability == "knock")
+    })
+}
+
+fn spawn_refresh(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = discover_devices(app.clone(), REFRESH_TIMEOUT_MS).await {
+            emit_system_log(&app, format!("failed to refresh devices: {err}"));
+            if let Ok(mut cache) = app.state::<AppState>().device_cache.lock() {
+                cache.refreshing = false;
+            }
+        }
+    });
+}
+
+/// The last discovered devices, returned at once. A discovery starts in the
+/// background unless one is running or the network kill switch is on.
+#[tauri::command]
+pub(crate) fn get_cached_devices(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<CachedDevicesPayload, String> {
+    let refresh = kill_switch::ensure_network_allowed(state.inner()).is_ok();
+    let (payload, start) = {
+        let mut cache = state
+            .device_cache
+            .lock()
+            .map_err(|_| "failed to lock device cache".to_string())?;
+        ensure_loaded(&app, &mut cache);
+        let start = refresh && !cache.refreshing;
+        if start {
+            cache.refreshing = true;
+        }
+        (cache.payload(), start)
+    };
+    if start {
+        spawn_refresh(app);
+    }
+    Ok(payload)
+}
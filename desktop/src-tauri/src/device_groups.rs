@@ -0,0 +1,178 @@
+use crate::send_multi::SendTarget;
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{discover_devices, AppState, DiscoverDevice};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const DEVICE_GROUPS_FILE: &str = "device-groups.json";
+/// Long enough for most devices to answer, short enough to start the send
+/// without a noticeable wait.
+const GROUP_DISCOVERY_TIMEOUT_MS: u64 = 2000;
+
+/// A named set of receivers, e.g. "All TVs". Members naming a `device` are
+/// looked up in discovery at send time; members with a `host` are used as
+/// entered.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceGroup {
+    name: String,
+    members: Vec<SendTarget>,
+    #[serde(default)]
+    updated_at_ms: u64,
+}
+
+/// A group expanded against the network.
+pub(crate) struct ResolvedGroup {
+    pub(crate) targets: Vec<SendTarget>,
+    /// Members that could not be reached, with the reason.
+    pub(crate) missing: Vec<(String, String)>,
+}
+
+fn load_groups(app: &AppHandle) -> Result<Vec<DeviceGroup>, String> {
+    read_json(&data_file(app, DEVICE_GROUPS_FILE)?)
+}
+
+fn save_groups(app: &AppHandle, groups: &[DeviceGroup]) -> Result<(), String> {
+    write_json(&data_file(app, DEVICE_GROUPS_FILE)?, groups)
+}
+
+fn same_group_name(left: &str, right: &str) -> bool {
+    left.trim().eq_ignore_ascii_case(right.trim())
+}
+
+fn find_group(app: &AppHandle, name: &str) -> Result<DeviceGroup, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .device_groups_lock
+        .lock()
+        .map_err(|_| "failed to lock device groups".to_string())?;
+    load_groups(app)?
+        .into_iter()
+        .find(|group| same_group_name(&group.name, name))
+        .ok_or_else(|| format!("device group `{name}` not found"))
+}
+
+/// Prefers an exact name match so "Living Room TV" does not also pick
+/// "Living Room TV 2".
+pub(crate) fn match_device<'a>(
+    devices: &'a [DiscoverDevice],
+    name: &str,
+) -> Option<&'a DiscoverDevice> {
+    let needle = name.trim().to_lowercase();
+    devices
+        .iter()
+        .find(|device| device.name.to_lowercase() == needle)
+        .or_else(|| {
+            devices
+                .iter()
+                .find(|device| device.name.to_lowercase().contains(&needle))
+        })
+}
+
+pub(crate) async fn resolve_group(app: &AppHandle, name: &str) -> Result<ResolvedGroup, String> {
+    let group = find_group(app, name)?;
+    let devices = if group.members.iter().any(|member| member.host().is_none()) {
+        discover_devices(app.clone(), GROUP_DISCOVERY_TIMEOUT_MS).await?
+    } else {
+        Vec::new()
+    };
+
+    let mut resolved = ResolvedGroup {
+        targets: Vec::with_capacity(group.members.len()),
+        missing: Vec::new(),
+    };
+    for member in group.members {
+        if member.host().is_some() {
+            resolved.targets.push(member);
+            continue;
+        }
+        let Some(device_name) = member.device().map(str::to_string) else {
+            continue;
+        };
+        match match_device(&devices, &device_name) {
+            Some(device) => resolved
+                .targets
+                .push(member.at(device.host.clone(), device.port)),
+            None => resolved.missing.push((
+                device_name,
+                format!("not found on the network (group `{}`)", group.name),
+            )),
+        }
+    }
+    Ok(resolved)
+}
+
+#[tauri::command]
+pub(crate) fn list_device_groups(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<DeviceGroup>, String> {
+    let _guard = state
+        .device_groups_lock
+        .lock()
+        .map_err(|_| "failed to lock device groups".to_string())?;
+    load_groups(&app)
+}
+
+/// Creates or replaces the group with the same name. Pair codes are not
+/// kept: they rotate, and a stale one would fail every send to the group.
+#[tauri::command]
+pub(crate) fn save_device_group(
+    app: AppHandle,
+    state: State<AppState>,
+    group: DeviceGroup,
+) -> Result<DeviceGroup, String> {
+    let name = group.name.trim().to_string();
+    if name.is_empty() {
+        return Err("group name is required".to_string());
+    }
+    let members: Vec<SendTarget> = group
+        .members
+        .into_iter()
+        .filter(|member| member.host().is_some() || member.device().is_some())
+        .map(SendTarget::without_pair_code)
+        .collect();
+    if members.is_empty() {
+        return Err("a group needs at least one device or host".to_string());
+    }
+
+    let _guard = state
+        .device_groups_lock
+        .lock()
+        .map_err(|_| "failed to lock device groups".to_string())?;
+    let mut groups = load_groups(&app)?;
+    let group = DeviceGroup {
+        name,
+        members,
+        updated_at_ms: now_ms(),
+    };
+    match groups
+        .iter_mut()
+        .find(|item| same_group_name(&item.name, &group.name))
+    {
+        Some(existing) => *existing = group.clone(),
+        None => groups.push(group.clone()),
+    }
+    save_groups(&app, &groups)?;
+    Ok(group)
+}
+
+#[tauri::command]
+pub(crate) fn delete_device_group(
+    app: AppHandle,
+    state: State<AppState>,
+    name: String,
+) -> Result<bool, String> {
+    let _guard = state
+        .device_groups_lock
+        .lock()
+        .map_err(|_| "failed to lock device groups".to_string())?;
+    let mut groups = load_groups(&app)?;
+    let before = groups.len();
+    groups.retain(|group| !same_group_name(&group.name, &name));
+    if groups.len() == before {
+        return Ok(false);
+    }
+    save_groups(&app, &groups)?;
+    Ok(true)
+}
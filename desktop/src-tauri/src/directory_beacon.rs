@@ -0,0 +1,542 @@
+//! Discovery across subnets through a small directory service the user
+//! hosts. While the listener runs it registers itself there, renewing the
+//! registration on every heartbeat, and discovery asks the service for the
+//! other registered devices. The service is reached over HTTP(S) with a
+//! bearer token kept in the keychain:
+//!
+//! - `PUT {url}/devices/{id}` registers or renews this device for `ttlSecs`;
+//! - `DELETE {url}/devices/{id}` removes it when the listener stops;
+//! - `GET {url}/devices` lists the registered devices. Each has the fields
+//!   registered, plus `host` when the service records the address the
+//!   registration came from.
+//!
+//! A listener that dies without deregistering drops out once its TTL ends.
+
+use crate::dry_run::sha256_hex;
+use crate::parser::{clean_text, DeviceSource, DiscoverDevice};
+use crate::secrets::{get_secret, store_secret};
+use crate::settings::{load_settings, update_settings};
+use crate::storage::now_ms;
+use crate::{emit_system_log, identity, listen_session, AppState};
+use if_addrs::get_if_addrs;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, Url};
+
+const TOKEN_SECRET: &str = "directory-beacon-token";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Registrations outlive this many missed heartbeats.
+const TTL_HEARTBEATS: u32 = 3;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct DirectoryBeaconSettings {
+    /// Base URL of the directory service; empty turns the beacon off.
+    pub(crate) url: String,
+    pub(crate) heartbeat_secs: u32,
+}
+
+impl Default for DirectoryBeaconSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            heartbeat_secs: 60,
+        }
+    }
+}
+
+/// What this device registers, and what the service lists for others.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BeaconDevice {
+    id: String,
+    name: String,
+    /// Set by the service, never registered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    port: u16,
+    addresses: Vec<String>,
+    fingerprint: Option<String>,
+    tls: bool,
+    pair_code_required: bool,
+    version: Option<String>,
+    ttl_secs: u32,
+}
+
+/// The registration of the running listener.
+#[derive(Debug, Clone)]
+struct Registration {
+    /// Bumped by every `start` and `stop`, so a heartbeat thread left from
+    /// an earlier listener stops on its next beat.
+    generation: u64,
+    device_id: String,
+    name: String,
+    port: u16,
+    fingerprint: Option<String>,
+    registered: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BeaconState {
+    generation: u64,
+    registration: Option<Registration>,
+    last_registered_at_ms: Option<u64>,
+    last_error: Option<String>,
+}
+
+/// Returned by `get_directory_beacon_status`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BeaconStatus {
+    url: String,
+    has_token: bool,
+    /// Whether the service currently lists this device.
+    registered: bool,
+    last_registered_at_ms: Option<u64>,
+    last_error: Option<String>,
+}
+
+fn settings(app: &AppHandle) -> DirectoryBeaconSettings {
+    load_settings(app)
+        .map(|settings| settings.directory_beacon)
+        .unwrap_or_default()
+}
+
+fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(REQUEST_TIMEOUT))
+        .build()
+        .new_agent()
+}
+
+fn bearer() -> Result<String, String> {
+    get_secret(TOKEN_SECRET)?
+        .map(|token| format!("Bearer {token}"))
+        .ok_or_else(|| "no directory beacon token is set".to_string())
+}
+
+fn device_url(url: &str, device_id: &str) -> String {
+    format!("{}/devices/{device_id}", url.trim_end_matches('/'))
+}
+
+/// SHA-256 of the listener's certificate, in the CLI's format.
+fn certificate_fingerprint(cert_path: &str) -> Result<String, String> {
+    let der = CertificateDer::from_pem_file(cert_path)
+        .map_err(|err| format!("failed to parse {cert_path}: {err}"))?;
+    Ok(sha256_hex(der.as_ref()))
+}
+
+/// The addresses peers may reach the listener on: the bound one, or every
+/// interface but loopback.
+fn listen_addresses(app: &AppHandle) -> Vec<String> {
+    let state = app.state::<AppState>();
+    let bind = listen_session::bind_address(state.inner());
+    if let Some(bind) = bind.filter(|bind| !bind.is_unspecified()) {
+        return vec![bind.to_string()];
+    }
+    get_if_addrs()
+        .map(|interfaces| {
+            interfaces
+                .iter()
+                .map(|interface| interface.ip())
+                .filter(|ip| !ip.is_loopback())
+                .map(|ip: IpAddr| ip.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn register(
+    app: &AppHandle,
+    url: &str,
+    registration: &Registration,
+    ttl_secs: u32,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let device = BeaconDevice {
+        id: registration.device_id.clone(),
+        name: registration.name.clone(),
+        host: None,
+        port: registration.port,
+        addresses: listen_addresses(app),
+        fingerprint: registration.fingerprint.clone(),
+        tls: listen_session::uses_tls(state.inner()),
+        pair_code_required: listen_session::requires_pair_code(state.inner()),
+        version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        ttl_secs,
+    };
+    agent()
+        .put(device_url(url, &registration.device_id))
+        .header("Authorization", bearer()?)
+        .send_json(&device)
+        .map_err(|err| format!("failed to register with the directory: {err}"))?;
+    Ok(())
+}
+
+fn deregister(url: &str, device_id: &str) -> Result<(), String> {
+    agent()
+        .delete(device_url(url, device_id))
+        .header("Authorization", bearer()?)
+        .call()
+        .map_err(|err| format!("failed to leave the directory: {err}"))?;
+    Ok(())
+}
+
+/// The registration for `generation`, or `None` once the listener it
+/// belongs to stopped.
+fn current(app: &AppHandle, generation: u64) -> Option<Registration> {
+    let state = app.state::<AppState>();
+    let beacon = state.directory_beacon.lock().ok()?;
+    beacon
+        .registration
+        .clone()
+        .filter(|registration| registration.generation == generation)
+}
+
+fn note_result(app: &AppHandle, generation: u64, registered: bool, result: Result<(), String>) {
+    let state = app.state::<AppState>();
+    let Ok(mut beacon) = state.directory_beacon.lock() else {
+        return;
+    };
+    let Some(registration) = beacon
+        .registration
+        .as_mut()
+        .filter(|registration| registration.generation == generation)
+    else {
+        return;
+    };
+    match result {
+        Ok(()) => {
+            registration.registered = registered;
+            if registered {
+                beacon.last_registered_at_ms = Some(now_ms());
+            }
+            beacon.last_error = None;
+        }
+        Err(err) => {
+            // Logged once per failure streak, not on every beat.
+            if beacon.last_error.as_deref() != Some(err.as_str()) {
+                emit_system_log(app, err.clone());
+            }
+            beacon.last_error = Some(err);
+        }
+    }
+}
+
+/// One beat: registers, or leaves the directory while the listener is
+/// hidden from discovery.
+fn beat(app: &AppHandle, registration: &Registration) {
+    let settings = settings(app);
+    let url = settings.url.trim();
+    if url.is_empty() {
+        return;
+    }
+    let hidden = load_settings(app).is_ok_and(|settings| settings.hidden_from_discovery);
+    let (registered, result) = if hidden {
+        let result = if registration.registered {
+            deregister(url, &registration.device_id)
+        } else {
+            Ok(())
+        };
+        (false, result)
+    } else {
+        let ttl_secs = settings.heartbeat_secs.max(1) * TTL_HEARTBEATS;
+        (true, register(app, url, registration, ttl_secs))
+    };
+    note_result(app, registration.generation, registered, result);
+}
+
+/// Registers the listener that just started on `port` and keeps the
+/// registration alive until `stop`.
+pub(crate) fn start(app: &AppHandle, port: u16, name: Option<String>, tls_cert_path: Option<&str>) {
+    if settings(app).url.trim().is_empty() {
+        return;
+    }
+    let identity = match identity::load_or_create_identity(app) {
+        Ok(identity) => identity,
+        Err(err) => {
+            emit_system_log(app, format!("failed to register with the directory: {err}"));
+            return;
+        }
+    };
+    let fingerprint = match tls_cert_path.map(certificate_fingerprint).transpose() {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            emit_system_log(app, format!("failed to register with the directory: {err}"));
+            None
+        }
+    };
+    let generation = {
+        let state = app.state::<AppState>();
+        let Ok(mut beacon) = state.directory_beacon.lock() else {
+            return;
+        };
+        beacon.generation += 1;
+        beacon.registration = Some(Registration {
+            generation: beacon.generation,
+            device_id: identity.device_id.clone(),
+            name: name.unwrap_or_else(|| identity.effective_name()),
+            port,
+            fingerprint,
+            registered: false,
+        });
+        beacon.last_error = None;
+        beacon.generation
+    };
+    let app = app.clone();
+    thread::spawn(move || {
+        while let Some(registration) = current(&app, generation) {
+            let state = app.state::<AppState>();
+            if state.shutdown_cleanup_started.load(Ordering::SeqCst)
+                || state.network_halted.load(Ordering::SeqCst)
+            {
+                return;
+            }
+            beat(&app, &registration);
+            let heartbeat = settings(&app).heartbeat_secs.max(1);
+            thread::sleep(Duration::from_secs(u64::from(heartbeat)));
+        }
+    });
+}
+
+/// Ends the registration of the listener that stopped. The directory is
+/// told in the background; if that fails, or the kill-switch is engaged,
+/// the entry expires on its own.
+pub(crate) fn stop(app: &AppHandle) {
+    end(app, None);
+}
+
+/// The generation of the current registration, if there is one.
+pub(crate) fn registered_generation(app: &AppHandle) -> Option<u64> {
+    let state = app.state::<AppState>();
+    let beacon = state.directory_beacon.lock().ok()?;
+    beacon
+        .registration
+        .as_ref()
+        .map(|registration| registration.generation)
+}
+
+/// Like `stop`, but leaves a registration made since `generation` alone,
+/// for a listener that exited on its own while another may be starting.
+pub(crate) fn stop_generation(app: &AppHandle, generation: u64) {
+    end(app, Some(generation));
+}
+
+fn end(app: &AppHandle, generation: Option<u64>) {
+    let registration = {
+        let state = app.state::<AppState>();
+        let Ok(mut beacon) = state.directory_beacon.lock() else {
+            return;
+        };
+        let current = beacon
+            .registration
+            .as_ref()
+            .map(|registration| registration.generation);
+        if generation.is_some_and(|generation| current != Some(generation)) {
+            return;
+        }
+        beacon.generation += 1;
+        beacon.registration.take()
+    };
+    let Some(registration) = registration.filter(|registration| registration.registered) else {
+        return;
+    };
+    if app
+        .state::<AppState>()
+        .network_halted
+        .load(Ordering::SeqCst)
+    {
+        return;
+    }
+    let url = settings(app).url.trim().to_string();
+    if url.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        if let Err(err) = deregister(&url, &registration.device_id) {
+            emit_system_log(&app, err);
+        }
+    });
+}
+
+/// The other devices the directory lists. Empty when the beacon is off
+/// or the service cannot be reached.
+pub(crate) fn peers(app: &AppHandle) -> Vec<DiscoverDevice> {
+    let url = settings(app).url.trim().to_string();
+    if url.is_empty() {
+        return Vec::new();
+    }
+    let own_id = identity::load_or_create_identity(app)
+        .ok()
+        .map(|identity| identity.device_id);
+    let listed: Result<Vec<BeaconDevice>, String> = bearer().and_then(|bearer| {
+        agent()
+            .get(format!("{}/devices", url.trim_end_matches('/')))
+            .header("Authorization", bearer)
+            .call()
+            .map_err(|err| err.to_string())?
+            .body_mut()
+            .read_json()
+            .map_err(|err| err.to_string())
+    });
+    let listed = match listed {
+        Ok(listed) => listed,
+        Err(err) => {
+            emit_system_log(app, format!("failed to query the directory: {err}"));
+            return Vec::new();
+        }
+    };
+    listed
+        .into_iter()
+        .filter(|device| own_id.as_deref() != Some(device.id.as_str()))
+        .filter_map(discovered)
+        .collect()
+}
+
+/// A listed device as a discovery entry; `None` without anywhere to reach
+/// it.
+fn discovered(device: BeaconDevice) -> Option<DiscoverDevice> {
+    let addresses: Vec<String> = device
+        .addresses
+        .iter()
+        .map(|address| clean_text(address.trim()))
+        .filter(|address| address.parse::<IpAddr>().is_ok())
+        .collect();
+    let host = device
+        .host
+        .as_deref()
+        .map(|host| clean_text(host.trim()))
+        .filter(|host| host.parse::<IpAddr>().is_ok())
+        .or_else(|| addresses.first().cloned())?;
+    if device.port == 0 {
+        return None;
+    }
+    let id = Some(clean_text(device.id.trim())).filter(|id| !id.is_empty());
+    Some(DiscoverDevice {
+        name: Some(clean_text(device.name.trim()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| host.clone()),
+        host,
+        port: device.port,
+        addresses,
+        id,
+        fingerprint: device.fingerprint.as_deref().map(clean_text),
+        version: device.version.as_deref().map(clean_text),
+        protocol_version: None,
+        tls: Some(device.tls),
+        pair_code_required: Some(device.pair_code_required),
+        capabilities: Vec::new(),
+        appearance: Default::default(),
+        source: DeviceSource::LocalSent,
+        routes: Vec::new(),
+        proximity: None,
+        directory: None,
+    })
+}
+
+#[tauri::command]
+pub(crate) fn get_directory_beacon_status(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<BeaconStatus, String> {
+    let has_token = get_secret(TOKEN_SECRET)?.is_some();
+    let beacon = state
+        .directory_beacon
+        .lock()
+        .map_err(|_| "failed to lock directory beacon".to_string())?;
+    Ok(BeaconStatus {
+        url: settings(&app).url,
+        has_token,
+        registered: beacon
+            .registration
+            .as_ref()
+            .is_some_and(|registration| registration.registered),
+        last_registered_at_ms: beacon.last_registered_at_ms,
+        last_error: beacon.last_error.clone(),
+    })
+}
+
+/// `token` replaces the stored token when given; `None` keeps it. Takes
+/// effect with the next heartbeat and discovery; a listener started while
+/// the beacon was off registers from its next start.
+#[tauri::command]
+pub(crate) fn set_directory_beacon(
+    app: AppHandle,
+    beacon: DirectoryBeaconSettings,
+    token: Option<String>,
+) -> Result<DirectoryBeaconSettings, String> {
+    let url = beacon.url.trim().trim_end_matches('/').to_string();
+    if !url.is_empty() {
+        let parsed = Url::parse(&url).map_err(|err| format!("directory URL is invalid: {err}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err("directory URL must start with http:// or https://".to_string());
+        }
+    }
+    if beacon.heartbeat_secs < 10 {
+        return Err("send a heartbeat at most every 10 seconds".to_string());
+    }
+    if let Some(token) = token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+    {
+        store_secret(TOKEN_SECRET, token)?;
+    }
+    update_settings(&app, |settings| {
+        settings.directory_beacon = DirectoryBeaconSettings { url, ..beacon };
+        Ok(())
+    })
+    .map(|settings| settings.directory_beacon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(json: &str) -> Option<DiscoverDevice> {
+        discovered(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn the_observed_address_is_preferred() {
+        let device = listed(
+            r#"{ "id": "a1", "name": "Studio", "host": "10.3.0.8", "port": 37373,
+                 "addresses": ["192.168.56.1", "10.3.0.8"], "tls": true }"#,
+        )
+        .unwrap();
+        assert_eq!(device.host, "10.3.0.8");
+        assert_eq!(device.id.as_deref(), Some("a1"));
+        assert_eq!(device.tls, Some(true));
+
+        let device = listed(r#"{ "id": "a2", "port": 4000, "addresses": ["10.3.0.9"] }"#).unwrap();
+        assert_eq!(device.host, "10.3.0.9");
+        assert_eq!(device.name, "10.3.0.9");
+    }
+
+    #[test]
+    fn unreachable_entries_are_dropped() {
+        assert!(listed(r#"{ "id": "a", "port": 37373, "addresses": [] }"#).is_none());
+        assert!(listed(r#"{ "id": "a", "port": 37373, "addresses": ["laptop"] }"#).is_none());
+        assert!(listed(r#"{ "id": "a", "port": 0, "addresses": ["10.0.0.1"] }"#).is_none());
+    }
+
+    #[test]
+    fn the_service_host_is_never_registered() {
+        let body = serde_json::to_value(BeaconDevice {
+            id: "a".to_string(),
+            port: 37373,
+            ttl_secs: 180,
+            ..BeaconDevice::default()
+        })
+        .unwrap();
+        assert!(body.get("host").is_none());
+        assert_eq!(body["ttlSecs"], 180);
+        assert_eq!(body["pairCodeRequired"], false);
+    }
+}
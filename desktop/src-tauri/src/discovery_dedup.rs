@@ -0,0 +1,167 @@
+//! A listener announcing on several interfaces is seen once per interface.
+//! Entries announcing the same device id, or failing that the same TLS
+//! certificate, are one device; entries with neither are left alone, since
+//! different devices can share an address behind NAT or a VPN.
+
+use crate::parser::{DeviceSource, DiscoverDevice};
+
+fn same_device(a: &DiscoverDevice, b: &DiscoverDevice) -> bool {
+    match (&a.id, &b.id) {
+        (Some(a), Some(b)) => a == b,
+        _ => {
+            matches!((&a.fingerprint, &b.fingerprint), (Some(a), Some(b)) if a.eq_ignore_ascii_case(b))
+        }
+    }
+}
+
+fn is_link_local(address: &str) -> bool {
+    address.starts_with("169.254.") || address.to_ascii_lowercase().starts_with("fe80:")
+}
+
+fn absorb(into: &mut DiscoverDevice, other: DiscoverDevice) {
+    if into.name.trim().is_empty() {
+        into.name = other.name;
+    }
+    into.id = into.id.take().or(other.id);
+    into.fingerprint = into.fingerprint.take().or(other.fingerprint);
+    into.version = into.version.take().or(other.version);
+    into.protocol_version = into.protocol_version.or(other.protocol_version);
+    into.tls = into.tls.or(other.tls);
+    into.pair_code_required = into.pair_code_required.or(other.pair_code_required);
+    for capability in other.capabilities {
+        if !into.capabilities.contains(&capability) {
+            into.capabilities.push(capability);
+        }
+    }
+    if into.source == DeviceSource::Ssdp {
+        into.source = other.source;
+    }
+    for address in std::iter::once(other.host).chain(other.addresses) {
+        if !into.addresses.contains(&address) {
+            into.addresses.push(address);
+        }
+    }
+}
+
+/// Merges entries for the same device, combining their addresses. The host
+/// becomes the first address that is not link-local; `apply_policy` later
+/// ranks the addresses by the interface they are reached through.
+pub(crate) fn merge_duplicates(devices: Vec<DiscoverDevice>) -> Vec<DiscoverDevice> {
+    let mut merged: Vec<DiscoverDevice> = Vec::with_capacity(devices.len());
+    for mut device in devices {
+        if !device.addresses.contains(&device.host) {
+            device.addresses.insert(0, device.host.clone());
+        }
+        match merged.iter_mut().find(|known| same_device(known, &device)) {
+            Some(known) => absorb(known, device),
+            None => merged.push(device),
+        }
+    }
+    for device in &mut merged {
+        if let Some(best) = device
+            .addresses
+            .iter()
+            .find(|address| !is_link_local(address))
+        {
+            device.host = best.clone();
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(
+        name: &str,
+        host: &str,
+        id: Option<&str>,
+        fingerprint: Option<&str>,
+    ) -> DiscoverDevice {
+        DiscoverDevice {
+            name: name.to_string(),
+            host: host.to_string(),
+            port: 37373,
+            addresses: vec![host.to_string()],
+            id: id.map(str::to_string),
+            fingerprint: fingerprint.map(str::to_string),
+            version: None,
+            protocol_version: None,
+            tls: None,
+            pair_code_required: None,
+            capabilities: Vec::new(),
+            appearance: Default::default(),
+            source: DeviceSource::LocalSent,
+            routes: Vec::new(),
+            proximity: None,
+            directory: None,
+        }
+    }
+
+    #[test]
+    fn same_id_on_two_interfaces_is_one_device() {
+        let devices = merge_duplicates(vec![
+            device("desk", "192.168.1.20", Some("a"), None),
+            device("other", "192.168.1.30", Some("b"), None),
+            device("desk", "10.8.0.5", Some("a"), Some(&"ab".repeat(32))),
+        ]);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].addresses, ["192.168.1.20", "10.8.0.5"]);
+        assert_eq!(devices[0].fingerprint, Some("ab".repeat(32)));
+        assert_eq!(devices[1].name, "other");
+    }
+
+    #[test]
+    fn shared_address_without_identity_is_not_merged() {
+        let devices = merge_duplicates(vec![
+            device("a", "10.0.0.1", Some("a"), None),
+            device("b", "10.0.0.1", Some("b"), None),
+            device("c", "10.0.0.1", None, None),
+            device("d", "10.0.0.1", None, None),
+        ]);
+        assert_eq!(devices.len(), 4);
+    }
+
+    #[test]
+    fn fingerprint_merges_when_ids_are_missing() {
+        let fingerprint = "cd".repeat(32);
+        let devices = merge_duplicates(vec![
+            device("nas", "192.168.1.5", None, Some(&fingerprint)),
+            device(
+                "nas",
+                "192.168.2.5",
+                None,
+                Some(&fingerprint.to_uppercase()),
+            ),
+            device("nas", "192.168.3.5", Some("x"), Some(&"ef".repeat(32))),
+        ]);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].addresses, ["192.168.1.5", "192.168.2.5"]);
+    }
+
+    #[test]
+    fn link_local_host_gives_way_to_a_routable_address() {
+        let devices = merge_duplicates(vec![
+            device("desk", "169.254.10.2", Some("a"), None),
+            device("desk", "192.168.1.20", Some("a"), None),
+        ]);
+        assert_eq!(devices[0].host, "192.168.1.20");
+        assert_eq!(devices[0].addresses, ["169.254.10.2", "192.168.1.20"]);
+    }
+
+    #[test]
+    fn ssdp_tag_is_dropped_once_another_mechanism_sees_the_device() {
+        let mut ssdp = device("desk", "192.168.1.20", Some("a"), None);
+        ssdp.source = DeviceSource::Ssdp;
+        let mut ssdp_only = device("tv", "192.168.1.40", Some("b"), None);
+        ssdp_only.source = DeviceSource::Ssdp;
+        let devices = merge_duplicates(vec![
+            ssdp,
+            ssdp_only,
+            device("desk", "10.8.0.5", Some("a"), None),
+        ]);
+        assert_eq!(devices[0].source, DeviceSource::LocalSent);
+        assert_eq!(devices[1].source, DeviceSource::Ssdp);
+    }
+}
@@ -0,0 +1,148 @@
+//! Wide-area DNS-SD: the listener publishes itself into a unicast DNS zone
+//! with dynamic updates and discovery browses the same zone, for networks
+//! where multicast does not get through. The CLI does the DNS work; the
+//! TSIG key stays in the OS keychain and reaches it through its
+//! environment, never its command line.
+
+use crate::secrets::{get_secret, store_secret};
+use crate::settings::{load_settings, update_settings};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use tauri::AppHandle;
+
+const TSIG_KEY: &str = "dns-sd-tsig-key";
+const TSIG_ENV: &str = "LOCAL_SENT_DNS_SD_KEY";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct DnsSdSettings {
+    /// Zone the records live under, e.g. `sd.example.com`; empty turns
+    /// wide-area DNS-SD off.
+    pub(crate) zone: String,
+    /// `host[:port]` to query and update; empty uses the system resolver
+    /// for browsing and the zone's primary server for updates.
+    pub(crate) server: String,
+    /// Also publish the listener, not just browse; applies from the next
+    /// `start_listen`.
+    pub(crate) publish: bool,
+}
+
+fn configured(app: &AppHandle) -> Option<DnsSdSettings> {
+    load_settings(app)
+        .ok()
+        .map(|settings| settings.dns_sd)
+        .filter(|dns_sd| !dns_sd.zone.is_empty())
+}
+
+fn zone_args(dns_sd: DnsSdSettings) -> Vec<String> {
+    let mut args = vec!["--dns-sd-zone".to_string(), dns_sd.zone];
+    if !dns_sd.server.is_empty() {
+        args.push("--dns-sd-server".to_string());
+        args.push(dns_sd.server);
+    }
+    args
+}
+
+/// `--dns-sd-zone` and `--dns-sd-server` for `discover` while a zone is
+/// configured.
+pub(crate) fn discover_args(app: &AppHandle) -> Vec<String> {
+    configured(app).map(zone_args).unwrap_or_default()
+}
+
+/// The same for `listen`, while publishing is on.
+pub(crate) fn listen_args(app: &AppHandle) -> Vec<String> {
+    configured(app)
+        .filter(|dns_sd| dns_sd.publish)
+        .map(zone_args)
+        .unwrap_or_default()
+}
+
+/// Hands the stored TSIG key to the listener `command`.
+pub(crate) fn set_listen_key(app: &AppHandle, command: &mut Command) -> Result<(), String> {
+    if configured(app).is_some_and(|dns_sd| dns_sd.publish) {
+        if let Some(key) = get_secret(TSIG_KEY)?.filter(|key| !key.is_empty()) {
+            command.env(TSIG_ENV, key);
+        }
+    }
+    Ok(())
+}
+
+/// Checks a TSIG key in the `[hmac-sha256:]name:secret` form `nsupdate -y`
+/// takes; only HMAC-SHA256 is supported.
+fn validate_key(key: &str) -> Result<(), String> {
+    let parts: Vec<&str> = key.split(':').collect();
+    let parts = match parts.as_slice() {
+        [algorithm, rest @ ..] if rest.len() == 2 => {
+            if !algorithm.eq_ignore_ascii_case("hmac-sha256") {
+                return Err(format!("unsupported TSIG algorithm `{algorithm}`"));
+            }
+            rest
+        }
+        parts => parts,
+    };
+    match parts {
+        [name, secret] if !name.is_empty() && !secret.is_empty() => Ok(()),
+        _ => Err("TSIG key must be [hmac-sha256:]name:secret".to_string()),
+    }
+}
+
+fn normalize_zone(zone: &str) -> Result<String, String> {
+    let zone = zone.trim().trim_end_matches('.').to_lowercase();
+    let valid = zone
+        .split('.')
+        .all(|label| !label.is_empty() && label.len() <= 63);
+    if !zone.is_empty() && !valid {
+        return Err(format!("invalid DNS-SD zone `{zone}`"));
+    }
+    Ok(zone)
+}
+
+/// `key` replaces the stored TSIG key when given, and an empty one clears
+/// it for zones that take unsigned updates; `None` keeps it.
+#[tauri::command]
+pub(crate) fn set_dns_sd_settings(
+    app: AppHandle,
+    dns_sd: DnsSdSettings,
+    key: Option<String>,
+) -> Result<DnsSdSettings, String> {
+    let zone = normalize_zone(&dns_sd.zone)?;
+    if dns_sd.publish && zone.is_empty() {
+        return Err("set a DNS-SD zone to publish to".to_string());
+    }
+    if let Some(key) = key {
+        let key = key.trim();
+        if !key.is_empty() {
+            validate_key(key)?;
+        }
+        store_secret(TSIG_KEY, key)?;
+    }
+    let dns_sd = DnsSdSettings {
+        zone,
+        server: dns_sd.server.trim().to_string(),
+        publish: dns_sd.publish,
+    };
+    update_settings(&app, |settings| {
+        settings.dns_sd = dns_sd;
+        Ok(())
+    })
+    .map(|settings| settings.dns_sd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_zones_are_checked() {
+        assert!(validate_key("ls-update:c2VjcmV0").is_ok());
+        assert!(validate_key("HMAC-SHA256:ls-update:c2VjcmV0").is_ok());
+        assert!(validate_key("hmac-md5:ls-update:c2VjcmV0").is_err());
+        assert!(validate_key("c2VjcmV0").is_err());
+        assert_eq!(
+            normalize_zone(" SD.Example.com. ").unwrap(),
+            "sd.example.com"
+        );
+        assert_eq!(normalize_zone("").unwrap(), "");
+        assert!(normalize_zone("sd..example.com").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use crate::cli_ipc::{self, CliMessage};
+use crate::paths::{home_dir, measure_path};
+use crate::process_pool::ChildKind;
+use crate::{discover_devices, run_cli_capture_async, CommandResult, SendRequest};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DryRunCheck {
+    name: &'static str,
+    status: &'static str,
+    detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DryRunReport {
+    dry_run: bool,
+    target: Option<String>,
+    host: Option<String>,
+    port: u16,
+    files: usize,
+    total_bytes: u64,
+    checks: Vec<DryRunCheck>,
+}
+
+impl DryRunReport {
+    fn check(&mut self, name: &'static str, status: &'static str, detail: impl Into<String>) {
+        self.checks.push(DryRunCheck {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    fn failed(&self) -> bool {
+        self.checks.iter().any(|check| check.status == "fail")
+    }
+}
+
+/// Accepts any certificate; callers only need the peer certificate to
+/// compare its fingerprint the way the CLI does.
+#[derive(Debug)]
+pub(crate) struct CaptureVerifier(pub(crate) Arc<CryptoProvider>);
+
+impl ServerCertVerifier for CaptureVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+pub(crate) fn normalize_fingerprint(raw: &str) -> String {
+    raw.replace(':', "").trim().to_ascii_lowercase()
+}
+
+/// Completes a TLS handshake with the receiver and returns the SHA-256 of its
+/// leaf certificate, matching the CLI's fingerprint format.
+pub(crate) fn probe_tls_fingerprint(
+    address: SocketAddr,
+    host: &str,
+    timeout: Duration,
+) -> Result<String, String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("failed to configure TLS: {err}"))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(CaptureVerifier(provider)))
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|err| format!("invalid TLS server name `{host}`: {err}"))?;
+    let mut connection = ClientConnection::new(Arc::new(config), server_name)
+        .map_err(|err| format!("failed to start TLS handshake: {err}"))?;
+
+    let mut socket = TcpStream::connect_timeout(&address, timeout)
+        .map_err(|err| format!("failed to connect for TLS handshake: {err}"))?;
+    let _ = socket.set_read_timeout(Some(timeout));
+    let _ = socket.set_write_timeout(Some(timeout));
+    while connection.is_handshaking() {
+        connection
+            .complete_io(&mut socket)
+            .map_err(|err| format!("TLS handshake failed: {err}"))?;
+    }
+    connection.send_close_notify();
+    let _ = connection.complete_io(&mut socket);
+
+    connection
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .map(|cert| sha256_hex(cert.as_ref()))
+        .ok_or_else(|| "receiver did not present a TLS certificate".to_string())
+}
+
+pub(crate) fn known_host_fingerprint(
+    request: &SendRequest,
+    host: &str,
+    port: u16,
+) -> Option<String> {
+    let path = match request
+        .tls_known_hosts
+        .as_ref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        Some(path) => Path::new(path).to_path_buf(),
+        None => home_dir()?.join(".local-sent").join("known_hosts.json"),
+    };
+    let raw = fs::read_to_string(path).ok()?;
+    let known_hosts: HashMap<String, String> = serde_json::from_str(&raw).ok()?;
+    known_hosts
+        .get(&format!("{}:{port}", host.to_lowercase()))
+        .cloned()
+}
+
+fn check_tls(report: &mut DryRunReport, request: &SendRequest, address: SocketAddr, host: &str) {
+    if !request.tls.unwrap_or(false) {
+        report.check("tls", "skip", "TLS not requested");
+        return;
+    }
+    let timeout = Duration::from_millis(request.timeout_ms.unwrap_or(3000).max(100));
+    let fingerprint = match probe_tls_fingerprint(address, host, timeout) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            report.check("tls", "fail", err);
+            return;
+        }
+    };
+
+    if let Some(expected) = request
+        .tls_fingerprint
+        .as_ref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        let expected = normalize_fingerprint(expected);
+        if expected == fingerprint {
+            report.check("tls", "pass", format!("fingerprint matches {fingerprint}"));
+        } else {
+            report.check(
+                "tls",
+                "fail",
+                format!("fingerprint mismatch: expected={expected} actual={fingerprint}"),
+            );
+        }
+    } else if request.tls_tofu.unwrap_or(false) {
+        match known_host_fingerprint(request, host, report.port) {
+            Some(known) if known == fingerprint => {
+                report.check("tls", "pass", format!("matches known host {fingerprint}"))
+            }
+            Some(known) => report.check(
+                "tls",
+                "fail",
+                format!("fingerprint changed: expected={known} actual={fingerprint}"),
+            ),
+            None => report.check(
+                "tls",
+                "warn",
+                format!("not trusted yet; a real send would pin {fingerprint}"),
+            ),
+        }
+    } else if request.tls_insecure.unwrap_or(false) {
+        report.check(
+            "tls",
+            "warn",
+            format!("handshake ok, certificate not verified (insecure) {fingerprint}"),
+        );
+    } else {
+        report.check(
+            "tls",
+            "pass",
+            format!("handshake ok, certificate {fingerprint}; CA validation is left to the CLI"),
+        );
+    }
+}
+
+fn check_connectivity(report: &mut DryRunReport, request: &SendRequest, host: &str) {
+    let timeout = Duration::from_millis(request.timeout_ms.unwrap_or(3000).max(100));
+    let address = match (host, report.port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+    {
+        Some(address) => address,
+        None => {
+            report.check("connect", "fail", format!("cannot resolve {host}"));
+            return;
+        }
+    };
+    let started = Instant::now();
+    match TcpStream::connect_timeout(&address, timeout) {
+        Ok(_) => report.check(
+            "connect",
+            "pass",
+            format!(
+                "{address} reachable in {} ms",
+                started.elapsed().as_millis()
+            ),
+        ),
+        Err(err) => {
+            report.check("connect", "fail", format!("{address}: {err}"));
+            return;
+        }
+    }
+    check_tls(report, request, address, host);
+}
+
+/// Runs the send `args` with `--dry-run`: the CLI announces the files and
+/// their size, and the receiver answers after checking the pair code and its
+/// free space, without any confirm prompt or payload bytes.
+async fn check_receiver(
+    app: AppHandle,
+    report: &mut DryRunReport,
+    args: Vec<String>,
+    host: &str,
+) -> Result<(), String> {
+    let mut args: Vec<String> = cli_ipc::IPC_ARGS
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain(args)
+        .collect();
+    // Later options win, so the receiver found above is not looked up again.
+    args.extend([
+        "--host".to_string(),
+        host.to_string(),
+        "--port".to_string(),
+        report.port.to_string(),
+        "--dry-run".to_string(),
+    ]);
+    let output = run_cli_capture_async(app, args, ChildKind::Send).await?;
+    let mut passed = None;
+    let mut error = None;
+    for message in output.stdout.lines().filter_map(cli_ipc::parse_line) {
+        match message {
+            CliMessage::DryRun {
+                files,
+                bytes,
+                free_bytes,
+            } => passed = Some((files, bytes, free_bytes)),
+            CliMessage::Error { message } => error = Some(message),
+            _ => {}
+        }
+    }
+    match (passed, error) {
+        (Some((files, bytes, free_bytes)), _) if output.success => {
+            report.check("pairCode", "pass", "accepted by the receiver");
+            match free_bytes {
+                Some(free_bytes) => report.check(
+                    "space",
+                    "pass",
+                    format!("{files} file(s), {bytes} bytes fit, {free_bytes} bytes free"),
+                ),
+                None => report.check("space", "warn", "receiver did not report its free space"),
+            }
+        }
+        (_, error) => {
+            let error = error.unwrap_or_else(|| output.stderr.trim().to_string());
+            if error.contains("pair code") {
+                report.check("pairCode", "fail", error);
+            } else if error.contains("free space") {
+                report.check("pairCode", "pass", "accepted by the receiver");
+                report.check("space", "fail", error);
+            } else {
+                report.check("receiver", "fail", error);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs every pre-transfer step of a send, including the receiver's pair
+/// code and free-space checks, without moving payload bytes.
+pub(crate) async fn run_dry_run(
+    app: AppHandle,
+    request: SendRequest,
+    args: Vec<String>,
+) -> Result<CommandResult, String> {
+    let mut report = DryRunReport {
+        dry_run: true,
+        target: request.device.clone(),
+        host: request
+            .host
+            .clone()
+            .filter(|value| !value.trim().is_empty()),
+        port: request.port,
+        files: 0,
+        total_bytes: 0,
+        checks: Vec::new(),
+    };
+
+    match measure_path(Path::new(&request.path)) {
+        Ok((files, total_bytes)) => {
+            report.files = files;
+            report.total_bytes = total_bytes;
+            report.check(
+                "source",
+                "pass",
+                format!("{files} file(s), {total_bytes} bytes would be sent"),
+            );
+        }
+        Err(err) => report.check(
+            "source",
+            "fail",
+            format!("cannot read {}: {err}", request.path),
+        ),
+    }
+
+    if report.host.is_none() {
+        let timeout = request.timeout_ms.unwrap_or(3000).max(100);
+        match discover_devices(app.clone(), timeout).await {
+            Ok(devices) => {
+                let needle = request
+                    .device
+                    .as_ref()
+                    .map(|device| device.trim().to_lowercase())
+                    .filter(|device| !device.is_empty());
+                let found = devices.into_iter().find(|device| {
+                    needle
+                        .as_ref()
+                        .is_none_or(|needle| device.name.to_lowercase().contains(needle))
+                });
+                match found {
+                    Some(device) => {
+                        report.check(
+                            "discovery",
+                            "pass",
+                            format!("found {} at {}:{}", device.name, device.host, device.port),
+                        );
+                        report.target = Some(device.name);
+                        report.host = Some(device.host);
+                        report.port = device.port;
+                    }
+                    None => report.check("discovery", "fail", "no matching receiver found"),
+                }
+            }
+            Err(err) => report.check("discovery", "fail", err),
+        }
+    }
+
+    if let Some(host) = report.host.clone() {
+        let probe_request = request.clone();
+        let probe_host = host.clone();
+        report = tauri::async_runtime::spawn_blocking(move || {
+            check_connectivity(&mut report, &probe_request, &probe_host);
+            report
+        })
+        .await
+        .map_err(|err| format!("failed to join dry-run task: {err}"))?;
+        if report.failed() {
+            report.check("pairCode", "skip", "not checked after an earlier failure");
+        } else if let Err(err) = check_receiver(app, &mut report, args, &host).await {
+            report.check("receiver", "fail", err);
+        }
+    } else {
+        report.check("pairCode", "skip", "no receiver to ask");
+    }
+
+    let success = !report.failed();
+    let stdout = serde_json::to_string_pretty(&report)
+        .map_err(|err| format!("failed to serialize dry-run report: {err}"))?;
+    Ok(CommandResult {
+        success,
+        code: if success { 0 } else { 1 },
+        stdout,
+        stderr: String::new(),
+    })
+}
@@ -0,0 +1,386 @@
+use crate::activity::ActivityEntry;
+use crate::app_profiles::AppProfilesPayload;
+use crate::archive::ExtractProgressPayload;
+use crate::device_cache::CachedDevicesPayload;
+use crate::hashing::HashProgressPayload;
+use crate::job_journal::InterruptedJobsPayload;
+use crate::kill_switch::AllStoppedPayload;
+use crate::listener_lock::OrphanedListener;
+use crate::log_queue::LogDroppedPayload;
+use crate::messages::ChatMessage;
+use crate::network_watch::NetworkChangedPayload;
+use crate::ports::PortSubstitutionPayload;
+use crate::preflight::PreflightProgressPayload;
+use crate::scheduler::JobStatePayload;
+use crate::storage::{now_ms, secure_remove, shared_file};
+use crate::throttle::RateLimitedPayload;
+use crate::watchdog::TransferStalledPayload;
+use crate::{AppState, ListenStatePayload, TransferConfirmRequestPayload, VisibilityPayload};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+pub(crate) const APP_EVENT_NAME: &str = "app-event";
+pub(crate) const APP_EVENT_VERSION: u32 = 1;
+const REPLAY_BUFFER_LIMIT: usize = 2000;
+const EVENT_ARCHIVE_FILE: &str = "events.jsonl";
+const EVENT_ARCHIVE_ROTATED_FILE: &str = "events.1.jsonl";
+const EVENT_ARCHIVE_MAX_BYTES: u64 = 8 * 1024 * 1024;
+const DEFAULT_FEED_PAGE_SIZE: usize = 50;
+const MAX_FEED_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub(crate) enum AppEvent {
+    Activity(ActivityEntry),
+    ListenState(ListenStatePayload),
+    TransferConfirmRequest(TransferConfirmRequestPayload),
+    VisibilityChanged(VisibilityPayload),
+    LogDropped(LogDroppedPayload),
+    AllStopped(AllStoppedPayload),
+    PortSubstituted(PortSubstitutionPayload),
+    RateLimited(RateLimitedPayload),
+    ExtractProgress(ExtractProgressPayload),
+    HashProgress(HashProgressPayload),
+    JobState(JobStatePayload),
+    PreflightProgress(PreflightProgressPayload),
+    ProfileSwitched(AppProfilesPayload),
+    OrphanedListener(OrphanedListener),
+    TransferStalled(TransferStalledPayload),
+    MessageReceived(ChatMessage),
+    NetworkChanged(NetworkChangedPayload),
+    DevicesRefreshed(CachedDevicesPayload),
+    JobsInterrupted(InterruptedJobsPayload),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppEventEnvelope {
+    pub(crate) version: u32,
+    pub(crate) seq: u64,
+    pub(crate) timestamp_ms: u64,
+    #[serde(flatten)]
+    pub(crate) event: AppEvent,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    initialized: bool,
+    next_seq: u64,
+    recent: VecDeque<AppEventEnvelope>,
+    archive: Option<File>,
+    archive_path: Option<PathBuf>,
+    /// Set while history retention records nothing.
+    archive_paused: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ActivityFeedFilter {
+    /// Envelope `type` values to include, e.g. `activity` or `listenState`.
+    types: Vec<String>,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+    /// Case-insensitive substring matched against the serialized payload.
+    text: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ActivityFeedPage {
+    offset: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActivityFeedPayload {
+    entries: Vec<Value>,
+    total: usize,
+    has_more: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReplayEventsPayload {
+    events: Vec<AppEventEnvelope>,
+    latest_seq: u64,
+    /// True when events after `since_seq` were already evicted from the
+    /// buffer, so the caller should refresh its state from scratch.
+    truncated: bool,
+}
+
+/// Stamps `event` with the next sequence number, keeps it for replay, appends
+/// it to the on-disk archive and emits it. Sequencing and emitting happen
+/// under one lock so the frontend observes events in `seq` order.
+pub(crate) fn emit_app_event(app: &AppHandle, event: AppEvent) {
+    let state = app.state::<AppState>();
+    let Ok(mut log) = state.event_log.lock() else {
+        return;
+    };
+    if !log.initialized {
+        initialize_event_log(app, &mut log);
+    }
+
+    log.next_seq += 1;
+    let envelope = AppEventEnvelope {
+        version: APP_EVENT_VERSION,
+        seq: log.next_seq,
+        timestamp_ms: now_ms(),
+        event,
+    };
+    let _ = app.emit(APP_EVENT_NAME, envelope.clone());
+    if !log.archive_paused && should_archive(&envelope.event) {
+        append_to_archive(&mut log, &envelope);
+    }
+    log.recent.push_back(envelope);
+    while log.recent.len() > REPLAY_BUFFER_LIMIT {
+        log.recent.pop_front();
+    }
+}
+
+/// Raw send output chunks are mostly progress redraws; they stay in the
+/// replay buffer but are not worth keeping across restarts.
+fn should_archive(event: &AppEvent) -> bool {
+    !matches!(event, AppEvent::Activity(ActivityEntry::SendOutput { .. }))
+}
+
+fn initialize_event_log(app: &AppHandle, log: &mut EventLog) {
+    log.initialized = true;
+    let Ok(path) = shared_file(app, EVENT_ARCHIVE_FILE) else {
+        return;
+    };
+    // Continue numbering after the archive so seq stays monotonic across restarts.
+    let rotated = path.with_file_name(EVENT_ARCHIVE_ROTATED_FILE);
+    log.next_seq = last_archived_seq(&path)
+        .or_else(|| last_archived_seq(&rotated))
+        .unwrap_or(0);
+    log.archive = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .ok();
+    log.archive_path = Some(path);
+}
+
+fn last_archived_seq(path: &Path) -> Option<u64> {
+    let file = File::open(path).ok()?;
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+        .filter_map(|value| value.get("seq").and_then(Value::as_u64))
+        .max()
+}
+
+fn append_to_archive(log: &mut EventLog, envelope: &AppEventEnvelope) {
+    let Ok(line) = serde_json::to_string(envelope) else {
+        return;
+    };
+    let Some(archive) = log.archive.as_mut() else {
+        return;
+    };
+    if writeln!(archive, "{line}").is_err() {
+        log.archive = None;
+        return;
+    }
+
+    let too_large = archive
+        .metadata()
+        .map(|metadata| metadata.len() > EVENT_ARCHIVE_MAX_BYTES)
+        .unwrap_or(false);
+    if too_large {
+        rotate_archive(log);
+    }
+}
+
+fn rotate_archive(log: &mut EventLog) {
+    let Some(path) = log.archive_path.clone() else {
+        return;
+    };
+    log.archive = None;
+    let rotated = path.with_file_name(EVENT_ARCHIVE_ROTATED_FILE);
+    let _ = fs::rename(&path, rotated);
+    reopen_archive(log);
+}
+
+fn reopen_archive(log: &mut EventLog) {
+    log.archive = log
+        .archive_path
+        .as_ref()
+        .and_then(|path| OpenOptions::new().create(true).append(true).open(path).ok());
+}
+
+/// Stops or resumes archiving events to disk.
+pub(crate) fn set_archiving(app: &AppHandle, enabled: bool) {
+    if let Ok(mut log) = app.state::<AppState>().event_log.lock() {
+        log.archive_paused = !enabled;
+    }
+}
+
+/// Drops archived events from before `cutoff_ms`, or all of them when
+/// `None`. The archive files are overwritten before they are deleted, so
+/// file names in old events do not linger on disk.
+pub(crate) fn prune_archive(app: &AppHandle, cutoff_ms: Option<u64>) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut log = state
+        .event_log
+        .lock()
+        .map_err(|_| "failed to lock event log".to_string())?;
+    let path = shared_file(app, EVENT_ARCHIVE_FILE)?;
+    for file in [
+        path.with_file_name(EVENT_ARCHIVE_ROTATED_FILE),
+        path.clone(),
+    ] {
+        let entries = read_archive(&file);
+        let kept: Vec<&Value> = entries
+            .iter()
+            .filter(|entry| {
+                cutoff_ms.is_some_and(|cutoff| {
+                    entry
+                        .get("timestampMs")
+                        .and_then(Value::as_u64)
+                        .is_some_and(|timestamp| timestamp >= cutoff)
+                })
+            })
+            .collect();
+        if kept.len() == entries.len() {
+            continue;
+        }
+        if file == path {
+            log.archive = None;
+        }
+        secure_remove(&file)?;
+        if !kept.is_empty() {
+            let mut raw = String::new();
+            for entry in kept {
+                raw.push_str(&entry.to_string());
+                raw.push('\n');
+            }
+            fs::write(&file, raw)
+                .map_err(|err| format!("failed to write {}: {err}", file.display()))?;
+        }
+    }
+    if log.initialized && log.archive.is_none() {
+        reopen_archive(&mut log);
+    }
+    Ok(())
+}
+
+fn read_archive(path: &Path) -> Vec<Value> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+        .collect()
+}
+
+fn matches_feed_filter(entry: &Value, filter: &ActivityFeedFilter, needle: Option<&str>) -> bool {
+    if !filter.types.is_empty() {
+        let entry_type = entry
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !filter.types.iter().any(|item| item == entry_type) {
+            return false;
+        }
+    }
+    let timestamp = entry
+        .get("timestampMs")
+        .and_then(Value::as_u64)
+        .unwrap_or(0);
+    if filter.since_ms.is_some_and(|since| timestamp < since) {
+        return false;
+    }
+    if filter.until_ms.is_some_and(|until| timestamp > until) {
+        return false;
+    }
+    if let Some(needle) = needle {
+        let haystack = entry
+            .get("payload")
+            .map(Value::to_string)
+            .unwrap_or_default()
+            .to_lowercase();
+        if !haystack.contains(needle) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Returns archived events newest first, spanning the current and the
+/// previously rotated archive file.
+#[tauri::command]
+pub(crate) fn get_activity_feed(
+    app: AppHandle,
+    state: State<AppState>,
+    filter: Option<ActivityFeedFilter>,
+    page: Option<ActivityFeedPage>,
+) -> Result<ActivityFeedPayload, String> {
+    let filter = filter.unwrap_or_default();
+    let page = page.unwrap_or_default();
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_FEED_PAGE_SIZE)
+        .clamp(1, MAX_FEED_PAGE_SIZE);
+    let needle = filter
+        .text
+        .as_ref()
+        .map(|text| text.trim().to_lowercase())
+        .filter(|text| !text.is_empty());
+
+    let path = shared_file(&app, EVENT_ARCHIVE_FILE)?;
+    let rotated = path.with_file_name(EVENT_ARCHIVE_ROTATED_FILE);
+    let mut entries = {
+        // Hold the log lock so a concurrent rotation cannot hide events.
+        let _log = state
+            .event_log
+            .lock()
+            .map_err(|_| "failed to lock event log".to_string())?;
+        let mut entries = read_archive(&rotated);
+        entries.extend(read_archive(&path));
+        entries
+    };
+    entries.retain(|entry| matches_feed_filter(entry, &filter, needle.as_deref()));
+    entries.reverse();
+
+    let total = entries.len();
+    let entries: Vec<Value> = entries.into_iter().skip(page.offset).take(limit).collect();
+    Ok(ActivityFeedPayload {
+        has_more: page.offset + entries.len() < total,
+        entries,
+        total,
+    })
+}
+
+#[tauri::command]
+pub(crate) fn replay_events(
+    state: State<AppState>,
+    since_seq: Option<u64>,
+) -> Result<ReplayEventsPayload, String> {
+    let since_seq = since_seq.unwrap_or(0);
+    let log = state
+        .event_log
+        .lock()
+        .map_err(|_| "failed to lock event log".to_string())?;
+    let oldest_seq = log.recent.front().map(|envelope| envelope.seq);
+    let truncated = oldest_seq.is_some_and(|oldest| oldest > since_seq + 1);
+    let events = log
+        .recent
+        .iter()
+        .filter(|envelope| envelope.seq > since_seq)
+        .cloned()
+        .collect();
+    Ok(ReplayEventsPayload {
+        events,
+        latest_seq: log.next_seq,
+        truncated,
+    })
+}
@@ -0,0 +1,149 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::paths;
+use memmap2::Mmap;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+/// Each mapped chunk is split across the rayon pool; progress is reported
+/// between chunks.
+const CHUNK_BYTES: usize = 64 * 1024 * 1024;
+const READ_BUFFER_BYTES: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HashProgressPayload {
+    path: String,
+    /// What the hash is for, e.g. `verify`.
+    purpose: &'static str,
+    bytes_hashed: u64,
+    total_bytes: u64,
+    done: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileDigest {
+    pub(crate) path: String,
+    pub(crate) size: u64,
+    pub(crate) blake3: String,
+    elapsed_ms: u64,
+}
+
+fn hash_buffered(
+    file: &mut File,
+    total: u64,
+    on_progress: &mut impl FnMut(u64, u64),
+) -> Result<blake3::Hash, String> {
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; READ_BUFFER_BYTES];
+    let mut hashed = 0u64;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|err| format!("failed to read file: {err}"))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        hashed += read as u64;
+        on_progress(hashed, total.max(hashed));
+    }
+    Ok(hasher.finalize())
+}
+
+/// Hashes `path` with BLAKE3. Regular files are memory-mapped and every
+/// chunk is hashed on all cores, so a multi-GB file is read once at disk
+/// speed; files that cannot be mapped fall back to buffered reads.
+/// `on_progress(hashed, total)` runs after each chunk.
+pub(crate) fn blake3_file(
+    path: &Path,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<FileDigest, String> {
+    let started = Instant::now();
+    let mut file = File::open(path).map_err(|err| format!("failed to open file: {err}"))?;
+    let metadata = file
+        .metadata()
+        .map_err(|err| format!("failed to read file metadata: {err}"))?;
+    let total = metadata.len();
+
+    // SAFETY: the mapping is read-only and dropped before returning. A file
+    // truncated by another process while mapped can fault; that is the same
+    // trade-off every mmap-based hasher makes for speed.
+    let mapped = if metadata.is_file() && total > 0 {
+        unsafe { Mmap::map(&file) }.ok()
+    } else {
+        None
+    };
+    let hash = match mapped {
+        Some(map) => {
+            let mut hasher = blake3::Hasher::new();
+            let mut hashed = 0u64;
+            for chunk in map.chunks(CHUNK_BYTES) {
+                hasher.update_rayon(chunk);
+                hashed += chunk.len() as u64;
+                on_progress(hashed, total);
+            }
+            hasher.finalize()
+        }
+        None => hash_buffered(&mut file, total, &mut on_progress)?,
+    };
+
+    Ok(FileDigest {
+        path: path.to_string_lossy().to_string(),
+        size: total,
+        blake3: hash.to_hex().to_string(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// `blake3_file` that reports its progress as `hashProgress` app events.
+pub(crate) fn blake3_file_with_events(
+    app: &AppHandle,
+    path: &Path,
+    purpose: &'static str,
+) -> Result<FileDigest, String> {
+    let display = path.to_string_lossy().to_string();
+    let mut last_emit = Instant::now();
+    let emit = |bytes_hashed: u64, total_bytes: u64, done: bool| {
+        emit_app_event(
+            app,
+            AppEvent::HashProgress(HashProgressPayload {
+                path: display.clone(),
+                purpose,
+                bytes_hashed,
+                total_bytes,
+                done,
+            }),
+        );
+    };
+    emit(0, 0, false);
+    let digest = blake3_file(path, |hashed, total| {
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            emit(hashed, total, false);
+        }
+    });
+    match &digest {
+        Ok(digest) => emit(digest.size, digest.size, true),
+        Err(_) => emit(0, 0, true),
+    }
+    digest
+}
+
+/// Hashes a local file for manual verification, e.g. to compare with the
+/// copy on another device.
+#[tauri::command]
+pub(crate) async fn hash_file(app: AppHandle, path: String) -> Result<FileDigest, String> {
+    let path = paths::validate_send_source(&path)?;
+    if !path.is_file() {
+        return Err("only regular files can be hashed".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || blake3_file_with_events(&app, &path, "verify"))
+        .await
+        .map_err(|err| format!("failed to join hashing task: {err}"))?
+}
@@ -0,0 +1,469 @@
+use crate::history_retention::{load_retention, HistoryRetention};
+use crate::paths::measure_path;
+use crate::secrets;
+use crate::storage::{data_file, now_ms, read_json, secure_remove, write_json};
+use crate::{run_send_request, AppState, CommandResult, SendRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const SEND_HISTORY_FILE: &str = "send-history.json";
+pub(crate) const RECEIVE_HISTORY_FILE: &str = "receive-history.json";
+const SEND_HISTORY_LIMIT: usize = 500;
+const RECEIVE_HISTORY_LIMIT: usize = 500;
+const DEFAULT_TRANSFER_HISTORY: usize = 100;
+const DEFAULT_RECENT_PATHS: usize = 10;
+const THROUGHPUT_SAMPLES: usize = 5;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendHistory {
+    next_id: u64,
+    entries: Vec<SendHistoryEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendHistoryEntry {
+    pub(crate) id: u64,
+    pub(crate) sent_at_ms: u64,
+    pub(crate) request: SendRequest,
+    pub(crate) success: bool,
+    pub(crate) error: Option<String>,
+    /// Size of the sent file or folder and how long the send took; used to
+    /// estimate later sends to the same device.
+    #[serde(default)]
+    pub(crate) bytes: Option<u64>,
+    #[serde(default)]
+    pub(crate) duration_ms: Option<u64>,
+}
+
+/// The sender's note on a received file (`send --note/--tag`).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct TransferNote {
+    text: Option<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReceiveHistory {
+    next_id: u64,
+    entries: Vec<ReceiveHistoryEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReceiveHistoryEntry {
+    id: u64,
+    received_at_ms: u64,
+    path: String,
+    from: Option<String>,
+    #[serde(default)]
+    note: TransferNote,
+    #[serde(default)]
+    bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum TransferDirection {
+    Send,
+    Receive,
+}
+
+/// One send or receive as listed by `get_transfer_history`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TransferHistoryItem {
+    pub(crate) direction: TransferDirection,
+    /// Id within the send or the receive history.
+    pub(crate) id: u64,
+    pub(crate) at_ms: u64,
+    pub(crate) path: String,
+    /// Host or device for sends, the sender's address for receives.
+    pub(crate) peer: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) success: bool,
+    pub(crate) bytes: Option<u64>,
+    /// Only known for sends.
+    pub(crate) duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RecentSendPath {
+    path: String,
+    kind: &'static str,
+    exists: bool,
+    history_id: u64,
+    last_sent_at_ms: u64,
+}
+
+fn load_history(app: &AppHandle) -> Result<SendHistory, String> {
+    read_json(&data_file(app, SEND_HISTORY_FILE)?)
+}
+
+fn save_history(app: &AppHandle, history: &SendHistory) -> Result<(), String> {
+    write_json(&data_file(app, SEND_HISTORY_FILE)?, history)
+}
+
+fn load_receive_history(app: &AppHandle) -> Result<ReceiveHistory, String> {
+    read_json(&data_file(app, RECEIVE_HISTORY_FILE)?)
+}
+
+/// Drops the oldest entries `retention` no longer keeps; entries are
+/// appended in time order.
+fn apply_retention<T>(
+    entries: &mut Vec<T>,
+    retention: HistoryRetention,
+    at_ms: impl Fn(&T) -> u64,
+) -> usize {
+    let times: Vec<u64> = entries.iter().map(at_ms).collect();
+    let excess = retention.excess(&times);
+    entries.drain(..excess);
+    excess
+}
+
+/// Applies `retention` to both history files; returns how many entries
+/// were removed.
+pub(crate) fn prune_history(app: &AppHandle, retention: HistoryRetention) -> Result<usize, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .history_lock
+        .lock()
+        .map_err(|_| "failed to lock send history".to_string())?;
+    let mut sends = load_history(app)?;
+    let removed_sends = apply_retention(&mut sends.entries, retention, |entry| entry.sent_at_ms);
+    if removed_sends > 0 {
+        save_history(app, &sends)?;
+    }
+    let mut receives = load_receive_history(app)?;
+    let removed_receives = apply_retention(&mut receives.entries, retention, |entry| {
+        entry.received_at_ms
+    });
+    if removed_receives > 0 {
+        write_json(&data_file(app, RECEIVE_HISTORY_FILE)?, &receives)?;
+    }
+    Ok(removed_sends + removed_receives)
+}
+
+/// Overwrites and deletes both history files. Ids start again from 1.
+pub(crate) fn wipe_history(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .history_lock
+        .lock()
+        .map_err(|_| "failed to lock send history".to_string())?;
+    secure_remove(&data_file(app, SEND_HISTORY_FILE)?)?;
+    secure_remove(&data_file(app, RECEIVE_HISTORY_FILE)?)
+}
+
+/// Every recorded receive with the time it arrived.
+pub(crate) fn received_paths(app: &AppHandle) -> Result<Vec<(PathBuf, u64)>, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .history_lock
+        .lock()
+        .map_err(|_| "failed to lock send history".to_string())?;
+    Ok(load_receive_history(app)?
+        .entries
+        .into_iter()
+        .map(|entry| (PathBuf::from(entry.path), entry.received_at_ms))
+        .collect())
+}
+
+/// Keeps a received file, with the sender's note, for `get_transfer_history`.
+pub(crate) fn record_receive(
+    app: &AppHandle,
+    path: &Path,
+    from: Option<&str>,
+    note: Option<TransferNote>,
+) -> Result<(), String> {
+    let bytes = fs::metadata(path).ok().map(|metadata| metadata.len());
+    let retention = load_retention(app);
+    let state = app.state::<AppState>();
+    let _guard = state
+        .history_lock
+        .lock()
+        .map_err(|_| "failed to lock send history".to_string())?;
+    let mut history = load_receive_history(app)?;
+    history.next_id = history.next_id.max(1);
+    history.entries.push(ReceiveHistoryEntry {
+        id: history.next_id,
+        received_at_ms: now_ms(),
+        path: path.to_string_lossy().to_string(),
+        from: from.map(str::to_string),
+        note: note.unwrap_or_default(),
+        bytes,
+    });
+    history.next_id += 1;
+    let overflow = history.entries.len().saturating_sub(RECEIVE_HISTORY_LIMIT);
+    history.entries.drain(..overflow);
+    apply_retention(&mut history.entries, retention, |entry| {
+        entry.received_at_ms
+    });
+    write_json(&data_file(app, RECEIVE_HISTORY_FILE)?, &history)
+}
+
+pub(crate) fn record_send(
+    app: &AppHandle,
+    request: &SendRequest,
+    result: &Result<CommandResult, String>,
+    duration_ms: u64,
+) -> Result<SendHistoryEntry, String> {
+    let bytes = match result {
+        Ok(output) if output.success => measure_path(Path::new(&request.path))
+            .ok()
+            .map(|(_, bytes)| bytes),
+        _ => None,
+    };
+    let retention = load_retention(app);
+    let state = app.state::<AppState>();
+    let _guard = state
+        .history_lock
+        .lock()
+        .map_err(|_| "failed to lock send history".to_string())?;
+
+    let mut history = load_history(app)?;
+    history.next_id = history.next_id.max(1);
+    let (success, error) = match result {
+        Ok(output) if output.success => (true, None),
+        Ok(output) => (false, Some(format!("exit code {}", output.code))),
+        Err(err) => (false, Some(err.clone())),
+    };
+    let mut stored_request = request.clone();
+    if secrets::stash_pair_code(app, &mut stored_request).is_err() {
+        // Never fall back to plain text; resending will just ask for the code.
+        stored_request.pair_code = None;
+    }
+    let entry = SendHistoryEntry {
+        id: history.next_id,
+        sent_at_ms: now_ms(),
+        request: stored_request,
+        success,
+        error,
+        bytes,
+        duration_ms: Some(duration_ms),
+    };
+    history.next_id += 1;
+    history.entries.push(entry.clone());
+    if history.entries.len() > SEND_HISTORY_LIMIT {
+        let overflow = history.entries.len() - SEND_HISTORY_LIMIT;
+        history.entries.drain(..overflow);
+    }
+    apply_retention(&mut history.entries, retention, |entry| entry.sent_at_ms);
+    save_history(app, &history)?;
+    Ok(entry)
+}
+
+/// Average throughput of the last successful sends to `host` or `device`,
+/// falling back to recent sends to any device.
+pub(crate) fn recent_throughput(
+    app: &AppHandle,
+    host: Option<&str>,
+    device: Option<&str>,
+) -> Option<u64> {
+    let state = app.state::<AppState>();
+    let _guard = state.history_lock.lock().ok()?;
+    let history = load_history(app).ok()?;
+    let timed = || {
+        history.entries.iter().rev().filter_map(|entry| {
+            let bytes = entry.bytes.filter(|_| entry.success)?;
+            let duration_ms = entry.duration_ms.filter(|value| *value > 0)?;
+            Some((entry, bytes, duration_ms))
+        })
+    };
+    let average = |samples: Vec<(u64, u64)>| {
+        let bytes: u64 = samples.iter().map(|(bytes, _)| bytes).sum();
+        let duration_ms: u64 = samples.iter().map(|(_, duration_ms)| duration_ms).sum();
+        (duration_ms > 0 && bytes > 0).then(|| bytes * 1000 / duration_ms)
+    };
+    let same_target = timed()
+        .filter(|(entry, _, _)| {
+            host.is_some_and(|host| entry.request.host.as_deref() == Some(host))
+                || device.is_some_and(|device| entry.request.device.as_deref() == Some(device))
+        })
+        .take(THROUGHPUT_SAMPLES)
+        .map(|(_, bytes, duration_ms)| (bytes, duration_ms))
+        .collect::<Vec<_>>();
+    average(same_target).or_else(|| {
+        average(
+            timed()
+                .take(THROUGHPUT_SAMPLES)
+                .map(|(_, bytes, duration_ms)| (bytes, duration_ms))
+                .collect(),
+        )
+    })
+}
+
+fn path_kind(path: &Path) -> &'static str {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() => "directory",
+        Ok(_) => "file",
+        Err(_) => "missing",
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_recent_send_paths(
+    app: AppHandle,
+    state: State<AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<RecentSendPath>, String> {
+    let _guard = state
+        .history_lock
+        .lock()
+        .map_err(|_| "failed to lock send history".to_string())?;
+    let history = load_history(&app)?;
+    let limit = limit.unwrap_or(DEFAULT_RECENT_PATHS).max(1);
+
+    let mut seen = HashSet::new();
+    let mut recent = Vec::new();
+    for entry in history.entries.iter().rev() {
+        if !seen.insert(entry.request.path.clone()) {
+            continue;
+        }
+        let kind = path_kind(Path::new(&entry.request.path));
+        recent.push(RecentSendPath {
+            path: entry.request.path.clone(),
+            kind,
+            exists: kind != "missing",
+            history_id: entry.id,
+            last_sent_at_ms: entry.sent_at_ms,
+        });
+        if recent.len() >= limit {
+            break;
+        }
+    }
+    Ok(recent)
+}
+
+impl TransferHistoryItem {
+    /// Every word of `query` has to appear in the path, peer, note or tags.
+    fn matches(&self, query: &[String], tag: Option<&str>) -> bool {
+        if tag.is_some_and(|tag| !self.tags.iter().any(|item| item.eq_ignore_ascii_case(tag))) {
+            return false;
+        }
+        let haystack = [
+            Some(self.path.as_str()),
+            self.peer.as_deref(),
+            self.note.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(self.tags.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase();
+        query.iter().all(|word| haystack.contains(word.as_str()))
+    }
+}
+
+/// Every send and receive still in the history files, in no particular
+/// order.
+pub(crate) fn transfer_items(app: &AppHandle) -> Result<Vec<TransferHistoryItem>, String> {
+    let (sends, receives) = {
+        let state = app.state::<AppState>();
+        let _guard = state
+            .history_lock
+            .lock()
+            .map_err(|_| "failed to lock send history".to_string())?;
+        (load_history(app)?, load_receive_history(app)?)
+    };
+    let sent = sends.entries.into_iter().map(|entry| TransferHistoryItem {
+        direction: TransferDirection::Send,
+        id: entry.id,
+        at_ms: entry.sent_at_ms,
+        path: entry.request.path,
+        peer: entry.request.host.or(entry.request.device),
+        note: entry.request.note,
+        tags: entry.request.tags,
+        success: entry.success,
+        bytes: entry.bytes,
+        duration_ms: entry.duration_ms,
+    });
+    let received = receives
+        .entries
+        .into_iter()
+        .map(|entry| TransferHistoryItem {
+            direction: TransferDirection::Receive,
+            id: entry.id,
+            at_ms: entry.received_at_ms,
+            path: entry.path,
+            peer: entry.from,
+            note: entry.note.text,
+            tags: entry.note.tags,
+            success: true,
+            bytes: entry.bytes,
+            duration_ms: None,
+        });
+    Ok(sent.chain(received).collect())
+}
+
+/// Sends and receives, newest first. `query` searches paths, peers, notes
+/// and tags ("contract" finds where the contract went); `tag` keeps only
+/// transfers with that tag.
+#[tauri::command]
+pub(crate) fn get_transfer_history(
+    app: AppHandle,
+    query: Option<String>,
+    tag: Option<String>,
+    direction: Option<TransferDirection>,
+    limit: Option<usize>,
+) -> Result<Vec<TransferHistoryItem>, String> {
+    let query: Vec<String> = query
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    let tag = tag
+        .as_deref()
+        .map(|tag| tag.trim().trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty());
+
+    let mut items: Vec<TransferHistoryItem> = transfer_items(&app)?
+        .into_iter()
+        .filter(|item| direction.is_none_or(|direction| item.direction == direction))
+        .filter(|item| item.matches(&query, tag))
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.at_ms));
+    items.truncate(limit.unwrap_or(DEFAULT_TRANSFER_HISTORY).max(1));
+    Ok(items)
+}
+
+#[tauri::command]
+pub(crate) async fn resend(
+    app: AppHandle,
+    history_id: u64,
+    device: Option<String>,
+) -> Result<CommandResult, String> {
+    let entry = {
+        let state = app.state::<AppState>();
+        let _guard = state
+            .history_lock
+            .lock()
+            .map_err(|_| "failed to lock send history".to_string())?;
+        load_history(&app)?
+            .entries
+            .into_iter()
+            .find(|entry| entry.id == history_id)
+            .ok_or_else(|| format!("send history entry {history_id} not found"))?
+    };
+
+    if !Path::new(&entry.request.path).exists() {
+        return Err(format!("{} no longer exists", entry.request.path));
+    }
+
+    let mut request = entry.request;
+    secrets::restore_pair_code(&app, &mut request);
+    if let Some(device) = device.filter(|value| !value.trim().is_empty()) {
+        request.device = Some(device);
+        request.host = None;
+    }
+    run_send_request(app, request).await
+}
@@ -0,0 +1,147 @@
+use crate::activity_search::{prune_index, wipe_index};
+use crate::history::{prune_history, wipe_history};
+use crate::settings::{load_settings, update_settings};
+use crate::storage::now_ms;
+use crate::{dedup, events, job_journal, send_batches};
+use crate::{emit_system_log, AppState};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// How long send and receive history, the activity index built from it and
+/// from the logs, and the event archive are kept. The archive follows the
+/// day limit and is not kept at all for `Nothing`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(
+    tag = "mode",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub(crate) enum HistoryRetention {
+    /// Up to the history files' own limit; the index keeps everything.
+    #[default]
+    KeepAll,
+    Days {
+        days: u32,
+    },
+    /// The newest `entries` sends and as many receives.
+    Entries {
+        entries: u32,
+    },
+    /// Nothing is recorded; search and statistics stay empty.
+    Nothing,
+}
+
+impl HistoryRetention {
+    /// Entries recorded before this are dropped.
+    pub(crate) fn cutoff_ms(self) -> Option<u64> {
+        match self {
+            HistoryRetention::Days { days } => {
+                Some(now_ms().saturating_sub(u64::from(days) * DAY_MS))
+            }
+            _ => None,
+        }
+    }
+
+    /// How many of the oldest entries to drop from a history whose entries
+    /// were recorded at `times`, oldest first.
+    pub(crate) fn excess(self, times: &[u64]) -> usize {
+        match self {
+            HistoryRetention::KeepAll => 0,
+            HistoryRetention::Days { .. } => {
+                let cutoff = self.cutoff_ms().unwrap_or(0);
+                times.iter().take_while(|at_ms| **at_ms < cutoff).count()
+            }
+            HistoryRetention::Entries { entries } => times.len().saturating_sub(entries as usize),
+            HistoryRetention::Nothing => times.len(),
+        }
+    }
+
+    pub(crate) fn records(self) -> bool {
+        self != HistoryRetention::Nothing
+    }
+}
+
+pub(crate) fn load_retention(app: &AppHandle) -> HistoryRetention {
+    load_settings(app)
+        .map(|settings| settings.history_retention)
+        .unwrap_or_default()
+}
+
+fn run_prune_pass(app: &AppHandle, retention: HistoryRetention) -> Result<(), String> {
+    let removed = prune_history(app, retention)?;
+    prune_index(app, retention)?;
+    events::set_archiving(app, retention.records());
+    match retention {
+        HistoryRetention::Days { .. } => events::prune_archive(app, retention.cutoff_ms())?,
+        HistoryRetention::Nothing => events::prune_archive(app, None)?,
+        _ => {}
+    }
+    if removed > 0 {
+        emit_system_log(
+            app,
+            format!("history retention removed {removed} record(s)"),
+        );
+    }
+    Ok(())
+}
+
+pub(crate) fn spawn_prune_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Err(err) = run_prune_pass(&app, load_retention(&app)) {
+            emit_system_log(&app, format!("failed to prune history: {err}"));
+        }
+        thread::sleep(PRUNE_INTERVAL);
+    });
+}
+
+/// Applies at once: entries outside the new retention are removed now.
+#[tauri::command]
+pub(crate) fn set_history_retention(
+    app: AppHandle,
+    retention: HistoryRetention,
+) -> Result<HistoryRetention, String> {
+    match retention {
+        HistoryRetention::Days { days: 0 } => {
+            return Err("keep history for at least 1 day, or choose nothing".to_string());
+        }
+        HistoryRetention::Entries { entries: 0 } => {
+            return Err("keep at least 1 entry, or choose nothing".to_string());
+        }
+        _ => {}
+    }
+    let saved = update_settings(&app, |settings| {
+        settings.history_retention = retention;
+        Ok(())
+    })
+    .map(|settings| settings.history_retention)?;
+    run_prune_pass(&app, saved)?;
+    Ok(saved)
+}
+
+/// Removes the active profile's send and receive history, the activity
+/// index, and everything else that names sent or received files: the
+/// received index, stored send batches, the job journal and the event
+/// archive. Files are overwritten before they are deleted and the index is
+/// vacuumed, so filenames do not linger in free pages. Messages have their
+/// own `clear_messages`.
+#[tauri::command]
+pub(crate) fn secure_wipe_history(app: AppHandle) -> Result<(), String> {
+    wipe_history(&app)?;
+    wipe_index(&app)?;
+    dedup::wipe_received_index(&app)?;
+    send_batches::wipe(&app)?;
+    job_journal::wipe(&app)?;
+    events::prune_archive(&app, None)?;
+    emit_system_log(&app, "send and receive history wiped");
+    Ok(())
+}
@@ -0,0 +1,266 @@
+//! Resolves host names typed into a send, so every platform behaves the
+//! same: `.local` names are asked over mDNS first, then the system
+//! resolver; plain names that DNS does not know are retried as
+//! `<name>.local`. Each address is tried in order and the first that
+//! accepts a connection is handed to the CLI.
+
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, TcpStream, ToSocketAddrs, UdpSocket,
+};
+use std::time::{Duration, Instant};
+
+const MDNS_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+const MDNS_TIMEOUT: Duration = Duration::from_millis(1500);
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(1500);
+const RECORD_A: u16 = 1;
+const RECORD_AAAA: u16 = 28;
+/// Compression pointers followed per name; real names need a few.
+const MAX_NAME_JUMPS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResolvedVia {
+    Mdns,
+    Dns,
+}
+
+impl ResolvedVia {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ResolvedVia::Mdns => "mDNS",
+            ResolvedVia::Dns => "DNS",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Resolution {
+    pub(crate) addresses: Vec<IpAddr>,
+    pub(crate) via: ResolvedVia,
+    /// The name that answered, e.g. `nas.local` for a typed `nas`.
+    pub(crate) name: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Selected {
+    pub(crate) address: IpAddr,
+    pub(crate) resolution: Resolution,
+    /// Addresses tried before it, with why they failed.
+    pub(crate) failed: Vec<(IpAddr, String)>,
+}
+
+/// A query for A and AAAA records of `name`, sent from an ephemeral port
+/// so responders answer by unicast (RFC 6762, section 6.7).
+fn mdns_query(id: u16, name: &str) -> Option<Vec<u8>> {
+    let mut packet = Vec::with_capacity(64);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 0, 0, 0]);
+    for record in [RECORD_A, RECORD_AAAA] {
+        for label in name.trim_end_matches('.').split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return None;
+            }
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&record.to_be_bytes());
+        packet.extend_from_slice(&1u16.to_be_bytes());
+    }
+    Some(packet)
+}
+
+fn read_u16(packet: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]))
+}
+
+/// The name at `at` in lowercase and the offset just past it.
+fn read_name(packet: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    for _ in 0..MAX_NAME_JUMPS {
+        loop {
+            let len = *packet.get(at)? as usize;
+            if len & 0xc0 == 0xc0 {
+                end.get_or_insert(at + 2);
+                at = (read_u16(packet, at)? & 0x3fff) as usize;
+                break;
+            }
+            if len == 0 {
+                return Some((labels.join("."), end.unwrap_or(at + 1)));
+            }
+            let label = packet.get(at + 1..at + 1 + len)?;
+            labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+            at += 1 + len;
+        }
+    }
+    None
+}
+
+/// A and AAAA answers for `name` in a response to query `id`. Malformed
+/// packets yield nothing.
+fn parse_mdns_response(packet: &[u8], id: u16, name: &str) -> Vec<IpAddr> {
+    let mut addresses = Vec::new();
+    if read_u16(packet, 0) != Some(id) {
+        return addresses;
+    }
+    let (Some(questions), Some(answers)) = (read_u16(packet, 4), read_u16(packet, 6)) else {
+        return addresses;
+    };
+    let mut at = 12;
+    for _ in 0..questions {
+        let Some((_, next)) = read_name(packet, at) else {
+            return addresses;
+        };
+        at = next + 4;
+    }
+    for _ in 0..answers {
+        let Some((owner, next)) = read_name(packet, at) else {
+            return addresses;
+        };
+        let (Some(kind), Some(len)) = (read_u16(packet, next), read_u16(packet, next + 8)) else {
+            return addresses;
+        };
+        let data_at = next + 10;
+        let Some(data) = packet.get(data_at..data_at + len as usize) else {
+            return addresses;
+        };
+        at = data_at + len as usize;
+        if owner != name {
+            continue;
+        }
+        match (kind, data.len()) {
+            (RECORD_A, 4) => addresses.push(IpAddr::V4(Ipv4Addr::new(
+                data[0], data[1], data[2], data[3],
+            ))),
+            (RECORD_AAAA, 16) => {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(data);
+                addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+    }
+    addresses
+}
+
+fn resolve_mdns(name: &str) -> Vec<IpAddr> {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    let id = query_id();
+    let Some(query) = mdns_query(id, &name) else {
+        return Vec::new();
+    };
+    let Ok(socket) = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) else {
+        return Vec::new();
+    };
+    if socket.send_to(&query, MDNS_GROUP).is_err() {
+        return Vec::new();
+    }
+    let deadline = Instant::now() + MDNS_TIMEOUT;
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    let mut buf = [0; 1500];
+    // The first answer settles it; give other responders no extra time.
+    while addresses.is_empty() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() || socket.set_read_timeout(Some(left)).is_err() {
+            break;
+        }
+        let Ok(len) = socket.recv(&mut buf) else {
+            break;
+        };
+        addresses = parse_mdns_response(&buf[..len], id, &name);
+    }
+    addresses
+}
+
+/// Query ids only need to tell this query's answers apart.
+fn query_id() -> u16 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos() as u16)
+        .unwrap_or(0)
+}
+
+fn resolve_dns(name: &str) -> Vec<IpAddr> {
+    (name, 0)
+        .to_socket_addrs()
+        .map(|addresses| addresses.map(|address| address.ip()).collect())
+        .unwrap_or_default()
+}
+
+fn dedup(addresses: Vec<IpAddr>) -> Vec<IpAddr> {
+    let mut unique = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        if !unique.contains(&address) {
+            unique.push(address);
+        }
+    }
+    unique
+}
+
+/// `None` for IP literals, which need no resolving.
+pub(crate) fn resolve(host: &str) -> Option<Result<Resolution, String>> {
+    let host = host.trim();
+    if host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    let is_local = host
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+        .ends_with(".local");
+    let mut attempts = Vec::new();
+    if is_local {
+        attempts.push((host.to_string(), ResolvedVia::Mdns));
+        attempts.push((host.to_string(), ResolvedVia::Dns));
+    } else {
+        attempts.push((host.to_string(), ResolvedVia::Dns));
+        if !host.contains('.') {
+            attempts.push((format!("{host}.local"), ResolvedVia::Mdns));
+        }
+    }
+    for (name, via) in attempts {
+        let addresses = dedup(match via {
+            ResolvedVia::Mdns => resolve_mdns(&name),
+            ResolvedVia::Dns => resolve_dns(&name),
+        });
+        if !addresses.is_empty() {
+            return Some(Ok(Resolution {
+                addresses,
+                via,
+                name,
+            }));
+        }
+    }
+    Some(Err(format!(
+        "failed to resolve {host}: no mDNS or DNS answer"
+    )))
+}
+
+/// Resolves `host` and picks the first address that accepts a connection
+/// on `port`. `None` for IP literals.
+pub(crate) fn select_address(host: &str, port: u16) -> Option<Result<Selected, String>> {
+    let resolution = match resolve(host)? {
+        Ok(resolution) => resolution,
+        Err(err) => return Some(Err(err)),
+    };
+    let mut failed = Vec::new();
+    for address in &resolution.addresses {
+        match TcpStream::connect_timeout(&SocketAddr::new(*address, port), CONNECT_TIMEOUT) {
+            Ok(_) => {
+                return Some(Ok(Selected {
+                    address: *address,
+                    resolution,
+                    failed,
+                }))
+            }
+            Err(err) => failed.push((*address, err.to_string())),
+        }
+    }
+    let tried: Vec<String> = failed
+        .iter()
+        .map(|(address, err)| format!("{address} ({err})"))
+        .collect();
+    Some(Err(format!(
+        "failed to connect to {host}:{port}; tried {}",
+        tried.join(", ")
+    )))
+}
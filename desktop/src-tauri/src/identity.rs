@@ -0,0 +1,248 @@
+use crate::storage::{now_ms, read_json, shared_file, write_json};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+pub(crate) const DEVICE_IDENTITY_FILE: &str = "device-identity.json";
+const MAX_DISPLAY_NAME_CHARS: usize = 63;
+const MAX_AVATAR_EMOJI_CHARS: usize = 8;
+const AVATAR_COLORS: &[&str] = &[
+    "#ef4444", "#f97316", "#eab308", "#22c55e", "#14b8a6", "#3b82f6", "#6366f1", "#a855f7",
+    "#ec4899",
+];
+const NAME_ADJECTIVES: &[&str] = &[
+    "Brave", "Calm", "Clever", "Cosmic", "Curious", "Daring", "Eager", "Gentle", "Golden", "Happy",
+    "Jolly", "Lucky", "Mellow", "Misty", "Nimble", "Noble", "Quick", "Quiet", "Rapid", "Silent",
+    "Sleepy", "Snowy", "Sunny", "Swift", "Tidy", "Vivid", "Witty", "Zesty",
+];
+const NAME_ANIMALS: &[&str] = &[
+    "Badger", "Beaver", "Bison", "Falcon", "Ferret", "Fox", "Gecko", "Heron", "Ibis", "Koala",
+    "Lemur", "Lynx", "Marten", "Narwhal", "Otter", "Owl", "Panda", "Penguin", "Puffin", "Quokka",
+    "Raven", "Robin", "Seal", "Sparrow", "Tapir", "Tiger", "Walrus", "Wombat",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DeviceType {
+    Laptop,
+    #[default]
+    Desktop,
+    Server,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceIdentity {
+    pub(crate) device_id: String,
+    pub(crate) display_name: Option<String>,
+    #[serde(default)]
+    pub(crate) generated_name: String,
+    pub(crate) device_type: DeviceType,
+    pub(crate) avatar_color: String,
+    pub(crate) avatar_emoji: Option<String>,
+    pub(crate) created_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceIdentityPayload {
+    #[serde(flatten)]
+    identity: DeviceIdentity,
+    effective_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceIdentityUpdate {
+    display_name: Option<String>,
+    device_type: Option<DeviceType>,
+    avatar_color: Option<String>,
+    avatar_emoji: Option<String>,
+}
+
+impl DeviceType {
+    /// The name the CLI announces, as in `listen --device-type`.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            DeviceType::Laptop => "laptop",
+            DeviceType::Desktop => "desktop",
+            DeviceType::Server => "server",
+        }
+    }
+}
+
+impl DeviceIdentity {
+    pub(crate) fn effective_name(&self) -> String {
+        self.display_name
+            .clone()
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| Some(self.generated_name.clone()).filter(|name| !name.trim().is_empty()))
+            .unwrap_or_else(|| "local-sent".to_string())
+    }
+}
+
+fn generate_device_name() -> String {
+    let seed = Uuid::new_v4().as_u128();
+    let adjective = NAME_ADJECTIVES[(seed % NAME_ADJECTIVES.len() as u128) as usize];
+    let animal = NAME_ANIMALS[((seed >> 64) % NAME_ANIMALS.len() as u128) as usize];
+    format!("{adjective} {animal}")
+}
+
+fn avatar_color_for(device_id: &str) -> String {
+    let seed = device_id.bytes().fold(0usize, |acc, byte| {
+        acc.wrapping_mul(31).wrapping_add(byte as usize)
+    });
+    AVATAR_COLORS[seed % AVATAR_COLORS.len()].to_string()
+}
+
+/// Matches the CLI's check, which refuses to announce anything else: emoji
+/// sequences keep their joiners, but other invisible and bidi characters
+/// are refused.
+pub(crate) fn is_valid_avatar_emoji(emoji: &str) -> bool {
+    !emoji.is_empty()
+        && emoji.chars().count() <= MAX_AVATAR_EMOJI_CHARS
+        && emoji.chars().all(is_avatar_emoji_char)
+}
+
+fn is_avatar_emoji_char(ch: char) -> bool {
+    !ch.is_control()
+        && !ch.is_whitespace()
+        && !matches!(
+            ch,
+            '\u{ad}'
+                | '\u{61c}'
+                | '\u{180e}'
+                | '\u{200b}'
+                | '\u{200c}'
+                | '\u{200e}'
+                | '\u{200f}'
+                | '\u{202a}'..='\u{202e}'
+                | '\u{2060}'..='\u{2064}'
+                | '\u{2066}'..='\u{206f}'
+                | '\u{feff}'
+        )
+}
+
+fn is_hex_color(value: &str) -> bool {
+    let Some(hex) = value.strip_prefix('#') else {
+        return false;
+    };
+    hex.len() == 6 && hex.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Loads the persisted identity, creating and saving a fresh one on first use.
+pub(crate) fn load_or_create_identity(app: &AppHandle) -> Result<DeviceIdentity, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .identity_lock
+        .lock()
+        .map_err(|_| "failed to lock device identity".to_string())?;
+    load_or_create_identity_locked(app)
+}
+
+fn load_or_create_identity_locked(app: &AppHandle) -> Result<DeviceIdentity, String> {
+    let path = shared_file(app, DEVICE_IDENTITY_FILE)?;
+    let mut identity: DeviceIdentity = read_json(&path)?;
+    let mut changed = false;
+    if identity.device_id.trim().is_empty() {
+        identity.device_id = Uuid::new_v4().to_string();
+        identity.avatar_color = avatar_color_for(&identity.device_id);
+        identity.created_at_ms = now_ms();
+        changed = true;
+    }
+    if identity.generated_name.trim().is_empty() {
+        identity.generated_name = generate_device_name();
+        changed = true;
+    }
+    if changed {
+        write_json(&path, &identity)?;
+    }
+    Ok(identity)
+}
+
+fn identity_payload(identity: DeviceIdentity) -> DeviceIdentityPayload {
+    DeviceIdentityPayload {
+        effective_name: identity.effective_name(),
+        identity,
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_device_identity(app: AppHandle) -> Result<DeviceIdentityPayload, String> {
+    load_or_create_identity(&app).map(identity_payload)
+}
+
+#[tauri::command]
+pub(crate) fn update_device_identity(
+    app: AppHandle,
+    state: State<AppState>,
+    update: DeviceIdentityUpdate,
+) -> Result<DeviceIdentityPayload, String> {
+    let _guard = state
+        .identity_lock
+        .lock()
+        .map_err(|_| "failed to lock device identity".to_string())?;
+    let mut identity = load_or_create_identity_locked(&app)?;
+
+    if let Some(name) = update.display_name {
+        let name = name.trim().to_string();
+        if name.chars().count() > MAX_DISPLAY_NAME_CHARS {
+            return Err(format!(
+                "display name must be at most {MAX_DISPLAY_NAME_CHARS} characters"
+            ));
+        }
+        identity.display_name = (!name.is_empty()).then_some(name);
+    }
+    if let Some(device_type) = update.device_type {
+        identity.device_type = device_type;
+    }
+    if let Some(color) = update.avatar_color {
+        let color = color.trim().to_ascii_lowercase();
+        if !is_hex_color(&color) {
+            return Err("avatar color must be a #rrggbb hex value".to_string());
+        }
+        identity.avatar_color = color;
+    }
+    if let Some(emoji) = update.avatar_emoji {
+        let emoji = emoji.trim().to_string();
+        if emoji.chars().count() > MAX_AVATAR_EMOJI_CHARS {
+            return Err("avatar emoji is too long".to_string());
+        }
+        if !emoji.is_empty() && !is_valid_avatar_emoji(&emoji) {
+            return Err("avatar emoji cannot contain spaces or invisible characters".to_string());
+        }
+        identity.avatar_emoji = (!emoji.is_empty()).then_some(emoji);
+    }
+
+    write_json(&shared_file(&app, DEVICE_IDENTITY_FILE)?, &identity)?;
+    Ok(identity_payload(identity))
+}
+
+/// Picks a new random name and makes it the effective one by clearing any
+/// custom display name.
+#[tauri::command]
+pub(crate) fn regenerate_device_name(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<DeviceIdentityPayload, String> {
+    let _guard = state
+        .identity_lock
+        .lock()
+        .map_err(|_| "failed to lock device identity".to_string())?;
+    let mut identity = load_or_create_identity_locked(&app)?;
+
+    let previous = identity.generated_name.clone();
+    let mut next = generate_device_name();
+    for _ in 0..5 {
+        if next != previous {
+            break;
+        }
+        next = generate_device_name();
+    }
+    identity.generated_name = next;
+    identity.display_name = None;
+
+    write_json(&shared_file(&app, DEVICE_IDENTITY_FILE)?, &identity)?;
+    Ok(identity_payload(identity))
+}
@@ -0,0 +1,274 @@
+use crate::parser::{AddressRoute, DiscoverDevice, InterfaceKind};
+use crate::settings::update_settings;
+use if_addrs::{get_if_addrs, IfAddr};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr};
+use tauri::AppHandle;
+
+/// Name prefixes and fragments of VPN and overlay interfaces, lowercase.
+const VPN_PREFIXES: &[&str] = &[
+    "utun",
+    "tun",
+    "tap",
+    "wg",
+    "tailscale",
+    "zt",
+    "ppp",
+    "ipsec",
+    "nordlynx",
+];
+const VPN_FRAGMENTS: &[&str] = &["vpn", "wireguard", "tailscale", "zerotier"];
+/// Container, VM and host-only bridges, and Apple's peer-to-peer links.
+const VIRTUAL_PREFIXES: &[&str] = &[
+    "docker", "br-", "veth", "virbr", "vboxnet", "vmnet", "lxc", "lxd", "cni", "flannel", "podman",
+    "awdl", "llw",
+];
+const VIRTUAL_FRAGMENTS: &[&str] = &["vethernet", "virtualbox", "vmware", "hyper-v", "wsl"];
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct InterfacePolicy {
+    /// Interface names whose addresses discovery tries first, in order. The
+    /// first of them that is up is also the only one the listener binds to.
+    pub(crate) prefer: Vec<String>,
+    /// Interfaces discovery ignores; devices only seen through them are
+    /// left out.
+    pub(crate) exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkInterfaceInfo {
+    name: String,
+    kind: InterfaceKind,
+    addresses: Vec<String>,
+    preferred: bool,
+    excluded: bool,
+}
+
+struct LocalInterface {
+    name: String,
+    ip: IpAddr,
+    netmask: Option<Ipv4Addr>,
+    kind: InterfaceKind,
+}
+
+fn listed(names: &[String], name: &str) -> bool {
+    names.iter().any(|listed| listed.eq_ignore_ascii_case(name))
+}
+
+/// Tailscale and other overlays hand out addresses from the CGNAT range.
+fn is_cgnat(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    first == 100 && (64..128).contains(&second)
+}
+
+fn classify_address(ip: IpAddr) -> InterfaceKind {
+    match ip {
+        ip if ip.is_loopback() => InterfaceKind::Loopback,
+        IpAddr::V4(v4) if is_cgnat(v4) => InterfaceKind::Vpn,
+        _ => InterfaceKind::Lan,
+    }
+}
+
+pub(crate) fn classify(name: &str, ip: IpAddr) -> InterfaceKind {
+    let name = name.to_ascii_lowercase();
+    let matches = |prefixes: &[&str], fragments: &[&str]| {
+        prefixes.iter().any(|prefix| name.starts_with(prefix))
+            || fragments.iter().any(|fragment| name.contains(fragment))
+    };
+    if ip.is_loopback() {
+        InterfaceKind::Loopback
+    } else if matches(VPN_PREFIXES, VPN_FRAGMENTS) {
+        InterfaceKind::Vpn
+    } else if matches(VIRTUAL_PREFIXES, VIRTUAL_FRAGMENTS) {
+        InterfaceKind::Virtual
+    } else {
+        classify_address(ip)
+    }
+}
+
+fn local_interfaces() -> Vec<LocalInterface> {
+    let Ok(ifaces) = get_if_addrs() else {
+        return Vec::new();
+    };
+    ifaces
+        .into_iter()
+        .map(|iface| {
+            let ip = iface.ip();
+            let netmask = match &iface.addr {
+                IfAddr::V4(v4) => Some(v4.netmask),
+                IfAddr::V6(_) => None,
+            };
+            LocalInterface {
+                kind: classify(&iface.name, ip),
+                name: iface.name,
+                ip,
+                netmask,
+            }
+        })
+        .collect()
+}
+
+fn route_for(address: &str, locals: &[LocalInterface]) -> AddressRoute {
+    let ip: Option<IpAddr> = address.parse().ok();
+    let local = ip.and_then(|ip| {
+        let same_subnet = locals
+            .iter()
+            .find(|local| match (ip, local.ip, local.netmask) {
+                (IpAddr::V4(remote), IpAddr::V4(own), Some(netmask)) => {
+                    let mask = u32::from(netmask);
+                    u32::from(remote) & mask == u32::from(own) & mask
+                }
+                _ => false,
+            });
+        // Overlays give each node a /32, so a peer's overlay address is
+        // reached through whichever interface holds one too.
+        same_subnet.or_else(|| match ip {
+            IpAddr::V4(remote) if is_cgnat(remote) => locals
+                .iter()
+                .find(|local| matches!(local.ip, IpAddr::V4(own) if is_cgnat(own))),
+            _ => None,
+        })
+    });
+    AddressRoute {
+        address: address.to_string(),
+        interface: local.map(|local| local.name.clone()),
+        kind: match (local, ip) {
+            (Some(local), _) => local.kind,
+            (None, Some(ip)) => classify_address(ip),
+            (None, None) => InterfaceKind::Lan,
+        },
+    }
+}
+
+/// The local interface `address` is reached through, if any.
+pub(crate) fn interface_for(address: &str) -> Option<String> {
+    route_for(address, &local_interfaces()).interface
+}
+
+/// Preferred interfaces in their listed order first, then LAN before VPN
+/// before virtual networks.
+fn rank(route: &AddressRoute, policy: &InterfacePolicy) -> (usize, InterfaceKind) {
+    let preference = route
+        .interface
+        .as_deref()
+        .and_then(|name| {
+            policy
+                .prefer
+                .iter()
+                .position(|preferred| preferred.eq_ignore_ascii_case(name))
+        })
+        .unwrap_or(policy.prefer.len());
+    (preference, route.kind)
+}
+
+/// Labels each device address with the local interface and kind it is
+/// reached through, drops addresses on excluded interfaces and makes the
+/// best remaining address the device's host.
+pub(crate) fn apply_policy(devices: &mut Vec<DiscoverDevice>, policy: &InterfacePolicy) {
+    let locals = local_interfaces();
+    devices.retain_mut(|device| {
+        let mut routes: Vec<AddressRoute> = device
+            .addresses
+            .iter()
+            .map(|address| route_for(address, &locals))
+            .collect();
+        if routes.is_empty() {
+            routes.push(route_for(&device.host, &locals));
+        }
+        routes.retain(|route| {
+            route
+                .interface
+                .as_deref()
+                .is_none_or(|name| !listed(&policy.exclude, name))
+        });
+        routes.sort_by_key(|route| rank(route, policy));
+        let Some(best) = routes.first() else {
+            return false;
+        };
+        device.host = best.address.clone();
+        device.addresses = routes.iter().map(|route| route.address.clone()).collect();
+        device.routes = routes;
+        true
+    });
+}
+
+/// The address the listener binds to: the first preferred interface that
+/// is up and not excluded, IPv4 first. `None` binds every interface.
+pub(crate) fn bind_address(policy: &InterfacePolicy) -> Option<IpAddr> {
+    let locals = local_interfaces();
+    policy
+        .prefer
+        .iter()
+        .filter(|name| !listed(&policy.exclude, name))
+        .find_map(|name| {
+            let mut addresses: Vec<IpAddr> = locals
+                .iter()
+                .filter(|local| local.name.eq_ignore_ascii_case(name))
+                .map(|local| local.ip)
+                .collect();
+            addresses.sort_by_key(|ip| ip.is_ipv6());
+            addresses.first().copied()
+        })
+}
+
+fn clean_names(names: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = Vec::new();
+    for name in names {
+        let name = name.trim();
+        if !name.is_empty() && !listed(&cleaned, name) {
+            cleaned.push(name.to_string());
+        }
+    }
+    cleaned
+}
+
+/// Local interfaces with their kind, for labelling in the UI.
+#[tauri::command]
+pub(crate) fn list_network_interfaces(app: AppHandle) -> Vec<NetworkInterfaceInfo> {
+    let policy = crate::settings::load_settings(&app)
+        .map(|settings| settings.interfaces)
+        .unwrap_or_default();
+    let mut grouped: BTreeMap<String, NetworkInterfaceInfo> = BTreeMap::new();
+    for local in local_interfaces() {
+        let info = grouped
+            .entry(local.name.clone())
+            .or_insert_with(|| NetworkInterfaceInfo {
+                preferred: listed(&policy.prefer, &local.name),
+                excluded: listed(&policy.exclude, &local.name),
+                name: local.name.clone(),
+                kind: local.kind,
+                addresses: Vec::new(),
+            });
+        info.addresses.push(local.ip.to_string());
+    }
+    grouped.into_values().collect()
+}
+
+/// Takes effect for the next discovery and the next `start_listen`.
+#[tauri::command]
+pub(crate) fn set_interface_policy(
+    app: AppHandle,
+    policy: InterfacePolicy,
+) -> Result<InterfacePolicy, String> {
+    let policy = InterfacePolicy {
+        prefer: clean_names(policy.prefer),
+        exclude: clean_names(policy.exclude),
+    };
+    if let Some(name) = policy
+        .prefer
+        .iter()
+        .find(|name| listed(&policy.exclude, name))
+    {
+        return Err(format!(
+            "interface `{name}` cannot be both preferred and excluded"
+        ));
+    }
+    update_settings(&app, |settings| {
+        settings.interfaces = policy;
+        Ok(())
+    })
+    .map(|settings| settings.interfaces)
+}
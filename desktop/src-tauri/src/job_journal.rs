@@ -0,0 +1,325 @@
+//! Sends are written to disk while they run, under ids that outlive the
+//! app, so a restart or an update in the middle of a transfer can offer to
+//! pick every one of them up again. A job with a send batch resumes from
+//! the batch's manifest and sends only the files still missing; any other
+//! job is sent again and the receiver continues partial files itself.
+
+use crate::events::{emit_app_event, AppEvent};
+use crate::storage::{data_file, now_ms, read_json, secure_remove, write_json};
+use crate::{emit_system_log, run_send_request, send_batches, AppState, SendRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+pub(crate) const JOBS_FILE: &str = "jobs.json";
+/// Progress is saved at most this often per job.
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JournaledJob {
+    id: String,
+    /// The send as requested, before host resolution.
+    request: SendRequest,
+    /// The send batch recording which files are done, for folders.
+    #[serde(default)]
+    batch_id: Option<String>,
+    #[serde(default)]
+    bytes: u64,
+    #[serde(default)]
+    total: u64,
+    started_at_ms: u64,
+    updated_at_ms: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct StoredJobs {
+    jobs: Vec<JournaledJob>,
+}
+
+/// A job a previous session left unfinished.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InterruptedJob {
+    id: String,
+    path: String,
+    /// Host or device name as the user gave it.
+    target: Option<String>,
+    bytes: u64,
+    total: u64,
+    /// Resumes file by file rather than from the start of the folder.
+    batch: bool,
+    updated_at_ms: u64,
+}
+
+/// Sent once at startup when a previous session left jobs unfinished.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InterruptedJobsPayload {
+    jobs: Vec<InterruptedJob>,
+}
+
+#[derive(Debug)]
+struct RunningJob {
+    id: String,
+    saved_at: Instant,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct JobJournal {
+    /// Durable ids of the sends of this session, by send id.
+    running: HashMap<u64, RunningJob>,
+    /// Jobs found on disk at startup that have not been resumed or
+    /// discarded yet.
+    interrupted: Vec<JournaledJob>,
+}
+
+impl From<&JournaledJob> for InterruptedJob {
+    fn from(job: &JournaledJob) -> Self {
+        Self {
+            id: job.id.clone(),
+            path: job.request.path.clone(),
+            target: job
+                .request
+                .host
+                .clone()
+                .or_else(|| job.request.device.clone()),
+            bytes: job.bytes,
+            total: job.total,
+            batch: job.batch_id.is_some(),
+            updated_at_ms: job.updated_at_ms,
+        }
+    }
+}
+
+fn load(app: &AppHandle) -> Result<StoredJobs, String> {
+    read_json(&data_file(app, JOBS_FILE)?)
+}
+
+fn save(app: &AppHandle, stored: &StoredJobs) -> Result<(), String> {
+    write_json(&data_file(app, JOBS_FILE)?, stored)
+}
+
+fn update_job(
+    app: &AppHandle,
+    job_id: &str,
+    change: impl FnOnce(&mut JournaledJob),
+) -> Result<(), String> {
+    let mut stored = load(app)?;
+    if let Some(job) = stored.jobs.iter_mut().find(|job| job.id == job_id) {
+        change(job);
+        job.updated_at_ms = now_ms();
+        save(app, &stored)?;
+    }
+    Ok(())
+}
+
+/// Overwrites and deletes the journal and forgets interrupted jobs, whose
+/// requests name the sent paths.
+pub(crate) fn wipe(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut journal = state
+        .job_journal
+        .lock()
+        .map_err(|_| "failed to lock job journal".to_string())?;
+    journal.interrupted.clear();
+    secure_remove(&data_file(app, JOBS_FILE)?)
+}
+
+/// Run once at startup: every job on disk belongs to a session that ended
+/// before the job did. The UI is told and offers to resume them.
+pub(crate) fn recover(app: &AppHandle) {
+    let jobs = match load(app) {
+        Ok(stored) => stored.jobs,
+        Err(err) => {
+            emit_system_log(app, format!("failed to read unfinished jobs: {err}"));
+            return;
+        }
+    };
+    if jobs.is_empty() {
+        return;
+    }
+    let payload = InterruptedJobsPayload {
+        jobs: jobs.iter().map(InterruptedJob::from).collect(),
+    };
+    if let Ok(mut journal) = app.state::<AppState>().job_journal.lock() {
+        journal.interrupted = jobs;
+    }
+    emit_app_event(app, AppEvent::JobsInterrupted(payload));
+}
+
+/// Journals a send as it starts. A resumed job keeps its id.
+pub(crate) fn begin(app: &AppHandle, send_id: u64, request: &SendRequest) {
+    let state = app.state::<AppState>();
+    let Ok(mut journal) = state.job_journal.lock() else {
+        return;
+    };
+    let id = request
+        .job_id
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    journal.interrupted.retain(|job| job.id != id);
+    journal.running.insert(
+        send_id,
+        RunningJob {
+            id: id.clone(),
+            saved_at: Instant::now(),
+        },
+    );
+    let now = now_ms();
+    let result = load(app).and_then(|mut stored| {
+        let previous = stored.jobs.iter().position(|job| job.id == id);
+        let batch_id = previous.and_then(|index| stored.jobs.remove(index).batch_id);
+        stored.jobs.push(JournaledJob {
+            id,
+            request: request.clone(),
+            batch_id,
+            bytes: 0,
+            total: 0,
+            started_at_ms: now,
+            updated_at_ms: now,
+        });
+        save(app, &stored)
+    });
+    if let Err(err) = result {
+        emit_system_log(app, format!("failed to journal send: {err}"));
+    }
+}
+
+/// Remembers the batch a folder send is recorded in.
+pub(crate) fn attach_batch(app: &AppHandle, send_id: u64, batch_id: &str) {
+    let state = app.state::<AppState>();
+    let Ok(journal) = state.job_journal.lock() else {
+        return;
+    };
+    let Some(job) = journal.running.get(&send_id) else {
+        return;
+    };
+    if let Err(err) = update_job(app, &job.id, |job| {
+        job.batch_id = Some(batch_id.to_string());
+    }) {
+        emit_system_log(app, format!("failed to journal send: {err}"));
+    }
+}
+
+/// Saves how far a send got, at most every `PROGRESS_SAVE_INTERVAL`.
+pub(crate) fn progress(app: &AppHandle, send_id: u64, bytes: u64, total: u64) {
+    let state = app.state::<AppState>();
+    let Ok(mut journal) = state.job_journal.lock() else {
+        return;
+    };
+    let Some(job) = journal.running.get_mut(&send_id) else {
+        return;
+    };
+    if job.saved_at.elapsed() < PROGRESS_SAVE_INTERVAL {
+        return;
+    }
+    job.saved_at = Instant::now();
+    if let Err(err) = update_job(app, &job.id, |job| {
+        job.bytes = bytes;
+        job.total = total;
+    }) {
+        emit_system_log(app, format!("failed to journal send: {err}"));
+    }
+}
+
+/// A send that ended, well or not, while the app ran is not resumed at
+/// the next start; failed folder sends keep their batch for
+/// `resume_batch`.
+pub(crate) fn finish(app: &AppHandle, send_id: u64) {
+    let state = app.state::<AppState>();
+    let Ok(mut journal) = state.job_journal.lock() else {
+        return;
+    };
+    let Some(job) = journal.running.remove(&send_id) else {
+        return;
+    };
+    let result = load(app).and_then(|mut stored| {
+        stored.jobs.retain(|stored| stored.id != job.id);
+        save(app, &stored)
+    });
+    if let Err(err) = result {
+        emit_system_log(app, format!("failed to journal send: {err}"));
+    }
+}
+
+/// Takes the interrupted jobs with the given ids, or all of them.
+fn take_interrupted(
+    state: &AppState,
+    job_ids: Option<Vec<String>>,
+) -> Result<Vec<JournaledJob>, String> {
+    let mut journal = state
+        .job_journal
+        .lock()
+        .map_err(|_| "failed to lock job journal".to_string())?;
+    let (taken, kept) = journal
+        .interrupted
+        .drain(..)
+        .partition(|job| job_ids.as_ref().is_none_or(|ids| ids.contains(&job.id)));
+    journal.interrupted = kept;
+    Ok(taken)
+}
+
+async fn resume_job(app: AppHandle, job: JournaledJob) {
+    let path = job.request.path.clone();
+    let result = match job.batch_id {
+        Some(batch_id) if send_batches::exists(&app, &batch_id) => {
+            send_batches::resume(app.clone(), batch_id, Some(job.id)).await
+        }
+        _ => {
+            let mut request = job.request;
+            request.job_id = Some(job.id);
+            run_send_request(app.clone(), request).await
+        }
+    };
+    if let Err(err) = result {
+        emit_system_log(&app, format!("failed to resume send {path}: {err}"));
+    }
+}
+
+#[tauri::command]
+pub(crate) fn list_interrupted_jobs(state: State<AppState>) -> Result<Vec<InterruptedJob>, String> {
+    let journal = state
+        .job_journal
+        .lock()
+        .map_err(|_| "failed to lock job journal".to_string())?;
+    Ok(journal
+        .interrupted
+        .iter()
+        .map(InterruptedJob::from)
+        .collect())
+}
+
+/// Starts the interrupted jobs with the given ids, or all of them, in the
+/// background; each reports through the activity feed like any send.
+#[tauri::command]
+pub(crate) fn resume_interrupted_jobs(
+    app: AppHandle,
+    job_ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let jobs = take_interrupted(app.state::<AppState>().inner(), job_ids)?;
+    let count = jobs.len();
+    for job in jobs {
+        tauri::async_runtime::spawn(resume_job(app.clone(), job));
+    }
+    Ok(count)
+}
+
+/// Forgets the interrupted jobs with the given ids, or all of them. Their
+/// send batches stay for `resume_batch` until discarded as well.
+#[tauri::command]
+pub(crate) fn discard_interrupted_jobs(
+    app: AppHandle,
+    job_ids: Option<Vec<String>>,
+) -> Result<usize, String> {
+    let jobs = take_interrupted(app.state::<AppState>().inner(), job_ids)?;
+    let mut stored = load(&app)?;
+    stored
+        .jobs
+        .retain(|stored| !jobs.iter().any(|job| job.id == stored.id));
+    save(&app, &stored)?;
+    Ok(jobs.len())
+}
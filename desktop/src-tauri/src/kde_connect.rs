@@ -0,0 +1,722 @@
+//! Sending to phones that run KDE Connect (or desktops running GSConnect).
+//! Only pairing and the share plugin are spoken: JSON packets over a TLS
+//! link, with each file streamed over its own TLS connection that the
+//! phone opens. Phones are found by broadcasting this device's identity,
+//! which makes every KDE Connect install on the network connect back.
+
+use crate::delivery::SourceFile;
+use crate::dry_run::{sha256_hex, CaptureVerifier};
+use crate::identity::{load_or_create_identity, DeviceType};
+use crate::onboarding::write_private;
+use crate::parser::{clean_text, DeviceSource, DiscoverDevice};
+use crate::settings::{load_settings, update_settings};
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::zero_copy::{format_bytes, Output};
+use crate::{kill_switch, scheduler, AppState, CommandResult};
+use rcgen::{CertificateParams, DnType, KeyPair};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, ServerConfig, ServerConnection, StreamOwned};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, TcpListener, TcpStream, UdpSocket};
+use std::ops::RangeInclusive;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+/// Sends whose `device` starts with this go to the paired KDE Connect
+/// device with the id that follows.
+pub(crate) const DEVICE_PREFIX: &str = "kdeconnect:";
+const PAIRED_DEVICES_FILE: &str = "kde-connect-devices.json";
+const CERT_FILE: &str = "kde-connect-cert.pem";
+const KEY_FILE: &str = "kde-connect-key.pem";
+/// Version 7 exchanges identities before TLS; phones that speak 8 fall
+/// back to it.
+const PROTOCOL_VERSION: u32 = 7;
+const UDP_PORT: u16 = 1716;
+const LINK_PORTS: RangeInclusive<u16> = 1716..=1764;
+const PAYLOAD_PORTS: RangeInclusive<u16> = 1739..=1764;
+const BROADCAST_INTERVAL: Duration = Duration::from_secs(1);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long the phone shows its pairing prompt.
+const PAIR_TIMEOUT: Duration = Duration::from_secs(30);
+const PAYLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Identities carry capability lists, which grow with each plugin.
+const MAX_PACKET_BYTES: u64 = 512 * 1024;
+const CHUNK_BYTES: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct KdeConnectSettings {
+    /// Look for KDE Connect devices during discovery. Off by default, as
+    /// each discovery announces this device to every KDE Connect install
+    /// on the network.
+    pub(crate) enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PairedDevice {
+    id: String,
+    name: String,
+    /// SHA-256 of the device's certificate, pinned when pairing.
+    fingerprint: String,
+    /// Where it was last reached; asked directly in case broadcasts are
+    /// filtered.
+    #[serde(default)]
+    last_address: Option<String>,
+    paired_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KdeConnectTarget {
+    pub(crate) device_id: String,
+}
+
+impl KdeConnectTarget {
+    pub(crate) fn label(&self) -> String {
+        format!("{DEVICE_PREFIX}{}", self.device_id)
+    }
+
+    pub(crate) fn validated(self) -> Result<Self, String> {
+        let device_id = self.device_id.trim().to_string();
+        if device_id.is_empty() {
+            return Err("KDE Connect device id is required".to_string());
+        }
+        Ok(Self { device_id })
+    }
+}
+
+/// This device as KDE Connect sees it.
+struct LocalIdentity {
+    /// KDE Connect only accepts letters, digits and `_` in ids.
+    id: String,
+    name: String,
+    device_type: &'static str,
+    cert: CertificateDer<'static>,
+    key: PrivateKeyDer<'static>,
+}
+
+/// A KDE Connect device that connected back after an announcement.
+struct Announced {
+    id: String,
+    name: String,
+    address: IpAddr,
+    port: u16,
+    protocol_version: u32,
+}
+
+/// The TLS link to a device, over which packets are exchanged.
+struct Link {
+    stream: BufReader<StreamOwned<ClientConnection, TcpStream>>,
+    device: Announced,
+    fingerprint: String,
+}
+
+/// KDE Connect requires the certificate's common name to be the device id,
+/// so it gets its own certificate rather than the listener's.
+fn local_identity(app: &AppHandle) -> Result<LocalIdentity, String> {
+    let identity = load_or_create_identity(app)?;
+    let id = identity.device_id.replace('-', "_");
+    let cert_path = data_file(app, CERT_FILE)?;
+    let key_path = data_file(app, KEY_FILE)?;
+    if !cert_path.is_file() || !key_path.is_file() {
+        let mut params = CertificateParams::default();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, id.as_str());
+        params
+            .distinguished_name
+            .push(DnType::OrganizationName, "KDE");
+        params
+            .distinguished_name
+            .push(DnType::OrganizationalUnitName, "Kde connect");
+        let key_pair = KeyPair::generate()
+            .map_err(|err| format!("failed to generate KDE Connect key: {err}"))?;
+        let cert = params
+            .self_signed(&key_pair)
+            .map_err(|err| format!("failed to generate KDE Connect certificate: {err}"))?;
+        write_private(&key_path, &key_pair.serialize_pem())?;
+        fs::write(&cert_path, cert.pem())
+            .map_err(|err| format!("failed to write {}: {err}", cert_path.display()))?;
+    }
+    let cert = CertificateDer::from_pem_file(&cert_path)
+        .map_err(|err| format!("failed to read {}: {err}", cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .map_err(|err| format!("failed to read {}: {err}", key_path.display()))?;
+    Ok(LocalIdentity {
+        id,
+        name: identity.effective_name(),
+        device_type: match identity.device_type {
+            DeviceType::Laptop => "laptop",
+            DeviceType::Desktop | DeviceType::Server => "desktop",
+        },
+        cert,
+        key,
+    })
+}
+
+fn packet(kind: &str, body: Value) -> Value {
+    json!({ "id": now_ms(), "type": kind, "body": body })
+}
+
+fn identity_packet(local: &LocalIdentity, tcp_port: u16) -> Value {
+    packet(
+        "kdeconnect.identity",
+        json!({
+            "deviceId": local.id,
+            "deviceName": local.name,
+            "deviceType": local.device_type,
+            "protocolVersion": PROTOCOL_VERSION,
+            "incomingCapabilities": [],
+            "outgoingCapabilities": ["kdeconnect.share.request"],
+            "tcpPort": tcp_port,
+        }),
+    )
+}
+
+fn write_packet(writer: &mut impl Write, packet: &Value) -> Result<(), String> {
+    writer
+        .write_all(format!("{packet}\n").as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|err| format!("failed to send KDE Connect packet: {err}"))
+}
+
+fn read_packet(reader: &mut impl BufRead) -> Result<Value, String> {
+    let mut line = String::new();
+    let read = reader
+        .take(MAX_PACKET_BYTES)
+        .read_line(&mut line)
+        .map_err(|err| format!("failed to read KDE Connect packet: {err}"))?;
+    if read == 0 {
+        return Err("the device closed the connection".to_string());
+    }
+    serde_json::from_str(&line).map_err(|err| format!("invalid KDE Connect packet: {err}"))
+}
+
+fn packet_type(packet: &Value) -> &str {
+    packet
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+fn parse_identity(packet: &Value, address: IpAddr, fallback_port: u16) -> Option<Announced> {
+    if packet_type(packet) != "kdeconnect.identity" {
+        return None;
+    }
+    let body = packet.get("body")?;
+    let id = clean_text(body.get("deviceId")?.as_str()?.trim());
+    if id.is_empty() {
+        return None;
+    }
+    let name = body
+        .get("deviceName")
+        .and_then(Value::as_str)
+        .map(clean_text)
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| id.clone());
+    Some(Announced {
+        id,
+        name,
+        address,
+        port: body
+            .get("tcpPort")
+            .and_then(Value::as_u64)
+            .and_then(|port| u16::try_from(port).ok())
+            .unwrap_or(fallback_port),
+        protocol_version: body
+            .get("protocolVersion")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or(0),
+    })
+}
+
+fn bind_in(ports: RangeInclusive<u16>) -> Result<TcpListener, String> {
+    let (first, last) = (*ports.start(), *ports.end());
+    ports
+        .into_iter()
+        .find_map(|port| TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).ok())
+        .ok_or_else(|| format!("no free port in {first}-{last} for KDE Connect"))
+}
+
+/// Announces this device, also straight to `direct` when given, and hands
+/// each device that connects back to `on_device` until it returns `Some`
+/// or `timeout` passes.
+fn reach_devices<T>(
+    local: &LocalIdentity,
+    direct: Option<IpAddr>,
+    timeout: Duration,
+    mut on_device: impl FnMut(TcpStream, Announced) -> Option<T>,
+) -> Result<Option<T>, String> {
+    let listener = bind_in(LINK_PORTS)?;
+    let port = listener
+        .local_addr()
+        .map_err(|err| format!("failed to read KDE Connect port: {err}"))?
+        .port();
+    listener
+        .set_nonblocking(true)
+        .map_err(|err| format!("failed to configure KDE Connect port: {err}"))?;
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| socket.set_broadcast(true).map(|_| socket))
+        .map_err(|err| format!("failed to open KDE Connect broadcast socket: {err}"))?;
+    let announcement = format!("{}\n", identity_packet(local, port));
+
+    let deadline = Instant::now() + timeout;
+    let mut next_broadcast = Instant::now();
+    while Instant::now() < deadline {
+        if Instant::now() >= next_broadcast {
+            let _ = socket.send_to(announcement.as_bytes(), (Ipv4Addr::BROADCAST, UDP_PORT));
+            if let Some(address) = direct {
+                let _ = socket.send_to(announcement.as_bytes(), (address, UDP_PORT));
+            }
+            next_broadcast += BROADCAST_INTERVAL;
+        }
+        let (stream, peer) = match listener.accept() {
+            Ok(accepted) => accepted,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(err) => return Err(format!("failed to accept KDE Connect device: {err}")),
+        };
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(CONNECT_TIMEOUT));
+        let _ = stream.set_write_timeout(Some(CONNECT_TIMEOUT));
+        // The device waits for the TLS handshake after its identity, so
+        // nothing past the first line is buffered away.
+        let identity = read_packet(&mut BufReader::new(&stream))
+            .ok()
+            .and_then(|packet| parse_identity(&packet, peer.ip(), peer.port()));
+        if let Some(device) = identity.filter(|device| device.id != local.id) {
+            if let Some(found) = on_device(stream, device) {
+                return Ok(Some(found));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The device that connected back acts as the TLS server and asks for this
+/// device's certificate. Certificates are self-signed on both sides; the
+/// caller pins the returned fingerprint.
+fn client_tls(
+    local: &LocalIdentity,
+    mut stream: TcpStream,
+    address: IpAddr,
+) -> Result<(StreamOwned<ClientConnection, TcpStream>, String), String> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("failed to configure TLS: {err}"))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(CaptureVerifier(provider)))
+        .with_client_auth_cert(vec![local.cert.clone()], local.key.clone_key())
+        .map_err(|err| format!("failed to configure TLS: {err}"))?;
+    let mut connection = ClientConnection::new(Arc::new(config), ServerName::from(address))
+        .map_err(|err| format!("failed to start TLS handshake: {err}"))?;
+    while connection.is_handshaking() {
+        connection
+            .complete_io(&mut stream)
+            .map_err(|err| format!("TLS handshake with the device failed: {err}"))?;
+    }
+    let fingerprint = connection
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .map(|cert| sha256_hex(cert.as_ref()))
+        .ok_or_else(|| "the device did not present a TLS certificate".to_string())?;
+    Ok((StreamOwned::new(connection, stream), fingerprint))
+}
+
+fn open_link(
+    local: &LocalIdentity,
+    device_id: &str,
+    direct: Option<IpAddr>,
+) -> Result<Link, String> {
+    reach_devices(local, direct, CONNECT_TIMEOUT, |stream, device| {
+        (device.id == device_id).then(|| {
+            let (stream, fingerprint) = client_tls(local, stream, device.address)?;
+            Ok(Link {
+                stream: BufReader::new(stream),
+                device,
+                fingerprint,
+            })
+        })
+    })?
+    .ok_or_else(|| {
+        format!("KDE Connect device {device_id} did not answer; check that it is on this network")
+    })?
+}
+
+fn load_paired(app: &AppHandle) -> Result<Vec<PairedDevice>, String> {
+    read_json(&data_file(app, PAIRED_DEVICES_FILE)?)
+}
+
+fn save_paired(app: &AppHandle, devices: &[PairedDevice]) -> Result<(), String> {
+    write_json(&data_file(app, PAIRED_DEVICES_FILE)?, devices)
+}
+
+fn find_paired(app: &AppHandle, device_id: &str) -> Result<Option<PairedDevice>, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .kde_connect_lock
+        .lock()
+        .map_err(|_| "failed to lock KDE Connect devices".to_string())?;
+    Ok(load_paired(app)?
+        .into_iter()
+        .find(|device| device.id == device_id))
+}
+
+fn store_paired(app: &AppHandle, device: PairedDevice) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .kde_connect_lock
+        .lock()
+        .map_err(|_| "failed to lock KDE Connect devices".to_string())?;
+    let mut devices = load_paired(app)?;
+    devices.retain(|known| known.id != device.id);
+    devices.push(device);
+    save_paired(app, &devices)
+}
+
+/// Asks the device to pair and waits for it to be accepted there.
+fn pair(app: &AppHandle, device_id: &str) -> Result<PairedDevice, String> {
+    let local = local_identity(app)?;
+    let mut link = open_link(&local, device_id, None)?;
+    write_packet(
+        link.stream.get_mut(),
+        &packet("kdeconnect.pair", json!({ "pair": true })),
+    )?;
+    let deadline = Instant::now() + PAIR_TIMEOUT;
+    let _ = link
+        .stream
+        .get_ref()
+        .sock
+        .set_read_timeout(Some(PAIR_TIMEOUT));
+    loop {
+        let packet = read_packet(&mut link.stream).map_err(|err| {
+            if Instant::now() >= deadline {
+                "pairing was not accepted on the device in time".to_string()
+            } else {
+                err
+            }
+        })?;
+        if packet_type(&packet) != "kdeconnect.pair" {
+            continue;
+        }
+        if packet["body"]["pair"].as_bool() != Some(true) {
+            return Err("pairing was declined on the device".to_string());
+        }
+        break;
+    }
+    let device = PairedDevice {
+        id: link.device.id,
+        name: link.device.name,
+        fingerprint: link.fingerprint,
+        last_address: Some(link.device.address.to_string()),
+        paired_at_ms: now_ms(),
+    };
+    store_paired(app, device.clone())?;
+    Ok(device)
+}
+
+fn accept_payload(
+    app: &AppHandle,
+    listener: &TcpListener,
+    from: IpAddr,
+) -> Result<TcpStream, String> {
+    listener
+        .set_nonblocking(true)
+        .map_err(|err| format!("failed to configure payload port: {err}"))?;
+    let deadline = Instant::now() + PAYLOAD_TIMEOUT;
+    while Instant::now() < deadline {
+        if app
+            .state::<AppState>()
+            .network_halted
+            .load(Ordering::SeqCst)
+        {
+            return Err("send aborted by the kill-switch".to_string());
+        }
+        match listener.accept() {
+            Ok((stream, peer)) if peer.ip() == from => {
+                let _ = stream.set_nonblocking(false);
+                let _ = stream.set_read_timeout(Some(PAYLOAD_TIMEOUT));
+                let _ = stream.set_write_timeout(Some(PAYLOAD_TIMEOUT));
+                return Ok(stream);
+            }
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(err) => return Err(format!("failed to accept payload connection: {err}")),
+        }
+    }
+    Err("the device did not fetch the file".to_string())
+}
+
+/// One send to a paired device: the open link plus what every share
+/// request in it announces.
+struct ShareSession<'a> {
+    app: &'a AppHandle,
+    local: LocalIdentity,
+    link: Link,
+    output: Output<'a>,
+    count: usize,
+    total_bytes: u64,
+    send_id: u64,
+}
+
+/// Offers one file to the share plugin and streams it once the device
+/// connects for it. On the payload connection this device is the TLS
+/// server, and the device checks the certificate it pinned.
+fn share(session: &mut ShareSession, file: &SourceFile) -> Result<(), String> {
+    let app = session.app;
+    let send_id = session.send_id;
+    let state = app.state::<AppState>();
+    let listener = bind_in(PAYLOAD_PORTS)?;
+    let port = listener
+        .local_addr()
+        .map_err(|err| format!("failed to read payload port: {err}"))?
+        .port();
+    // The share plugin saves everything into one folder.
+    let filename = file
+        .relative_path
+        .rsplit('/')
+        .next()
+        .unwrap_or(&file.relative_path);
+    let mut request = packet(
+        "kdeconnect.share.request",
+        json!({
+            "filename": filename,
+            "numberOfFiles": session.count,
+            "totalPayloadSize": session.total_bytes,
+        }),
+    );
+    request["payloadSize"] = json!(file.size);
+    request["payloadTransferInfo"] = json!({ "port": port });
+    let mut source = File::open(&file.path).map_err(|err| format!("failed to open file: {err}"))?;
+    write_packet(session.link.stream.get_mut(), &request)?;
+
+    let stream = accept_payload(app, &listener, session.link.device.address)?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| format!("failed to configure TLS: {err}"))?
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![session.local.cert.clone()],
+            session.local.key.clone_key(),
+        )
+        .map_err(|err| format!("failed to configure TLS: {err}"))?;
+    let connection = ServerConnection::new(Arc::new(config))
+        .map_err(|err| format!("failed to start TLS handshake: {err}"))?;
+    let mut tls = StreamOwned::new(connection, stream);
+
+    let started = Instant::now();
+    let mut last_progress = Instant::now() - PROGRESS_INTERVAL;
+    let mut buffer = vec![0; CHUNK_BYTES];
+    let mut sent = 0u64;
+    loop {
+        scheduler::wait_while_paused(app, send_id);
+        if state.network_halted.load(Ordering::SeqCst) {
+            return Err("send aborted by the kill-switch".to_string());
+        }
+        let read = source
+            .read(&mut buffer)
+            .map_err(|err| format!("failed to read file: {err}"))?;
+        if read == 0 {
+            break;
+        }
+        tls.write_all(&buffer[..read])
+            .map_err(|err| format!("failed to send file: {err}"))?;
+        sent += read as u64;
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            session
+                .output
+                .progress(&file.relative_path, sent, file.size, started);
+        }
+    }
+    // The device reads exactly the announced size.
+    if sent != file.size {
+        return Err("file changed while sending".to_string());
+    }
+    tls.conn.send_close_notify();
+    let _ = tls.flush();
+    session
+        .output
+        .progress(&file.relative_path, sent, file.size, started);
+    session.output.write("\n".to_string());
+    Ok(())
+}
+
+/// Shares `files` with a paired device, which saves them into its download
+/// folder. Folders arrive flattened, as the share plugin has no paths.
+pub(crate) fn send(
+    app: &AppHandle,
+    target: &KdeConnectTarget,
+    files: &[SourceFile],
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let paired = find_paired(app, &target.device_id)?.ok_or_else(|| {
+        format!(
+            "pair with KDE Connect device {} before sending to it",
+            target.device_id
+        )
+    })?;
+    let local = local_identity(app)?;
+    let direct = paired
+        .last_address
+        .as_deref()
+        .and_then(|address| address.parse().ok());
+    let link = open_link(&local, &paired.id, direct)?;
+    if link.fingerprint != paired.fingerprint {
+        return Err(format!(
+            "{} presented a different certificate than when it was paired; pair with it again",
+            paired.name
+        ));
+    }
+    let address = link.device.address.to_string();
+    if paired.last_address.as_deref() != Some(address.as_str()) {
+        let _ = store_paired(
+            app,
+            PairedDevice {
+                last_address: Some(address),
+                ..paired.clone()
+            },
+        );
+    }
+
+    let started = Instant::now();
+    let bytes: u64 = files.iter().map(|file| file.size).sum();
+    let mut session = ShareSession {
+        app,
+        local,
+        link,
+        output: Output::new(app, send_id),
+        count: files.len(),
+        total_bytes: bytes,
+        send_id,
+    };
+    for (index, file) in files.iter().enumerate() {
+        session.output.start_file(index, files);
+        share(&mut session, file)
+            .map_err(|err| format!("failed to send {}: {err}", file.relative_path))?;
+    }
+
+    let mut output = session.output;
+    let seconds = started.elapsed().as_secs_f64().max(0.001);
+    output.write(format!(
+        "[send] done: files={} bytes={}\n[send] kdeconnect: {} in {seconds:.2}s ({}/s)\n",
+        files.len(),
+        format_bytes(bytes as f64),
+        paired.name,
+        format_bytes(bytes as f64 / seconds)
+    ));
+    Ok(output.finish())
+}
+
+/// KDE Connect devices that answer an announcement within `timeout`, when
+/// enabled in settings.
+pub(crate) fn discover(app: &AppHandle, timeout: Duration) -> Vec<DiscoverDevice> {
+    let enabled = load_settings(app)
+        .map(|settings| settings.kde_connect.enabled)
+        .unwrap_or(false);
+    let Some(local) = enabled.then(|| local_identity(app).ok()).flatten() else {
+        return Vec::new();
+    };
+    let mut devices: Vec<DiscoverDevice> = Vec::new();
+    let _ = reach_devices(&local, None, timeout, |_, device| {
+        if !devices
+            .iter()
+            .any(|known| known.id.as_deref() == Some(device.id.as_str()))
+        {
+            devices.push(DiscoverDevice {
+                name: device.name,
+                host: device.address.to_string(),
+                port: device.port,
+                addresses: vec![device.address.to_string()],
+                id: Some(device.id),
+                fingerprint: None,
+                version: None,
+                protocol_version: Some(device.protocol_version),
+                tls: Some(true),
+                pair_code_required: None,
+                capabilities: Vec::new(),
+                appearance: Default::default(),
+                source: DeviceSource::KdeConnect,
+                routes: Vec::new(),
+                proximity: None,
+                directory: None,
+            });
+        }
+        None::<()>
+    });
+    devices
+}
+
+#[tauri::command]
+pub(crate) fn list_kde_connect_devices(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<PairedDevice>, String> {
+    let _guard = state
+        .kde_connect_lock
+        .lock()
+        .map_err(|_| "failed to lock KDE Connect devices".to_string())?;
+    load_paired(&app)
+}
+
+/// Resolves once pairing is accepted or declined on the device, or after
+/// its prompt times out.
+#[tauri::command]
+pub(crate) async fn pair_kde_connect_device(
+    app: AppHandle,
+    device_id: String,
+) -> Result<PairedDevice, String> {
+    kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+    tauri::async_runtime::spawn_blocking(move || pair(&app, device_id.trim()))
+        .await
+        .map_err(|err| format!("failed to join KDE Connect task: {err}"))?
+}
+
+/// Forgets the device here; it stays paired on its side until unpaired
+/// there too.
+#[tauri::command]
+pub(crate) fn unpair_kde_connect_device(
+    app: AppHandle,
+    state: State<AppState>,
+    device_id: String,
+) -> Result<bool, String> {
+    let _guard = state
+        .kde_connect_lock
+        .lock()
+        .map_err(|_| "failed to lock KDE Connect devices".to_string())?;
+    let mut devices = load_paired(&app)?;
+    let before = devices.len();
+    devices.retain(|device| device.id != device_id.trim());
+    if devices.len() == before {
+        return Ok(false);
+    }
+    save_paired(&app, &devices)?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub(crate) fn set_kde_connect_settings(
+    app: AppHandle,
+    kde_connect: KdeConnectSettings,
+) -> Result<KdeConnectSettings, String> {
+    update_settings(&app, |settings| {
+        settings.kde_connect = kde_connect;
+        Ok(())
+    })
+    .map(|settings| settings.kde_connect)
+}
@@ -0,0 +1,69 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::{
+    cleanup_child_processes, directory_beacon, inspect_listen_state, listener_lock, process_pool,
+    AppState,
+};
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, State};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AllStoppedPayload {
+    listener_stopped: bool,
+    sends_aborted: usize,
+    processes_terminated: usize,
+}
+
+/// Fails while the kill-switch is engaged so no new network activity starts
+/// until the user explicitly resumes.
+pub(crate) fn ensure_network_allowed(state: &AppState) -> Result<(), String> {
+    if state.network_halted.load(Ordering::SeqCst) {
+        return Err(
+            "network activity is stopped by the kill-switch; resume it before continuing"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Immediately terminates the listener and every tracked CLI child (sends
+/// and discovery), then blocks new network activity. Background work (the
+/// directory heartbeat, the org directory refresh) checks the same flag
+/// and holds off until the user resumes.
+#[tauri::command]
+pub(crate) fn panic_stop(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<AllStoppedPayload, String> {
+    state.network_halted.store(true, Ordering::SeqCst);
+
+    let listener_stopped = inspect_listen_state(&state)?.running;
+    let sends_aborted = state
+        .active_sends
+        .lock()
+        .map(|guard| guard.len())
+        .unwrap_or(0);
+    let processes_terminated = process_pool::len(state.inner());
+
+    cleanup_child_processes(state.inner());
+    listener_lock::terminate_adopted(&app)?;
+    listener_lock::clear(&app);
+    if let Ok(mut profile_guard) = state.listen_network_profile.lock() {
+        *profile_guard = None;
+    }
+    directory_beacon::stop(&app);
+
+    let payload = AllStoppedPayload {
+        listener_stopped,
+        sends_aborted,
+        processes_terminated,
+    };
+    emit_app_event(&app, AppEvent::AllStopped(payload.clone()));
+    Ok(payload)
+}
+
+#[tauri::command]
+pub(crate) fn resume_network_activity(state: State<AppState>) -> bool {
+    state.network_halted.swap(false, Ordering::SeqCst)
+}
@@ -0,0 +1,154 @@
+use crate::AppState;
+use if_addrs::get_if_addrs;
+use serde::Serialize;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::time::Instant;
+
+/// What the running listener was started with and has done since; reset on
+/// every `start_listen`.
+#[derive(Debug, Default)]
+pub(crate) struct ListenSession {
+    started: Option<Instant>,
+    port: u16,
+    /// The one address the listener accepts connections on, if limited.
+    bind: Option<IpAddr>,
+    tls: bool,
+    pair_code_required: bool,
+    transfers_received: u64,
+    bytes_received: u64,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListenSessionReport {
+    uptime_secs: Option<u64>,
+    /// `ip:port` per local interface the CLI listens on; all of them unless
+    /// the interface settings bind it to one.
+    bound_addresses: Vec<String>,
+    tls: bool,
+    pair_code_required: bool,
+    transfers_received: u64,
+    bytes_received: u64,
+    last_error: Option<String>,
+}
+
+pub(crate) fn begin(
+    state: &AppState,
+    port: u16,
+    bind: Option<IpAddr>,
+    tls: bool,
+    pair_code_required: bool,
+) {
+    if let Ok(mut session) = state.listen_session.lock() {
+        *session = ListenSession {
+            started: Some(Instant::now()),
+            port,
+            bind,
+            tls,
+            pair_code_required,
+            ..ListenSession::default()
+        };
+    }
+}
+
+pub(crate) fn set_pair_code_required(state: &AppState, required: bool) {
+    if let Ok(mut session) = state.listen_session.lock() {
+        session.pair_code_required = required;
+    }
+}
+
+pub(crate) fn uses_tls(state: &AppState) -> bool {
+    state.listen_session.lock().is_ok_and(|session| session.tls)
+}
+
+pub(crate) fn requires_pair_code(state: &AppState) -> bool {
+    state
+        .listen_session
+        .lock()
+        .is_ok_and(|session| session.pair_code_required)
+}
+
+/// The one address the listener accepts connections on, if limited.
+pub(crate) fn bind_address(state: &AppState) -> Option<IpAddr> {
+    state
+        .listen_session
+        .lock()
+        .ok()
+        .and_then(|session| session.bind)
+}
+
+/// Where this machine reaches its own listener: loopback, or the bound
+/// address when the listener is limited to one.
+pub(crate) fn local_address(state: &AppState, port: u16) -> SocketAddr {
+    let bind = state
+        .listen_session
+        .lock()
+        .ok()
+        .and_then(|session| session.bind);
+    SocketAddr::new(bind.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)), port)
+}
+
+/// Stops the uptime clock; the counters stay readable until the next start.
+pub(crate) fn end(state: &AppState) {
+    if let Ok(mut session) = state.listen_session.lock() {
+        session.started = None;
+    }
+}
+
+pub(crate) fn note_saved(state: &AppState, path: &Path) {
+    let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    if let Ok(mut session) = state.listen_session.lock() {
+        session.transfers_received += 1;
+        session.bytes_received += size;
+    }
+}
+
+/// Keeps stderr output and failed receives as the last error.
+pub(crate) fn note_error(state: &AppState, error: &str) {
+    let error = error.trim();
+    if error.is_empty() {
+        return;
+    }
+    if let Ok(mut session) = state.listen_session.lock() {
+        session.last_error = Some(error.to_string());
+    }
+}
+
+fn bound_addresses(port: u16, bind: Option<IpAddr>) -> Vec<String> {
+    if let Some(bind) = bind {
+        return vec![SocketAddr::new(bind, port).to_string()];
+    }
+    let Ok(ifaces) = get_if_addrs() else {
+        return Vec::new();
+    };
+    let mut addresses: Vec<String> = ifaces
+        .into_iter()
+        .map(|iface| SocketAddr::new(iface.ip(), port).to_string())
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+pub(crate) fn report(state: &AppState, running: bool) -> ListenSessionReport {
+    let Ok(session) = state.listen_session.lock() else {
+        return ListenSessionReport::default();
+    };
+    let started = session.started.filter(|_| running);
+    ListenSessionReport {
+        uptime_secs: started.map(|started| started.elapsed().as_secs()),
+        bound_addresses: if started.is_some() {
+            bound_addresses(session.port, session.bind)
+        } else {
+            Vec::new()
+        },
+        tls: session.tls,
+        pair_code_required: session.pair_code_required,
+        transfers_received: session.transfers_received,
+        bytes_received: session.bytes_received,
+        last_error: session.last_error.clone(),
+    }
+}
@@ -0,0 +1,227 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::storage::{now_ms, read_json, shared_file, write_json};
+use crate::{resolve_cli_runtime, terminate_process_tree, AppState, CliRuntime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+/// Device-wide: the port belongs to the machine, not to a profile.
+const LISTENER_LOCK_FILE: &str = "listener-lock.json";
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenerLock {
+    pid: u32,
+    port: u16,
+    /// The app process that started the listener.
+    app_pid: u32,
+    started_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OrphanedListener {
+    pub(crate) pid: u32,
+    pub(crate) port: u16,
+    started_at_ms: u64,
+    command_line: String,
+    /// Left running on request; `stop_listen` still terminates it.
+    adopted: bool,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum OrphanAction {
+    Terminate,
+    Adopt,
+}
+
+/// Remembers the running listener so the next launch can find it if this
+/// one crashes before stopping it.
+pub(crate) fn record(app: &AppHandle, pid: u32, port: u16) -> Result<(), String> {
+    let lock = ListenerLock {
+        pid,
+        port,
+        app_pid: std::process::id(),
+        started_at_ms: now_ms(),
+    };
+    write_json(&shared_file(app, LISTENER_LOCK_FILE)?, &Some(lock))
+}
+
+pub(crate) fn clear(app: &AppHandle) {
+    if let Ok(path) = shared_file(app, LISTENER_LOCK_FILE) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn command_line(pid: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .args(["-o", "command=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty()).then_some(line)
+}
+
+#[cfg(target_os = "windows")]
+fn command_line(pid: u32) -> Option<String> {
+    let mut command = Command::new("powershell");
+    command
+        .args([
+            "-NoProfile",
+            "-Command",
+            &format!("(Get-CimInstance Win32_Process -Filter \"ProcessId={pid}\").CommandLine"),
+        ])
+        .stderr(Stdio::null());
+    crate::configure_cli_command_for_platform(&mut command);
+    let output = command.output().ok()?;
+    let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !line.is_empty()).then_some(line)
+}
+
+/// A recycled pid must never be killed: the process has to run our CLI
+/// binary or script with the `listen` subcommand.
+fn is_our_listener(command_line: &str) -> bool {
+    let cli_name = match resolve_cli_runtime() {
+        Ok(CliRuntime::Binary(path)) | Ok(CliRuntime::NodeScript(path)) => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+        Err(_) => None,
+    };
+    cli_name.is_some_and(|name| command_line.contains(&name))
+        && command_line
+            .split_whitespace()
+            .any(|arg| arg.trim_matches('"') == "listen")
+}
+
+fn inspect_lock(app: &AppHandle) -> Result<Option<OrphanedListener>, String> {
+    let path = shared_file(app, LISTENER_LOCK_FILE)?;
+    let Some(lock) = read_json::<Option<ListenerLock>>(&path).unwrap_or(None) else {
+        return Ok(None);
+    };
+    let orphan = (lock.app_pid != std::process::id())
+        .then(|| command_line(lock.pid))
+        .flatten()
+        .filter(|command_line| is_our_listener(command_line))
+        .map(|command_line| OrphanedListener {
+            pid: lock.pid,
+            port: lock.port,
+            started_at_ms: lock.started_at_ms,
+            command_line,
+            adopted: false,
+        });
+    if orphan.is_none() {
+        // The old listener is gone, or its pid now belongs to something else.
+        let _ = fs::remove_file(&path);
+    }
+    Ok(orphan)
+}
+
+/// Run once at startup: a listener left behind by a crashed session keeps
+/// its port, so the next start fails with "address in use". The UI is told
+/// about it and asks whether to terminate or keep it.
+pub(crate) fn detect_orphan(app: &AppHandle) {
+    let Ok(Some(orphan)) = inspect_lock(app) else {
+        return;
+    };
+    if let Ok(mut guard) = app.state::<AppState>().orphaned_listener.lock() {
+        *guard = Some(orphan.clone());
+    }
+    emit_app_event(app, AppEvent::OrphanedListener(orphan));
+}
+
+/// Terminates an adopted listener; used when the user stops listening.
+pub(crate) fn terminate_adopted(app: &AppHandle) -> Result<bool, String> {
+    let state = app.state::<AppState>();
+    let adopted = state
+        .orphaned_listener
+        .lock()
+        .map_err(|_| "failed to lock orphaned listener state".to_string())?
+        .as_ref()
+        .filter(|orphan| orphan.adopted)
+        .map(|orphan| orphan.pid);
+    match adopted {
+        Some(pid) => {
+            terminate(app, pid)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+fn terminate(app: &AppHandle, pid: u32) -> Result<(), String> {
+    // Checked again: the pid may have been recycled since startup.
+    if command_line(pid).is_some_and(|command_line| is_our_listener(&command_line)) {
+        terminate_process_tree(pid);
+        let deadline = Instant::now() + TERMINATE_TIMEOUT;
+        while command_line(pid).is_some() {
+            if Instant::now() >= deadline {
+                return Err(format!("listener {pid} did not exit"));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+    if let Ok(mut guard) = app.state::<AppState>().orphaned_listener.lock() {
+        *guard = None;
+    }
+    clear(app);
+    Ok(())
+}
+
+/// The listener left over from a previous session, if one was found.
+#[tauri::command]
+pub(crate) fn get_orphaned_listener(
+    state: State<AppState>,
+) -> Result<Option<OrphanedListener>, String> {
+    state
+        .orphaned_listener
+        .lock()
+        .map(|guard| guard.clone())
+        .map_err(|_| "failed to lock orphaned listener state".to_string())
+}
+
+/// `terminate` kills the leftover listener and frees its port; `adopt`
+/// leaves it receiving (without progress or confirm prompts, since its
+/// pipes died with the old session) until the user stops listening.
+#[tauri::command]
+pub(crate) fn resolve_orphaned_listener(
+    app: AppHandle,
+    action: OrphanAction,
+) -> Result<Option<OrphanedListener>, String> {
+    let state = app.state::<AppState>();
+    let orphan = state
+        .orphaned_listener
+        .lock()
+        .map_err(|_| "failed to lock orphaned listener state".to_string())?
+        .clone()
+        .ok_or_else(|| "no orphaned listener was found".to_string())?;
+    match action {
+        OrphanAction::Terminate => {
+            terminate(&app, orphan.pid)?;
+            Ok(None)
+        }
+        OrphanAction::Adopt => {
+            let adopted = OrphanedListener {
+                adopted: true,
+                ..orphan
+            };
+            let lock = ListenerLock {
+                pid: adopted.pid,
+                port: adopted.port,
+                app_pid: std::process::id(),
+                started_at_ms: adopted.started_at_ms,
+            };
+            write_json(&shared_file(&app, LISTENER_LOCK_FILE)?, &Some(lock))?;
+            if let Ok(mut guard) = state.orphaned_listener.lock() {
+                *guard = Some(adopted.clone());
+            }
+            Ok(Some(adopted))
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use crate::storage::now_ms;
+use crate::{emit_system_log, listen_session, log_queue, AppState};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Release builds only accept `simulate_load` with this set.
+const DEV_COMMANDS_ENV: &str = "LOCAL_SENT_DEV_COMMANDS";
+const MAX_PEERS: u32 = 64;
+const MAX_FILES_PER_PEER: u32 = 1000;
+const MAX_FILE_SIZE: u64 = 256 * 1024 * 1024;
+const IO_TIMEOUT: Duration = Duration::from_secs(60);
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Distinct error messages kept in the report.
+const MAX_ERROR_KINDS: usize = 20;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LoadReport {
+    peers: u32,
+    files: u32,
+    size: u64,
+    transfers: u64,
+    succeeded: u64,
+    failed: u64,
+    bytes: u64,
+    elapsed_ms: u64,
+    bytes_per_sec: f64,
+    /// Per successful transfer, from connect to ack.
+    median_ms: u64,
+    slowest_ms: u64,
+    /// Failure messages, e.g. rate-limit rejections, by how often they
+    /// occurred.
+    errors: BTreeMap<String, u64>,
+    /// Activity lines the log queue dropped during the run.
+    log_lines_dropped: u64,
+}
+
+struct Outcome {
+    elapsed: Duration,
+    result: Result<Option<PathBuf>, String>,
+}
+
+/// Fills `buf` with the bytes of a fake file at `offset`. Every byte only
+/// depends on `seed` and its position, so a resumed send can regenerate
+/// any range.
+pub(crate) fn fill(seed: u64, offset: u64, buf: &mut [u8]) {
+    for (index, byte) in buf.iter_mut().enumerate() {
+        let position = offset + index as u64;
+        // splitmix64 of the 8-byte word the position falls in.
+        let mut word = seed ^ (position / 8).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        word = (word ^ (word >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        word = (word ^ (word >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        word ^= word >> 31;
+        *byte = word.to_le_bytes()[(position % 8) as usize];
+    }
+}
+
+fn sha256_of(seed: u64, size: u64) -> String {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut offset = 0;
+    while offset < size {
+        let len = (size - offset).min(CHUNK_SIZE as u64) as usize;
+        fill(seed, offset, &mut buf[..len]);
+        hasher.update(&buf[..len]);
+        offset += len as u64;
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn read_reply(reader: &mut impl BufRead, expected: &str) -> Result<Value, String> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|err| format!("failed to read {expected}: {err}"))?;
+    let reply: Value = serde_json::from_str(line.trim())
+        .map_err(|_| format!("protocol error: expected {expected} message"))?;
+    if reply["type"] != expected {
+        return Err(format!("protocol error: expected {expected} message"));
+    }
+    if reply["ok"] != true {
+        return Err(reply["message"]
+            .as_str()
+            .unwrap_or("receiver rejected transfer")
+            .to_string());
+    }
+    Ok(reply)
+}
+
+/// One file over protocol version 1, the way `sendFile` in
+/// `src/transfer.ts` sends it. Returns where the receiver saved it.
+fn send_fake_file(
+    address: SocketAddr,
+    relative_path: &str,
+    size: u64,
+    seed: u64,
+    pair_code: Option<&str>,
+) -> Result<Option<PathBuf>, String> {
+    let header = json!({
+        "type": "header",
+        "version": 1,
+        "relativePath": relative_path,
+        "fileSize": size,
+        "sha256": sha256_of(seed, size),
+        "pairCode": pair_code,
+    });
+    let mut stream = TcpStream::connect_timeout(&address, IO_TIMEOUT)
+        .map_err(|err| format!("failed to connect to the listener: {err}"))?;
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(IO_TIMEOUT));
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .map_err(|err| format!("failed to clone socket: {err}"))?,
+    );
+    writeln!(stream, "{header}").map_err(|err| format!("failed to send header: {err}"))?;
+
+    let ready = read_reply(&mut reader, "ready")?;
+    let mut offset = ready["offset"].as_u64().unwrap_or(0).min(size);
+    let mut buf = vec![0; CHUNK_SIZE];
+    while offset < size {
+        let len = (size - offset).min(CHUNK_SIZE as u64) as usize;
+        fill(seed, offset, &mut buf[..len]);
+        stream
+            .write_all(&buf[..len])
+            .map_err(|err| format!("failed to send data: {err}"))?;
+        offset += len as u64;
+    }
+    let _ = stream.shutdown(Shutdown::Write);
+
+    let ack = read_reply(&mut reader, "ack")?;
+    Ok(ack["savedPath"].as_str().map(PathBuf::from))
+}
+
+/// Removes the received copies and the folders the run created for them.
+fn clean_up(saved: &[PathBuf], run_dir: &str) {
+    for path in saved {
+        let _ = fs::remove_file(path);
+    }
+    for path in saved {
+        // `<run>/peer-N/file-M.bin`: the peer folder, then the run folder.
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if fs::remove_dir(current).is_err() || current.file_name() == Some(run_dir.as_ref()) {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+}
+
+fn median(mut values: Vec<u64>) -> u64 {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied().unwrap_or(0)
+}
+
+fn run(
+    app: &AppHandle,
+    port: u16,
+    peers: u32,
+    files: u32,
+    size: u64,
+    pair_code: Option<String>,
+) -> LoadReport {
+    let run_dir = format!("simulate-load-{}", now_ms());
+    let address = listen_session::local_address(app.state::<AppState>().inner(), port);
+    let dropped_before = log_queue::dropped_total(app.state::<AppState>().inner());
+    let started = Instant::now();
+
+    let workers: Vec<_> = (0..peers)
+        .map(|peer| {
+            let run_dir = run_dir.clone();
+            let pair_code = pair_code.clone();
+            thread::spawn(move || {
+                (0..files)
+                    .map(|file| {
+                        let path = format!("{run_dir}/peer-{peer}/file-{file}.bin");
+                        let seed = (u64::from(peer) << 32) | u64::from(file);
+                        let attempt = Instant::now();
+                        let result =
+                            send_fake_file(address, &path, size, seed, pair_code.as_deref());
+                        Outcome {
+                            elapsed: attempt.elapsed(),
+                            result,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let outcomes: Vec<Outcome> = workers
+        .into_iter()
+        .flat_map(|worker| worker.join().unwrap_or_default())
+        .collect();
+    let elapsed = started.elapsed();
+
+    let mut report = LoadReport {
+        peers,
+        files,
+        size,
+        transfers: outcomes.len() as u64,
+        elapsed_ms: elapsed.as_millis() as u64,
+        ..LoadReport::default()
+    };
+    let mut durations = Vec::new();
+    let mut saved = Vec::new();
+    for outcome in outcomes {
+        match outcome.result {
+            Ok(path) => {
+                report.succeeded += 1;
+                report.bytes += size;
+                durations.push(outcome.elapsed.as_millis() as u64);
+                saved.extend(path);
+            }
+            Err(err) => {
+                report.failed += 1;
+                if report.errors.len() < MAX_ERROR_KINDS || report.errors.contains_key(&err) {
+                    *report.errors.entry(err).or_default() += 1;
+                }
+            }
+        }
+    }
+    report.slowest_ms = durations.iter().copied().max().unwrap_or(0);
+    report.median_ms = median(durations);
+    report.bytes_per_sec = report.bytes as f64 / elapsed.as_secs_f64().max(0.001);
+    report.log_lines_dropped =
+        log_queue::dropped_total(app.state::<AppState>().inner()).saturating_sub(dropped_before);
+    clean_up(&saved, &run_dir);
+    report
+}
+
+/// Developer command, not offered in the UI: `peers` in-process fake peers
+/// each send `files` files of `size` bytes to the running listener at once,
+/// to see how the queue, throttling and event batching hold up. The
+/// listener treats them like real transfers (confirmations, rate limits,
+/// history); only the received copies are removed afterwards. All peers
+/// connect from this machine, so per-sender limits see them as one sender.
+#[tauri::command]
+pub(crate) async fn simulate_load(
+    app: AppHandle,
+    peers: u32,
+    files: u32,
+    size: u64,
+    pair_code: Option<String>,
+) -> Result<LoadReport, String> {
+    if !cfg!(debug_assertions) && std::env::var_os(DEV_COMMANDS_ENV).is_none() {
+        return Err(format!(
+            "simulate_load is a developer command; set {DEV_COMMANDS_ENV}=1 to use it"
+        ));
+    }
+    if peers == 0 || peers > MAX_PEERS {
+        return Err(format!("peers must be between 1 and {MAX_PEERS}"));
+    }
+    if files == 0 || files > MAX_FILES_PER_PEER {
+        return Err(format!("files must be between 1 and {MAX_FILES_PER_PEER}"));
+    }
+    if size > MAX_FILE_SIZE {
+        return Err(format!("size must be at most {MAX_FILE_SIZE} bytes"));
+    }
+    let state = app.state::<AppState>();
+    let port = state
+        .listen_port
+        .lock()
+        .map_err(|_| "failed to lock listen port state".to_string())?
+        .ok_or_else(|| "start the listener before simulating load".to_string())?;
+    if listen_session::uses_tls(state.inner()) {
+        return Err("simulate_load needs a listener without TLS".to_string());
+    }
+
+    emit_system_log(
+        &app,
+        format!("simulating load: {peers} peer(s) x {files} file(s) of {size} bytes"),
+    );
+    let task_app = app.clone();
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        run(&task_app, port, peers, files, size, pair_code)
+    })
+    .await
+    .map_err(|err| format!("failed to join load simulation: {err}"))?;
+    emit_system_log(
+        &app,
+        format!(
+            "load simulation done: {}/{} succeeded in {} ms",
+            report.succeeded, report.transfers, report.elapsed_ms
+        ),
+    );
+    Ok(report)
+}
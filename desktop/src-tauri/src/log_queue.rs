@@ -0,0 +1,106 @@
+use crate::activity::ActivityEntry;
+use crate::events::{emit_app_event, AppEvent};
+use crate::AppState;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const LOG_QUEUE_CAPACITY: usize = 1000;
+const LOG_EMIT_BATCH: usize = 64;
+const LOG_EMIT_PAUSE: Duration = Duration::from_millis(50);
+
+/// Bounded buffer between CLI output readers and the event bus. When a child
+/// produces output faster than the webview can take it, the oldest entries
+/// are dropped and counted instead of piling up unbounded emits.
+#[derive(Default)]
+pub(crate) struct LogQueue {
+    inner: Mutex<LogQueueInner>,
+    ready: Condvar,
+    consumer_started: AtomicBool,
+}
+
+#[derive(Default)]
+struct LogQueueInner {
+    entries: VecDeque<ActivityEntry>,
+    dropped_pending: u64,
+    dropped_total: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LogDroppedPayload {
+    dropped: u64,
+    dropped_total: u64,
+}
+
+pub(crate) fn enqueue_activity(app: &AppHandle, entry: ActivityEntry) {
+    let state = app.state::<AppState>();
+    let queue = &state.log_queue;
+    if !queue.consumer_started.swap(true, Ordering::SeqCst) {
+        let consumer_app = app.clone();
+        thread::spawn(move || run_consumer(consumer_app));
+    }
+
+    let Ok(mut inner) = queue.inner.lock() else {
+        return;
+    };
+    if inner.entries.len() >= LOG_QUEUE_CAPACITY {
+        inner.entries.pop_front();
+        inner.dropped_pending += 1;
+        inner.dropped_total += 1;
+    }
+    inner.entries.push_back(entry);
+    drop(inner);
+    queue.ready.notify_one();
+}
+
+/// Entries dropped since the app started.
+pub(crate) fn dropped_total(state: &AppState) -> u64 {
+    state
+        .log_queue
+        .inner
+        .lock()
+        .map(|inner| inner.dropped_total)
+        .unwrap_or(0)
+}
+
+fn run_consumer(app: AppHandle) {
+    loop {
+        let (batch, dropped) = {
+            let state = app.state::<AppState>();
+            let queue = &state.log_queue;
+            let Ok(mut inner) = queue.inner.lock() else {
+                return;
+            };
+            while inner.entries.is_empty() && inner.dropped_pending == 0 {
+                inner = match queue.ready.wait(inner) {
+                    Ok(guard) => guard,
+                    Err(_) => return,
+                };
+            }
+            let take = inner.entries.len().min(LOG_EMIT_BATCH);
+            let batch: Vec<ActivityEntry> = inner.entries.drain(..take).collect();
+            let dropped = (inner.dropped_pending > 0).then(|| LogDroppedPayload {
+                dropped: inner.dropped_pending,
+                dropped_total: inner.dropped_total,
+            });
+            inner.dropped_pending = 0;
+            (batch, dropped)
+        };
+
+        if let Some(payload) = dropped {
+            emit_app_event(&app, AppEvent::LogDropped(payload));
+        }
+        let full_batch = batch.len() == LOG_EMIT_BATCH;
+        for entry in batch {
+            emit_app_event(&app, AppEvent::Activity(entry));
+        }
+        if full_batch {
+            thread::sleep(LOG_EMIT_PAUSE);
+        }
+    }
+}
@@ -1,25 +1,166 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod activity;
+mod activity_search;
+mod app_data;
+mod app_exit;
+mod app_profiles;
+mod archive;
+mod audit;
+mod auto_print;
+mod battery;
+mod benchmark;
+mod broadcast_ping;
+mod cli_control;
+#[cfg(test)]
+mod cli_harness;
+mod cli_ipc;
+mod clipboard_sync;
+mod dedup;
+mod delivery;
+mod device_cache;
+mod device_groups;
+mod directory_beacon;
+mod discovery_dedup;
+mod dns_sd;
+mod dry_run;
+mod events;
+mod hashing;
+mod history;
+mod history_retention;
+mod host_resolution;
+mod identity;
+mod interfaces;
+mod job_journal;
+mod kde_connect;
+mod kill_switch;
+mod listen_session;
+mod listener_lock;
+mod load_simulation;
+mod log_queue;
+mod managed_config;
+mod media_import;
+mod messages;
+mod network;
+mod network_profiles;
+mod network_watch;
+mod onboarding;
+mod org_directory;
+mod outbound_policy;
+mod overlay;
+mod parser;
+mod paths;
+mod pickers;
+mod ports;
+mod post_receive;
+mod preflight;
+mod presets;
+mod process_pool;
+mod progress_stats;
+mod proximity;
+mod quick_share;
+mod quiet_hours;
+mod reachability;
+mod receive_folder;
+mod remote_shares;
+mod s3;
+mod scheduler;
+mod screen_capture;
+mod secrets;
+mod send_batches;
+mod send_diagnosis;
+mod send_multi;
+mod settings;
+mod sftp;
+mod shared_folders;
+mod smb;
+mod stealth;
+mod storage;
+mod stream_bridge;
+mod syncthing;
+mod taskbar;
+mod telemetry;
+mod throttle;
+mod usage_stats;
+mod watchdog;
+mod webdav;
+mod wsl_paths;
+mod zero_copy;
+
+use activity::{emit_activity, ActivityEntry};
+use audit::{record_audit, AuditAction, AuditChain};
+use events::{emit_app_event, AppEvent, EventLog};
 use if_addrs::get_if_addrs;
+use log_queue::LogQueue;
+use network_profiles::NetworkProfile;
+use parser::{parse_discovered_devices, DiscoverDevice};
 use rfd::FileDialog;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
 use std::path::PathBuf;
 use std::process::{Child, ChildStdin, Command, Stdio};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::thread;
+use std::time::{Duration, Instant};
 use tauri::path::BaseDirectory;
-use tauri::{AppHandle, Emitter, Manager, RunEvent, State, WindowEvent};
+use tauri::{AppHandle, Manager, RunEvent, State, WindowEvent};
 
 #[derive(Default)]
 struct AppState {
     listen_child: Mutex<Option<Child>>,
     listen_stdin: Mutex<Option<ChildStdin>>,
+    listen_control: Mutex<cli_control::ControlChannel>,
+    orphaned_listener: Mutex<Option<listener_lock::OrphanedListener>>,
     listen_port: Mutex<Option<u16>>,
-    active_child_pids: Mutex<HashSet<u32>>,
+    process_pool: Mutex<process_pool::ProcessPool>,
     shutdown_cleanup_started: AtomicBool,
+    history_lock: Mutex<()>,
+    presets_lock: Mutex<()>,
+    device_groups_lock: Mutex<()>,
+    delivery_targets_lock: Mutex<()>,
+    kde_connect_lock: Mutex<()>,
+    shared_folders_lock: Mutex<()>,
+    messages_lock: Mutex<()>,
+    clipboard_sync: Mutex<clipboard_sync::ClipboardSyncState>,
+    activity_index: Mutex<activity_search::ActivityIndex>,
+    telemetry_lock: Mutex<()>,
+    app_exit: app_exit::ExitState,
+    /// Folders shared by the running listener's `start_listen` request.
+    listen_shares: Mutex<Vec<shared_folders::SharedFolder>>,
+    identity_lock: Mutex<()>,
+    settings_lock: Mutex<()>,
+    network_profiles_lock: Mutex<()>,
+    listen_network_profile: Mutex<Option<NetworkProfile>>,
+    next_send_id: AtomicU64,
+    active_sends: Mutex<HashSet<u64>>,
+    event_log: Mutex<EventLog>,
+    log_queue: LogQueue,
+    network_halted: AtomicBool,
+    pending_confirms: Mutex<HashMap<u64, TransferConfirmRequestPayload>>,
+    audit_chain: Mutex<AuditChain>,
+    receive_throttle: Mutex<throttle::ReceiveThrottle>,
+    received_index_lock: Mutex<()>,
+    incoming_files: Mutex<HashMap<String, dedup::IncomingFile>>,
+    listen_output_dir: Mutex<Option<PathBuf>>,
+    stream_bridge: Mutex<Option<stream_bridge::StreamBridge>>,
+    scheduler: Mutex<scheduler::Scheduler>,
+    preflight_generation: AtomicU64,
+    progress: Mutex<taskbar::ProgressAggregator>,
+    quiet_hours: Mutex<quiet_hours::QuietHoursState>,
+    listen_session: Mutex<listen_session::ListenSession>,
+    network_watch: Mutex<network_watch::NetworkWatchState>,
+    battery: Mutex<battery::BatteryState>,
+    device_cache: Mutex<device_cache::DeviceCache>,
+    send_batches: Mutex<send_batches::ActiveBatches>,
+    job_journal: Mutex<job_journal::JobJournal>,
+    org_directory_lock: Mutex<()>,
+    directory_beacon: Mutex<directory_beacon::BeaconState>,
+    /// Folder name of the active app profile; `None` is the default one.
+    active_profile: Mutex<Option<String>>,
 }
 
 impl Drop for AppState {
@@ -44,20 +185,17 @@ enum CliRuntime {
 struct ListenStatePayload {
     running: bool,
     pid: Option<u32>,
+    discoverable: bool,
+    quiet_hours_active: bool,
+    clipboard_sync: clipboard_sync::ClipboardSyncStatus,
+    #[serde(flatten)]
+    session: listen_session::ListenSessionReport,
 }
 
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ListenLogPayload {
-    stream: String,
-    line: String,
-}
-
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct SendOutputPayload {
-    stream: String,
-    chunk: String,
+struct VisibilityPayload {
+    discoverable: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -69,15 +207,6 @@ struct CommandResult {
     stderr: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct DiscoverDevice {
-    name: String,
-    host: String,
-    port: u16,
-    addresses: Vec<String>,
-}
-
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ListenRequest {
@@ -87,11 +216,34 @@ struct ListenRequest {
     pair_code: Option<String>,
     tls_cert_path: Option<String>,
     tls_key_path: Option<String>,
+    /// Keep the sender's permission bits and modification times.
+    preserve_metadata: Option<bool>,
+    /// Combined receive rate limit in KiB/s; `None` or 0 is unlimited.
+    max_receive_kbps: Option<u32>,
+    /// Folders peers may browse and pull from; none unless listed here.
+    shares: Option<Vec<shared_folders::SharedFolder>>,
+    /// Keep the port closed except to senders that first knock with the
+    /// knock secret (see `stealth`), so port scans do not find it.
+    stealth: Option<bool>,
+}
+
+/// Changes for a running listener; unset fields are left alone.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ListenConfigUpdate {
+    output_dir: Option<String>,
+    /// An empty code turns pairing off.
+    pair_code: Option<String>,
+    /// Overrides the network profile's auto-accept until the listener stops.
+    auto_accept: Option<bool>,
+    /// KiB/s; 0 lifts the limit.
+    max_receive_kbps: Option<u32>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct SendRequest {
+    #[serde(default)]
     path: String,
     host: Option<String>,
     port: u16,
@@ -103,6 +255,40 @@ struct SendRequest {
     tls_fingerprint: Option<String>,
     tls_tofu: Option<bool>,
     tls_known_hosts: Option<String>,
+    /// Knock with the knock secret before connecting, for receivers that
+    /// announce the `knock` capability.
+    knock: Option<bool>,
+    /// Parallel connections for large files; `None` lets the CLI pick.
+    streams: Option<u8>,
+    /// Overrides the zero-copy setting for this send, e.g. to benchmark.
+    zero_copy: Option<bool>,
+    /// Check discovery, connectivity and TLS without transferring payload.
+    dry_run: Option<bool>,
+    /// Low-priority sends pause while a high-priority send runs.
+    priority: Option<scheduler::JobPriority>,
+    /// `skip`, `follow` or `preserve` symlinks inside a sent folder.
+    symlinks: Option<String>,
+    /// Recreate hard-linked files as hard links on the receiver.
+    hardlinks: Option<bool>,
+    /// `nfc` or `keep` the Unicode form of sent file names.
+    unicode: Option<String>,
+    /// `rename`, `skip` or `fail` on paths that differ only in case or
+    /// Unicode form.
+    name_collisions: Option<String>,
+    /// Stage the whole folder on the receiver and move it into place only
+    /// once every file verified.
+    atomic: Option<bool>,
+    /// Kept in both sides' history and searchable with
+    /// `get_transfer_history`.
+    note: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Set by `resume_batch` for the rest of an interrupted batch.
+    #[serde(skip)]
+    resume: Option<send_batches::Resume>,
+    /// Durable id of a journaled job this send resumes.
+    #[serde(skip)]
+    job_id: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -112,15 +298,6 @@ struct TransferConfirmResponse {
     accept: bool,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CliConfirmRequest {
-    id: u64,
-    from: Option<String>,
-    path: String,
-    size: u64,
-}
-
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct TransferConfirmRequestPayload {
@@ -128,32 +305,12 @@ struct TransferConfirmRequestPayload {
     from: String,
     path: String,
     size: u64,
-}
-
-fn register_active_pid_with_state(state: &AppState, pid: u32) {
-    if pid == 0 {
-        return;
-    }
-    if let Ok(mut guard) = state.active_child_pids.lock() {
-        guard.insert(pid);
-    }
-}
-
-fn unregister_active_pid_with_state(state: &AppState, pid: u32) {
-    if pid == 0 {
-        return;
-    }
-    if let Ok(mut guard) = state.active_child_pids.lock() {
-        guard.remove(&pid);
-    }
-}
-
-fn register_active_pid(app: &AppHandle, pid: u32) {
-    register_active_pid_with_state(app.state::<AppState>().inner(), pid);
-}
-
-fn unregister_active_pid(app: &AppHandle, pid: u32) {
-    unregister_active_pid_with_state(app.state::<AppState>().inner(), pid);
+    sha256: Option<String>,
+    /// Already-received file with identical content; accepting keeps both.
+    duplicate_of: Option<String>,
+    streaming: bool,
+    /// An unpaired Quick Share sender; always left to the user.
+    quick_share: bool,
 }
 
 #[cfg(target_os = "windows")]
@@ -255,46 +412,35 @@ fn cleanup_child_processes(state: &AppState) {
     if let Ok(mut stdin_guard) = state.listen_stdin.lock() {
         *stdin_guard = None;
     }
+    cli_control::reset(state);
     if let Ok(mut listen_port) = state.listen_port.lock() {
         *listen_port = None;
     }
 
-    let mut tracked_pids = if let Ok(mut guard) = state.active_child_pids.lock() {
-        let pids = guard.iter().copied().collect::<Vec<u32>>();
-        guard.clear();
-        pids
-    } else {
-        Vec::new()
-    };
-
-    if let Some(pid) = listen_pid {
-        tracked_pids.retain(|item| *item != pid);
-    }
-
-    for pid in tracked_pids {
-        terminate_process_tree(pid);
-    }
+    process_pool::terminate_all(state, listen_pid);
+    network_watch::stop_sleep_monitor(state);
 }
 
 fn emit_system_log(app: &AppHandle, line: impl Into<String>) {
-    let payload = ListenLogPayload {
-        stream: "system".to_string(),
-        line: line.into(),
-    };
-    let _ = app.emit("listen-log", payload);
+    emit_activity(app, ActivityEntry::System { line: line.into() });
 }
 
 fn cleanup_child_processes_from_app(app: &AppHandle) {
     let state = app.state::<AppState>();
-    if state.shutdown_cleanup_started.swap(true, Ordering::SeqCst) {
+    state.shutdown_cleanup_started.store(true, Ordering::SeqCst);
+    if !app_exit::claim_child_cleanup(state.inner()) {
         return;
     }
-    let tracked_count = state.active_child_pids.lock().map(|guard| guard.len()).unwrap_or(0);
+    let tracked_count = process_pool::len(state.inner());
     emit_system_log(
         app,
         format!("应用正在关闭，准备清理后台进程（{tracked_count}）..."),
     );
     cleanup_child_processes(state.inner());
+    if let Err(err) = listener_lock::terminate_adopted(app) {
+        emit_system_log(app, err);
+    }
+    listener_lock::clear(app);
     emit_system_log(app, "后台进程清理完成。");
 }
 
@@ -304,36 +450,356 @@ async fn discover(
     timeout_ms: Option<u64>,
     state: State<'_, AppState>,
 ) -> Result<Vec<DiscoverDevice>, String> {
+    kill_switch::ensure_network_allowed(state.inner())?;
     let timeout = timeout_ms.unwrap_or(3000).max(100);
-    let args = vec![
+    let devices = discover_devices(app, timeout).await?;
+    let _ = inspect_listen_state(&state)?;
+    Ok(devices)
+}
+
+/// Runs CLI discovery and drops entries that point back at this machine.
+async fn discover_devices(app: AppHandle, timeout: u64) -> Result<Vec<DiscoverDevice>, String> {
+    // This machine is filtered out below, where our listen port is known.
+    let mut args = vec![
         "discover".to_string(),
         "-t".to_string(),
         timeout.to_string(),
         "--json".to_string(),
+        "--include-self".to_string(),
     ];
-
-    let output = run_cli_capture_async(app, args).await?;
+    args.extend(dns_sd::discover_args(&app));
+    args.extend(broadcast_ping::discover_args(&app));
+    let self_id = identity::load_or_create_identity(&app)
+        .ok()
+        .map(|identity| identity.device_id);
+    let listen_port = app
+        .state::<AppState>()
+        .listen_port
+        .lock()
+        .ok()
+        .and_then(|port| *port);
+
+    let policy = settings::load_settings(&app)
+        .map(|settings| settings.interfaces)
+        .unwrap_or_default();
+    let overlay_app = app.clone();
+    let cache_app = app.clone();
+    let overlay =
+        tauri::async_runtime::spawn_blocking(move || overlay::overlay_devices(&overlay_app));
+    let beacon_app = app.clone();
+    let beacon = tauri::async_runtime::spawn_blocking(move || directory_beacon::peers(&beacon_app));
+    let kde_connect_app = app.clone();
+    let kde_connect = tauri::async_runtime::spawn_blocking(move || {
+        kde_connect::discover(&kde_connect_app, Duration::from_millis(timeout))
+    });
+    let output = run_cli_capture_async(app, args, process_pool::ChildKind::Discover).await?;
     if !output.success {
         return Err(render_cli_error("discover", &output));
     }
 
-    let stdout = output.stdout.trim();
-    if stdout.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let mut devices: Vec<DiscoverDevice> = serde_json::from_str(stdout)
-        .map_err(|err| format!("failed to parse discovery JSON: {err}"))?;
-
-    let _ = inspect_listen_state(&state)?;
+    let mut devices = parse_discovered_devices(&output.stdout)?;
     let local_addresses = local_address_set();
-    devices.retain(|device| !is_local_discovered_device(device, &local_addresses));
+    devices.retain(|device| {
+        !is_local_discovered_device(device, self_id.as_deref(), listen_port, &local_addresses)
+    });
+    overlay::merge(&mut devices, overlay.await.unwrap_or_default());
+    devices.extend(kde_connect.await.unwrap_or_default());
+    devices.extend(beacon.await.unwrap_or_default());
+    let mut devices = discovery_dedup::merge_duplicates(devices);
+    org_directory::merge(&cache_app, &mut devices);
+    interfaces::apply_policy(&mut devices, &policy);
+    if let Ok(managed) = managed_config::load() {
+        devices.retain(|device| managed.allows_peer(&device.host));
+    }
+    let devices = tauri::async_runtime::spawn_blocking(move || {
+        proximity::rank(&mut devices);
+        devices
+    })
+    .await
+    .map_err(|err| format!("failed to join proximity probe: {err}"))?;
+    device_cache::store(&cache_app, &devices);
 
     Ok(devices)
 }
 
 #[tauri::command]
 async fn send_file(app: AppHandle, request: SendRequest) -> Result<CommandResult, String> {
+    run_send_request(app, request).await
+}
+
+async fn run_send_request(
+    app: AppHandle,
+    mut request: SendRequest,
+) -> Result<CommandResult, String> {
+    kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+    if let Some(original) = wsl_paths::translate_send_path(&mut request) {
+        emit_system_log(&app, format!("{original} is opened as {}", request.path));
+    }
+    validate_send_paths(&mut request)?;
+    let batch_request = request.clone();
+    let delivery_target = delivery::find_target(&app, &request);
+    if delivery_target.is_some() && request.dry_run.unwrap_or(false) {
+        return Err("dry runs are not available for delivery targets".to_string());
+    }
+    if battery::limits_parallelism(&app) {
+        request.streams = Some(1);
+    }
+    if request.tls_tofu.unwrap_or(false)
+        && request
+            .tls_known_hosts
+            .as_ref()
+            .is_none_or(|path| path.trim().is_empty())
+        && app_profiles::active_profile_dir(app.state::<AppState>().inner()).is_some()
+    {
+        // Each non-default profile keeps its own trust store.
+        let known_hosts = storage::data_file(&app, app_profiles::KNOWN_HOSTS_FILE)?;
+        request.tls_known_hosts = Some(known_hosts.to_string_lossy().to_string());
+    }
+    if !request.dry_run.unwrap_or(false) {
+        resolve_send_host(&app, &mut request).await?;
+    }
+    // Before the loopback switch: the policy is about the receiver's address.
+    let policy_app = app.clone();
+    let policy_request = request.clone();
+    let policy_target = delivery_target.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        outbound_policy::enforce(&policy_app, &policy_request, policy_target.as_ref())
+    })
+    .await
+    .map_err(|err| format!("failed to join outbound policy check: {err}"))??;
+    if !request.dry_run.unwrap_or(false) {
+        use_loopback_for_own_address(&app, &mut request).await;
+    }
+    let mut args = build_send_args(&request)?;
+    let app_settings = settings::load_settings(&app).unwrap_or_default();
+    if app_settings.preserve_xattrs {
+        args.push("--xattrs".to_string());
+    }
+    enforce_network_profile_for_send(&app, &request).await?;
+    if request.dry_run.unwrap_or(false) {
+        return dry_run::run_dry_run(app, request, args).await;
+    }
+    let target = match &delivery_target {
+        Some(delivery_target) => delivery_target.label(),
+        None => request
+            .host
+            .clone()
+            .or_else(|| request.device.clone())
+            .map(|target| format!("{target}:{}", request.port))
+            .unwrap_or_else(|| format!("auto:{}", request.port)),
+    };
+    if let Some(fingerprint) = request
+        .tls_fingerprint
+        .as_ref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        audit::note_tls_fingerprint(&app, &target, fingerprint);
+    }
+    let send_id = begin_tracked_send(&app, &batch_request, target);
+    let started = Instant::now();
+    let output = if let Some(delivery_target) = delivery_target {
+        let delivery_app = app.clone();
+        let delivery_request = request.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            delivery::send(&delivery_app, &delivery_request, &delivery_target, send_id)
+        })
+        .await
+        .map_err(|err| format!("failed to join send task: {err}"))
+        .and_then(|result| result)
+    } else if zero_copy::takes_native_path(&app, &request, &app_settings) {
+        let native_app = app.clone();
+        let native_request = request.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            zero_copy::send(&native_app, &native_request, send_id)
+        })
+        .await
+        .map_err(|err| format!("failed to join send task: {err}"))
+        .and_then(|result| result)
+    } else {
+        send_batches::track(&app, send_id, &batch_request);
+        run_cli_capture_streaming_async(app.clone(), args, send_id, None).await
+    };
+    finish_tracked_send(&app, send_id, &request, &output, started);
+
+    let output = output?;
+    if !output.success {
+        return Err(render_cli_error("send", &output));
+    }
+    Ok(output)
+}
+
+/// Registers a send with the activity list, the job journal and the
+/// scheduler; `finish_tracked_send` undoes it once the send ends.
+pub(crate) fn begin_tracked_send(app: &AppHandle, request: &SendRequest, target: String) -> u64 {
+    let send_id = activity::begin_send(app, &request.path, target);
+    job_journal::begin(app, send_id, request);
+    scheduler::enqueue(app, send_id, request.priority.unwrap_or_default());
+    send_id
+}
+
+/// Ends a send started with `begin_tracked_send` and records its outcome
+/// in history and telemetry.
+pub(crate) fn finish_tracked_send(
+    app: &AppHandle,
+    send_id: u64,
+    request: &SendRequest,
+    output: &Result<CommandResult, String>,
+    started: Instant,
+) {
+    let success = output.as_ref().is_ok_and(|output| output.success);
+    scheduler::finish(app, send_id);
+    send_batches::finish(app, send_id, success);
+    job_journal::finish(app, send_id);
+    taskbar::finish_send(app, send_id);
+    activity::finish_send(app, send_id, success);
+    if let Err(err) =
+        history::record_send(app, request, output, started.elapsed().as_millis() as u64)
+    {
+        emit_system_log(app, format!("failed to record send history: {err}"));
+    }
+    telemetry::count_send(app, output);
+}
+
+/// TLS sends verified by certificate or trust-on-first-use must keep the
+/// host as typed, since both checks are tied to it.
+fn tls_checks_host(request: &SendRequest) -> bool {
+    let pinned = request
+        .tls_fingerprint
+        .as_ref()
+        .is_some_and(|value| !value.trim().is_empty());
+    request.tls.unwrap_or(false) && !pinned && !request.tls_insecure.unwrap_or(false)
+}
+
+/// Replaces a typed host name with the first of its addresses that accepts
+/// a connection.
+async fn resolve_send_host(app: &AppHandle, request: &mut SendRequest) -> Result<(), String> {
+    let Some(host) = request.host.clone().filter(|host| !host.trim().is_empty()) else {
+        return Ok(());
+    };
+    if tls_checks_host(request) {
+        return Ok(());
+    }
+    let port = request.port;
+    let lookup_host = host.clone();
+    let selected = tauri::async_runtime::spawn_blocking(move || {
+        host_resolution::select_address(&lookup_host, port)
+    })
+    .await
+    .map_err(|err| format!("failed to join host resolution: {err}"))?;
+    let Some(selected) = selected.transpose()? else {
+        return Ok(());
+    };
+    for (address, err) in &selected.failed {
+        emit_system_log(app, format!("{host}: {address} unreachable ({err})"));
+    }
+    emit_system_log(
+        app,
+        format!(
+            "{host} resolved via {} as {} to {}; sending to {}",
+            selected.resolution.via.label(),
+            selected.resolution.name,
+            selected
+                .resolution
+                .addresses
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            selected.address
+        ),
+    );
+    request.host = Some(selected.address.to_string());
+    Ok(())
+}
+
+const LOOPBACK_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Sends to a receiver on one of this machine's own addresses, such as
+/// another user's listener, over loopback when it accepts connections
+/// there; a receiver bound to one address only keeps the address given.
+async fn use_loopback_for_own_address(app: &AppHandle, request: &mut SendRequest) {
+    let Some(ip) = request
+        .host
+        .as_deref()
+        .and_then(|host| host.trim().parse::<IpAddr>().ok())
+    else {
+        return;
+    };
+    if ip.is_loopback()
+        || tls_checks_host(request)
+        || !local_address_set().contains(&ip.to_string())
+    {
+        return;
+    }
+    let loopback = match ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+    };
+    let address = SocketAddr::new(loopback, request.port);
+    let reachable = tauri::async_runtime::spawn_blocking(move || {
+        TcpStream::connect_timeout(&address, LOOPBACK_PROBE_TIMEOUT).is_ok()
+    })
+    .await
+    .unwrap_or(false);
+    if reachable {
+        emit_system_log(
+            app,
+            format!("{ip} is this machine; sending over {loopback}"),
+        );
+        request.host = Some(loopback.to_string());
+    }
+}
+
+async fn enforce_network_profile_for_send(
+    app: &AppHandle,
+    request: &SendRequest,
+) -> Result<(), String> {
+    let profile_app = app.clone();
+    let (_, profile) = tauri::async_runtime::spawn_blocking(move || {
+        network_profiles::resolve_active_profile(&profile_app)
+    })
+    .await
+    .map_err(|err| format!("failed to join network detection task: {err}"))??;
+    let Some(profile) = profile else {
+        return Ok(());
+    };
+
+    if profile.require_tls && !request.tls.unwrap_or(false) {
+        return Err(format!(
+            "network profile `{}` requires TLS for sending",
+            profile.label
+        ));
+    }
+    let has_pair_code = request
+        .pair_code
+        .as_ref()
+        .is_some_and(|value| !value.trim().is_empty());
+    if profile.require_pair_code && !has_pair_code {
+        return Err(format!(
+            "network profile `{}` requires a pair code for sending",
+            profile.label
+        ));
+    }
+    Ok(())
+}
+
+/// Canonicalizes and checks every path in `request` so bad input fails here
+/// with a structured error instead of inside the CLI child.
+fn validate_send_paths(request: &mut SendRequest) -> Result<(), String> {
+    let source = paths::validate_send_source(&request.path)?;
+    request.path = source.to_string_lossy().to_string();
+    if let Some(known_hosts) = request
+        .tls_known_hosts
+        .as_ref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        let known_hosts = paths::validate_output_file(known_hosts, "TLS known-hosts file")?;
+        request.tls_known_hosts = Some(known_hosts.to_string_lossy().to_string());
+    }
+    Ok(())
+}
+
+fn build_send_args(request: &SendRequest) -> Result<Vec<String>, String> {
     if request.path.trim().is_empty() {
         return Err("path is required".to_string());
     }
@@ -346,16 +812,24 @@ async fn send_file(app: AppHandle, request: SendRequest) -> Result<CommandResult
         }
     }
 
-    let mut args = vec!["send".to_string(), request.path];
+    let mut args = vec!["send".to_string(), request.path.clone()];
     args.push("--port".to_string());
     args.push(request.port.to_string());
 
-    if let Some(host) = request.host.filter(|value| !value.trim().is_empty()) {
+    if let Some(host) = request
+        .host
+        .clone()
+        .filter(|value| !value.trim().is_empty())
+    {
         args.push("--host".to_string());
         args.push(host);
     }
 
-    if let Some(device) = request.device.filter(|value| !value.trim().is_empty()) {
+    if let Some(device) = request
+        .device
+        .clone()
+        .filter(|value| !value.trim().is_empty())
+    {
         args.push("--device".to_string());
         args.push(device);
     }
@@ -363,7 +837,11 @@ async fn send_file(app: AppHandle, request: SendRequest) -> Result<CommandResult
     args.push("-t".to_string());
     args.push(request.timeout_ms.unwrap_or(3000).max(100).to_string());
 
-    if let Some(code) = request.pair_code.filter(|value| !value.trim().is_empty()) {
+    if let Some(code) = request
+        .pair_code
+        .clone()
+        .filter(|value| !value.trim().is_empty())
+    {
         args.push("--pair-code".to_string());
         args.push(code);
     }
@@ -376,6 +854,7 @@ async fn send_file(app: AppHandle, request: SendRequest) -> Result<CommandResult
     }
     if let Some(fingerprint) = request
         .tls_fingerprint
+        .clone()
         .filter(|value| !value.trim().is_empty())
     {
         args.push("--tls-fingerprint".to_string());
@@ -386,25 +865,97 @@ async fn send_file(app: AppHandle, request: SendRequest) -> Result<CommandResult
     }
     if let Some(known_hosts_path) = request
         .tls_known_hosts
+        .clone()
         .filter(|value| !value.trim().is_empty())
     {
         args.push("--tls-known-hosts".to_string());
         args.push(known_hosts_path);
     }
-
-    let output = run_cli_capture_streaming_async(app, args).await?;
-    if !output.success {
-        return Err(render_cli_error("send", &output));
+    if request.knock.unwrap_or(false) {
+        args.push("--knock".to_string());
     }
-    Ok(output)
+    if let Some(streams) = request.streams {
+        if !(1..=16).contains(&streams) {
+            return Err("streams must be in 1-16".to_string());
+        }
+        args.push("--streams".to_string());
+        args.push(streams.to_string());
+    }
+    if let Some(symlinks) = request
+        .symlinks
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        if !matches!(symlinks, "skip" | "follow" | "preserve") {
+            return Err("symlinks must be skip, follow or preserve".to_string());
+        }
+        args.push("--symlinks".to_string());
+        args.push(symlinks.to_string());
+    }
+    if request.hardlinks.unwrap_or(false) {
+        args.push("--hardlinks".to_string());
+    }
+    if let Some(unicode) = request
+        .unicode
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        if !matches!(unicode, "nfc" | "keep") {
+            return Err("unicode must be nfc or keep".to_string());
+        }
+        args.push("--unicode".to_string());
+        args.push(unicode.to_string());
+    }
+    if let Some(strategy) = request
+        .name_collisions
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        if !matches!(strategy, "rename" | "skip" | "fail") {
+            return Err("name collisions must be rename, skip or fail".to_string());
+        }
+        args.push("--name-collisions".to_string());
+        args.push(strategy.to_string());
+    }
+    if request.atomic.unwrap_or(false) {
+        args.push("--atomic".to_string());
+    }
+    if let Some(note) = request
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        args.push("--note".to_string());
+        args.push(note.to_string());
+    }
+    for tag in request
+        .tags
+        .iter()
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty())
+    {
+        args.push("--tag".to_string());
+        args.push(tag.to_string());
+    }
+    if let Some(resume) = &request.resume {
+        args.push("--skip-from".to_string());
+        args.push(resume.skip_from.to_string_lossy().to_string());
+    }
+
+    Ok(args)
 }
 
 #[tauri::command]
-fn start_listen(
+async fn start_listen(
     app: AppHandle,
-    state: State<AppState>,
+    state: State<'_, AppState>,
     request: ListenRequest,
 ) -> Result<ListenStatePayload, String> {
+    kill_switch::ensure_network_allowed(state.inner())?;
     if request.port == 0 {
         return Err("port must be in 1-65535".to_string());
     }
@@ -413,6 +964,49 @@ fn start_listen(
     {
         return Err("--tls-cert and --tls-key must be provided together".to_string());
     }
+    let output_dir = paths::ensure_output_dir(app.clone(), request.output_dir.clone())?;
+    let tls_paths = match (
+        request.tls_cert_path.as_deref(),
+        request.tls_key_path.as_deref(),
+    ) {
+        (Some(cert_path), Some(key_path)) => Some((
+            paths::validate_input_file(cert_path, "TLS certificate")?,
+            paths::validate_input_file(key_path, "TLS key")?,
+        )),
+        _ => None,
+    };
+
+    // Detecting the network runs `ip`/`nmcli`/`arp`, so keep it off the
+    // async runtime like the send path does.
+    let profile_app = app.clone();
+    let (_, network_profile) = tauri::async_runtime::spawn_blocking(move || {
+        network_profiles::resolve_active_profile(&profile_app)
+    })
+    .await
+    .map_err(|err| format!("failed to join network detection task: {err}"))??;
+    if let Some(profile) = network_profile.as_ref() {
+        if profile.require_tls && request.tls_cert_path.is_none() {
+            return Err(format!(
+                "network profile `{}` requires TLS; provide --tls-cert and --tls-key",
+                profile.label
+            ));
+        }
+    }
+    let managed = managed_config::load()?;
+    if managed.requires_tls() && request.tls_cert_path.is_none() {
+        return Err(
+            "the managed configuration requires TLS; provide --tls-cert and --tls-key".to_string(),
+        );
+    }
+
+    // Asks the CLI, so it blocks as well.
+    let port_app = app.clone();
+    let requested_port = request.port;
+    let (port, port_substitution) = tauri::async_runtime::spawn_blocking(move || {
+        ports::resolve_listen_port(&port_app, requested_port)
+    })
+    .await
+    .map_err(|err| format!("failed to join port check task: {err}"))??;
 
     let mut guard = state
         .listen_child
@@ -433,40 +1027,150 @@ fn start_listen(
         }
     }
 
-    let mut args = vec![
+    if let Some(orphan) = state
+        .orphaned_listener
+        .lock()
+        .map_err(|_| "failed to lock orphaned listener state".to_string())?
+        .as_ref()
+        .filter(|orphan| orphan.port == port)
+    {
+        return Err(format!(
+            "a listener from an earlier session (pid {}) still holds port {port}; terminate it first",
+            orphan.pid
+        ));
+    }
+
+    let mut args: Vec<String> = cli_ipc::IPC_ARGS
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    args.extend([
         "listen".to_string(),
         "-p".to_string(),
-        request.port.to_string(),
+        port.to_string(),
         "-o".to_string(),
-        request.output_dir,
-    ];
+        output_dir.clone(),
+    ]);
 
-    if let Some(name) = request.name.filter(|value| !value.trim().is_empty()) {
+    let identity = identity::load_or_create_identity(&app).ok();
+    let name = match request.name.filter(|value| !value.trim().is_empty()) {
+        Some(name) => Some(name),
+        None => identity.as_ref().map(|identity| identity.effective_name()),
+    };
+    if let Some(name) = name.clone() {
         args.push("-n".to_string());
         args.push(name);
     }
+    if let Some(identity) = &identity {
+        args.push("--device-id".to_string());
+        args.push(identity.device_id.clone());
+        args.push("--device-type".to_string());
+        args.push(identity.device_type.as_str().to_string());
+        if !identity.avatar_color.is_empty() {
+            args.push("--avatar-color".to_string());
+            args.push(identity.avatar_color.clone());
+        }
+        if let Some(emoji) = identity
+            .avatar_emoji
+            .clone()
+            .filter(|emoji| identity::is_valid_avatar_emoji(emoji))
+        {
+            args.push("--avatar-emoji".to_string());
+            args.push(emoji);
+        }
+    }
+    let session_shares = request.shares.unwrap_or_default();
+    let share_args = shared_folders::listen_args(&app, &session_shares)?;
+    // Peer addresses can be spoofed on the LAN, so anything granted by
+    // address alone also needs the pair code.
+    let trusts_addresses = share_args.iter().any(|arg| arg == "--share-allow")
+        || settings::load_settings(&app).is_ok_and(|settings| settings.auto_print.enabled);
+    let mut pair_code_source = None;
     if let Some(pair_code) = request.pair_code.filter(|value| !value.trim().is_empty()) {
         args.push("--pair-code".to_string());
         args.push(pair_code);
+        pair_code_source = Some("provided");
+    } else if managed.requires_pair_code()
+        || trusts_addresses
+        || network_profile
+            .as_ref()
+            .is_some_and(|profile| profile.require_pair_code)
+    {
+        args.push("--pair-generate".to_string());
+        pair_code_source = Some("generated");
     }
-    if let (Some(cert_path), Some(key_path)) = (request.tls_cert_path, request.tls_key_path) {
+    let stealth = request.stealth.unwrap_or(false);
+    if stealth {
+        args.push("--stealth".to_string());
+    }
+    if settings::load_settings(&app).is_ok_and(|settings| settings.hidden_from_discovery) {
+        args.push("--hidden".to_string());
+    }
+    let tls = tls_paths.is_some();
+    let beacon_cert_path = tls_paths
+        .as_ref()
+        .map(|(cert_path, _)| cert_path.to_string_lossy().to_string());
+    if let Some((cert_path, key_path)) = tls_paths {
         args.push("--tls-cert".to_string());
-        args.push(cert_path);
+        args.push(cert_path.to_string_lossy().to_string());
         args.push("--tls-key".to_string());
-        args.push(key_path);
+        args.push(key_path.to_string_lossy().to_string());
     }
     args.push("--confirm-each".to_string());
+    let keep_duplicates = settings::load_settings(&app)
+        .is_ok_and(|settings| settings.duplicate_policy == dedup::DuplicatePolicy::KeepBoth);
+    if keep_duplicates {
+        args.push("--keep-duplicates".to_string());
+    }
+    if request.preserve_metadata.unwrap_or(false) {
+        args.push("--preserve-metadata".to_string());
+    }
+    if settings::load_settings(&app).is_ok_and(|settings| settings.preserve_xattrs) {
+        args.push("--preserve-xattrs".to_string());
+    }
+    if let Some(kbps) = request.max_receive_kbps.filter(|kbps| *kbps > 0) {
+        args.push("--max-rate".to_string());
+        args.push(kbps.to_string());
+    }
+    args.extend(share_args);
+    args.extend(clipboard_sync::listen_args(&app));
+    let bind = settings::load_settings(&app)
+        .ok()
+        .and_then(|settings| interfaces::bind_address(&settings.interfaces));
+    if let Some(bind) = bind {
+        args.push("--bind".to_string());
+        args.push(bind.to_string());
+    }
+
+    args.extend(webdav::listen_args(&app));
+    args.extend(quick_share::listen_args(&app));
+    args.extend(broadcast_ping::listen_args(&app));
+    args.extend(dns_sd::listen_args(&app));
 
     let mut command = build_cli_command(&args)?;
-    let mut child = command
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|err| format!("failed to start listen process: {err}"))?;
+    webdav::set_listen_password(&app, &mut command)?;
+    dns_sd::set_listen_key(&app, &mut command)?;
+    if stealth {
+        stealth::set_listen_secret(&mut command)?;
+    }
+    let mut child = process_pool::spawn(
+        state.inner(),
+        process_pool::ChildKind::Listen,
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
 
     let pid = child.id();
-    register_active_pid_with_state(state.inner(), pid);
+    if let Some(source) = pair_code_source {
+        record_audit(
+            &app,
+            AuditAction::PairCodeSet,
+            serde_json::json!({ "source": source, "port": port }),
+        );
+    }
+    listen_session::begin(state.inner(), port, bind, tls, pair_code_source.is_some());
     let child_stdin = child.stdin.take();
     if let Some(stdout) = child.stdout.take() {
         spawn_log_reader(stdout, "stdout", app.clone());
@@ -489,19 +1193,43 @@ fn start_listen(
         .listen_port
         .lock()
         .map_err(|_| "failed to lock listen port state".to_string())?;
-    *listen_port = Some(request.port);
+    *listen_port = Some(port);
     drop(listen_port);
+    if let Err(err) = listener_lock::record(&app, pid, port) {
+        emit_system_log(&app, format!("failed to record listener lock: {err}"));
+    }
+    if let Ok(mut output_guard) = state.listen_output_dir.lock() {
+        *output_guard = Some(PathBuf::from(&output_dir));
+    }
+    if let Ok(mut shares_guard) = state.listen_shares.lock() {
+        *shares_guard = session_shares;
+    }
 
-    let payload = ListenStatePayload {
-        running: true,
-        pid: Some(pid),
-    };
-    let _ = app.emit("listen-state", payload.clone());
+    if let Some(substitution) = port_substitution {
+        emit_app_event(&app, AppEvent::PortSubstituted(substitution));
+    }
+    directory_beacon::start(&app, port, name, beacon_cert_path.as_deref());
+
+    if let Ok(mut profile_guard) = state.listen_network_profile.lock() {
+        *profile_guard = network_profile;
+    }
+
+    let payload = listen_state_payload(&app, true, Some(pid));
+    emit_app_event(&app, AppEvent::ListenState(payload.clone()));
     Ok(payload)
 }
 
+/// How long a listener gets to roll back and exit after `shutdown`.
+const LISTEN_SHUTDOWN_GRACE: Duration = Duration::from_secs(3);
+
 #[tauri::command]
-fn stop_listen(app: AppHandle, state: State<AppState>) -> Result<ListenStatePayload, String> {
+async fn stop_listen(app: AppHandle) -> Result<ListenStatePayload, String> {
+    run_blocking(move || stop_listener(&app)).await
+}
+
+/// Also used on exit, where blocking is fine.
+fn stop_listener(app: &AppHandle) -> Result<ListenStatePayload, String> {
+    let state = app.state::<AppState>();
     let mut guard = state
         .listen_child
         .lock()
@@ -509,12 +1237,24 @@ fn stop_listen(app: AppHandle, state: State<AppState>) -> Result<ListenStatePayl
 
     if let Some(mut child) = guard.take() {
         let pid = child.id();
-        terminate_process_tree(pid);
-        let _ = child.kill();
+        // A graceful shutdown lets the CLI roll back open batches and stop
+        // announcing itself; the process tree is killed if it hangs.
+        if cli_control::request(state.inner(), "shutdown", json!({})).is_ok() {
+            let deadline = Instant::now() + LISTEN_SHUTDOWN_GRACE;
+            while matches!(child.try_wait(), Ok(None)) && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        if matches!(child.try_wait(), Ok(None)) {
+            terminate_process_tree(pid);
+            let _ = child.kill();
+        }
         let _ = child.wait();
-        unregister_active_pid_with_state(state.inner(), pid);
+        process_pool::release(state.inner(), pid);
     }
     drop(guard);
+    listener_lock::terminate_adopted(app)?;
+    listener_lock::clear(app);
 
     let mut stdin_guard = state
         .listen_stdin
@@ -522,6 +1262,7 @@ fn stop_listen(app: AppHandle, state: State<AppState>) -> Result<ListenStatePayl
         .map_err(|_| "failed to lock listen stdin state".to_string())?;
     *stdin_guard = None;
     drop(stdin_guard);
+    cli_control::reset(state.inner());
 
     let mut listen_port = state
         .listen_port
@@ -530,44 +1271,229 @@ fn stop_listen(app: AppHandle, state: State<AppState>) -> Result<ListenStatePayl
     *listen_port = None;
     drop(listen_port);
 
-    let payload = ListenStatePayload {
-        running: false,
-        pid: None,
-    };
-    let _ = app.emit("listen-state", payload.clone());
+    if let Ok(mut profile_guard) = state.listen_network_profile.lock() {
+        *profile_guard = None;
+    }
+    throttle::reset(state.inner());
+    if let Ok(mut output_guard) = state.listen_output_dir.lock() {
+        *output_guard = None;
+    }
+    if let Ok(mut shares_guard) = state.listen_shares.lock() {
+        shares_guard.clear();
+    }
+    if let Ok(mut incoming) = state.incoming_files.lock() {
+        incoming.clear();
+    }
+    taskbar::finish_receives(app);
+    directory_beacon::stop(app);
+    listen_session::end(state.inner());
+
+    let payload = listen_state_payload(app, false, None);
+    emit_app_event(app, AppEvent::ListenState(payload.clone()));
     Ok(payload)
 }
 
+/// Runs `task` off the main thread: a control request waits seconds for a
+/// listener that stopped answering, and the UI must not freeze meanwhile.
+async fn run_blocking<T, F>(task: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|err| format!("failed to join listen control task: {err}"))?
+}
+
 #[tauri::command]
-fn respond_transfer_confirm(
-    state: State<AppState>,
+async fn respond_transfer_confirm(
+    app: AppHandle,
     response: TransferConfirmResponse,
 ) -> Result<(), String> {
-    let mut stdin_guard = state
-        .listen_stdin
-        .lock()
-        .map_err(|_| "failed to lock listen stdin state".to_string())?;
-
-    let stdin = stdin_guard
-        .as_mut()
-        .ok_or_else(|| "listen process is not running".to_string())?;
+    run_blocking(move || answer_transfer_confirm(&app, response)).await
+}
 
+fn answer_transfer_confirm(
+    app: &AppHandle,
+    response: TransferConfirmResponse,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
     let action = if response.accept { "approve" } else { "reject" };
-    writeln!(stdin, "{action} {}", response.id)
-        .map_err(|err| format!("failed to write confirm response: {err}"))?;
-    stdin
-        .flush()
-        .map_err(|err| format!("failed to flush confirm response: {err}"))?;
+    cli_control::request(state.inner(), action, json!({ "confirmId": response.id }))?;
+
+    let request = state
+        .pending_confirms
+        .lock()
+        .ok()
+        .and_then(|mut guard| guard.remove(&response.id));
+    let audit_action = if response.accept {
+        AuditAction::TransferAccepted
+    } else {
+        AuditAction::TransferRejected
+    };
+    if let Some(request) = request.as_ref() {
+        if response.accept {
+            throttle::receive_started(state.inner(), response.id, &request.path);
+            if let Some(sha256) = request.sha256.as_deref() {
+                dedup::receive_approved(state.inner(), &request.path, sha256, request.size);
+            }
+        } else if let Some(existing) = request.duplicate_of.as_deref() {
+            dedup::note_skipped(app, &request.path, request.size, existing);
+        }
+    }
+    let detail = match request {
+        Some(request) => serde_json::to_value(&request).unwrap_or_default(),
+        None => serde_json::json!({ "id": response.id }),
+    };
+    record_audit(app, audit_action, detail);
     Ok(())
 }
 
 #[tauri::command]
-fn listen_status(state: State<AppState>) -> Result<ListenStatePayload, String> {
+fn listen_status(app: AppHandle, state: State<AppState>) -> Result<ListenStatePayload, String> {
     let snapshot = inspect_listen_state(&state)?;
-    Ok(ListenStatePayload {
-        running: snapshot.running,
-        pid: snapshot.pid,
-    })
+    Ok(listen_state_payload(&app, snapshot.running, snapshot.pid))
+}
+
+/// Hides or shows this device in peer discovery without stopping the
+/// listener. A running listener is told to toggle its announcements over
+/// stdin; new listeners start with `--hidden` when the setting is on.
+#[tauri::command]
+async fn set_discovery_visibility(
+    app: AppHandle,
+    hidden: bool,
+) -> Result<ListenStatePayload, String> {
+    run_blocking(move || apply_discovery_visibility(&app, hidden)).await
+}
+
+/// Shared by the command and the tray menu's "Hide from discovery" item;
+/// blocks on the listener, so callers keep it off the main thread.
+fn apply_discovery_visibility(app: &AppHandle, hidden: bool) -> Result<ListenStatePayload, String> {
+    settings::update_settings(app, |settings| {
+        settings.hidden_from_discovery = hidden;
+        Ok(())
+    })?;
+
+    let state = app.state::<AppState>();
+    let snapshot = inspect_listen_state(&state)?;
+    if snapshot.running {
+        cli_control::request(state.inner(), "update", json!({ "announce": !hidden }))?;
+    }
+
+    taskbar::set_discovery_hidden(app, hidden);
+    emit_app_event(
+        app,
+        AppEvent::VisibilityChanged(VisibilityPayload {
+            discoverable: !hidden,
+        }),
+    );
+    Ok(listen_state_payload(app, snapshot.running, snapshot.pid))
+}
+
+/// Applies `changes` to the running listener with one `update` control
+/// request, so transfers in flight keep going and the device never drops out
+/// of discovery the way a stop and start would make it. The listener
+/// validates the whole update before applying any of it.
+#[tauri::command]
+async fn update_listen_config(
+    app: AppHandle,
+    changes: ListenConfigUpdate,
+) -> Result<ListenStatePayload, String> {
+    run_blocking(move || apply_listen_config(&app, changes)).await
+}
+
+fn apply_listen_config(
+    app: &AppHandle,
+    changes: ListenConfigUpdate,
+) -> Result<ListenStatePayload, String> {
+    let state = app.state::<AppState>();
+    let snapshot = inspect_listen_state(&state)?;
+    if !snapshot.running {
+        return Err("listen process is not running".to_string());
+    }
+
+    let output_dir = changes
+        .output_dir
+        .map(|output_dir| paths::ensure_output_dir(app.clone(), output_dir))
+        .transpose()?;
+    let pair_code = changes
+        .pair_code
+        .map(|pair_code| pair_code.trim().to_string());
+    if let Some(pair_code) = pair_code.as_deref() {
+        if !pair_code.is_empty()
+            && (pair_code.len() != 6 || !pair_code.chars().all(|c| c.is_ascii_digit()))
+        {
+            return Err("pair code must be 6 digits".to_string());
+        }
+        if pair_code.is_empty() && managed_config::load()?.requires_pair_code() {
+            return Err("the managed configuration requires a pair code".to_string());
+        }
+    }
+
+    let mut params = serde_json::Map::new();
+    if let Some(output_dir) = output_dir.as_deref() {
+        params.insert("outputDir".to_string(), json!(output_dir));
+    }
+    if let Some(pair_code) = pair_code.as_deref() {
+        let value = if pair_code.is_empty() {
+            Value::Null
+        } else {
+            json!(pair_code)
+        };
+        params.insert("pairCode".to_string(), value);
+    }
+    if let Some(kbps) = changes.max_receive_kbps {
+        params.insert("maxRateKib".to_string(), json!(kbps));
+    }
+    if !params.is_empty() {
+        cli_control::request(state.inner(), "update", Value::Object(params))?;
+    }
+
+    if let Some(output_dir) = output_dir {
+        if let Ok(mut output_guard) = state.listen_output_dir.lock() {
+            *output_guard = Some(PathBuf::from(&output_dir));
+        }
+    }
+    if let Some(pair_code) = pair_code {
+        listen_session::set_pair_code_required(state.inner(), !pair_code.is_empty());
+        record_audit(
+            app,
+            AuditAction::PairCodeSet,
+            serde_json::json!({
+                "source": if pair_code.is_empty() { "removed" } else { "updated" },
+            }),
+        );
+    }
+    if let Some(auto_accept) = changes.auto_accept {
+        let mut profile_guard = state
+            .listen_network_profile
+            .lock()
+            .map_err(|_| "failed to lock listen network profile".to_string())?;
+        let profile = profile_guard.as_mut().ok_or_else(|| {
+            "auto-accept comes from a network profile; none is active".to_string()
+        })?;
+        profile.auto_accept = auto_accept;
+    }
+
+    let payload = listen_state_payload(app, snapshot.running, snapshot.pid);
+    emit_app_event(app, AppEvent::ListenState(payload.clone()));
+    Ok(payload)
+}
+
+fn listen_state_payload(app: &AppHandle, running: bool, pid: Option<u32>) -> ListenStatePayload {
+    let settings = settings::load_settings(app).ok();
+    ListenStatePayload {
+        running,
+        pid,
+        discoverable: settings
+            .as_ref()
+            .is_none_or(|settings| !settings.hidden_from_discovery),
+        quiet_hours_active: settings
+            .as_ref()
+            .is_some_and(|settings| quiet_hours::active_action(settings).is_some()),
+        clipboard_sync: clipboard_sync::status(app, running),
+        session: listen_session::report(app.state::<AppState>().inner(), running),
+    }
 }
 
 fn spawn_log_reader<R>(reader: R, stream: &'static str, app: AppHandle)
@@ -575,95 +1501,295 @@ where
     R: Read + Send + 'static,
 {
     thread::spawn(move || {
-        let mut reader = reader;
-        let mut chunk = [0u8; 4096];
-        let mut pending = String::new();
-        let mut last_live_progress: Option<String> = None;
-
-        loop {
-            let read_size = match reader.read(&mut chunk) {
-                Ok(size) => size,
-                Err(_) => break,
-            };
-            if read_size == 0 {
-                break;
-            }
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            emit_listen_line(&app, stream, &String::from_utf8_lossy(&line));
+            line.clear();
+        }
+    });
+}
 
-            let text = String::from_utf8_lossy(&chunk[..read_size]);
-            pending.push_str(&text);
+fn emit_listen_line(app: &AppHandle, stream: &'static str, raw_line: &str) {
+    let line = raw_line.trim();
+    if line.is_empty() {
+        return;
+    }
+    let state = app.state::<AppState>();
 
-            let normalized = pending.replace('\r', "\n");
-            let mut parts: Vec<&str> = normalized.split('\n').collect();
-            let tail = parts.pop().unwrap_or_default().to_string();
-            for line in parts {
-                let trimmed = line.trim();
-                if trimmed.is_empty() {
-                    continue;
+    let message = (stream == "stdout")
+        .then(|| cli_ipc::parse_line(line))
+        .flatten();
+    let display = match message {
+        Some(cli_ipc::CliMessage::ConfirmRequest(request)) => {
+            handle_confirm_request(app, request);
+            return;
+        }
+        Some(message) => {
+            match &message {
+                cli_ipc::CliMessage::Progress(cli_ipc::Progress {
+                    direction: cli_ipc::Direction::Recv,
+                    path,
+                    bytes,
+                    total,
+                    ..
+                }) => {
+                    let complete = message.completes();
+                    throttle::observe_receive_progress(state.inner(), path, complete);
+                    if complete {
+                        dedup::record_received(app, path);
+                    }
+                    taskbar::observe_receive_progress(app, path, *bytes, total.unwrap_or(0));
                 }
-                if last_live_progress.as_deref() == Some(trimmed) {
-                    last_live_progress = None;
-                    continue;
+                cli_ipc::CliMessage::Saved {
+                    path,
+                    already_had: false,
+                    from,
+                    note,
+                    quick_share,
+                } => {
+                    listen_session::note_saved(state.inner(), path);
+                    if let Err(err) =
+                        history::record_receive(app, path, from.as_deref(), note.clone())
+                    {
+                        emit_system_log(app, format!("failed to record receive history: {err}"));
+                    }
+                    if !webdav::is_uploaded(path) {
+                        // Trusted-sender stages such as auto-print key on
+                        // `from`; a Quick Share address is not a sender.
+                        let trusted_from = from.clone().filter(|_| !*quick_share);
+                        post_receive::file_saved(app, path.clone(), trusted_from);
+                    }
+                    telemetry::count_receive(app, None);
                 }
-                emit_listen_line(&app, stream, trimmed);
-            }
-
-            let trimmed_tail = tail.trim();
-            if is_transfer_progress_line(trimmed_tail) {
-                if last_live_progress.as_deref() != Some(trimmed_tail) {
-                    emit_listen_line(&app, stream, trimmed_tail);
-                    last_live_progress = Some(trimmed_tail.to_string());
+                cli_ipc::CliMessage::ReceiveFailed { message } => {
+                    listen_session::note_error(state.inner(), message);
+                    telemetry::count_receive(app, Some(message));
                 }
-            } else {
-                last_live_progress = None;
+                cli_ipc::CliMessage::Message { from, name, text } => {
+                    messages::received(app, from, name, text);
+                }
+                cli_ipc::CliMessage::Clipboard { from, text } => {
+                    clipboard_sync::received(app, from, text);
+                }
+                // Browsing is only in the receive log; pulls and refusals
+                // are audited too.
+                cli_ipc::CliMessage::ShareAccess {
+                    from,
+                    action,
+                    path,
+                    allowed,
+                    bytes,
+                } if !*allowed || action == "fetch" => {
+                    record_audit(
+                        app,
+                        if *allowed {
+                            AuditAction::ShareAccessed
+                        } else {
+                            AuditAction::ShareDenied
+                        },
+                        json!({ "from": from, "action": action, "path": path, "bytes": bytes }),
+                    );
+                }
+                cli_ipc::CliMessage::Response {
+                    id,
+                    ok,
+                    result,
+                    error,
+                } => {
+                    let outcome = if *ok {
+                        Ok(result.clone())
+                    } else {
+                        Err(error.clone().unwrap_or_default())
+                    };
+                    let claimed = cli_control::resolve(state.inner(), *id, outcome);
+                    // Failed notifications have nobody waiting for them.
+                    if claimed || *ok {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+            match cli_ipc::display_text(&message) {
+                Some(text) => text,
+                None => return,
             }
-            pending = tail;
         }
-
-        if !pending.trim().is_empty() {
-            if last_live_progress.as_deref() != Some(pending.trim()) {
-                emit_listen_line(&app, stream, &pending);
+        None => {
+            if stream == "stderr" {
+                listen_session::note_error(state.inner(), line);
             }
+            line.to_string()
         }
-    });
-}
+    };
 
-fn is_transfer_progress_line(raw_line: &str) -> bool {
-    let line = raw_line.trim_start();
-    (line.starts_with("[send ") || line.starts_with("[recv ")) && line.contains('%')
+    emit_activity(
+        app,
+        ActivityEntry::ReceiveLog {
+            stream: stream.to_string(),
+            line: display,
+        },
+    );
 }
 
-fn parse_confirm_request(line: &str) -> Option<CliConfirmRequest> {
-    const PREFIX: &str = "[confirm-request] ";
-    let raw = line.strip_prefix(PREFIX)?;
-    serde_json::from_str::<CliConfirmRequest>(raw).ok()
+/// Runs on the listener's output reader, which must keep reading to deliver
+/// the answer, so decisions taken here are not waited for.
+fn answer_confirm(state: &AppState, action: &str, id: u64) -> Result<(), String> {
+    cli_control::notify(state, action, json!({ "confirmId": id }))
 }
 
-fn emit_listen_line(app: &AppHandle, stream: &'static str, raw_line: &str) {
-    let line = raw_line.trim();
-    if line.is_empty() {
+fn handle_confirm_request(app: &AppHandle, request: cli_ipc::ConfirmRequest) {
+    let state = app.state::<AppState>();
+    let from = canonical_discovery_address(request.from.as_deref().unwrap_or("unknown"));
+
+    if !throttle::admit_incoming(app, request.id, &from, &request.path) {
+        if let Err(err) = answer_confirm(state.inner(), "reject", request.id) {
+            emit_system_log(
+                app,
+                format!("failed to reject rate-limited transfer: {err}"),
+            );
+        }
         return;
     }
 
-    if stream == "stdout" {
-        if let Some(request) = parse_confirm_request(line) {
-            let payload = TransferConfirmRequestPayload {
-                id: request.id,
-                from: canonical_discovery_address(
-                    &request.from.unwrap_or_else(|| "unknown".to_string()),
-                ),
-                path: request.path,
-                size: request.size,
-            };
-            let _ = app.emit("transfer-confirm-request", payload);
+    // An unreadable policy refuses everything, like a sender outside it.
+    if !managed_config::load().is_ok_and(|managed| managed.allows_peer(&from)) {
+        match answer_confirm(state.inner(), "reject", request.id) {
+            Ok(()) => {
+                record_audit(
+                    app,
+                    AuditAction::TransferRejected,
+                    serde_json::json!({
+                        "id": request.id,
+                        "from": from,
+                        "path": request.path,
+                        "size": request.size,
+                        "reason": "managedConfig",
+                    }),
+                );
+                emit_system_log(
+                    app,
+                    format!(
+                        "rejected {} from {from}: outside the subnets the managed configuration allows",
+                        request.path
+                    ),
+                );
+            }
+            Err(err) => emit_system_log(app, format!("failed to reject transfer: {err}")),
+        }
+        return;
+    }
+
+    let duplicate_policy = settings::load_settings(app)
+        .map(|settings| settings.duplicate_policy)
+        .unwrap_or_default();
+    let duplicate_of = request
+        .sha256
+        .as_deref()
+        .and_then(|sha256| dedup::find_duplicate(app, sha256, request.size));
+    if let Some(existing) = duplicate_of.as_deref() {
+        if duplicate_policy == dedup::DuplicatePolicy::Skip {
+            match answer_confirm(state.inner(), "reject", request.id) {
+                Ok(()) => dedup::note_skipped(app, &request.path, request.size, existing),
+                Err(err) => emit_system_log(app, format!("failed to skip duplicate: {err}")),
+            }
             return;
         }
     }
+    // Quick Share senders are not paired, so nothing decides for the user.
+    let may_auto_accept = !request.quick_share
+        && (duplicate_of.is_none() || duplicate_policy == dedup::DuplicatePolicy::KeepBoth);
+
+    if let Some(label) = auto_accept_profile_label(app).filter(|_| may_auto_accept) {
+        let result = answer_confirm(state.inner(), "approve", request.id);
+        match result {
+            Ok(()) => {
+                throttle::receive_started(state.inner(), request.id, &request.path);
+                if let Some(sha256) = request.sha256.as_deref() {
+                    dedup::receive_approved(state.inner(), &request.path, sha256, request.size);
+                }
+                record_audit(
+                    app,
+                    AuditAction::TransferAutoAccepted,
+                    serde_json::json!({
+                        "id": request.id,
+                        "from": from,
+                        "path": request.path,
+                        "size": request.size,
+                        "networkProfile": label,
+                    }),
+                );
+                emit_system_log(
+                    app,
+                    format!("auto-accepted {} (network profile `{label}`)", request.path),
+                )
+            }
+            Err(err) => emit_system_log(app, format!("auto-accept failed: {err}")),
+        }
+        return;
+    }
+
+    let quiet_action = settings::load_settings(app)
+        .ok()
+        .and_then(|settings| quiet_hours::active_action(&settings));
+    if quiet_action == Some(quiet_hours::QuietAction::Reject) {
+        match answer_confirm(state.inner(), "reject", request.id) {
+            Ok(()) => {
+                record_audit(
+                    app,
+                    AuditAction::TransferRejected,
+                    serde_json::json!({
+                        "id": request.id,
+                        "from": from,
+                        "path": request.path,
+                        "size": request.size,
+                        "reason": "quietHours",
+                    }),
+                );
+                emit_system_log(
+                    app,
+                    format!("rejected {} from {from} during quiet hours", request.path),
+                );
+            }
+            Err(err) => emit_system_log(app, format!("failed to reject during quiet hours: {err}")),
+        }
+        return;
+    }
 
-    let payload = ListenLogPayload {
-        stream: stream.to_string(),
-        line: line.to_string(),
+    let payload = TransferConfirmRequestPayload {
+        id: request.id,
+        from,
+        path: request.path,
+        size: request.size,
+        sha256: request.sha256,
+        duplicate_of,
+        streaming: request.streaming,
+        quick_share: request.quick_share,
     };
-    let _ = app.emit("listen-log", payload);
+    if let Ok(mut guard) = state.pending_confirms.lock() {
+        guard.insert(payload.id, payload.clone());
+    }
+    if quiet_action == Some(quiet_hours::QuietAction::Queue) {
+        quiet_hours::queue_confirm(state.inner(), payload.id);
+        emit_system_log(
+            app,
+            format!(
+                "queued {} from {} until quiet hours end",
+                payload.path, payload.from
+            ),
+        );
+        return;
+    }
+    emit_app_event(app, AppEvent::TransferConfirmRequest(payload));
+}
+
+fn auto_accept_profile_label(app: &AppHandle) -> Option<String> {
+    let state = app.state::<AppState>();
+    let guard = state.listen_network_profile.lock().ok()?;
+    guard
+        .as_ref()
+        .filter(|profile| profile.auto_accept)
+        .map(|profile| profile.label.clone())
 }
 
 fn inspect_listen_state(state: &State<AppState>) -> Result<ListenStateSnapshot, String> {
@@ -694,7 +1820,7 @@ fn inspect_listen_state(state: &State<AppState>) -> Result<ListenStateSnapshot,
     };
 
     if let Some(pid) = exited_pid {
-        unregister_active_pid_with_state(state.inner(), pid);
+        process_pool::release(state.inner(), pid);
     }
 
     if !running {
@@ -703,6 +1829,7 @@ fn inspect_listen_state(state: &State<AppState>) -> Result<ListenStateSnapshot,
             .lock()
             .map_err(|_| "failed to lock listen stdin state".to_string())?;
         *listen_stdin = None;
+        cli_control::reset(state.inner());
 
         let mut listen_port = state
             .listen_port
@@ -735,7 +1862,23 @@ fn local_address_set() -> HashSet<String> {
     addresses
 }
 
-fn is_local_discovered_device(device: &DiscoverDevice, local_addresses: &HashSet<String>) -> bool {
+/// Devices announcing an id are this machine only when it is ours: others
+/// can share our address behind a VPN or NAT. Local addresses decide for
+/// listeners that announce no id, and only on our own listen port, since
+/// another user account or a container can listen on a different one.
+fn is_local_discovered_device(
+    device: &DiscoverDevice,
+    self_id: Option<&str>,
+    listen_port: Option<u16>,
+    local_addresses: &HashSet<String>,
+) -> bool {
+    if let (Some(self_id), Some(id)) = (self_id, device.id.as_deref()) {
+        return id == self_id;
+    }
+    if listen_port != Some(device.port) {
+        return false;
+    }
+
     if local_addresses.contains(&device.host) {
         return true;
     }
@@ -776,8 +1919,12 @@ fn default_output_dir() -> String {
         .unwrap_or_else(|| "./received".to_string())
 }
 
-async fn run_cli_capture_async(app: AppHandle, args: Vec<String>) -> Result<CommandResult, String> {
-    tauri::async_runtime::spawn_blocking(move || run_cli_capture(app, args))
+async fn run_cli_capture_async(
+    app: AppHandle,
+    args: Vec<String>,
+    kind: process_pool::ChildKind,
+) -> Result<CommandResult, String> {
+    tauri::async_runtime::spawn_blocking(move || run_cli_capture(app, args, kind))
         .await
         .map_err(|err| format!("failed to join CLI task: {err}"))?
 }
@@ -785,24 +1932,78 @@ async fn run_cli_capture_async(app: AppHandle, args: Vec<String>) -> Result<Comm
 async fn run_cli_capture_streaming_async(
     app: AppHandle,
     args: Vec<String>,
+    send_id: u64,
+    input: Option<Box<dyn Read + Send>>,
 ) -> Result<CommandResult, String> {
-    tauri::async_runtime::spawn_blocking(move || run_cli_capture_streaming(app, args))
-        .await
-        .map_err(|err| format!("failed to join CLI task: {err}"))?
+    tauri::async_runtime::spawn_blocking(move || {
+        run_cli_capture_streaming(app, args, send_id, input)
+    })
+    .await
+    .map_err(|err| format!("failed to join CLI task: {err}"))?
 }
 
-fn run_cli_capture(app: AppHandle, args: Vec<String>) -> Result<CommandResult, String> {
-    let mut command = build_cli_command(&args)?;
-    let mut child = command
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|err| format!("failed to execute CLI: {err}"))?;
+fn run_cli_capture(
+    app: AppHandle,
+    args: Vec<String>,
+    kind: process_pool::ChildKind,
+) -> Result<CommandResult, String> {
+    run_cli_capture_with_input(app, args, kind, None)
+}
+
+/// `run_cli_capture` with `input` written to the child's stdin, for data that
+/// should not show up in the process list.
+fn run_cli_capture_with_input(
+    app: AppHandle,
+    args: Vec<String>,
+    kind: process_pool::ChildKind,
+    input: Option<Vec<u8>>,
+) -> Result<CommandResult, String> {
+    let timeouts = settings::load_settings(&app)
+        .map(|settings| settings.command_timeouts)
+        .unwrap_or_default();
+    let deadline = match kind {
+        process_pool::ChildKind::Send => timeouts.send(),
+        _ => timeouts.discover(),
+    };
+    capture_cli(
+        app.state::<AppState>().inner(),
+        &args,
+        kind,
+        deadline,
+        input,
+    )
+}
+
+/// Runs the CLI until it exits, or kills it once `deadline` passes.
+fn capture_cli(
+    state: &AppState,
+    args: &[String],
+    kind: process_pool::ChildKind,
+    deadline: Option<Duration>,
+    input: Option<Vec<u8>>,
+) -> Result<CommandResult, String> {
+    let mut command = build_cli_command(args)?;
+    if args.iter().any(|arg| arg == "--knock") {
+        stealth::set_send_secret(&mut command)?;
+    }
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = process_pool::spawn(
+        state,
+        kind,
+        command.stdout(Stdio::piped()).stderr(Stdio::piped()),
+    )?;
 
     let pid = child.id();
-    register_active_pid(&app, pid);
 
     let result = (|| -> Result<CommandResult, String> {
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            // Closing stdin once written tells the CLI the input is complete.
+            thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            });
+        }
         let stdout = child
             .stdout
             .take()
@@ -815,9 +2016,24 @@ fn run_cli_capture(app: AppHandle, args: Vec<String>) -> Result<CommandResult, S
         let stdout_reader = thread::spawn(move || read_output_stream(stdout, "stdout"));
         let stderr_reader = thread::spawn(move || read_output_stream(stderr, "stderr"));
 
-        let status = child
-            .wait()
-            .map_err(|err| format!("failed to wait CLI process: {err}"))?;
+        let waited = watchdog::wait(
+            &mut child,
+            deadline,
+            None,
+            &watchdog::Heartbeat::default(),
+            || false,
+        )?;
+        let status = match waited {
+            watchdog::Waited::Exited(status) => status,
+            watchdog::Waited::Hung(hang) => {
+                terminate_process_tree(pid);
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = join_stream_reader(stdout_reader, "stdout");
+                let _ = join_stream_reader(stderr_reader, "stderr");
+                return Err(hang.message(kind.label()));
+            }
+        };
         let stdout = join_stream_reader(stdout_reader, "stdout")?;
         let stderr = join_stream_reader(stderr_reader, "stderr")?;
 
@@ -829,22 +2045,102 @@ fn run_cli_capture(app: AppHandle, args: Vec<String>) -> Result<CommandResult, S
         })
     })();
 
-    unregister_active_pid(&app, pid);
+    if result.is_err() {
+        // Never leave a child running, or unreaped, behind a failed capture.
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    process_pool::release(state, pid);
     result
 }
 
-fn run_cli_capture_streaming(app: AppHandle, args: Vec<String>) -> Result<CommandResult, String> {
-    let mut command = build_cli_command(&args)?;
-    let mut child = command
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|err| format!("failed to execute CLI: {err}"))?;
+/// Like `run_cli_capture`, but forwards output lines to the activity feed. When
+/// `input` is given it is piped into the child's stdin; if reading it fails the
+/// child is killed before stdin closes, so a truncated stream is never sent as
+/// complete. A send that stalls is killed and, without `input` to replay and
+/// while `stall_retries` allows, started again.
+fn run_cli_capture_streaming(
+    app: AppHandle,
+    args: Vec<String>,
+    send_id: u64,
+    mut input: Option<Box<dyn Read + Send>>,
+) -> Result<CommandResult, String> {
+    let args: Vec<String> = cli_ipc::IPC_ARGS
+        .iter()
+        .map(|arg| arg.to_string())
+        .chain(args)
+        .collect();
+    let timeouts = settings::load_settings(&app)
+        .map(|settings| settings.command_timeouts)
+        .unwrap_or_default();
+    let replayable = input.is_none();
+    let mut attempt = 0;
+    loop {
+        let hang = match run_cli_send_attempt(&app, &args, send_id, input.take(), &timeouts)? {
+            Ok(result) => return Ok(result),
+            Err(hang) => hang,
+        };
+        let watchdog::Hang::Stalled(idle) = hang else {
+            return Err(hang.message("send"));
+        };
+        attempt += 1;
+        let retrying = replayable
+            && attempt <= timeouts.stall_retries
+            && kill_switch::ensure_network_allowed(app.state::<AppState>().inner()).is_ok();
+        emit_app_event(
+            &app,
+            AppEvent::TransferStalled(watchdog::TransferStalledPayload {
+                send_id,
+                idle_secs: idle.as_secs(),
+                attempt,
+                retrying,
+            }),
+        );
+        if !retrying {
+            return Err(hang.message("send"));
+        }
+        emit_system_log(
+            &app,
+            format!(
+                "send #{send_id} stalled, retrying ({attempt}/{})",
+                timeouts.stall_retries
+            ),
+        );
+    }
+}
+
+/// One run of the send CLI. `Ok(Err(_))` means the watchdog stopped a hung
+/// child.
+fn run_cli_send_attempt(
+    app: &AppHandle,
+    args: &[String],
+    send_id: u64,
+    input: Option<Box<dyn Read + Send>>,
+    timeouts: &watchdog::CommandTimeouts,
+) -> Result<Result<CommandResult, watchdog::Hang>, String> {
+    let mut command = build_cli_command(args)?;
+    // The CLI also knocks on discovered stealth listeners without `--knock`,
+    // so a keychain error only matters when the knock was asked for.
+    if let Err(err) = stealth::set_send_secret(&mut command) {
+        if args.iter().any(|arg| arg == "--knock") {
+            return Err(err);
+        }
+    }
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let state = app.state::<AppState>();
+    let mut child = process_pool::spawn(
+        state.inner(),
+        process_pool::ChildKind::Send,
+        command.stdout(Stdio::piped()).stderr(Stdio::piped()),
+    )?;
 
     let pid = child.id();
-    register_active_pid(&app, pid);
+    scheduler::attach_pid(app, send_id, pid);
+    let heartbeat = watchdog::Heartbeat::default();
 
-    let result = (|| -> Result<CommandResult, String> {
+    let result = (|| -> Result<Result<CommandResult, watchdog::Hang>, String> {
         let stdout = child
             .stdout
             .take()
@@ -856,24 +2152,66 @@ fn run_cli_capture_streaming(app: AppHandle, args: Vec<String>) -> Result<Comman
 
         let stdout_app = app.clone();
         let stderr_app = app.clone();
-        let stdout_reader = thread::spawn(move || stream_output(stdout, "stdout", stdout_app));
-        let stderr_reader = thread::spawn(move || stream_output(stderr, "stderr", stderr_app));
+        let stdout_heartbeat = heartbeat.clone();
+        let stderr_heartbeat = heartbeat.clone();
+        let stdout_reader = thread::spawn(move || {
+            stream_output(stdout, "stdout", stdout_app, send_id, stdout_heartbeat)
+        });
+        let stderr_reader = thread::spawn(move || {
+            stream_output(stderr, "stderr", stderr_app, send_id, stderr_heartbeat)
+        });
+
+        if let Some(mut input) = input {
+            let mut stdin = child
+                .stdin
+                .take()
+                .ok_or_else(|| "failed to capture CLI stdin".to_string())?;
+            let copied = std::io::copy(&mut input, &mut stdin);
+            if let Err(err) = copied {
+                let _ = child.kill();
+                drop(stdin);
+                let _ = child.wait();
+                let _ = join_stream_reader(stdout_reader, "stdout");
+                let _ = join_stream_reader(stderr_reader, "stderr");
+                return Err(format!("failed to read input stream: {err}"));
+            }
+        }
 
-        let status = child
-            .wait()
-            .map_err(|err| format!("failed to wait CLI process: {err}"))?;
+        let waited = watchdog::wait(
+            &mut child,
+            timeouts.send(),
+            timeouts.stall(),
+            &heartbeat,
+            || scheduler::is_paused(app, send_id),
+        )?;
+        let status = match waited {
+            watchdog::Waited::Exited(status) => status,
+            watchdog::Waited::Hung(hang) => {
+                terminate_process_tree(pid);
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = join_stream_reader(stdout_reader, "stdout");
+                let _ = join_stream_reader(stderr_reader, "stderr");
+                return Ok(Err(hang));
+            }
+        };
         let stdout = join_stream_reader(stdout_reader, "stdout")?;
         let stderr = join_stream_reader(stderr_reader, "stderr")?;
 
-        Ok(CommandResult {
+        Ok(Ok(CommandResult {
             success: status.success(),
             code: status.code().unwrap_or(-1),
             stdout,
             stderr,
-        })
+        }))
     })();
 
-    unregister_active_pid(&app, pid);
+    if result.is_err() {
+        // Never leave a child running, or unreaped, behind a failed capture.
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    process_pool::release(state.inner(), pid);
     result
 }
 
@@ -898,41 +2236,106 @@ where
     Ok(String::from_utf8_lossy(&output).to_string())
 }
 
-fn stream_output<R>(mut reader: R, stream: &'static str, app: AppHandle) -> Result<String, String>
+/// Forwards a send's output to the activity feed. Stdout carries IPC
+/// messages, which are rendered back to the CLI's text format so the
+/// returned output and the feed read the same as without `--ipc`.
+fn stream_output<R>(
+    reader: R,
+    stream: &'static str,
+    app: AppHandle,
+    send_id: u64,
+    heartbeat: watchdog::Heartbeat,
+) -> Result<String, String>
 where
     R: Read,
 {
-    let mut output = Vec::new();
-    let mut buffer = [0u8; 4096];
+    let mut reader = BufReader::new(reader);
+    let mut output = String::new();
+    let mut raw = Vec::new();
 
     loop {
+        raw.clear();
         let read_size = reader
-            .read(&mut buffer)
+            .read_until(b'\n', &mut raw)
             .map_err(|err| format!("failed to read CLI {stream}: {err}"))?;
         if read_size == 0 {
             break;
         }
 
-        let chunk = &buffer[..read_size];
-        output.extend_from_slice(chunk);
-        let payload = SendOutputPayload {
-            stream: stream.to_string(),
-            chunk: String::from_utf8_lossy(chunk).to_string(),
+        let line = String::from_utf8_lossy(&raw);
+        let message = (stream == "stdout")
+            .then(|| cli_ipc::parse_line(&line))
+            .flatten();
+        heartbeat.beat(matches!(message, Some(cli_ipc::CliMessage::Progress(_))));
+        let chunk = match message {
+            Some(message) => {
+                match &message {
+                    cli_ipc::CliMessage::Progress(cli_ipc::Progress {
+                        direction: cli_ipc::Direction::Send,
+                        path,
+                        bytes,
+                        total,
+                        batch,
+                        ..
+                    }) => {
+                        taskbar::observe_send_progress(
+                            &app,
+                            send_id,
+                            path,
+                            *bytes,
+                            total.unwrap_or(0),
+                        );
+                        let (sent, size) = batch
+                            .as_ref()
+                            .map_or((*bytes, total.unwrap_or(0)), |batch| {
+                                (batch.bytes, batch.total)
+                            });
+                        job_journal::progress(&app, send_id, sent, size);
+                    }
+                    cli_ipc::CliMessage::SendPlan { files } => {
+                        send_batches::plan(&app, send_id, files);
+                    }
+                    cli_ipc::CliMessage::FileSent {
+                        path,
+                        source,
+                        sha256,
+                    } => {
+                        send_batches::file_sent(&app, send_id, path, source, sha256.as_deref());
+                    }
+                    cli_ipc::CliMessage::SendDone(done) if !done.manifest.is_empty() => {
+                        emit_activity(
+                            &app,
+                            ActivityEntry::SendManifest {
+                                send_id,
+                                files: done.manifest.clone(),
+                            },
+                        );
+                    }
+                    _ => {}
+                }
+                match cli_ipc::display_text(&message) {
+                    Some(text) => format!("{text}\n"),
+                    None => continue,
+                }
+            }
+            None => line.to_string(),
         };
-        let _ = app.emit("send-output", payload);
+        output.push_str(&chunk);
+        emit_activity(
+            &app,
+            ActivityEntry::SendOutput {
+                send_id,
+                stream: stream.to_string(),
+                chunk,
+            },
+        );
     }
 
-    Ok(String::from_utf8_lossy(&output).to_string())
+    Ok(output)
 }
 
 fn default_download_dir() -> Option<PathBuf> {
-    let home = if cfg!(target_os = "windows") {
-        std::env::var_os("USERPROFILE").or_else(|| std::env::var_os("HOME"))
-    } else {
-        std::env::var_os("HOME")
-    }?;
-
-    Some(PathBuf::from(home).join("Downloads"))
+    paths::home_dir().map(|home| home.join("Downloads"))
 }
 
 fn is_progress_line_for_error(line: &str) -> bool {
@@ -980,6 +2383,10 @@ fn render_cli_error(command: &str, output: &CommandResult) -> String {
 }
 
 fn build_cli_command(args: &[String]) -> Result<Command, String> {
+    #[cfg(test)]
+    if let Some(command) = cli_harness::mock_command(args) {
+        return Ok(command);
+    }
     match resolve_cli_runtime()? {
         CliRuntime::Binary(path) => {
             let mut command = Command::new(path);
@@ -1148,10 +2555,7 @@ fn configure_bundled_cli_env(app: &tauri::AppHandle) {
                 return;
             }
         }
-        if let Ok(path) = app
-            .path()
-            .resolve(name.to_string(), BaseDirectory::Resource)
-        {
+        if let Ok(path) = app.path().resolve(name, BaseDirectory::Resource) {
             if path.exists() {
                 set_cli_path_env(path);
                 return;
@@ -1174,18 +2578,159 @@ fn main() {
     let app = tauri::Builder::default()
         .manage(AppState::default())
         .setup(|app| {
+            app_profiles::load_active_profile(app.handle());
             configure_bundled_cli_env(app.handle());
+            receive_folder::spawn_retention_task(app.handle().clone());
+            quiet_hours::spawn_watch_task(app.handle().clone());
+            network_watch::spawn_watch_task(app.handle().clone());
+            battery::spawn_watch_task(app.handle().clone());
+            clipboard_sync::spawn_watch_task(app.handle().clone());
+            activity_search::spawn_index_task(app.handle().clone());
+            history_retention::spawn_prune_task(app.handle().clone());
+            org_directory::spawn_refresh_task(app.handle().clone());
+            telemetry::spawn_task(app.handle().clone());
+            process_pool::spawn_reaper_task(app.handle().clone());
+            listener_lock::detect_orphan(app.handle());
+            job_journal::recover(app.handle());
+            if let Err(err) = taskbar::create_tray(app.handle()) {
+                emit_system_log(app.handle(), format!("failed to create tray icon: {err}"));
+            }
+            let migrate_app = app.handle().clone();
+            thread::spawn(move || {
+                if let Err(err) = secrets::migrate_plaintext_secrets(&migrate_app) {
+                    emit_system_log(
+                        &migrate_app,
+                        format!("failed to move saved pair codes to the keychain: {err}"),
+                    );
+                }
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             discover,
             send_file,
+            send_diagnosis::diagnose_send_failure,
+            send_multi::send_multi,
             pick_send_path,
+            pickers::pick_send_paths,
+            pickers::pick_output_dir,
+            paths::ensure_output_dir,
+            receive_folder::receive_folder_stats,
+            receive_folder::set_retention_policy,
+            dedup::get_dedup_stats,
+            dedup::set_duplicate_policy,
+            stream_bridge::start_stream_bridge,
+            stream_bridge::stop_stream_bridge,
+            archive::set_auto_extract,
+            media_import::set_media_import,
+            zero_copy::set_zero_copy_mode,
+            hashing::hash_file,
+            dedup::verify_received_index,
+            scheduler::set_job_priority,
+            preflight::preflight_send,
+            preflight::cancel_preflight,
+            settings::set_preserve_xattrs,
+            app_profiles::list_profiles,
+            app_profiles::switch_profile,
+            app_data::export_app_data,
+            app_data::import_app_data,
+            onboarding::get_onboarding_state,
+            onboarding::complete_onboarding_step,
+            quiet_hours::set_quiet_hours,
+            watchdog::set_command_timeouts,
             default_output_dir,
             start_listen,
             stop_listen,
+            update_listen_config,
             respond_transfer_confirm,
-            listen_status
+            listen_status,
+            process_pool::list_cli_processes,
+            listener_lock::get_orphaned_listener,
+            listener_lock::resolve_orphaned_listener,
+            cli_control::list_receive_transfers,
+            history::get_recent_send_paths,
+            history::resend,
+            history::get_transfer_history,
+            presets::list_send_presets,
+            presets::save_send_preset,
+            presets::delete_send_preset,
+            presets::run_send_preset,
+            device_groups::list_device_groups,
+            device_groups::save_device_group,
+            device_groups::delete_device_group,
+            identity::get_device_identity,
+            identity::update_device_identity,
+            identity::regenerate_device_name,
+            settings::get_app_settings,
+            set_discovery_visibility,
+            network_profiles::get_current_network,
+            network_profiles::list_network_profiles,
+            network_profiles::set_network_profile,
+            network_profiles::delete_network_profile,
+            activity::activity_status,
+            events::replay_events,
+            events::get_activity_feed,
+            kill_switch::panic_stop,
+            kill_switch::resume_network_activity,
+            audit::export_audit_log,
+            throttle::set_receive_limits,
+            reachability::diagnose_reachability,
+            remote_shares::list_remote_shares,
+            remote_shares::pull_file,
+            shared_folders::list_shared_folders,
+            shared_folders::add_shared_folder,
+            shared_folders::remove_shared_folder,
+            messages::send_message,
+            messages::list_messages,
+            messages::clear_messages,
+            clipboard_sync::set_clipboard_sync,
+            screen_capture::capture_and_send,
+            auto_print::set_auto_print,
+            activity_search::search_activity,
+            usage_stats::get_usage_stats,
+            history_retention::set_history_retention,
+            history_retention::secure_wipe_history,
+            telemetry::set_telemetry,
+            telemetry::preview_telemetry,
+            telemetry::discard_telemetry,
+            load_simulation::simulate_load,
+            benchmark::run_benchmark,
+            benchmark::list_benchmarks,
+            battery::get_battery_status,
+            battery::set_battery_policy,
+            interfaces::list_network_interfaces,
+            interfaces::set_interface_policy,
+            overlay::get_overlay_status,
+            overlay::set_overlay_settings,
+            device_cache::get_cached_devices,
+            delivery::list_delivery_targets,
+            delivery::save_delivery_target,
+            delivery::delete_delivery_target,
+            webdav::set_webdav_settings,
+            dns_sd::set_dns_sd_settings,
+            broadcast_ping::set_broadcast_ping,
+            stealth::get_knock_secret,
+            stealth::set_knock_secret,
+            syncthing::list_syncthing_folders,
+            syncthing::set_syncthing_settings,
+            kde_connect::list_kde_connect_devices,
+            kde_connect::pair_kde_connect_device,
+            kde_connect::unpair_kde_connect_device,
+            kde_connect::set_kde_connect_settings,
+            quick_share::set_quick_share_settings,
+            send_batches::resume_batch,
+            send_batches::list_send_batches,
+            send_batches::discard_send_batch,
+            job_journal::list_interrupted_jobs,
+            job_journal::resume_interrupted_jobs,
+            job_journal::discard_interrupted_jobs,
+            managed_config::get_managed_config,
+            outbound_policy::set_outbound_policy,
+            org_directory::get_org_directory,
+            org_directory::set_org_directory,
+            org_directory::refresh_org_directory,
+            directory_beacon::get_directory_beacon_status,
+            directory_beacon::set_directory_beacon
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri app");
@@ -1196,10 +2741,13 @@ fn main() {
             ..
         } => {
             api.prevent_close();
-            cleanup_child_processes_from_app(app_handle);
-            app_handle.exit(0);
+            app_exit::request_exit(app_handle);
+        }
+        RunEvent::ExitRequested { api, .. } if !app_exit::ready(app_handle) => {
+            api.prevent_exit();
+            app_exit::request_exit(app_handle);
         }
-        RunEvent::ExitRequested { .. } | RunEvent::Exit => {
+        RunEvent::Exit => {
             cleanup_child_processes_from_app(app_handle);
         }
         _ => {}
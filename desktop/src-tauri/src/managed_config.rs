@@ -0,0 +1,259 @@
+//! Settings an administrator pins with a read-only file deployed outside
+//! the user's reach, e.g. `/etc/local-sent/managed.json`. Every field the
+//! file sets is locked: the app enforces it and reports it so the UI can
+//! disable the matching controls. Fields left out stay the user's choice.
+//! A file that exists but cannot be read or parsed fails closed: sending
+//! and receiving refuse to start until it is fixed.
+
+use crate::settings::AppSettings;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Points at another file, e.g. to try a policy before deploying it.
+const MANAGED_CONFIG_ENV: &str = "LOCAL_SENT_MANAGED_CONFIG";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub(crate) struct ManagedConfig {
+    pub(crate) require_tls: Option<bool>,
+    pub(crate) require_pair_code: Option<bool>,
+    /// Locks usage reporting on or, more usually, off.
+    pub(crate) telemetry: Option<bool>,
+    /// CIDR ranges peers may be in, e.g. `10.20.0.0/16`; sends, discovered
+    /// devices and incoming transfers outside them are refused.
+    pub(crate) allowed_subnets: Option<Vec<String>>,
+}
+
+/// Returned by `get_managed_config`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ManagedConfigPayload {
+    /// Whether a managed configuration file is in place.
+    managed: bool,
+    path: Option<String>,
+    /// camelCase names of the locked settings.
+    locked: Vec<&'static str>,
+    #[serde(flatten)]
+    config: ManagedConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Subnet {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Subnet {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let (address, prefix) = match value.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = address.parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().ok().filter(|prefix| *prefix <= max)?,
+            None => max,
+        };
+        Some(Self { network, prefix })
+    }
+
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl ManagedConfig {
+    fn locked(&self) -> Vec<&'static str> {
+        [
+            ("requireTls", self.require_tls.is_some()),
+            ("requirePairCode", self.require_pair_code.is_some()),
+            ("telemetry", self.telemetry.is_some()),
+            ("allowedSubnets", self.allowed_subnets.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, locked)| locked.then_some(name))
+        .collect()
+    }
+
+    pub(crate) fn requires_tls(&self) -> bool {
+        self.require_tls.unwrap_or(false)
+    }
+
+    pub(crate) fn requires_pair_code(&self) -> bool {
+        self.require_pair_code.unwrap_or(false)
+    }
+
+    /// `None` when any address is allowed.
+    fn subnets(&self) -> Option<Vec<Subnet>> {
+        self.allowed_subnets.as_ref().map(|subnets| {
+            subnets
+                .iter()
+                .filter_map(|subnet| Subnet::parse(subnet))
+                .collect()
+        })
+    }
+
+    pub(crate) fn restricts_peers(&self) -> bool {
+        self.allowed_subnets.is_some()
+    }
+
+    pub(crate) fn allows_ip(&self, ip: IpAddr) -> bool {
+        self.subnets()
+            .is_none_or(|subnets| subnets.iter().any(|subnet| subnet.contains(ip)))
+    }
+
+    /// Whether `address` is a peer the configuration allows. Names are not
+    /// resolved here, so with subnets locked only addresses pass.
+    pub(crate) fn allows_peer(&self, address: &str) -> bool {
+        if !self.restricts_peers() {
+            return true;
+        }
+        let address = address.trim().trim_start_matches('[').trim_end_matches(']');
+        address.parse().is_ok_and(|ip| self.allows_ip(ip))
+    }
+
+    /// Overwrites the user's settings with the locked values.
+    fn apply(&self, settings: &mut AppSettings) {
+        if let Some(telemetry) = self.telemetry {
+            settings.telemetry.enabled = telemetry;
+        }
+    }
+}
+
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(MANAGED_CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(target_os = "windows") {
+        let program_data = std::env::var_os("ProgramData")?;
+        Some(
+            PathBuf::from(program_data)
+                .join("Local Sent")
+                .join("managed.json"),
+        )
+    } else if cfg!(target_os = "macos") {
+        Some(PathBuf::from(
+            "/Library/Application Support/Local Sent/managed.json",
+        ))
+    } else {
+        Some(PathBuf::from("/etc/local-sent/managed.json"))
+    }
+}
+
+fn parse(raw: &str) -> Result<ManagedConfig, String> {
+    let config: ManagedConfig = serde_json::from_str(raw).map_err(|err| err.to_string())?;
+    for subnet in config.allowed_subnets.iter().flatten() {
+        if Subnet::parse(subnet).is_none() {
+            return Err(format!("invalid subnet `{subnet}`"));
+        }
+    }
+    Ok(config)
+}
+
+/// Read on every use, so a policy IT updates applies without a restart.
+pub(crate) fn load() -> Result<ManagedConfig, String> {
+    let Some(path) = config_path() else {
+        return Ok(ManagedConfig::default());
+    };
+    let raw = match fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ManagedConfig::default()),
+        Err(err) => {
+            return Err(format!(
+                "failed to read managed configuration {}: {err}",
+                path.display()
+            ))
+        }
+    };
+    parse(&raw).map_err(|err| {
+        format!(
+            "failed to parse managed configuration {}: {err}",
+            path.display()
+        )
+    })
+}
+
+/// Applies the locked values to `settings`. Without a readable policy,
+/// telemetry is treated as locked off.
+pub(crate) fn apply_to(settings: &mut AppSettings) {
+    match load() {
+        Ok(config) => config.apply(settings),
+        Err(_) => settings.telemetry.enabled = false,
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_managed_config() -> Result<ManagedConfigPayload, String> {
+    let config = load()?;
+    let path = config_path().filter(|path| path.exists());
+    Ok(ManagedConfigPayload {
+        managed: path.is_some(),
+        path: path.map(|path| path.to_string_lossy().to_string()),
+        locked: config.locked(),
+        config,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subnets_match_by_prefix() {
+        let lan = Subnet::parse("192.168.10.0/24").unwrap();
+        assert!(lan.contains("192.168.10.77".parse().unwrap()));
+        assert!(!lan.contains("192.168.11.1".parse().unwrap()));
+        assert!(lan.contains("::ffff:192.168.10.5".parse().unwrap()));
+        let v6 = Subnet::parse("fd00::/8").unwrap();
+        assert!(v6.contains("fd12::1".parse().unwrap()));
+        assert!(!v6.contains("10.0.0.1".parse().unwrap()));
+        assert!(Subnet::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(Subnet::parse("10.0.0.1").unwrap().prefix, 32);
+        assert_eq!(Subnet::parse("10.0.0.0/33"), None);
+        assert_eq!(Subnet::parse("printer.local/24"), None);
+    }
+
+    #[test]
+    fn only_listed_fields_are_locked() {
+        let config = parse(r#"{ "requireTls": true, "telemetry": false }"#).unwrap();
+        assert_eq!(config.locked(), ["requireTls", "telemetry"]);
+        assert!(config.requires_tls());
+        assert!(!config.requires_pair_code());
+        assert!(config.allows_peer("203.0.113.9"));
+    }
+
+    #[test]
+    fn peers_outside_the_allowed_subnets_are_refused() {
+        let config = parse(r#"{ "allowedSubnets": ["10.20.0.0/16"] }"#).unwrap();
+        assert!(config.allows_peer("10.20.3.4"));
+        assert!(config.allows_peer("[::ffff:10.20.3.4]"));
+        assert!(!config.allows_peer("10.21.0.1"));
+        assert!(!config.allows_peer("laptop.local"));
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(parse(r#"{ "allowedSubnets": ["10.0.0.0/40"] }"#).is_err());
+        assert!(parse(r#"{ "requireTSL": true }"#).is_err());
+    }
+}
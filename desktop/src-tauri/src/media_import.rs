@@ -0,0 +1,232 @@
+use crate::dedup;
+use crate::emit_system_log;
+use crate::paths::validate_output_dir;
+use crate::settings::update_settings;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::AppHandle;
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "heic", "heif", "tif", "tiff", "webp", "dng", "gif",
+];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "m4v", "3gp"];
+const MAX_SUFFIX: usize = 1000;
+/// Seconds between the QuickTime epoch (1904-01-01) and the Unix epoch.
+const QUICKTIME_EPOCH_OFFSET: u64 = 2_082_844_800;
+
+/// Sorts received photos and videos into `YYYY/MM/` below `folder` (or below
+/// the folder they were received into).
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct MediaImport {
+    pub(crate) enabled: bool,
+    pub(crate) folder: Option<String>,
+}
+
+fn media_kind(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        Some("image")
+    } else if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+        Some("video")
+    } else {
+        None
+    }
+}
+
+fn exif_year_month(path: &Path) -> Option<(i64, u32)> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    [exif::Tag::DateTimeOriginal, exif::Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Ascii(values) => {
+                let taken = exif::DateTime::from_ascii(values.first()?).ok()?;
+                (taken.year > 0 && (1..=12).contains(&taken.month))
+                    .then_some((i64::from(taken.year), u32::from(taken.month)))
+            }
+            _ => None,
+        })
+}
+
+fn read_box_header(file: &mut File) -> Option<(u64, [u8; 4], u64)> {
+    let mut header = [0u8; 8];
+    file.read_exact(&mut header).ok()?;
+    let size = u64::from(u32::from_be_bytes(header[..4].try_into().ok()?));
+    let kind: [u8; 4] = header[4..].try_into().ok()?;
+    if size == 1 {
+        let mut large = [0u8; 8];
+        file.read_exact(&mut large).ok()?;
+        return Some((u64::from_be_bytes(large), kind, 16));
+    }
+    Some((size, kind, 8))
+}
+
+/// Reads the creation time from the `moov/mvhd` box of an MP4/QuickTime file.
+fn video_created_unix(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let mut position = 0;
+    let mut end = file_len;
+    while position + 8 <= end {
+        file.seek(SeekFrom::Start(position)).ok()?;
+        let (size, kind, header_len) = read_box_header(&mut file)?;
+        let size = if size == 0 { end - position } else { size };
+        if size < header_len {
+            return None;
+        }
+        match &kind {
+            b"moov" => {
+                // Descend: scan the children of `moov` next.
+                end = position + size;
+                position += header_len;
+            }
+            b"mvhd" => {
+                let mut version = [0u8; 4];
+                file.read_exact(&mut version).ok()?;
+                let created = if version[0] == 1 {
+                    let mut raw = [0u8; 8];
+                    file.read_exact(&mut raw).ok()?;
+                    u64::from_be_bytes(raw)
+                } else {
+                    let mut raw = [0u8; 4];
+                    file.read_exact(&mut raw).ok()?;
+                    u64::from(u32::from_be_bytes(raw))
+                };
+                return created.checked_sub(QUICKTIME_EPOCH_OFFSET);
+            }
+            _ => position += size,
+        }
+    }
+    None
+}
+
+/// Converts days since 1970-01-01 to a (year, month) civil date.
+fn civil_year_month(days: i64) -> (i64, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month)
+}
+
+/// Capture date from EXIF for images and `mvhd` for videos, falling back to
+/// the file's modification time (UTC).
+fn capture_year_month(path: &Path, kind: &str) -> Option<(i64, u32)> {
+    let embedded = match kind {
+        "image" => exif_year_month(path),
+        _ => video_created_unix(path)
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| civil_year_month((seconds / 86_400) as i64)),
+    };
+    embedded.or_else(|| {
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        let seconds = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        Some(civil_year_month((seconds / 86_400) as i64))
+    })
+}
+
+fn free_target(dir: &Path, file_name: &Path) -> Option<PathBuf> {
+    let stem = file_name.file_stem()?.to_string_lossy().to_string();
+    let extension = file_name
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (0..MAX_SUFFIX)
+        .map(|index| match index {
+            0 => dir.join(format!("{stem}{extension}")),
+            _ => dir.join(format!("{stem}({index}){extension}")),
+        })
+        .find(|candidate| !candidate.exists())
+}
+
+/// Moves a received photo or video into its `YYYY/MM/` folder. Other files
+/// are left alone.
+pub(crate) fn import_received(app: &AppHandle, path: &Path, settings: &MediaImport) {
+    let Some(kind) = media_kind(path) else {
+        return;
+    };
+    let root = match settings.folder.as_deref() {
+        Some(folder) => match validate_output_dir(app, folder) {
+            Ok(root) => root,
+            Err(err) => {
+                emit_system_log(app, format!("media import skipped: {err}"));
+                return;
+            }
+        },
+        None => match path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => return,
+        },
+    };
+    let Some((year, month)) = capture_year_month(path, kind) else {
+        return;
+    };
+    let dir = root.join(format!("{year:04}")).join(format!("{month:02}"));
+    if path.parent() == Some(dir.as_path()) {
+        return;
+    }
+
+    let result = (|| -> Result<PathBuf, String> {
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| "received file has no name".to_string())?;
+        let target = free_target(&dir, Path::new(file_name))
+            .ok_or_else(|| format!("no free file name in {}", dir.display()))?;
+        if fs::rename(path, &target).is_err() {
+            // Different volume: copy, then remove the original.
+            fs::copy(path, &target).map_err(|err| format!("failed to copy: {err}"))?;
+            fs::remove_file(path).map_err(|err| format!("failed to remove original: {err}"))?;
+        }
+        Ok(target)
+    })();
+
+    match result {
+        Ok(target) => {
+            dedup::note_moved(app, path, &target);
+            emit_system_log(
+                app,
+                format!("imported {} to {}", path.display(), target.display()),
+            );
+        }
+        Err(err) => emit_system_log(
+            app,
+            format!("media import failed for {}: {err}", path.display()),
+        ),
+    }
+}
+
+#[tauri::command]
+pub(crate) fn set_media_import(
+    app: AppHandle,
+    settings: MediaImport,
+) -> Result<MediaImport, String> {
+    let settings = MediaImport {
+        folder: match settings.folder.filter(|folder| !folder.trim().is_empty()) {
+            Some(folder) => Some(
+                validate_output_dir(&app, &folder)?
+                    .to_string_lossy()
+                    .to_string(),
+            ),
+            None => None,
+        },
+        ..settings
+    };
+    update_settings(&app, |current| {
+        current.media_import = settings;
+        Ok(())
+    })
+    .map(|current| current.media_import)
+}
@@ -0,0 +1,149 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::process_pool::ChildKind;
+use crate::remote_shares::RemoteDevice;
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{
+    identity, kill_switch, outbound_policy, render_cli_error, run_cli_capture_async, AppState,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+const MESSAGES_FILE: &str = "messages.json";
+/// Oldest messages are dropped beyond this; it is a side channel, not a chat
+/// archive.
+const MAX_STORED_MESSAGES: usize = 500;
+/// Matches `MAX_MESSAGE_CHARS` in `src/protocol.ts`.
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum MessageDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ChatMessage {
+    direction: MessageDirection,
+    /// Host or device name for sent messages, the sender's name for
+    /// received ones.
+    peer: String,
+    /// Where a received message came from.
+    #[serde(default)]
+    address: Option<String>,
+    text: String,
+    at_ms: u64,
+}
+
+fn load_messages(app: &AppHandle) -> Result<Vec<ChatMessage>, String> {
+    read_json(&data_file(app, MESSAGES_FILE)?)
+}
+
+fn store(app: &AppHandle, message: &ChatMessage) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .messages_lock
+        .lock()
+        .map_err(|_| "failed to lock messages".to_string())?;
+    let mut messages = load_messages(app)?;
+    messages.push(message.clone());
+    let overflow = messages.len().saturating_sub(MAX_STORED_MESSAGES);
+    messages.drain(..overflow);
+    write_json(&data_file(app, MESSAGES_FILE)?, &messages)
+}
+
+/// Called from the listener's output reader for each incoming message.
+pub(crate) fn received(app: &AppHandle, address: &str, name: &str, text: &str) {
+    let message = ChatMessage {
+        direction: MessageDirection::Received,
+        peer: name.to_string(),
+        address: Some(address.to_string()),
+        text: text.to_string(),
+        at_ms: now_ms(),
+    };
+    if let Err(err) = store(app, &message) {
+        crate::emit_system_log(app, format!("failed to save message: {err}"));
+    }
+    emit_app_event(app, AppEvent::MessageReceived(message));
+}
+
+/// Sends a short note to `device`, which must be listening. It shows up in
+/// the receiver's log and history; nothing is saved as a file.
+#[tauri::command]
+pub(crate) async fn send_message(
+    app: AppHandle,
+    device: RemoteDevice,
+    text: String,
+) -> Result<ChatMessage, String> {
+    kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("message is empty".to_string());
+    }
+    if text.chars().count() > MAX_MESSAGE_CHARS {
+        return Err(format!(
+            "message is longer than {MAX_MESSAGE_CHARS} characters"
+        ));
+    }
+    let mut args = vec!["message".to_string(), text.clone()];
+    args.extend(device.args()?);
+    let policy_app = app.clone();
+    let policy_device = device.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        outbound_policy::enforce_destination(&policy_app, &policy_device.destination())
+    })
+    .await
+    .map_err(|err| format!("failed to join outbound policy check: {err}"))??;
+    if let Ok(identity) = identity::load_or_create_identity(&app) {
+        args.push("--from".to_string());
+        args.push(identity.effective_name());
+    }
+
+    let output = run_cli_capture_async(app.clone(), args, ChildKind::Discover).await?;
+    if !output.success {
+        return Err(render_cli_error("message", &output));
+    }
+    let message = ChatMessage {
+        direction: MessageDirection::Sent,
+        peer: device.label(),
+        address: None,
+        text,
+        at_ms: now_ms(),
+    };
+    store(&app, &message)?;
+    Ok(message)
+}
+
+/// Stored messages, oldest first; `peer` keeps only those to or from it.
+#[tauri::command]
+pub(crate) fn list_messages(
+    app: AppHandle,
+    state: State<AppState>,
+    peer: Option<String>,
+) -> Result<Vec<ChatMessage>, String> {
+    let _guard = state
+        .messages_lock
+        .lock()
+        .map_err(|_| "failed to lock messages".to_string())?;
+    let mut messages = load_messages(&app)?;
+    if let Some(peer) = peer
+        .as_deref()
+        .map(str::trim)
+        .filter(|peer| !peer.is_empty())
+    {
+        messages.retain(|message| {
+            message.peer.eq_ignore_ascii_case(peer) || message.address.as_deref() == Some(peer)
+        });
+    }
+    Ok(messages)
+}
+
+#[tauri::command]
+pub(crate) fn clear_messages(app: AppHandle, state: State<AppState>) -> Result<(), String> {
+    let _guard = state
+        .messages_lock
+        .lock()
+        .map_err(|_| "failed to lock messages".to_string())?;
+    write_json(&data_file(&app, MESSAGES_FILE)?, &Vec::<ChatMessage>::new())
+}
@@ -0,0 +1,190 @@
+use crate::configure_cli_command_for_platform;
+use serde::Serialize;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkIdentity {
+    /// Stable key used to match profiles: gateway MAC when known, otherwise
+    /// the SSID, otherwise the gateway address and interface.
+    pub(crate) key: Option<String>,
+    pub(crate) ssid: Option<String>,
+    pub(crate) gateway: Option<String>,
+    pub(crate) gateway_mac: Option<String>,
+    pub(crate) interface: Option<String>,
+}
+
+pub(crate) fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null());
+    configure_cli_command_for_platform(&mut command);
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+pub(crate) fn current_network() -> NetworkIdentity {
+    let (gateway, interface) = default_gateway();
+    let gateway_mac = gateway.as_deref().and_then(gateway_mac_address);
+    let ssid = current_ssid(interface.as_deref());
+
+    let key = if let Some(mac) = gateway_mac.as_ref() {
+        Some(format!("mac:{mac}"))
+    } else if let Some(ssid) = ssid.as_ref() {
+        Some(format!("ssid:{ssid}"))
+    } else {
+        gateway
+            .as_ref()
+            .map(|gateway| format!("gw:{gateway}@{}", interface.as_deref().unwrap_or("unknown")))
+    };
+
+    NetworkIdentity {
+        key,
+        ssid,
+        gateway,
+        gateway_mac,
+        interface,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn default_gateway() -> (Option<String>, Option<String>) {
+    let Some(output) = command_stdout("ip", &["-4", "route", "show", "default"]) else {
+        return (None, None);
+    };
+    // default via 192.168.1.1 dev wlan0 proto dhcp metric 600
+    let Some(line) = output.lines().find(|line| line.starts_with("default")) else {
+        return (None, None);
+    };
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let value_after = |key: &str| {
+        parts
+            .iter()
+            .position(|part| *part == key)
+            .and_then(|index| parts.get(index + 1))
+            .map(|value| value.to_string())
+    };
+    (value_after("via"), value_after("dev"))
+}
+
+#[cfg(target_os = "macos")]
+fn default_gateway() -> (Option<String>, Option<String>) {
+    let Some(output) = command_stdout("route", &["-n", "get", "default"]) else {
+        return (None, None);
+    };
+    let mut gateway = None;
+    let mut interface = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("gateway:") {
+            gateway = non_empty(value);
+        } else if let Some(value) = line.strip_prefix("interface:") {
+            interface = non_empty(value);
+        }
+    }
+    (gateway, interface)
+}
+
+#[cfg(target_os = "windows")]
+fn default_gateway() -> (Option<String>, Option<String>) {
+    let Some(output) = command_stdout("route", &["print", "-4", "0.0.0.0"]) else {
+        return (None, None);
+    };
+    // Network Destination  Netmask  Gateway  Interface  Metric
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 4 && parts[0] == "0.0.0.0" && parts[1] == "0.0.0.0" {
+            return (Some(parts[2].to_string()), Some(parts[3].to_string()));
+        }
+    }
+    (None, None)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn default_gateway() -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+fn gateway_mac_address(gateway: &str) -> Option<String> {
+    let output = if cfg!(target_os = "linux") {
+        command_stdout("ip", &["neigh", "show", gateway])
+    } else if cfg!(target_os = "windows") {
+        command_stdout("arp", &["-a", gateway])
+    } else {
+        command_stdout("arp", &["-n", gateway])
+    }?;
+
+    output
+        .split_whitespace()
+        .map(|token| token.trim_matches(|ch| ch == '(' || ch == ')'))
+        .find_map(normalize_mac)
+}
+
+fn normalize_mac(raw: &str) -> Option<String> {
+    let parts: Vec<&str> = raw.split(['-', ':']).collect();
+    if parts.len() != 6 {
+        return None;
+    }
+    let mut octets = Vec::with_capacity(6);
+    for part in parts {
+        if part.is_empty() || part.len() > 2 || !part.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return None;
+        }
+        octets.push(format!("{:0>2}", part.to_ascii_lowercase()));
+    }
+    let mac = octets.join(":");
+    (mac != "00:00:00:00:00:00" && mac != "ff:ff:ff:ff:ff:ff").then_some(mac)
+}
+
+#[cfg(target_os = "linux")]
+fn current_ssid(interface: Option<&str>) -> Option<String> {
+    if let Some(output) = command_stdout("nmcli", &["-t", "-f", "active,ssid", "dev", "wifi"]) {
+        if let Some(ssid) = output
+            .lines()
+            .find_map(|line| line.strip_prefix("yes:"))
+            .and_then(non_empty)
+        {
+            return Some(ssid);
+        }
+    }
+    let args: Vec<&str> = match interface {
+        Some(interface) => vec![interface, "-r"],
+        None => vec!["-r"],
+    };
+    command_stdout("iwgetid", &args).and_then(|output| non_empty(&output))
+}
+
+#[cfg(target_os = "macos")]
+fn current_ssid(interface: Option<&str>) -> Option<String> {
+    let interface = interface.unwrap_or("en0");
+    let output = command_stdout("networksetup", &["-getairportnetwork", interface])?;
+    // Current Wi-Fi Network: MyNetwork
+    output
+        .split_once(':')
+        .filter(|(label, _)| label.contains("Network"))
+        .and_then(|(_, ssid)| non_empty(ssid))
+}
+
+#[cfg(target_os = "windows")]
+fn current_ssid(_interface: Option<&str>) -> Option<String> {
+    let output = command_stdout("netsh", &["wlan", "show", "interfaces"])?;
+    output.lines().find_map(|line| {
+        let (label, value) = line.split_once(':')?;
+        (label.trim() == "SSID").then(|| non_empty(value)).flatten()
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn current_ssid(_interface: Option<&str>) -> Option<String> {
+    None
+}
@@ -0,0 +1,141 @@
+use crate::audit::{record_audit, AuditAction};
+use crate::network::{current_network, NetworkIdentity};
+use crate::storage::{data_file, read_json, write_json};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const NETWORK_PROFILES_FILE: &str = "network-profiles.json";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkProfile {
+    pub(crate) network_key: String,
+    #[serde(default)]
+    pub(crate) label: String,
+    #[serde(default)]
+    pub(crate) auto_accept: bool,
+    #[serde(default)]
+    pub(crate) require_pair_code: bool,
+    #[serde(default)]
+    pub(crate) require_tls: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CurrentNetworkPayload {
+    network: NetworkIdentity,
+    profile: Option<NetworkProfile>,
+}
+
+fn load_profiles(app: &AppHandle) -> Result<Vec<NetworkProfile>, String> {
+    read_json(&data_file(app, NETWORK_PROFILES_FILE)?)
+}
+
+fn save_profiles(app: &AppHandle, profiles: &[NetworkProfile]) -> Result<(), String> {
+    write_json(&data_file(app, NETWORK_PROFILES_FILE)?, profiles)
+}
+
+/// Detects the current network and returns the profile saved for it, if any.
+pub(crate) fn resolve_active_profile(
+    app: &AppHandle,
+) -> Result<(NetworkIdentity, Option<NetworkProfile>), String> {
+    let network = current_network();
+    let Some(key) = network.key.clone() else {
+        return Ok((network, None));
+    };
+
+    let state = app.state::<AppState>();
+    let _guard = state
+        .network_profiles_lock
+        .lock()
+        .map_err(|_| "failed to lock network profiles".to_string())?;
+    let profile = load_profiles(app)?
+        .into_iter()
+        .find(|profile| profile.network_key == key);
+    Ok((network, profile))
+}
+
+#[tauri::command]
+pub(crate) async fn get_current_network(app: AppHandle) -> Result<CurrentNetworkPayload, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (network, profile) = resolve_active_profile(&app)?;
+        Ok(CurrentNetworkPayload { network, profile })
+    })
+    .await
+    .map_err(|err| format!("failed to join network detection task: {err}"))?
+}
+
+#[tauri::command]
+pub(crate) fn list_network_profiles(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<NetworkProfile>, String> {
+    let _guard = state
+        .network_profiles_lock
+        .lock()
+        .map_err(|_| "failed to lock network profiles".to_string())?;
+    load_profiles(&app)
+}
+
+#[tauri::command]
+pub(crate) fn set_network_profile(
+    app: AppHandle,
+    state: State<AppState>,
+    profile: NetworkProfile,
+) -> Result<NetworkProfile, String> {
+    let network_key = profile.network_key.trim().to_string();
+    if network_key.is_empty() {
+        return Err("network key is required".to_string());
+    }
+    let profile = NetworkProfile {
+        network_key,
+        label: profile.label.trim().to_string(),
+        ..profile
+    };
+
+    let _guard = state
+        .network_profiles_lock
+        .lock()
+        .map_err(|_| "failed to lock network profiles".to_string())?;
+    let mut profiles = load_profiles(&app)?;
+    match profiles
+        .iter_mut()
+        .find(|item| item.network_key == profile.network_key)
+    {
+        Some(existing) => *existing = profile.clone(),
+        None => profiles.push(profile.clone()),
+    }
+    save_profiles(&app, &profiles)?;
+    record_audit(
+        &app,
+        AuditAction::NetworkProfileSaved,
+        serde_json::to_value(&profile).unwrap_or_default(),
+    );
+    Ok(profile)
+}
+
+#[tauri::command]
+pub(crate) fn delete_network_profile(
+    app: AppHandle,
+    state: State<AppState>,
+    network_key: String,
+) -> Result<bool, String> {
+    let _guard = state
+        .network_profiles_lock
+        .lock()
+        .map_err(|_| "failed to lock network profiles".to_string())?;
+    let mut profiles = load_profiles(&app)?;
+    let before = profiles.len();
+    profiles.retain(|item| item.network_key != network_key);
+    if profiles.len() == before {
+        return Ok(false);
+    }
+    save_profiles(&app, &profiles)?;
+    record_audit(
+        &app,
+        AuditAction::NetworkProfileDeleted,
+        serde_json::json!({ "networkKey": network_key }),
+    );
+    Ok(true)
+}
@@ -0,0 +1,282 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::network::{current_network, NetworkIdentity};
+use crate::parser::DiscoverDevice;
+use crate::{
+    cli_control, discover_devices, emit_system_log, inspect_listen_state, kill_switch,
+    listen_session, listen_state_payload, settings, AppState,
+};
+use if_addrs::get_if_addrs;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::net::TcpStream;
+use std::process::Child;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// A check that comes this much later than planned means the machine was
+/// asleep: the thread does not run while suspended, the wall clock does.
+const WAKE_GAP: Duration = Duration::from_secs(30);
+const DISCOVERY_TIMEOUT_MS: u64 = 3000;
+const LISTENER_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum NetworkChangeReason {
+    Sleep,
+    Wake,
+    /// Addresses or the network itself changed, e.g. another Wi-Fi.
+    Network,
+}
+
+/// Sent as the `networkChanged` app event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NetworkChangedPayload {
+    reason: NetworkChangeReason,
+    /// `None` on sleep; detecting it runs tools the suspend would cut off.
+    network: Option<NetworkIdentity>,
+    /// Devices found after the change; `None` when discovery did not run
+    /// or failed, in which case the last list is out of date.
+    devices: Option<Vec<DiscoverDevice>>,
+    devices_stale: bool,
+    /// Whether the running listener still accepts connections; `None`
+    /// without a listener.
+    listener_reachable: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct NetworkWatchState {
+    /// Between a sleep notice and the following wake.
+    asleep: bool,
+    /// Set by the sleep monitor on resume, handled by the next check once
+    /// the network had a moment to come back.
+    wake_pending: bool,
+    sleep_monitor: Option<Child>,
+}
+
+fn local_addresses() -> BTreeSet<String> {
+    get_if_addrs()
+        .map(|ifaces| {
+            ifaces
+                .into_iter()
+                .filter(|iface| !iface.is_loopback())
+                .map(|iface| iface.ip().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn listener_running(state: &AppState) -> bool {
+    state.listen_child.lock().is_ok_and(|child| child.is_some())
+}
+
+/// Turns the listener's announcements on or off; they stay off while the
+/// user hides the device from discovery.
+fn set_announce(app: &AppHandle, announce: bool) {
+    let state = app.state::<AppState>();
+    if !listener_running(state.inner()) {
+        return;
+    }
+    let hidden = settings::load_settings(app)
+        .map(|settings| settings.hidden_from_discovery)
+        .unwrap_or(false);
+    if announce && hidden {
+        return;
+    }
+    if let Err(err) = cli_control::notify(state.inner(), "update", json!({ "announce": announce }))
+    {
+        emit_system_log(app, format!("failed to update announcements: {err}"));
+    }
+}
+
+fn on_sleep(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    if let Ok(mut watch) = state.network_watch.lock() {
+        if watch.asleep {
+            return;
+        }
+        watch.asleep = true;
+    }
+    set_announce(app, false);
+    emit_system_log(app, "系统即将休眠，已暂停广播");
+    emit_app_event(
+        app,
+        AppEvent::NetworkChanged(NetworkChangedPayload {
+            reason: NetworkChangeReason::Sleep,
+            network: None,
+            devices: None,
+            devices_stale: true,
+            listener_reachable: None,
+        }),
+    );
+}
+
+/// Re-emits the listener state, which lists the addresses it is reachable
+/// on now, and checks the port still accepts connections.
+fn revalidate_listener(app: &AppHandle) -> Option<bool> {
+    let state = app.state::<AppState>();
+    let snapshot = inspect_listen_state(&state).ok()?;
+    emit_app_event(
+        app,
+        AppEvent::ListenState(listen_state_payload(app, snapshot.running, snapshot.pid)),
+    );
+    if !snapshot.running {
+        return None;
+    }
+    let port = (*state.listen_port.lock().ok()?)?;
+    let address = listen_session::local_address(state.inner(), port);
+    let reachable = TcpStream::connect_timeout(&address, LISTENER_PROBE_TIMEOUT).is_ok();
+    if !reachable {
+        emit_system_log(app, format!("接收端口 {port} 已无法连接，请重新启动接收"));
+    }
+    Some(reachable)
+}
+
+fn refresh(app: &AppHandle, reason: NetworkChangeReason, network: NetworkIdentity) {
+    let state = app.state::<AppState>();
+    if let Ok(mut watch) = state.network_watch.lock() {
+        watch.asleep = false;
+        watch.wake_pending = false;
+    }
+    match reason {
+        NetworkChangeReason::Wake => emit_system_log(app, "系统已唤醒，正在重新发现设备"),
+        _ => emit_system_log(
+            app,
+            format!(
+                "网络已变化（{}），正在重新发现设备",
+                network.ssid.as_deref().unwrap_or("未知网络")
+            ),
+        ),
+    }
+    set_announce(app, true);
+    let listener_reachable = revalidate_listener(app);
+
+    // Discovery is left alone while the network kill switch is on.
+    let devices = if kill_switch::ensure_network_allowed(state.inner()).is_ok() {
+        tauri::async_runtime::block_on(discover_devices(app.clone(), DISCOVERY_TIMEOUT_MS))
+            .inspect_err(|err| emit_system_log(app, format!("failed to rediscover devices: {err}")))
+            .ok()
+    } else {
+        None
+    };
+    emit_app_event(
+        app,
+        AppEvent::NetworkChanged(NetworkChangedPayload {
+            reason,
+            network: Some(network),
+            devices_stale: devices.is_none(),
+            devices,
+            listener_reachable,
+        }),
+    );
+}
+
+/// Follows logind's `PrepareForSleep`, which comes before the suspend, so
+/// announcements stop while peers can still see them go. Without it, sleep
+/// is only noticed afterwards from the gap between checks.
+#[cfg(target_os = "linux")]
+fn spawn_sleep_monitor(app: &AppHandle) {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    let spawned = Command::new("gdbus")
+        .args([
+            "monitor",
+            "--system",
+            "--dest",
+            "org.freedesktop.login1",
+            "--object-path",
+            "/org/freedesktop/login1",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = spawned else {
+        return;
+    };
+    let Some(stdout) = child.stdout.take() else {
+        let _ = child.kill();
+        return;
+    };
+    if let Ok(mut watch) = app.state::<AppState>().network_watch.lock() {
+        watch.sleep_monitor = Some(child);
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if !line.contains("PrepareForSleep") {
+                continue;
+            }
+            if line.contains("(true") {
+                on_sleep(&app);
+            } else if line.contains("(false") {
+                if let Ok(mut watch) = app.state::<AppState>().network_watch.lock() {
+                    watch.wake_pending = true;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+fn spawn_sleep_monitor(_app: &AppHandle) {}
+
+/// Ends the sleep monitor; part of child cleanup on exit.
+pub(crate) fn stop_sleep_monitor(state: &AppState) {
+    let child = state
+        .network_watch
+        .lock()
+        .ok()
+        .and_then(|mut watch| watch.sleep_monitor.take());
+    if let Some(mut child) = child {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Watches for sleep, wake and network switches. After waking or on a new
+/// network, announcements resume, discovery runs again and the listener is
+/// checked; each step is reported as a `networkChanged` app event.
+pub(crate) fn spawn_watch_task(app: AppHandle) {
+    spawn_sleep_monitor(&app);
+    thread::spawn(move || {
+        let mut addresses = local_addresses();
+        let mut network_key = current_network().key;
+        let mut last_check = SystemTime::now();
+        loop {
+            thread::sleep(CHECK_INTERVAL);
+            let state = app.state::<AppState>();
+            if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+                return;
+            }
+
+            let now = SystemTime::now();
+            let gap = now.duration_since(last_check).unwrap_or_default();
+            last_check = now;
+            let wake_pending = state
+                .network_watch
+                .lock()
+                .map(|mut watch| std::mem::take(&mut watch.wake_pending))
+                .unwrap_or(false);
+            let current_addresses = local_addresses();
+            let network = current_network();
+            let changed = current_addresses != addresses || network.key != network_key;
+            addresses = current_addresses;
+            network_key = network.key.clone();
+
+            if wake_pending || gap > CHECK_INTERVAL + WAKE_GAP {
+                refresh(&app, NetworkChangeReason::Wake, network);
+            } else if changed {
+                refresh(&app, NetworkChangeReason::Network, network);
+            }
+        }
+    });
+}
@@ -0,0 +1,226 @@
+use crate::identity::{load_or_create_identity, DEVICE_IDENTITY_FILE};
+use crate::reachability::diagnose_reachability;
+use crate::storage::{now_ms, read_json, shared_dir, shared_file, write_json};
+use crate::{ports, AppState};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+const ONBOARDING_FILE: &str = "onboarding.json";
+const TLS_DIR: &str = "tls";
+const TLS_CERT_FILE: &str = "cert.pem";
+const TLS_KEY_FILE: &str = "key.pem";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum OnboardingStep {
+    Identity,
+    TlsIdentity,
+    Port,
+    Reachability,
+}
+
+const STEPS: [OnboardingStep; 4] = [
+    OnboardingStep::Identity,
+    OnboardingStep::TlsIdentity,
+    OnboardingStep::Port,
+    OnboardingStep::Reachability,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlsIdentity {
+    cert_path: String,
+    key_path: String,
+    /// SHA-256 of the certificate, as peers pin it with `--tls-fingerprint`.
+    fingerprint: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct OnboardingRecord {
+    completed_steps: Vec<OnboardingStep>,
+    tls: Option<TlsIdentity>,
+    port: Option<u16>,
+    /// The last `ReachabilityReport`, kept as sent to the UI.
+    reachability: Option<Value>,
+    completed_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StepStatus {
+    step: OnboardingStep,
+    done: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OnboardingState {
+    /// No earlier install left an identity behind and the wizard never
+    /// finished.
+    first_run: bool,
+    complete: bool,
+    steps: Vec<StepStatus>,
+    next_step: Option<OnboardingStep>,
+    device_name: Option<String>,
+    tls: Option<TlsIdentity>,
+    port: Option<u16>,
+    reachability: Option<Value>,
+}
+
+fn load_record(app: &AppHandle) -> Result<OnboardingRecord, String> {
+    read_json(&shared_file(app, ONBOARDING_FILE)?)
+}
+
+fn state_from(app: &AppHandle, record: OnboardingRecord) -> Result<OnboardingState, String> {
+    let identity_exists = shared_file(app, DEVICE_IDENTITY_FILE)?.exists();
+    let steps: Vec<StepStatus> = STEPS
+        .iter()
+        .map(|step| StepStatus {
+            step: *step,
+            done: record.completed_steps.contains(step),
+        })
+        .collect();
+    let next_step = steps
+        .iter()
+        .find(|status| !status.done)
+        .map(|status| status.step);
+    Ok(OnboardingState {
+        first_run: record.completed_at_ms.is_none() && !identity_exists,
+        complete: record.completed_at_ms.is_some(),
+        steps,
+        next_step,
+        device_name: identity_exists
+            .then(|| load_or_create_identity(app).ok())
+            .flatten()
+            .map(|identity| identity.effective_name()),
+        tls: record.tls,
+        port: record.port,
+        reachability: record.reachability,
+    })
+}
+
+fn fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Creates a self-signed certificate for `--tls-cert`/`--tls-key`, keeping
+/// an existing one so peers that pinned it keep trusting this device.
+fn ensure_tls_identity(app: &AppHandle, name: &str) -> Result<TlsIdentity, String> {
+    let dir = shared_dir(app)?.join(TLS_DIR);
+    let cert_path = dir.join(TLS_CERT_FILE);
+    let key_path = dir.join(TLS_KEY_FILE);
+    if !cert_path.is_file() || !key_path.is_file() {
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+        let generated =
+            rcgen::generate_simple_self_signed(vec![name.to_string(), "localhost".to_string()])
+                .map_err(|err| format!("failed to generate TLS certificate: {err}"))?;
+        write_private(&key_path, &generated.key_pair.serialize_pem())?;
+        fs::write(&cert_path, generated.cert.pem())
+            .map_err(|err| format!("failed to write {}: {err}", cert_path.display()))?;
+    }
+
+    let pem = fs::read_to_string(&cert_path)
+        .map_err(|err| format!("failed to read {}: {err}", cert_path.display()))?;
+    let der = CertificateDer::from_pem_slice(pem.as_bytes())
+        .map_err(|err| format!("failed to parse {}: {err}", cert_path.display()))?;
+    Ok(TlsIdentity {
+        cert_path: cert_path.to_string_lossy().to_string(),
+        key_path: key_path.to_string_lossy().to_string(),
+        fingerprint: fingerprint(&der),
+    })
+}
+
+pub(crate) fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents)
+        .map_err(|err| format!("failed to write {}: {err}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .map_err(|err| format!("failed to protect {}: {err}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn get_onboarding_state(app: AppHandle) -> Result<OnboardingState, String> {
+    let record = load_record(&app)?;
+    state_from(&app, record)
+}
+
+/// Runs `step` against the real system and records the outcome: the device
+/// and TLS identities are created, a free port is picked, and reachability
+/// is probed on that port with a short-lived listener when nothing else
+/// holds it. Finishing the last step marks onboarding complete.
+#[tauri::command]
+pub(crate) async fn complete_onboarding_step(
+    app: AppHandle,
+    step: OnboardingStep,
+) -> Result<OnboardingState, String> {
+    let mut record = load_record(&app)?;
+    match step {
+        OnboardingStep::Identity => {
+            load_or_create_identity(&app)?;
+        }
+        OnboardingStep::TlsIdentity => {
+            let name = load_or_create_identity(&app)?.effective_name();
+            let worker = app.clone();
+            record.tls = Some(
+                tauri::async_runtime::spawn_blocking(move || ensure_tls_identity(&worker, &name))
+                    .await
+                    .map_err(|err| format!("failed to join TLS task: {err}"))??,
+            );
+        }
+        OnboardingStep::Port => {
+            record.port = Some(ports::free_high_port()?);
+        }
+        OnboardingStep::Reachability => {
+            let port = match record.port {
+                Some(port) => port,
+                None => ports::free_high_port()?,
+            };
+            // Bound but never accepted from: enough for the local probes.
+            let probe_listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).ok();
+            let report = diagnose_reachability(port).await?;
+            record.reachability = Some(
+                serde_json::to_value(report)
+                    .map_err(|err| format!("failed to encode reachability report: {err}"))?,
+            );
+            drop(probe_listener);
+            record.port = Some(port);
+        }
+    }
+    if !record.completed_steps.contains(&step) {
+        record.completed_steps.push(step);
+    }
+    if record.completed_at_ms.is_none()
+        && STEPS
+            .iter()
+            .all(|step| record.completed_steps.contains(step))
+    {
+        record.completed_at_ms = Some(now_ms());
+    }
+
+    let state = app.state::<AppState>();
+    {
+        let _guard = state
+            .settings_lock
+            .lock()
+            .map_err(|_| "failed to lock app settings".to_string())?;
+        write_json(&shared_file(&app, ONBOARDING_FILE)?, &record)?;
+    }
+    state_from(&app, record)
+}
@@ -0,0 +1,507 @@
+//! A device list an office publishes as a JSON or CSV file, on a web server
+//! or a shared drive, so listeners on other VLANs, which mDNS does not
+//! reach, still show up. The list is fetched now and then and kept on disk;
+//! its devices are merged into discovery results, and listed devices the
+//! network also announces are marked with their group.
+//!
+//! JSON is a list of `{ "name", "host", "port"?, "fingerprint"?, "group"? }`
+//! objects, or an object with such a list under `devices`. CSV has a header
+//! row naming the same columns, in any order.
+
+use crate::dry_run::normalize_fingerprint;
+use crate::parser::{clean_text, DeviceSource, DirectoryListing, DiscoverDevice};
+use crate::settings::{load_settings, update_settings};
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{emit_system_log, kill_switch, AppState};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub(crate) const ORG_DIRECTORY_FILE: &str = "org-directory.json";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// Far more than any office's device list; guards against pointing the
+/// setting at something else.
+const MAX_DIRECTORY_BYTES: u64 = 4 * 1024 * 1024;
+const DEFAULT_PORT: u16 = 37373;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct OrgDirectorySettings {
+    /// `http(s)://` URL or local path of the list; empty turns it off.
+    pub(crate) source: String,
+    pub(crate) refresh_minutes: u32,
+}
+
+impl Default for OrgDirectorySettings {
+    fn default() -> Self {
+        Self {
+            source: String::new(),
+            refresh_minutes: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DirectoryEntry {
+    name: String,
+    host: String,
+    port: u16,
+    /// Normalized to lowercase hex without colons.
+    fingerprint: Option<String>,
+    group: Option<String>,
+}
+
+/// The last fetched list, which stays in use while the source is
+/// unreachable.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct StoredDirectory {
+    /// The source the entries came from; a changed setting refetches.
+    source: String,
+    entries: Vec<DirectoryEntry>,
+    fetched_at_ms: Option<u64>,
+    /// The last attempt, successful or not.
+    checked_at_ms: Option<u64>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawEntry {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    host: String,
+    #[serde(default)]
+    port: Option<Value>,
+    #[serde(default)]
+    fingerprint: Option<String>,
+    #[serde(default)]
+    group: Option<String>,
+}
+
+fn load(app: &AppHandle) -> Result<StoredDirectory, String> {
+    read_json(&data_file(app, ORG_DIRECTORY_FILE)?)
+}
+
+fn save(app: &AppHandle, stored: &StoredDirectory) -> Result<(), String> {
+    write_json(&data_file(app, ORG_DIRECTORY_FILE)?, stored)
+}
+
+fn is_url(source: &str) -> bool {
+    let lower = source.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+fn fetch(source: &str) -> Result<String, String> {
+    if is_url(source) {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(FETCH_TIMEOUT))
+            .build()
+            .new_agent();
+        return agent
+            .get(source)
+            .call()
+            .map_err(|err| format!("failed to fetch {source}: {err}"))?
+            .body_mut()
+            .with_config()
+            .limit(MAX_DIRECTORY_BYTES)
+            .read_to_string()
+            .map_err(|err| format!("failed to read {source}: {err}"));
+    }
+    let size = fs::metadata(source)
+        .map_err(|err| format!("failed to read {source}: {err}"))?
+        .len();
+    if size > MAX_DIRECTORY_BYTES {
+        return Err(format!(
+            "{source} is larger than {MAX_DIRECTORY_BYTES} bytes"
+        ));
+    }
+    fs::read_to_string(source).map_err(|err| format!("failed to read {source}: {err}"))
+}
+
+/// Cleans an entry up; `None` for entries without a name or host, with a
+/// bad port or with a fingerprint that is not SHA-256 hex.
+fn entry(raw: RawEntry) -> Option<DirectoryEntry> {
+    let text = |value: &str| Some(clean_text(value.trim())).filter(|value| !value.is_empty());
+    let port = match raw.port {
+        None | Some(Value::Null) => DEFAULT_PORT,
+        Some(Value::Number(number)) => u16::try_from(number.as_u64()?).ok()?,
+        Some(Value::String(text)) if text.trim().is_empty() => DEFAULT_PORT,
+        Some(Value::String(text)) => text.trim().parse().ok()?,
+        Some(_) => return None,
+    };
+    let fingerprint = match raw.fingerprint.as_deref().map(normalize_fingerprint) {
+        Some(value) if value.is_empty() => None,
+        Some(value) if value.len() == 64 && value.chars().all(|ch| ch.is_ascii_hexdigit()) => {
+            Some(value)
+        }
+        Some(_) => return None,
+        None => None,
+    };
+    Some(DirectoryEntry {
+        name: text(&raw.name)?,
+        host: text(&raw.host)?,
+        port: (port != 0).then_some(port)?,
+        fingerprint,
+        group: raw.group.as_deref().and_then(text),
+    })
+}
+
+/// Splits one CSV line, honouring double quotes and `""` inside them.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let field = fields.last_mut().expect("fields is never empty");
+        match ch {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            _ => field.push(ch),
+        }
+    }
+    fields
+}
+
+fn parse_csv(raw: &str) -> Result<Vec<RawEntry>, String> {
+    let mut lines = raw
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty());
+    let header: Vec<String> = csv_fields(lines.next().unwrap_or_default())
+        .iter()
+        .map(|column| column.trim().to_ascii_lowercase())
+        .collect();
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let (Some(name), Some(host)) = (column("name"), column("host")) else {
+        return Err("the CSV header must name `name` and `host` columns".to_string());
+    };
+    let (port, fingerprint, group) = (column("port"), column("fingerprint"), column("group"));
+    Ok(lines
+        .map(|line| {
+            let fields = csv_fields(line);
+            let field = |index: Option<usize>| index.and_then(|index| fields.get(index)).cloned();
+            RawEntry {
+                name: field(Some(name)).unwrap_or_default(),
+                host: field(Some(host)).unwrap_or_default(),
+                port: field(port).map(Value::String),
+                fingerprint: field(fingerprint),
+                group: field(group),
+            }
+        })
+        .collect())
+}
+
+/// Entries that do not make sense are left out rather than failing the
+/// whole list; a list that is neither JSON nor CSV with a header is an
+/// error.
+fn parse(raw: &str) -> Result<Vec<DirectoryEntry>, String> {
+    let raw = raw.trim_start_matches('\u{feff}').trim();
+    let entries = if raw.starts_with('[') || raw.starts_with('{') {
+        let mut value: Value = serde_json::from_str(raw)
+            .map_err(|err| format!("failed to parse directory JSON: {err}"))?;
+        if let Some(devices) = value.get_mut("devices") {
+            value = devices.take();
+        }
+        let Value::Array(items) = value else {
+            return Err("the directory JSON must be a list of devices".to_string());
+        };
+        items
+            .into_iter()
+            .filter_map(|item| serde_json::from_value(item).ok())
+            .collect()
+    } else {
+        parse_csv(raw)?
+    };
+    let mut parsed: Vec<DirectoryEntry> = Vec::new();
+    for entry in entries.into_iter().filter_map(entry) {
+        if !parsed.contains(&entry) {
+            parsed.push(entry);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Fetches the list from `source` and stores it. A failure is recorded
+/// and the previous list kept.
+fn refresh_from(app: &AppHandle, source: &str) -> Result<StoredDirectory, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .org_directory_lock
+        .lock()
+        .map_err(|_| "failed to lock org directory".to_string())?;
+    kill_switch::ensure_network_allowed(state.inner())?;
+    let mut stored = load(app)?;
+    if stored.source != source {
+        // Another list: the old entries no longer apply.
+        stored = StoredDirectory {
+            source: source.to_string(),
+            ..StoredDirectory::default()
+        };
+    }
+    let now = now_ms();
+    stored.checked_at_ms = Some(now);
+    let result = fetch(source).and_then(|raw| parse(&raw));
+    // A fetch the kill-switch caught in flight is dropped.
+    kill_switch::ensure_network_allowed(state.inner())?;
+    match &result {
+        Ok(entries) => {
+            stored.entries = entries.clone();
+            stored.fetched_at_ms = Some(now);
+            stored.last_error = None;
+        }
+        Err(err) => stored.last_error = Some(err.clone()),
+    }
+    save(app, &stored)?;
+    result.map(|_| stored)
+}
+
+/// Whether the list from `source` is due for another fetch.
+fn is_due(stored: &StoredDirectory, settings: &OrgDirectorySettings) -> bool {
+    if stored.source != settings.source.trim() {
+        return true;
+    }
+    let interval_ms = u64::from(settings.refresh_minutes.max(1)) * 60 * 1000;
+    stored
+        .checked_at_ms
+        .is_none_or(|checked| now_ms().saturating_sub(checked) >= interval_ms)
+}
+
+pub(crate) fn spawn_refresh_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        // Resumes with the next check once the kill-switch is released.
+        if state.network_halted.load(Ordering::SeqCst) {
+            thread::sleep(CHECK_INTERVAL);
+            continue;
+        }
+        let settings = load_settings(&app)
+            .map(|settings| settings.org_directory)
+            .unwrap_or_default();
+        let source = settings.source.trim();
+        if !source.is_empty() && load(&app).is_ok_and(|stored| is_due(&stored, &settings)) {
+            if let Err(err) = refresh_from(&app, source) {
+                emit_system_log(&app, format!("failed to refresh org directory: {err}"));
+            }
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+fn same_host(device: &DiscoverDevice, host: &str) -> bool {
+    device.host.eq_ignore_ascii_case(host)
+        || device
+            .addresses
+            .iter()
+            .any(|address| address.eq_ignore_ascii_case(host))
+}
+
+/// Marks the discovered devices the directory lists and adds the listed
+/// devices discovery did not find. An entry with a fingerprint matches
+/// only the device serving that certificate, so a different device at a
+/// listed address is not taken for the listed one; other entries match by
+/// address or name.
+pub(crate) fn merge_entries(devices: &mut Vec<DiscoverDevice>, entries: &[DirectoryEntry]) {
+    for entry in entries {
+        let found = devices.iter_mut().find(|device| match &entry.fingerprint {
+            Some(fingerprint) => device
+                .fingerprint
+                .as_deref()
+                .is_some_and(|known| normalize_fingerprint(known) == *fingerprint),
+            None => same_host(device, &entry.host) || device.name.eq_ignore_ascii_case(&entry.name),
+        });
+        let listing = DirectoryListing {
+            group: entry.group.clone(),
+        };
+        match found {
+            Some(device) => device.directory = Some(listing),
+            None => devices.push(DiscoverDevice {
+                name: entry.name.clone(),
+                host: entry.host.clone(),
+                port: entry.port,
+                addresses: vec![entry.host.clone()],
+                id: None,
+                fingerprint: entry.fingerprint.clone(),
+                version: None,
+                protocol_version: None,
+                tls: entry.fingerprint.is_some().then_some(true),
+                pair_code_required: None,
+                capabilities: Vec::new(),
+                appearance: Default::default(),
+                source: DeviceSource::LocalSent,
+                routes: Vec::new(),
+                proximity: None,
+                directory: Some(listing),
+            }),
+        }
+    }
+}
+
+/// Merges the stored list into discovery results; nothing when the
+/// directory is off.
+pub(crate) fn merge(app: &AppHandle, devices: &mut Vec<DiscoverDevice>) {
+    let enabled =
+        load_settings(app).is_ok_and(|settings| !settings.org_directory.source.trim().is_empty());
+    if !enabled {
+        return;
+    }
+    if let Ok(stored) = load(app) {
+        merge_entries(devices, &stored.entries);
+    }
+}
+
+#[tauri::command]
+pub(crate) fn get_org_directory(app: AppHandle) -> Result<StoredDirectory, String> {
+    load(&app)
+}
+
+/// Saves the settings and fetches the list at once, so a mistyped source
+/// shows up right away; the settings are kept even if the fetch fails.
+#[tauri::command]
+pub(crate) async fn set_org_directory(
+    app: AppHandle,
+    directory: OrgDirectorySettings,
+) -> Result<StoredDirectory, String> {
+    if directory.refresh_minutes == 0 {
+        return Err("refresh at least every 1 minute".to_string());
+    }
+    let source = directory.source.trim().to_string();
+    update_settings(&app, |settings| {
+        settings.org_directory = OrgDirectorySettings {
+            source: source.clone(),
+            ..directory
+        };
+        Ok(())
+    })?;
+    if source.is_empty() {
+        return load(&app);
+    }
+    tauri::async_runtime::spawn_blocking(move || refresh_from(&app, &source))
+        .await
+        .map_err(|err| format!("failed to join org directory refresh: {err}"))?
+}
+
+#[tauri::command]
+pub(crate) async fn refresh_org_directory(app: AppHandle) -> Result<StoredDirectory, String> {
+    let source = load_settings(&app)?.org_directory.source;
+    if source.trim().is_empty() {
+        return Err("no org directory is set up".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || refresh_from(&app, source.trim()))
+        .await
+        .map_err(|err| format!("failed to join org directory refresh: {err}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str = "AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89:AB:CD:EF:01:23:45:67:89";
+
+    #[test]
+    fn json_and_csv_lists_parse_alike() {
+        let json = format!(
+            r#"{{ "devices": [
+                {{ "name": "Reception PC", "host": "10.1.2.3", "group": "Front desk" }},
+                {{ "name": "Print room", "host": "print.office.lan", "port": "40000", "fingerprint": "{FINGERPRINT}" }},
+                {{ "name": "", "host": "10.1.2.4" }},
+                {{ "name": "Bad port", "host": "10.1.2.5", "port": 70000 }}
+            ] }}"#
+        );
+        let csv = format!(
+            "\u{feff}host,name,group,fingerprint,port\r\n\
+             10.1.2.3,Reception PC,Front desk,,\r\n\
+             print.office.lan,\"Print room\",,{FINGERPRINT},40000\r\n\
+             10.1.2.4,,,,\r\n\
+             10.1.2.5,Bad port,,,70000\r\n"
+        );
+        let entries = parse(&json).unwrap();
+        assert_eq!(parse(&csv).unwrap(), entries);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].port, DEFAULT_PORT);
+        assert_eq!(entries[0].group.as_deref(), Some("Front desk"));
+        assert_eq!(entries[1].port, 40000);
+        assert_eq!(
+            entries[1].fingerprint.as_deref(),
+            Some(normalize_fingerprint(FINGERPRINT).as_str())
+        );
+    }
+
+    #[test]
+    fn malformed_lists_are_rejected() {
+        assert!(parse(r#"{ "devices": 3 }"#).is_err());
+        assert!(parse("hostname,address\nlaptop,10.0.0.1").is_err());
+        assert!(
+            parse(r#"[{ "name": "x", "host": "y", "fingerprint": "abc" }]"#)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn csv_fields_keep_quoted_commas() {
+        assert_eq!(
+            csv_fields(r#"a,"b, ""c""",,d"#),
+            ["a", r#"b, "c""#, "", "d"]
+        );
+    }
+
+    fn discovered(name: &str, host: &str, fingerprint: Option<&str>) -> DiscoverDevice {
+        DiscoverDevice {
+            name: name.to_string(),
+            host: host.to_string(),
+            port: DEFAULT_PORT,
+            addresses: vec![host.to_string()],
+            id: None,
+            fingerprint: fingerprint.map(str::to_string),
+            version: None,
+            protocol_version: None,
+            tls: None,
+            pair_code_required: None,
+            capabilities: Vec::new(),
+            appearance: Default::default(),
+            source: DeviceSource::LocalSent,
+            routes: Vec::new(),
+            proximity: None,
+            directory: None,
+        }
+    }
+
+    #[test]
+    fn listed_devices_are_marked_or_added() {
+        let entries = parse(&format!(
+            "name,host,fingerprint,group\n\
+             Laptop,10.0.0.5,,Sales\n\
+             Server,10.0.9.9,{FINGERPRINT},IT\n"
+        ))
+        .unwrap();
+        let mut devices = vec![
+            discovered("laptop", "192.168.1.5", None),
+            // At the listed address but serving another certificate.
+            discovered("Server", "10.0.9.9", Some(&"0".repeat(64))),
+        ];
+        merge_entries(&mut devices, &entries);
+        assert_eq!(devices.len(), 3);
+        assert_eq!(
+            devices[0].directory.as_ref().unwrap().group.as_deref(),
+            Some("Sales")
+        );
+        assert!(devices[1].directory.is_none());
+        assert_eq!(devices[2].host, "10.0.9.9");
+        assert_eq!(devices[2].tls, Some(true));
+    }
+}
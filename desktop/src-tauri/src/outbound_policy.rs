@@ -0,0 +1,272 @@
+//! Where sends may go. Every send, text message and clipboard push passes
+//! through `enforce` or `enforce_destination` before anything leaves the
+//! machine, so a destination the policy rules out fails with a policy error
+//! instead of a network one. The user's own policy sits on top of whatever
+//! a managed configuration requires.
+
+use crate::app_data;
+use crate::delivery::DeliveryTarget;
+use crate::managed_config::{self, Subnet};
+use crate::settings::{load_settings, update_settings};
+use crate::storage::read_json;
+use crate::SendRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct OutboundPolicy {
+    /// CIDR ranges receivers must be in, e.g. `192.168.1.0/24`; empty
+    /// allows any.
+    pub(crate) allowed_subnets: Vec<String>,
+    /// Only send over TLS to receivers whose certificate is pinned or
+    /// already in the known hosts, never on first use.
+    pub(crate) trusted_only: bool,
+    /// Keep sends on local networks: refuse public addresses, overlay
+    /// addresses whose traffic may be relayed over the internet and
+    /// delivery targets that are internet services.
+    pub(crate) block_internet_relay: bool,
+}
+
+/// The parts of an outgoing request the policy looks at.
+pub(crate) struct Destination<'a> {
+    pub(crate) host: Option<&'a str>,
+    pub(crate) port: u16,
+    pub(crate) pair_code: Option<&'a str>,
+    pub(crate) tls: bool,
+    pub(crate) tls_fingerprint: Option<&'a str>,
+    pub(crate) tls_tofu: bool,
+    pub(crate) tls_known_hosts: Option<&'a str>,
+}
+
+impl<'a> From<&'a SendRequest> for Destination<'a> {
+    fn from(request: &'a SendRequest) -> Self {
+        Self {
+            host: request.host.as_deref(),
+            port: request.port,
+            pair_code: request.pair_code.as_deref(),
+            tls: request.tls.unwrap_or(false),
+            tls_fingerprint: request.tls_fingerprint.as_deref(),
+            tls_tofu: request.tls_tofu.unwrap_or(false),
+            tls_known_hosts: request.tls_known_hosts.as_deref(),
+        }
+    }
+}
+
+fn violation(reason: impl std::fmt::Display) -> String {
+    format!("blocked by outbound policy: {reason}")
+}
+
+/// Private, loopback and link-local addresses; the shared range overlay
+/// networks such as Tailscale use is not, since their traffic may go
+/// through a relay.
+fn is_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local(),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_local(IpAddr::V4(v4)),
+            None => {
+                v6.is_loopback()
+                    || (v6.segments()[0] & 0xfe00) == 0xfc00
+                    || (v6.segments()[0] & 0xffc0) == 0xfe80
+            }
+        },
+    }
+}
+
+fn known_hosts_path(app: &AppHandle, destination: &Destination) -> Result<PathBuf, String> {
+    match destination
+        .tls_known_hosts
+        .filter(|path| !path.trim().is_empty())
+    {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => app_data::known_hosts_path(app),
+    }
+}
+
+/// TLS with a pinned fingerprint, or trust-on-first-use for a receiver
+/// whose fingerprint is already known so it is checked, not learned.
+fn ensure_trusted(app: &AppHandle, destination: &Destination, host: &str) -> Result<(), String> {
+    if !destination.tls {
+        return Err(violation("sends must use TLS to a trusted receiver"));
+    }
+    let pinned = destination
+        .tls_fingerprint
+        .is_some_and(|value| !value.trim().is_empty());
+    if pinned {
+        return Ok(());
+    }
+    let endpoint = format!("{}:{}", host.to_lowercase(), destination.port);
+    let known = destination.tls_tofu
+        && read_json::<BTreeMap<String, String>>(&known_hosts_path(app, destination)?)?
+            .contains_key(&endpoint);
+    if known {
+        return Ok(());
+    }
+    Err(violation(format!(
+        "{endpoint} has no pinned or previously trusted TLS fingerprint"
+    )))
+}
+
+/// The addresses a send to `host` may reach. Looks names up, so call it
+/// off the async runtime.
+fn addresses(host: &str, port: u16) -> Result<Vec<IpAddr>, String> {
+    let host = host.trim().trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse() {
+        return Ok(vec![ip]);
+    }
+    (host, port)
+        .to_socket_addrs()
+        .map(|addresses| addresses.map(|address| address.ip()).collect())
+        .map_err(|err| format!("failed to resolve {host}: {err}"))
+}
+
+/// Checks a send against the managed configuration and the user's
+/// outbound policy. `delivery` is the delivery target the send goes to,
+/// if any.
+pub(crate) fn enforce(
+    app: &AppHandle,
+    request: &SendRequest,
+    delivery: Option<&DeliveryTarget>,
+) -> Result<(), String> {
+    let policy = load_settings(app)?.outbound_policy;
+    if let Some(target) = delivery {
+        // Storage services, not peers: only the internet rule applies.
+        if policy.block_internet_relay && target.is_internet_service() {
+            return Err(violation(format!(
+                "{} is an internet service",
+                target.label()
+            )));
+        }
+        return Ok(());
+    }
+    enforce_destination(app, &Destination::from(request))
+}
+
+/// Checks anything sent to a peer against the managed configuration and
+/// the user's outbound policy.
+pub(crate) fn enforce_destination(
+    app: &AppHandle,
+    destination: &Destination,
+) -> Result<(), String> {
+    let managed = managed_config::load()?;
+    let policy = load_settings(app)?.outbound_policy;
+    if managed.requires_tls() && !destination.tls {
+        return Err("the managed configuration requires TLS for sending".to_string());
+    }
+    let has_pair_code = destination
+        .pair_code
+        .is_some_and(|value| !value.trim().is_empty());
+    if managed.requires_pair_code() && !has_pair_code {
+        return Err("the managed configuration requires a pair code for sending".to_string());
+    }
+
+    let subnets: Vec<Subnet> = policy
+        .allowed_subnets
+        .iter()
+        .filter_map(|subnet| Subnet::parse(subnet))
+        .collect();
+    let checks_address = managed.restricts_peers()
+        || !subnets.is_empty()
+        || policy.block_internet_relay
+        || policy.trusted_only;
+    if !checks_address {
+        return Ok(());
+    }
+    let Some(host) = destination.host.filter(|host| !host.trim().is_empty()) else {
+        return Err(violation(
+            "the receiver must be given by address, not found by name",
+        ));
+    };
+    if policy.trusted_only {
+        ensure_trusted(app, destination, host)?;
+    }
+    for ip in addresses(host, destination.port)? {
+        if !managed.allows_ip(ip) {
+            return Err(format!(
+                "{host} ({ip}) is outside the subnets the managed configuration allows"
+            ));
+        }
+        if !subnets.is_empty() && !subnets.iter().any(|subnet| subnet.contains(ip)) {
+            return Err(violation(format!(
+                "{host} ({ip}) is not in an allowed subnet"
+            )));
+        }
+        if policy.block_internet_relay && !is_local(ip) {
+            return Err(violation(format!(
+                "{host} ({ip}) is not on a local network"
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub(crate) fn set_outbound_policy(
+    app: AppHandle,
+    policy: OutboundPolicy,
+) -> Result<OutboundPolicy, String> {
+    let mut allowed_subnets = Vec::new();
+    for subnet in policy.allowed_subnets {
+        let subnet = subnet.trim();
+        if subnet.is_empty() {
+            continue;
+        }
+        if Subnet::parse(subnet).is_none() {
+            return Err(format!("invalid subnet `{subnet}`"));
+        }
+        allowed_subnets.push(subnet.to_string());
+    }
+    update_settings(&app, |settings| {
+        settings.outbound_policy = OutboundPolicy {
+            allowed_subnets,
+            ..policy
+        };
+        Ok(())
+    })
+    .map(|settings| settings.outbound_policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_private_loopback_and_link_local_are_local() {
+        for local in [
+            "192.168.1.4",
+            "10.2.3.4",
+            "172.20.0.1",
+            "127.0.0.1",
+            "169.254.1.1",
+        ] {
+            assert!(is_local(local.parse().unwrap()), "{local}");
+        }
+        for local in ["::1", "fd12::5", "fe80::1", "::ffff:192.168.1.4"] {
+            assert!(is_local(local.parse().unwrap()), "{local}");
+        }
+        for remote in [
+            "8.8.8.8",
+            "100.101.102.103",
+            "2001:db8::1",
+            "::ffff:1.1.1.1",
+        ] {
+            assert!(!is_local(remote.parse().unwrap()), "{remote}");
+        }
+    }
+
+    #[test]
+    fn addresses_are_taken_as_given() {
+        assert_eq!(
+            addresses("[fe80::1]", 37373).unwrap(),
+            vec!["fe80::1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(
+            addresses(" 10.0.0.7 ", 37373).unwrap(),
+            vec!["10.0.0.7".parse::<IpAddr>().unwrap()]
+        );
+    }
+}
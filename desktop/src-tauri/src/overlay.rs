@@ -0,0 +1,300 @@
+//! Peers on mesh VPNs the user already runs. Tailscale lists its peers with
+//! their stable `100.x` addresses, so those that run a listener become
+//! sendable devices even at another site. ZeroTier carries broadcast and
+//! multicast, so its peers show up through normal discovery; only the
+//! joined networks are read here.
+
+use crate::network::command_stdout;
+use crate::parser::{clean_text, DeviceSource, DiscoverDevice};
+use crate::settings::{load_settings, update_settings};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const ZEROTIER_API: &str = "http://127.0.0.1:9993";
+const API_TIMEOUT: Duration = Duration::from_secs(2);
+/// Per peer; peers are probed at the same time.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct OverlaySettings {
+    /// Add overlay peers with a listener to discovery results.
+    pub(crate) enabled: bool,
+    /// Port peers are expected to listen on.
+    pub(crate) port: u16,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            port: 37373,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OverlayPeer {
+    name: String,
+    address: Ipv4Addr,
+    /// MagicDNS name, e.g. `laptop.tail1234.ts.net`.
+    dns_name: Option<String>,
+    os: Option<String>,
+    online: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ZeroTierNetwork {
+    id: String,
+    name: String,
+    interface: Option<String>,
+    addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OverlayStatus {
+    /// `None` when Tailscale is not installed or not running.
+    tailscale_peers: Option<Vec<OverlayPeer>>,
+    /// `None` when ZeroTier is not running or its API token is unreadable.
+    zero_tier_networks: Option<Vec<ZeroTierNetwork>>,
+}
+
+/// The local API of tailscaled. Status is readable without root.
+#[cfg(target_os = "linux")]
+fn tailscale_local_api() -> Option<Value> {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    [
+        "/var/run/tailscale/tailscaled.sock",
+        "/run/tailscale/tailscaled.sock",
+    ]
+    .iter()
+    .find_map(|path| {
+        let mut stream = UnixStream::connect(path).ok()?;
+        stream.set_read_timeout(Some(API_TIMEOUT)).ok()?;
+        // HTTP/1.0 keeps the body unchunked.
+        stream
+            .write_all(b"GET /localapi/v0/status HTTP/1.0\r\nHost: local-tailscaled.sock\r\n\r\n")
+            .ok()?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).ok()?;
+        let (head, body) = response.split_once("\r\n\r\n")?;
+        if head.split(' ').nth(1) != Some("200") {
+            return None;
+        }
+        serde_json::from_str(body).ok()
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn tailscale_local_api() -> Option<Value> {
+    None
+}
+
+/// The local API where it is a plain socket, the `tailscale` CLI (which
+/// talks to the same API) elsewhere.
+fn tailscale_status() -> Option<Value> {
+    if let Some(status) = tailscale_local_api() {
+        return Some(status);
+    }
+    let output = [
+        "tailscale",
+        "/Applications/Tailscale.app/Contents/MacOS/Tailscale",
+    ]
+    .iter()
+    .find_map(|program| command_stdout(program, &["status", "--json"]))?;
+    serde_json::from_str(&output).ok()
+}
+
+fn parse_tailscale_peers(status: &Value) -> Vec<OverlayPeer> {
+    let Some(peers) = status.get("Peer").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    peers
+        .values()
+        .filter_map(|peer| {
+            let address = peer
+                .get("TailscaleIPs")?
+                .as_array()?
+                .iter()
+                .filter_map(Value::as_str)
+                .find_map(|ip| ip.parse::<Ipv4Addr>().ok())?;
+            let text = |key: &str| {
+                peer.get(key)
+                    .and_then(Value::as_str)
+                    .map(|value| clean_text(value.trim_end_matches('.')))
+                    .filter(|value| !value.is_empty())
+            };
+            Some(OverlayPeer {
+                name: text("HostName").unwrap_or_else(|| address.to_string()),
+                address,
+                dns_name: text("DNSName"),
+                os: text("OS"),
+                online: peer.get("Online").and_then(Value::as_bool).unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+/// ZeroTier only answers with the token from its home folder, or the copy
+/// the GUI leaves in the user's home.
+fn zerotier_token() -> Option<String> {
+    let mut candidates = Vec::new();
+    if let Some(home) = crate::paths::home_dir() {
+        candidates.push(home.join(".zeroTierOneAuthToken"));
+        candidates.push(home.join("AppData/Local/ZeroTier/One/authtoken.secret"));
+    }
+    candidates.push("/var/lib/zerotier-one/authtoken.secret".into());
+    candidates.push("/Library/Application Support/ZeroTier/One/authtoken.secret".into());
+    candidates.push("C:\\ProgramData\\ZeroTier\\One\\authtoken.secret".into());
+    candidates.iter().find_map(|path| {
+        let token = std::fs::read_to_string(path).ok()?;
+        let token = token.trim();
+        (!token.is_empty()).then(|| token.to_string())
+    })
+}
+
+fn zerotier_networks() -> Option<Vec<ZeroTierNetwork>> {
+    let token = zerotier_token()?;
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(API_TIMEOUT))
+        .build()
+        .new_agent();
+    let networks: Vec<Value> = agent
+        .get(format!("{ZEROTIER_API}/network"))
+        .header("X-ZT1-Auth", token)
+        .call()
+        .ok()?
+        .body_mut()
+        .read_json()
+        .ok()?;
+    let text = |network: &Value, key: &str| {
+        network
+            .get(key)
+            .and_then(Value::as_str)
+            .map(clean_text)
+            .filter(|value| !value.is_empty())
+    };
+    Some(
+        networks
+            .iter()
+            .filter_map(|network| {
+                Some(ZeroTierNetwork {
+                    id: text(network, "id")?,
+                    name: text(network, "name").unwrap_or_default(),
+                    interface: text(network, "portDeviceName"),
+                    addresses: network
+                        .get("assignedAddresses")
+                        .and_then(Value::as_array)
+                        .map(|addresses| {
+                            addresses
+                                .iter()
+                                .filter_map(Value::as_str)
+                                .map(clean_text)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Online Tailscale peers that accept connections on the configured port,
+/// as discovery entries. Empty when disabled or Tailscale is absent.
+pub(crate) fn overlay_devices(app: &AppHandle) -> Vec<DiscoverDevice> {
+    let settings = load_settings(app)
+        .map(|settings| settings.overlay)
+        .unwrap_or_default();
+    if !settings.enabled {
+        return Vec::new();
+    }
+    let Some(status) = tailscale_status() else {
+        return Vec::new();
+    };
+    let peers: Vec<OverlayPeer> = parse_tailscale_peers(&status)
+        .into_iter()
+        .filter(|peer| peer.online)
+        .collect();
+    thread::scope(|scope| {
+        let probes: Vec<_> = peers
+            .iter()
+            .map(|peer| {
+                scope.spawn(move || {
+                    let address = SocketAddr::new(IpAddr::V4(peer.address), settings.port);
+                    TcpStream::connect_timeout(&address, PROBE_TIMEOUT).is_ok()
+                })
+            })
+            .collect();
+        peers
+            .iter()
+            .zip(probes)
+            .filter_map(|(peer, probe)| probe.join().unwrap_or(false).then_some(peer))
+            .map(|peer| DiscoverDevice {
+                name: peer.name.clone(),
+                host: peer.address.to_string(),
+                port: settings.port,
+                addresses: vec![peer.address.to_string()],
+                id: None,
+                fingerprint: None,
+                version: None,
+                protocol_version: None,
+                tls: None,
+                pair_code_required: None,
+                capabilities: Vec::new(),
+                appearance: Default::default(),
+                source: DeviceSource::LocalSent,
+                routes: Vec::new(),
+                proximity: None,
+                directory: None,
+            })
+            .collect()
+    })
+}
+
+/// Adds overlay devices not already found on the LAN, by address or name.
+pub(crate) fn merge(devices: &mut Vec<DiscoverDevice>, overlay: Vec<DiscoverDevice>) {
+    for device in overlay {
+        let known = devices.iter().any(|known| {
+            known.name.eq_ignore_ascii_case(&device.name)
+                || known.host == device.host
+                || known.addresses.contains(&device.host)
+        });
+        if !known {
+            devices.push(device);
+        }
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn get_overlay_status() -> Result<OverlayStatus, String> {
+    tauri::async_runtime::spawn_blocking(|| OverlayStatus {
+        tailscale_peers: tailscale_status().map(|status| parse_tailscale_peers(&status)),
+        zero_tier_networks: zerotier_networks(),
+    })
+    .await
+    .map_err(|err| format!("failed to join overlay status task: {err}"))
+}
+
+#[tauri::command]
+pub(crate) fn set_overlay_settings(
+    app: AppHandle,
+    overlay: OverlaySettings,
+) -> Result<OverlaySettings, String> {
+    if overlay.port == 0 {
+        return Err("port must be in 1-65535".to_string());
+    }
+    update_settings(&app, |settings| {
+        settings.overlay = overlay;
+        Ok(())
+    })
+    .map(|settings| settings.overlay)
+}
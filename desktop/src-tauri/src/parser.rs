@@ -0,0 +1,527 @@
+//! Parsing of what the CLI prints. Much of it comes from peers: file names,
+//! sizes and hashes in confirm requests and progress, device names in
+//! discovery results. Nothing here may panic on any input, and what comes
+//! out is safe to show and to act on.
+//!
+//! Only `serde` and `serde_json` are used, so the fuzz targets in `fuzz/`
+//! build this file on its own.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Longer fields are cut; no real path or device name comes close.
+const MAX_FIELD_CHARS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Direction {
+    Send,
+    Recv,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConfirmRequest {
+    pub(crate) id: u64,
+    pub(crate) from: Option<String>,
+    pub(crate) path: String,
+    pub(crate) size: u64,
+    /// Lowercase hex; `None` when the sender sent none or an invalid one.
+    pub(crate) sha256: Option<String>,
+    /// Content of unknown length piped from stdin; `size` is 0 and there is
+    /// no hash to check up front.
+    #[serde(default)]
+    pub(crate) streaming: bool,
+    /// From a Quick Share sender, which never went through pairing.
+    #[serde(default)]
+    pub(crate) quick_share: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Progress {
+    pub(crate) direction: Direction,
+    pub(crate) path: String,
+    /// Never more than `total`.
+    pub(crate) bytes: u64,
+    /// `None` while streaming content of unknown length.
+    pub(crate) total: Option<u64>,
+    /// Smoothed by the CLI's progress analytics.
+    pub(crate) bytes_per_second: u64,
+    /// `None` while stalled or streaming.
+    #[serde(default)]
+    pub(crate) eta_seconds: Option<u64>,
+    #[serde(default)]
+    pub(crate) stalled: bool,
+    #[serde(default)]
+    pub(crate) regime: ThroughputRegime,
+    /// Set for the files of a multi-file send.
+    #[serde(default)]
+    pub(crate) batch: Option<BatchProgress>,
+}
+
+/// Where a file sits in a multi-file send, and how far the whole send is.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BatchProgress {
+    /// 1-based, never more than `count`.
+    pub(crate) index: u64,
+    pub(crate) count: u64,
+    /// Never more than `total`.
+    pub(crate) bytes: u64,
+    pub(crate) total: u64,
+}
+
+impl Progress {
+    /// Whether this reports the last byte of its file.
+    pub(crate) fn completes(&self) -> bool {
+        self.total.is_some_and(|total| self.bytes >= total)
+    }
+}
+
+/// How a transfer's throughput behaves; see `progress_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ThroughputRegime {
+    #[default]
+    Starting,
+    Steady,
+    /// The rate swings widely between samples.
+    Bursty,
+    /// Well below the best rate the transfer reached.
+    Degraded,
+    Stalled,
+}
+
+/// What kind of interface an address is reached through, best first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum InterfaceKind {
+    Lan,
+    /// VPN and overlay networks such as `utun` or `tailscale0`.
+    Vpn,
+    /// Bridges to containers and virtual machines such as `docker0`.
+    Virtual,
+    Loopback,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AddressRoute {
+    pub(crate) address: String,
+    /// The local interface on the same subnet, if any.
+    pub(crate) interface: Option<String>,
+    pub(crate) kind: InterfaceKind,
+}
+
+/// What a discovered device runs, and so how it is sent to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DeviceSource {
+    #[default]
+    LocalSent,
+    /// Sent to through `kdeconnect:<id>` once paired.
+    KdeConnect,
+    /// A LocalSent listener only the SSDP fallback found; sent to like any
+    /// other.
+    Ssdp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Nearness {
+    Near,
+    Nearby,
+    Far,
+}
+
+/// How close a device probably is, worked out by `proximity::rank`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Proximity {
+    /// 0 to 100; devices are listed highest first.
+    pub(crate) score: u8,
+    pub(crate) nearness: Nearness,
+    /// Time to open a connection to the device, if it answered in time.
+    pub(crate) rtt_ms: Option<u32>,
+    pub(crate) same_subnet: bool,
+    /// Wi-Fi link quality, 0 to 100, of the interface the device is reached
+    /// through. `None` when wired or where the platform does not tell.
+    pub(crate) signal: Option<u8>,
+}
+
+/// Set on discovery results the org directory lists.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DirectoryListing {
+    pub(crate) group: Option<String>,
+}
+
+/// How a listener shows itself, from its device identity. All `None` for
+/// listeners that do not announce it.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct DeviceAppearance {
+    pub(crate) device_type: Option<String>,
+    pub(crate) avatar_color: Option<String>,
+    pub(crate) avatar_emoji: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiscoverDevice {
+    pub(crate) name: String,
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) addresses: Vec<String>,
+    /// Announced by the listener; absent for older ones and overlay peers.
+    #[serde(default)]
+    pub(crate) id: Option<String>,
+    /// SHA-256 of the listener's TLS certificate, when it serves TLS.
+    #[serde(default)]
+    pub(crate) fingerprint: Option<String>,
+    /// What the listener announces it supports. All `None` for listeners
+    /// that predate announcing them and for overlay peers.
+    #[serde(default)]
+    pub(crate) version: Option<String>,
+    #[serde(default)]
+    pub(crate) protocol_version: Option<u32>,
+    #[serde(default)]
+    pub(crate) tls: Option<bool>,
+    #[serde(default)]
+    pub(crate) pair_code_required: Option<bool>,
+    /// Optional features the listener announces, e.g. `ping`.
+    #[serde(default)]
+    pub(crate) capabilities: Vec<String>,
+    #[serde(flatten)]
+    pub(crate) appearance: DeviceAppearance,
+    #[serde(default)]
+    pub(crate) source: DeviceSource,
+    /// Filled in by the app from the local interfaces, never read from the
+    /// CLI.
+    #[serde(default, skip_deserializing)]
+    pub(crate) routes: Vec<AddressRoute>,
+    #[serde(default, skip_deserializing)]
+    pub(crate) proximity: Option<Proximity>,
+    /// Set for devices the org directory lists.
+    #[serde(default, skip_deserializing)]
+    pub(crate) directory: Option<DirectoryListing>,
+}
+
+/// Replaces control characters, including bidi overrides that could make a
+/// name read as something else, and cuts overlong text.
+pub(crate) fn clean_text(text: &str) -> String {
+    text.chars()
+        .take(MAX_FIELD_CHARS)
+        .map(|ch| {
+            let bidi = matches!(ch, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}');
+            if ch.is_control() || bidi {
+                '\u{fffd}'
+            } else {
+                ch
+            }
+        })
+        .collect()
+}
+
+fn clean_sha256(sha256: Option<String>) -> Option<String> {
+    sha256
+        .filter(|sha256| sha256.len() == 64 && sha256.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(|sha256| sha256.to_ascii_lowercase())
+}
+
+/// The JSON object on an IPC line. `None` for lines that are not IPC
+/// messages, e.g. stderr output or a CLI too old to know `--ipc`. Every
+/// version `v` from 1 up is accepted: later versions only add fields and
+/// message types.
+pub(crate) fn ipc_value(line: &str) -> Option<Value> {
+    let value: Value = serde_json::from_str(line.trim()).ok()?;
+    if value.get("v")?.as_u64()? == 0 {
+        return None;
+    }
+    value.is_object().then_some(value)
+}
+
+pub(crate) fn message_type(value: &Value) -> Option<&str> {
+    value.get("type")?.as_str()
+}
+
+pub(crate) fn parse_confirm_request(value: Value) -> Option<ConfirmRequest> {
+    let mut request: ConfirmRequest = serde_json::from_value(value).ok()?;
+    request.path = clean_text(&request.path);
+    if request.path.trim().is_empty() {
+        return None;
+    }
+    request.from = request.from.map(|from| clean_text(&from));
+    request.sha256 = clean_sha256(request.sha256);
+    if request.streaming {
+        request.size = 0;
+        request.sha256 = None;
+    }
+    Some(request)
+}
+
+pub(crate) fn parse_progress(value: Value) -> Option<Progress> {
+    let mut progress: Progress = serde_json::from_value(value).ok()?;
+    progress.path = clean_text(&progress.path);
+    if let Some(total) = progress.total {
+        progress.bytes = progress.bytes.min(total);
+    }
+    if let Some(batch) = &mut progress.batch {
+        batch.index = batch.index.min(batch.count);
+        batch.bytes = batch.bytes.min(batch.total);
+    }
+    Some(progress)
+}
+
+/// The output of `discover --json`. Entries that do not parse are left out
+/// rather than failing the whole list; output that is not a list at all is
+/// an error.
+pub(crate) fn parse_discovered_devices(stdout: &str) -> Result<Vec<DiscoverDevice>, String> {
+    let stdout = stdout.trim();
+    if stdout.is_empty() {
+        return Ok(Vec::new());
+    }
+    let entries: Vec<Value> = serde_json::from_str(stdout)
+        .map_err(|err| format!("failed to parse discovery JSON: {err}"))?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value::<DiscoverDevice>(entry).ok())
+        .map(|device| DiscoverDevice {
+            name: clean_text(&device.name),
+            host: clean_text(&device.host),
+            port: device.port,
+            addresses: device
+                .addresses
+                .iter()
+                .map(|address| clean_text(address))
+                .collect(),
+            id: device.id.as_deref().map(clean_text),
+            fingerprint: device.fingerprint.as_deref().map(clean_text),
+            version: device.version.as_deref().map(clean_text),
+            protocol_version: device.protocol_version,
+            tls: device.tls,
+            pair_code_required: device.pair_code_required,
+            capabilities: device
+                .capabilities
+                .iter()
+                .map(|capability| clean_text(capability))
+                .collect(),
+            appearance: DeviceAppearance {
+                device_type: device.appearance.device_type.as_deref().map(clean_text),
+                avatar_color: device.appearance.avatar_color.as_deref().map(clean_text),
+                avatar_emoji: device.appearance.avatar_emoji.as_deref().map(clean_text),
+            },
+            source: device.source,
+            routes: Vec::new(),
+            proximity: None,
+            directory: None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Random text biased towards what breaks parsers: JSON punctuation,
+    /// quotes, escapes, control and bidi characters, and multi-byte text.
+    fn random_text(rng: &mut fastrand::Rng, max_len: usize) -> String {
+        const PIECES: &[&str] = &[
+            "{",
+            "}",
+            "[",
+            "]",
+            "\"",
+            ":",
+            ",",
+            "\\",
+            "\\u",
+            "\\ud800",
+            "null",
+            "true",
+            "-1",
+            "1e999",
+            "18446744073709551616",
+            "\"v\":1",
+            "\"type\":",
+            "\u{202e}",
+            "\u{0}",
+            "\n",
+            "\t",
+            "报告",
+            "📦",
+            "é",
+            " ",
+        ];
+        let len = rng.usize(..=max_len);
+        (0..len)
+            .map(|_| {
+                if rng.bool() {
+                    PIECES[rng.usize(..PIECES.len())].to_string()
+                } else {
+                    rng.char(..).to_string()
+                }
+            })
+            .collect()
+    }
+
+    fn random_value(rng: &mut fastrand::Rng, depth: u32) -> Value {
+        match rng.u8(..if depth == 0 { 5 } else { 7 }) {
+            0 => Value::Null,
+            1 => json!(rng.bool()),
+            2 => json!(rng.u64(..)),
+            3 => json!(rng.i64(..)),
+            4 => json!(random_text(rng, 12)),
+            5 => Value::Array(
+                (0..rng.usize(..4))
+                    .map(|_| random_value(rng, depth - 1))
+                    .collect(),
+            ),
+            _ => Value::Object(
+                (0..rng.usize(..4))
+                    .map(|_| (random_text(rng, 6), random_value(rng, depth - 1)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn assert_clean(text: &str) {
+        assert_eq!(clean_text(text), text, "{text:?}");
+    }
+
+    #[test]
+    fn confirm_request_is_parsed_and_normalized() {
+        let hash = "AB".repeat(32);
+        let value = json!({ "v": 1, "type": "confirmRequest", "id": 7, "from": "10.0.0.2",
+                            "path": "a\u{202e}fdp.exe", "size": 10, "sha256": hash });
+        let request = parse_confirm_request(value).expect("valid request");
+        assert_eq!(request.id, 7);
+        assert_eq!(request.path, "a\u{fffd}fdp.exe");
+        assert_eq!(request.sha256, Some("ab".repeat(32)));
+
+        let value = json!({ "id": 1, "path": "x", "size": 3, "sha256": "" });
+        assert_eq!(parse_confirm_request(value).expect("valid").sha256, None);
+        let value = json!({ "id": 1, "path": "x", "size": 3, "streaming": true,
+                            "sha256": "ab".repeat(32) });
+        let request = parse_confirm_request(value).expect("valid");
+        assert_eq!((request.size, request.sha256), (0, None));
+
+        assert!(parse_confirm_request(json!({ "id": 1, "path": " ", "size": 0 })).is_none());
+        assert!(parse_confirm_request(json!({ "id": -1, "path": "x", "size": 0 })).is_none());
+    }
+
+    #[test]
+    fn progress_never_exceeds_total() {
+        let value = json!({ "direction": "recv", "path": "a", "bytes": 900, "total": 100,
+                            "bytesPerSecond": 1 });
+        let progress = parse_progress(value).expect("valid progress");
+        assert_eq!(progress.bytes, 100);
+        assert!(progress.completes());
+        let value = json!({ "direction": "send", "path": "a", "bytes": 5, "total": null,
+                            "bytesPerSecond": 1 });
+        assert!(!parse_progress(value).expect("streaming").completes());
+        let value = json!({ "direction": "send", "path": "a", "bytes": 1, "total": 2,
+                            "bytesPerSecond": 1,
+                            "batch": { "index": 9, "count": 3, "bytes": 50, "total": 40 } });
+        let batch = parse_progress(value)
+            .and_then(|progress| progress.batch)
+            .expect("batch");
+        assert_eq!((batch.index, batch.bytes), (3, 40));
+    }
+
+    #[test]
+    fn ipc_value_needs_a_versioned_object() {
+        assert!(ipc_value(r#"{"v":1,"type":"log","line":"x"}"#).is_some());
+        assert!(ipc_value(r#"{"v":7,"type":"future"}"#).is_some());
+        for line in [
+            r#"{"v":0}"#,
+            r#"{"type":"log"}"#,
+            "[1]",
+            "",
+            "sent a.txt",
+            "{",
+        ] {
+            assert!(ipc_value(line).is_none(), "{line}");
+        }
+    }
+
+    #[test]
+    fn discovery_skips_bad_entries() {
+        let stdout = r#"[{"name":"a\u0007","host":"a.local","port":1,"addresses":[]},
+                         {"name":"b","host":"b.local","port":70000,"addresses":[]},
+                         "junk"]"#;
+        let devices = parse_discovered_devices(stdout).expect("a list");
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "a\u{fffd}");
+        assert!(parse_discovered_devices(r#"{"name":"a"}"#).is_err());
+    }
+
+    #[test]
+    fn discovery_reads_the_announced_appearance() {
+        let stdout = r##"[{"name":"a","host":"a.local","port":1,"addresses":[],
+                          "deviceType":"laptop","avatarColor":"#3b82f6","avatarEmoji":"🦊"},
+                         {"name":"b","host":"b.local","port":1,"addresses":[]}]"##;
+        let devices = parse_discovered_devices(stdout).expect("a list");
+        assert_eq!(devices[0].appearance.device_type.as_deref(), Some("laptop"));
+        assert_eq!(
+            devices[0].appearance.avatar_color.as_deref(),
+            Some("#3b82f6")
+        );
+        assert_eq!(devices[0].appearance.avatar_emoji.as_deref(), Some("🦊"));
+        assert!(devices[1].appearance.device_type.is_none());
+    }
+
+    #[test]
+    fn random_lines_never_panic_and_come_out_clean() {
+        let mut rng = fastrand::Rng::with_seed(0x0001_0ca1_5e27);
+        for _ in 0..20_000 {
+            let line = random_text(&mut rng, 40);
+            if let Some(value) = ipc_value(&line) {
+                let _ = message_type(&value);
+                let _ = parse_confirm_request(value.clone());
+                let _ = parse_progress(value);
+            }
+            if let Ok(devices) = parse_discovered_devices(&line) {
+                for device in devices {
+                    assert_clean(&device.name);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_messages_keep_their_invariants() {
+        let mut rng = fastrand::Rng::with_seed(0x0005_e271_0ca1);
+        for _ in 0..20_000 {
+            let mut value = random_value(&mut rng, 2);
+            if let Value::Object(fields) = &mut value {
+                // Often well-formed enough to get past serde.
+                if rng.bool() {
+                    fields.insert("id".into(), json!(rng.u64(..)));
+                    fields.insert("path".into(), json!(random_text(&mut rng, 20)));
+                    fields.insert("size".into(), json!(rng.u64(..)));
+                    fields.insert("direction".into(), json!(["send", "recv"][rng.usize(..2)]));
+                    fields.insert("bytes".into(), json!(rng.u64(..)));
+                    fields.insert("total".into(), json!(rng.u64(..)));
+                    fields.insert("bytesPerSecond".into(), json!(rng.u64(..)));
+                }
+            }
+            if let Some(request) = parse_confirm_request(value.clone()) {
+                assert_clean(&request.path);
+                assert!(!request.path.trim().is_empty());
+                if let Some(sha256) = &request.sha256 {
+                    assert_eq!(sha256.len(), 64);
+                    assert_eq!(*sha256, sha256.to_ascii_lowercase());
+                }
+            }
+            if let Some(progress) = parse_progress(value) {
+                assert_clean(&progress.path);
+                if let Some(total) = progress.total {
+                    assert!(progress.bytes <= total);
+                }
+            }
+        }
+    }
+}
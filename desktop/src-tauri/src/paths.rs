@@ -0,0 +1,447 @@
+use crate::settings::{load_settings, update_settings};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PathErrorKind {
+    Empty,
+    NotFound,
+    NotAFile,
+    NotADirectory,
+    NotReadable,
+    NotWritable,
+    OutsideApprovedDirs,
+}
+
+impl PathErrorKind {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Empty => "empty",
+            Self::NotFound => "not-found",
+            Self::NotAFile => "not-a-file",
+            Self::NotADirectory => "not-a-directory",
+            Self::NotReadable => "not-readable",
+            Self::NotWritable => "not-writable",
+            Self::OutsideApprovedDirs => "outside-approved-dirs",
+        }
+    }
+}
+
+/// Rejection produced before any CLI child is spawned. `label` names the
+/// field the path came from so the message points at the right input.
+#[derive(Debug, Clone)]
+pub(crate) struct PathError {
+    pub(crate) kind: PathErrorKind,
+    pub(crate) label: &'static str,
+    pub(crate) path: String,
+    pub(crate) detail: Option<String>,
+}
+
+impl PathError {
+    fn new(kind: PathErrorKind, label: &'static str, path: &Path) -> Self {
+        Self {
+            kind,
+            label,
+            path: path.display().to_string(),
+            detail: None,
+        }
+    }
+
+    fn with_detail(mut self, detail: impl fmt::Display) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            PathErrorKind::Empty => "is required",
+            PathErrorKind::NotFound => "does not exist",
+            PathErrorKind::NotAFile => "is not a file",
+            PathErrorKind::NotADirectory => "is not a directory",
+            PathErrorKind::NotReadable => "is not readable",
+            PathErrorKind::NotWritable => "is not writable",
+            PathErrorKind::OutsideApprovedDirs => {
+                "is outside the approved folders; choose it with the folder picker first"
+            }
+        };
+        if self.path.is_empty() {
+            write!(f, "[{}] {} {reason}", self.kind.code(), self.label)?;
+        } else {
+            write!(
+                f,
+                "[{}] {} `{}` {reason}",
+                self.kind.code(),
+                self.label,
+                self.path
+            )?;
+        }
+        if let Some(detail) = self.detail.as_ref() {
+            write!(f, " ({detail})")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<PathError> for String {
+    fn from(err: PathError) -> Self {
+        err.to_string()
+    }
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    let home = if cfg!(target_os = "windows") {
+        std::env::var_os("USERPROFILE").or_else(|| std::env::var_os("HOME"))
+    } else {
+        std::env::var_os("HOME")
+    }?;
+    Some(PathBuf::from(home))
+}
+
+fn expand_path(raw: &str, label: &'static str) -> Result<PathBuf, PathError> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Err(PathError::new(PathErrorKind::Empty, label, Path::new("")));
+    }
+    let expanded = match trimmed.strip_prefix("~") {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => home_dir()
+            .map(|home| home.join(rest.trim_start_matches(['/', '\\'])))
+            .unwrap_or_else(|| PathBuf::from(trimmed)),
+        _ => PathBuf::from(trimmed),
+    };
+    if expanded.is_absolute() {
+        return Ok(expanded);
+    }
+    Ok(std::env::current_dir()
+        .map(|cwd| cwd.join(&expanded))
+        .unwrap_or(expanded))
+}
+
+fn canonicalize_existing(path: &Path, label: &'static str) -> Result<PathBuf, PathError> {
+    fs::canonicalize(path).map_err(|err| {
+        let kind = if err.kind() == std::io::ErrorKind::PermissionDenied {
+            PathErrorKind::NotReadable
+        } else {
+            PathErrorKind::NotFound
+        };
+        PathError::new(kind, label, path).with_detail(err)
+    })
+}
+
+fn ensure_readable(path: &Path, label: &'static str) -> Result<(), PathError> {
+    let result = if path.is_dir() {
+        fs::read_dir(path).map(|_| ())
+    } else {
+        File::open(path).map(|_| ())
+    };
+    result.map_err(|err| PathError::new(PathErrorKind::NotReadable, label, path).with_detail(err))
+}
+
+fn ensure_writable_dir(path: &Path, label: &'static str) -> Result<(), PathError> {
+    let probe = path.join(format!(".local_sent-write-probe-{}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|err| PathError::new(PathErrorKind::NotWritable, label, path).with_detail(err))?;
+    let _ = fs::remove_file(probe);
+    Ok(())
+}
+
+/// Validates a file or folder that is going to be sent and returns its
+/// canonical form.
+pub(crate) fn validate_send_source(raw: &str) -> Result<PathBuf, PathError> {
+    const LABEL: &str = "send path";
+    let path = canonicalize_existing(&expand_path(raw, LABEL)?, LABEL)?;
+    ensure_readable(&path, LABEL)?;
+    Ok(path)
+}
+
+/// Validates an input file such as a TLS certificate or key.
+pub(crate) fn validate_input_file(raw: &str, label: &'static str) -> Result<PathBuf, PathError> {
+    let path = canonicalize_existing(&expand_path(raw, label)?, label)?;
+    if !path.is_file() {
+        return Err(PathError::new(PathErrorKind::NotAFile, label, &path));
+    }
+    ensure_readable(&path, label)?;
+    Ok(path)
+}
+
+/// Validates a file the CLI may create or update (e.g. a TOFU known-hosts
+/// file): it may be missing, but its folder must exist and be writable.
+pub(crate) fn validate_output_file(raw: &str, label: &'static str) -> Result<PathBuf, PathError> {
+    let path = expand_path(raw, label)?;
+    if path.is_dir() {
+        return Err(PathError::new(PathErrorKind::NotAFile, label, &path));
+    }
+    let parent = path
+        .parent()
+        .ok_or_else(|| PathError::new(PathErrorKind::NotFound, label, &path))?;
+    let parent = canonicalize_existing(parent, label)?;
+    ensure_writable_dir(&parent, label)?;
+    Ok(match path.file_name() {
+        Some(name) => parent.join(name),
+        None => path,
+    })
+}
+
+/// Folders the receiver may write into without further confirmation: the
+/// home folder (which covers Downloads) plus any folder the user approved.
+pub(crate) fn approved_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = home_dir().into_iter().collect();
+    if let Ok(settings) = load_settings(app) {
+        dirs.extend(settings.approved_dirs.iter().map(PathBuf::from));
+    }
+    dirs.into_iter()
+        .filter_map(|dir| fs::canonicalize(dir).ok())
+        .collect()
+}
+
+/// Validates the receive folder. The folder itself may not exist yet (the CLI
+/// creates it), so the nearest existing ancestor is checked for write access
+/// and every check runs against canonical paths so symlinks cannot escape
+/// the approved folders.
+pub(crate) fn validate_output_dir(app: &AppHandle, raw: &str) -> Result<PathBuf, PathError> {
+    const LABEL: &str = "output folder";
+    let path = expand_path(raw, LABEL)?;
+
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let Some(parent) = existing.parent() else {
+            return Err(PathError::new(PathErrorKind::NotFound, LABEL, &path));
+        };
+        let Some(name) = existing.file_name() else {
+            return Err(PathError::new(PathErrorKind::NotFound, LABEL, &path)
+                .with_detail("`..` after a missing folder"));
+        };
+        missing.push(name.to_os_string());
+        existing = parent;
+    }
+    let mut canonical = canonicalize_existing(existing, LABEL)?;
+    if !canonical.is_dir() {
+        return Err(PathError::new(
+            PathErrorKind::NotADirectory,
+            LABEL,
+            &canonical,
+        ));
+    }
+    ensure_writable_dir(&canonical, LABEL)?;
+    for name in missing.into_iter().rev() {
+        canonical.push(name);
+    }
+
+    if !approved_dirs(app)
+        .iter()
+        .any(|approved| canonical.starts_with(approved))
+    {
+        return Err(PathError::new(
+            PathErrorKind::OutsideApprovedDirs,
+            LABEL,
+            &canonical,
+        ));
+    }
+    Ok(canonical)
+}
+
+/// Adds a folder the user picked through a native dialog to the approved
+/// receive folders. Folders already covered by an approved one are skipped.
+pub(crate) fn approve_dir(app: &AppHandle, dir: &Path) -> Result<PathBuf, String> {
+    let canonical = fs::canonicalize(dir)
+        .map_err(|err| format!("failed to resolve {}: {err}", dir.display()))?;
+    if approved_dirs(app)
+        .iter()
+        .any(|approved| canonical.starts_with(approved))
+    {
+        return Ok(canonical);
+    }
+    let entry = canonical.to_string_lossy().to_string();
+    update_settings(app, |settings| {
+        if !settings.approved_dirs.contains(&entry) {
+            settings.approved_dirs.push(entry.clone());
+        }
+        Ok(())
+    })?;
+    Ok(canonical)
+}
+
+/// Validates `path` as a receive folder and creates it (with parents) if it
+/// is missing, so a deleted folder is reported here rather than by the
+/// listener at receive time.
+#[tauri::command]
+pub(crate) fn ensure_output_dir(app: AppHandle, path: String) -> Result<String, String> {
+    let dir = validate_output_dir(&app, &path)?;
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("failed to create output folder {}: {err}", dir.display()))?;
+    ensure_writable_dir(&dir, "output folder")?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// Win32 APIs reject longer paths without the `\\?\` prefix; the margin
+/// leaves room for an 8.3 file name inside a directory.
+const WINDOWS_MAX_PATH: usize = 260 - 12;
+
+/// Device names open the device instead of a file on Windows, whatever
+/// the extension (`nul.txt` included).
+pub(crate) fn is_reserved_windows_name(name: &str) -> bool {
+    let trimmed = name.trim_end_matches(['.', ' ']);
+    let stem = trimmed
+        .split('.')
+        .next()
+        .unwrap_or(trimmed)
+        .to_ascii_lowercase();
+    match stem.as_str() {
+        "con" | "prn" | "aux" | "nul" | "conin$" | "conout$" => true,
+        _ => {
+            let mut chars = stem.chars();
+            let prefix: String = chars.by_ref().take(3).collect();
+            let digit = chars.next();
+            (prefix == "com" || prefix == "lpt")
+                && chars.next().is_none()
+                && digit.is_some_and(|c| c.is_ascii_digit() || matches!(c, '¹' | '²' | '³'))
+        }
+    }
+}
+
+/// Rewrites one path segment into a name Windows can create, matching the
+/// CLI receiver: invalid characters and trailing dots or spaces become `_`
+/// and device names get a `_` prefix.
+pub(crate) fn windows_safe_name(name: &str) -> String {
+    let kept = name.trim_end_matches(['.', ' ']).len();
+    let mut safe: String = name
+        .char_indices()
+        .map(|(index, c)| {
+            if index >= kept
+                || c.is_control()
+                || matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*')
+            {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if is_reserved_windows_name(&safe) {
+        safe.insert(0, '_');
+    }
+    safe
+}
+
+/// Adds the `\\?\` (or `\\?\UNC\`) prefix to an absolute Windows path that
+/// is too long for the classic APIs.
+fn with_long_path_prefix(path: &str) -> Option<String> {
+    if path.len() < WINDOWS_MAX_PATH || path.starts_with(r"\\?\") {
+        return None;
+    }
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{share}"));
+    }
+    let bytes = path.as_bytes();
+    (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\')
+        .then(|| format!(r"\\?\{path}"))
+}
+
+/// `path` in a form Windows can open however deep it is; unchanged
+/// elsewhere.
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    if !cfg!(windows) {
+        return path.to_path_buf();
+    }
+    match path.to_str().and_then(with_long_path_prefix) {
+        Some(prefixed) => PathBuf::from(prefixed),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Walks a file or folder the same way the CLI builds its transfer entries
+/// and returns the number of regular files and their total size.
+pub(crate) fn measure_path(path: &Path) -> std::io::Result<(usize, u64)> {
+    measure_path_until(path, |_, _, _| true).map(Option::unwrap_or_default)
+}
+
+/// `measure_path` that reports running totals and the file just counted
+/// after every file and stops,
+/// returning `None`, as soon as `keep_going` returns false.
+pub(crate) fn measure_path_until(
+    path: &Path,
+    mut keep_going: impl FnMut(usize, u64, &Path) -> bool,
+) -> std::io::Result<Option<(usize, u64)>> {
+    let metadata = fs::metadata(path)?;
+    if metadata.is_file() {
+        keep_going(1, metadata.len(), path);
+        return Ok(Some((1, metadata.len())));
+    }
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                files += 1;
+                bytes += entry.metadata()?.len();
+                if !keep_going(files, bytes, &entry.path()) {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+    Ok(Some((files, bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_windows_names_are_detected() {
+        for name in [
+            "CON",
+            "nul.txt",
+            "Aux.tar.gz",
+            "com1",
+            "LPT9.log",
+            "prn. ",
+            "COM¹",
+        ] {
+            assert!(is_reserved_windows_name(name), "{name}");
+        }
+        for name in ["console.log", "com", "com10", "lpt", "nul_", "icon.png"] {
+            assert!(!is_reserved_windows_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn windows_safe_name_rewrites_unusable_names() {
+        assert_eq!(windows_safe_name("aux"), "_aux");
+        assert_eq!(windows_safe_name("nul.txt"), "_nul.txt");
+        assert_eq!(windows_safe_name("a:b?.txt"), "a_b_.txt");
+        assert_eq!(windows_safe_name("notes. "), "notes__");
+        assert_eq!(windows_safe_name("index.js"), "index.js");
+    }
+
+    #[test]
+    fn long_paths_get_the_namespace_prefix() {
+        assert_eq!(with_long_path_prefix(r"C:\Users\me\a.txt"), None);
+
+        let deep = format!(r"C:\Users\me\{}index.js", r"node_modules\pkg\".repeat(20));
+        assert_eq!(with_long_path_prefix(&deep), Some(format!(r"\\?\{deep}")));
+        assert_eq!(with_long_path_prefix(&format!(r"\\?\{deep}")), None);
+
+        let share = format!(r"nas\share\{}", "x".repeat(300));
+        assert_eq!(
+            with_long_path_prefix(&format!(r"\\{share}")),
+            Some(format!(r"\\?\UNC\{share}"))
+        );
+        assert_eq!(
+            with_long_path_prefix(&format!("/home/me/{}", "x".repeat(300))),
+            None
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::paths::{approve_dir, measure_path};
+use rfd::{AsyncFileDialog, FileHandle};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "gif", "webp", "heic", "heif", "bmp", "tif", "tiff", "raw", "dng", "svg",
+];
+const VIDEO_EXTENSIONS: &[&str] = &[
+    "mp4", "mov", "m4v", "mkv", "avi", "webm", "wmv", "flv", "3gp", "mts",
+];
+const DOCUMENT_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "txt", "md", "rtf",
+    "csv", "pages", "numbers", "key",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PickedPath {
+    path: String,
+    kind: &'static str,
+    file_count: usize,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PickedPathsPayload {
+    entries: Vec<PickedPath>,
+    total_files: usize,
+    total_bytes: u64,
+}
+
+fn filter_extensions(filter: &str) -> Result<(&'static str, &'static [&'static str]), String> {
+    match filter {
+        "images" => Ok(("Images", IMAGE_EXTENSIONS)),
+        "videos" => Ok(("Videos", VIDEO_EXTENSIONS)),
+        "documents" => Ok(("Documents", DOCUMENT_EXTENSIONS)),
+        _ => Err(format!(
+            "invalid picker filter `{filter}`, expected `images`, `videos` or `documents`"
+        )),
+    }
+}
+
+fn describe(path: PathBuf) -> PickedPath {
+    let is_dir = path.is_dir();
+    let (file_count, size_bytes) = measure_path(&path).unwrap_or((0, 0));
+    PickedPath {
+        path: path.to_string_lossy().to_string(),
+        kind: if is_dir { "directory" } else { "file" },
+        file_count,
+        size_bytes,
+    }
+}
+
+/// Multi-select variant of `pick_send_path`. `filters` only applies to file
+/// pickers; sizes are measured up front so the UI can show the batch total.
+#[tauri::command]
+pub(crate) async fn pick_send_paths(
+    kind: String,
+    filters: Option<Vec<String>>,
+    multi: Option<bool>,
+) -> Result<Option<PickedPathsPayload>, String> {
+    let multi = multi.unwrap_or(true);
+    let filters = filters.unwrap_or_default();
+    let mut dialog = AsyncFileDialog::new();
+    if kind == "file" {
+        let named = filters
+            .iter()
+            .map(|filter| filter_extensions(filter.trim()))
+            .collect::<Result<Vec<_>, String>>()?;
+        if named.len() > 1 {
+            let all_extensions: Vec<&str> = named
+                .iter()
+                .flat_map(|(_, extensions)| extensions.iter().copied())
+                .collect();
+            dialog = dialog.add_filter("All supported", &all_extensions);
+        }
+        for (name, extensions) in named {
+            dialog = dialog.add_filter(name, extensions);
+        }
+    }
+
+    let selected: Option<Vec<FileHandle>> = match (kind.as_str(), multi) {
+        ("file", true) => dialog.pick_files().await,
+        ("file", false) => dialog.pick_file().await.map(|handle| vec![handle]),
+        ("directory", true) => dialog.pick_folders().await,
+        ("directory", false) => dialog.pick_folder().await.map(|handle| vec![handle]),
+        _ => return Err("invalid picker kind, expected `file` or `directory`".to_string()),
+    };
+    let Some(selected) = selected.filter(|handles| !handles.is_empty()) else {
+        return Ok(None);
+    };
+    let selected: Vec<PathBuf> = selected
+        .iter()
+        .map(|handle| handle.path().to_path_buf())
+        .collect();
+
+    let entries = tauri::async_runtime::spawn_blocking(move || {
+        selected.into_iter().map(describe).collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|err| format!("failed to join picker task: {err}"))?;
+    Ok(Some(PickedPathsPayload {
+        total_files: entries.iter().map(|entry| entry.file_count).sum(),
+        total_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+        entries,
+    }))
+}
+
+/// Folder picker for the receive folder. New folders can be created from the
+/// dialog, and the chosen folder is remembered as user-approved so path
+/// validation accepts it even outside the home folder.
+#[tauri::command]
+pub(crate) async fn pick_output_dir(
+    app: AppHandle,
+    current: Option<String>,
+) -> Result<Option<String>, String> {
+    let mut dialog = AsyncFileDialog::new()
+        .set_title("Choose receive folder")
+        .set_can_create_directories(true);
+    if let Some(current) = current.filter(|value| !value.trim().is_empty()) {
+        dialog = dialog.set_directory(current);
+    }
+    let Some(handle) = dialog.pick_folder().await else {
+        return Ok(None);
+    };
+    let dir = approve_dir(&app, handle.path())?;
+    Ok(Some(dir.to_string_lossy().to_string()))
+}
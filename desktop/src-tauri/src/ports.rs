@@ -0,0 +1,95 @@
+use crate::process_pool::ChildKind;
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, TcpListener};
+use tauri::AppHandle;
+
+/// Port the CLI listens on by default; tried first when a privileged port is
+/// refused.
+const CLI_DEFAULT_PORT: u16 = 37373;
+const PRIVILEGED_PORT_LIMIT: u16 = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PortSubstitutionPayload {
+    requested_port: u16,
+    port: u16,
+    reason: String,
+    hint: Option<String>,
+}
+
+/// One line of `check-port` output.
+#[derive(Debug, Deserialize)]
+struct PortProbe {
+    code: Option<String>,
+}
+
+/// Checks up front whether the listener may bind `requested`. Ports below
+/// 1024 need elevation on Linux and older macOS; instead of surfacing the
+/// CLI's raw EACCES, fall back to a high port and report the substitution.
+/// The CLI is asked rather than this process, since a capability such as
+/// `cap_net_bind_service` may be granted to one binary and not the other.
+pub(crate) fn resolve_listen_port(
+    app: &AppHandle,
+    requested: u16,
+) -> Result<(u16, Option<PortSubstitutionPayload>), String> {
+    if requested >= PRIVILEGED_PORT_LIMIT {
+        return Ok((requested, None));
+    }
+    let result = crate::run_cli_capture(
+        app.clone(),
+        vec!["check-port".to_string(), requested.to_string()],
+        ChildKind::Discover,
+    )?;
+    let probe = result
+        .stdout
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<PortProbe>(line.trim()).ok());
+    // Any other failure (usually "address in use") is reported by the CLI.
+    let denied = probe.is_some_and(|probe| probe.code.as_deref() == Some("EACCES"));
+    if !denied {
+        return Ok((requested, None));
+    }
+
+    let port = free_high_port()?;
+    Ok((
+        port,
+        Some(PortSubstitutionPayload {
+            requested_port: requested,
+            port,
+            reason: format!(
+                "port {requested} requires elevated privileges; listening on {port} instead"
+            ),
+            hint: privileged_port_hint(),
+        }),
+    ))
+}
+
+/// The CLI's default port when it is free, else any free port.
+pub(crate) fn free_high_port() -> Result<u16, String> {
+    if TcpListener::bind((Ipv4Addr::UNSPECIFIED, CLI_DEFAULT_PORT)).is_ok() {
+        return Ok(CLI_DEFAULT_PORT);
+    }
+    TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|err| format!("failed to find a free fallback port: {err}"))
+}
+
+#[cfg(target_os = "linux")]
+fn privileged_port_hint() -> Option<String> {
+    // The capability belongs on whatever actually binds: the bundled CLI
+    // binary, or node when running the CLI from source.
+    let binary = match crate::resolve_cli_runtime() {
+        Ok(crate::CliRuntime::Binary(path)) => path.display().to_string(),
+        _ => "$(command -v node)".to_string(),
+    };
+    Some(format!(
+        "To keep a low port, grant the bind capability once: sudo setcap 'cap_net_bind_service=+ep' {binary}, or lower net.ipv4.ip_unprivileged_port_start"
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn privileged_port_hint() -> Option<String> {
+    Some("Ports below 1024 need administrator rights on this system; choose a port of 1024 or higher".to_string())
+}
@@ -0,0 +1,34 @@
+use crate::archive;
+use crate::auto_print;
+use crate::media_import;
+use crate::settings::load_settings;
+use std::path::PathBuf;
+use std::thread;
+use tauri::AppHandle;
+
+/// Runs the opt-in processing stages for a file the listener just saved. Each
+/// file is handled on its own worker thread so slow stages never hold up the
+/// listener's output; the stages themselves run in order.
+pub(crate) fn file_saved(app: &AppHandle, path: PathBuf, from: Option<String>) {
+    let Ok(settings) = load_settings(app) else {
+        return;
+    };
+    if !settings.auto_extract.enabled
+        && !settings.media_import.enabled
+        && !settings.auto_print.enabled
+    {
+        return;
+    }
+    let app = app.clone();
+    thread::spawn(move || {
+        if settings.auto_extract.enabled {
+            archive::extract_received(&app, &path, settings.auto_extract);
+        }
+        if settings.auto_print.enabled && path.is_file() {
+            auto_print::print_received(&app, &path, from.as_deref(), &settings.auto_print);
+        }
+        if settings.media_import.enabled && path.is_file() {
+            media_import::import_received(&app, &path, &settings.media_import);
+        }
+    });
+}
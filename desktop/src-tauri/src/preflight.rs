@@ -0,0 +1,210 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::paths::{measure_path_until, validate_send_source};
+use crate::{history, AppState};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+/// Keeps the report readable for trees with many colliding names.
+const MAX_NAME_ISSUES: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightProgressPayload {
+    preflight_id: u64,
+    path: String,
+    files: usize,
+    total_bytes: u64,
+    done: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightReport {
+    preflight_id: u64,
+    path: String,
+    files: usize,
+    total_bytes: u64,
+    /// Average of recent sends to the same device (or any device when there
+    /// is no history for it yet); `None` without any timed history.
+    throughput_bytes_per_sec: Option<u64>,
+    eta_seconds: Option<u64>,
+    walk_ms: u64,
+    /// Paths that would land on the same file on a case-insensitive or
+    /// normalizing filesystem (macOS, Windows); the CLI renames them unless
+    /// told otherwise.
+    name_issues: Vec<NameIssue>,
+    /// Names in decomposed (NFD) form, typical for files from macOS. They are
+    /// sent composed by default.
+    decomposed_names: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum NameIssueKind {
+    Case,
+    Unicode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NameIssue {
+    kind: NameIssueKind,
+    paths: Vec<String>,
+}
+
+/// Groups relative paths the way `src/names.ts` does: by NFC form, lowercased.
+#[derive(Debug, Default)]
+struct NameCheck {
+    groups: HashMap<String, Vec<String>>,
+    decomposed: usize,
+}
+
+impl NameCheck {
+    fn add(&mut self, relative: &str) {
+        if !is_nfc(relative) {
+            self.decomposed += 1;
+        }
+        let key = relative.nfc().collect::<String>().to_lowercase();
+        self.groups
+            .entry(key)
+            .or_default()
+            .push(relative.to_string());
+    }
+
+    fn issues(self) -> Vec<NameIssue> {
+        let mut issues = self
+            .groups
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .map(|mut paths| {
+                paths.sort();
+                let mut composed = paths
+                    .iter()
+                    .map(|path| path.nfc().collect::<String>())
+                    .collect::<Vec<_>>();
+                composed.sort();
+                composed.dedup();
+                NameIssue {
+                    kind: if composed.len() < paths.len() {
+                        NameIssueKind::Unicode
+                    } else {
+                        NameIssueKind::Case
+                    },
+                    paths,
+                }
+            })
+            .collect::<Vec<_>>();
+        issues.sort_by(|a, b| a.paths.cmp(&b.paths));
+        issues.truncate(MAX_NAME_ISSUES);
+        issues
+    }
+}
+
+fn relative_name(root: &Path, file: &Path) -> String {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    let relative = if relative.as_os_str().is_empty() {
+        file.file_name().map(Path::new).unwrap_or(file)
+    } else {
+        relative
+    };
+    relative
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+/// Counts the files and bytes a send of `path` would transfer and estimates
+/// how long it takes from recent throughput to the target. Walking a huge
+/// tree emits `preflightProgress` events and stops when `cancel_preflight`
+/// is called or another preflight starts. Names that would collide on the
+/// receiver's filesystem are listed as well.
+#[tauri::command]
+pub(crate) async fn preflight_send(
+    app: AppHandle,
+    path: String,
+    host: Option<String>,
+    device: Option<String>,
+) -> Result<PreflightReport, String> {
+    let source = validate_send_source(&path)?;
+    let preflight_id = app
+        .state::<AppState>()
+        .preflight_generation
+        .fetch_add(1, Ordering::SeqCst)
+        + 1;
+    let display = source.to_string_lossy().to_string();
+
+    let walk_app = app.clone();
+    let walk_display = display.clone();
+    let started = Instant::now();
+    let measured = tauri::async_runtime::spawn_blocking(move || {
+        let state = walk_app.state::<AppState>();
+        let mut last_emit = Instant::now();
+        let mut names = NameCheck::default();
+        let emit = |files: usize, total_bytes: u64, done: bool| {
+            emit_app_event(
+                &walk_app,
+                AppEvent::PreflightProgress(PreflightProgressPayload {
+                    preflight_id,
+                    path: walk_display.clone(),
+                    files,
+                    total_bytes,
+                    done,
+                }),
+            );
+        };
+        let measured = measure_path_until(&source, |files, total_bytes, file| {
+            if state.preflight_generation.load(Ordering::SeqCst) != preflight_id {
+                return false;
+            }
+            names.add(&relative_name(&source, file));
+            if last_emit.elapsed() >= PROGRESS_INTERVAL {
+                last_emit = Instant::now();
+                emit(files, total_bytes, false);
+            }
+            true
+        });
+        let (files, total_bytes) = measured
+            .as_ref()
+            .ok()
+            .and_then(|totals| *totals)
+            .unwrap_or_default();
+        emit(files, total_bytes, true);
+        measured.map(|totals| totals.map(|totals| (totals, names)))
+    })
+    .await
+    .map_err(|err| format!("failed to join preflight task: {err}"))?
+    .map_err(|err| format!("failed to scan {display}: {err}"))?;
+    let Some(((files, total_bytes), names)) = measured else {
+        return Err("preflight cancelled".to_string());
+    };
+    let walk_ms = started.elapsed().as_millis() as u64;
+
+    let host = host.filter(|value| !value.trim().is_empty());
+    let device = device.filter(|value| !value.trim().is_empty());
+    let throughput = history::recent_throughput(&app, host.as_deref(), device.as_deref());
+    Ok(PreflightReport {
+        preflight_id,
+        path: display,
+        files,
+        total_bytes,
+        throughput_bytes_per_sec: throughput,
+        eta_seconds: throughput
+            .filter(|bytes_per_sec| *bytes_per_sec > 0)
+            .map(|bytes_per_sec| total_bytes.div_ceil(bytes_per_sec)),
+        walk_ms,
+        decomposed_names: names.decomposed,
+        name_issues: names.issues(),
+    })
+}
+
+#[tauri::command]
+pub(crate) fn cancel_preflight(app: AppHandle) {
+    app.state::<AppState>()
+        .preflight_generation
+        .fetch_add(1, Ordering::SeqCst);
+}
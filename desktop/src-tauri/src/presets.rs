@@ -0,0 +1,170 @@
+use crate::device_groups::resolve_group;
+use crate::secrets;
+use crate::send_multi::fan_out;
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{run_send_request, AppState, CommandResult, SendRequest};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const SEND_PRESETS_FILE: &str = "send-presets.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendPreset {
+    name: String,
+    pub(crate) request: SendRequest,
+    #[serde(default)]
+    paths: Vec<String>,
+    /// Sends to every member of this device group instead of the request's
+    /// own host or device.
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    updated_at_ms: u64,
+}
+
+fn load_presets(app: &AppHandle) -> Result<Vec<SendPreset>, String> {
+    read_json(&data_file(app, SEND_PRESETS_FILE)?)
+}
+
+fn save_presets(app: &AppHandle, presets: &[SendPreset]) -> Result<(), String> {
+    write_json(&data_file(app, SEND_PRESETS_FILE)?, presets)
+}
+
+fn same_preset_name(left: &str, right: &str) -> bool {
+    left.trim().eq_ignore_ascii_case(right.trim())
+}
+
+#[tauri::command]
+pub(crate) fn list_send_presets(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<SendPreset>, String> {
+    let _guard = state
+        .presets_lock
+        .lock()
+        .map_err(|_| "failed to lock send presets".to_string())?;
+    load_presets(&app)
+}
+
+#[tauri::command]
+pub(crate) fn save_send_preset(
+    app: AppHandle,
+    state: State<AppState>,
+    preset: SendPreset,
+) -> Result<SendPreset, String> {
+    let name = preset.name.trim().to_string();
+    if name.is_empty() {
+        return Err("preset name is required".to_string());
+    }
+    if preset.request.port == 0 {
+        return Err("port must be in 1-65535".to_string());
+    }
+
+    let _guard = state
+        .presets_lock
+        .lock()
+        .map_err(|_| "failed to lock send presets".to_string())?;
+    let mut presets = load_presets(&app)?;
+    let mut request = preset.request;
+    secrets::stash_pair_code(&app, &mut request)?;
+    let preset = SendPreset {
+        request,
+        name,
+        paths: preset
+            .paths
+            .into_iter()
+            .filter(|path| !path.trim().is_empty())
+            .collect(),
+        group: preset
+            .group
+            .map(|group| group.trim().to_string())
+            .filter(|group| !group.is_empty()),
+        updated_at_ms: now_ms(),
+    };
+    match presets
+        .iter_mut()
+        .find(|item| same_preset_name(&item.name, &preset.name))
+    {
+        Some(existing) => *existing = preset.clone(),
+        None => presets.push(preset.clone()),
+    }
+    save_presets(&app, &presets)?;
+    Ok(preset)
+}
+
+#[tauri::command]
+pub(crate) fn delete_send_preset(
+    app: AppHandle,
+    state: State<AppState>,
+    name: String,
+) -> Result<bool, String> {
+    let _guard = state
+        .presets_lock
+        .lock()
+        .map_err(|_| "failed to lock send presets".to_string())?;
+    let mut presets = load_presets(&app)?;
+    let before = presets.len();
+    presets.retain(|item| !same_preset_name(&item.name, &name));
+    if presets.len() == before {
+        return Ok(false);
+    }
+    save_presets(&app, &presets)?;
+    Ok(true)
+}
+
+/// Runs a saved preset. `paths` overrides the preset's fixed path set; when
+/// neither is present the preset's own request path is used. A preset with
+/// a device group sends each path to every member, one result per path.
+#[tauri::command]
+pub(crate) async fn run_send_preset(
+    app: AppHandle,
+    name: String,
+    paths: Option<Vec<String>>,
+) -> Result<Vec<CommandResult>, String> {
+    let preset = {
+        let state = app.state::<AppState>();
+        let _guard = state
+            .presets_lock
+            .lock()
+            .map_err(|_| "failed to lock send presets".to_string())?;
+        load_presets(&app)?
+            .into_iter()
+            .find(|item| same_preset_name(&item.name, &name))
+            .ok_or_else(|| format!("send preset `{name}` not found"))?
+    };
+
+    let mut targets = paths.unwrap_or_default();
+    targets.retain(|path| !path.trim().is_empty());
+    if targets.is_empty() {
+        targets = preset.paths.clone();
+    }
+    if targets.is_empty() && !preset.request.path.trim().is_empty() {
+        targets.push(preset.request.path.clone());
+    }
+    if targets.is_empty() {
+        return Err(format!(
+            "send preset `{}` has no paths to send",
+            preset.name
+        ));
+    }
+
+    let mut results = Vec::with_capacity(targets.len());
+    for path in targets {
+        let mut request = preset.request.clone();
+        request.path = path;
+        secrets::restore_pair_code(&app, &mut request);
+        let result = match preset.group.as_deref() {
+            Some(group) => {
+                // Resolved per path: devices may come and go during a long run.
+                let resolved = resolve_group(&app, group).await?;
+                fan_out(&app, &request, resolved.targets, resolved.missing)
+                    .await?
+                    .to_command_result()
+            }
+            None => run_send_request(app.clone(), request).await?,
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
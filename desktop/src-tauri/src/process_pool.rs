@@ -0,0 +1,167 @@
+use crate::storage::now_ms;
+use crate::{directory_beacon, inspect_listen_state, terminate_process_tree, AppState};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::process::{Child, Command};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+/// Upper bound on CLI children alive at once, so a runaway batch of sends
+/// fails loudly instead of exhausting the machine.
+pub(crate) const MAX_CHILDREN: usize = 16;
+const REAP_INTERVAL: Duration = Duration::from_secs(10);
+/// Read by the CLI, which exits once this process is gone (`src/cli.ts`).
+const PARENT_PID_ENV: &str = "LOCAL_SENT_PARENT_PID";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ChildKind {
+    Listen,
+    Send,
+    Discover,
+}
+
+impl ChildKind {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ChildKind::Listen => "listen",
+            ChildKind::Send => "send",
+            ChildKind::Discover => "discover",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PooledProcess {
+    pid: u32,
+    kind: ChildKind,
+    started_at_ms: u64,
+}
+
+/// Every CLI child the app has started and not yet waited for.
+#[derive(Debug, Default)]
+pub(crate) struct ProcessPool {
+    children: HashMap<u32, PooledProcess>,
+}
+
+/// Starts `command` as a tracked child. Owners must `release` the pid once
+/// they have waited for the child.
+pub(crate) fn spawn(
+    state: &AppState,
+    kind: ChildKind,
+    command: &mut Command,
+) -> Result<Child, String> {
+    // Held across the spawn so exit cleanup cannot miss a child started
+    // while it drains the pool.
+    let mut pool = state
+        .process_pool
+        .lock()
+        .map_err(|_| "failed to lock process pool".to_string())?;
+    if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+        return Err("the app is shutting down".to_string());
+    }
+    if pool.children.len() >= MAX_CHILDREN {
+        return Err(format!(
+            "too many CLI processes are running ({MAX_CHILDREN}); wait for a transfer to finish"
+        ));
+    }
+    command.env(PARENT_PID_ENV, std::process::id().to_string());
+    let child = command
+        .spawn()
+        .map_err(|err| format!("failed to start {} process: {err}", kind.label()))?;
+    let pid = child.id();
+    pool.children.insert(
+        pid,
+        PooledProcess {
+            pid,
+            kind,
+            started_at_ms: now_ms(),
+        },
+    );
+    Ok(child)
+}
+
+pub(crate) fn release(state: &AppState, pid: u32) {
+    if let Ok(mut pool) = state.process_pool.lock() {
+        pool.children.remove(&pid);
+    }
+}
+
+pub(crate) fn len(state: &AppState) -> usize {
+    state
+        .process_pool
+        .lock()
+        .map(|pool| pool.children.len())
+        .unwrap_or(0)
+}
+
+/// Empties the pool and kills every process tree in it except `keep`, which
+/// the caller terminates itself.
+pub(crate) fn terminate_all(state: &AppState, keep: Option<u32>) {
+    let pids = match state.process_pool.lock() {
+        Ok(mut pool) => pool.children.drain().map(|(pid, _)| pid).collect(),
+        Err(_) => Vec::new(),
+    };
+    for pid in pids {
+        if Some(pid) != keep {
+            terminate_process_tree(pid);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn process_exists(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks that the pid can be signalled.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: u32) -> bool {
+    true
+}
+
+/// Waits for a listener that exited on its own, so it does not linger as a
+/// zombie until the UI next asks for its state, takes it out of the
+/// directory, and forgets pids whose process is gone without its owner
+/// releasing it.
+fn reap(app: &AppHandle) {
+    let state = app.state::<AppState>();
+    let beacon = directory_beacon::registered_generation(app);
+    let stopped = inspect_listen_state(&state).is_ok_and(|snapshot| !snapshot.running);
+    if let (true, Some(generation)) = (stopped, beacon) {
+        directory_beacon::stop_generation(app, generation);
+    }
+    if let Ok(mut pool) = state.inner().process_pool.lock() {
+        pool.children.retain(|pid, _| process_exists(*pid));
+    }
+}
+
+pub(crate) fn spawn_reaper_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(REAP_INTERVAL);
+        if app
+            .state::<AppState>()
+            .shutdown_cleanup_started
+            .load(Ordering::SeqCst)
+        {
+            return;
+        }
+        reap(&app);
+    });
+}
+
+/// The CLI processes currently running, oldest first.
+#[tauri::command]
+pub(crate) fn list_cli_processes(state: State<AppState>) -> Result<Vec<PooledProcess>, String> {
+    let pool = state
+        .process_pool
+        .lock()
+        .map_err(|_| "failed to lock process pool".to_string())?;
+    let mut processes: Vec<PooledProcess> = pool.children.values().cloned().collect();
+    processes.sort_by_key(|process| process.started_at_ms);
+    Ok(processes)
+}
@@ -0,0 +1,257 @@
+//! The app's side of the CLI's progress analytics (`src/progressAnalytics.ts`):
+//! the same smoothed rate, ETA, stall and regime rules for the sends the
+//! app makes itself, and the one place progress lines are rendered, so CLI
+//! and native transfers read the same everywhere.
+
+use crate::parser::{BatchProgress, ThroughputRegime};
+use crate::zero_copy::format_bytes;
+use std::time::{Duration, Instant};
+
+/// Weight of the newest sample in the moving average.
+const RATE_SMOOTHING: f64 = 0.3;
+/// Bytes arriving closer together than this are pooled into one sample.
+const MIN_SAMPLE: Duration = Duration::from_millis(250);
+/// No new bytes for this long counts as a stall.
+const STALL_AFTER: Duration = Duration::from_secs(5);
+/// Before this, the average is too young to classify.
+const WARMUP: Duration = Duration::from_secs(2);
+/// A smoothed deviation above this share of the rate is bursty.
+const BURSTY_DEVIATION: f64 = 0.5;
+/// Below this share of the best rate seen, the transfer has degraded.
+const DEGRADED_SHARE: f64 = 0.25;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ProgressSnapshot {
+    pub(crate) bytes_per_second: u64,
+    /// `None` while the rate is unknown or the transfer is stalled.
+    pub(crate) eta_seconds: Option<u64>,
+    pub(crate) stalled: bool,
+    pub(crate) regime: ThroughputRegime,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ProgressStats {
+    started: Instant,
+    bytes: u64,
+    sample_at: Instant,
+    sample_bytes: u64,
+    last_advance: Instant,
+    rate: Option<f64>,
+    deviation: f64,
+    peak_rate: f64,
+}
+
+impl ProgressStats {
+    pub(crate) fn new(started: Instant) -> Self {
+        Self {
+            started,
+            bytes: 0,
+            sample_at: started,
+            sample_bytes: 0,
+            last_advance: started,
+            rate: None,
+            deviation: 0.0,
+            peak_rate: 0.0,
+        }
+    }
+
+    pub(crate) fn observe(&mut self, bytes: u64, now: Instant) {
+        if bytes > self.bytes {
+            self.last_advance = now;
+        }
+        self.bytes = bytes;
+        let elapsed = now.saturating_duration_since(self.sample_at);
+        if elapsed < MIN_SAMPLE {
+            return;
+        }
+        let sample = bytes.saturating_sub(self.sample_bytes) as f64 / elapsed.as_secs_f64();
+        let rate = match self.rate {
+            None => sample,
+            Some(rate) => {
+                self.deviation = RATE_SMOOTHING * (sample - rate).abs()
+                    + (1.0 - RATE_SMOOTHING) * self.deviation;
+                RATE_SMOOTHING * sample + (1.0 - RATE_SMOOTHING) * rate
+            }
+        };
+        self.rate = Some(rate);
+        self.peak_rate = self.peak_rate.max(rate);
+        self.sample_at = now;
+        self.sample_bytes = bytes;
+    }
+
+    /// `total` is `None` for content of unknown length.
+    pub(crate) fn snapshot(&self, total: Option<u64>, now: Instant) -> ProgressSnapshot {
+        let stalled = now.saturating_duration_since(self.last_advance) >= STALL_AFTER
+            && total.is_none_or(|total| self.bytes < total);
+        // Until the first sample, the plain average is all there is.
+        let rate = self.rate.unwrap_or_else(|| {
+            let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+            self.bytes as f64 / elapsed.max(1.0)
+        });
+        let eta_seconds = match total {
+            Some(total) if !stalled && rate > 0.0 => {
+                Some((total.saturating_sub(self.bytes) as f64 / rate).ceil() as u64)
+            }
+            _ => None,
+        };
+        ProgressSnapshot {
+            bytes_per_second: rate.round() as u64,
+            eta_seconds,
+            stalled,
+            regime: self.regime(stalled, rate, now),
+        }
+    }
+
+    fn regime(&self, stalled: bool, rate: f64, now: Instant) -> ThroughputRegime {
+        if stalled {
+            ThroughputRegime::Stalled
+        } else if self.rate.is_none() || now.saturating_duration_since(self.started) < WARMUP {
+            ThroughputRegime::Starting
+        } else if rate < self.peak_rate * DEGRADED_SHARE {
+            ThroughputRegime::Degraded
+        } else if self.deviation > rate * BURSTY_DEVIATION {
+            ThroughputRegime::Bursty
+        } else {
+            ThroughputRegime::Steady
+        }
+    }
+}
+
+fn regime_note(regime: ThroughputRegime) -> &'static str {
+    match regime {
+        ThroughputRegime::Steady => "",
+        ThroughputRegime::Starting => " starting",
+        ThroughputRegime::Bursty => " bursty",
+        ThroughputRegime::Degraded => " degraded",
+        ThroughputRegime::Stalled => " stalled",
+    }
+}
+
+/// Same shape as the CLI's progress line, e.g.
+/// `[send a.bin] 42.0% (42.0 MB/100.0 MB) 9.8 MB/s ETA 6s`, which is what
+/// the frontend parses.
+pub(crate) fn render_line(
+    prefix: &str,
+    bytes: u64,
+    total: Option<u64>,
+    snapshot: &ProgressSnapshot,
+) -> String {
+    let speed = format_bytes(snapshot.bytes_per_second as f64);
+    let note = regime_note(snapshot.regime);
+    match total {
+        Some(total) => {
+            let percent = if total == 0 {
+                100.0
+            } else {
+                bytes as f64 / total as f64 * 100.0
+            };
+            let eta = snapshot
+                .eta_seconds
+                .map(|eta| format!(" ETA {eta}s"))
+                .unwrap_or_default();
+            format!(
+                "{prefix} {percent:.1}% ({}/{}) {speed}/s{eta}{note}",
+                format_bytes(bytes as f64),
+                format_bytes(total as f64)
+            )
+        }
+        None => format!(
+            "{prefix} streaming {} {speed}/s{note}",
+            format_bytes(bytes as f64)
+        ),
+    }
+}
+
+/// Appended to the progress line of each file of a multi-file send.
+pub(crate) fn render_batch(batch: &BatchProgress) -> String {
+    let overall = if batch.total == 0 {
+        100.0
+    } else {
+        batch.bytes as f64 / batch.total as f64 * 100.0
+    };
+    format!(
+        " [{}/{} files, {overall:.1}% overall]",
+        batch.index, batch.count
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(started: Instant, millis: u64) -> Instant {
+        started + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn one_slow_sample_moves_the_rate_part_of_the_way() {
+        let started = Instant::now();
+        let mut stats = ProgressStats::new(started);
+        stats.observe(1000, at(started, 250));
+        stats.observe(2000, at(started, 500));
+        let early = stats.snapshot(Some(10_000), at(started, 500));
+        assert_eq!(early.bytes_per_second, 4000);
+        assert_eq!(early.eta_seconds, Some(2));
+        assert_eq!(early.regime, ThroughputRegime::Starting);
+
+        stats.observe(3000, at(started, 2500));
+        let slowed = stats.snapshot(Some(10_000), at(started, 2500));
+        assert_eq!(slowed.bytes_per_second, 2950);
+        assert_eq!(slowed.eta_seconds, Some(3));
+        assert_eq!(slowed.regime, ThroughputRegime::Steady);
+    }
+
+    #[test]
+    fn no_new_bytes_for_a_while_is_a_stall_without_eta() {
+        let started = Instant::now();
+        let mut stats = ProgressStats::new(started);
+        stats.observe(1000, at(started, 500));
+        let stalled = stats.snapshot(Some(10_000), at(started, 5500));
+        assert!(stalled.stalled);
+        assert_eq!(stalled.eta_seconds, None);
+        assert_eq!(stalled.regime, ThroughputRegime::Stalled);
+        // A finished transfer is not stalled, however long ago it ended.
+        assert!(!stats.snapshot(Some(1000), at(started, 9000)).stalled);
+    }
+
+    #[test]
+    fn falling_far_below_the_peak_is_degraded() {
+        let started = Instant::now();
+        let mut stats = ProgressStats::new(started);
+        let mut bytes = 0;
+        for step in 1..=8 {
+            bytes += 100_000;
+            stats.observe(bytes, at(started, step * 500));
+        }
+        for step in 9..=20 {
+            bytes += 1_000;
+            stats.observe(bytes, at(started, step * 500));
+        }
+        let snapshot = stats.snapshot(Some(10_000_000), at(started, 10_000));
+        assert_eq!(snapshot.regime, ThroughputRegime::Degraded);
+    }
+
+    #[test]
+    fn renders_the_cli_line() {
+        let snapshot = ProgressSnapshot {
+            bytes_per_second: 2048,
+            eta_seconds: Some(3),
+            stalled: false,
+            regime: ThroughputRegime::Steady,
+        };
+        assert_eq!(
+            render_line("[send a.bin]", 512, Some(1024), &snapshot),
+            "[send a.bin] 50.0% (512 B/1.0 KB) 2.0 KB/s ETA 3s"
+        );
+        let stalled = ProgressSnapshot {
+            eta_seconds: None,
+            stalled: true,
+            regime: ThroughputRegime::Stalled,
+            ..snapshot
+        };
+        assert_eq!(
+            render_line("[recv b.bin]", 512, None, &stalled),
+            "[recv b.bin] streaming 512 B 2.0 KB/s stalled"
+        );
+    }
+}
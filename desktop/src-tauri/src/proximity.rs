@@ -0,0 +1,248 @@
+//! Orders discovered devices so the one the user most likely means is first
+//! in the picker, much as AirDrop lists the people in the room first. Each
+//! device is scored from how quickly it accepts a connection, whether it is
+//! on one of our subnets, the kind of interface that reaches it and, where
+//! the platform tells, the Wi-Fi signal of that interface.
+
+use crate::parser::{DiscoverDevice, InterfaceKind, Nearness, Proximity};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Every device is probed at once, so this is about how much longer
+/// discovery takes.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+const NEAR_SCORE: u8 = 75;
+const NEARBY_SCORE: u8 = 45;
+/// Below this Wi-Fi quality a device is likelier to be in another room.
+const WEAK_SIGNAL: u8 = 40;
+
+/// What is known about how far one device is.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Hints {
+    /// `None` when the device did not accept a connection in time.
+    pub(crate) rtt: Option<Duration>,
+    pub(crate) same_subnet: bool,
+    /// `None` when no route to the device is known.
+    pub(crate) kind: Option<InterfaceKind>,
+    pub(crate) signal: Option<u8>,
+}
+
+pub(crate) fn score(hints: &Hints) -> Proximity {
+    let mut score: i32 = match hints.kind {
+        Some(InterfaceKind::Lan | InterfaceKind::Loopback) => 40,
+        Some(InterfaceKind::Virtual) => 25,
+        None => 20,
+        Some(InterfaceKind::Vpn) => 10,
+    };
+    if hints.same_subnet {
+        score += 20;
+    }
+    score += match hints.rtt.map(|rtt| rtt.as_millis()) {
+        Some(0..=2) => 40,
+        Some(3..=10) => 25,
+        Some(11..=50) => 10,
+        _ => 0,
+    };
+    if hints.signal.is_some_and(|signal| signal < WEAK_SIGNAL) {
+        score -= 10;
+    }
+    let score = score.clamp(0, 100) as u8;
+    Proximity {
+        score,
+        nearness: match score {
+            NEAR_SCORE.. => Nearness::Near,
+            NEARBY_SCORE.. => Nearness::Nearby,
+            _ => Nearness::Far,
+        },
+        rtt_ms: hints
+            .rtt
+            .map(|rtt| rtt.as_millis().min(u32::MAX as u128) as u32),
+        same_subnet: hints.same_subnet,
+        signal: hints.signal,
+    }
+}
+
+/// Highest score first. The sort is stable, so equally close devices keep
+/// the order `apply_policy` left them in.
+pub(crate) fn order(devices: &mut [DiscoverDevice]) {
+    devices.sort_by_key(|device| {
+        std::cmp::Reverse(
+            device
+                .proximity
+                .as_ref()
+                .map_or(0, |proximity| proximity.score),
+        )
+    });
+}
+
+fn probe(host: &str, port: u16) -> Option<Duration> {
+    let ip: IpAddr = host.parse().ok()?;
+    let started = Instant::now();
+    TcpStream::connect_timeout(&SocketAddr::new(ip, port), PROBE_TIMEOUT).ok()?;
+    Some(started.elapsed())
+}
+
+/// Link quality of `interface` out of the 0 to 70 scale of
+/// `/proc/net/wireless`, as a percentage.
+fn parse_wireless(text: &str, interface: &str) -> Option<u8> {
+    text.lines().skip(2).find_map(|line| {
+        let (name, rest) = line.split_once(':')?;
+        if name.trim() != interface {
+            return None;
+        }
+        let link: f64 = rest
+            .split_whitespace()
+            .nth(1)?
+            .trim_end_matches('.')
+            .parse()
+            .ok()?;
+        Some((link * 100.0 / 70.0).clamp(0.0, 100.0) as u8)
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn wireless_signal(interface: &str) -> Option<u8> {
+    let text = std::fs::read_to_string("/proc/net/wireless").ok()?;
+    parse_wireless(&text, interface)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wireless_signal(_interface: &str) -> Option<u8> {
+    None
+}
+
+/// Probes every device, annotates it with its proximity and sorts the list.
+/// Expects the routes `interfaces::apply_policy` fills in.
+pub(crate) fn rank(devices: &mut [DiscoverDevice]) {
+    let rtts: Vec<Option<Duration>> = std::thread::scope(|scope| {
+        let probes: Vec<_> = devices
+            .iter()
+            .map(|device| scope.spawn(|| probe(&device.host, device.port)))
+            .collect();
+        probes
+            .into_iter()
+            .map(|probe| probe.join().ok().flatten())
+            .collect()
+    });
+    for (device, rtt) in devices.iter_mut().zip(rtts) {
+        let route = device.routes.first();
+        let interface = route.and_then(|route| route.interface.as_deref());
+        device.proximity = Some(score(&Hints {
+            rtt,
+            same_subnet: interface.is_some(),
+            kind: route.map(|route| route.kind),
+            signal: interface.and_then(wireless_signal),
+        }));
+    }
+    order(devices);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::DeviceSource;
+
+    fn hints(rtt_ms: Option<u64>, same_subnet: bool, kind: InterfaceKind) -> Hints {
+        Hints {
+            rtt: rtt_ms.map(Duration::from_millis),
+            same_subnet,
+            kind: Some(kind),
+            signal: None,
+        }
+    }
+
+    fn device(name: &str, score: Option<u8>) -> DiscoverDevice {
+        DiscoverDevice {
+            name: name.to_string(),
+            host: "192.168.1.20".to_string(),
+            port: 37373,
+            addresses: Vec::new(),
+            id: None,
+            fingerprint: None,
+            version: None,
+            protocol_version: None,
+            tls: None,
+            pair_code_required: None,
+            capabilities: Vec::new(),
+            appearance: Default::default(),
+            source: DeviceSource::LocalSent,
+            routes: Vec::new(),
+            proximity: score.map(|score| Proximity {
+                score,
+                nearness: Nearness::Far,
+                rtt_ms: None,
+                same_subnet: false,
+                signal: None,
+            }),
+            directory: None,
+        }
+    }
+
+    #[test]
+    fn fast_device_on_our_subnet_is_near() {
+        let proximity = score(&hints(Some(1), true, InterfaceKind::Lan));
+        assert_eq!(proximity.score, 100);
+        assert_eq!(proximity.nearness, Nearness::Near);
+        assert_eq!(proximity.rtt_ms, Some(1));
+    }
+
+    #[test]
+    fn same_subnet_beats_routed_and_vpn() {
+        let subnet = score(&hints(Some(8), true, InterfaceKind::Lan)).score;
+        let routed = score(&hints(Some(8), false, InterfaceKind::Lan)).score;
+        let vpn = score(&hints(Some(8), true, InterfaceKind::Vpn)).score;
+        assert!(subnet > routed);
+        assert!(subnet > vpn);
+    }
+
+    #[test]
+    fn unanswered_probe_ranks_below_slow_answer() {
+        let slow = score(&hints(Some(40), true, InterfaceKind::Lan));
+        let silent = score(&hints(None, true, InterfaceKind::Lan));
+        assert!(slow.score > silent.score);
+        assert_eq!(silent.nearness, Nearness::Nearby);
+        assert_eq!(silent.rtt_ms, None);
+    }
+
+    #[test]
+    fn distant_vpn_peer_is_far() {
+        let proximity = score(&hints(Some(120), false, InterfaceKind::Vpn));
+        assert_eq!(proximity.nearness, Nearness::Far);
+    }
+
+    #[test]
+    fn weak_signal_lowers_the_score() {
+        let strong = Hints {
+            signal: Some(90),
+            ..hints(Some(5), true, InterfaceKind::Lan)
+        };
+        let weak = Hints {
+            signal: Some(20),
+            ..hints(Some(5), true, InterfaceKind::Lan)
+        };
+        assert_eq!(score(&strong).score, 85);
+        assert_eq!(score(&weak).score, 75);
+    }
+
+    #[test]
+    fn order_is_highest_first_and_stable() {
+        let mut devices = vec![
+            device("a", Some(50)),
+            device("b", None),
+            device("c", Some(90)),
+            device("d", Some(50)),
+        ];
+        order(&mut devices);
+        let names: Vec<_> = devices.iter().map(|device| device.name.as_str()).collect();
+        assert_eq!(names, ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn reads_link_quality_from_proc_net_wireless() {
+        let text = "Inter-| sta-|   Quality        |   Discarded packets               | Missed | WE\n \
+                    face | tus | link level noise |  nwid  crypt   frag  retry   misc | beacon | 22\n \
+                    wlan0: 0000   56.  -54.  -256        0      0      0      0     12        0\n";
+        assert_eq!(parse_wireless(text, "wlan0"), Some(80));
+        assert_eq!(parse_wireless(text, "wlan1"), None);
+    }
+}
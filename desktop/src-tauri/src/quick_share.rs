@@ -0,0 +1,39 @@
+//! Receiving from Google Quick Share senders through the listener's
+//! experimental `--quick-share` mode. Shares go through the same confirm
+//! prompt as other transfers.
+
+use crate::settings::{load_settings, update_settings};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct QuickShareSettings {
+    /// Applies from the next `start_listen`. Cannot be combined with
+    /// receiving to WebDAV.
+    pub(crate) enabled: bool,
+}
+
+/// `--quick-share` for `listen` while it is on.
+pub(crate) fn listen_args(app: &AppHandle) -> Vec<String> {
+    load_settings(app)
+        .ok()
+        .filter(|settings| settings.quick_share.enabled)
+        .map(|_| vec!["--quick-share".to_string()])
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub(crate) fn set_quick_share_settings(
+    app: AppHandle,
+    quick_share: QuickShareSettings,
+) -> Result<QuickShareSettings, String> {
+    update_settings(&app, |settings| {
+        if quick_share.enabled && settings.webdav.enabled {
+            return Err("turn off receiving to WebDAV before enabling Quick Share".to_string());
+        }
+        settings.quick_share = quick_share;
+        Ok(())
+    })
+    .map(|settings| settings.quick_share)
+}
@@ -0,0 +1,179 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::settings::{load_settings, update_settings, AppSettings};
+use crate::{emit_system_log, inspect_listen_state, listen_state_payload, AppState};
+use chrono::{Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum QuietAction {
+    /// Incoming prompts are answered with a rejection straight away.
+    #[default]
+    Reject,
+    /// Prompts are held back and shown once quiet hours end. Senders that
+    /// gave up waiting by then simply fail.
+    Queue,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct QuietHours {
+    pub(crate) enabled: bool,
+    /// Local `HH:MM`; a window ending before it starts spans midnight and
+    /// equal times cover the whole day.
+    pub(crate) start: String,
+    pub(crate) end: String,
+    pub(crate) action: QuietAction,
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+            action: QuietAction::Reject,
+        }
+    }
+}
+
+/// Whether quiet hours were active at the last check, and the confirm ids
+/// queued since.
+#[derive(Debug, Default)]
+pub(crate) struct QuietHoursState {
+    active: bool,
+    queued: Vec<u64>,
+}
+
+fn parse_clock(value: &str) -> Result<u32, String> {
+    let (hours, minutes) = value
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| format!("invalid time `{value}`, expected HH:MM"))?;
+    let hours: u32 = hours
+        .parse()
+        .map_err(|_| format!("invalid time `{value}`, expected HH:MM"))?;
+    let minutes: u32 = minutes
+        .parse()
+        .map_err(|_| format!("invalid time `{value}`, expected HH:MM"))?;
+    if hours > 23 || minutes > 59 {
+        return Err(format!("invalid time `{value}`, expected HH:MM"));
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl QuietHours {
+    fn contains(&self, minute: u32) -> bool {
+        let (Ok(start), Ok(end)) = (parse_clock(&self.start), parse_clock(&self.end)) else {
+            return false;
+        };
+        match start.cmp(&end) {
+            std::cmp::Ordering::Equal => true,
+            std::cmp::Ordering::Less => (start..end).contains(&minute),
+            std::cmp::Ordering::Greater => minute >= start || minute < end,
+        }
+    }
+}
+
+/// The action to take on an incoming prompt right now, if quiet hours apply.
+pub(crate) fn active_action(settings: &AppSettings) -> Option<QuietAction> {
+    let quiet = &settings.quiet_hours;
+    let now = Local::now();
+    (quiet.enabled && quiet.contains(now.hour() * 60 + now.minute())).then_some(quiet.action)
+}
+
+pub(crate) fn is_active(app: &AppHandle) -> bool {
+    load_settings(app)
+        .map(|settings| active_action(&settings).is_some())
+        .unwrap_or(false)
+}
+
+/// Holds back the prompt for `id` until quiet hours end.
+pub(crate) fn queue_confirm(state: &AppState, id: u64) {
+    if let Ok(mut quiet) = state.quiet_hours.lock() {
+        quiet.queued.push(id);
+    }
+}
+
+/// Re-evaluates quiet hours. On a change the listener state is re-emitted,
+/// and when they end the queued prompts still pending are shown.
+fn check(app: &AppHandle) {
+    let active = is_active(app);
+    let state = app.state::<AppState>();
+    let released = {
+        let Ok(mut quiet) = state.quiet_hours.lock() else {
+            return;
+        };
+        if quiet.active == active {
+            return;
+        }
+        quiet.active = active;
+        if active {
+            Vec::new()
+        } else {
+            std::mem::take(&mut quiet.queued)
+        }
+    };
+
+    if let Ok(snapshot) = inspect_listen_state(&state) {
+        emit_app_event(
+            app,
+            AppEvent::ListenState(listen_state_payload(app, snapshot.running, snapshot.pid)),
+        );
+    }
+    let pending: Vec<_> = state
+        .pending_confirms
+        .lock()
+        .map(|pending| {
+            released
+                .iter()
+                .filter_map(|id| pending.get(id).cloned())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !pending.is_empty() {
+        emit_system_log(
+            app,
+            format!(
+                "quiet hours ended, showing {} queued request(s)",
+                pending.len()
+            ),
+        );
+    }
+    for payload in pending {
+        emit_app_event(app, AppEvent::TransferConfirmRequest(payload));
+    }
+}
+
+pub(crate) fn spawn_watch_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        check(&app);
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+#[tauri::command]
+pub(crate) fn set_quiet_hours(
+    app: AppHandle,
+    quiet_hours: QuietHours,
+) -> Result<QuietHours, String> {
+    parse_clock(&quiet_hours.start)?;
+    parse_clock(&quiet_hours.end)?;
+    let saved = update_settings(&app, |settings| {
+        settings.quiet_hours = quiet_hours;
+        Ok(())
+    })
+    .map(|settings| settings.quiet_hours)?;
+    check(&app);
+    Ok(saved)
+}
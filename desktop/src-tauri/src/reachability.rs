@@ -0,0 +1,313 @@
+use crate::network::{command_stdout, current_network};
+use if_addrs::{get_if_addrs, IfAddr};
+use serde::Serialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+const PROBE_TIMEOUT: Duration = Duration::from_millis(800);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReachabilityFinding {
+    code: &'static str,
+    status: &'static str,
+    detail: String,
+    hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReachabilityReport {
+    port: u16,
+    listening: bool,
+    findings: Vec<ReachabilityFinding>,
+}
+
+fn finding(
+    code: &'static str,
+    status: &'static str,
+    detail: impl Into<String>,
+    hint: Option<&str>,
+) -> ReachabilityFinding {
+    ReachabilityFinding {
+        code,
+        status,
+        detail: detail.into(),
+        hint: hint.map(str::to_string),
+    }
+}
+
+fn probe(address: IpAddr, port: u16) -> bool {
+    TcpStream::connect_timeout(&SocketAddr::new(address, port), PROBE_TIMEOUT).is_ok()
+}
+
+fn lan_ipv4_interfaces() -> Vec<(String, Ipv4Addr, Ipv4Addr)> {
+    let Ok(ifaces) = get_if_addrs() else {
+        return Vec::new();
+    };
+    ifaces
+        .into_iter()
+        .filter(|iface| !iface.is_loopback())
+        .filter_map(|iface| match iface.addr {
+            IfAddr::V4(v4) if !v4.ip.is_link_local() => Some((iface.name, v4.ip, v4.netmask)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn same_subnet(left: Ipv4Addr, right: Ipv4Addr, netmask: Ipv4Addr) -> bool {
+    let mask = u32::from(netmask);
+    u32::from(left) & mask == u32::from(right) & mask
+}
+
+/// Runs the probes for `diagnose_reachability`. Every check is local: the
+/// listener is probed on each LAN address, the firewall state is read where
+/// the platform allows it, and the neighbor table hints at AP isolation.
+fn diagnose(port: u16) -> ReachabilityReport {
+    let mut findings = Vec::new();
+
+    let listening = probe(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+    if listening {
+        findings.push(finding(
+            "listener-local",
+            "pass",
+            format!("port {port} accepts connections on loopback"),
+            None,
+        ));
+    } else {
+        findings.push(finding(
+            "listener-local",
+            "fail",
+            format!("nothing accepts connections on port {port}"),
+            Some("Start the listener, or check whether it exited with an error"),
+        ));
+    }
+
+    let interfaces = lan_ipv4_interfaces();
+    if interfaces.is_empty() {
+        findings.push(finding(
+            "no-lan-interface",
+            "fail",
+            "no LAN IPv4 interface is up",
+            Some("Connect to Wi-Fi or Ethernet"),
+        ));
+    }
+    for (name, address, _) in &interfaces {
+        if !listening {
+            break;
+        }
+        if probe(IpAddr::V4(*address), port) {
+            findings.push(finding(
+                "interface-reachable",
+                "pass",
+                format!("{address}:{port} on {name} accepts connections"),
+                None,
+            ));
+        } else {
+            findings.push(finding(
+                "interface-unreachable",
+                "fail",
+                format!("{address}:{port} on {name} refused or timed out"),
+                Some("The listener may be bound to loopback only, or a local firewall filters this interface"),
+            ));
+        }
+    }
+
+    findings.extend(firewall_findings(port));
+    findings.extend(ap_isolation_findings(&interfaces));
+
+    ReachabilityReport {
+        port,
+        listening,
+        findings,
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn firewall_findings(port: u16) -> Vec<ReachabilityFinding> {
+    let Some(output) = command_stdout("netsh", &["advfirewall", "show", "currentprofile", "state"])
+    else {
+        return vec![finding(
+            "firewall-unknown",
+            "info",
+            "could not read Windows Firewall state",
+            None,
+        )];
+    };
+    let enabled = output
+        .lines()
+        .any(|line| line.trim_start().starts_with("State") && line.contains("ON"));
+    if !enabled {
+        return vec![finding(
+            "firewall-off",
+            "pass",
+            "Windows Firewall is off",
+            None,
+        )];
+    }
+    vec![finding(
+        "firewall-blocking",
+        "warn",
+        format!("Windows Firewall is on for the current profile; inbound TCP {port} may be blocked"),
+        Some("Allow Local Sent through Windows Defender Firewall, or make sure the network is set to Private rather than Public"),
+    )]
+}
+
+#[cfg(target_os = "macos")]
+fn firewall_findings(_port: u16) -> Vec<ReachabilityFinding> {
+    const SOCKETFILTERFW: &str = "/usr/libexec/ApplicationFirewall/socketfilterfw";
+    let Some(state) = command_stdout(SOCKETFILTERFW, &["--getglobalstate"]) else {
+        return vec![finding(
+            "firewall-unknown",
+            "info",
+            "could not read macOS application firewall state",
+            None,
+        )];
+    };
+    if !state.contains("enabled") {
+        return vec![finding(
+            "firewall-off",
+            "pass",
+            "macOS firewall is off",
+            None,
+        )];
+    }
+    let block_all = command_stdout(SOCKETFILTERFW, &["--getblockall"])
+        .is_some_and(|output| output.contains("enabled") || output.contains("ENABLED"));
+    if block_all {
+        return vec![finding(
+            "firewall-blocking",
+            "fail",
+            "macOS firewall blocks all incoming connections",
+            Some("Turn off \"Block all incoming connections\" in System Settings > Network > Firewall"),
+        )];
+    }
+    vec![finding(
+        "firewall-blocking",
+        "warn",
+        "macOS firewall is on; the CLI must be allowed to accept incoming connections",
+        Some("Allow incoming connections for Local Sent in System Settings > Network > Firewall > Options"),
+    )]
+}
+
+#[cfg(target_os = "linux")]
+fn firewall_findings(port: u16) -> Vec<ReachabilityFinding> {
+    if let Some(output) = command_stdout("ufw", &["status"]) {
+        if output.contains("Status: active") {
+            let allowed = output
+                .lines()
+                .any(|line| line.starts_with(&format!("{port}")) && line.contains("ALLOW"));
+            return vec![if allowed {
+                finding(
+                    "firewall-allowed",
+                    "pass",
+                    format!("ufw allows port {port}"),
+                    None,
+                )
+            } else {
+                finding(
+                    "firewall-blocking",
+                    "warn",
+                    format!("ufw is active and has no rule for port {port}"),
+                    Some(&format!("Run: sudo ufw allow {port}/tcp")),
+                )
+            }];
+        }
+    }
+    if let Some(output) = command_stdout("firewall-cmd", &["--state"]) {
+        if output.trim() == "running" {
+            return vec![finding(
+                "firewall-blocking",
+                "warn",
+                format!("firewalld is running; inbound TCP {port} may be blocked"),
+                Some(&format!(
+                    "Run: sudo firewall-cmd --add-port={port}/tcp (add --permanent to keep it)"
+                )),
+            )];
+        }
+    }
+    vec![finding(
+        "firewall-unknown",
+        "info",
+        "no active ufw or firewalld detected (iptables/nftables rules are not inspected)",
+        None,
+    )]
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn firewall_findings(_port: u16) -> Vec<ReachabilityFinding> {
+    Vec::new()
+}
+
+/// Counts other hosts in the neighbor table that share a subnet with us. A
+/// reachable gateway with no visible neighbors is typical of client/AP
+/// isolation on guest and public Wi-Fi.
+fn ap_isolation_findings(interfaces: &[(String, Ipv4Addr, Ipv4Addr)]) -> Vec<ReachabilityFinding> {
+    let network = current_network();
+    let Some(gateway) = network
+        .gateway
+        .as_deref()
+        .and_then(|gateway| gateway.parse::<Ipv4Addr>().ok())
+    else {
+        return Vec::new();
+    };
+
+    let neighbors = neighbor_ipv4_addresses();
+    let peers = neighbors
+        .iter()
+        .filter(|address| **address != gateway)
+        .filter(|address| {
+            interfaces.iter().any(|(_, local, netmask)| {
+                *address != local && same_subnet(**address, *local, *netmask)
+            })
+        })
+        .count();
+
+    if peers > 0 {
+        return vec![finding(
+            "lan-peers-visible",
+            "pass",
+            format!("{peers} other LAN host(s) visible in the neighbor table"),
+            None,
+        )];
+    }
+    vec![finding(
+        "ap-isolation-suspected",
+        "warn",
+        format!("only the gateway {gateway} is visible on this network"),
+        Some("Guest or public Wi-Fi often isolates clients from each other; try a private network or a wired connection"),
+    )]
+}
+
+fn neighbor_ipv4_addresses() -> Vec<Ipv4Addr> {
+    let output = if cfg!(target_os = "linux") {
+        command_stdout("ip", &["-4", "neigh", "show"])
+    } else {
+        command_stdout("arp", &["-a"])
+    };
+    let Some(output) = output else {
+        return Vec::new();
+    };
+    output
+        .lines()
+        .filter(|line| !line.contains("FAILED") && !line.contains("incomplete"))
+        .filter_map(|line| {
+            line.split_whitespace()
+                .map(|token| token.trim_matches(|ch| ch == '(' || ch == ')'))
+                .find_map(|token| token.parse::<Ipv4Addr>().ok())
+        })
+        .filter(|address| !address.is_multicast() && !address.is_broadcast())
+        .collect()
+}
+
+/// Explains the common "listener started but nobody can reach it" case with
+/// concrete findings the UI can show next to the listener status.
+#[tauri::command]
+pub(crate) async fn diagnose_reachability(port: u16) -> Result<ReachabilityReport, String> {
+    if port == 0 {
+        return Err("port must be between 1 and 65535".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || diagnose(port))
+        .await
+        .map_err(|err| format!("failed to join reachability task: {err}"))
+}
@@ -0,0 +1,426 @@
+use crate::network::command_stdout;
+use crate::paths::{approved_dirs, home_dir, validate_output_dir};
+use crate::settings::{load_settings, update_settings};
+use crate::{dedup, default_download_dir, emit_system_log, history, AppState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Auto-deletes files received more than `max_age_days` ago from `folder`.
+/// Only files recorded in the receive history or the received index are
+/// touched, and their age is the time they were received, not their mtime
+/// (which `--preserve-metadata` sets to the sender's).
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RetentionPolicy {
+    pub(crate) folder: String,
+    pub(crate) max_age_days: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileAge {
+    path: String,
+    modified_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReceiveFolderStats {
+    path: String,
+    total_bytes: u64,
+    file_count: usize,
+    oldest: Option<FileAge>,
+    newest: Option<FileAge>,
+    /// Free space on the folder's volume, when the platform tool reports it.
+    free_bytes: Option<u64>,
+}
+
+fn modified_ms(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Visits every regular file below `root` (symlinks are not followed).
+fn walk_files(root: &Path, mut visit: impl FnMut(&Path, &fs::Metadata)) {
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                if let Ok(metadata) = entry.metadata() {
+                    visit(&entry.path(), &metadata);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn volume_free_bytes(path: &Path) -> Option<u64> {
+    let path = path.to_string_lossy();
+    let output = command_stdout("df", &["-Pk", path.as_ref()])?;
+    let line = output.lines().nth(1)?;
+    let available_kb = line.split_whitespace().nth(3)?.parse::<u64>().ok()?;
+    Some(available_kb * 1024)
+}
+
+#[cfg(target_os = "windows")]
+fn volume_free_bytes(path: &Path) -> Option<u64> {
+    let path = path.to_string_lossy();
+    let script = format!(
+        "(Get-Item -LiteralPath '{}').PSDrive.Free",
+        path.replace('\'', "''")
+    );
+    command_stdout("powershell", &["-NoProfile", "-Command", &script])?
+        .trim()
+        .parse::<u64>()
+        .ok()
+}
+
+fn collect_stats(path: PathBuf) -> ReceiveFolderStats {
+    let mut total_bytes = 0;
+    let mut file_count = 0;
+    let mut oldest: Option<FileAge> = None;
+    let mut newest: Option<FileAge> = None;
+    walk_files(&path, |file, metadata| {
+        total_bytes += metadata.len();
+        file_count += 1;
+        let modified_at_ms = modified_ms(metadata);
+        if oldest
+            .as_ref()
+            .is_none_or(|age| modified_at_ms < age.modified_at_ms)
+        {
+            oldest = Some(FileAge {
+                path: file.to_string_lossy().to_string(),
+                modified_at_ms,
+            });
+        }
+        if newest
+            .as_ref()
+            .is_none_or(|age| modified_at_ms > age.modified_at_ms)
+        {
+            newest = Some(FileAge {
+                path: file.to_string_lossy().to_string(),
+                modified_at_ms,
+            });
+        }
+    });
+    ReceiveFolderStats {
+        free_bytes: volume_free_bytes(&path),
+        path: path.to_string_lossy().to_string(),
+        total_bytes,
+        file_count,
+        oldest,
+        newest,
+    }
+}
+
+#[tauri::command]
+pub(crate) async fn receive_folder_stats(
+    app: AppHandle,
+    path: String,
+) -> Result<ReceiveFolderStats, String> {
+    let dir = validate_output_dir(&app, &path)?;
+    if !dir.is_dir() {
+        return Err(format!("receive folder {} does not exist", dir.display()));
+    }
+    tauri::async_runtime::spawn_blocking(move || collect_stats(dir))
+        .await
+        .map_err(|err| format!("failed to join folder stats task: {err}"))
+}
+
+#[tauri::command]
+pub(crate) fn set_retention_policy(
+    app: AppHandle,
+    policy: Option<RetentionPolicy>,
+) -> Result<Option<RetentionPolicy>, String> {
+    let policy = match policy {
+        Some(policy) => {
+            if policy.max_age_days == 0 {
+                return Err("retention must keep files for at least 1 day".to_string());
+            }
+            let folder = validate_retention_folder(&app, &policy.folder)?;
+            Some(RetentionPolicy {
+                folder: folder.to_string_lossy().to_string(),
+                ..policy
+            })
+        }
+        None => None,
+    };
+    update_settings(&app, |settings| {
+        settings.retention = policy;
+        Ok(())
+    })
+    .map(|settings| settings.retention)
+}
+
+/// Receive folders a retention policy may cover: the default one and the
+/// running listener's.
+fn receive_folders(app: &AppHandle) -> Vec<PathBuf> {
+    let state = app.state::<AppState>();
+    let listening = state
+        .listen_output_dir
+        .lock()
+        .ok()
+        .and_then(|guard| guard.clone());
+    default_download_dir()
+        .into_iter()
+        .chain(listening)
+        .filter_map(|dir| fs::canonicalize(dir).ok())
+        .collect()
+}
+
+/// Retention deletes files, so it only ever covers a receive folder, and
+/// never the home folder or an approved folder as a whole.
+fn validate_retention_folder(app: &AppHandle, raw: &str) -> Result<PathBuf, String> {
+    let folder = validate_output_dir(app, raw)?;
+    let home = home_dir().and_then(|home| fs::canonicalize(home).ok());
+    if home.as_ref() == Some(&folder) || approved_dirs(app).contains(&folder) {
+        return Err(format!(
+            "retention cannot cover {} itself; choose the receive folder",
+            folder.display()
+        ));
+    }
+    if !receive_folders(app).contains(&folder) {
+        return Err(format!(
+            "retention only applies to the receive folder, not {}",
+            folder.display()
+        ));
+    }
+    Ok(folder)
+}
+
+/// Received files below `folder`, keyed by canonical path, with the time
+/// each was last received.
+fn received_files(app: &AppHandle, folder: &Path) -> HashMap<PathBuf, u64> {
+    let recorded = history::received_paths(app)
+        .unwrap_or_default()
+        .into_iter()
+        .chain(dedup::received_paths(app).unwrap_or_default());
+    recorded_under(folder, recorded)
+}
+
+fn recorded_under(
+    folder: &Path,
+    recorded: impl IntoIterator<Item = (PathBuf, u64)>,
+) -> HashMap<PathBuf, u64> {
+    let mut files = HashMap::new();
+    for (path, received_at_ms) in recorded {
+        let Ok(path) = fs::canonicalize(&path) else {
+            continue;
+        };
+        if path.starts_with(folder) && path != folder {
+            let latest = files.entry(path).or_insert(received_at_ms);
+            *latest = (*latest).max(received_at_ms);
+        }
+    }
+    files
+}
+
+/// Deletes expired received files and then folders left empty by that,
+/// never the receive folder itself. Returns the number of files and bytes
+/// removed.
+fn apply_retention(folder: &Path, files: HashMap<PathBuf, u64>, max_age: Duration) -> (usize, u64) {
+    let Some(cutoff) = SystemTime::now()
+        .checked_sub(max_age)
+        .and_then(|cutoff| cutoff.duration_since(UNIX_EPOCH).ok())
+        .map(|cutoff| cutoff.as_millis() as u64)
+    else {
+        return (0, 0);
+    };
+    let mut removed = 0;
+    let mut removed_bytes = 0;
+    let mut touched_dirs = Vec::new();
+    for (file, received_at_ms) in files {
+        if received_at_ms >= cutoff {
+            continue;
+        }
+        // Regular files only; a link is never followed out of the folder.
+        let Ok(metadata) = fs::symlink_metadata(&file) else {
+            continue;
+        };
+        if metadata.is_file() && fs::remove_file(&file).is_ok() {
+            removed += 1;
+            removed_bytes += metadata.len();
+            if let Some(parent) = file.parent() {
+                touched_dirs.push(parent.to_path_buf());
+            }
+        }
+    }
+    // Deepest folders first so nested empty folders collapse upwards.
+    touched_dirs.sort_by(|left, right| {
+        right
+            .components()
+            .count()
+            .cmp(&left.components().count())
+            .then_with(|| left.cmp(right))
+    });
+    touched_dirs.dedup();
+    for dir in touched_dirs {
+        let mut current = dir.as_path();
+        while current != folder && current.starts_with(folder) {
+            if fs::remove_dir(current).is_err() {
+                break;
+            }
+            let Some(parent) = current.parent() else {
+                break;
+            };
+            current = parent;
+        }
+    }
+    (removed, removed_bytes)
+}
+
+fn run_retention_pass(app: &AppHandle) {
+    let Ok(Some(policy)) = load_settings(app).map(|settings| settings.retention) else {
+        return;
+    };
+    // Re-validate every pass so a policy can never reach outside the
+    // receive folder, even if settings.json was edited by hand.
+    let Ok(folder) = validate_retention_folder(app, &policy.folder) else {
+        return;
+    };
+    if !folder.is_dir() {
+        return;
+    }
+    let files = received_files(app, &folder);
+    let (removed, removed_bytes) = apply_retention(&folder, files, DAY * policy.max_age_days);
+    if removed > 0 {
+        emit_system_log(
+            app,
+            format!(
+                "retention removed {removed} received file(s) ({removed_bytes} bytes) older than {} day(s) from {}",
+                policy.max_age_days,
+                folder.display()
+            ),
+        );
+    }
+}
+
+pub(crate) fn spawn_retention_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        run_retention_pass(&app);
+        thread::sleep(RETENTION_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "local-sent-retention-test-{}",
+                uuid::Uuid::new_v4().simple()
+            ));
+            fs::create_dir_all(&dir).unwrap();
+            Scratch(fs::canonicalize(dir).unwrap())
+        }
+
+        fn file(&self, relative: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn days_ago(days: u64) -> u64 {
+        (SystemTime::now() - DAY * days as u32)
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    #[test]
+    fn old_recorded_files_are_removed() {
+        let scratch = Scratch::new();
+        let from_history = scratch.file("nested/deeper/old.txt", "12345");
+        let from_dedup = scratch.file("old.bin", "123");
+        let files = recorded_under(
+            &scratch.0,
+            [(from_history.clone(), days_ago(10))]
+                .into_iter()
+                .chain([(from_dedup.clone(), days_ago(8))]),
+        );
+        assert_eq!(apply_retention(&scratch.0, files, DAY * 7), (2, 8));
+        assert!(!from_history.exists());
+        assert!(!from_dedup.exists());
+        // Folders emptied by retention go too, the receive folder stays.
+        assert!(!scratch.0.join("nested").exists());
+        assert!(scratch.0.is_dir());
+    }
+
+    #[test]
+    fn unrecorded_files_are_left_alone() {
+        let scratch = Scratch::new();
+        let recorded = scratch.file("sub/received.txt", "data");
+        let unrecorded = scratch.file("sub/mine.txt", "data");
+        let outside = Scratch::new();
+        let elsewhere = outside.file("received.txt", "data");
+        let files = recorded_under(
+            &scratch.0,
+            [
+                (recorded.clone(), days_ago(30)),
+                (elsewhere.clone(), days_ago(30)),
+            ],
+        );
+        assert_eq!(files.len(), 1);
+        assert_eq!(apply_retention(&scratch.0, files, DAY * 7), (1, 4));
+        assert!(!recorded.exists());
+        assert!(unrecorded.exists());
+        assert!(elsewhere.exists());
+    }
+
+    #[test]
+    fn files_inside_the_window_are_kept() {
+        let scratch = Scratch::new();
+        let recent = scratch.file("recent.txt", "data");
+        // Received again recently: the latest time counts.
+        let again = scratch.file("again.txt", "data");
+        let files = recorded_under(
+            &scratch.0,
+            [
+                (recent.clone(), days_ago(1)),
+                (again.clone(), days_ago(30)),
+                (again.clone(), days_ago(2)),
+            ],
+        );
+        assert_eq!(apply_retention(&scratch.0, files, DAY * 7), (0, 0));
+        assert!(recent.exists());
+        assert!(again.exists());
+    }
+}
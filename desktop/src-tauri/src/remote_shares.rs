@@ -0,0 +1,178 @@
+use crate::outbound_policy::Destination;
+use crate::process_pool::ChildKind;
+use crate::{
+    emit_system_log, kill_switch, paths, render_cli_error, run_cli_capture_async, AppState,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+/// The device to browse, as picked from discovery or entered by hand.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RemoteDevice {
+    host: Option<String>,
+    device: Option<String>,
+    port: u16,
+    pair_code: Option<String>,
+    tls: Option<bool>,
+    tls_insecure: Option<bool>,
+    tls_fingerprint: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+impl RemoteDevice {
+    pub(crate) fn host(&self) -> Option<&str> {
+        non_empty(&self.host)
+    }
+
+    pub(crate) fn device(&self) -> Option<&str> {
+        non_empty(&self.device)
+    }
+
+    pub(crate) fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The host, or the device name when discovery picks the host.
+    pub(crate) fn label(&self) -> String {
+        self.host()
+            .or(self.device())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// What the outbound policy checks before anything is sent to it.
+    pub(crate) fn destination(&self) -> Destination<'_> {
+        Destination {
+            host: self.host(),
+            port: self.port,
+            pair_code: non_empty(&self.pair_code),
+            tls: self.tls.unwrap_or(false),
+            tls_fingerprint: non_empty(&self.tls_fingerprint),
+            tls_tofu: false,
+            tls_known_hosts: None,
+        }
+    }
+
+    pub(crate) fn args(&self) -> Result<Vec<String>, String> {
+        if self.port == 0 {
+            return Err("port must be in 1-65535".to_string());
+        }
+        let mut args = vec!["--port".to_string(), self.port.to_string()];
+        match (self.host(), self.device()) {
+            (Some(host), _) => args.extend(["--host".to_string(), host.to_string()]),
+            (None, Some(device)) => args.extend(["--device".to_string(), device.to_string()]),
+            (None, None) => return Err("select a device or enter a host".to_string()),
+        }
+        if let Some(code) = non_empty(&self.pair_code) {
+            args.extend(["--pair-code".to_string(), code.to_string()]);
+        }
+        if self.tls.unwrap_or(false) {
+            args.push("--tls".to_string());
+            if self.tls_insecure.unwrap_or(false) {
+                args.push("--tls-insecure".to_string());
+            }
+            if let Some(fingerprint) = non_empty(&self.tls_fingerprint) {
+                args.extend(["--tls-fingerprint".to_string(), fingerprint.to_string()]);
+            }
+        }
+        Ok(args)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ShareEntry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    mtime_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PulledFile {
+    path: String,
+    bytes: u64,
+}
+
+/// Lists `path` (`share/sub/folder`) on `device`, or its shared folders when
+/// `path` is empty. Devices only answer if their listener shares folders.
+#[tauri::command]
+pub(crate) async fn list_remote_shares(
+    app: AppHandle,
+    device: RemoteDevice,
+    path: Option<String>,
+) -> Result<Vec<ShareEntry>, String> {
+    kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+    let mut args = vec![
+        "shares".to_string(),
+        path.unwrap_or_default().trim().to_string(),
+    ];
+    args.extend(device.args()?);
+    args.push("--json".to_string());
+
+    let output = run_cli_capture_async(app, args, ChildKind::Discover).await?;
+    if !output.success {
+        return Err(render_cli_error("shares", &output));
+    }
+    serde_json::from_str(output.stdout.trim())
+        .map_err(|err| format!("failed to parse shares JSON: {err}"))
+}
+
+/// Downloads `remote_path` from `device` into the folder `dest`. The file
+/// keeps its remote name; an existing file is never overwritten.
+#[tauri::command]
+pub(crate) async fn pull_file(
+    app: AppHandle,
+    device: RemoteDevice,
+    remote_path: String,
+    dest: String,
+) -> Result<PulledFile, String> {
+    kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+    let remote_path = remote_path.trim().trim_matches('/').to_string();
+    let file_name = remote_path
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+        .ok_or_else(|| "remote path must name a file".to_string())?;
+    let dest_dir = paths::validate_output_dir(&app, &dest)?;
+    let target = if cfg!(windows) {
+        dest_dir.join(paths::windows_safe_name(file_name))
+    } else {
+        dest_dir.join(file_name)
+    };
+    if target.exists() {
+        return Err(format!("{} already exists", target.display()));
+    }
+
+    let mut args = vec![
+        "pull".to_string(),
+        remote_path.clone(),
+        "-o".to_string(),
+        target.to_string_lossy().to_string(),
+    ];
+    args.extend(device.args()?);
+
+    let output = run_cli_capture_async(app.clone(), args, ChildKind::Send).await?;
+    if !output.success {
+        return Err(render_cli_error("pull", &output));
+    }
+    let bytes = std::fs::metadata(&target)
+        .map(|metadata| metadata.len())
+        .map_err(|err| format!("failed to read pulled file {}: {err}", target.display()))?;
+    emit_system_log(
+        &app,
+        format!("pulled {remote_path} into {}", target.display()),
+    );
+    Ok(PulledFile {
+        path: target.to_string_lossy().to_string(),
+        bytes,
+    })
+}
@@ -0,0 +1,619 @@
+//! Delivery to S3-compatible object stores such as MinIO or Backblaze B2.
+//! Requests are signed with AWS signature version 4 and leave the payload
+//! unsigned, so files stream from disk without being read twice. The
+//! secret key lives in the OS keychain, not in the targets file.
+
+use crate::delivery::SourceFile;
+use crate::secrets::{get_secret, store_secret};
+use crate::zero_copy::{format_bytes, Output};
+use crate::{scheduler, AppState, CommandResult};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Url};
+use ureq::http::Response;
+use ureq::{Agent, Body, SendBody};
+
+const DEFAULT_REGION: &str = "us-east-1";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+const SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+/// Files up to this size go up in one request; larger ones in parts of at
+/// least this size.
+const PART_BYTES: u64 = 64 * 1024 * 1024;
+/// The most parts S3 accepts for one object.
+const MAX_PARTS: u64 = 10_000;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Error bodies are short XML documents.
+const MAX_ERROR_BODY_BYTES: u64 = 64 * 1024;
+
+fn default_region() -> String {
+    DEFAULT_REGION.to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct S3Target {
+    /// e.g. `http://minio.lan:9000` or `https://s3.us-west-004.backblazeb2.com`.
+    endpoint: String,
+    #[serde(default = "default_region")]
+    region: String,
+    bucket: String,
+    /// Prepended to each object key, e.g. `inbox/`.
+    #[serde(default)]
+    prefix: String,
+    access_key_id: String,
+    /// Moved to the keychain on save; `None` keeps the stored key.
+    #[serde(default, skip_serializing)]
+    secret_access_key: Option<String>,
+    /// Address the bucket as `bucket.endpoint` rather than
+    /// `endpoint/bucket`; MinIO expects the latter.
+    #[serde(default)]
+    virtual_hosted: bool,
+}
+
+fn secret_key(target_name: &str) -> String {
+    format!("s3-secret:{}", target_name.trim().to_lowercase())
+}
+
+/// Dots are only allowed with path-style addressing: as `bucket.endpoint`
+/// they add labels the endpoint's wildcard certificate does not cover.
+fn valid_bucket(bucket: &str, virtual_hosted: bool) -> bool {
+    (3..=63).contains(&bucket.len())
+        && bucket
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        && !(virtual_hosted && bucket.contains('.'))
+}
+
+impl S3Target {
+    pub(crate) fn label(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.prefix)
+    }
+
+    /// Cleans the fields and stores a new secret key for `target_name`.
+    pub(crate) fn validated(self, target_name: &str) -> Result<Self, String> {
+        let endpoint = self.endpoint.trim().trim_end_matches('/').to_string();
+        let url = Url::parse(&endpoint).map_err(|err| format!("endpoint is invalid: {err}"))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err("endpoint must be an http:// or https:// URL".to_string());
+        }
+        if url.path() != "/" || url.query().is_some() {
+            return Err("endpoint must not have a path".to_string());
+        }
+        let bucket = self.bucket.trim().to_string();
+        if !valid_bucket(&bucket, self.virtual_hosted) {
+            return Err(if self.virtual_hosted && bucket.contains('.') {
+                "bucket names with dots need path-style addressing".to_string()
+            } else {
+                "bucket name is invalid".to_string()
+            });
+        }
+        let access_key_id = self.access_key_id.trim().to_string();
+        if access_key_id.is_empty() {
+            return Err("access key id is required".to_string());
+        }
+        let prefix = self.prefix.trim().trim_matches('/').to_string();
+        match self
+            .secret_access_key
+            .as_deref()
+            .map(str::trim)
+            .filter(|secret| !secret.is_empty())
+        {
+            Some(secret) => store_secret(&secret_key(target_name), secret)?,
+            None if get_secret(&secret_key(target_name))?.is_none() => {
+                return Err("secret access key is required".to_string());
+            }
+            None => {}
+        }
+        Ok(Self {
+            endpoint,
+            region: match self.region.trim() {
+                "" => default_region(),
+                region => region.to_string(),
+            },
+            bucket,
+            prefix: if prefix.is_empty() {
+                prefix
+            } else {
+                format!("{prefix}/")
+            },
+            access_key_id,
+            secret_access_key: None,
+            virtual_hosted: self.virtual_hosted,
+        })
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> [u8; 32] {
+    const BLOCK_BYTES: usize = 64;
+    let mut block = [0u8; BLOCK_BYTES];
+    if key.len() > BLOCK_BYTES {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(data);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Percent-encodes all but the characters SigV4 leaves alone.
+fn uri_encode(value: &str, keep_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+fn canonical_request(
+    method: &str,
+    path: &str,
+    query: &str,
+    host: &str,
+    payload_hash: &str,
+    amz_date: &str,
+) -> String {
+    format!(
+        "{method}\n{path}\n{query}\nhost:{host}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\n{SIGNED_HEADERS}\n{payload_hash}"
+    )
+}
+
+fn string_to_sign(amz_date: &str, scope: &str, canonical: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical.as_bytes()))
+    )
+}
+
+/// Signs with the key derived for `date` (`YYYYMMDD`) and `region`.
+fn signature(secret: &str, date: &str, region: &str, string_to_sign: &str) -> String {
+    let mut signing_key = hmac(format!("AWS4{secret}").as_bytes(), date.as_bytes());
+    for part in [region, "s3", "aws4_request"] {
+        signing_key = hmac(&signing_key, part.as_bytes());
+    }
+    hex(&hmac(&signing_key, string_to_sign.as_bytes()))
+}
+
+struct Signed {
+    url: String,
+    headers: Vec<(&'static str, String)>,
+}
+
+struct Client<'a> {
+    target: &'a S3Target,
+    secret: String,
+    agent: Agent,
+}
+
+impl<'a> Client<'a> {
+    fn new(target: &'a S3Target, target_name: &str) -> Result<Self, String> {
+        let secret = get_secret(&secret_key(target_name))?
+            .ok_or_else(|| "secret access key is missing from the keychain".to_string())?;
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .timeout_connect(Some(CONNECT_TIMEOUT))
+            .build()
+            .new_agent();
+        Ok(Self {
+            target,
+            secret,
+            agent,
+        })
+    }
+
+    fn sign(&self, method: &str, key: &str, query: &[(&str, &str)]) -> Result<Signed, String> {
+        let endpoint = Url::parse(&self.target.endpoint)
+            .map_err(|err| format!("endpoint is invalid: {err}"))?;
+        let mut host = endpoint.host_str().unwrap_or_default().to_string();
+        if let Some(port) = endpoint.port() {
+            host = format!("{host}:{port}");
+        }
+        let path = if self.target.virtual_hosted {
+            host = format!("{}.{host}", self.target.bucket);
+            format!("/{}", uri_encode(key, true))
+        } else {
+            format!("/{}/{}", self.target.bucket, uri_encode(key, true))
+        };
+        let mut query: Vec<String> = query
+            .iter()
+            .map(|(name, value)| {
+                format!("{}={}", uri_encode(name, false), uri_encode(value, false))
+            })
+            .collect();
+        query.sort();
+        let query = query.join("&");
+
+        let now = chrono::Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{date}/{}/s3/aws4_request", self.target.region);
+        let canonical =
+            canonical_request(method, &path, &query, &host, UNSIGNED_PAYLOAD, &amz_date);
+        let string_to_sign = string_to_sign(&amz_date, &scope, &canonical);
+        let signature = signature(&self.secret, &date, &self.target.region, &string_to_sign);
+
+        let mut url = format!("{}://{host}{path}", endpoint.scheme());
+        if !query.is_empty() {
+            url = format!("{url}?{query}");
+        }
+        Ok(Signed {
+            url,
+            headers: vec![
+                ("host", host),
+                ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+                ("x-amz-date", amz_date),
+                (
+                    "authorization",
+                    format!(
+                        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={SIGNED_HEADERS}, Signature={signature}",
+                        self.target.access_key_id
+                    ),
+                ),
+            ],
+        })
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        key: &str,
+        query: &[(&str, &str)],
+        body: Option<(&mut dyn Read, u64)>,
+    ) -> Result<Response<Body>, String> {
+        let signed = self.sign(method, key, query)?;
+        let mut request = ureq::http::Request::builder()
+            .method(method)
+            .uri(&signed.url);
+        for (name, value) in &signed.headers {
+            request = request.header(*name, value);
+        }
+        let mut empty = io::empty();
+        let (body, len) = body.unwrap_or((&mut empty, 0));
+        let request = request
+            .header("content-length", len)
+            .body(SendBody::from_reader(body))
+            .map_err(|err| format!("failed to build S3 request: {err}"))?;
+        let mut response = self
+            .agent
+            .run(request)
+            .map_err(|err| format!("S3 request failed: {err}"))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let text = response
+            .body_mut()
+            .with_config()
+            .limit(MAX_ERROR_BODY_BYTES)
+            .read_to_string()
+            .unwrap_or_default();
+        Err(format!(
+            "S3 answered HTTP {}{}",
+            response.status().as_u16(),
+            xml_value(&text, "Message")
+                .or_else(|| xml_value(&text, "Code"))
+                .map(|message| format!(": {message}"))
+                .unwrap_or_default()
+        ))
+    }
+}
+
+fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{tag}>"))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{tag}>"))?;
+    Some(xml[start..end].trim().to_string())
+}
+
+/// Reports progress as the request body is read, and stops the upload
+/// while paused or once the kill-switch engages.
+struct ProgressReader<'a, 'o, R> {
+    inner: R,
+    app: &'a AppHandle,
+    output: &'a mut Output<'o>,
+    file: &'a SourceFile,
+    send_id: u64,
+    sent: &'a mut u64,
+    started: Instant,
+    last_progress: Instant,
+}
+
+impl<R: Read> Read for ProgressReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        scheduler::wait_while_paused(self.app, self.send_id);
+        if self
+            .app
+            .state::<AppState>()
+            .network_halted
+            .load(Ordering::SeqCst)
+        {
+            return Err(io::Error::other("send aborted by the kill-switch"));
+        }
+        let read = self.inner.read(buf)?;
+        *self.sent += read as u64;
+        if self.last_progress.elapsed() >= PROGRESS_INTERVAL {
+            self.last_progress = Instant::now();
+            self.output.progress(
+                &self.file.relative_path,
+                *self.sent,
+                self.file.size,
+                self.started,
+            );
+        }
+        Ok(read)
+    }
+}
+
+/// One file on its way up, with what its progress reports need.
+struct Upload<'a, 'o> {
+    app: &'a AppHandle,
+    output: &'a mut Output<'o>,
+    file: &'a SourceFile,
+    source: File,
+    send_id: u64,
+    sent: u64,
+    started: Instant,
+}
+
+/// Sends the next `len` bytes of the file as a request body.
+fn send_from_file(
+    client: &Client,
+    upload: &mut Upload,
+    len: u64,
+    request: (&str, &str, &[(&str, &str)]),
+) -> Result<Response<Body>, String> {
+    let before = upload.sent;
+    let mut body = ProgressReader {
+        inner: Read::take(&mut upload.source, len),
+        app: upload.app,
+        output: &mut *upload.output,
+        file: upload.file,
+        send_id: upload.send_id,
+        sent: &mut upload.sent,
+        started: upload.started,
+        last_progress: Instant::now(),
+    };
+    let (method, key, query) = request;
+    let response = client.send(method, key, query, Some((&mut body, len)))?;
+    if upload.sent - before != len {
+        return Err("file changed while uploading".to_string());
+    }
+    Ok(response)
+}
+
+/// A multipart upload, aborted on failure so the store drops the parts.
+fn upload_parts(client: &Client, upload: &mut Upload, key: &str) -> Result<(), String> {
+    let size = upload.file.size;
+    let part_bytes = PART_BYTES.max(size.div_ceil(MAX_PARTS));
+    let created = client
+        .send("POST", key, &[("uploads", "")], None)?
+        .body_mut()
+        .read_to_string()
+        .map_err(|err| format!("failed to read S3 response: {err}"))?;
+    let upload_id = xml_value(&created, "UploadId")
+        .ok_or_else(|| "S3 did not start a multipart upload".to_string())?;
+    let result = (|| {
+        let mut parts = String::new();
+        let mut number = 1u64;
+        while upload.sent < size {
+            let len = part_bytes.min(size - upload.sent);
+            let part_number = number.to_string();
+            let query = [
+                ("partNumber", part_number.as_str()),
+                ("uploadId", upload_id.as_str()),
+            ];
+            let response = send_from_file(client, upload, len, ("PUT", key, &query))?;
+            let etag = response
+                .headers()
+                .get("etag")
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| "S3 returned no ETag for a part".to_string())?;
+            parts.push_str(&format!(
+                "<Part><PartNumber>{number}</PartNumber><ETag>{etag}</ETag></Part>"
+            ));
+            number += 1;
+        }
+        let xml = format!("<CompleteMultipartUpload>{parts}</CompleteMultipartUpload>");
+        let completed = client
+            .send(
+                "POST",
+                key,
+                &[("uploadId", &upload_id)],
+                Some((&mut xml.as_bytes(), xml.len() as u64)),
+            )?
+            .body_mut()
+            .read_to_string()
+            .unwrap_or_default();
+        // Completing can fail after the status line already said 200.
+        if completed.contains("<Error>") {
+            return Err(format!(
+                "S3 could not complete the upload: {}",
+                xml_value(&completed, "Message").unwrap_or_default()
+            ));
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = client.send("DELETE", key, &[("uploadId", &upload_id)], None);
+    }
+    result
+}
+
+fn upload(
+    app: &AppHandle,
+    client: &Client,
+    output: &mut Output,
+    file: &SourceFile,
+    send_id: u64,
+) -> Result<(), String> {
+    let key = format!("{}{}", client.target.prefix, file.relative_path);
+    let source = File::open(&file.path).map_err(|err| format!("failed to open file: {err}"))?;
+    let mut upload = Upload {
+        app,
+        output,
+        file,
+        source,
+        send_id,
+        sent: 0,
+        started: Instant::now(),
+    };
+    if file.size <= PART_BYTES {
+        send_from_file(client, &mut upload, file.size, ("PUT", &key, &[]))?;
+    } else {
+        upload_parts(client, &mut upload, &key)?;
+    }
+    upload
+        .output
+        .progress(&file.relative_path, upload.sent, file.size, upload.started);
+    upload.output.write("\n".to_string());
+    Ok(())
+}
+
+/// Puts `files` under the target's prefix, replacing objects with the same
+/// key. `target_name` finds the secret key in the keychain.
+pub(crate) fn send(
+    app: &AppHandle,
+    target: &S3Target,
+    target_name: &str,
+    files: &[SourceFile],
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let client = Client::new(target, target_name)?;
+    let mut output = Output::new(app, send_id);
+    let started = Instant::now();
+    for (index, file) in files.iter().enumerate() {
+        output.start_file(index, files);
+        upload(app, &client, &mut output, file, send_id)
+            .map_err(|err| format!("failed to upload {}: {err}", file.relative_path))?;
+    }
+
+    let bytes: u64 = files.iter().map(|file| file.size).sum();
+    let seconds = started.elapsed().as_secs_f64().max(0.001);
+    output.write(format!(
+        "[send] done: files={} bytes={}\n[send] s3: {} in {seconds:.2}s ({}/s)\n",
+        files.len(),
+        format_bytes(bytes as f64),
+        target.label(),
+        format_bytes(bytes as f64 / seconds)
+    ));
+    Ok(output.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        let large_key = [0xaa; 131];
+        let cases: [(&[u8], &[u8], &str); 6] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &[0xaa; 20],
+                &[0xdd; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                &unhex("0102030405060708090a0b0c0d0e0f10111213141516171819"),
+                &[0xcd; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            (
+                &large_key,
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                &large_key,
+                b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+        for (key, data, expected) in cases {
+            assert_eq!(hex(&hmac(key, data)), expected);
+        }
+    }
+
+    /// The "GET Bucket Lifecycle" example from the AWS SigV4 documentation.
+    #[test]
+    fn signing_matches_the_aws_example() {
+        let empty_hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let canonical = canonical_request(
+            "GET",
+            "/",
+            "lifecycle=",
+            "examplebucket.s3.amazonaws.com",
+            empty_hash,
+            "20130524T000000Z",
+        );
+        assert_eq!(
+            canonical,
+            format!(
+                "GET\n/\nlifecycle=\nhost:examplebucket.s3.amazonaws.com\n\
+                 x-amz-content-sha256:{empty_hash}\nx-amz-date:20130524T000000Z\n\n\
+                 host;x-amz-content-sha256;x-amz-date\n{empty_hash}"
+            )
+        );
+        let string_to_sign = string_to_sign(
+            "20130524T000000Z",
+            "20130524/us-east-1/s3/aws4_request",
+            &canonical,
+        );
+        assert_eq!(
+            string_to_sign,
+            "AWS4-HMAC-SHA256\n20130524T000000Z\n20130524/us-east-1/s3/aws4_request\n\
+             9766c798316ff2757b517bc739a67f6213b4ab36dd5da2f94eaebf79c77395ca"
+        );
+        assert_eq!(
+            signature(
+                "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY",
+                "20130524",
+                "us-east-1",
+                &string_to_sign,
+            ),
+            "fea454ca298b7da1c68078a5d1bdbfbbe0d65c699e0f91ac7a200a0136783543"
+        );
+    }
+
+    #[test]
+    fn dotted_buckets_need_path_style() {
+        assert!(valid_bucket("my.bucket", false));
+        assert!(!valid_bucket("my.bucket", true));
+        assert!(valid_bucket("my-bucket", true));
+        assert!(!valid_bucket("My_Bucket", false));
+        assert!(!valid_bucket("ab", false));
+    }
+}
@@ -0,0 +1,240 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::{emit_system_log, AppState};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum JobPriority {
+    /// Bulk transfers that step aside while a high-priority send runs.
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug)]
+struct Job {
+    priority: JobPriority,
+    /// CLI child running the send; zero-copy sends run in-process and poll
+    /// `is_paused` instead.
+    pid: Option<u32>,
+    paused: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Scheduler {
+    jobs: HashMap<u64, Job>,
+    /// Low-priority jobs wait while the battery policy defers them.
+    battery_deferred: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PauseReason {
+    HighPriority,
+    Battery,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JobStatePayload {
+    send_id: u64,
+    priority: JobPriority,
+    paused: bool,
+    /// Why the job is paused; `None` while it runs.
+    reason: Option<PauseReason>,
+}
+
+#[cfg(unix)]
+fn signal_process_tree(pid: u32, pause: bool) {
+    let signal = if pause { libc::SIGSTOP } else { libc::SIGCONT };
+    let mut pids = crate::collect_unix_process_descendants(pid);
+    pids.push(pid);
+    for target in pids {
+        // SAFETY: `kill` only sends a signal; a stale pid fails with ESRCH.
+        unsafe {
+            libc::kill(target as libc::pid_t, signal);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_process_tree(_pid: u32, _pause: bool) {}
+
+/// Pauses low-priority jobs while any high-priority job is running or the
+/// battery policy defers them, and resumes them once neither holds. Returns
+/// the jobs whose state changed.
+fn rebalance(scheduler: &mut Scheduler) -> Vec<JobStatePayload> {
+    let high_running = scheduler
+        .jobs
+        .values()
+        .any(|job| job.priority == JobPriority::High);
+    let reason = if high_running {
+        Some(PauseReason::HighPriority)
+    } else if scheduler.battery_deferred {
+        Some(PauseReason::Battery)
+    } else {
+        None
+    };
+    let mut changed = Vec::new();
+    for (send_id, job) in &mut scheduler.jobs {
+        let paused = reason.is_some() && job.priority == JobPriority::Low;
+        if paused == job.paused {
+            continue;
+        }
+        if let Some(pid) = job.pid {
+            signal_process_tree(pid, paused);
+        }
+        job.paused = paused;
+        changed.push(JobStatePayload {
+            send_id: *send_id,
+            priority: job.priority,
+            paused,
+            reason: reason.filter(|_| paused),
+        });
+    }
+    changed
+}
+
+fn update(app: &AppHandle, change: impl FnOnce(&mut Scheduler)) {
+    let state = app.state::<AppState>();
+    let changed = {
+        let Ok(mut scheduler) = state.scheduler.lock() else {
+            return;
+        };
+        change(&mut scheduler);
+        rebalance(&mut scheduler)
+    };
+    for job in changed {
+        emit_system_log(
+            app,
+            match job.reason {
+                Some(PauseReason::HighPriority) => format!(
+                    "paused low-priority send #{} for a high-priority transfer",
+                    job.send_id
+                ),
+                Some(PauseReason::Battery) => {
+                    format!("paused low-priority send #{} on low battery", job.send_id)
+                }
+                None => format!("resumed send #{}", job.send_id),
+            },
+        );
+        emit_app_event(app, AppEvent::JobState(job));
+    }
+}
+
+pub(crate) fn enqueue(app: &AppHandle, send_id: u64, priority: JobPriority) {
+    if cfg!(not(unix)) && priority != JobPriority::Normal {
+        emit_system_log(
+            app,
+            "send priority is recorded but cannot pause transfers on this platform",
+        );
+    }
+    update(app, |scheduler| {
+        scheduler.jobs.insert(
+            send_id,
+            Job {
+                priority,
+                pid: None,
+                paused: false,
+            },
+        );
+    });
+}
+
+/// Ties a spawned CLI child to its job; a job that should already be paused
+/// is stopped right away.
+pub(crate) fn attach_pid(app: &AppHandle, send_id: u64, pid: u32) {
+    let state = app.state::<AppState>();
+    let Ok(mut scheduler) = state.scheduler.lock() else {
+        return;
+    };
+    if let Some(job) = scheduler.jobs.get_mut(&send_id) {
+        job.pid = Some(pid);
+        if job.paused {
+            signal_process_tree(pid, true);
+        }
+    }
+}
+
+pub(crate) fn finish(app: &AppHandle, send_id: u64) {
+    update(app, |scheduler| {
+        if let Some(job) = scheduler.jobs.remove(&send_id) {
+            // Never leave a stopped process behind, e.g. when it is killed.
+            if job.paused {
+                if let Some(pid) = job.pid {
+                    signal_process_tree(pid, false);
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn set_battery_deferred(app: &AppHandle, deferred: bool) {
+    update(app, |scheduler| scheduler.battery_deferred = deferred);
+}
+
+pub(crate) fn is_paused(app: &AppHandle, send_id: u64) -> bool {
+    app.state::<AppState>()
+        .scheduler
+        .lock()
+        .is_ok_and(|scheduler| scheduler.jobs.get(&send_id).is_some_and(|job| job.paused))
+}
+
+pub(crate) fn paused_sends(app: &AppHandle) -> HashSet<u64> {
+    app.state::<AppState>()
+        .scheduler
+        .lock()
+        .map(|scheduler| {
+            scheduler
+                .jobs
+                .iter()
+                .filter(|(_, job)| job.paused)
+                .map(|(send_id, _)| *send_id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Blocks an in-process send while its job is paused. Returns early when the
+/// kill-switch engages so the caller can abort.
+pub(crate) fn wait_while_paused(app: &AppHandle, send_id: u64) {
+    let state = app.state::<AppState>();
+    while is_paused(app, send_id) && !state.network_halted.load(Ordering::SeqCst) {
+        thread::sleep(PAUSE_POLL_INTERVAL);
+    }
+}
+
+/// Changes the priority of a running send. Raising a job to high
+/// pauses low-priority bulk sends until it finishes; lowering one may pause
+/// it behind an already running high-priority send.
+#[tauri::command]
+pub(crate) fn set_job_priority(
+    app: AppHandle,
+    state: State<AppState>,
+    id: u64,
+    priority: JobPriority,
+) -> Result<JobPriority, String> {
+    let known = state
+        .scheduler
+        .lock()
+        .map_err(|_| "failed to lock scheduler".to_string())?
+        .jobs
+        .contains_key(&id);
+    if !known {
+        return Err(format!("send #{id} is not running"));
+    }
+    update(&app, |scheduler| {
+        if let Some(job) = scheduler.jobs.get_mut(&id) {
+            job.priority = priority;
+        }
+    });
+    Ok(priority)
+}
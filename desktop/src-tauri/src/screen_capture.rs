@@ -0,0 +1,171 @@
+use crate::{emit_system_log, run_send_request, CommandResult, SendRequest};
+use serde::Deserialize;
+use std::fs;
+#[cfg(not(target_os = "windows"))]
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tauri::AppHandle;
+
+const CAPTURE_DIR: &str = "local-sent-captures";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CaptureMode {
+    /// The user drags out an area.
+    Region,
+    /// The user picks a window, or the focused one where the tool cannot ask.
+    Window,
+    /// Every screen.
+    Screen,
+}
+
+/// Runs one screenshot tool. `Ok(false)` means it is not installed.
+#[cfg(not(target_os = "windows"))]
+fn run_tool(program: &str, args: &[&str], path: &Path) -> Result<bool, String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    match command.status() {
+        Ok(status) if status.success() => Ok(true),
+        // Interactive tools exit non-zero when the selection is cancelled.
+        Ok(_) => Err("screenshot was cancelled".to_string()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(format!("failed to run {program}: {err}")),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn capture(mode: CaptureMode, path: &Path) -> Result<(), String> {
+    let args: &[&str] = match mode {
+        CaptureMode::Region => &["-x", "-s"],
+        CaptureMode::Window => &["-x", "-w"],
+        CaptureMode::Screen => &["-x"],
+    };
+    if !run_tool("screencapture", args, path)? {
+        return Err("screencapture was not found".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn capture(mode: CaptureMode, path: &Path) -> Result<(), String> {
+    if mode != CaptureMode::Screen {
+        return Err("only full-screen capture is supported on Windows".to_string());
+    }
+    // The path goes through the environment to avoid quoting it in a script.
+    const SCRIPT: &str = "Add-Type -AssemblyName System.Windows.Forms,System.Drawing; \
+        $b = [System.Windows.Forms.SystemInformation]::VirtualScreen; \
+        $bmp = New-Object System.Drawing.Bitmap $b.Width, $b.Height; \
+        $g = [System.Drawing.Graphics]::FromImage($bmp); \
+        $g.CopyFromScreen($b.Left, $b.Top, 0, 0, $bmp.Size); \
+        $bmp.Save($env:LOCAL_SENT_CAPTURE, [System.Drawing.Imaging.ImageFormat]::Png)";
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT])
+        .env("LOCAL_SENT_CAPTURE", path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    crate::configure_cli_command_for_platform(&mut command);
+    let status = command
+        .status()
+        .map_err(|err| format!("failed to run powershell: {err}"))?;
+    if !status.success() {
+        return Err("failed to capture the screen".to_string());
+    }
+    Ok(())
+}
+
+/// Tries the common tools in turn: GNOME and KDE first, then the wlroots
+/// (grim) and X11 (maim, scrot) ones.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn capture(mode: CaptureMode, path: &Path) -> Result<(), String> {
+    let gnome: &[&str] = match mode {
+        CaptureMode::Region => &["-a", "-f"],
+        CaptureMode::Window => &["-w", "-f"],
+        CaptureMode::Screen => &["-f"],
+    };
+    if run_tool("gnome-screenshot", gnome, path)? {
+        return Ok(());
+    }
+    let spectacle: &[&str] = match mode {
+        CaptureMode::Region => &["-b", "-n", "-r", "-o"],
+        CaptureMode::Window => &["-b", "-n", "-a", "-o"],
+        CaptureMode::Screen => &["-b", "-n", "-f", "-o"],
+    };
+    if run_tool("spectacle", spectacle, path)? {
+        return Ok(());
+    }
+    match mode {
+        CaptureMode::Region => {
+            if let Some(geometry) = crate::network::command_stdout("slurp", &[]) {
+                if run_tool("grim", &["-g", geometry.trim()], path)? {
+                    return Ok(());
+                }
+            }
+        }
+        CaptureMode::Screen => {
+            if run_tool("grim", &[], path)? {
+                return Ok(());
+            }
+        }
+        CaptureMode::Window => {}
+    }
+    let maim: &[&str] = match mode {
+        CaptureMode::Region | CaptureMode::Window => &["-s"],
+        CaptureMode::Screen => &[],
+    };
+    if run_tool("maim", maim, path)? {
+        return Ok(());
+    }
+    let scrot: &[&str] = match mode {
+        CaptureMode::Region => &["-s"],
+        CaptureMode::Window => &["-u"],
+        CaptureMode::Screen => &[],
+    };
+    if run_tool("scrot", scrot, path)? {
+        return Ok(());
+    }
+    Err(
+        "no screenshot tool found; install gnome-screenshot, spectacle, grim, maim or scrot"
+            .to_string(),
+    )
+}
+
+fn capture_path() -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(CAPTURE_DIR);
+    fs::create_dir_all(&dir).map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    Ok(dir.join(format!("screenshot-{stamp}.png")))
+}
+
+/// Takes a screenshot and sends it at once with `request`, whose `path` is
+/// ignored. Meant for a hotkey: region and window captures let the user
+/// pick before anything is sent. The temporary PNG is deleted afterwards.
+#[tauri::command]
+pub(crate) async fn capture_and_send(
+    app: AppHandle,
+    mode: CaptureMode,
+    mut request: SendRequest,
+) -> Result<CommandResult, String> {
+    let path = capture_path()?;
+    let captured = path.clone();
+    tauri::async_runtime::spawn_blocking(move || capture(mode, &captured))
+        .await
+        .map_err(|err| format!("failed to join capture task: {err}"))??;
+    if !fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0) {
+        let _ = fs::remove_file(&path);
+        return Err("screenshot was cancelled".to_string());
+    }
+    emit_system_log(&app, format!("captured {}, sending", path.display()));
+
+    request.path = path.to_string_lossy().to_string();
+    let result = run_send_request(app, request).await;
+    let _ = fs::remove_file(&path);
+    result
+}
@@ -0,0 +1,148 @@
+use crate::app_profiles::active_profile_dir;
+use crate::storage::{data_file, read_json, write_json};
+use crate::{AppState, SendRequest};
+use keyring::Entry;
+use serde_json::Value;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+const KEYRING_SERVICE: &str = "local_sent";
+
+fn keyring_entry(key: &str) -> Result<Entry, String> {
+    Entry::new(KEYRING_SERVICE, key).map_err(|err| format!("failed to open keychain entry: {err}"))
+}
+
+pub(crate) fn store_secret(key: &str, value: &str) -> Result<(), String> {
+    keyring_entry(key)?
+        .set_password(value)
+        .map_err(|err| format!("failed to store secret in the OS keychain: {err}"))
+}
+
+pub(crate) fn get_secret(key: &str) -> Result<Option<String>, String> {
+    match keyring_entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(format!("failed to read secret from the OS keychain: {err}")),
+    }
+}
+
+/// Pair codes are remembered per target, so history entries and presets that
+/// send to the same receiver share one keychain item. This is the key
+/// without the profile, as it appears in data exports.
+pub(crate) fn pair_code_target_key(request: &SendRequest) -> String {
+    let target = request
+        .host
+        .as_deref()
+        .or(request.device.as_deref())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("auto");
+    format!("pair-code:{target}")
+}
+
+/// Scopes a keychain `key` to the active profile, so "Home" and "Office"
+/// each keep their own code for the same address. The default profile
+/// keeps the bare key, which is where codes were stored before profiles.
+pub(crate) fn profile_key(app: &AppHandle, key: &str) -> String {
+    match active_profile_dir(app.state::<AppState>().inner()) {
+        Some(profile_dir) => format!("{profile_dir}/{key}"),
+        None => key.to_string(),
+    }
+}
+
+pub(crate) fn pair_code_key(app: &AppHandle, request: &SendRequest) -> String {
+    profile_key(app, &pair_code_target_key(request))
+}
+
+/// Moves the pair code of a request that is about to be written to disk into
+/// the keychain. On failure the request is left untouched so the caller can
+/// decide between dropping the code and aborting.
+pub(crate) fn stash_pair_code(app: &AppHandle, request: &mut SendRequest) -> Result<(), String> {
+    let Some(code) = request
+        .pair_code
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    else {
+        request.pair_code = None;
+        return Ok(());
+    };
+    store_secret(&pair_code_key(app, request), code)?;
+    request.pair_code = None;
+    Ok(())
+}
+
+/// Fills in the remembered pair code for a persisted request that has none.
+pub(crate) fn restore_pair_code(app: &AppHandle, request: &mut SendRequest) {
+    if request
+        .pair_code
+        .as_ref()
+        .is_some_and(|value| !value.trim().is_empty())
+    {
+        return;
+    }
+    if let Ok(Some(code)) = get_secret(&pair_code_key(app, request)) {
+        request.pair_code = Some(code);
+    }
+}
+
+/// One-time move of pair codes that older versions stored in plain JSON.
+/// A file is only rewritten once every code in it reached the keychain.
+pub(crate) fn migrate_plaintext_secrets(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    {
+        let _guard = state
+            .presets_lock
+            .lock()
+            .map_err(|_| "failed to lock send presets".to_string())?;
+        let path = data_file(app, crate::presets::SEND_PRESETS_FILE)?;
+        migrate_json_file(app, &path)?;
+    }
+    {
+        let _guard = state
+            .history_lock
+            .lock()
+            .map_err(|_| "failed to lock send history".to_string())?;
+        let path = data_file(app, crate::history::SEND_HISTORY_FILE)?;
+        migrate_json_file(app, &path)?;
+    }
+    Ok(())
+}
+
+/// Works on raw JSON so the migration does not depend on the current shape
+/// of the preset or history structs beyond the embedded send request.
+fn migrate_json_file(app: &AppHandle, path: &Path) -> Result<(), String> {
+    let mut document: Value = read_json(path)?;
+    let items = match &mut document {
+        Value::Array(items) => items,
+        Value::Object(object) => match object.get_mut("entries") {
+            Some(Value::Array(items)) => items,
+            _ => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    let mut changed = false;
+    for item in items.iter_mut() {
+        let Some(raw_request) = item.get_mut("request") else {
+            continue;
+        };
+        let has_code = raw_request
+            .get("pairCode")
+            .and_then(Value::as_str)
+            .is_some_and(|value| !value.trim().is_empty());
+        if !has_code {
+            continue;
+        }
+        let Ok(mut request) = serde_json::from_value::<SendRequest>(raw_request.clone()) else {
+            continue;
+        };
+        stash_pair_code(app, &mut request)?;
+        raw_request["pairCode"] = Value::Null;
+        changed = true;
+    }
+    if changed {
+        write_json(path, &document)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,392 @@
+//! Multi-file sends that stop part-way are kept with their file list and
+//! which files the receiver acknowledged, so `resume_batch` sends only the
+//! rest instead of the whole folder again. Files counted as sent are hashed
+//! again first: one that changed since goes again.
+
+use crate::cli_ipc::PlannedFile;
+use crate::storage::{data_file, now_ms, read_json, secure_remove, write_json};
+use crate::{
+    emit_system_log, job_journal, run_send_request, zero_copy, AppState, CommandResult, SendRequest,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+pub(crate) const SEND_BATCHES_FILE: &str = "send-batches.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchFile {
+    /// As the CLI names it, relative to the sent folder.
+    path: String,
+    size: u64,
+    /// The local file; known once it was sent.
+    #[serde(default)]
+    source: Option<PathBuf>,
+    /// What the receiver verified when it acknowledged the file.
+    #[serde(default)]
+    sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendBatch {
+    id: String,
+    /// The send as first requested, before host resolution.
+    request: SendRequest,
+    files: Vec<BatchFile>,
+    /// Bit `i` is set once the receiver acknowledged `files[i]`.
+    completed: Vec<u8>,
+    created_at_ms: u64,
+    updated_at_ms: u64,
+}
+
+impl SendBatch {
+    fn is_completed(&self, index: usize) -> bool {
+        self.completed
+            .get(index / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    fn mark_completed(&mut self, index: usize) {
+        if self.completed.len() <= index / 8 {
+            self.completed.resize(index / 8 + 1, 0);
+        }
+        self.completed[index / 8] |= 1 << (index % 8);
+    }
+
+    fn completed_count(&self) -> usize {
+        (0..self.files.len())
+            .filter(|index| self.is_completed(*index))
+            .count()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+struct StoredBatches {
+    batches: Vec<SendBatch>,
+}
+
+/// Returned by `list_send_batches`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendBatchSummary {
+    id: String,
+    path: String,
+    /// Host or device name as the user gave it.
+    target: Option<String>,
+    files: usize,
+    completed: usize,
+    updated_at_ms: u64,
+}
+
+/// Set on a send by `resume_batch`.
+#[derive(Debug, Clone)]
+pub(crate) struct Resume {
+    batch_id: String,
+    /// The files to leave out, for the CLI's `--skip-from`.
+    pub(crate) skip_from: PathBuf,
+}
+
+#[derive(Debug)]
+struct Tracked {
+    request: SendRequest,
+    /// `None` until the CLI reports more than one file.
+    batch_id: Option<String>,
+}
+
+/// CLI sends in progress whose files are being recorded, by send id.
+#[derive(Debug, Default)]
+pub(crate) struct ActiveBatches {
+    sends: HashMap<u64, Tracked>,
+}
+
+fn load(app: &AppHandle) -> Result<StoredBatches, String> {
+    read_json(&data_file(app, SEND_BATCHES_FILE)?)
+}
+
+fn save(app: &AppHandle, stored: &StoredBatches) -> Result<(), String> {
+    write_json(&data_file(app, SEND_BATCHES_FILE)?, stored)
+}
+
+/// Loads the stored batches, lets `change` edit the one with `batch_id` and
+/// saves them again.
+fn update_batch(
+    app: &AppHandle,
+    batch_id: &str,
+    change: impl FnOnce(&mut SendBatch),
+) -> Result<(), String> {
+    let mut stored = load(app)?;
+    if let Some(batch) = stored.batches.iter_mut().find(|batch| batch.id == batch_id) {
+        change(batch);
+        batch.updated_at_ms = now_ms();
+        save(app, &stored)?;
+    }
+    Ok(())
+}
+
+/// Overwrites and deletes the stored batches, which list sent file paths.
+pub(crate) fn wipe(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _active = state
+        .send_batches
+        .lock()
+        .map_err(|_| "failed to lock send batches".to_string())?;
+    secure_remove(&data_file(app, SEND_BATCHES_FILE)?)
+}
+
+/// Starts recording the files of a CLI send; `request` is as the user made
+/// it, so a resumed send resolves the host again.
+pub(crate) fn track(app: &AppHandle, send_id: u64, request: &SendRequest) {
+    let state = app.state::<AppState>();
+    let Ok(mut active) = state.send_batches.lock() else {
+        return;
+    };
+    let batch_id = request
+        .resume
+        .as_ref()
+        .map(|resume| resume.batch_id.clone());
+    let mut request = request.clone();
+    request.resume = None;
+    active.sends.insert(send_id, Tracked { request, batch_id });
+}
+
+/// The CLI's `sendPlan`. A new batch is stored for sends of several files;
+/// a resumed one only gains files added to the folder since.
+pub(crate) fn plan(app: &AppHandle, send_id: u64, files: &[PlannedFile]) {
+    let state = app.state::<AppState>();
+    let Ok(mut active) = state.send_batches.lock() else {
+        return;
+    };
+    let Some(tracked) = active.sends.get_mut(&send_id) else {
+        return;
+    };
+    let result = match &tracked.batch_id {
+        Some(batch_id) => {
+            job_journal::attach_batch(app, send_id, batch_id);
+            update_batch(app, batch_id, |batch| {
+                for file in files {
+                    if !batch.files.iter().any(|known| known.path == file.path) {
+                        batch.files.push(batch_file(file));
+                    }
+                }
+            })
+        }
+        None if files.len() > 1 => {
+            let now = now_ms();
+            let batch = SendBatch {
+                id: Uuid::new_v4().to_string(),
+                request: tracked.request.clone(),
+                files: files.iter().map(batch_file).collect(),
+                completed: vec![0; files.len().div_ceil(8)],
+                created_at_ms: now,
+                updated_at_ms: now,
+            };
+            tracked.batch_id = Some(batch.id.clone());
+            job_journal::attach_batch(app, send_id, &batch.id);
+            load(app).and_then(|mut stored| {
+                stored.batches.push(batch);
+                save(app, &stored)
+            })
+        }
+        None => Ok(()),
+    };
+    if let Err(err) = result {
+        emit_system_log(app, format!("failed to record send batch: {err}"));
+    }
+}
+
+fn batch_file(file: &PlannedFile) -> BatchFile {
+    BatchFile {
+        path: file.path.clone(),
+        size: file.size,
+        source: None,
+        sha256: None,
+    }
+}
+
+/// The CLI's `fileSent`: the receiver has `path`.
+pub(crate) fn file_sent(
+    app: &AppHandle,
+    send_id: u64,
+    path: &str,
+    source: &Path,
+    sha256: Option<&str>,
+) {
+    let state = app.state::<AppState>();
+    let Ok(active) = state.send_batches.lock() else {
+        return;
+    };
+    let Some(batch_id) = active
+        .sends
+        .get(&send_id)
+        .and_then(|tracked| tracked.batch_id.as_deref())
+    else {
+        return;
+    };
+    let result = update_batch(app, batch_id, |batch| {
+        let Some(index) = batch.files.iter().position(|file| file.path == path) else {
+            return;
+        };
+        batch.files[index].source = Some(source.to_path_buf());
+        batch.files[index].sha256 = sha256.map(str::to_string);
+        batch.mark_completed(index);
+    });
+    if let Err(err) = result {
+        emit_system_log(app, format!("failed to record send batch: {err}"));
+    }
+}
+
+/// A finished send drops its batch; one that failed keeps it for
+/// `resume_batch`.
+pub(crate) fn finish(app: &AppHandle, send_id: u64, success: bool) {
+    let state = app.state::<AppState>();
+    let Ok(mut active) = state.send_batches.lock() else {
+        return;
+    };
+    let Some(batch_id) = active
+        .sends
+        .remove(&send_id)
+        .and_then(|tracked| tracked.batch_id)
+    else {
+        return;
+    };
+    let result = load(app).and_then(|mut stored| {
+        let Some(position) = stored.batches.iter().position(|batch| batch.id == batch_id) else {
+            return Ok(());
+        };
+        if success {
+            stored.batches.remove(position);
+            return save(app, &stored);
+        }
+        let batch = &stored.batches[position];
+        emit_system_log(
+            app,
+            format!(
+                "send stopped after {} of {} files; batch {batch_id} can resume the rest",
+                batch.completed_count(),
+                batch.files.len()
+            ),
+        );
+        Ok(())
+    });
+    if let Err(err) = result {
+        emit_system_log(app, format!("failed to record send batch: {err}"));
+    }
+}
+
+/// Files the receiver acknowledged that still hash to what it verified;
+/// the rest are sent again.
+fn verified_paths(batch: &SendBatch) -> Vec<String> {
+    batch
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| batch.is_completed(*index))
+        .filter_map(|(_, file)| {
+            let expected = file.sha256.as_deref()?;
+            let actual = zero_copy::sha256_file(file.source.as_deref()?).ok()?;
+            actual
+                .eq_ignore_ascii_case(expected)
+                .then(|| file.path.clone())
+        })
+        .collect()
+}
+
+pub(crate) fn exists(app: &AppHandle, batch_id: &str) -> bool {
+    load(app).is_ok_and(|stored| stored.batches.iter().any(|batch| batch.id == batch_id))
+}
+
+/// Sends the files of an interrupted batch the receiver does not have yet.
+#[tauri::command]
+pub(crate) async fn resume_batch(
+    app: AppHandle,
+    batch_id: String,
+) -> Result<CommandResult, String> {
+    resume(app, batch_id, None).await
+}
+
+/// `resume_batch` for `job_journal`, which passes the job's durable id on.
+pub(crate) async fn resume(
+    app: AppHandle,
+    batch_id: String,
+    job_id: Option<String>,
+) -> Result<CommandResult, String> {
+    let batch = load(&app)?
+        .batches
+        .into_iter()
+        .find(|batch| batch.id == batch_id)
+        .ok_or_else(|| format!("no interrupted send batch {batch_id}"))?;
+    let acknowledged = batch.completed_count();
+    let total = batch.files.len();
+    let mut request = batch.request.clone();
+    let verified = tauri::async_runtime::spawn_blocking(move || verified_paths(&batch))
+        .await
+        .map_err(|err| format!("failed to join batch verification: {err}"))?;
+    emit_system_log(
+        &app,
+        format!(
+            "resuming batch {batch_id}: {} of {total} files verified on the receiver, {} changed since",
+            verified.len(),
+            acknowledged - verified.len()
+        ),
+    );
+    // The id is one of ours, checked above, so it is safe in a file name.
+    let skip_from = data_file(&app, &format!("send-batch-{batch_id}.skip"))?;
+    fs::write(&skip_from, verified.join("\n"))
+        .map_err(|err| format!("failed to write {}: {err}", skip_from.display()))?;
+    request.job_id = job_id;
+    request.resume = Some(Resume {
+        batch_id,
+        skip_from: skip_from.clone(),
+    });
+    let result = run_send_request(app, request).await;
+    let _ = fs::remove_file(&skip_from);
+    result
+}
+
+#[tauri::command]
+pub(crate) fn list_send_batches(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<SendBatchSummary>, String> {
+    let _guard = state
+        .send_batches
+        .lock()
+        .map_err(|_| "failed to lock send batches".to_string())?;
+    Ok(load(&app)?
+        .batches
+        .iter()
+        .map(|batch| SendBatchSummary {
+            id: batch.id.clone(),
+            path: batch.request.path.clone(),
+            target: batch
+                .request
+                .host
+                .clone()
+                .or_else(|| batch.request.device.clone()),
+            files: batch.files.len(),
+            completed: batch.completed_count(),
+            updated_at_ms: batch.updated_at_ms,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub(crate) fn discard_send_batch(
+    app: AppHandle,
+    state: State<AppState>,
+    batch_id: String,
+) -> Result<(), String> {
+    let _guard = state
+        .send_batches
+        .lock()
+        .map_err(|_| "failed to lock send batches".to_string())?;
+    let mut stored = load(&app)?;
+    stored.batches.retain(|batch| batch.id != batch_id);
+    save(&app, &stored)
+}
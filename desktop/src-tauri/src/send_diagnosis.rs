@@ -0,0 +1,385 @@
+use crate::dry_run::{known_host_fingerprint, normalize_fingerprint, probe_tls_fingerprint};
+use crate::{discover_devices, host_resolution, kill_switch, AppState, SendRequest};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Short enough that the diagnosis feels instant next to the failed send.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiagnosisCheck {
+    name: &'static str,
+    status: &'static str,
+    detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LikelyCause {
+    code: &'static str,
+    /// 0-100; causes are sorted by it, highest first.
+    score: u8,
+    detail: String,
+    fix: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendDiagnosis {
+    host: Option<String>,
+    port: u16,
+    checks: Vec<DiagnosisCheck>,
+    causes: Vec<LikelyCause>,
+    /// The original error, for a "show details" toggle.
+    error: String,
+}
+
+impl SendDiagnosis {
+    fn check(&mut self, name: &'static str, status: &'static str, detail: impl Into<String>) {
+        self.checks.push(DiagnosisCheck {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+
+    /// Adds a cause, or raises the score of one already suspected.
+    fn cause(&mut self, code: &'static str, score: u8, detail: impl Into<String>, fix: &str) {
+        if let Some(existing) = self.causes.iter_mut().find(|cause| cause.code == code) {
+            existing.score = existing.score.max(score);
+            return;
+        }
+        self.causes.push(LikelyCause {
+            code,
+            score,
+            detail: detail.into(),
+            fix: fix.to_string(),
+        });
+    }
+}
+
+/// Suspects taken from the CLI's error text alone, before any probe runs.
+fn causes_from_error(diagnosis: &mut SendDiagnosis, request: &SendRequest) {
+    let error = diagnosis.error.to_lowercase();
+    let has_pair_code = request
+        .pair_code
+        .as_ref()
+        .is_some_and(|value| !value.trim().is_empty());
+    if error.contains("pair code mismatch") {
+        if has_pair_code {
+            diagnosis.cause(
+                "pairCodeMismatch",
+                90,
+                "the receiver did not accept the pair code",
+                "Check the code shown on the receiver; it may have rotated since you copied it",
+            );
+        } else {
+            diagnosis.cause(
+                "pairCodeMissing",
+                90,
+                "the receiver requires a pair code and none was given",
+                "Enter the 6-digit code shown on the receiver",
+            );
+        }
+    }
+    if error.contains("fingerprint mismatch") || error.contains("fingerprint changed") {
+        diagnosis.cause(
+            "tlsFingerprintChanged",
+            85,
+            "the receiver presented a different TLS certificate than expected",
+            "Confirm the new fingerprint with the receiver, then update the pinned fingerprint or known hosts entry",
+        );
+    }
+    if error.contains("rejected") {
+        diagnosis.cause(
+            "receiverRejected",
+            70,
+            "the receiver declined the transfer",
+            "Ask the receiver to accept the prompt, or check its quiet hours and auto-accept rules",
+        );
+    }
+    if error.contains("no receiver found") {
+        diagnosis.cause(
+            "notDiscovered",
+            60,
+            "no receiver answered discovery",
+            "Make sure the receiver is listening on the same network, or enter its address",
+        );
+    }
+    if error.contains("enotfound") || error.contains("eai_again") {
+        diagnosis.cause(
+            "hostUnresolved",
+            60,
+            "the receiver's host name could not be resolved",
+            "Use the receiver's IP address instead of its name",
+        );
+    }
+}
+
+fn check_pair_code_format(diagnosis: &mut SendDiagnosis, request: &SendRequest) {
+    let Some(code) = request
+        .pair_code
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    else {
+        diagnosis.check("pairCode", "skip", "no pair code provided");
+        return;
+    };
+    if code.len() == 6 && code.chars().all(|ch| ch.is_ascii_digit()) {
+        diagnosis.check(
+            "pairCode",
+            "pass",
+            "format is valid; only the receiver can confirm the code itself",
+        );
+    } else {
+        diagnosis.check("pairCode", "fail", "pair code must be exactly 6 digits");
+        diagnosis.cause(
+            "pairCodeFormat",
+            95,
+            "the pair code is not 6 digits",
+            "Re-enter the 6-digit code shown on the receiver",
+        );
+    }
+}
+
+fn resolve_host(diagnosis: &mut SendDiagnosis, host: &str) -> Option<SocketAddr> {
+    // The same lookup a send does, so `.local` names behave alike.
+    let resolved = match host_resolution::resolve(host) {
+        None => host
+            .parse::<IpAddr>()
+            .map(|address| vec![address])
+            .map_err(|err| err.to_string()),
+        Some(resolution) => resolution.map(|resolution| resolution.addresses),
+    };
+    match resolved.map(|addresses| addresses.first().copied()) {
+        Ok(Some(address)) => {
+            let address = SocketAddr::new(address, diagnosis.port);
+            diagnosis.check("resolve", "pass", format!("{host} resolves to {address}"));
+            return Some(address);
+        }
+        Ok(None) => diagnosis.check("resolve", "fail", format!("{host} has no addresses")),
+        Err(err) => diagnosis.check("resolve", "fail", format!("cannot resolve {host}: {err}")),
+    }
+    diagnosis.cause(
+        "hostUnresolved",
+        85,
+        format!("{host} does not resolve to an address"),
+        "Check the spelling, or use the receiver's IP address instead of its name",
+    );
+    None
+}
+
+fn check_connect(diagnosis: &mut SendDiagnosis, address: SocketAddr) -> bool {
+    match TcpStream::connect_timeout(&address, PROBE_TIMEOUT) {
+        Ok(_) => {
+            diagnosis.check("connect", "pass", format!("{address} accepts connections"));
+            true
+        }
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => {
+            diagnosis.check(
+                "connect",
+                "fail",
+                format!("{address} refused the connection"),
+            );
+            diagnosis.cause(
+                "notListening",
+                90,
+                format!("nothing is listening on port {}", address.port()),
+                "Start the receiver, and check that both sides use the same port",
+            );
+            false
+        }
+        Err(err) => {
+            diagnosis.check("connect", "fail", format!("{address}: {err}"));
+            diagnosis.cause(
+                "blockedOrUnreachable",
+                80,
+                format!("{address} did not answer"),
+                "Check that both devices are on the same network and that the receiver's firewall allows the port; guest Wi-Fi often isolates devices",
+            );
+            false
+        }
+    }
+}
+
+/// Compares what the receiver actually speaks with what the send asked for.
+fn check_tls(
+    diagnosis: &mut SendDiagnosis,
+    request: &SendRequest,
+    address: SocketAddr,
+    host: &str,
+) {
+    let handshake = probe_tls_fingerprint(address, host, PROBE_TIMEOUT);
+    if !request.tls.unwrap_or(false) {
+        match handshake {
+            Ok(_) => {
+                diagnosis.check("tls", "fail", "receiver expects TLS");
+                diagnosis.cause(
+                    "tlsRequired",
+                    85,
+                    "the receiver only accepts TLS connections",
+                    "Turn on TLS for this send",
+                );
+            }
+            Err(_) => diagnosis.check("tls", "skip", "TLS not requested"),
+        }
+        return;
+    }
+
+    let fingerprint = match handshake {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            diagnosis.check("tls", "fail", err);
+            diagnosis.cause(
+                "tlsNotEnabled",
+                80,
+                "the TLS handshake with the receiver failed",
+                "Start the receiver with TLS, or turn TLS off for this send",
+            );
+            return;
+        }
+    };
+    let expected = request
+        .tls_fingerprint
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+        .map(normalize_fingerprint)
+        .or_else(|| {
+            request
+                .tls_tofu
+                .unwrap_or(false)
+                .then(|| known_host_fingerprint(request, host, diagnosis.port))
+                .flatten()
+        });
+    match expected {
+        Some(expected) if expected != fingerprint => {
+            diagnosis.check(
+                "tls",
+                "fail",
+                format!("fingerprint mismatch: expected={expected} actual={fingerprint}"),
+            );
+            diagnosis.cause(
+                "tlsFingerprintChanged",
+                90,
+                "the receiver presented a different TLS certificate than expected",
+                "Confirm the new fingerprint with the receiver, then update the pinned fingerprint or known hosts entry",
+            );
+        }
+        _ => diagnosis.check(
+            "tls",
+            "pass",
+            format!("handshake ok, certificate {fingerprint}"),
+        ),
+    }
+}
+
+/// Re-runs the cheap parts of a failed send — name or mDNS resolution, a TCP
+/// connect, a TLS handshake and the pair code format — and ranks the likely
+/// causes with a suggested fix for each, so the UI can show those instead of
+/// the raw CLI error. Nothing is transferred.
+#[tauri::command]
+pub(crate) async fn diagnose_send_failure(
+    app: AppHandle,
+    request: SendRequest,
+    error: String,
+) -> Result<SendDiagnosis, String> {
+    let mut diagnosis = SendDiagnosis {
+        host: request
+            .host
+            .clone()
+            .filter(|value| !value.trim().is_empty()),
+        port: request.port,
+        checks: Vec::new(),
+        causes: Vec::new(),
+        error,
+    };
+    causes_from_error(&mut diagnosis, &request);
+    check_pair_code_format(&mut diagnosis, &request);
+
+    if kill_switch::ensure_network_allowed(app.state::<AppState>().inner()).is_err() {
+        diagnosis.cause(
+            "killSwitch",
+            100,
+            "network activity is stopped by the kill-switch",
+            "Resume network activity, then retry",
+        );
+    } else {
+        if diagnosis.host.is_none() {
+            let needle = request
+                .device
+                .as_ref()
+                .map(|device| device.trim().to_lowercase())
+                .filter(|device| !device.is_empty());
+            let timeout = request.timeout_ms.unwrap_or(3000).max(100);
+            match discover_devices(app, timeout).await {
+                Ok(devices) => match devices.into_iter().find(|device| {
+                    needle
+                        .as_ref()
+                        .is_none_or(|needle| device.name.to_lowercase().contains(needle))
+                }) {
+                    Some(device) => {
+                        diagnosis.check(
+                            "discovery",
+                            "pass",
+                            format!("found {} at {}:{}", device.name, device.host, device.port),
+                        );
+                        if device.port != request.port {
+                            diagnosis.cause(
+                                "portMismatch",
+                                60,
+                                format!(
+                                    "the receiver advertises port {}, the send used {}",
+                                    device.port, request.port
+                                ),
+                                "Use the port the receiver advertises",
+                            );
+                        }
+                        diagnosis.host = Some(device.host);
+                        diagnosis.port = device.port;
+                    }
+                    None => {
+                        diagnosis.check("discovery", "fail", "no matching receiver found");
+                        diagnosis.cause(
+                            "notDiscovered",
+                            85,
+                            "no receiver answered mDNS discovery",
+                            "Make sure the receiver is listening on the same network; if multicast is blocked, enter its IP address",
+                        );
+                    }
+                },
+                Err(err) => diagnosis.check("discovery", "fail", err),
+            }
+        }
+
+        if let Some(host) = diagnosis.host.clone() {
+            let probe_request = request.clone();
+            diagnosis = tauri::async_runtime::spawn_blocking(move || {
+                if let Some(address) = resolve_host(&mut diagnosis, &host) {
+                    if check_connect(&mut diagnosis, address) {
+                        check_tls(&mut diagnosis, &probe_request, address, &host);
+                    }
+                }
+                diagnosis
+            })
+            .await
+            .map_err(|err| format!("failed to join diagnosis task: {err}"))?;
+        }
+    }
+
+    if diagnosis.causes.is_empty() {
+        diagnosis.cause(
+            "unknown",
+            30,
+            "every check passed; the failure happened during the transfer itself",
+            "Retry the send; if it keeps failing, look at the receiver's log around the same time",
+        );
+    }
+    diagnosis.causes.sort_by_key(|cause| Reverse(cause.score));
+    Ok(diagnosis)
+}
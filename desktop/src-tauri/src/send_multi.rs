@@ -0,0 +1,244 @@
+use crate::device_groups::resolve_group;
+use crate::{emit_system_log, process_pool, run_send_request, CommandResult, SendRequest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Instant;
+use tauri::AppHandle;
+
+/// Each target runs its own CLI child; leave room in the process pool for
+/// the listener and discovery.
+const MAX_TARGETS: usize = process_pool::MAX_CHILDREN / 2;
+
+/// One receiver of a fan-out send. Unset fields fall back to the shared
+/// request; pair codes and pinned fingerprints differ per device.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SendTarget {
+    host: Option<String>,
+    device: Option<String>,
+    port: Option<u16>,
+    pair_code: Option<String>,
+    tls_fingerprint: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+impl SendTarget {
+    pub(crate) fn host(&self) -> Option<&str> {
+        non_empty(&self.host)
+    }
+
+    pub(crate) fn device(&self) -> Option<&str> {
+        non_empty(&self.device)
+    }
+
+    /// The member of a device group, pointed at where discovery found it.
+    pub(crate) fn at(self, host: String, port: u16) -> Self {
+        Self {
+            host: Some(host),
+            device: None,
+            port: self.port.or(Some(port)),
+            ..self
+        }
+    }
+
+    pub(crate) fn without_pair_code(self) -> Self {
+        Self {
+            pair_code: None,
+            ..self
+        }
+    }
+
+    fn apply(&self, request: &SendRequest) -> SendRequest {
+        let mut request = request.clone();
+        request.host = non_empty(&self.host).map(str::to_string);
+        request.device = non_empty(&self.device).map(str::to_string);
+        if let Some(port) = self.port {
+            request.port = port;
+        }
+        if let Some(pair_code) = non_empty(&self.pair_code) {
+            request.pair_code = Some(pair_code.to_string());
+        }
+        if let Some(fingerprint) = non_empty(&self.tls_fingerprint) {
+            request.tls_fingerprint = Some(fingerprint.to_string());
+        }
+        request
+    }
+
+    fn label(&self, default_port: u16) -> Option<String> {
+        let name = non_empty(&self.host).or_else(|| non_empty(&self.device))?;
+        Some(format!("{name}:{}", self.port.unwrap_or(default_port)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetOutcome {
+    /// `host:port` or `device:port`, as in the send's `sendStarted` entry.
+    target: String,
+    success: bool,
+    code: Option<i32>,
+    error: Option<String>,
+    duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MultiSendSummary {
+    succeeded: usize,
+    failed: usize,
+    /// In the order the targets were given.
+    outcomes: Vec<TargetOutcome>,
+}
+
+impl MultiSendSummary {
+    /// For callers that report one result per send, such as presets: one
+    /// line per target, failures on stderr.
+    pub(crate) fn to_command_result(&self) -> CommandResult {
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        for outcome in &self.outcomes {
+            match &outcome.error {
+                None => stdout.push_str(&format!("{}: sent\n", outcome.target)),
+                Some(error) => stderr.push_str(&format!("{}: {error}\n", outcome.target)),
+            }
+        }
+        CommandResult {
+            success: self.failed == 0,
+            code: if self.failed == 0 { 0 } else { 1 },
+            stdout,
+            stderr,
+        }
+    }
+}
+
+/// Runs one send per target in parallel and waits for all of them.
+/// `missing` are targets that could not be resolved; they are reported as
+/// failures without being attempted.
+pub(crate) async fn fan_out(
+    app: &AppHandle,
+    request: &SendRequest,
+    targets: Vec<SendTarget>,
+    missing: Vec<(String, String)>,
+) -> Result<MultiSendSummary, String> {
+    if targets.is_empty() && missing.is_empty() {
+        return Err("select at least one device".to_string());
+    }
+    if targets.len() > MAX_TARGETS {
+        return Err(format!(
+            "a send can go to at most {MAX_TARGETS} devices at once"
+        ));
+    }
+    let mut labels = Vec::with_capacity(targets.len());
+    for target in &targets {
+        labels.push(
+            target
+                .label(request.port)
+                .ok_or_else(|| "every target needs a host or a device name".to_string())?,
+        );
+    }
+
+    let tasks: Vec<_> = targets
+        .iter()
+        .map(|target| {
+            let app = app.clone();
+            let request = target.apply(request);
+            tauri::async_runtime::spawn(async move {
+                let started = Instant::now();
+                let result = run_send_request(app, request).await;
+                (result, started.elapsed().as_millis() as u64)
+            })
+        })
+        .collect();
+
+    let mut outcomes = Vec::with_capacity(tasks.len() + missing.len());
+    for (task, target) in tasks.into_iter().zip(labels) {
+        let outcome = match task.await {
+            Ok((Ok(output), duration_ms)) => TargetOutcome {
+                target,
+                success: true,
+                code: Some(output.code),
+                error: None,
+                duration_ms,
+            },
+            Ok((Err(err), duration_ms)) => TargetOutcome {
+                target,
+                success: false,
+                code: None,
+                error: Some(err),
+                duration_ms,
+            },
+            Err(err) => TargetOutcome {
+                target,
+                success: false,
+                code: None,
+                error: Some(format!("failed to join send task: {err}")),
+                duration_ms: 0,
+            },
+        };
+        outcomes.push(outcome);
+    }
+    outcomes.extend(missing.into_iter().map(|(target, error)| TargetOutcome {
+        target,
+        success: false,
+        code: None,
+        error: Some(error),
+        duration_ms: 0,
+    }));
+
+    let succeeded = outcomes.iter().filter(|outcome| outcome.success).count();
+    let summary = MultiSendSummary {
+        succeeded,
+        failed: outcomes.len() - succeeded,
+        outcomes,
+    };
+    emit_system_log(
+        app,
+        format!(
+            "multi-device send finished: {} of {} succeeded",
+            summary.succeeded,
+            summary.succeeded + summary.failed
+        ),
+    );
+    Ok(summary)
+}
+
+/// Sends `request` to every target at once, each as an ordinary send with
+/// its own progress, history entry and job priority. `group` adds the
+/// members of a saved device group. One target failing does not stop the
+/// others; the summary reports each outcome.
+#[tauri::command]
+pub(crate) async fn send_multi(
+    app: AppHandle,
+    request: SendRequest,
+    devices: Vec<SendTarget>,
+    group: Option<String>,
+) -> Result<MultiSendSummary, String> {
+    let mut seen = HashSet::new();
+    for target in &devices {
+        let label = target
+            .label(request.port)
+            .ok_or_else(|| "every target needs a host or a device name".to_string())?;
+        if !seen.insert(label.to_lowercase()) {
+            return Err(format!("{label} is selected more than once"));
+        }
+    }
+    let mut targets = devices;
+    let mut missing = Vec::new();
+    if let Some(group) = group.as_deref().filter(|group| !group.trim().is_empty()) {
+        let resolved = resolve_group(&app, group).await?;
+        // A device picked explicitly and through the group is sent to once.
+        targets.extend(resolved.targets.into_iter().filter(|target| {
+            target
+                .label(request.port)
+                .is_some_and(|label| seen.insert(label.to_lowercase()))
+        }));
+        missing = resolved.missing;
+    }
+    fan_out(&app, &request, targets, missing).await
+}
@@ -0,0 +1,111 @@
+use crate::archive::AutoExtract;
+use crate::auto_print::AutoPrint;
+use crate::battery::BatteryPolicy;
+use crate::broadcast_ping::BroadcastPingSettings;
+use crate::clipboard_sync::ClipboardSync;
+use crate::dedup::DuplicatePolicy;
+use crate::directory_beacon::DirectoryBeaconSettings;
+use crate::dns_sd::DnsSdSettings;
+use crate::history_retention::HistoryRetention;
+use crate::interfaces::InterfacePolicy;
+use crate::kde_connect::KdeConnectSettings;
+use crate::managed_config;
+use crate::media_import::MediaImport;
+use crate::org_directory::OrgDirectorySettings;
+use crate::outbound_policy::OutboundPolicy;
+use crate::overlay::OverlaySettings;
+use crate::quick_share::QuickShareSettings;
+use crate::quiet_hours::QuietHours;
+use crate::receive_folder::RetentionPolicy;
+use crate::storage::{data_file, read_json, write_json};
+use crate::syncthing::SyncthingSettings;
+use crate::telemetry::Telemetry;
+use crate::throttle::ReceiveLimits;
+use crate::watchdog::CommandTimeouts;
+use crate::webdav::WebDavSettings;
+use crate::zero_copy::ZeroCopyMode;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+pub(crate) const APP_SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct AppSettings {
+    pub(crate) hidden_from_discovery: bool,
+    /// Receive folders outside the home folder the user explicitly chose.
+    pub(crate) approved_dirs: Vec<String>,
+    pub(crate) receive_limits: ReceiveLimits,
+    pub(crate) retention: Option<RetentionPolicy>,
+    pub(crate) duplicate_policy: DuplicatePolicy,
+    pub(crate) auto_extract: AutoExtract,
+    pub(crate) media_import: MediaImport,
+    pub(crate) zero_copy: ZeroCopyMode,
+    /// Send extended attributes with files and restore those received from
+    /// the same platform.
+    pub(crate) preserve_xattrs: bool,
+    pub(crate) quiet_hours: QuietHours,
+    pub(crate) command_timeouts: CommandTimeouts,
+    pub(crate) clipboard_sync: ClipboardSync,
+    pub(crate) auto_print: AutoPrint,
+    pub(crate) history_retention: HistoryRetention,
+    pub(crate) telemetry: Telemetry,
+    pub(crate) battery: BatteryPolicy,
+    pub(crate) interfaces: InterfacePolicy,
+    /// Which destinations sends may go to.
+    pub(crate) outbound_policy: OutboundPolicy,
+    pub(crate) overlay: OverlaySettings,
+    pub(crate) org_directory: OrgDirectorySettings,
+    pub(crate) directory_beacon: DirectoryBeaconSettings,
+    pub(crate) dns_sd: DnsSdSettings,
+    pub(crate) broadcast_ping: BroadcastPingSettings,
+    pub(crate) webdav: WebDavSettings,
+    pub(crate) syncthing: SyncthingSettings,
+    pub(crate) kde_connect: KdeConnectSettings,
+    pub(crate) quick_share: QuickShareSettings,
+}
+
+pub(crate) fn load_settings(app: &AppHandle) -> Result<AppSettings, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .settings_lock
+        .lock()
+        .map_err(|_| "failed to lock app settings".to_string())?;
+    let mut settings: AppSettings = read_json(&data_file(app, APP_SETTINGS_FILE)?)?;
+    managed_config::apply_to(&mut settings);
+    Ok(settings)
+}
+
+/// Applies `change` to the persisted settings under the settings lock and
+/// returns the updated copy, with any managed values applied on top.
+pub(crate) fn update_settings<F>(app: &AppHandle, change: F) -> Result<AppSettings, String>
+where
+    F: FnOnce(&mut AppSettings) -> Result<(), String>,
+{
+    let state = app.state::<AppState>();
+    let _guard = state
+        .settings_lock
+        .lock()
+        .map_err(|_| "failed to lock app settings".to_string())?;
+    let path = data_file(app, APP_SETTINGS_FILE)?;
+    let mut settings: AppSettings = read_json(&path)?;
+    change(&mut settings)?;
+    write_json(&path, &settings)?;
+    managed_config::apply_to(&mut settings);
+    Ok(settings)
+}
+
+#[tauri::command]
+pub(crate) fn get_app_settings(app: AppHandle) -> Result<AppSettings, String> {
+    load_settings(&app)
+}
+
+#[tauri::command]
+pub(crate) fn set_preserve_xattrs(app: AppHandle, enabled: bool) -> Result<bool, String> {
+    update_settings(&app, |settings| {
+        settings.preserve_xattrs = enabled;
+        Ok(())
+    })
+    .map(|settings| settings.preserve_xattrs)
+}
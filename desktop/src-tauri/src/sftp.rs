@@ -0,0 +1,523 @@
+//! Delivery over SFTP to machines that will never run this app, such as a
+//! NAS or a router. The system `ssh` connects and authenticates with keys
+//! from the SSH agent, in batch mode so it never prompts; the SFTP exchange
+//! over its stdio is done here so every write can report progress.
+
+use crate::delivery::SourceFile;
+use crate::zero_copy::{format_bytes, Output};
+use crate::{configure_cli_command_for_platform, scheduler, AppState, CommandResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const FXP_INIT: u8 = 1;
+const FXP_VERSION: u8 = 2;
+const FXP_OPEN: u8 = 3;
+const FXP_CLOSE: u8 = 4;
+const FXP_WRITE: u8 = 6;
+const FXP_MKDIR: u8 = 14;
+const FXP_STATUS: u8 = 101;
+const FXP_HANDLE: u8 = 102;
+const FXF_WRITE: u32 = 0x02;
+const FXF_CREAT: u32 = 0x08;
+const FXF_TRUNC: u32 = 0x10;
+const FX_OK: u32 = 0;
+/// Version 3 is the one every server speaks.
+const SFTP_VERSION: u32 = 3;
+/// Data per write; every server accepts packets this size.
+const WRITE_CHUNK_BYTES: usize = 32 * 1024;
+/// Writes sent before waiting for their replies; one at a time would cost
+/// a round trip per chunk.
+const MAX_PENDING_WRITES: usize = 16;
+/// Replies are small; anything larger means the stream is out of step.
+const MAX_REPLY_BYTES: usize = 256 * 1024;
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+fn default_port() -> u16 {
+    22
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SftpTarget {
+    host: String,
+    #[serde(default = "default_port")]
+    port: u16,
+    /// `None` uses the user from the SSH config, or the local one.
+    #[serde(default)]
+    user: Option<String>,
+    /// Folder files are put under; relative paths start at the login
+    /// folder.
+    remote_dir: String,
+}
+
+impl SftpTarget {
+    pub(crate) fn label(&self) -> String {
+        format!(
+            "sftp://{}:{}/{}",
+            self.destination(),
+            self.port,
+            self.remote_dir.trim_start_matches('/')
+        )
+    }
+
+    fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{user}@{}", self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    /// Trims the fields and rejects values `ssh` would read as options.
+    pub(crate) fn validated(self) -> Result<Self, String> {
+        let host = self.host.trim().to_string();
+        if host.is_empty() || host.starts_with('-') || host.contains(char::is_whitespace) {
+            return Err("SSH host is invalid".to_string());
+        }
+        let user = self
+            .user
+            .map(|user| user.trim().to_string())
+            .filter(|user| !user.is_empty());
+        if user
+            .as_deref()
+            .is_some_and(|user| user.starts_with('-') || user.contains(['@', ' ']))
+        {
+            return Err("SSH user is invalid".to_string());
+        }
+        if self.port == 0 {
+            return Err("port must be in 1-65535".to_string());
+        }
+        let remote_dir = self.remote_dir.trim().trim_end_matches('/').to_string();
+        Ok(Self {
+            host,
+            port: self.port,
+            user,
+            remote_dir: if remote_dir.is_empty() && self.remote_dir.trim().starts_with('/') {
+                "/".to_string()
+            } else if remote_dir.is_empty() {
+                ".".to_string()
+            } else {
+                remote_dir
+            },
+        })
+    }
+
+    fn remote_path(&self, relative_path: &str) -> String {
+        match self.remote_dir.as_str() {
+            "." => relative_path.to_string(),
+            "/" => format!("/{relative_path}"),
+            dir => format!("{dir}/{relative_path}"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Packet(Vec<u8>);
+
+impl Packet {
+    fn new(kind: u8, id: Option<u32>) -> Self {
+        let mut packet = Self(vec![kind]);
+        if let Some(id) = id {
+            packet.u32(id);
+        }
+        packet
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn u64(&mut self, value: u64) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value);
+        self
+    }
+}
+
+struct Reply {
+    kind: u8,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes = self.body.get(at..at + 4)?;
+        Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn bytes_at(&self, at: usize) -> Option<&[u8]> {
+        let len = self.u32_at(at)? as usize;
+        self.body.get(at + 4..at + 4 + len)
+    }
+
+    /// `Ok` for a success status, the server's message otherwise. The
+    /// request id comes first and is not checked: requests are answered in
+    /// order and only counted.
+    fn status(&self) -> Result<(), String> {
+        if self.kind != FXP_STATUS {
+            return Err(format!("unexpected SFTP reply {}", self.kind));
+        }
+        match self.u32_at(4) {
+            Some(FX_OK) => Ok(()),
+            Some(code) => Err(self
+                .bytes_at(8)
+                .map(|message| String::from_utf8_lossy(message).to_string())
+                .filter(|message| !message.is_empty())
+                .unwrap_or_else(|| format!("SFTP error {code}"))),
+            None => Err("invalid SFTP status".to_string()),
+        }
+    }
+}
+
+fn write_packet(writer: &mut impl Write, packet: &Packet) -> Result<(), String> {
+    let mut framed = Vec::with_capacity(packet.0.len() + 4);
+    framed.extend_from_slice(&(packet.0.len() as u32).to_be_bytes());
+    framed.extend_from_slice(&packet.0);
+    writer
+        .write_all(&framed)
+        .map_err(|err| format!("failed to write to ssh: {err}"))
+}
+
+fn read_packet(reader: &mut impl Read) -> Result<Reply, String> {
+    let mut len = [0; 4];
+    reader
+        .read_exact(&mut len)
+        .map_err(|_| "ssh closed the connection".to_string())?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_REPLY_BYTES {
+        return Err(format!("invalid SFTP packet length {len}"));
+    }
+    let mut packet = vec![0; len];
+    reader
+        .read_exact(&mut packet)
+        .map_err(|_| "ssh closed the connection".to_string())?;
+    Ok(Reply {
+        kind: packet[0],
+        body: packet.split_off(1),
+    })
+}
+
+struct Session {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u32,
+}
+
+impl Session {
+    fn connect(target: &SftpTarget) -> Result<Self, String> {
+        let mut command = Command::new("ssh");
+        command
+            .args([
+                "-o",
+                "BatchMode=yes",
+                "-o",
+                &format!("ConnectTimeout={CONNECT_TIMEOUT_SECS}"),
+                "-p",
+                &target.port.to_string(),
+                "-s",
+                "--",
+                &target.destination(),
+                "sftp",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        configure_cli_command_for_platform(&mut command);
+        let mut child = command
+            .spawn()
+            .map_err(|err| format!("failed to start ssh: {err}"))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            return Err("failed to open ssh pipes".to_string());
+        };
+        let mut session = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 0,
+        };
+        let mut init = Packet::new(FXP_INIT, None);
+        init.u32(SFTP_VERSION);
+        session.send(init)?;
+        let reply = session.read()?;
+        if reply.kind != FXP_VERSION {
+            return Err("the server did not start an SFTP session".to_string());
+        }
+        Ok(session)
+    }
+
+    fn request(&mut self, kind: u8) -> Packet {
+        self.next_id = self.next_id.wrapping_add(1);
+        Packet::new(kind, Some(self.next_id))
+    }
+
+    fn send(&mut self, packet: Packet) -> Result<(), String> {
+        write_packet(&mut self.stdin, &packet)
+    }
+
+    fn read(&mut self) -> Result<Reply, String> {
+        read_packet(&mut self.stdout)
+    }
+
+    /// Fails when the folder exists too; callers ignore the result and
+    /// let opening a file inside report real problems.
+    fn mkdir(&mut self, path: &str) -> Result<(), String> {
+        let mut packet = self.request(FXP_MKDIR);
+        packet.bytes(path.as_bytes()).u32(0);
+        self.send(packet)?;
+        self.read()?.status()
+    }
+
+    fn open(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let mut packet = self.request(FXP_OPEN);
+        packet
+            .bytes(path.as_bytes())
+            .u32(FXF_WRITE | FXF_CREAT | FXF_TRUNC)
+            .u32(0);
+        self.send(packet)?;
+        let reply = self.read()?;
+        if reply.kind != FXP_HANDLE {
+            reply.status()?;
+            return Err("the server returned no file handle".to_string());
+        }
+        reply
+            .bytes_at(4)
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| "invalid SFTP handle".to_string())
+    }
+
+    fn close(&mut self, handle: &[u8]) -> Result<(), String> {
+        let mut packet = self.request(FXP_CLOSE);
+        packet.bytes(handle);
+        self.send(packet)?;
+        self.read()?.status()
+    }
+
+    /// Ends ssh and adds what it printed, which explains failed logins.
+    fn fail(mut self, err: String) -> String {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let mut stderr = String::new();
+        if let Some(mut pipe) = self.child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => format!("{err}: {}", line.trim()),
+            None => err,
+        }
+    }
+}
+
+/// Folders to create before the files, parents first.
+fn remote_dirs(files: &[SourceFile]) -> BTreeSet<String> {
+    let mut dirs = BTreeSet::new();
+    for file in files {
+        let mut prefix = String::new();
+        let parts: Vec<&str> = file.relative_path.split('/').collect();
+        for part in &parts[..parts.len().saturating_sub(1)] {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            dirs.insert(prefix.clone());
+        }
+    }
+    dirs
+}
+
+fn upload(
+    app: &AppHandle,
+    session: &mut Session,
+    output: &mut Output,
+    file: &SourceFile,
+    remote_path: &str,
+    send_id: u64,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let mut source = File::open(&file.path).map_err(|err| format!("failed to open file: {err}"))?;
+    let handle = session.open(remote_path)?;
+    let started = Instant::now();
+    let mut last_progress = Instant::now() - PROGRESS_INTERVAL;
+    let mut buffer = vec![0; WRITE_CHUNK_BYTES];
+    let mut offset = 0u64;
+    let mut pending = 0usize;
+    loop {
+        scheduler::wait_while_paused(app, send_id);
+        if state.network_halted.load(Ordering::SeqCst) {
+            return Err("send aborted by the kill-switch".to_string());
+        }
+        let read = source
+            .read(&mut buffer)
+            .map_err(|err| format!("failed to read file: {err}"))?;
+        if read == 0 {
+            break;
+        }
+        let mut packet = session.request(FXP_WRITE);
+        packet.bytes(&handle).u64(offset).bytes(&buffer[..read]);
+        session.send(packet)?;
+        offset += read as u64;
+        pending += 1;
+        if pending == MAX_PENDING_WRITES {
+            session.read()?.status()?;
+            pending -= 1;
+        }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            output.progress(&file.relative_path, offset, file.size, started);
+        }
+    }
+    for _ in 0..pending {
+        session.read()?.status()?;
+    }
+    session.close(&handle)?;
+    output.progress(&file.relative_path, offset, file.size.max(offset), started);
+    output.write("\n".to_string());
+    Ok(())
+}
+
+/// Puts `files` under the target's folder, creating subfolders as needed
+/// and replacing files of the same name.
+pub(crate) fn send(
+    app: &AppHandle,
+    target: &SftpTarget,
+    files: &[SourceFile],
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let mut output = Output::new(app, send_id);
+    let mut session = Session::connect(target)?;
+    let started = Instant::now();
+    let result = (|| {
+        for dir in remote_dirs(files) {
+            let _ = session.mkdir(&target.remote_path(&dir));
+        }
+        for (index, file) in files.iter().enumerate() {
+            output.start_file(index, files);
+            let remote_path = target.remote_path(&file.relative_path);
+            upload(app, &mut session, &mut output, file, &remote_path, send_id)
+                .map_err(|err| format!("failed to upload {}: {err}", file.relative_path))?;
+        }
+        Ok(())
+    })();
+    if let Err(err) = result {
+        return Err(session.fail(err));
+    }
+    let _ = session.child.kill();
+    let _ = session.child.wait();
+
+    let bytes: u64 = files.iter().map(|file| file.size).sum();
+    let seconds = started.elapsed().as_secs_f64().max(0.001);
+    output.write(format!(
+        "[send] done: files={} bytes={}\n[send] sftp: {} in {seconds:.2}s ({}/s)\n",
+        files.len(),
+        format_bytes(bytes as f64),
+        target.label(),
+        format_bytes(bytes as f64 / seconds)
+    ));
+    Ok(output.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn framed(packet: &Packet) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_packet(&mut out, packet).unwrap();
+        out
+    }
+
+    fn status(id: u32, code: u32, message: &str) -> Packet {
+        let mut packet = Packet::new(FXP_STATUS, Some(id));
+        packet.u32(code).bytes(message.as_bytes()).bytes(b"en");
+        packet
+    }
+
+    #[test]
+    fn packets_are_framed_big_endian() {
+        let mut packet = Packet::new(FXP_WRITE, Some(7));
+        packet.bytes(b"h").u64(0x0102).bytes(b"data");
+        assert_eq!(
+            framed(&packet),
+            [
+                &[0, 0, 0, 26, FXP_WRITE][..],
+                &[0, 0, 0, 7],
+                &[0, 0, 0, 1, b'h'],
+                &[0, 0, 0, 0, 0, 0, 1, 2],
+                &[0, 0, 0, 4, b'd', b'a', b't', b'a'],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn written_packets_read_back() {
+        let mut packet = Packet::new(FXP_HANDLE, Some(3));
+        packet.bytes(b"handle-1");
+        let mut reader = Cursor::new(framed(&packet));
+        let reply = read_packet(&mut reader).unwrap();
+        assert_eq!(reply.kind, FXP_HANDLE);
+        assert_eq!(reply.u32_at(0), Some(3));
+        assert_eq!(reply.bytes_at(4), Some(&b"handle-1"[..]));
+        assert_eq!(reply.bytes_at(8), None);
+    }
+
+    #[test]
+    fn status_codes_map_to_results() {
+        let reply = |packet: Packet| read_packet(&mut Cursor::new(framed(&packet))).unwrap();
+        assert_eq!(reply(status(1, FX_OK, "")).status(), Ok(()));
+        assert_eq!(
+            reply(status(2, 3, "Permission denied")).status(),
+            Err("Permission denied".to_string())
+        );
+        assert_eq!(
+            reply(status(3, 4, "")).status(),
+            Err("SFTP error 4".to_string())
+        );
+        assert_eq!(
+            reply(Packet::new(FXP_HANDLE, Some(4))).status(),
+            Err(format!("unexpected SFTP reply {FXP_HANDLE}"))
+        );
+        assert_eq!(
+            reply(Packet::new(FXP_STATUS, Some(5))).status(),
+            Err("invalid SFTP status".to_string())
+        );
+    }
+
+    #[test]
+    fn short_reads_are_errors() {
+        let whole = framed(&status(1, FX_OK, "ok"));
+        for cut in [0, 2, 4, whole.len() - 1] {
+            assert_eq!(
+                read_packet(&mut Cursor::new(&whole[..cut])).err(),
+                Some("ssh closed the connection".to_string()),
+                "cut at {cut}"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_lengths_are_rejected() {
+        assert_eq!(
+            read_packet(&mut Cursor::new(vec![0, 0, 0, 0])).err(),
+            Some("invalid SFTP packet length 0".to_string())
+        );
+        let oversized = (MAX_REPLY_BYTES as u32 + 1).to_be_bytes();
+        assert!(read_packet(&mut Cursor::new(oversized.to_vec()))
+            .err()
+            .is_some_and(|err| err.starts_with("invalid SFTP packet length")));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::audit::{record_audit, AuditAction};
+use crate::device_groups::match_device;
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{
+    cli_control, discover_devices, emit_system_log, inspect_listen_state, paths, AppState,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+
+pub(crate) const SHARED_FOLDERS_FILE: &str = "shared-folders.json";
+const SHARE_DISCOVERY_TIMEOUT_MS: u64 = 2000;
+
+/// A folder shared for one listen session only (`start_listen`'s `shares`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SharedFolder {
+    name: String,
+    path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ShareVisibility {
+    /// Every peer that passes pairing.
+    Everyone,
+    /// Only the listed devices.
+    Devices,
+}
+
+/// A peer allowed to see a `Devices` share. The receiver only knows where a
+/// connection comes from, so the name is resolved to addresses when the
+/// folder is shared.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AllowedDevice {
+    name: String,
+    addresses: Vec<String>,
+}
+
+/// A folder shared read-only with peers whenever this device is listening.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SharedFolderEntry {
+    name: String,
+    path: String,
+    visibility: ShareVisibility,
+    #[serde(default)]
+    devices: Vec<AllowedDevice>,
+    #[serde(default)]
+    added_at_ms: u64,
+}
+
+/// Share names and folders, plus allow lists for restricted shares, in the
+/// shape of `listen --share` / `--share-allow`.
+type ShareMaps = (BTreeMap<String, String>, BTreeMap<String, Vec<String>>);
+
+fn load_folders(app: &AppHandle) -> Result<Vec<SharedFolderEntry>, String> {
+    read_json(&data_file(app, SHARED_FOLDERS_FILE)?)
+}
+
+fn save_folders(app: &AppHandle, folders: &[SharedFolderEntry]) -> Result<(), String> {
+    write_json(&data_file(app, SHARED_FOLDERS_FILE)?, folders)
+}
+
+fn lock_folders(state: &AppState) -> Result<std::sync::MutexGuard<'_, ()>, String> {
+    state
+        .shared_folders_lock
+        .lock()
+        .map_err(|_| "failed to lock shared folders".to_string())
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', '=']) {
+        return Err(format!("invalid share name `{name}`"));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_folder(path: &str) -> Result<String, String> {
+    let path = paths::validate_send_source(path)?;
+    if !path.is_dir() {
+        return Err(format!("shared path {} is not a folder", path.display()));
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Saved folders plus the session's. A saved folder that has since been
+/// deleted or become unreadable is left out rather than blocking the listener.
+fn share_maps(app: &AppHandle, session: &[SharedFolder]) -> Result<ShareMaps, String> {
+    let state = app.state::<AppState>();
+    let saved = {
+        let _guard = lock_folders(state.inner())?;
+        load_folders(app)?
+    };
+    let mut shares: BTreeMap<String, String> = BTreeMap::new();
+    let mut allow = BTreeMap::new();
+    for folder in &saved {
+        let addresses: Vec<String> = folder
+            .devices
+            .iter()
+            .flat_map(|device| device.addresses.iter().cloned())
+            .collect();
+        if folder.visibility == ShareVisibility::Devices && addresses.is_empty() {
+            continue;
+        }
+        match validate_folder(&folder.path) {
+            Ok(path) => {
+                shares.insert(folder.name.clone(), path);
+            }
+            Err(err) => {
+                emit_system_log(app, format!("not sharing `{}`: {err}", folder.name));
+                continue;
+            }
+        }
+        if folder.visibility == ShareVisibility::Devices {
+            allow.insert(folder.name.clone(), addresses);
+        }
+    }
+    for folder in session {
+        let name = validate_name(&folder.name)?;
+        if shares
+            .keys()
+            .any(|existing| existing.eq_ignore_ascii_case(&name))
+        {
+            return Err(format!("share name `{name}` is used twice"));
+        }
+        shares.insert(name, validate_folder(&folder.path)?);
+    }
+    Ok((shares, allow))
+}
+
+/// `--share` and `--share-allow` arguments for `listen`.
+pub(crate) fn listen_args(
+    app: &AppHandle,
+    session: &[SharedFolder],
+) -> Result<Vec<String>, String> {
+    let (shares, allow) = share_maps(app, session)?;
+    let mut args = Vec::new();
+    for (name, path) in &shares {
+        args.push("--share".to_string());
+        args.push(format!("{name}={path}"));
+    }
+    for (name, addresses) in allow {
+        args.push("--share-allow".to_string());
+        args.push(format!("{name}={}", addresses.join(",")));
+    }
+    Ok(args)
+}
+
+/// Hands the current folders to a running listener; otherwise they apply
+/// the next time listening starts.
+fn apply_to_listener(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    if !inspect_listen_state(&state)?.running {
+        return Ok(());
+    }
+    let session = state
+        .listen_shares
+        .lock()
+        .map_err(|_| "failed to lock listen shares".to_string())?
+        .clone();
+    let (shares, allow) = share_maps(app, &session)?;
+    cli_control::request(
+        state.inner(),
+        "update",
+        json!({ "shares": shares, "shareAllow": allow }),
+    )?;
+    Ok(())
+}
+
+/// Device names are looked up once, now; plain addresses are kept as is.
+async fn resolve_devices(
+    app: &AppHandle,
+    devices: Vec<String>,
+) -> Result<Vec<AllowedDevice>, String> {
+    let wanted: Vec<String> = devices
+        .into_iter()
+        .map(|device| device.trim().to_string())
+        .filter(|device| !device.is_empty())
+        .collect();
+    let discovered = if wanted
+        .iter()
+        .any(|device| device.parse::<IpAddr>().is_err())
+    {
+        discover_devices(app.clone(), SHARE_DISCOVERY_TIMEOUT_MS).await?
+    } else {
+        Vec::new()
+    };
+    wanted
+        .into_iter()
+        .map(|wanted| {
+            if wanted.parse::<IpAddr>().is_ok() {
+                return Ok(AllowedDevice {
+                    addresses: vec![wanted.clone()],
+                    name: wanted,
+                });
+            }
+            let device = match_device(&discovered, &wanted)
+                .ok_or_else(|| format!("device `{wanted}` was not found on the network"))?;
+            let mut addresses = device.addresses.clone();
+            if !addresses.contains(&device.host) {
+                addresses.push(device.host.clone());
+            }
+            Ok(AllowedDevice {
+                name: device.name.clone(),
+                addresses,
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub(crate) fn list_shared_folders(
+    app: AppHandle,
+    state: State<AppState>,
+) -> Result<Vec<SharedFolderEntry>, String> {
+    let _guard = lock_folders(state.inner())?;
+    load_folders(&app)
+}
+
+/// Shares `path` read-only with `everyone` that passes pairing or only with
+/// `devices` (names from discovery, or addresses). Replaces an earlier share
+/// of the same name; a running listener picks the change up right away.
+#[tauri::command]
+pub(crate) async fn add_shared_folder(
+    app: AppHandle,
+    path: String,
+    visibility: ShareVisibility,
+    devices: Vec<String>,
+    name: Option<String>,
+) -> Result<SharedFolderEntry, String> {
+    let path = validate_folder(&path)?;
+    let name = match name.filter(|name| !name.trim().is_empty()) {
+        Some(name) => validate_name(&name)?,
+        None => validate_name(
+            &Path::new(&path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        )?,
+    };
+    let devices = match visibility {
+        ShareVisibility::Everyone => Vec::new(),
+        ShareVisibility::Devices => {
+            let devices = resolve_devices(&app, devices).await?;
+            if devices.is_empty() {
+                return Err("select at least one device to share with".to_string());
+            }
+            devices
+        }
+    };
+    let entry = SharedFolderEntry {
+        name,
+        path,
+        visibility,
+        devices,
+        added_at_ms: now_ms(),
+    };
+
+    {
+        let state = app.state::<AppState>();
+        let _guard = lock_folders(state.inner())?;
+        let mut folders = load_folders(&app)?;
+        folders.retain(|folder| !folder.name.eq_ignore_ascii_case(&entry.name));
+        folders.push(entry.clone());
+        save_folders(&app, &folders)?;
+    }
+    record_audit(
+        &app,
+        AuditAction::SharedFolderAdded,
+        json!({
+            "name": entry.name,
+            "path": entry.path,
+            "visibility": entry.visibility,
+            "devices": entry.devices.iter().map(|device| &device.name).collect::<Vec<_>>(),
+        }),
+    );
+    apply_to_listener(&app)?;
+    Ok(entry)
+}
+
+#[tauri::command]
+pub(crate) fn remove_shared_folder(app: AppHandle, name: String) -> Result<bool, String> {
+    {
+        let state = app.state::<AppState>();
+        let _guard = lock_folders(state.inner())?;
+        let mut folders = load_folders(&app)?;
+        let before = folders.len();
+        folders.retain(|folder| !folder.name.eq_ignore_ascii_case(name.trim()));
+        if folders.len() == before {
+            return Ok(false);
+        }
+        save_folders(&app, &folders)?;
+    }
+    record_audit(
+        &app,
+        AuditAction::SharedFolderRemoved,
+        json!({ "name": name.trim() }),
+    );
+    apply_to_listener(&app)?;
+    Ok(true)
+}
@@ -0,0 +1,171 @@
+//! Delivery to an SMB share such as a department file server. Windows opens
+//! `\\server\share` paths directly; elsewhere the share is written through
+//! the folder it is mounted on, e.g. `/Volumes/share` or a `cifs` mount.
+
+use crate::delivery::SourceFile;
+use crate::zero_copy::{format_bytes, Output};
+use crate::{scheduler, AppState, CommandResult};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+/// Large writes keep the round trips to the server down.
+const COPY_CHUNK_BYTES: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Added while a file is copied, so a cut-off copy is never taken for the
+/// real file.
+const PARTIAL_SUFFIX: &str = ".part";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SmbTarget {
+    /// `\\server\share\folder` on Windows, the mounted folder elsewhere.
+    path: String,
+}
+
+impl SmbTarget {
+    pub(crate) fn label(&self) -> String {
+        self.path.clone()
+    }
+
+    pub(crate) fn validated(self) -> Result<Self, String> {
+        let path = self.path.trim().to_string();
+        if path.is_empty() {
+            return Err("share path is required".to_string());
+        }
+        if !Path::new(&path).is_absolute() {
+            return Err(if cfg!(windows) {
+                "share path must look like \\\\server\\share".to_string()
+            } else {
+                "share path must be the folder the share is mounted on".to_string()
+            });
+        }
+        Ok(Self { path })
+    }
+}
+
+fn destination(root: &Path, relative_path: &str) -> PathBuf {
+    relative_path
+        .split('/')
+        .fold(root.to_path_buf(), |path, part| path.join(part))
+}
+
+fn copy(
+    app: &AppHandle,
+    output: &mut Output,
+    file: &SourceFile,
+    destination: &Path,
+    send_id: u64,
+) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|err| format!("failed to create folder: {err}"))?;
+    }
+    let mut partial = destination.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    let partial = PathBuf::from(partial);
+    let mut source = File::open(&file.path).map_err(|err| format!("failed to open file: {err}"))?;
+    let mut target =
+        File::create(&partial).map_err(|err| format!("failed to create file: {err}"))?;
+    let started = Instant::now();
+    let mut last_progress = Instant::now() - PROGRESS_INTERVAL;
+    let mut buffer = vec![0; COPY_CHUNK_BYTES];
+    let mut copied = 0u64;
+    let result = loop {
+        scheduler::wait_while_paused(app, send_id);
+        if state.network_halted.load(Ordering::SeqCst) {
+            break Err("send aborted by the kill-switch".to_string());
+        }
+        let read = match source.read(&mut buffer) {
+            Ok(0) => {
+                break target
+                    .sync_all()
+                    .map_err(|err| format!("failed to write file: {err}"))
+            }
+            Ok(read) => read,
+            Err(err) => break Err(format!("failed to read file: {err}")),
+        };
+        if let Err(err) = target.write_all(&buffer[..read]) {
+            break Err(format!("failed to write file: {err}"));
+        }
+        copied += read as u64;
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            output.progress(&file.relative_path, copied, file.size, started);
+        }
+    };
+    drop(target);
+    if let Err(err) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    // Windows does not rename over an existing file.
+    if destination.is_file() {
+        fs::remove_file(destination)
+            .map_err(|err| format!("failed to replace existing file: {err}"))?;
+    }
+    fs::rename(&partial, destination).map_err(|err| format!("failed to rename file: {err}"))?;
+    output.progress(&file.relative_path, copied, file.size.max(copied), started);
+    output.write("\n".to_string());
+    Ok(())
+}
+
+pub(crate) fn send(
+    app: &AppHandle,
+    target: &SmbTarget,
+    files: &[SourceFile],
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let root = Path::new(&target.path);
+    match fs::metadata(root) {
+        Ok(metadata) if metadata.is_dir() => {}
+        Ok(_) => return Err(format!("share path {} is not a folder", target.path)),
+        Err(err) => return Err(format!("failed to open share {}: {err}", target.path)),
+    }
+    copy_to_folder(
+        app,
+        root,
+        &format!("smb: {}", target.label()),
+        files,
+        send_id,
+    )
+}
+
+/// Copies `files` under `root`, creating subfolders as needed and
+/// replacing files of the same name. `summary` names the destination in
+/// the final output line.
+pub(crate) fn copy_to_folder(
+    app: &AppHandle,
+    root: &Path,
+    summary: &str,
+    files: &[SourceFile],
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let mut output = Output::new(app, send_id);
+    let started = Instant::now();
+    for (index, file) in files.iter().enumerate() {
+        output.start_file(index, files);
+        copy(
+            app,
+            &mut output,
+            file,
+            &destination(root, &file.relative_path),
+            send_id,
+        )
+        .map_err(|err| format!("failed to copy {}: {err}", file.relative_path))?;
+    }
+
+    let bytes: u64 = files.iter().map(|file| file.size).sum();
+    let seconds = started.elapsed().as_secs_f64().max(0.001);
+    output.write(format!(
+        "[send] done: files={} bytes={}\n[send] {summary} in {seconds:.2}s ({}/s)\n",
+        files.len(),
+        format_bytes(bytes as f64),
+        format_bytes(bytes as f64 / seconds)
+    ));
+    Ok(output.finish())
+}
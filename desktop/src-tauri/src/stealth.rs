@@ -0,0 +1,76 @@
+//! Stealth listening keeps the port closed until a sender knocks with a
+//! secret shared between the user's devices. The secret is separate from
+//! the pair code, since a knock travels in the clear and a 6-digit code
+//! behind it is found offline in moments. It stays in the OS keychain and
+//! reaches the CLI through its environment, never its command line.
+
+use crate::secrets::{get_secret, store_secret};
+use std::process::Command;
+
+const KNOCK_SECRET: &str = "knock-secret";
+const KNOCK_SECRET_ENV: &str = "LOCAL_SENT_KNOCK_SECRET";
+/// Matches the CLI's minimum.
+const MIN_SECRET_LEN: usize = 16;
+
+fn stored_secret() -> Result<Option<String>, String> {
+    Ok(get_secret(KNOCK_SECRET)?.filter(|secret| !secret.is_empty()))
+}
+
+fn generated_secret() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+fn validate_secret(secret: &str) -> Result<(), String> {
+    if secret.chars().count() < MIN_SECRET_LEN {
+        return Err(format!(
+            "knock secret must be at least {MIN_SECRET_LEN} characters"
+        ));
+    }
+    Ok(())
+}
+
+/// Hands the knock secret to a stealth listener `command`, generating one
+/// the first time.
+pub(crate) fn set_listen_secret(command: &mut Command) -> Result<(), String> {
+    let secret = match stored_secret()? {
+        Some(secret) => secret,
+        None => {
+            let secret = generated_secret();
+            store_secret(KNOCK_SECRET, &secret)?;
+            secret
+        }
+    };
+    command.env(KNOCK_SECRET_ENV, secret);
+    Ok(())
+}
+
+/// Hands the knock secret, when there is one, to a send `command`; the CLI
+/// knocks on its own for discovered stealth listeners.
+pub(crate) fn set_send_secret(command: &mut Command) -> Result<(), String> {
+    if let Some(secret) = stored_secret()? {
+        command.env(KNOCK_SECRET_ENV, secret);
+    }
+    Ok(())
+}
+
+/// The knock secret, to copy to the user's other devices.
+#[tauri::command]
+pub(crate) fn get_knock_secret() -> Result<Option<String>, String> {
+    stored_secret()
+}
+
+/// Stores `secret`, e.g. one copied from another device; `None` generates a
+/// new one. Returns the stored secret.
+#[tauri::command]
+pub(crate) fn set_knock_secret(secret: Option<String>) -> Result<String, String> {
+    let secret = match secret {
+        Some(secret) => {
+            let secret = secret.trim().to_string();
+            validate_secret(&secret)?;
+            secret
+        }
+        None => generated_secret(),
+    };
+    store_secret(KNOCK_SECRET, &secret)?;
+    Ok(secret)
+}
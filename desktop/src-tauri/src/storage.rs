@@ -0,0 +1,99 @@
+use crate::app_profiles::active_profile_dir;
+use crate::AppState;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+/// The app data folder itself, shared by every profile.
+pub(crate) fn shared_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|err| format!("failed to resolve app data directory: {err}"))?;
+    fs::create_dir_all(&dir)
+        .map_err(|err| format!("failed to create app data directory: {err}"))?;
+    Ok(dir)
+}
+
+/// The active profile's folder; the app data folder for the default one.
+pub(crate) fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let root = shared_dir(app)?;
+    let Some(profile_dir) = active_profile_dir(app.state::<AppState>().inner()) else {
+        return Ok(root);
+    };
+    let dir = root.join(profile_dir);
+    fs::create_dir_all(&dir).map_err(|err| format!("failed to create profile directory: {err}"))?;
+    Ok(dir)
+}
+
+pub(crate) fn data_file(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    Ok(data_dir(app)?.join(name))
+}
+
+/// For device-wide files such as the identity and the audit log.
+pub(crate) fn shared_file(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    Ok(shared_dir(app)?.join(name))
+}
+
+pub(crate) fn read_json<T>(path: &Path) -> Result<T, String>
+where
+    T: DeserializeOwned + Default,
+{
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(err) => return Err(format!("failed to read {}: {err}", path.display())),
+    };
+    if raw.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(&raw).map_err(|err| format!("failed to parse {}: {err}", path.display()))
+}
+
+pub(crate) fn write_json<T>(path: &Path, value: &T) -> Result<(), String>
+where
+    T: Serialize + ?Sized,
+{
+    let raw = serde_json::to_string_pretty(value)
+        .map_err(|err| format!("failed to serialize {}: {err}", path.display()))?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, raw)
+        .map_err(|err| format!("failed to write {}: {err}", temp_path.display()))?;
+    fs::rename(&temp_path, path)
+        .map_err(|err| format!("failed to replace {}: {err}", path.display()))
+}
+
+/// Overwrites `path` with zeros before deleting it, so its contents are not
+/// left behind in the freed blocks (on file systems that write in place).
+pub(crate) fn secure_remove(path: &Path) -> Result<(), String> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(());
+    };
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|err| format!("failed to open {}: {err}", path.display()))?;
+    let zeros = vec![0u8; 64 * 1024];
+    let mut remaining = metadata.len();
+    while remaining > 0 {
+        let chunk = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])
+            .map_err(|err| format!("failed to overwrite {}: {err}", path.display()))?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()
+        .map_err(|err| format!("failed to overwrite {}: {err}", path.display()))?;
+    drop(file);
+    fs::remove_file(path).map_err(|err| format!("failed to remove {}: {err}", path.display()))
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -0,0 +1,598 @@
+use crate::{
+    begin_tracked_send, build_send_args, emit_system_log, enforce_network_profile_for_send,
+    finish_tracked_send, kill_switch, outbound_policy, run_cli_capture_streaming_async, AppState,
+    CommandResult, SendRequest,
+};
+use serde::Serialize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use uuid::Uuid;
+
+const TOKEN_HEADER: &str = "x-local-sent-token";
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+/// A producer such as `tar` may pause for a while between writes.
+const BODY_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_SEND_PORT: u16 = 37373;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StreamBridgeInfo {
+    port: u16,
+    /// Must be sent as `X-Local-Sent-Token`; browsers cannot add that header
+    /// cross-origin without a preflight, which the bridge never answers.
+    token: String,
+}
+
+pub(crate) struct StreamBridge {
+    info: StreamBridgeInfo,
+    stop: Arc<AtomicBool>,
+}
+
+struct BridgeRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    content_length: Option<u64>,
+    chunked: bool,
+    expect_continue: bool,
+    token: Option<String>,
+}
+
+impl BridgeRequest {
+    fn param(&self, name: &str) -> Option<String> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn flag(&self, name: &str) -> Option<bool> {
+        self.param(name)
+            .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+    }
+}
+
+/// Body of exactly `remaining` bytes; ending early is an error rather than EOF.
+struct LengthReader<R> {
+    inner: R,
+    remaining: u64,
+}
+
+impl<R: Read> Read for LengthReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+        let limit = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let read = self.inner.read(&mut buf[..limit])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("body ended {} bytes short", self.remaining),
+            ));
+        }
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Decodes `Transfer-Encoding: chunked`, as sent by `curl -T -`. A body that
+/// stops before the terminating zero-length chunk is an error.
+struct ChunkedReader<R> {
+    inner: R,
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> ChunkedReader<R> {
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.inner.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked body ended early",
+            ));
+        }
+        Ok(line.trim_end().to_string())
+    }
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let line = self.read_line()?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+            self.remaining = u64::from_str_radix(size, 16)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid chunk size"))?;
+            if self.remaining == 0 {
+                // Skip trailers up to the final empty line.
+                while !self.read_line()?.is_empty() {}
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let limit = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let read = self.inner.read(&mut buf[..limit])?;
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "chunked body ended early",
+            ));
+        }
+        self.remaining -= read as u64;
+        if self.remaining == 0 && !self.read_line()?.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing chunk terminator",
+            ));
+        }
+        Ok(read)
+    }
+}
+
+fn percent_decode(raw: &str) -> String {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'+' => decoded.push(b' '),
+            b'%' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn read_head(reader: &mut impl BufRead) -> Result<BridgeRequest, String> {
+    let mut total = 0;
+    let mut next_line = |reader: &mut dyn BufRead| -> Result<String, String> {
+        let mut line = String::new();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|err| format!("failed to read request: {err}"))?;
+        total += read;
+        if read == 0 || total > MAX_HEAD_BYTES {
+            return Err("incomplete or oversized request head".to_string());
+        }
+        Ok(line.trim_end().to_string())
+    };
+
+    let request_line = next_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_ascii_uppercase();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    let mut request = BridgeRequest {
+        method,
+        path: path.to_string(),
+        query,
+        content_length: None,
+        chunked: false,
+        expect_continue: false,
+        token: None,
+    };
+    loop {
+        let line = next_line(reader)?;
+        if line.is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                request.content_length = Some(
+                    value
+                        .parse()
+                        .map_err(|_| "invalid Content-Length".to_string())?,
+                )
+            }
+            "transfer-encoding" => request.chunked = value.to_ascii_lowercase().contains("chunked"),
+            "expect" => request.expect_continue = value.eq_ignore_ascii_case("100-continue"),
+            TOKEN_HEADER => request.token = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    Ok(request)
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, body: &serde_json::Value) {
+    let body = body.to_string();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}\n",
+        body.len() + 1
+    );
+    let _ = stream.flush();
+}
+
+fn error_response(stream: &mut TcpStream, status: &str, message: impl Into<String>) {
+    write_response(
+        stream,
+        status,
+        &serde_json::json!({ "success": false, "error": message.into() }),
+    );
+}
+
+fn send_request_from(request: &BridgeRequest) -> Result<SendRequest, String> {
+    let port = match request.param("port") {
+        Some(port) => port
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port > 0)
+            .ok_or_else(|| format!("invalid port `{port}`"))?,
+        None => DEFAULT_SEND_PORT,
+    };
+    Ok(SendRequest {
+        path: "-".to_string(),
+        host: request.param("host"),
+        port,
+        device: request.param("device"),
+        timeout_ms: request
+            .param("timeoutMs")
+            .and_then(|value| value.parse().ok()),
+        pair_code: request.param("pairCode"),
+        tls: request.flag("tls"),
+        tls_insecure: request.flag("tlsInsecure"),
+        tls_fingerprint: request.param("tlsFingerprint"),
+        tls_tofu: request.flag("tlsTofu"),
+        tls_known_hosts: None,
+        knock: request.flag("knock"),
+        streams: None,
+        zero_copy: None,
+        dry_run: None,
+        priority: None,
+        symlinks: None,
+        hardlinks: None,
+        unicode: None,
+        name_collisions: None,
+        atomic: None,
+        note: request.param("note"),
+        tags: request
+            .param("tags")
+            .map(|tags| tags.split(',').map(str::to_string).collect())
+            .unwrap_or_default(),
+        resume: None,
+        job_id: None,
+    })
+}
+
+fn handle_connection(app: AppHandle, mut stream: TcpStream, token: String) {
+    let _ = stream.set_read_timeout(Some(HEAD_TIMEOUT));
+    let Ok(read_half) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(read_half);
+    let request = match read_head(&mut reader) {
+        Ok(request) => request,
+        Err(err) => return error_response(&mut stream, "400 Bad Request", err),
+    };
+
+    if request.path != "/send" {
+        return error_response(&mut stream, "404 Not Found", "use POST or PUT /send");
+    }
+    if request.method != "POST" && request.method != "PUT" {
+        return error_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            "use POST or PUT /send",
+        );
+    }
+    if request.token.as_deref() != Some(token.as_str()) {
+        return error_response(
+            &mut stream,
+            "401 Unauthorized",
+            "missing or wrong X-Local-Sent-Token",
+        );
+    }
+    let Some(name) = request.param("name") else {
+        return error_response(&mut stream, "400 Bad Request", "`name` is required");
+    };
+    if !request.chunked && request.content_length.is_none() {
+        return error_response(
+            &mut stream,
+            "411 Length Required",
+            "send a Content-Length or a chunked body",
+        );
+    }
+
+    let prepared = (|| -> Result<(SendRequest, Vec<String>), String> {
+        kill_switch::ensure_network_allowed(app.state::<AppState>().inner())?;
+        let send_request = send_request_from(&request)?;
+        let mut args = build_send_args(&send_request)?;
+        args.push("--name".to_string());
+        args.push(name.clone());
+        outbound_policy::enforce(&app, &send_request, None)?;
+        tauri::async_runtime::block_on(enforce_network_profile_for_send(&app, &send_request))?;
+        Ok((send_request, args))
+    })();
+    let (send_request, args) = match prepared {
+        Ok(prepared) => prepared,
+        Err(err) => return error_response(&mut stream, "403 Forbidden", err),
+    };
+
+    if request.expect_continue {
+        let _ = stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+    let _ = stream.set_read_timeout(Some(BODY_IDLE_TIMEOUT));
+    let body: Box<dyn Read + Send> = if request.chunked {
+        Box::new(ChunkedReader {
+            inner: reader,
+            remaining: 0,
+            done: false,
+        })
+    } else {
+        Box::new(LengthReader {
+            inner: reader,
+            remaining: request.content_length.unwrap_or(0),
+        })
+    };
+
+    let target = send_request
+        .host
+        .clone()
+        .or_else(|| send_request.device.clone())
+        .map(|target| format!("{target}:{}", send_request.port))
+        .unwrap_or_else(|| format!("auto:{}", send_request.port));
+    // How the send shows in activity, the journal and history; the CLI
+    // still reads `-`.
+    let send_request = SendRequest {
+        path: format!("stdin:{name}"),
+        ..send_request
+    };
+    let send_id = begin_tracked_send(&app, &send_request, target);
+    let started = Instant::now();
+    let output = tauri::async_runtime::block_on(run_cli_capture_streaming_async(
+        app.clone(),
+        args,
+        send_id,
+        Some(body),
+    ));
+    finish_tracked_send(&app, send_id, &send_request, &output, started);
+
+    match output {
+        Ok(CommandResult {
+            success,
+            code,
+            stdout,
+            stderr,
+        }) => write_response(
+            &mut stream,
+            if success { "200 OK" } else { "502 Bad Gateway" },
+            &serde_json::json!({
+                "success": success,
+                "code": code,
+                "stdout": stdout,
+                "stderr": stderr,
+            }),
+        ),
+        Err(err) => error_response(&mut stream, "502 Bad Gateway", err),
+    }
+}
+
+/// Starts the localhost-only bridge, or returns the running one. Bodies are
+/// piped straight into `local-sent send - --name <name>`, e.g.
+/// `tar c dir | curl -T - -H "X-Local-Sent-Token: <token>" "http://127.0.0.1:<port>/send?name=backup.tar&host=<peer>"`.
+#[tauri::command]
+pub(crate) fn start_stream_bridge(
+    app: AppHandle,
+    state: State<AppState>,
+    port: Option<u16>,
+) -> Result<StreamBridgeInfo, String> {
+    let mut guard = state
+        .stream_bridge
+        .lock()
+        .map_err(|_| "failed to lock stream bridge state".to_string())?;
+    if let Some(bridge) = guard.as_ref() {
+        return Ok(bridge.info.clone());
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port.unwrap_or(0)))
+        .map_err(|err| format!("failed to bind stream bridge: {err}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|err| format!("failed to configure stream bridge: {err}"))?;
+    let bound_port = listener
+        .local_addr()
+        .map_err(|err| format!("failed to read stream bridge address: {err}"))?
+        .port();
+
+    let info = StreamBridgeInfo {
+        port: bound_port,
+        token: Uuid::new_v4().simple().to_string(),
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_app = app.clone();
+    let token = info.token.clone();
+    thread::spawn(move || {
+        while !thread_stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = stream.set_nonblocking(false);
+                    let app = thread_app.clone();
+                    let token = token.clone();
+                    thread::spawn(move || handle_connection(app, stream, token));
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL_INTERVAL)
+                }
+                Err(_) => thread::sleep(ACCEPT_POLL_INTERVAL),
+            }
+        }
+    });
+
+    emit_system_log(
+        &app,
+        format!("stream bridge listening on 127.0.0.1:{bound_port}"),
+    );
+    *guard = Some(StreamBridge {
+        info: info.clone(),
+        stop,
+    });
+    Ok(info)
+}
+
+#[tauri::command]
+pub(crate) fn stop_stream_bridge(app: AppHandle, state: State<AppState>) -> Result<(), String> {
+    let bridge = state
+        .stream_bridge
+        .lock()
+        .map_err(|_| "failed to lock stream bridge state".to_string())?
+        .take();
+    if let Some(bridge) = bridge {
+        bridge.stop.store(true, Ordering::SeqCst);
+        emit_system_log(&app, "stream bridge stopped");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read_all(mut reader: impl Read) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        Ok(body)
+    }
+
+    fn chunked(raw: &[u8]) -> ChunkedReader<Cursor<Vec<u8>>> {
+        ChunkedReader {
+            inner: Cursor::new(raw.to_vec()),
+            remaining: 0,
+            done: false,
+        }
+    }
+
+    #[test]
+    fn length_reader_stops_at_the_length() {
+        let reader = LengthReader {
+            inner: Cursor::new(b"hello world".to_vec()),
+            remaining: 5,
+        };
+        assert_eq!(read_all(reader).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn length_reader_rejects_a_short_body() {
+        let reader = LengthReader {
+            inner: Cursor::new(b"hel".to_vec()),
+            remaining: 5,
+        };
+        let err = read_all(reader).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(err.to_string().contains("2 bytes short"));
+    }
+
+    #[test]
+    fn chunked_reader_joins_chunks_and_skips_trailers() {
+        let raw = b"5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nX-Trailer: yes\r\n\r\n";
+        assert_eq!(read_all(chunked(raw)).unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn chunked_reader_rejects_truncated_bodies() {
+        for raw in [&b"5\r\nhel"[..], b"5\r\nhello\r\n", b"5\r\nhello\r\n0\r\n"] {
+            let err = read_all(chunked(raw)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn chunked_reader_rejects_malformed_framing() {
+        for raw in [
+            &b"zz\r\nhello\r\n0\r\n\r\n"[..],
+            b"5\r\nhelloX\r\n0\r\n\r\n",
+        ] {
+            let err = read_all(chunked(raw)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn percent_decode_handles_escapes_and_plus() {
+        assert_eq!(percent_decode("a+b%20c%2Fd"), "a b c/d");
+        assert_eq!(percent_decode("%E2%9C%93"), "\u{2713}");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn read_head_parses_the_request() {
+        let raw = "PUT /send?name=backup%20one.tar&host=10.0.0.2&tls=1 HTTP/1.1\r\n\
+                   Host: 127.0.0.1\r\n\
+                   Transfer-Encoding: chunked\r\n\
+                   Expect: 100-continue\r\n\
+                   X-Local-Sent-Token: secret\r\n\
+                   \r\n\
+                   body";
+        let mut reader = Cursor::new(raw.as_bytes().to_vec());
+        let request = read_head(&mut reader).unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/send");
+        assert_eq!(request.param("name").as_deref(), Some("backup one.tar"));
+        assert_eq!(request.param("host").as_deref(), Some("10.0.0.2"));
+        assert_eq!(request.flag("tls"), Some(true));
+        assert!(request.chunked);
+        assert!(request.expect_continue);
+        assert_eq!(request.token.as_deref(), Some("secret"));
+        assert_eq!(request.content_length, None);
+        assert_eq!(read_all(reader).unwrap(), b"body");
+    }
+
+    #[test]
+    fn read_head_rejects_bad_heads() {
+        let truncated = "POST /send HTTP/1.1\r\nContent-Length: 3\r\n";
+        assert!(read_head(&mut Cursor::new(truncated.as_bytes().to_vec())).is_err());
+
+        let bad_length = "POST /send HTTP/1.1\r\nContent-Length: three\r\n\r\n";
+        assert_eq!(
+            read_head(&mut Cursor::new(bad_length.as_bytes().to_vec())).err(),
+            Some("invalid Content-Length".to_string())
+        );
+
+        let oversized = format!(
+            "POST /send HTTP/1.1\r\nX-Filler: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD_BYTES)
+        );
+        assert!(read_head(&mut Cursor::new(oversized.into_bytes())).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Hand-off to a local Syncthing. Each folder it syncs can be sent to as a
+//! pseudo-device: the files are copied into the folder and Syncthing is
+//! asked to scan them right away rather than when its watcher notices.
+//! The API key comes from the keychain, or from Syncthing's own config.
+
+use crate::delivery::SourceFile;
+use crate::secrets::{get_secret, store_secret};
+use crate::settings::{load_settings, update_settings};
+use crate::{smb, CommandResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Url};
+
+/// Sends whose `device` starts with this go to the Syncthing folder with
+/// the id that follows.
+pub(crate) const DEVICE_PREFIX: &str = "syncthing:";
+const DEFAULT_API_URL: &str = "http://127.0.0.1:8384";
+const API_KEY_SECRET: &str = "syncthing-api-key";
+const API_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct SyncthingSettings {
+    pub(crate) api_url: String,
+}
+
+impl Default for SyncthingSettings {
+    fn default() -> Self {
+        Self {
+            api_url: DEFAULT_API_URL.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SyncthingFolder {
+    id: String,
+    label: String,
+    path: String,
+    paused: bool,
+    /// What to send as the request's `device` to drop files here.
+    device: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SyncthingTarget {
+    pub(crate) folder_id: String,
+}
+
+impl SyncthingTarget {
+    pub(crate) fn label(&self) -> String {
+        format!("{DEVICE_PREFIX}{}", self.folder_id)
+    }
+
+    pub(crate) fn validated(self) -> Result<Self, String> {
+        let folder_id = self.folder_id.trim().to_string();
+        if folder_id.is_empty() {
+            return Err("Syncthing folder id is required".to_string());
+        }
+        Ok(Self { folder_id })
+    }
+}
+
+/// Where Syncthing keeps `config.xml`, newest layout first.
+fn config_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(home) = crate::paths::home_dir() {
+        candidates.push(home.join(".local/state/syncthing/config.xml"));
+        candidates.push(home.join(".config/syncthing/config.xml"));
+        candidates.push(home.join("Library/Application Support/Syncthing/config.xml"));
+        candidates.push(home.join("AppData/Local/Syncthing/config.xml"));
+    }
+    candidates
+}
+
+fn config_api_key(path: &Path) -> Option<String> {
+    let config = std::fs::read_to_string(path).ok()?;
+    let start = config.find("<apikey>")? + "<apikey>".len();
+    let end = start + config[start..].find("</apikey>")?;
+    let key = config[start..end].trim();
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+fn api_key() -> Result<String, String> {
+    if let Some(key) = get_secret(API_KEY_SECRET)? {
+        return Ok(key);
+    }
+    config_candidates()
+        .iter()
+        .find_map(|path| config_api_key(path))
+        .ok_or_else(|| "Syncthing API key not found; set it in the Syncthing settings".to_string())
+}
+
+fn api_get(app: &AppHandle, path: &str) -> Result<Value, String> {
+    let settings = load_settings(app)?.syncthing;
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(API_TIMEOUT))
+        .build()
+        .new_agent();
+    agent
+        .get(format!("{}{path}", settings.api_url))
+        .header("X-API-Key", api_key()?)
+        .call()
+        .map_err(|err| format!("failed to reach Syncthing: {err}"))?
+        .body_mut()
+        .read_json()
+        .map_err(|err| format!("failed to read Syncthing response: {err}"))
+}
+
+fn folders(app: &AppHandle) -> Result<Vec<SyncthingFolder>, String> {
+    let folders = api_get(app, "/rest/config/folders")?;
+    let text = |folder: &Value, key: &str| {
+        folder
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    Ok(folders
+        .as_array()
+        .map(|folders| {
+            folders
+                .iter()
+                .filter_map(|folder| {
+                    let id = text(folder, "id");
+                    if id.is_empty() {
+                        return None;
+                    }
+                    Some(SyncthingFolder {
+                        label: text(folder, "label"),
+                        path: text(folder, "path"),
+                        paused: folder
+                            .get("paused")
+                            .and_then(Value::as_bool)
+                            .unwrap_or(false),
+                        device: format!("{DEVICE_PREFIX}{id}"),
+                        id,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Syncthing expands a leading `~` in folder paths.
+fn local_path(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), crate::paths::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches(['/', '\\'])),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Asks Syncthing to scan what was just copied; it would otherwise pick
+/// the files up when its watcher fires.
+fn request_scan(app: &AppHandle, folder_id: &str, sub: &str) -> Result<(), String> {
+    let settings = load_settings(app)?.syncthing;
+    let agent = ureq::Agent::config_builder()
+        .timeout_global(Some(API_TIMEOUT))
+        .build()
+        .new_agent();
+    agent
+        .post(format!("{}/rest/db/scan", settings.api_url))
+        .query("folder", folder_id)
+        .query("sub", sub)
+        .header("X-API-Key", api_key()?)
+        .send_empty()
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+pub(crate) fn send(
+    app: &AppHandle,
+    target: &SyncthingTarget,
+    files: &[SourceFile],
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let folder = folders(app)?
+        .into_iter()
+        .find(|folder| folder.id == target.folder_id)
+        .ok_or_else(|| format!("Syncthing has no folder {}", target.folder_id))?;
+    let root = local_path(&folder.path);
+    if !root.is_dir() {
+        return Err(format!(
+            "Syncthing folder {} is missing at {}",
+            folder.id,
+            root.display()
+        ));
+    }
+    let name = if folder.label.is_empty() {
+        &folder.id
+    } else {
+        &folder.label
+    };
+    let mut result = smb::copy_to_folder(
+        app,
+        &root,
+        &format!("syncthing: {name} ({})", root.display()),
+        files,
+        send_id,
+    )?;
+    // Every file of one send shares its top-level name.
+    if let Some(sub) = files
+        .first()
+        .and_then(|file| file.relative_path.split('/').next())
+    {
+        if let Err(err) = request_scan(app, &folder.id, sub) {
+            result
+                .stdout
+                .push_str(&format!("[send] syncthing scan not started: {err}\n"));
+        }
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub(crate) async fn list_syncthing_folders(app: AppHandle) -> Result<Vec<SyncthingFolder>, String> {
+    tauri::async_runtime::spawn_blocking(move || folders(&app))
+        .await
+        .map_err(|err| format!("failed to join Syncthing task: {err}"))?
+}
+
+/// `api_key` replaces the stored key when given; `None` keeps it.
+#[tauri::command]
+pub(crate) fn set_syncthing_settings(
+    app: AppHandle,
+    syncthing: SyncthingSettings,
+    api_key: Option<String>,
+) -> Result<SyncthingSettings, String> {
+    let api_url = match syncthing.api_url.trim().trim_end_matches('/') {
+        "" => DEFAULT_API_URL.to_string(),
+        url => url.to_string(),
+    };
+    let parsed = Url::parse(&api_url).map_err(|err| format!("API URL is invalid: {err}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("API URL must start with http:// or https://".to_string());
+    }
+    if let Some(api_key) = api_key
+        .as_deref()
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+    {
+        store_secret(API_KEY_SECRET, api_key)?;
+    }
+    update_settings(&app, |settings| {
+        settings.syncthing = SyncthingSettings { api_url };
+        Ok(())
+    })
+    .map(|settings| settings.syncthing)
+}
@@ -0,0 +1,225 @@
+use crate::AppState;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::menu::{CheckMenuItem, Menu, MenuEvent};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager};
+
+const TRAY_ID: &str = "main";
+const HIDE_FROM_DISCOVERY_ID: &str = "hide-from-discovery";
+const IDLE_TOOLTIP: &str = "Local Sent";
+/// OS surfaces are redrawn at most this often however chatty the CLI is.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+/// A transfer that reported no progress for this long is assumed gone, e.g.
+/// a receive whose sender disconnected.
+const STALE_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+struct TransferProgress {
+    sent: f64,
+    total: f64,
+    updated: Instant,
+}
+
+/// Sums the progress of every running send and receive so the tray
+/// tooltip, the Windows taskbar button and the macOS dock show one figure.
+#[derive(Debug, Default)]
+pub(crate) struct ProgressAggregator {
+    /// Keyed by `send:<id>:<label>` or `recv:<label>`.
+    transfers: HashMap<String, TransferProgress>,
+    last_refresh: Option<Instant>,
+    shown: Option<(usize, u64)>,
+}
+
+fn observe(app: &AppHandle, key: String, sent: u64, total: u64) {
+    let state = app.state::<AppState>();
+    let Ok(mut progress) = state.progress.lock() else {
+        return;
+    };
+    let finished = total > 0 && sent >= total;
+    if finished {
+        if progress.transfers.remove(&key).is_none() {
+            return;
+        }
+    } else {
+        progress.transfers.insert(
+            key,
+            TransferProgress {
+                sent: sent as f64,
+                total: total as f64,
+                updated: Instant::now(),
+            },
+        );
+    }
+    // A finished transfer is always shown, later progress may never come.
+    refresh(app, &mut progress, finished);
+}
+
+/// Feeds a progress update of a send, from the CLI or the zero-copy path.
+pub(crate) fn observe_send_progress(
+    app: &AppHandle,
+    send_id: u64,
+    label: &str,
+    sent: u64,
+    total: u64,
+) {
+    observe(app, format!("send:{send_id}:{label}"), sent, total);
+}
+
+/// Feeds a progress update of one of the listener's receives.
+pub(crate) fn observe_receive_progress(app: &AppHandle, label: &str, sent: u64, total: u64) {
+    observe(app, format!("recv:{label}"), sent, total);
+}
+
+/// Drops whatever a finished send left behind, e.g. after it failed midway.
+pub(crate) fn finish_send(app: &AppHandle, send_id: u64) {
+    forget(app, &format!("send:{send_id}:"));
+}
+
+/// Drops all receives, e.g. when the listener stops.
+pub(crate) fn finish_receives(app: &AppHandle) {
+    forget(app, "recv:");
+}
+
+fn forget(app: &AppHandle, key_prefix: &str) {
+    let state = app.state::<AppState>();
+    let Ok(mut progress) = state.progress.lock() else {
+        return;
+    };
+    progress
+        .transfers
+        .retain(|key, _| !key.starts_with(key_prefix));
+    refresh(app, &mut progress, true);
+}
+
+fn refresh(app: &AppHandle, progress: &mut ProgressAggregator, force: bool) {
+    if !force
+        && progress
+            .last_refresh
+            .is_some_and(|last| last.elapsed() < REFRESH_INTERVAL)
+    {
+        return;
+    }
+    progress.last_refresh = Some(Instant::now());
+    progress
+        .transfers
+        .retain(|_, transfer| transfer.updated.elapsed() < STALE_AFTER);
+
+    let count = progress.transfers.len();
+    let (sent, total) = progress
+        .transfers
+        .values()
+        .fold((0.0, 0.0), |(sent, total), transfer| {
+            (sent + transfer.sent, total + transfer.total)
+        });
+    let percent = if total > 0.0 {
+        (sent / total * 100.0).clamp(0.0, 100.0) as u64
+    } else {
+        0
+    };
+    if progress.shown == Some((count, percent)) {
+        return;
+    }
+    progress.shown = Some((count, percent));
+    apply(app, count, percent);
+}
+
+/// Best effort: surfaces a platform lacks are skipped silently.
+fn apply(app: &AppHandle, count: usize, percent: u64) {
+    let tooltip = match count {
+        0 => IDLE_TOOLTIP.to_string(),
+        1 => format!("{IDLE_TOOLTIP}: {percent}%"),
+        _ => format!("{IDLE_TOOLTIP}: {count} transfers, {percent}%"),
+    };
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        let _ = tray.set_tooltip(Some(tooltip));
+    }
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+    let _ = window.set_progress_bar(ProgressBarState {
+        status: Some(if count == 0 {
+            ProgressBarStatus::None
+        } else {
+            ProgressBarStatus::Normal
+        }),
+        progress: (count > 0).then_some(percent),
+    });
+    #[cfg(target_os = "macos")]
+    let _ = window.set_badge_label((count > 0).then(|| format!("{percent}%")));
+}
+
+/// The tray's "Hide from discovery" item, to mirror changes made elsewhere.
+struct DiscoveryMenuItem(CheckMenuItem<tauri::Wry>);
+
+/// Adds the tray icon; clicking it brings a minimized window back, and its
+/// menu toggles "Hide from discovery".
+pub(crate) fn create_tray(app: &AppHandle) -> tauri::Result<()> {
+    let hidden =
+        crate::settings::load_settings(app).is_ok_and(|settings| settings.hidden_from_discovery);
+    let hide_item = CheckMenuItem::with_id(
+        app,
+        HIDE_FROM_DISCOVERY_ID,
+        "Hide from discovery",
+        true,
+        hidden,
+        None::<&str>,
+    )?;
+    let menu = Menu::with_items(app, &[&hide_item])?;
+    app.manage(DiscoveryMenuItem(hide_item));
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(IDLE_TOOLTIP)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                if let Some(window) = tray.app_handle().get_webview_window("main") {
+                    let _ = window.unminimize();
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    if event.id() != HIDE_FROM_DISCOVERY_ID {
+        return;
+    }
+    // The item has already flipped its own check mark.
+    let hidden = app
+        .state::<DiscoveryMenuItem>()
+        .0
+        .is_checked()
+        .unwrap_or(false);
+    // Menu events arrive on the main thread; the listener may take a while.
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(err) = crate::apply_discovery_visibility(&app, hidden) {
+            crate::emit_system_log(
+                &app,
+                format!("failed to change discovery visibility: {err}"),
+            );
+            set_discovery_hidden(&app, !hidden);
+        }
+    });
+}
+
+/// Mirrors the discovery setting in the tray menu.
+pub(crate) fn set_discovery_hidden(app: &AppHandle, hidden: bool) {
+    if let Some(item) = app.try_state::<DiscoveryMenuItem>() {
+        let _ = item.0.set_checked(hidden);
+    }
+}
@@ -0,0 +1,332 @@
+use crate::managed_config;
+use crate::settings::{load_settings, update_settings};
+use crate::storage::{data_file, now_ms, read_json, write_json};
+use crate::{emit_system_log, kill_switch, AppState, CommandResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const TELEMETRY_QUEUE_FILE: &str = "telemetry-queue.json";
+/// Set for official builds; without it reports are only ever queued.
+const TELEMETRY_ENDPOINT: Option<&str> = option_env!("LOCAL_SENT_TELEMETRY_URL");
+const TASK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const REPORT_PERIOD_MS: u64 = 24 * 60 * 60 * 1000;
+/// How long a finished report waits in the queue, for `preview_telemetry`,
+/// before it is sent.
+const REVIEW_DELAY_MS: u64 = 24 * 60 * 60 * 1000;
+const MAX_QUEUED_REPORTS: usize = 30;
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Strictly opt-in; off unless the user turns it on.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct Telemetry {
+    pub(crate) enabled: bool,
+}
+
+/// Everything a report contains. Only counts and fixed error kinds: never
+/// filenames, paths, addresses, device names or error messages.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TelemetryReport {
+    period_start_ms: u64,
+    period_end_ms: u64,
+    app_version: String,
+    os: String,
+    arch: String,
+    sends: u64,
+    failed_sends: u64,
+    receives: u64,
+    failed_receives: u64,
+    /// Failures by kind, e.g. `timeout` or `pairCode`.
+    error_kinds: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TelemetryQueue {
+    /// Counts of the period in progress.
+    current: Option<TelemetryReport>,
+    queued: Vec<QueuedReport>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QueuedReport {
+    queued_at_ms: u64,
+    report: TelemetryReport,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TelemetryPreview {
+    enabled: bool,
+    /// `None` when this build has no telemetry endpoint.
+    endpoint: Option<&'static str>,
+    /// Finished reports with the time each will be sent.
+    queued: Vec<PreviewReport>,
+    current: Option<TelemetryReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreviewReport {
+    sends_at_ms: u64,
+    report: TelemetryReport,
+}
+
+fn new_report(start_ms: u64) -> TelemetryReport {
+    TelemetryReport {
+        period_start_ms: start_ms,
+        period_end_ms: start_ms,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        ..TelemetryReport::default()
+    }
+}
+
+/// Maps an error message onto a fixed kind; the message itself is never
+/// kept.
+fn error_kind(message: &str) -> &'static str {
+    let message = message.to_lowercase();
+    let kinds: &[(&str, &[&str])] = &[
+        ("pairCode", &["pair code"]),
+        ("tls", &["tls", "certificate", "fingerprint"]),
+        ("timeout", &["timed out", "timeout", "stalled"]),
+        ("refused", &["refused"]),
+        (
+            "unreachable",
+            &["unreachable", "no route", "not found on the network"],
+        ),
+        ("reset", &["reset", "broken pipe", "closed"]),
+        ("diskFull", &["no space", "disk full", "enospc"]),
+        ("permission", &["permission", "access is denied", "eacces"]),
+        ("checksum", &["sha-256", "sha256", "checksum", "mismatch"]),
+        ("rejected", &["rejected", "declined"]),
+        ("cancelled", &["cancel", "aborted"]),
+    ];
+    kinds
+        .iter()
+        .find(|(_, needles)| needles.iter().any(|needle| message.contains(needle)))
+        .map(|(kind, _)| *kind)
+        .unwrap_or("other")
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    load_settings(app).is_ok_and(|settings| settings.telemetry.enabled)
+}
+
+fn load_queue(app: &AppHandle) -> Result<TelemetryQueue, String> {
+    read_json(&data_file(app, TELEMETRY_QUEUE_FILE)?)
+}
+
+fn save_queue(app: &AppHandle, queue: &TelemetryQueue) -> Result<(), String> {
+    write_json(&data_file(app, TELEMETRY_QUEUE_FILE)?, queue)
+}
+
+/// Applies `change` to the current period's counts while telemetry is on.
+fn count(app: &AppHandle, change: impl FnOnce(&mut TelemetryReport)) {
+    if !enabled(app) {
+        return;
+    }
+    let state = app.state::<AppState>();
+    let Ok(_guard) = state.telemetry_lock.lock() else {
+        return;
+    };
+    let Ok(mut queue) = load_queue(app) else {
+        return;
+    };
+    let now = now_ms();
+    let report = queue.current.get_or_insert_with(|| new_report(now));
+    change(report);
+    report.period_end_ms = now;
+    let _ = save_queue(app, &queue);
+}
+
+pub(crate) fn count_send(app: &AppHandle, result: &Result<CommandResult, String>) {
+    let error = match result {
+        Ok(output) if output.success => None,
+        Ok(output) => Some(output.stderr.as_str()),
+        Err(err) => Some(err.as_str()),
+    };
+    count(app, |report| {
+        report.sends += 1;
+        if let Some(error) = error {
+            report.failed_sends += 1;
+            *report
+                .error_kinds
+                .entry(error_kind(error).to_string())
+                .or_default() += 1;
+        }
+    });
+}
+
+pub(crate) fn count_receive(app: &AppHandle, error: Option<&str>) {
+    count(app, |report| {
+        report.receives += 1;
+        if let Some(error) = error {
+            report.failed_receives += 1;
+            *report
+                .error_kinds
+                .entry(error_kind(error).to_string())
+                .or_default() += 1;
+        }
+    });
+}
+
+/// Closes the current period once it is a day old and returns the queued
+/// reports whose review delay has passed, by `queued_at_ms`.
+fn roll_over(app: &AppHandle, now: u64) -> Result<Vec<u64>, String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .telemetry_lock
+        .lock()
+        .map_err(|_| "failed to lock telemetry".to_string())?;
+    let mut queue = load_queue(app)?;
+    let period_over = queue
+        .current
+        .as_ref()
+        .is_some_and(|report| now.saturating_sub(report.period_start_ms) >= REPORT_PERIOD_MS);
+    if period_over {
+        let mut report = queue.current.take().expect("checked above");
+        report.period_end_ms = now;
+        queue.queued.push(QueuedReport {
+            queued_at_ms: now,
+            report,
+        });
+        let overflow = queue.queued.len().saturating_sub(MAX_QUEUED_REPORTS);
+        queue.queued.drain(..overflow);
+        save_queue(app, &queue)?;
+    }
+    Ok(queue
+        .queued
+        .iter()
+        .filter(|queued| now.saturating_sub(queued.queued_at_ms) >= REVIEW_DELAY_MS)
+        .map(|queued| queued.queued_at_ms)
+        .collect())
+}
+
+fn run_pass(app: &AppHandle) -> Result<(), String> {
+    if !enabled(app) {
+        return Ok(());
+    }
+    let due = roll_over(app, now_ms())?;
+    let Some(endpoint) = TELEMETRY_ENDPOINT else {
+        return Ok(());
+    };
+    // Waits for the next pass while the network kill switch is on.
+    if kill_switch::ensure_network_allowed(app.state::<AppState>().inner()).is_err() {
+        return Ok(());
+    }
+
+    for queued_at_ms in due {
+        // Re-read: the user may have discarded it meanwhile.
+        let Some(report) = load_queue(app)?
+            .queued
+            .into_iter()
+            .find(|queued| queued.queued_at_ms == queued_at_ms)
+            .map(|queued| queued.report)
+        else {
+            continue;
+        };
+        if !enabled(app) {
+            return Ok(());
+        }
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(SEND_TIMEOUT))
+            .build()
+            .new_agent();
+        agent
+            .post(endpoint)
+            .send_json(&report)
+            .map_err(|err| format!("failed to send telemetry: {err}"))?;
+
+        let state = app.state::<AppState>();
+        let _guard = state
+            .telemetry_lock
+            .lock()
+            .map_err(|_| "failed to lock telemetry".to_string())?;
+        let mut queue = load_queue(app)?;
+        queue
+            .queued
+            .retain(|queued| queued.queued_at_ms != queued_at_ms);
+        save_queue(app, &queue)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn spawn_task(app: AppHandle) {
+    thread::spawn(move || loop {
+        let state = app.state::<AppState>();
+        if state.shutdown_cleanup_started.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Err(err) = run_pass(&app) {
+            emit_system_log(&app, err);
+        }
+        thread::sleep(TASK_INTERVAL);
+    });
+}
+
+fn clear_queue(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let _guard = state
+        .telemetry_lock
+        .lock()
+        .map_err(|_| "failed to lock telemetry".to_string())?;
+    save_queue(app, &TelemetryQueue::default())
+}
+
+/// Turning telemetry off also drops everything counted or queued so far.
+#[tauri::command]
+pub(crate) fn set_telemetry(app: AppHandle, enabled: bool) -> Result<Telemetry, String> {
+    if managed_config::load()?.telemetry.is_some() {
+        return Err("telemetry is locked by the managed configuration".to_string());
+    }
+    let saved = update_settings(&app, |settings| {
+        settings.telemetry.enabled = enabled;
+        Ok(())
+    })
+    .map(|settings| settings.telemetry)?;
+    if !enabled {
+        clear_queue(&app)?;
+    }
+    Ok(saved)
+}
+
+/// Exactly what would be sent, and when. Nothing leaves the machine until
+/// a report has been queued for a day.
+#[tauri::command]
+pub(crate) fn preview_telemetry(app: AppHandle) -> Result<TelemetryPreview, String> {
+    let queue = {
+        let state = app.state::<AppState>();
+        let _guard = state
+            .telemetry_lock
+            .lock()
+            .map_err(|_| "failed to lock telemetry".to_string())?;
+        load_queue(&app)?
+    };
+    Ok(TelemetryPreview {
+        enabled: enabled(&app),
+        endpoint: TELEMETRY_ENDPOINT,
+        queued: queue
+            .queued
+            .into_iter()
+            .map(|queued| PreviewReport {
+                sends_at_ms: queued.queued_at_ms + REVIEW_DELAY_MS,
+                report: queued.report,
+            })
+            .collect(),
+        current: queue.current,
+    })
+}
+
+/// Drops the queued reports and the current counts without sending them.
+#[tauri::command]
+pub(crate) fn discard_telemetry(app: AppHandle) -> Result<(), String> {
+    clear_queue(&app)
+}
@@ -0,0 +1,162 @@
+use crate::events::{emit_app_event, AppEvent};
+use crate::settings::{load_settings, update_settings};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// An accepted receive that has shown no progress for this long no longer
+/// counts against the concurrency limit (the sender most likely vanished).
+const STALE_RECEIVE_AFTER: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ReceiveLimits {
+    /// Incoming transfer requests allowed per sender IP per minute; 0 disables.
+    pub(crate) per_ip_per_minute: u32,
+    /// Receives allowed to run at once; 0 disables.
+    pub(crate) max_concurrent: u32,
+}
+
+impl Default for ReceiveLimits {
+    fn default() -> Self {
+        Self {
+            per_ip_per_minute: 30,
+            max_concurrent: 4,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct ReceiveThrottle {
+    requests_by_ip: HashMap<String, VecDeque<Instant>>,
+    /// Accepted receives keyed by transfer id, with their relative path and
+    /// last progress time.
+    active_receives: HashMap<u64, (String, Instant)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RateLimitedPayload {
+    transfer_id: u64,
+    from: String,
+    path: String,
+    reason: &'static str,
+    limit: u32,
+}
+
+/// Decides whether an incoming transfer request may proceed. Every request
+/// reaches the backend through `--confirm-each`, so this is where per-IP and
+/// concurrency limits are enforced; connection floods and pair code guesses
+/// are refused earlier, by the CLI listener. Rejected requests emit
+/// `rateLimited`.
+pub(crate) fn admit_incoming(app: &AppHandle, transfer_id: u64, from: &str, path: &str) -> bool {
+    let limits = load_settings(app)
+        .map(|settings| settings.receive_limits)
+        .unwrap_or_default();
+    let state = app.state::<AppState>();
+    let Ok(mut throttle) = state.receive_throttle.lock() else {
+        return true;
+    };
+    let now = Instant::now();
+
+    let requests = throttle.requests_by_ip.entry(from.to_string()).or_default();
+    while requests
+        .front()
+        .is_some_and(|at| now.duration_since(*at) > RATE_WINDOW)
+    {
+        requests.pop_front();
+    }
+    requests.push_back(now);
+    let request_count = requests.len();
+
+    throttle
+        .active_receives
+        .retain(|_, (_, last_progress)| now.duration_since(*last_progress) < STALE_RECEIVE_AFTER);
+    throttle
+        .requests_by_ip
+        .retain(|_, requests| !requests.is_empty());
+
+    let rejection =
+        if limits.per_ip_per_minute > 0 && request_count > limits.per_ip_per_minute as usize {
+            Some(("perIpRate", limits.per_ip_per_minute))
+        } else if limits.max_concurrent > 0
+            && throttle.active_receives.len() >= limits.max_concurrent as usize
+        {
+            Some(("maxConcurrent", limits.max_concurrent))
+        } else {
+            None
+        };
+    drop(throttle);
+
+    let Some((reason, limit)) = rejection else {
+        return true;
+    };
+    emit_app_event(
+        app,
+        AppEvent::RateLimited(RateLimitedPayload {
+            transfer_id,
+            from: from.to_string(),
+            path: path.to_string(),
+            reason,
+            limit,
+        }),
+    );
+    false
+}
+
+pub(crate) fn receive_started(state: &AppState, transfer_id: u64, path: &str) {
+    if let Ok(mut throttle) = state.receive_throttle.lock() {
+        throttle
+            .active_receives
+            .insert(transfer_id, (path.to_string(), Instant::now()));
+    }
+}
+
+/// Fed from `[recv <path>]` progress lines so finished receives free their
+/// concurrency slot. The lines carry no transfer id; when several receives
+/// share a path, progress touches all of them and completion frees the
+/// oldest.
+pub(crate) fn observe_receive_progress(state: &AppState, path: &str, complete: bool) {
+    let Ok(mut throttle) = state.receive_throttle.lock() else {
+        return;
+    };
+    let now = Instant::now();
+    if complete {
+        let finished = throttle
+            .active_receives
+            .iter()
+            .filter(|(_, (active_path, _))| active_path == path)
+            .map(|(id, _)| *id)
+            .min();
+        if let Some(id) = finished {
+            throttle.active_receives.remove(&id);
+        }
+    } else {
+        for (active_path, last_progress) in throttle.active_receives.values_mut() {
+            if active_path == path {
+                *last_progress = now;
+            }
+        }
+    }
+}
+
+pub(crate) fn reset(state: &AppState) {
+    if let Ok(mut throttle) = state.receive_throttle.lock() {
+        *throttle = ReceiveThrottle::default();
+    }
+}
+
+#[tauri::command]
+pub(crate) fn set_receive_limits(
+    app: AppHandle,
+    limits: ReceiveLimits,
+) -> Result<ReceiveLimits, String> {
+    update_settings(&app, |settings| {
+        settings.receive_limits = limits;
+        Ok(())
+    })
+    .map(|settings| settings.receive_limits)
+}
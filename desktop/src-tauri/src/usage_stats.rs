@@ -0,0 +1,205 @@
+use crate::activity_search::with_index;
+use crate::storage::now_ms;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+/// Devices beyond these are folded into the totals only.
+const TOP_DEVICES: i64 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum UsagePeriod {
+    /// The last 7 days.
+    Week,
+    /// The last 30 days.
+    Month,
+    /// The last 365 days.
+    Year,
+    All,
+}
+
+impl UsagePeriod {
+    fn since_ms(self) -> u64 {
+        let days = match self {
+            UsagePeriod::Week => 7,
+            UsagePeriod::Month => 30,
+            UsagePeriod::Year => 365,
+            UsagePeriod::All => return 0,
+        };
+        now_ms().saturating_sub(days * DAY_MS)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageTotals {
+    transfers: u64,
+    failed: u64,
+    bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeviceUsage {
+    /// Host or device name for sends, the sender's address for receives.
+    peer: String,
+    sent: UsageTotals,
+    received: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DayUsage {
+    /// Local date, `YYYY-MM-DD`; days without transfers are left out.
+    day: String,
+    sent: UsageTotals,
+    received: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct UsageStats {
+    period: UsagePeriod,
+    since_ms: u64,
+    sent: UsageTotals,
+    received: UsageTotals,
+    /// Failed sends out of all sends; receives are only recorded once saved.
+    failure_rate: f64,
+    /// Over successful sends, whose duration is known.
+    average_bytes_per_sec: Option<f64>,
+    /// Busiest first, up to 20.
+    by_device: Vec<DeviceUsage>,
+    by_day: Vec<DayUsage>,
+}
+
+fn query_error(err: rusqlite::Error) -> String {
+    format!("failed to read usage statistics: {err}")
+}
+
+/// Columns: `sent transfers, sent failed, sent bytes`, then the same for
+/// received, starting at `offset`.
+fn totals(row: &Row, offset: usize) -> rusqlite::Result<(UsageTotals, UsageTotals)> {
+    let total = |index: usize| -> rusqlite::Result<u64> {
+        Ok(row
+            .get::<_, Option<i64>>(offset + index)?
+            .unwrap_or(0)
+            .max(0) as u64)
+    };
+    Ok((
+        UsageTotals {
+            transfers: total(0)?,
+            failed: total(1)?,
+            bytes: total(2)?,
+        },
+        UsageTotals {
+            transfers: total(3)?,
+            failed: total(4)?,
+            bytes: total(5)?,
+        },
+    ))
+}
+
+const TOTAL_COLUMNS: &str = "
+    SUM(direction = 'send'),
+    SUM(direction = 'send' AND NOT success),
+    SUM(CASE WHEN direction = 'send' AND success THEN bytes END),
+    SUM(direction = 'receive'),
+    SUM(direction = 'receive' AND NOT success),
+    SUM(CASE WHEN direction = 'receive' AND success THEN bytes END)
+";
+
+fn usage_stats(connection: &Connection, period: UsagePeriod) -> Result<UsageStats, String> {
+    let since_ms = period.since_ms();
+    let since = since_ms as i64;
+
+    let ((sent, received), average_bytes_per_sec) = connection
+        .query_row(
+            &format!(
+                "SELECT {TOTAL_COLUMNS},
+                    SUM(CASE WHEN direction = 'send' AND success AND duration_ms > 0
+                        THEN bytes END),
+                    SUM(CASE WHEN direction = 'send' AND success AND bytes IS NOT NULL
+                        THEN duration_ms END)
+                 FROM transfers WHERE at_ms >= ?1"
+            ),
+            params![since],
+            |row| {
+                let timed_bytes: Option<i64> = row.get(6)?;
+                let timed_ms: Option<i64> = row.get(7)?;
+                let speed = match (timed_bytes, timed_ms) {
+                    (Some(bytes), Some(ms)) if ms > 0 => Some(bytes as f64 * 1000.0 / ms as f64),
+                    _ => None,
+                };
+                Ok((totals(row, 0)?, speed))
+            },
+        )
+        .map_err(query_error)?;
+
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT peer, {TOTAL_COLUMNS} FROM transfers
+             WHERE at_ms >= ?1 AND peer IS NOT NULL AND peer != ''
+             GROUP BY peer
+             ORDER BY COALESCE(SUM(bytes), 0) DESC, COUNT(*) DESC
+             LIMIT ?2"
+        ))
+        .map_err(query_error)?;
+    let by_device = statement
+        .query_map(params![since, TOP_DEVICES], |row| {
+            let (sent, received) = totals(row, 1)?;
+            Ok(DeviceUsage {
+                peer: row.get(0)?,
+                sent,
+                received,
+            })
+        })
+        .map_err(query_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(query_error)?;
+
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT date(at_ms / 1000, 'unixepoch', 'localtime') AS day, {TOTAL_COLUMNS}
+             FROM transfers WHERE at_ms >= ?1
+             GROUP BY day ORDER BY day"
+        ))
+        .map_err(query_error)?;
+    let by_day = statement
+        .query_map(params![since], |row| {
+            let (sent, received) = totals(row, 1)?;
+            Ok(DayUsage {
+                day: row.get(0)?,
+                sent,
+                received,
+            })
+        })
+        .map_err(query_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(query_error)?;
+
+    let failure_rate = if sent.transfers == 0 {
+        0.0
+    } else {
+        sent.failed as f64 / sent.transfers as f64
+    };
+    Ok(UsageStats {
+        period,
+        since_ms,
+        sent,
+        received,
+        failure_rate,
+        average_bytes_per_sec,
+        by_device,
+        by_day,
+    })
+}
+
+/// Totals and breakdowns for a usage dashboard, computed in the activity
+/// index so the UI never loads raw history. Covers every transfer indexed
+/// so far, including ones the capped history files have dropped.
+#[tauri::command]
+pub(crate) fn get_usage_stats(app: AppHandle, period: UsagePeriod) -> Result<UsageStats, String> {
+    with_index(&app, |connection| usage_stats(connection, period))
+}
@@ -0,0 +1,176 @@
+use crate::settings::update_settings;
+use crate::storage::now_ms;
+use serde::{Deserialize, Serialize};
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Progress lines come several times a second; anything shorter than this
+/// would flag a send waiting on a slow disk.
+const MIN_STALL_SECS: u64 = 10;
+
+/// Limits applied to CLI children, kept in the app settings. Zero turns a
+/// limit off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct CommandTimeouts {
+    pub(crate) discover_secs: u64,
+    /// Off by default: a large send can legitimately run for hours.
+    pub(crate) send_secs: u64,
+    /// A send that has started moving bytes and then prints nothing for
+    /// this long is considered hung.
+    pub(crate) stall_secs: u64,
+    /// How often a stalled send is restarted; the CLI resumes from what the
+    /// receiver already has.
+    pub(crate) stall_retries: u32,
+}
+
+impl Default for CommandTimeouts {
+    fn default() -> Self {
+        Self {
+            discover_secs: 30,
+            send_secs: 0,
+            stall_secs: 60,
+            stall_retries: 0,
+        }
+    }
+}
+
+fn limit(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+impl CommandTimeouts {
+    pub(crate) fn discover(&self) -> Option<Duration> {
+        limit(self.discover_secs)
+    }
+
+    pub(crate) fn send(&self) -> Option<Duration> {
+        limit(self.send_secs)
+    }
+
+    pub(crate) fn stall(&self) -> Option<Duration> {
+        limit(self.stall_secs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TransferStalledPayload {
+    pub(crate) send_id: u64,
+    pub(crate) idle_secs: u64,
+    /// 1 for the first stall of a send.
+    pub(crate) attempt: u32,
+    /// Whether the send is restarted automatically; otherwise the UI offers
+    /// a retry.
+    pub(crate) retrying: bool,
+}
+
+/// Shared between a child's output readers and `wait`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Heartbeat {
+    last_output_ms: Arc<AtomicU64>,
+    transferring: Arc<AtomicBool>,
+}
+
+impl Heartbeat {
+    pub(crate) fn beat(&self, progress: bool) {
+        self.last_output_ms.store(now_ms(), Ordering::SeqCst);
+        if progress {
+            self.transferring.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Time since the last output, once a transfer is under way.
+    fn idle(&self) -> Option<Duration> {
+        if !self.transferring.load(Ordering::SeqCst) {
+            return None;
+        }
+        let last = self.last_output_ms.load(Ordering::SeqCst);
+        Some(Duration::from_millis(now_ms().saturating_sub(last)))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Hang {
+    TimedOut(Duration),
+    Stalled(Duration),
+}
+
+impl Hang {
+    pub(crate) fn message(&self, command: &str) -> String {
+        match self {
+            Hang::TimedOut(limit) => {
+                format!("{command} timed out after {}s", limit.as_secs())
+            }
+            Hang::Stalled(idle) => {
+                format!("{command} stalled: no output for {}s", idle.as_secs())
+            }
+        }
+    }
+}
+
+pub(crate) enum Waited {
+    Exited(ExitStatus),
+    /// The child is still running; the caller decides how to stop it.
+    Hung(Hang),
+}
+
+/// `child.wait()` with a deadline and stall detection. `paused` reports
+/// when the scheduler has stopped the child on purpose, which neither
+/// counts towards a stall nor towards the deadline.
+pub(crate) fn wait(
+    child: &mut Child,
+    deadline: Option<Duration>,
+    stall: Option<Duration>,
+    heartbeat: &Heartbeat,
+    paused: impl Fn() -> bool,
+) -> Result<Waited, String> {
+    let started = Instant::now();
+    let mut paused_for = Duration::ZERO;
+    loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|err| format!("failed to wait CLI process: {err}"))?
+        {
+            return Ok(Waited::Exited(status));
+        }
+        if paused() {
+            paused_for += POLL_INTERVAL;
+            heartbeat.beat(false);
+        } else {
+            if let Some(limit) = deadline {
+                if started.elapsed().saturating_sub(paused_for) >= limit {
+                    return Ok(Waited::Hung(Hang::TimedOut(limit)));
+                }
+            }
+            if let (Some(stall), Some(idle)) = (stall, heartbeat.idle()) {
+                if idle >= stall {
+                    return Ok(Waited::Hung(Hang::Stalled(idle)));
+                }
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[tauri::command]
+pub(crate) fn set_command_timeouts(
+    app: AppHandle,
+    timeouts: CommandTimeouts,
+) -> Result<CommandTimeouts, String> {
+    if timeouts.stall_secs > 0 && timeouts.stall_secs < MIN_STALL_SECS {
+        return Err(format!(
+            "stall timeout must be 0 (off) or at least {MIN_STALL_SECS}s"
+        ));
+    }
+    update_settings(&app, |settings| {
+        settings.command_timeouts = timeouts;
+        Ok(())
+    })
+    .map(|settings| settings.command_timeouts)
+}
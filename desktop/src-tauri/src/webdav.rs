@@ -0,0 +1,100 @@
+//! Receiving straight into a WebDAV folder such as Nextcloud. The listener
+//! uploads each file as it arrives instead of writing to the receive
+//! folder; the password stays in the OS keychain and reaches the CLI
+//! through its environment, never its command line.
+
+use crate::secrets::{get_secret, store_secret};
+use crate::settings::{load_settings, update_settings};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+use tauri::AppHandle;
+
+const PASSWORD_KEY: &str = "webdav-password";
+const PASSWORD_ENV: &str = "LOCAL_SENT_WEBDAV_PASSWORD";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct WebDavSettings {
+    /// Upload received files instead of saving them locally; applies from
+    /// the next `start_listen`.
+    pub(crate) enabled: bool,
+    /// Folder URL, e.g. `https://cloud.example.com/remote.php/dav/files/me/Inbox`.
+    pub(crate) url: String,
+    /// Empty sends no credentials.
+    pub(crate) user: String,
+}
+
+fn enabled_settings(app: &AppHandle) -> Option<WebDavSettings> {
+    load_settings(app)
+        .ok()
+        .map(|settings| settings.webdav)
+        .filter(|webdav| webdav.enabled)
+}
+
+/// `--webdav` and `--webdav-user` for `listen` while receiving to WebDAV
+/// is on.
+pub(crate) fn listen_args(app: &AppHandle) -> Vec<String> {
+    let Some(webdav) = enabled_settings(app) else {
+        return Vec::new();
+    };
+    let mut args = vec!["--webdav".to_string(), webdav.url];
+    if !webdav.user.is_empty() {
+        args.push("--webdav-user".to_string());
+        args.push(webdav.user);
+    }
+    args
+}
+
+/// Hands the stored password to the listener `command`.
+pub(crate) fn set_listen_password(app: &AppHandle, command: &mut Command) -> Result<(), String> {
+    if enabled_settings(app).is_some_and(|webdav| !webdav.user.is_empty()) {
+        if let Some(password) = get_secret(PASSWORD_KEY)? {
+            command.env(PASSWORD_ENV, password);
+        }
+    }
+    Ok(())
+}
+
+/// Files received into WebDAV are reported by URL; there is nothing local
+/// to extract, import or print.
+pub(crate) fn is_uploaded(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+/// `password` replaces the stored one when given; `None` keeps it.
+#[tauri::command]
+pub(crate) fn set_webdav_settings(
+    app: AppHandle,
+    webdav: WebDavSettings,
+    password: Option<String>,
+) -> Result<WebDavSettings, String> {
+    let url = webdav.url.trim().to_string();
+    if webdav.enabled || !url.is_empty() {
+        let parsed =
+            tauri::Url::parse(&url).map_err(|err| format!("WebDAV URL is invalid: {err}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err("WebDAV URL must start with http:// or https://".to_string());
+        }
+        if !parsed.username().is_empty() || parsed.password().is_some() {
+            return Err("put the WebDAV user in its own field, not in the URL".to_string());
+        }
+    }
+    if let Some(password) = password {
+        store_secret(PASSWORD_KEY, &password)?;
+    }
+    let webdav = WebDavSettings {
+        enabled: webdav.enabled,
+        url,
+        user: webdav.user.trim().to_string(),
+    };
+    update_settings(&app, |settings| {
+        if webdav.enabled && settings.quick_share.enabled {
+            return Err("turn off Quick Share before receiving to WebDAV".to_string());
+        }
+        settings.webdav = webdav;
+        Ok(())
+    })
+    .map(|settings| settings.webdav)
+}
@@ -0,0 +1,111 @@
+//! The same file seen from both sides of WSL: `C:\Users\me` is
+//! `/mnt/c/Users/me` inside a distro, and a distro's `/home/me` is
+//! `\\wsl.localhost\Ubuntu\home\me` from Windows. Paths dropped or pasted
+//! in the other side's form are rewritten before the CLI opens them.
+
+use crate::SendRequest;
+
+const DISTRO_PREFIXES: &[&str] = &[r"\\wsl.localhost\", r"\\wsl$\"];
+
+fn strip_prefix_ignore_case<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = path.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &path[prefix.len()..])
+}
+
+/// `C:\dir\file` as `/mnt/c/dir/file`, and a path into a distro's share
+/// as the path inside the distro. `None` for anything else.
+pub(crate) fn windows_to_wsl(path: &str) -> Option<String> {
+    for prefix in DISTRO_PREFIXES {
+        if let Some(rest) = strip_prefix_ignore_case(path, prefix) {
+            let inner = rest.split_once('\\').map_or("", |(_, inner)| inner);
+            return Some(format!("/{}", inner.replace('\\', "/")));
+        }
+    }
+    let bytes = path.as_bytes();
+    let drive_path = bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes.get(2), None | Some(b'\\' | b'/'));
+    if !drive_path {
+        return None;
+    }
+    let drive = (bytes[0] as char).to_ascii_lowercase();
+    Some(format!("/mnt/{drive}{}", path[2..].replace('\\', "/")))
+}
+
+/// `/mnt/c/dir/file` as `C:\dir\file`. Other absolute paths live inside a
+/// distro and need its name; `None` without one or for relative paths.
+pub(crate) fn wsl_to_windows(path: &str, distro: Option<&str>) -> Option<String> {
+    if !path.starts_with('/') {
+        return None;
+    }
+    if let Some(rest) = path.strip_prefix("/mnt/") {
+        let (drive, tail) = rest.split_once('/').unwrap_or((rest, ""));
+        if drive.len() == 1 && drive.as_bytes()[0].is_ascii_alphabetic() {
+            return Some(format!(
+                "{}:\\{}",
+                drive.to_ascii_uppercase(),
+                tail.replace('/', "\\")
+            ));
+        }
+    }
+    Some(format!(
+        r"\\wsl.localhost\{}{}",
+        distro?,
+        path.replace('/', "\\")
+    ))
+}
+
+/// Whether this process runs inside a WSL distro, e.g. under WSLg.
+fn inside_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && (std::env::var_os("WSL_DISTRO_NAME").is_some()
+            || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                .is_ok_and(|release| release.to_ascii_lowercase().contains("microsoft")))
+}
+
+/// Distros running now. `wsl.exe` prints UTF-16, hence the NULs.
+fn running_distros() -> Vec<String> {
+    crate::network::command_stdout("wsl.exe", &["--list", "--quiet", "--running"])
+        .map(|output| {
+            output
+                .replace('\0', "")
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The distro behind `host` when it is a WSL listener on this Windows
+/// machine: NAT-mode distros are reached through the `vEthernet (WSL)`
+/// adapter. With several distros running the first is assumed.
+fn wsl_listener_distro(host: &str) -> Option<String> {
+    if !cfg!(windows) {
+        return None;
+    }
+    let interface = crate::interfaces::interface_for(host)?;
+    if !interface.to_ascii_lowercase().contains("wsl") {
+        return None;
+    }
+    running_distros().into_iter().next()
+}
+
+/// Rewrites a send path given in the other side's form. Paths inside a
+/// distro are only translated when the target is a WSL listener on this
+/// machine, since that tells which distro they belong to. Returns the
+/// original path when it was rewritten.
+pub(crate) fn translate_send_path(request: &mut SendRequest) -> Option<String> {
+    let path = request.path.trim();
+    let translated = if cfg!(windows) {
+        let distro = request.host.as_deref().and_then(wsl_listener_distro);
+        wsl_to_windows(path, distro.as_deref())
+    } else if inside_wsl() {
+        windows_to_wsl(path)
+    } else {
+        None
+    }?;
+    Some(std::mem::replace(&mut request.path, translated))
+}
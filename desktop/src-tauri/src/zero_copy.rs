@@ -0,0 +1,487 @@
+use crate::activity::{emit_activity, ActivityEntry};
+use crate::cli_ipc::SentFile;
+use crate::delivery::SourceFile;
+use crate::device_cache;
+use crate::parser::BatchProgress;
+use crate::progress_stats::{self, ProgressStats};
+use crate::scheduler;
+use crate::settings::{load_settings, update_settings, AppSettings};
+use crate::taskbar;
+use crate::{AppState, CommandResult, SendRequest};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use unicode_normalization::UnicodeNormalization;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Bytes handed to one `sendfile` call; small enough to notice the
+/// kill-switch and redraw progress between calls.
+const SEND_CHUNK_BYTES: u64 = 8 * 1024 * 1024;
+const HASH_BUFFER_BYTES: usize = 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// How long the socket may stall once the receiver is ready when the stall
+/// watchdog is turned off; a dead peer must not hold the send forever.
+const SOCKET_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ZeroCopyMode {
+    /// Plain-TCP sends of a single file to a known host use `sendfile`.
+    #[default]
+    Auto,
+    /// Always go through the CLI pipeline, e.g. to benchmark against it.
+    Off,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Header<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    version: u8,
+    relative_path: &'a str,
+    file_size: u64,
+    sha256: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pair_code: Option<&'a str>,
+}
+
+/// The receiver's `ready` and `ack` lines share these fields.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reply {
+    #[serde(rename = "type")]
+    kind: String,
+    ok: bool,
+    #[serde(default)]
+    offset: u64,
+    message: Option<String>,
+    saved_path: Option<String>,
+    #[serde(default)]
+    deduplicated: bool,
+}
+
+/// Whether `request` can skip the CLI: TLS needs the CLI's TLS stack,
+/// directories its batching, a missing host its discovery, a stealth
+/// receiver its knock, parallel streams their extra connections, and a
+/// note or tags its checks of them.
+pub(crate) fn eligible(request: &SendRequest, mode: ZeroCopyMode) -> bool {
+    let enabled = request.zero_copy.unwrap_or(mode == ZeroCopyMode::Auto);
+    enabled
+        && cfg!(any(target_os = "linux", target_os = "macos"))
+        && !request.tls.unwrap_or(false)
+        && !request.atomic.unwrap_or(false)
+        && !request.knock.unwrap_or(false)
+        && request.streams.unwrap_or(1) <= 1
+        && request
+            .note
+            .as_deref()
+            .is_none_or(|note| note.trim().is_empty())
+        && request.tags.iter().all(|tag| tag.trim().is_empty())
+        && request
+            .host
+            .as_ref()
+            .is_some_and(|host| !host.trim().is_empty())
+        && Path::new(&request.path).is_file()
+}
+
+/// Whether a send with these settings goes through `sendfile` rather than
+/// the CLI; only the CLI carries extended attributes. A host discovery
+/// reported as a stealth listener is left to the CLI even without `knock`.
+pub(crate) fn takes_native_path(
+    app: &AppHandle,
+    request: &SendRequest,
+    settings: &AppSettings,
+) -> bool {
+    !settings.preserve_xattrs
+        && eligible(request, settings.zero_copy)
+        && !request
+            .host
+            .as_deref()
+            .is_some_and(|host| device_cache::announces_knock(app, host.trim(), request.port))
+}
+
+/// Send output written by the app itself, shaped like the CLI's so
+/// history, progress and error rendering treat both paths the same.
+pub(crate) struct Output<'a> {
+    app: &'a AppHandle,
+    send_id: u64,
+    stdout: String,
+    /// For the file whose progress was last reported.
+    stats: Option<(String, ProgressStats)>,
+    /// Bytes of the files before the current one, and the current file's
+    /// position, in a multi-file send.
+    done_bytes: u64,
+    batch: Option<BatchProgress>,
+    /// Files started with `start_file`; all of them are done by `finish`.
+    manifest: Vec<SentFile>,
+}
+
+impl<'a> Output<'a> {
+    pub(crate) fn new(app: &'a AppHandle, send_id: u64) -> Self {
+        Self {
+            app,
+            send_id,
+            stdout: String::new(),
+            stats: None,
+            done_bytes: 0,
+            batch: None,
+            manifest: Vec::new(),
+        }
+    }
+
+    /// Writes the `[send] 2/5 name` line that starts each file of a send,
+    /// and keeps the file's place for nested progress and the manifest.
+    pub(crate) fn start_file(&mut self, index: usize, files: &[SourceFile]) {
+        let file = &files[index];
+        self.write(format!(
+            "\n[send] {}/{} {}\n",
+            index + 1,
+            files.len(),
+            file.relative_path
+        ));
+        self.done_bytes = files[..index].iter().map(|file| file.size).sum();
+        self.batch = (files.len() > 1).then(|| BatchProgress {
+            index: index as u64 + 1,
+            count: files.len() as u64,
+            bytes: self.done_bytes,
+            total: files.iter().map(|file| file.size).sum(),
+        });
+        self.manifest.push(SentFile {
+            path: file.relative_path.clone(),
+            bytes: file.size,
+            resumed_bytes: 0,
+            deduplicated: false,
+            saved_path: None,
+        });
+    }
+
+    pub(crate) fn progress(&mut self, label: &str, sent: u64, total: u64, started: Instant) {
+        taskbar::observe_send_progress(self.app, self.send_id, label, sent, total);
+        let now = Instant::now();
+        let stats = match &mut self.stats {
+            Some((file, stats)) if file == label => stats,
+            stats => {
+                &mut stats
+                    .insert((label.to_string(), ProgressStats::new(started)))
+                    .1
+            }
+        };
+        stats.observe(sent, now);
+        let snapshot = stats.snapshot(Some(total), now);
+        let mut line =
+            progress_stats::render_line(&format!("[send {label}]"), sent, Some(total), &snapshot);
+        if let Some(batch) = &mut self.batch {
+            batch.bytes = (self.done_bytes + sent).min(batch.total);
+            line.push_str(&progress_stats::render_batch(batch));
+        }
+        self.write(format!("\r{line}"));
+    }
+
+    pub(crate) fn finish(self) -> CommandResult {
+        if !self.manifest.is_empty() {
+            emit_activity(
+                self.app,
+                ActivityEntry::SendManifest {
+                    send_id: self.send_id,
+                    files: self.manifest,
+                },
+            );
+        }
+        CommandResult {
+            success: true,
+            code: 0,
+            stdout: self.stdout,
+            stderr: String::new(),
+        }
+    }
+
+    pub(crate) fn write(&mut self, chunk: String) {
+        self.stdout.push_str(&chunk);
+        emit_activity(
+            self.app,
+            ActivityEntry::SendOutput {
+                send_id: self.send_id,
+                stream: "stdout".to_string(),
+                chunk,
+            },
+        );
+    }
+}
+
+pub(crate) fn format_bytes(bytes: f64) -> String {
+    if bytes < 1024.0 {
+        return format!("{} B", bytes.round() as u64);
+    }
+    let mut value = bytes;
+    let mut unit = "B";
+    for next in ["KB", "MB", "GB", "TB"] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    format!("{value:.1} {unit}")
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|err| format!("failed to open file: {err}"))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_BYTES];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|err| format!("failed to hash file: {err}"))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Socket timeouts surface as `WouldBlock` on Unix and `TimedOut` on Windows.
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+fn read_reply(reader: &mut impl BufRead, label: &str) -> Result<Reply, String> {
+    let mut line = String::new();
+    let read = reader.read_line(&mut line).map_err(|err| {
+        if is_timeout(&err) {
+            format!("receiver stopped answering before {label}")
+        } else {
+            format!("failed to read {label}: {err}")
+        }
+    })?;
+    if read == 0 {
+        return Err(format!("connection closed before {label}"));
+    }
+    let reply: Reply =
+        serde_json::from_str(line.trim()).map_err(|err| format!("invalid {label}: {err}"))?;
+    if reply.kind != label {
+        return Err(format!("protocol error: expected {label} message"));
+    }
+    if !reply.ok {
+        return Err(reply
+            .message
+            .unwrap_or_else(|| "receiver rejected transfer".to_string()));
+    }
+    Ok(reply)
+}
+
+#[cfg(target_os = "linux")]
+fn send_chunk(file: &File, socket: &TcpStream, offset: u64, len: u64) -> io::Result<u64> {
+    use std::os::fd::AsRawFd;
+    let mut position = offset as libc::off_t;
+    // SAFETY: both descriptors are open for the duration of the call and
+    // `position` outlives it.
+    let sent = unsafe {
+        libc::sendfile(
+            socket.as_raw_fd(),
+            file.as_raw_fd(),
+            &mut position,
+            len as usize,
+        )
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(sent as u64)
+}
+
+#[cfg(target_os = "macos")]
+fn send_chunk(file: &File, socket: &TcpStream, offset: u64, len: u64) -> io::Result<u64> {
+    use std::os::fd::AsRawFd;
+    let mut length = len as libc::off_t;
+    // SAFETY: both descriptors are open for the duration of the call and
+    // `length` outlives it; no header/trailer vectors are passed.
+    let result = unsafe {
+        libc::sendfile(
+            file.as_raw_fd(),
+            socket.as_raw_fd(),
+            offset as libc::off_t,
+            &mut length,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if result < 0 && length == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // An interrupted call may still have sent part of the range.
+    Ok(length as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn send_chunk(_file: &File, _socket: &TcpStream, _offset: u64, _len: u64) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "zero-copy sending is not available on this platform",
+    ))
+}
+
+/// Sends one file with the CLI's version 1 protocol, moving the payload with
+/// `sendfile` so it never passes through user space.
+pub(crate) fn send(
+    app: &AppHandle,
+    request: &SendRequest,
+    send_id: u64,
+) -> Result<CommandResult, String> {
+    let path = Path::new(&request.path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| "path has no file name".to_string())?;
+    // Like the CLI, send the name in NFC unless asked to keep its form.
+    let label = if request.unicode.as_deref().map(str::trim) == Some("keep") {
+        name
+    } else {
+        name.nfc().collect()
+    };
+    let host = request
+        .host
+        .as_deref()
+        .map(str::trim)
+        .ok_or_else(|| "host is required".to_string())?;
+    let mut output = Output::new(app, send_id);
+    output.write(format!("\n[send] 1/1 {label}\n"));
+
+    let file = File::open(path).map_err(|err| format!("failed to open file: {err}"))?;
+    let size = file
+        .metadata()
+        .map_err(|err| format!("failed to read file metadata: {err}"))?
+        .len();
+    let digest = sha256_file(path)?;
+
+    let address = (host, request.port)
+        .to_socket_addrs()
+        .map_err(|err| format!("failed to resolve {host}: {err}"))?
+        .next()
+        .ok_or_else(|| format!("failed to resolve {host}"))?;
+    let socket = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+        .map_err(|err| format!("failed to connect to {address}: {err}"))?;
+    let _ = socket.set_nodelay(true);
+    let stall = load_settings(app)
+        .ok()
+        .and_then(|settings| settings.command_timeouts.stall())
+        .unwrap_or(SOCKET_IDLE_TIMEOUT);
+    socket
+        .set_write_timeout(Some(stall))
+        .map_err(|err| format!("failed to configure socket: {err}"))?;
+    let mut reader = BufReader::new(
+        socket
+            .try_clone()
+            .map_err(|err| format!("failed to clone socket: {err}"))?,
+    );
+
+    let header = Header {
+        kind: "header",
+        version: 1,
+        relative_path: &label,
+        file_size: size,
+        sha256: &digest,
+        pair_code: request
+            .pair_code
+            .as_deref()
+            .map(str::trim)
+            .filter(|code| !code.is_empty()),
+    };
+    let mut line =
+        serde_json::to_vec(&header).map_err(|err| format!("failed to encode header: {err}"))?;
+    line.push(b'\n');
+    (&socket)
+        .write_all(&line)
+        .map_err(|err| format!("failed to send header: {err}"))?;
+
+    // Unbounded like the CLI's: the receiver may be waiting for its user
+    // to accept the file.
+    let ready = read_reply(&mut reader, "ready")?;
+    if ready.offset > size {
+        return Err(format!("invalid resume offset: {}", ready.offset));
+    }
+    socket
+        .set_read_timeout(Some(stall))
+        .map_err(|err| format!("failed to configure socket: {err}"))?;
+
+    let state = app.state::<AppState>();
+    let started = Instant::now();
+    let mut last_progress = Instant::now() - PROGRESS_INTERVAL;
+    let mut sent = ready.offset;
+    while sent < size {
+        scheduler::wait_while_paused(app, send_id);
+        if state.network_halted.load(Ordering::SeqCst) {
+            let _ = socket.shutdown(Shutdown::Both);
+            return Err("send aborted by the kill-switch".to_string());
+        }
+        match send_chunk(&file, &socket, sent, (size - sent).min(SEND_CHUNK_BYTES)) {
+            Ok(0) => return Err("receiver stopped accepting data".to_string()),
+            Ok(count) => sent += count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) if is_timeout(&err) => {
+                return Err(format!(
+                    "send stalled: no progress for {}s",
+                    stall.as_secs()
+                ))
+            }
+            Err(err) => return Err(format!("failed to send file: {err}")),
+        }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            output.progress(&label, sent, size, started);
+        }
+    }
+    output.progress(&label, sent, size, started);
+    output.write("\n".to_string());
+    socket
+        .shutdown(Shutdown::Write)
+        .map_err(|err| format!("failed to finish sending: {err}"))?;
+
+    let ack = read_reply(&mut reader, "ack")?;
+    output.write(format!(
+        "[send] done: files=1 bytes={} resumed={}\n",
+        format_bytes(size as f64),
+        format_bytes(ready.offset as f64)
+    ));
+    if let Some(saved_path) = ack.saved_path {
+        output.write(if ack.deduplicated {
+            format!("[send] receiver already has it: {saved_path}\n")
+        } else {
+            format!("[send] receiver saved path: {saved_path}\n")
+        });
+    }
+    let seconds = started.elapsed().as_secs_f64().max(0.001);
+    output.write(format!(
+        "[send] zero-copy: {} in {seconds:.2}s ({}/s)\n",
+        format_bytes((size - ready.offset) as f64),
+        format_bytes((size - ready.offset) as f64 / seconds)
+    ));
+
+    Ok(output.finish())
+}
+
+#[tauri::command]
+pub(crate) fn set_zero_copy_mode(
+    app: AppHandle,
+    mode: ZeroCopyMode,
+) -> Result<ZeroCopyMode, String> {
+    update_settings(&app, |settings| {
+        settings.zero_copy = mode;
+        Ok(())
+    })
+    .map(|settings| settings.zero_copy)
+}
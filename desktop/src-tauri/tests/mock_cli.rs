@@ -0,0 +1,26 @@
+//! Checks the mock CLI itself. Having an integration test also makes cargo
+//! build `mock_cli` before the unit tests in `src/cli_harness.rs` use it.
+
+use std::process::Command;
+
+const MOCK_CLI: &str = env!("CARGO_BIN_EXE_mock_cli");
+
+#[test]
+fn mock_cli_rejects_unknown_commands() {
+    let output = Command::new(MOCK_CLI)
+        .arg("frobnicate")
+        .output()
+        .expect("mock CLI runs");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown command"));
+}
+
+#[test]
+fn mock_cli_crash_scenario_fails() {
+    let output = Command::new(MOCK_CLI)
+        .args(["send", "a.txt"])
+        .env("LOCAL_SENT_MOCK_SCENARIO", "crash")
+        .output()
+        .expect("mock CLI runs");
+    assert!(!output.status.success());
+}
@@ -34,6 +34,19 @@ const translations = {
     sendPathLabel: "待发送内容",
     sendPathEmpty: "点击此处选择文件或目录",
     sendPathChosen: "已选择：{name}",
+    sendPreflightScanning: "正在统计：{files} 个文件，{size}…",
+    sendPreflightSummary: "{files} 个文件，共 {size}",
+    sendPreflightEta: "，预计 {seconds} 秒",
+    progressStarting: "测速中",
+    progressBursty: "速度波动",
+    progressDegraded: "速度下降",
+    progressStalled: "已停滞",
+    progressBatchFile: "第 {index}/{count} 个文件",
+    progressBatchOverall: "总计 {percent}%",
+    sendFileListSummary: "已发送 {count} 个文件（{size}）",
+    sendFileResumed: "续传 {size}",
+    sendFileDeduplicated: "对方已有",
+    sendPreflightNameIssues: "；{count} 组路径仅大小写或 Unicode 形式不同，发送时将重命名",
     sendPathClearLabel: "清除已选择项",
     sendHostLabel: "主机（可选，留空自动发现）",
     sendHostPlaceholder: "192.168.1.10",
@@ -43,12 +56,47 @@ const translations = {
     sendButton: "立即发送",
     sendResumeButton: "继续发送",
     sendInterrupted: "发送已中断。请在接收端重新启动并同意后，点击“继续发送”。",
+    sendLikelyCauses: "发送失败，可能的原因：",
+    orphanedListenerPrompt: "上次运行遗留的接收进程（PID {pid}）仍占用端口 {port}。确定：结束该进程；取消：保留它继续接收。",
+    orphanedListenerAdopted: "已保留遗留的接收进程（PID {pid}），停止接收时会一并结束。",
+    orphanedListenerTerminated: "已结束遗留的接收进程，端口 {port} 已释放。",
+    jobsInterruptedPrompt: "上次退出时有 {count} 个发送未完成：\n{list}\n确定：继续发送；取消：放弃。",
+    jobsResumed: "正在继续 {count} 个未完成的发送。",
+    jobsDiscarded: "已放弃 {count} 个未完成的发送。",
+    managedConfigNotice: "部分设置由管理员锁定：{locked}",
+    managedPairCodeRequired: "管理员要求填写配对码",
+    transferStalled: "发送 #{id} 已 {secs} 秒无输出，已中止。",
+    transferStalledRetrying: "发送 #{id} 已 {secs} 秒无输出，正在自动重试（第 {attempt} 次）。",
+    networkSleeping: "系统休眠，设备列表可能已过期。",
+    networkRediscovered: "网络已变化，重新发现 {count} 台设备。",
+    cachedDevices: "显示 {minutes} 分钟前发现的 {count} 台设备，正在刷新…",
+    deviceProtocol: "协议 {version}",
+    deviceNeedsPairCode: "需要配对码",
+    deviceTypeLaptop: "笔记本",
+    deviceTypeDesktop: "台式机",
+    deviceTypeServer: "服务器",
+    deviceDirectory: "组织目录",
+    deviceDirectoryGroup: "组织目录：{group}",
+    proximityNear: "很近",
+    proximityNearby: "附近",
+    proximityFar: "较远",
+    devicePairCodeWarning: "{name} 需要配对码，请先在发送页填写配对码。",
+    interfaceKindVpn: "经 VPN",
+    interfaceKindVirtual: "经虚拟网卡",
     logsTitle: "运行日志",
     progressSendLabel: "发送进度",
     progressRecvLabel: "接收进度",
     progressIdle: "等待中",
     listenOffline: "接收端未运行",
     listenOnline: "接收端运行中（pid {pid}）",
+    listenQuietHours: "免打扰中",
+    listenClipboardSync: "剪贴板同步：{peer}",
+    listenClipboardSyncError: "剪贴板同步失败：{error}",
+    listenDetails: "已运行 {uptime}，收到 {transfers} 个文件（{bytes}）\nTLS：{tls}，配对码：{pairCode}",
+    listenAddresses: "监听地址：{addresses}",
+    listenLastError: "最近错误：{error}",
+    yes: "是",
+    no: "否",
     resultNoScanYet: "尚未扫描。",
     resultNoReceiver: "未发现接收端。",
     resultReceiverNotRunning: "接收端未运行。",
@@ -64,6 +112,9 @@ const translations = {
     resultPathSelected: "已选择发送项：{name}",
     resultSendDone: "发送完成（exit={code}）。",
     confirmReceivePrompt: "来自 {from} 的传输请求：\n{name}\n大小：{size}\n\n是否接受？",
+    confirmDuplicateNote: "已收到过相同内容的文件：\n{existing}",
+    confirmStreamingSize: "未知（流式传输）",
+    confirmQuickShareNote: "来自 Quick Share：对方未配对，地址无法证明身份。",
     logConfirmAccepted: "已接受传输请求：{name}（来自 {from}）",
     logConfirmRejected: "已拒绝传输请求：{name}（来自 {from}）",
     alertSendDone: "传输完毕",
@@ -106,6 +157,19 @@ const translations = {
     sendPathLabel: "Send Item",
     sendPathEmpty: "Click to pick file or directory.",
     sendPathChosen: "Selected: {name}",
+    sendPreflightScanning: "Scanning: {files} files, {size}…",
+    sendPreflightSummary: "{files} files, {size} total",
+    sendPreflightEta: ", ETA {seconds}s",
+    progressStarting: "measuring",
+    progressBursty: "unsteady",
+    progressDegraded: "slowing down",
+    progressStalled: "stalled",
+    progressBatchFile: "file {index}/{count}",
+    progressBatchOverall: "{percent}% overall",
+    sendFileListSummary: "Sent {count} files ({size})",
+    sendFileResumed: "resumed {size}",
+    sendFileDeduplicated: "receiver already had it",
+    sendPreflightNameIssues: "; {count} groups of paths differ only in case or Unicode form and will be renamed",
     sendPathClearLabel: "Clear selected item",
     sendHostLabel: "Host (optional, leave empty for discover mode)",
     sendHostPlaceholder: "192.168.1.10",
@@ -115,12 +179,47 @@ const translations = {
     sendButton: "Send Now",
     sendResumeButton: "Resume Send",
     sendInterrupted: "Transfer interrupted. Restart receiver and approve it, then click \"Resume Send\".",
+    sendLikelyCauses: "Send failed. Likely causes:",
+    orphanedListenerPrompt: "A receiver left over from the last session (PID {pid}) still holds port {port}. OK terminates it; Cancel keeps it receiving.",
+    orphanedListenerAdopted: "Kept the leftover receiver (PID {pid}); stopping the receiver will end it.",
+    orphanedListenerTerminated: "Terminated the leftover receiver; port {port} is free.",
+    jobsInterruptedPrompt: "{count} send(s) did not finish before the app last closed:\n{list}\nOK resumes them; Cancel discards them.",
+    jobsResumed: "Resuming {count} unfinished send(s).",
+    jobsDiscarded: "Discarded {count} unfinished send(s).",
+    managedConfigNotice: "Some settings are locked by your administrator: {locked}",
+    managedPairCodeRequired: "Required by your administrator",
+    transferStalled: "Send #{id} produced no output for {secs}s and was stopped.",
+    transferStalledRetrying: "Send #{id} produced no output for {secs}s; retrying (attempt {attempt}).",
+    networkSleeping: "System is going to sleep; the device list may be out of date.",
+    networkRediscovered: "Network changed; rediscovered {count} device(s).",
+    cachedDevices: "Showing {count} device(s) found {minutes} min ago; refreshing…",
+    deviceProtocol: "protocol {version}",
+    deviceNeedsPairCode: "needs a pair code",
+    deviceTypeLaptop: "laptop",
+    deviceTypeDesktop: "desktop",
+    deviceTypeServer: "server",
+    deviceDirectory: "org directory",
+    deviceDirectoryGroup: "org directory: {group}",
+    proximityNear: "near",
+    proximityNearby: "nearby",
+    proximityFar: "far",
+    devicePairCodeWarning: "{name} needs a pair code; enter it on the send page before sending.",
+    interfaceKindVpn: "via VPN",
+    interfaceKindVirtual: "via virtual interface",
     logsTitle: "Logs",
     progressSendLabel: "Send Progress",
     progressRecvLabel: "Receive Progress",
     progressIdle: "Idle",
     listenOffline: "Receiver Offline",
     listenOnline: "Receiver Online (pid {pid})",
+    listenQuietHours: "Quiet hours",
+    listenClipboardSync: "Clipboard sync: {peer}",
+    listenClipboardSyncError: "Clipboard sync failed: {error}",
+    listenDetails: "Up {uptime}, received {transfers} file(s) ({bytes})\nTLS: {tls}, pair code: {pairCode}",
+    listenAddresses: "Listening on: {addresses}",
+    listenLastError: "Last error: {error}",
+    yes: "yes",
+    no: "no",
     resultNoScanYet: "No scan yet.",
     resultNoReceiver: "No receiver found.",
     resultReceiverNotRunning: "Receiver not running.",
@@ -136,6 +235,9 @@ const translations = {
     resultPathSelected: "Selected item: {name}",
     resultSendDone: "Send done (exit={code}).",
     confirmReceivePrompt: "Incoming transfer from {from}:\n{name}\nSize: {size}\n\nAccept?",
+    confirmDuplicateNote: "You already have a file with the same content:\n{existing}",
+    confirmStreamingSize: "unknown (streamed)",
+    confirmQuickShareNote: "Sent over Quick Share: the sender is not paired and its address proves nothing.",
     logConfirmAccepted: "Accepted transfer request: {name} (from {from})",
     logConfirmRejected: "Rejected transfer request: {name} (from {from})",
     alertSendDone: "Transfer completed",
@@ -194,6 +296,9 @@ const ui = {
   sendBtn: document.querySelector("#sendBtn"),
   resumeSendBtn: document.querySelector("#resumeSendBtn"),
   sendResult: document.querySelector("#sendResult"),
+  sendFileList: document.querySelector("#sendFileList"),
+  sendFileListSummary: document.querySelector("#sendFileListSummary"),
+  sendFileListItems: document.querySelector("#sendFileListItems"),
   progressPanel: document.querySelector("#transferProgress"),
   sendProgressSection: document.querySelector("#sendProgressSection"),
   recvProgressSection: document.querySelector("#recvProgressSection"),
@@ -210,6 +315,12 @@ let currentTheme = detectInitialTheme();
 let currentView = "send";
 let selectedSendPath = "";
 let selectedSendLabel = "";
+// `kdeconnect:<id>` while a KDE Connect device from discovery is the target.
+let selectedSendDevice = null;
+// Whether the device picked from discovery is a stealth listener to knock on.
+let selectedSendKnock = false;
+// Result (or running totals) of the directory preflight for the selected path.
+let sendPreflight = null;
 const streamBuffers = new Map();
 let sendProgressResetTimer = null;
 let recvProgressResetTimer = null;
@@ -223,6 +334,8 @@ const progressActivity = {
 };
 const lastLogByStream = new Map();
 let lastSendRequest = null;
+// The interrupted batch `lastSendRequest` left behind, if it sent several files.
+let lastSendBatchId = null;
 let sendTaskRunning = false;
 
 function markPlatformClass() {
@@ -432,19 +545,62 @@ function normalizeDiscoveredDevice(rawDevice) {
   if (!isObject(rawDevice)) {
     return null;
   }
-  const host = normalizeLanIpv4(rawDevice.host);
+  const routes = Array.isArray(rawDevice.routes) ? rawDevice.routes.filter(isObject) : [];
+  // Overlay peers such as Tailscale ones are reached on 100.x addresses.
+  const overlayAddress = (address) => {
+    const ipv4 = normalizeIpv4(address);
+    return routes.some((item) => item.address === ipv4 && item.kind === "vpn") ? ipv4 : null;
+  };
+  // Org directory entries may name hosts on other VLANs or by DNS name.
+  const directory = isObject(rawDevice.directory)
+    ? { group: typeof rawDevice.directory.group === "string" ? rawDevice.directory.group : null }
+    : null;
+  const listedHost = directory && typeof rawDevice.host === "string" ? rawDevice.host.trim() : "";
+  const host = normalizeLanIpv4(rawDevice.host) ?? overlayAddress(rawDevice.host) ?? (listedHost || null);
   if (!host) {
     return null;
   }
   const addresses = Array.isArray(rawDevice.addresses)
-    ? [...new Set(rawDevice.addresses.map((item) => normalizeLanIpv4(item)).filter(Boolean))]
+    ? [...new Set(rawDevice.addresses.map((item) => normalizeLanIpv4(item) ?? overlayAddress(item)).filter(Boolean))]
     : [];
   const name = String(rawDevice.name ?? "").trim() || host;
   const port = Number.parseInt(String(rawDevice.port ?? ""), 10);
   if (!Number.isFinite(port) || port <= 0) {
     return null;
   }
-  return { name, host, port, addresses };
+  const route = routes.find((item) => item.address === host) ?? null;
+  // Announced by newer listeners; `null` means unknown.
+  const flag = (value) => (typeof value === "boolean" ? value : null);
+  return {
+    name,
+    host,
+    port,
+    addresses,
+    route,
+    version: typeof rawDevice.version === "string" ? rawDevice.version : null,
+    protocolVersion: Number.isInteger(rawDevice.protocolVersion) ? rawDevice.protocolVersion : null,
+    tls: flag(rawDevice.tls),
+    pairCodeRequired: flag(rawDevice.pairCodeRequired),
+    id: typeof rawDevice.id === "string" ? rawDevice.id : null,
+    capabilities: Array.isArray(rawDevice.capabilities)
+      ? rawDevice.capabilities.filter((item) => typeof item === "string")
+      : [],
+    source: ["kdeConnect", "ssdp"].includes(rawDevice.source) ? rawDevice.source : "localSent",
+    deviceType: ["laptop", "desktop", "server"].includes(rawDevice.deviceType) ? rawDevice.deviceType : null,
+    avatarColor:
+      typeof rawDevice.avatarColor === "string" && /^#[0-9a-f]{6}$/i.test(rawDevice.avatarColor)
+        ? rawDevice.avatarColor
+        : null,
+    avatarEmoji: typeof rawDevice.avatarEmoji === "string" ? rawDevice.avatarEmoji.slice(0, 16) : null,
+    directory,
+    // Devices arrive sorted by it; absent for cached lists from older versions.
+    proximity: isObject(rawDevice.proximity) && ["near", "nearby", "far"].includes(rawDevice.proximity.nearness)
+      ? {
+          nearness: rawDevice.proximity.nearness,
+          rttMs: Number.isInteger(rawDevice.proximity.rttMs) ? rawDevice.proximity.rttMs : null
+        }
+      : null
+  };
 }
 
 function setResult(target, message, isError = false) {
@@ -570,20 +726,36 @@ function queueProgressReset(kind) {
 
 function parseTransferProgressLine(line) {
   const matched =
-    /\[(send|recv)\s+([^\]]+)\]\s+(\d+(?:\.\d+)?)%\s*(?:\(([^)]*)\))?\s*([^\s]+\/s)?\s*(?:ETA\s+(\d+)s)?/i.exec(
+    /\[(send|recv)\s+([^\]]+)\]\s+(\d+(?:\.\d+)?)%\s*(?:\(([^)]*)\))?\s*(\d+(?:\.\d+)?\s[KMGT]?B\/s)?\s*(?:ETA\s+(\d+)s)?\s*(starting|bursty|degraded|stalled)?/i.exec(
       line
     );
   if (!matched) {
     return null;
   }
   const eta = matched[6] ? Number.parseInt(matched[6], 10) : null;
+  // Parallel transfers append `[N streams: a/s, b/s, ...]`.
+  const streamStats = /\[(\d+) streams: ([^\]]*)\]\s*$/.exec(line);
+  // Files of a multi-file send carry `[2/5 files, 40.0% overall]`.
+  const batch = /\[(\d+)\/(\d+) files, (\d+(?:\.\d+)?)% overall\]/.exec(line);
   return {
     kind: matched[1].toLowerCase() === "send" ? "send" : "recv",
     label: matched[2],
     percent: Number.parseFloat(matched[3]),
     amount: matched[4] ?? "",
     speed: matched[5] ?? "",
-    eta: Number.isFinite(eta) ? eta : null
+    eta: Number.isFinite(eta) ? eta : null,
+    // Throughput regime from the progress analytics; absent when steady.
+    regime: matched[7] ? matched[7].toLowerCase() : null,
+    streams: streamStats
+      ? { count: Number.parseInt(streamStats[1], 10), speeds: streamStats[2].split(", ").filter(Boolean) }
+      : null,
+    batch: batch
+      ? {
+          index: Number.parseInt(batch[1], 10),
+          count: Number.parseInt(batch[2], 10),
+          percent: Number.parseFloat(batch[3])
+        }
+      : null
   };
 }
 
@@ -597,11 +769,20 @@ function handleProgressLine(stream, line) {
       compactLabel,
       progress.amount,
       progress.speed,
-      progress.eta !== null ? `ETA ${progress.eta}s` : ""
+      progress.eta !== null ? `ETA ${progress.eta}s` : "",
+      progress.regime ? t(`progress${progress.regime[0].toUpperCase()}${progress.regime.slice(1)}`) : "",
+      progress.streams ? `${progress.streams.count}×` : "",
+      progress.batch ? t("progressBatchFile", progress.batch) : "",
+      progress.batch ? t("progressBatchOverall", { percent: progress.batch.percent.toFixed(1) }) : ""
     ]
       .filter(Boolean)
       .join(" · ");
-    setProgress(progress.kind, progress.percent, text);
+    // In a multi-file send the bar tracks the whole send, the text the file.
+    setProgress(progress.kind, progress.batch ? progress.batch.percent : progress.percent, text);
+    const label = progress.kind === "send" ? ui.sendProgressText : ui.recvProgressText;
+    if (label) {
+      label.title = progress.streams ? progress.streams.speeds.join("\n") : "";
+    }
     return true;
   }
 
@@ -641,9 +822,27 @@ function setActiveView(view) {
   syncProgressVisibility();
 }
 
+function describeSendPreflight() {
+  if (!sendPreflight) {
+    return "";
+  }
+  const vars = { files: sendPreflight.files, size: formatBytes(sendPreflight.totalBytes) };
+  if (sendPreflight.scanning) {
+    return t("sendPreflightScanning", vars);
+  }
+  const eta =
+    typeof sendPreflight.etaSeconds === "number" ? t("sendPreflightEta", { seconds: sendPreflight.etaSeconds }) : "";
+  const nameIssues = sendPreflight.nameIssues?.length
+    ? t("sendPreflightNameIssues", { count: sendPreflight.nameIssues.length })
+    : "";
+  return `${t("sendPreflightSummary", vars)}${eta}${nameIssues}`;
+}
+
 function refreshSendPathSummary() {
   if (selectedSendLabel) {
-    ui.sendPathSummary.textContent = t("sendPathChosen", { name: selectedSendLabel });
+    const preflight = describeSendPreflight();
+    const chosen = t("sendPathChosen", { name: selectedSendLabel });
+    ui.sendPathSummary.textContent = preflight ? `${chosen} · ${preflight}` : chosen;
     if (ui.clearSendPathBtn) {
       ui.clearSendPathBtn.classList.remove("is-hidden");
     }
@@ -655,7 +854,40 @@ function refreshSendPathSummary() {
   }
 }
 
+async function runSendPreflight(path) {
+  sendPreflight = { scanning: true, preflightId: null, files: 0, totalBytes: 0 };
+  refreshSendPathSummary();
+  try {
+    const report = await invoke("preflight_send", {
+      path,
+      host: ui.sendHost.value.trim() || null,
+      device: null
+    });
+    if (selectedSendPath === path) {
+      sendPreflight = report;
+    }
+  } catch (err) {
+    if (selectedSendPath === path) {
+      sendPreflight = null;
+      appendLog("system", toErrorMessage(err));
+    }
+  }
+  refreshSendPathSummary();
+}
+
+function handlePreflightProgress(payload) {
+  if (!sendPreflight?.scanning || payload.done) {
+    return;
+  }
+  sendPreflight = { ...sendPreflight, files: payload.files, totalBytes: payload.totalBytes };
+  refreshSendPathSummary();
+}
+
 function clearSendPathSelection() {
+  if (sendPreflight?.scanning) {
+    void invoke("cancel_preflight");
+  }
+  sendPreflight = null;
   selectedSendPath = "";
   selectedSendLabel = "";
   lastSendRequest = null;
@@ -709,12 +941,49 @@ async function waitForNextFrame() {
   await new Promise((resolve) => window.requestAnimationFrame(resolve));
 }
 
+function formatUptime(seconds) {
+  const total = Math.max(0, Math.floor(Number(seconds) || 0));
+  const hours = Math.floor(total / 3600);
+  const minutes = Math.floor((total % 3600) / 60);
+  return hours > 0 ? `${hours}h ${minutes}m` : `${minutes}m ${total % 60}s`;
+}
+
+function listenDetailsText(state) {
+  const lines = [
+    t("listenDetails", {
+      uptime: formatUptime(state.uptimeSecs),
+      transfers: Number(state.transfersReceived) || 0,
+      bytes: formatBytes(Number(state.bytesReceived) || 0),
+      tls: t(state.tls ? "yes" : "no"),
+      pairCode: t(state.pairCodeRequired ? "yes" : "no")
+    })
+  ];
+  if (Array.isArray(state.boundAddresses) && state.boundAddresses.length > 0) {
+    lines.push(t("listenAddresses", { addresses: state.boundAddresses.join(", ") }));
+  }
+  if (typeof state.lastError === "string" && state.lastError) {
+    lines.push(t("listenLastError", { error: state.lastError }));
+  }
+  const clipboard = state.clipboardSync;
+  if (clipboard?.enabled && typeof clipboard.lastError === "string" && clipboard.lastError) {
+    lines.push(t("listenClipboardSyncError", { error: clipboard.lastError }));
+  }
+  return lines.join("\n");
+}
+
 function setListeningUi(state) {
   const running = Boolean(state?.running);
   const pid = typeof state?.pid === "number" ? state.pid : null;
   ui.listenState.classList.toggle("online", running);
   ui.listenState.classList.toggle("offline", !running);
   ui.listenState.textContent = running ? t("listenOnline", { pid }) : t("listenOffline");
+  if (running && state?.quietHoursActive) {
+    ui.listenState.textContent += ` · ${t("listenQuietHours")}`;
+  }
+  if (running && state?.clipboardSync?.enabled) {
+    ui.listenState.textContent += ` · ${t("listenClipboardSync", { peer: state.clipboardSync.peer ?? "" })}`;
+  }
+  ui.listenState.title = running ? listenDetailsText(state) : "";
 
   ui.startListenBtn.disabled = running;
   ui.stopListenBtn.disabled = !running;
@@ -861,10 +1130,27 @@ function isSendInterruptedError(rawMessage) {
     "write epipe",
     "broken pipe",
     "socket hang up",
-    "receiver rejected transfer"
+    "receiver rejected transfer",
+    // The watchdog killed a hung send; resuming picks up where it stopped.
+    "send stalled"
   ].some((pattern) => message.includes(pattern));
 }
 
+/** Ranked causes from `diagnose_send_failure`, or the raw error if that fails. */
+async function describeSendFailure(request, message) {
+  try {
+    const diagnosis = await invoke("diagnose_send_failure", { request, error: message });
+    const causes = Array.isArray(diagnosis?.causes) ? diagnosis.causes.slice(0, 3) : [];
+    if (!causes.length) {
+      return message;
+    }
+    const lines = causes.map((cause, index) => `${index + 1}. ${cause.detail} — ${cause.fix}`);
+    return [t("sendLikelyCauses"), ...lines].join("\n");
+  } catch {
+    return message;
+  }
+}
+
 function buildSendRequestFromUi() {
   if (!selectedSendPath.trim()) {
     throw new Error(t("errorPathRequired"));
@@ -872,13 +1158,27 @@ function buildSendRequestFromUi() {
 
   return {
     path: selectedSendPath,
-    host: ui.sendHost.value.trim() || null,
+    device: selectedSendDevice,
+    host: selectedSendDevice ? null : ui.sendHost.value.trim() || null,
     port: toPositiveInt(ui.sendPort.value, 37373),
     timeoutMs: 3000,
-    pairCode: ui.sendPairCode.value.trim() || null
+    pairCode: ui.sendPairCode.value.trim() || null,
+    knock: selectedSendKnock
   };
 }
 
+async function findSendBatchId(path) {
+  try {
+    const batches = await invoke("list_send_batches");
+    const matching = batches
+      .filter((batch) => batch.path === path)
+      .sort((left, right) => right.updatedAtMs - left.updatedAtMs);
+    return matching[0]?.id ?? null;
+  } catch {
+    return null;
+  }
+}
+
 async function runSendRequest(request, options = {}) {
   const resumeMode = Boolean(options.resumeMode);
   if (sendTaskRunning) {
@@ -898,7 +1198,9 @@ async function runSendRequest(request, options = {}) {
   await waitForNextFrame();
 
   try {
-    const output = await invoke("send_file", { request });
+    const output = options.batchId
+      ? await invoke("resume_batch", { batchId: options.batchId })
+      : await invoke("send_file", { request });
     lastSendRequest = null;
     setResumeSendVisible(false);
     const resultMessage = t("resultSendDone", { code: output.code });
@@ -910,6 +1212,7 @@ async function runSendRequest(request, options = {}) {
     const message = toErrorMessage(err);
     if (isSendInterruptedError(message)) {
       lastSendRequest = { ...request };
+      lastSendBatchId = await findSendBatchId(request.path);
       setProgressActive("send", true);
       setResumeSendVisible(true);
       const interruptedMessage = t("sendInterrupted");
@@ -920,8 +1223,10 @@ async function runSendRequest(request, options = {}) {
       setProgressActive("send", false);
       resetProgress("send");
       setResumeSendVisible(false);
-      setResult(ui.sendResult, message, true);
-      await showPopup(message, "error");
+      appendLog("send", message);
+      const explained = await describeSendFailure(request, message);
+      setResult(ui.sendResult, explained, true);
+      await showPopup(explained, "error");
     }
   } finally {
     sendTaskRunning = false;
@@ -931,6 +1236,7 @@ async function runSendRequest(request, options = {}) {
 
 function renderDevices(devices) {
   ui.deviceList.innerHTML = "";
+  ui.deviceList.classList.remove("stale");
   const normalizedDevices = devices
     .map((device) => normalizeDiscoveredDevice(device))
     .filter((device) => Boolean(device));
@@ -946,6 +1252,15 @@ function renderDevices(devices) {
     const header = document.createElement("header");
     const title = document.createElement("strong");
     title.textContent = device.name;
+    if (device.avatarColor || device.avatarEmoji) {
+      const avatar = document.createElement("span");
+      avatar.className = "device-avatar";
+      avatar.textContent = device.avatarEmoji || device.name.charAt(0).toUpperCase();
+      if (device.avatarColor) {
+        avatar.style.backgroundColor = device.avatarColor;
+      }
+      title.prepend(avatar);
+    }
 
     const useBtn = document.createElement("button");
     useBtn.type = "button";
@@ -953,13 +1268,19 @@ function renderDevices(devices) {
     useBtn.addEventListener("click", () => {
       ui.sendHost.value = device.host;
       ui.sendPort.value = String(device.port);
+      selectedSendDevice = device.source === "kdeConnect" && device.id ? `kdeconnect:${device.id}` : null;
+      selectedSendKnock = device.capabilities.includes("knock");
       const message = t("resultTargetSelected", {
         name: device.name,
         host: device.host,
         port: device.port
       });
       setResult(ui.sendResult, message);
-      void showPopup(message, "info");
+      if (device.pairCodeRequired && !ui.sendPairCode.value.trim()) {
+        void showPopup(t("devicePairCodeWarning", { name: device.name }), "warning");
+      } else {
+        void showPopup(message, "info");
+      }
       setActiveView("send");
     });
 
@@ -971,6 +1292,44 @@ function renderDevices(devices) {
         ? device.addresses.join(", ")
         : "N/A";
     detail.textContent = `${device.host}:${device.port} | ${addresses}`;
+    if (device.route && (device.route.kind === "vpn" || device.route.kind === "virtual")) {
+      const via = device.route.interface ? ` (${device.route.interface})` : "";
+      detail.textContent += ` | ${t(device.route.kind === "vpn" ? "interfaceKindVpn" : "interfaceKindVirtual")}${via}`;
+    }
+    if (device.proximity) {
+      const key = { near: "proximityNear", nearby: "proximityNearby", far: "proximityFar" }[device.proximity.nearness];
+      const rtt = device.proximity.rttMs === null ? "" : ` (${device.proximity.rttMs} ms)`;
+      detail.textContent += ` | ${t(key)}${rtt}`;
+    }
+    const capabilities = [];
+    if (device.version) {
+      const protocol = device.protocolVersion ? ` / ${t("deviceProtocol", { version: device.protocolVersion })}` : "";
+      capabilities.push(`v${device.version}${protocol}`);
+    }
+    if (device.tls) {
+      capabilities.push("TLS");
+    }
+    if (device.pairCodeRequired) {
+      capabilities.push(t("deviceNeedsPairCode"));
+    }
+    if (device.deviceType) {
+      const key = { laptop: "deviceTypeLaptop", desktop: "deviceTypeDesktop", server: "deviceTypeServer" }[device.deviceType];
+      capabilities.push(t(key));
+    }
+    if (device.source === "kdeConnect") {
+      capabilities.push("KDE Connect");
+    }
+    if (device.source === "ssdp") {
+      capabilities.push("SSDP");
+    }
+    if (device.directory) {
+      capabilities.push(
+        device.directory.group ? t("deviceDirectoryGroup", { group: device.directory.group }) : t("deviceDirectory")
+      );
+    }
+    if (capabilities.length) {
+      detail.textContent += ` | ${capabilities.join(", ")}`;
+    }
 
     item.append(header, detail);
     ui.deviceList.append(item);
@@ -1012,9 +1371,13 @@ async function pickSendPath(kind) {
     if (typeof selectedPath === "string" && selectedPath.trim()) {
       selectedSendPath = selectedPath;
       selectedSendLabel = basenameFromPath(selectedPath);
+      sendPreflight = null;
       lastSendRequest = null;
       setResumeSendVisible(false);
       refreshSendPathSummary();
+      if (kind === "directory") {
+        void runSendPreflight(selectedPath);
+      }
     }
   } catch (err) {
     const message = toErrorMessage(err);
@@ -1105,6 +1468,12 @@ if (ui.clearSendPathBtn) {
   });
 }
 
+// Typing a host goes back to sending to this app's listeners.
+ui.sendHost.addEventListener("input", () => {
+  selectedSendDevice = null;
+  selectedSendKnock = false;
+});
+
 ui.pickOutputDirBtn.addEventListener("click", () => {
   void pickOutputDirectory();
 });
@@ -1194,96 +1563,319 @@ if (ui.resumeSendBtn) {
     if (!lastSendRequest) {
       return;
     }
-    await runSendRequest({ ...lastSendRequest }, { resumeMode: true });
+    await runSendRequest({ ...lastSendRequest }, { resumeMode: true, batchId: lastSendBatchId });
   });
 }
 
-async function bootstrap() {
-  applyTheme();
-  setActiveView(currentView);
-  applyI18n();
-  setupWindowDragging();
-  appendStartupLogs();
+const APP_EVENT_SEQ_KEY = "local_sent_last_event_seq";
+let lastAppEventSeq = Number(window.sessionStorage.getItem(APP_EVENT_SEQ_KEY)) || 0;
 
-  await listen("listen-log", (event) => {
-    const payload = event.payload;
-    if (!isObject(payload)) {
+function handleAppEvent(envelope) {
+  if (!isObject(envelope)) {
+    return;
+  }
+  const seq = Number(envelope.seq);
+  if (Number.isFinite(seq)) {
+    if (seq <= lastAppEventSeq) {
       return;
     }
-    const stream =
-      payload.stream === "stderr"
-        ? "recv-err"
-        : payload.stream === "system"
-          ? "system"
-          : "recv";
-    processLogLine(stream, payload.line ?? "");
-  });
+    lastAppEventSeq = seq;
+    window.sessionStorage.setItem(APP_EVENT_SEQ_KEY, String(seq));
+  }
+
+  const payload = envelope.payload;
+  switch (envelope.type) {
+    case "activity":
+      handleActivityEntry(payload);
+      break;
+    case "listenState":
+      setListeningUi(payload);
+      break;
+    case "transferConfirmRequest":
+      void handleTransferConfirmRequest(payload);
+      break;
+    case "allStopped":
+      setListeningUi({ running: false, pid: null });
+      appendLog("system", "all network activity stopped");
+      break;
+    case "logDropped":
+      if (isObject(payload)) {
+        appendLog("system", `log output too fast, dropped ${payload.dropped} line(s)`);
+      }
+      break;
+    case "rateLimited":
+      if (isObject(payload)) {
+        appendLog("system", `rejected ${payload.path} from ${payload.from}: ${payload.reason} limit (${payload.limit})`);
+      }
+      break;
+    case "preflightProgress":
+      if (isObject(payload)) {
+        handlePreflightProgress(payload);
+      }
+      break;
+    case "profileSwitched":
+      // Every view reads profile data, so start over from the new profile.
+      window.location.reload();
+      break;
+    case "transferStalled":
+      if (isObject(payload)) {
+        const vars = { id: payload.sendId, secs: payload.idleSecs, attempt: payload.attempt };
+        appendLog("send", t(payload.retrying ? "transferStalledRetrying" : "transferStalled", vars));
+      }
+      break;
+    case "networkChanged":
+      if (isObject(payload)) {
+        handleNetworkChanged(payload);
+      }
+      break;
+    case "devicesRefreshed":
+      if (isObject(payload) && Array.isArray(payload.devices)) {
+        renderDevices(payload.devices);
+      }
+      break;
+    case "orphanedListener":
+      void handleOrphanedListener();
+      break;
+    case "jobsInterrupted":
+      void handleInterruptedJobs();
+      break;
+    case "portSubstituted":
+      if (isObject(payload)) {
+        ui.listenPort.value = String(payload.port);
+        appendLog("system", payload.reason);
+        if (payload.hint) {
+          appendLog("system", payload.hint);
+        }
+      }
+      break;
+    default:
+      break;
+  }
+}
 
-  await listen("listen-state", (event) => {
-    setListeningUi(event.payload);
-  });
+function handleNetworkChanged(payload) {
+  if (Array.isArray(payload.devices)) {
+    const count = renderDevices(payload.devices);
+    setResult(ui.discoverResult, t("networkRediscovered", { count }));
+  }
+  ui.deviceList.classList.toggle("stale", Boolean(payload.devicesStale));
+  if (payload.reason === "sleep") {
+    appendLog("system", t("networkSleeping"));
+  }
+}
 
-  await listen("transfer-confirm-request", async (event) => {
-    const payload = event.payload;
-    if (!isObject(payload)) {
-      return;
-    }
-    const id = Number(payload.id);
-    if (!Number.isFinite(id) || id <= 0) {
+function handleActivityEntry(payload) {
+  if (!isObject(payload)) {
+    return;
+  }
+  if (payload.kind === "system") {
+    processLogLine("system", payload.line ?? "");
+    return;
+  }
+  if (payload.kind === "receiveLog") {
+    processLogLine(payload.stream === "stderr" ? "recv-err" : "recv", payload.line ?? "");
+    return;
+  }
+  if (payload.kind === "sendManifest") {
+    renderSendManifest(Array.isArray(payload.files) ? payload.files.filter(isObject) : []);
+    return;
+  }
+  if (payload.kind === "sendOutput") {
+    const stream = payload.stream === "stderr" ? "send-err" : "send";
+    const chunk = typeof payload.chunk === "string" ? payload.chunk : "";
+    if (!chunk) {
+      flushStreamBuffer(stream);
       return;
     }
+    appendChunkToLog(stream, chunk);
+  }
+}
 
-    const path = typeof payload.path === "string" ? payload.path : "";
-    const from = formatPeerAddress(payload.from);
-    const size = formatBytes(payload.size);
-    const name = basenameFromPath(path || "unknown");
-
-    const accepted = await showConfirmPopup(
-      t("confirmReceivePrompt", {
-        from,
-        name,
-        size
-      })
-    );
-
-    appendLog(
-      "confirm",
-      accepted
-        ? t("logConfirmAccepted", { name, from })
-        : t("logConfirmRejected", { name, from })
-    );
-
-    try {
-      await invoke("respond_transfer_confirm", {
-        response: {
-          id,
-          accept: accepted
-        }
-      });
-    } catch (err) {
-      const message = `confirm response failed: ${toErrorMessage(err)}`;
-      appendLog("confirm", message);
-      await showPopup(message, "error");
+/** The files of the last multi-file send, collapsed until expanded. */
+function renderSendManifest(files) {
+  if (!ui.sendFileList || !ui.sendFileListSummary || !ui.sendFileListItems) {
+    return;
+  }
+  ui.sendFileListItems.textContent = "";
+  ui.sendFileList.hidden = files.length < 2;
+  if (ui.sendFileList.hidden) {
+    return;
+  }
+  const size = (file) => Number(file.bytes) || 0;
+  ui.sendFileListSummary.textContent = t("sendFileListSummary", {
+    count: files.length,
+    size: formatBytes(files.reduce((sum, file) => sum + size(file), 0))
+  });
+  files.forEach((file) => {
+    const item = document.createElement("li");
+    const notes = [formatBytes(size(file))];
+    if (file.deduplicated) {
+      notes.push(t("sendFileDeduplicated"));
+    } else if (Number(file.resumedBytes) > 0) {
+      notes.push(t("sendFileResumed", { size: formatBytes(Number(file.resumedBytes)) }));
+    }
+    item.textContent = `${String(file.path ?? "")} · ${notes.join(" · ")}`;
+    if (typeof file.savedPath === "string") {
+      item.title = file.savedPath;
     }
+    ui.sendFileListItems.append(item);
   });
+}
 
-  await listen("send-output", (event) => {
-    const payload = event.payload;
-    if (!isObject(payload)) {
+async function handleOrphanedListener() {
+  try {
+    // Re-read: a replayed event may already have been answered.
+    const orphan = await invoke("get_orphaned_listener");
+    if (!isObject(orphan) || orphan.adopted) {
       return;
     }
-    const stream = payload.stream === "stderr" ? "send-err" : "send";
-    const chunk = typeof payload.chunk === "string" ? payload.chunk : "";
-    if (!chunk) {
-      flushStreamBuffer(stream);
+    const terminate = await showConfirmPopup(t("orphanedListenerPrompt", { pid: orphan.pid, port: orphan.port }));
+    await invoke("resolve_orphaned_listener", { action: terminate ? "terminate" : "adopt" });
+    appendLog(
+      "system",
+      terminate
+        ? t("orphanedListenerTerminated", { port: orphan.port })
+        : t("orphanedListenerAdopted", { pid: orphan.pid })
+    );
+  } catch (err) {
+    appendLog("system", toErrorMessage(err));
+  }
+}
+
+async function handleInterruptedJobs() {
+  try {
+    // Re-read: a replayed event may already have been answered.
+    const jobs = await invoke("list_interrupted_jobs");
+    if (!Array.isArray(jobs) || !jobs.length) {
       return;
     }
+    const list = jobs
+      .map((job) => {
+        const target = job.target ? ` → ${job.target}` : "";
+        const done = job.total ? ` (${formatBytes(job.bytes)}/${formatBytes(job.total)})` : "";
+        return `${basenameFromPath(String(job.path ?? ""))}${target}${done}`;
+      })
+      .join("\n");
+    const resume = await showConfirmPopup(t("jobsInterruptedPrompt", { count: jobs.length, list }));
+    const count = await invoke(resume ? "resume_interrupted_jobs" : "discard_interrupted_jobs", { jobIds: null });
+    appendLog("system", t(resume ? "jobsResumed" : "jobsDiscarded", { count }));
+  } catch (err) {
+    appendLog("system", toErrorMessage(err));
+  }
+}
 
-    appendChunkToLog(stream, chunk);
+async function handleTransferConfirmRequest(payload) {
+  if (!isObject(payload)) {
+    return;
+  }
+  const id = Number(payload.id);
+  if (!Number.isFinite(id) || id <= 0) {
+    return;
+  }
+
+  const path = typeof payload.path === "string" ? payload.path : "";
+  const from = formatPeerAddress(payload.from);
+  const size = payload.streaming ? t("confirmStreamingSize") : formatBytes(payload.size);
+  const name = basenameFromPath(path || "unknown");
+
+  const prompt = t("confirmReceivePrompt", {
+    from,
+    name,
+    size
   });
+  const duplicateOf = typeof payload.duplicateOf === "string" ? payload.duplicateOf : "";
+  const notes = [];
+  if (payload.quickShare === true) {
+    notes.push(t("confirmQuickShareNote"));
+  }
+  if (duplicateOf) {
+    notes.push(t("confirmDuplicateNote", { existing: duplicateOf }));
+  }
+  const accepted = await showConfirmPopup([...notes, prompt].join("\n\n"));
+
+  appendLog(
+    "confirm",
+    accepted
+      ? t("logConfirmAccepted", { name, from })
+      : t("logConfirmRejected", { name, from })
+  );
+
+  try {
+    await invoke("respond_transfer_confirm", {
+      response: {
+        id,
+        accept: accepted
+      }
+    });
+  } catch (err) {
+    const message = `confirm response failed: ${toErrorMessage(err)}`;
+    appendLog("confirm", message);
+    await showPopup(message, "error");
+  }
+}
+
+async function bootstrap() {
+  applyTheme();
+  setActiveView(currentView);
+  applyI18n();
+  setupWindowDragging();
+  appendStartupLogs();
+
+  await listen("app-event", (event) => {
+    handleAppEvent(event.payload);
+  });
+
+  try {
+    const replay = await invoke("replay_events", { sinceSeq: lastAppEventSeq });
+    if (isObject(replay) && Array.isArray(replay.events)) {
+      for (const envelope of replay.events) {
+        handleAppEvent(envelope);
+      }
+    }
+  } catch (err) {
+    appendLog("system", `event replay failed: ${toErrorMessage(err)}`);
+  }
 
   await ensureDefaultOutputDirectory();
   await refreshListenState();
+  await applyManagedConfig();
+  await showCachedDevices();
+}
+
+// Settings pinned by an administrator's managed configuration cannot be
+// changed here; the backend enforces them either way.
+async function applyManagedConfig() {
+  try {
+    const managed = await invoke("get_managed_config");
+    if (!isObject(managed) || !managed.managed) {
+      return;
+    }
+    if (managed.requirePairCode) {
+      ui.sendPairCode.required = true;
+      ui.sendPairCode.setAttribute("data-i18n-placeholder", "managedPairCodeRequired");
+      ui.sendPairCode.placeholder = t("managedPairCodeRequired");
+    }
+    appendLog("system", t("managedConfigNotice", { locked: managed.locked.join(", ") || "-" }));
+  } catch (err) {
+    appendLog("system", toErrorMessage(err));
+  }
+}
+
+// Shows the devices found last time while a fresh discovery runs; its
+// result arrives as a `devicesRefreshed` event.
+async function showCachedDevices() {
+  try {
+    const cached = await invoke("get_cached_devices");
+    if (!isObject(cached) || !Array.isArray(cached.devices) || !cached.devices.length) {
+      return;
+    }
+    renderDevices(cached.devices);
+    ui.deviceList.classList.toggle("stale", Boolean(cached.refreshing));
+    const minutes = Math.round((Number(cached.ageMs) || 0) / 60000);
+    setResult(ui.discoverResult, t("cachedDevices", { count: cached.devices.length, minutes }));
+  } catch (err) {
+    appendLog("system", `cached devices unavailable: ${toErrorMessage(err)}`);
+  }
 }
 
 void bootstrap();
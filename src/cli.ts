@@ -3,15 +3,33 @@
 import { randomInt } from "crypto";
 import { Command } from "commander";
 import { hostname, networkInterfaces } from "os";
+import { readFile } from "fs/promises";
 import { resolve } from "path";
 import { createInterface } from "readline";
-import { DEFAULT_DISCOVERY_TIMEOUT_MS, DEFAULT_PORT } from "./constants";
-import { runDoctor } from "./doctor";
-import { discoverDevices } from "./discovery";
+import { CAPABILITY_KNOCK, CLI_VERSION, DEFAULT_DISCOVERY_TIMEOUT_MS, DEFAULT_PORT } from "./constants";
+import { probeListenPort, runDoctor } from "./doctor";
+import { MAX_NOTE_CHARS, MAX_NOTE_TAGS, MAX_TAG_CHARS, TransferNote } from "./protocol";
+import { DEVICE_TYPES, discoverDevices, isAvatarColor, isAvatarEmoji } from "./discovery";
+import { emitIpc, enableIpc, ipcEnabled } from "./ipc";
 import { resolveCliLocale, t as translate } from "./i18n";
-import { sendEntries, startReceiver } from "./transfer";
+import {
+  listRemoteShares,
+  probeReceiver,
+  pullFile,
+  ReceiverConfigUpdate,
+  ReceiverHandle,
+  sendClipboardText,
+  sendEntries,
+  sendStream,
+  sendTextMessage,
+  startReceiver
+} from "./transfer";
 import { normalizeFingerprint } from "./tlsTrust";
-import { buildTransferEntries, formatBytes } from "./utils";
+import { buildTransferEntries, formatBytes, SymlinkMode } from "./utils";
+import { applyNameSafety, CollisionStrategy, UnicodeForm } from "./names";
+import { parseWebDavTarget } from "./webdav";
+import { normalizeZone, parseTsigKey } from "./dnsSd";
+import { generateKnockSecret, knock, knockSecretFromEnv } from "./knock";
 
 const locale = resolveCliLocale();
 const t = (key: Parameters<typeof translate>[1], vars?: Parameters<typeof translate>[2]): string =>
@@ -25,6 +43,135 @@ function parseIntOption(value: string, label: string): number {
   return num;
 }
 
+function parseStreamsOption(value: string): number | "auto" {
+  const trimmed = value.trim().toLowerCase();
+  if (trimmed === "auto") {
+    return "auto";
+  }
+  const num = Number(trimmed);
+  if (!Number.isInteger(num) || num < 1 || num > 16) {
+    throw new Error(t("err_streams_value"));
+  }
+  return num;
+}
+
+function parseSymlinksOption(value: string): SymlinkMode {
+  const trimmed = value.trim().toLowerCase();
+  if (trimmed !== "skip" && trimmed !== "follow" && trimmed !== "preserve") {
+    throw new Error(t("err_symlinks_value"));
+  }
+  return trimmed;
+}
+
+function parseUnicodeOption(value: string): UnicodeForm {
+  const trimmed = value.trim().toLowerCase();
+  if (trimmed !== "nfc" && trimmed !== "keep") {
+    throw new Error(t("err_unicode_value"));
+  }
+  return trimmed;
+}
+
+function parseDeviceTypeOption(value: string): string {
+  const trimmed = value.trim().toLowerCase();
+  if (!DEVICE_TYPES.includes(trimmed)) {
+    throw new Error(t("err_device_type_value"));
+  }
+  return trimmed;
+}
+
+function parseAvatarColorOption(value: string): string {
+  const trimmed = value.trim().toLowerCase();
+  if (!isAvatarColor(trimmed)) {
+    throw new Error(t("err_avatar_color_value"));
+  }
+  return trimmed;
+}
+
+function parseAvatarEmojiOption(value: string): string {
+  const trimmed = value.trim();
+  if (!isAvatarEmoji(trimmed)) {
+    throw new Error(t("err_avatar_emoji_value"));
+  }
+  return trimmed;
+}
+
+function parseNameCollisionsOption(value: string): CollisionStrategy {
+  const trimmed = value.trim().toLowerCase();
+  if (trimmed !== "rename" && trimmed !== "skip" && trimmed !== "fail") {
+    throw new Error(t("err_name_collisions_value"));
+  }
+  return trimmed;
+}
+
+/** `--share name=dir`, repeatable; the name is what peers browse by. */
+function parseShareOption(value: string, previous: Record<string, string> = {}): Record<string, string> {
+  const separator = value.indexOf("=");
+  const name = separator > 0 ? value.slice(0, separator).trim() : "";
+  const dir = separator > 0 ? value.slice(separator + 1).trim() : "";
+  if (!/^[^/\\]+$/.test(name) || name === "." || name === ".." || !dir) {
+    throw new Error(t("err_share_format"));
+  }
+  if (Object.prototype.hasOwnProperty.call(previous, name)) {
+    throw new Error(t("err_share_duplicate", { name }));
+  }
+  return { ...previous, [name]: resolve(dir) };
+}
+
+/**
+ * `--share-allow name=addr,addr`: only these peer addresses see the share.
+ * Addresses can be spoofed on the LAN, so this needs a pair code as well.
+ */
+function parseShareAllowOption(value: string, previous: Record<string, string[]> = {}): Record<string, string[]> {
+  const separator = value.indexOf("=");
+  const name = separator > 0 ? value.slice(0, separator).trim() : "";
+  const addresses =
+    separator > 0
+      ? value
+          .slice(separator + 1)
+          .split(",")
+          .map((item) => item.trim())
+          .filter(Boolean)
+      : [];
+  if (!name || addresses.length === 0) {
+    throw new Error(t("err_share_allow_format"));
+  }
+  return { ...previous, [name]: [...(previous[name] ?? []), ...addresses] };
+}
+
+function parseAddressListOption(value: string, previous: string[] = []): string[] {
+  return [
+    ...previous,
+    ...value
+      .split(",")
+      .map((item) => item.trim())
+      .filter(Boolean)
+  ];
+}
+
+function parseTagOption(value: string, previous: string[] = []): string[] {
+  const tag = value.trim();
+  if (!tag || tag.length > MAX_TAG_CHARS) {
+    throw new Error(t("err_tag_format", { max: MAX_TAG_CHARS }));
+  }
+  return [...previous, tag];
+}
+
+/** `--note` and `--tag` of `send`, or `undefined` when neither is given. */
+function buildTransferNote(note: string | undefined, tags: string[] | undefined): TransferNote | undefined {
+  const text = note?.trim() ?? "";
+  if (text.length > MAX_NOTE_CHARS) {
+    throw new Error(t("err_note_too_long", { max: MAX_NOTE_CHARS }));
+  }
+  const uniqueTags = [...new Set(tags ?? [])];
+  if (uniqueTags.length > MAX_NOTE_TAGS) {
+    throw new Error(t("err_too_many_tags", { max: MAX_NOTE_TAGS }));
+  }
+  if (!text && uniqueTags.length === 0) {
+    return undefined;
+  }
+  return { ...(text ? { text } : {}), ...(uniqueTags.length > 0 ? { tags: uniqueTags } : {}) };
+}
+
 function normalizePairCode(value: string): string {
   const code = value.trim();
   if (!/^\d{6}$/.test(code)) {
@@ -89,7 +236,24 @@ function resolveListenEndpointHost(): string {
   return candidates[0].address;
 }
 
-function createConfirmController() {
+interface ControlRequest {
+  id: number;
+  method: string;
+  params?: Record<string, unknown>;
+}
+
+/**
+ * Reads the listener's stdin. With `--ipc json` every line is a control
+ * request `{id, method, params}`, answered by a `response` message carrying
+ * the same id: `approve` and `reject` take a `confirmId`, any other method
+ * goes to `onRequest`. Without it, `approve <id>` and `reject <id>` answer
+ * confirm requests and any other line goes to `onCommand`, which returns
+ * false for lines it does not understand.
+ */
+function createConfirmController(
+  onCommand: (line: string) => boolean,
+  onRequest: (method: string, params: Record<string, unknown>) => Promise<unknown>
+) {
   const pending = new Map<number, (accept: boolean) => void>();
   let nextId = 0;
   const readline = createInterface({
@@ -97,35 +261,88 @@ function createConfirmController() {
     terminal: false
   });
 
+  const decide = (id: number, accept: boolean): boolean => {
+    const resolver = pending.get(id);
+    if (!resolver) {
+      return false;
+    }
+    pending.delete(id);
+    resolver(accept);
+    return true;
+  };
+
+  const answer = async (raw: string): Promise<void> => {
+    let request: ControlRequest;
+    try {
+      request = JSON.parse(raw) as ControlRequest;
+    } catch {
+      console.log(t("listen_control_ignored", { line: raw }));
+      return;
+    }
+    if (!Number.isInteger(request.id) || typeof request.method !== "string") {
+      console.log(t("listen_control_ignored", { line: raw }));
+      return;
+    }
+    const params = request.params ?? {};
+    try {
+      let result: unknown = {};
+      if (request.method === "approve" || request.method === "reject") {
+        const confirmId = Number(params.confirmId);
+        if (!decide(confirmId, request.method === "approve")) {
+          throw new Error(`no pending confirm request ${confirmId}`);
+        }
+      } else {
+        result = await onRequest(request.method, params);
+      }
+      emitIpc({ type: "response", id: request.id, ok: true, result });
+    } catch (err) {
+      emitIpc({ type: "response", id: request.id, ok: false, error: (err as Error).message });
+    }
+  };
+
   readline.on("line", (line) => {
     const trimmed = String(line).trim();
     if (!trimmed) {
       return;
     }
+    if (ipcEnabled()) {
+      void answer(trimmed);
+      return;
+    }
     const match = /^(approve|reject)\s+(\d+)$/i.exec(trimmed);
     if (!match) {
+      if (!onCommand(trimmed)) {
+        console.log(t("listen_control_ignored", { line: trimmed }));
+      }
       return;
     }
     const [, action, rawId] = match;
-    const id = Number.parseInt(rawId, 10);
-    const resolver = pending.get(id);
-    if (!resolver) {
-      return;
-    }
-    pending.delete(id);
-    resolver(action.toLowerCase() === "approve");
+    decide(Number.parseInt(rawId, 10), action.toLowerCase() === "approve");
   });
 
-  const request = (args: { from: string; path: string; size: number }): Promise<boolean> => {
+  const request = (args: {
+    from: string;
+    path: string;
+    size: number;
+    sha256: string;
+    streaming: boolean;
+    quickShare?: boolean;
+  }): Promise<boolean> => {
     const id = ++nextId;
-    console.log(
-      `[confirm-request] ${JSON.stringify({
-        id,
-        from: args.from,
-        path: args.path,
-        size: args.size
-      })}`
-    );
+    const message = {
+      id,
+      from: args.from,
+      path: args.path,
+      size: args.size,
+      sha256: args.sha256,
+      streaming: args.streaming,
+      ...(args.quickShare ? { quickShare: true } : {})
+    };
+    if (ipcEnabled()) {
+      emitIpc({ type: "confirmRequest", ...message });
+    } else {
+      console.log(`[confirm-request] ${JSON.stringify(message)}`);
+    }
     return new Promise<boolean>((resolve) => {
       pending.set(id, resolve);
     });
@@ -145,13 +362,271 @@ function createConfirmController() {
   };
 }
 
+/**
+ * `output <dir>`, `pair-code <code|off>`, `max-rate <KiB/s>` (0 lifts the
+ * limit) and `announce <on|off>` change a running listener without
+ * dropping connections.
+ */
+function applyListenControl(receiver: ReceiverHandle, line: string): boolean {
+  const [command] = line.split(/\s+/);
+  const argument = line.slice(command.length).trim();
+  let update: Promise<void>;
+  let report: string;
+  if (command === "output" && argument) {
+    const outputDir = resolve(argument);
+    update = receiver.update({ outputDir });
+    report = t("listen_output", { output: outputDir });
+  } else if (command === "pair-code" && (argument === "off" || /^\d{6}$/.test(argument))) {
+    const pairCode = argument === "off" ? null : argument;
+    update = receiver.update({ pairCode });
+    report = t("listen_pair_code", { code: pairCode ?? "off", ttlSuffix: "" });
+  } else if (command === "announce" && (argument === "on" || argument === "off")) {
+    update = receiver.update({ announce: argument === "on" });
+    report = `[listen] announce=${argument}`;
+  } else if (command === "max-rate" && /^\d+$/.test(argument)) {
+    const kib = Number.parseInt(argument, 10);
+    update = receiver.update({ maxReceiveBytesPerSecond: kib * 1024 });
+    report = t("listen_max_rate", { rate: kib > 0 ? `${formatBytes(kib * 1024)}/s` : "off" });
+  } else {
+    return false;
+  }
+  update.then(
+    () => console.log(report),
+    (err: Error) => console.log(t("listen_control_ignored", { line: `${line} (${err.message})` }))
+  );
+  return true;
+}
+
+function parseControlRecord<T>(
+  value: unknown,
+  label: string,
+  parseItem: (item: unknown) => T | null
+): Record<string, T> {
+  if (typeof value !== "object" || value === null || Array.isArray(value)) {
+    throw new Error(`${label} must be an object`);
+  }
+  const record: Record<string, T> = {};
+  for (const [key, item] of Object.entries(value)) {
+    const parsed = parseItem(item);
+    if (parsed === null) {
+      throw new Error(`${label}.${key} is invalid`);
+    }
+    record[key] = parsed;
+  }
+  return record;
+}
+
+/** Reads the `update` control request of `--ipc json` mode. */
+function parseControlUpdate(params: Record<string, unknown>): ReceiverConfigUpdate {
+  const update: ReceiverConfigUpdate = {};
+  if (params.outputDir !== undefined) {
+    if (typeof params.outputDir !== "string" || !params.outputDir.trim()) {
+      throw new Error("outputDir must be a non-empty string");
+    }
+    update.outputDir = resolve(params.outputDir);
+  }
+  if (params.pairCode !== undefined) {
+    if (params.pairCode !== null && !(typeof params.pairCode === "string" && /^\d{6}$/.test(params.pairCode))) {
+      throw new Error(t("err_pair_code_format"));
+    }
+    update.pairCode = params.pairCode as string | null;
+  }
+  if (params.maxRateKib !== undefined) {
+    if (!Number.isInteger(params.maxRateKib) || (params.maxRateKib as number) < 0) {
+      throw new Error("maxRateKib must be a non-negative integer");
+    }
+    update.maxReceiveBytesPerSecond = (params.maxRateKib as number) * 1024;
+  }
+  if (params.announce !== undefined) {
+    if (typeof params.announce !== "boolean") {
+      throw new Error("announce must be a boolean");
+    }
+    update.announce = params.announce;
+  }
+  if (params.shares !== undefined) {
+    update.shares = parseControlRecord(params.shares, "shares", (dir) =>
+      typeof dir === "string" && dir.trim() ? resolve(dir) : null
+    );
+    update.shareAllow =
+      params.shareAllow === undefined
+        ? {}
+        : parseControlRecord(params.shareAllow, "shareAllow", (addresses) =>
+            Array.isArray(addresses) && addresses.every((item) => typeof item === "string")
+              ? (addresses as string[])
+              : null
+          );
+    for (const name of Object.keys(update.shareAllow)) {
+      if (!Object.prototype.hasOwnProperty.call(update.shares, name)) {
+        throw new Error(t("err_share_allow_unknown", { name }));
+      }
+    }
+  }
+  if (params.clipboardFrom !== undefined) {
+    if (
+      !Array.isArray(params.clipboardFrom) ||
+      !params.clipboardFrom.every((item) => typeof item === "string" && item.trim())
+    ) {
+      throw new Error("clipboardFrom must be a list of addresses");
+    }
+    update.clipboardFrom = (params.clipboardFrom as string[]).map((item) => item.trim());
+  }
+  return update;
+}
+
+interface RemoteTargetOptions {
+  host?: string;
+  port: number;
+  device?: string;
+  timeout: number;
+  pairCode?: string;
+  tls?: boolean;
+  tlsCa?: string;
+  tlsInsecure?: boolean;
+  tlsFingerprint?: string;
+  tlsTofu?: boolean;
+  tlsKnownHosts?: string;
+  knock?: boolean;
+}
+
+/** Options shared by every command that connects to a receiver. */
+function withRemoteTargetOptions(command: Command): Command {
+  return command
+    .option("--host <host>", t("send_host_option"))
+    .option("--port <port>", t("send_port_option"), (v) => parseIntOption(v, t("label_port")), DEFAULT_PORT)
+    .option("--device <name>", t("send_device_option"))
+    .option(
+      "-t, --timeout <ms>",
+      t("send_timeout_option"),
+      (v) => parseIntOption(v, t("label_timeout")),
+      DEFAULT_DISCOVERY_TIMEOUT_MS
+    )
+    .option("--pair-code <code>", t("send_pair_code_option"))
+    .option("--tls", t("send_tls_option"))
+    .option("--tls-ca <path>", t("send_tls_ca_option"))
+    .option("--tls-insecure", t("send_tls_insecure_option"))
+    .option("--tls-fingerprint <sha256>", t("send_tls_fingerprint_option"))
+    .option("--tls-tofu", t("send_tls_tofu_option"))
+    .option("--tls-known-hosts <path>", t("send_tls_known_hosts_option"))
+    .option("--knock", t("send_knock_option"));
+}
+
+/**
+ * Validates the connection options and finds the receiver, through
+ * discovery unless `--host` is given.
+ */
+async function resolveRemoteTarget(opts: RemoteTargetOptions) {
+  if (opts.tlsCa && !opts.tls) {
+    throw new Error(t("err_tls_ca_requires_tls"));
+  }
+  if (opts.tlsInsecure && !opts.tls) {
+    throw new Error(t("err_tls_insecure_requires_tls"));
+  }
+  if (opts.tlsFingerprint && !opts.tls) {
+    throw new Error(t("err_tls_fingerprint_requires_tls"));
+  }
+  if (opts.tlsTofu && !opts.tls) {
+    throw new Error(t("err_tls_tofu_requires_tls"));
+  }
+  if (opts.tlsKnownHosts && !opts.tlsTofu) {
+    throw new Error(t("err_tls_known_hosts_requires_tofu"));
+  }
+  if (opts.tlsFingerprint && opts.tlsTofu) {
+    throw new Error(t("err_tls_fingerprint_tofu_conflict"));
+  }
+  const pairCode = opts.pairCode ? normalizePairCode(opts.pairCode) : undefined;
+  const tlsFingerprint = opts.tlsFingerprint ? normalizeFingerprint(opts.tlsFingerprint) : undefined;
+
+  let host = opts.host;
+  let port = opts.port;
+  let knockFirst = Boolean(opts.knock);
+
+  if (!host) {
+    const devices = await discoverDevices(opts.timeout);
+    const filtered = opts.device
+      ? devices.filter((d) => d.name.toLowerCase().includes(opts.device!.toLowerCase()))
+      : devices;
+
+    if (filtered.length === 0) {
+      throw new Error(t("err_no_receiver_found"));
+    }
+
+    const target = filtered[0];
+    host = target.host;
+    port = target.port;
+    knockFirst ||= Boolean(target.capabilities?.includes(CAPABILITY_KNOCK));
+    console.log(
+      t("discover_selected", {
+        name: target.name,
+        host,
+        port
+      })
+    );
+  }
+
+  if (knockFirst) {
+    const secret = knockSecretFromEnv();
+    if (!secret) {
+      throw new Error(t("err_knock_requires_secret"));
+    }
+    await knock(host, port, secret);
+  }
+
+  const tls = opts.tls
+    ? {
+        enabled: true,
+        caPath: opts.tlsCa ? resolve(opts.tlsCa) : undefined,
+        insecure: Boolean(opts.tlsInsecure),
+        fingerprint: tlsFingerprint,
+        trustOnFirstUse: Boolean(opts.tlsTofu),
+        knownHostsPath: opts.tlsKnownHosts ? resolve(opts.tlsKnownHosts) : undefined
+      }
+    : undefined;
+
+  return { host, port, pairCode, tls };
+}
+
+/**
+ * Desktop builds set LOCAL_SENT_PARENT_PID. If the app dies without cleaning
+ * up (a crash, a forced kill), the CLI stops itself instead of transferring
+ * on unattended; SIGTERM lets `listen` roll back its open batches first.
+ */
+function exitWithParent(): void {
+  const parentPid = Number(process.env.LOCAL_SENT_PARENT_PID);
+  if (!Number.isInteger(parentPid) || parentPid <= 0) {
+    return;
+  }
+  const timer = setInterval(() => {
+    try {
+      process.kill(parentPid, 0);
+    } catch (err) {
+      if ((err as NodeJS.ErrnoException).code === "ESRCH") {
+        clearInterval(timer);
+        process.kill(process.pid, "SIGTERM");
+      }
+    }
+  }, 2000);
+  timer.unref();
+}
+
 const program = new Command();
 
 program
   .name("local-sent")
   .description(t("app_description"))
   .option("--lang <lang>", t("lang_option_desc"), locale)
-  .version("0.1.0");
+  .option("--ipc <format>", t("ipc_option_desc"))
+  .version(CLI_VERSION)
+  .hook("preAction", (root, command) => {
+    const { ipc } = root.opts<{ ipc?: string }>();
+    if (ipc === undefined) {
+      return;
+    }
+    if (ipc !== "json") {
+      throw new Error(t("err_ipc_format"));
+    }
+    enableIpc();
+    emitIpc({ type: "hello", command: command.name(), cliVersion: CLI_VERSION });
+  });
 
 program
   .command("discover")
@@ -163,8 +638,19 @@ program
     DEFAULT_DISCOVERY_TIMEOUT_MS
   )
   .option("--json", t("discover_json_option"))
-  .action(async (opts: { timeout: number; json?: boolean }) => {
-    const devices = await discoverDevices(opts.timeout);
+  .option("--self-id <id>", t("discover_self_id_option"))
+  .option("--include-self", t("discover_include_self_option"))
+  .option("--dns-sd-zone <zone>", t("discover_dns_sd_zone_option"))
+  .option("--dns-sd-server <host>", t("discover_dns_sd_server_option"))
+  .option("--ping", t("discover_ping_option"))
+  .option("--ping-reply-port <port>", t("discover_ping_reply_port_option"), (v) => parseIntOption(v, t("label_port")))
+  .action(async (opts: { timeout: number; json?: boolean; selfId?: string; includeSelf?: boolean; dnsSdZone?: string; dnsSdServer?: string; ping?: boolean; pingReplyPort?: number }) => {
+    const devices = await discoverDevices(opts.timeout, {
+      selfId: opts.selfId,
+      includeSelf: Boolean(opts.includeSelf),
+      dnsSd: opts.dnsSdZone ? { zone: normalizeZone(opts.dnsSdZone), server: opts.dnsSdServer } : undefined,
+      ping: opts.ping || opts.pingReplyPort ? { replyPort: opts.pingReplyPort } : undefined
+    });
     if (opts.json) {
       console.log(JSON.stringify(devices, null, 2));
       return;
@@ -245,6 +731,14 @@ program
     }
   );
 
+program
+  .command("check-port")
+  .description(t("check_port_command_desc"))
+  .argument("<port>", t("check_port_arg"), (v) => parseIntOption(v, t("label_port")))
+  .action(async (port: number) => {
+    console.log(JSON.stringify({ port, ...(await probeListenPort(port)) }));
+  });
+
 program
   .command("listen")
   .description(t("listen_command_desc"))
@@ -262,8 +756,28 @@ program
   .option("--tls-cert <path>", t("listen_tls_cert_option"))
   .option("--tls-key <path>", t("listen_tls_key_option"))
   .option("--confirm-each", t("listen_confirm_each_option"))
+  .option("--keep-duplicates", t("listen_keep_duplicates_option"))
+  .option("--preserve-metadata", t("listen_preserve_metadata_option"))
+  .option("--preserve-xattrs", t("listen_preserve_xattrs_option"))
+  .option("--max-rate <kib>", t("listen_max_rate_option"), (v) => parseIntOption(v, t("label_max_rate")))
+  .option("--share <name=dir>", t("listen_share_option"), parseShareOption)
+  .option("--share-allow <name=addresses>", t("listen_share_allow_option"), parseShareAllowOption)
+  .option("--clipboard-from <addresses>", t("listen_clipboard_from_option"), parseAddressListOption)
+  .option("--bind <address>", t("listen_bind_option"))
+  .option("--device-id <id>", t("listen_device_id_option"))
+  .option("--device-type <type>", t("listen_device_type_option"), parseDeviceTypeOption)
+  .option("--avatar-color <#rrggbb>", t("listen_avatar_color_option"), parseAvatarColorOption)
+  .option("--avatar-emoji <emoji>", t("listen_avatar_emoji_option"), parseAvatarEmojiOption)
+  .option("--webdav <url>", t("listen_webdav_option"))
+  .option("--webdav-user <user>", t("listen_webdav_user_option"))
+  .option("--quick-share", t("listen_quick_share_option"))
+  .option("--dns-sd-zone <zone>", t("listen_dns_sd_zone_option"))
+  .option("--dns-sd-server <host>", t("listen_dns_sd_server_option"))
+  .option("--answer-pings", t("listen_answer_pings_option"))
+  .option("--stealth", t("listen_stealth_option"))
+  .option("--hidden", t("listen_hidden_option"))
   .action(
-    async (opts: { port: number; output: string; name?: string; pairCode?: string; pairGenerate?: boolean; pairOnce?: boolean; pairTtl?: number; tlsCert?: string; tlsKey?: string; confirmEach?: boolean }) => {
+    async (opts: { port: number; output: string; bind?: string; deviceId?: string; deviceType?: string; avatarColor?: string; avatarEmoji?: string; webdav?: string; webdavUser?: string; quickShare?: boolean; dnsSdZone?: string; dnsSdServer?: string; answerPings?: boolean; stealth?: boolean; hidden?: boolean; name?: string; pairCode?: string; pairGenerate?: boolean; pairOnce?: boolean; pairTtl?: number; tlsCert?: string; tlsKey?: string; confirmEach?: boolean; keepDuplicates?: boolean; preserveMetadata?: boolean; preserveXattrs?: boolean; maxRate?: number; share?: Record<string, string>; shareAllow?: Record<string, string[]>; clipboardFrom?: string[] }) => {
       const outputDir = resolve(opts.output);
       const serviceName = opts.name ?? hostname();
       if ((opts.tlsCert && !opts.tlsKey) || (!opts.tlsCert && opts.tlsKey)) {
@@ -278,6 +792,27 @@ program
       if (opts.pairOnce && !opts.pairCode && !opts.pairGenerate && !opts.pairTtl) {
         throw new Error(t("err_pair_once_requirement"));
       }
+      const knockSecretFromEnvironment = opts.stealth ? knockSecretFromEnv() : undefined;
+      const knockSecret = opts.stealth ? (knockSecretFromEnvironment ?? generateKnockSecret()) : undefined;
+      for (const name of Object.keys(opts.shareAllow ?? {})) {
+        if (!opts.share || !Object.prototype.hasOwnProperty.call(opts.share, name)) {
+          throw new Error(t("err_share_allow_unknown", { name }));
+        }
+      }
+
+      const webdav = opts.webdav
+        ? parseWebDavTarget(opts.webdav, opts.webdavUser, process.env.LOCAL_SENT_WEBDAV_PASSWORD)
+        : undefined;
+      const dnsSdKey = process.env.LOCAL_SENT_DNS_SD_KEY?.trim();
+      const dnsSd = opts.dnsSdZone
+        ? {
+            zone: normalizeZone(opts.dnsSdZone),
+            server: opts.dnsSdServer,
+            key: dnsSdKey ? parseTsigKey(dnsSdKey) : undefined,
+            addresses: opts.bind && opts.bind !== "0.0.0.0" && opts.bind !== "::" ? [opts.bind] : undefined,
+            onError: (error: Error) => console.error(t("listen_dns_sd_failed", { error: error.message }))
+          }
+        : undefined;
 
       const pairCode = opts.pairGenerate
         ? generatePairCode()
@@ -287,16 +822,61 @@ program
             ? generatePairCode()
             : undefined;
 
-      const confirmController = opts.confirmEach ? createConfirmController() : null;
+      let applyControl: (line: string) => boolean = () => false;
+      let markReady: (receiver: ReceiverHandle) => void = () => undefined;
+      const ready = new Promise<ReceiverHandle>((resolveReady) => {
+        markReady = resolveReady;
+      });
+      let shutdown: (signal: string) => Promise<void> = async () => undefined;
+      const handleRequest = async (method: string, params: Record<string, unknown>): Promise<unknown> => {
+        const receiver = await ready;
+        if (method === "update") {
+          await receiver.update(parseControlUpdate(params));
+          return {};
+        }
+        if (method === "transfers") {
+          return { transfers: receiver.transfers() };
+        }
+        if (method === "shutdown") {
+          // Answer first; the process exits once the receiver has stopped.
+          setImmediate(() => void shutdown("control"));
+          return {};
+        }
+        throw new Error(`unknown control method: ${method}`);
+      };
+      // Desktop builds pass --confirm-each and --ipc json and drive the
+      // running listener over stdin (see `createConfirmController`).
+      const confirmController =
+        opts.confirmEach || ipcEnabled()
+          ? createConfirmController((line) => applyControl(line), handleRequest)
+          : null;
 
       const stop = await startReceiver({
         port: opts.port,
+        host: opts.bind,
         outputDir,
         serviceName,
+        deviceId: opts.deviceId?.trim() || undefined,
+        deviceType: opts.deviceType,
+        avatarColor: opts.avatarColor,
+        avatarEmoji: opts.avatarEmoji,
         pairCode,
         rotatePairCodePerTransfer: Boolean(opts.pairOnce),
         pairCodeTtlSeconds: opts.pairTtl,
         generatePairCode,
+        keepDuplicates: Boolean(opts.keepDuplicates),
+        preserveMetadata: Boolean(opts.preserveMetadata),
+        preserveXattrs: Boolean(opts.preserveXattrs),
+        maxReceiveBytesPerSecond: opts.maxRate ? opts.maxRate * 1024 : undefined,
+        shares: opts.share,
+        shareAllow: opts.shareAllow,
+        clipboardFrom: opts.clipboardFrom,
+        webdav,
+        quickShare: Boolean(opts.quickShare),
+        dnsSd,
+        answerPings: Boolean(opts.answerPings),
+        stealth: knockSecret ? { secret: knockSecret } : undefined,
+        hidden: Boolean(opts.hidden),
         onPairCodeChange: (nextCode, reason) => {
           if (nextCode) {
             const ttlSuffix = opts.pairTtl ? ` valid-for=${opts.pairTtl}s` : "";
@@ -309,12 +889,15 @@ program
             );
           }
         },
-        confirmTransfer: confirmController
-          ? async ({ from, relativePath, fileSize }) => {
+        confirmTransfer: confirmController && opts.confirmEach
+          ? async ({ from, relativePath, fileSize, sha256, streaming, quickShare }) => {
               const accepted = await confirmController.request({
                 from,
                 path: relativePath,
-                size: fileSize
+                size: fileSize,
+                sha256,
+                streaming,
+                quickShare
               });
               return { accept: accepted };
             }
@@ -336,8 +919,42 @@ program
       if (opts.tlsCert) {
         console.log(t("listen_tls_enabled"));
       }
+      if (webdav) {
+        console.log(t("listen_webdav", { url: webdav.url.href }));
+      }
+      if (opts.quickShare) {
+        console.log(t("listen_quick_share"));
+      }
+      if (dnsSd) {
+        console.log(t("listen_dns_sd", { zone: dnsSd.zone }));
+      }
+      if (opts.answerPings) {
+        console.log(t("listen_answer_pings"));
+      }
+      if (opts.stealth) {
+        console.log(t("listen_stealth"));
+        if (knockSecret && !knockSecretFromEnvironment) {
+          console.log(t("listen_knock_secret_generated", { secret: knockSecret }));
+        }
+      }
+      if (opts.maxRate) {
+        console.log(t("listen_max_rate", { rate: `${formatBytes(opts.maxRate * 1024)}/s` }));
+      }
+      for (const [name, dir] of Object.entries(opts.share ?? {})) {
+        const allowed = opts.shareAllow?.[name];
+        console.log(t("listen_share", { name, dir: allowed ? `${dir} (${allowed.join(", ")})` : dir }));
+      }
+      emitIpc({
+        type: "listening",
+        service: serviceName,
+        host: listenHost,
+        port: opts.port,
+        output: outputDir,
+        tls: Boolean(opts.tlsCert)
+      });
+      applyControl = (line) => applyListenControl(stop, line);
       let stopped = false;
-      const shutdown = async (signal: string): Promise<void> => {
+      shutdown = async (signal: string): Promise<void> => {
         if (stopped) {
           return;
         }
@@ -348,6 +965,8 @@ program
         process.exit(0);
       };
 
+      markReady(stop);
+
       process.on("SIGINT", () => {
         void shutdown("SIGINT");
       });
@@ -361,105 +980,145 @@ program
     }
   );
 
-program
-  .command("send")
-  .description(t("send_command_desc"))
-  .argument("<path>", t("send_path_arg"))
-  .option("--host <host>", t("send_host_option"))
-  .option("--port <port>", t("send_port_option"), (v) => parseIntOption(v, t("label_port")), DEFAULT_PORT)
-  .option("--device <name>", t("send_device_option"))
+withRemoteTargetOptions(
+  program.command("send").description(t("send_command_desc")).argument("<path>", t("send_path_arg"))
+)
+  .option("--name <name>", t("send_name_option"))
+  .option("--streams <count>", t("send_streams_option"), parseStreamsOption, "auto" as number | "auto")
+  .option("--symlinks <mode>", t("send_symlinks_option"), parseSymlinksOption, "skip" as SymlinkMode)
+  .option("--hardlinks", t("send_hardlinks_option"))
+  .option("--xattrs", t("send_xattrs_option"))
+  .option("--atomic", t("send_atomic_option"))
+  .option("--unicode <form>", t("send_unicode_option"), parseUnicodeOption, "nfc" as UnicodeForm)
   .option(
-    "-t, --timeout <ms>",
-    t("send_timeout_option"),
-    (v) => parseIntOption(v, t("label_timeout")),
-    DEFAULT_DISCOVERY_TIMEOUT_MS
+    "--name-collisions <strategy>",
+    t("send_name_collisions_option"),
+    parseNameCollisionsOption,
+    "rename" as CollisionStrategy
   )
-  .option("--pair-code <code>", t("send_pair_code_option"))
-  .option("--tls", t("send_tls_option"))
-  .option("--tls-ca <path>", t("send_tls_ca_option"))
-  .option("--tls-insecure", t("send_tls_insecure_option"))
-  .option("--tls-fingerprint <sha256>", t("send_tls_fingerprint_option"))
-  .option("--tls-tofu", t("send_tls_tofu_option"))
-  .option("--tls-known-hosts <path>", t("send_tls_known_hosts_option"))
+  .option("--note <text>", t("send_note_option"))
+  .option("--tag <tag>", t("send_tag_option"), parseTagOption)
+  .option("--skip-from <file>", t("send_skip_from_option"))
+  .option("--dry-run", t("send_dry_run_option"))
   .action(
     async (
       pathInput: string,
-      opts: {
-        host?: string;
-        port: number;
-        device?: string;
-        timeout: number;
-        pairCode?: string;
-        tls?: boolean;
-        tlsCa?: string;
-        tlsInsecure?: boolean;
-        tlsFingerprint?: string;
-        tlsTofu?: boolean;
-        tlsKnownHosts?: string;
+      opts: RemoteTargetOptions & {
+        name?: string;
+        streams: number | "auto";
+        symlinks: SymlinkMode;
+        hardlinks?: boolean;
+        xattrs?: boolean;
+        unicode: UnicodeForm;
+        nameCollisions: CollisionStrategy;
+        atomic?: boolean;
+        note?: string;
+        tag?: string[];
+        skipFrom?: string;
+        dryRun?: boolean;
       }
     ) => {
-      if (opts.tlsCa && !opts.tls) {
-        throw new Error(t("err_tls_ca_requires_tls"));
+      const streamInput = pathInput === "-";
+      if (streamInput && !opts.name?.trim()) {
+        throw new Error(t("err_stream_name_required"));
       }
-      if (opts.tlsInsecure && !opts.tls) {
-        throw new Error(t("err_tls_insecure_requires_tls"));
+      if (streamInput && opts.dryRun) {
+        throw new Error(t("err_dry_run_stream"));
       }
-      if (opts.tlsFingerprint && !opts.tls) {
-        throw new Error(t("err_tls_fingerprint_requires_tls"));
+      const note = buildTransferNote(opts.note, opts.tag);
+      const { host, port, pairCode, tls } = await resolveRemoteTarget(opts);
+
+      if (streamInput) {
+        const ack = await sendStream({
+          input: process.stdin,
+          relativePath: opts.name!.trim(),
+          host,
+          port,
+          pairCode,
+          tls,
+          note
+        });
+        console.log(
+          t("send_done", {
+            fileCount: 1,
+            bytes: formatBytes(ack.receivedBytes ?? 0),
+            resumed: formatBytes(0)
+          })
+        );
+        emitIpc({
+          type: "sendDone",
+          files: 1,
+          bytes: ack.receivedBytes ?? 0,
+          resumedBytes: 0,
+          manifest: [
+            {
+              path: opts.name!.trim(),
+              bytes: ack.receivedBytes ?? 0,
+              resumedBytes: 0,
+              deduplicated: false,
+              savedPath: ack.savedPath
+            }
+          ]
+        });
+        if (ack.savedPath) {
+          console.log(t("send_saved_path", { savedPath: ack.savedPath }));
+          emitIpc({ type: "sendSaved", path: ack.savedPath, deduplicated: false });
+        }
+        return;
       }
-      if (opts.tlsTofu && !opts.tls) {
-        throw new Error(t("err_tls_tofu_requires_tls"));
+
+      const named = applyNameSafety(
+        await buildTransferEntries(resolve(pathInput), {
+          symlinks: opts.symlinks,
+          hardlinks: Boolean(opts.hardlinks),
+          xattrs: Boolean(opts.xattrs)
+        }),
+        { unicode: opts.unicode, collisions: opts.nameCollisions }
+      );
+      for (const issue of named.issues) {
+        console.log(t("send_name_issue", { kind: issue.kind, paths: issue.paths.join(", ") }));
       }
-      if (opts.tlsKnownHosts && !opts.tlsTofu) {
-        throw new Error(t("err_tls_known_hosts_requires_tofu"));
+      for (const item of named.renamed) {
+        console.log(t("send_name_renamed", item));
       }
-      if (opts.tlsFingerprint && opts.tlsTofu) {
-        throw new Error(t("err_tls_fingerprint_tofu_conflict"));
+      for (const path of named.skipped) {
+        console.log(t("send_name_skipped", { path }));
       }
-      const pairCode = opts.pairCode ? normalizePairCode(opts.pairCode) : undefined;
-      const tlsFingerprint = opts.tlsFingerprint ? normalizeFingerprint(opts.tlsFingerprint) : undefined;
-
-      let host = opts.host;
-      let port = opts.port;
-
-      if (!host) {
-        const devices = await discoverDevices(opts.timeout);
-        const filtered = opts.device
-          ? devices.filter((d) => d.name.toLowerCase().includes(opts.device!.toLowerCase()))
-          : devices;
-
-        if (filtered.length === 0) {
-          throw new Error(t("err_no_receiver_found"));
-        }
-
-        const target = filtered[0];
-        host = target.host;
-        port = target.port;
+      let entries = named.entries;
+      if (opts.skipFrom) {
+        const done = new Set(
+          (await readFile(opts.skipFrom, "utf8"))
+            .split(/\r?\n/)
+            .map((line) => line.trim())
+            .filter(Boolean)
+        );
+        entries = entries.filter((entry) => !done.has(entry.relativePath));
+        console.log(t("send_skip_from_skipped", { count: named.entries.length - entries.length }));
+      }
+      if (opts.dryRun) {
+        const payload = entries.filter((entry) => !entry.link);
+        const files = payload.length;
+        const bytes = payload.reduce((sum, entry) => sum + entry.size, 0);
+        const { freeBytes } = await probeReceiver({ host, port, pairCode, tls, files, totalBytes: bytes });
         console.log(
-          t("discover_selected", {
-            name: target.name,
-            host,
-            port
+          t("send_dry_run_ok", {
+            fileCount: files,
+            bytes: formatBytes(bytes),
+            free: freeBytes === undefined ? "?" : formatBytes(freeBytes)
           })
         );
+        emitIpc({ type: "dryRun", files, bytes, freeBytes });
+        return;
       }
-
-      const entries = await buildTransferEntries(resolve(pathInput));
       const batch = await sendEntries({
         entries,
         host,
         port,
         pairCode,
-        tls: opts.tls
-          ? {
-              enabled: true,
-              caPath: opts.tlsCa ? resolve(opts.tlsCa) : undefined,
-              insecure: Boolean(opts.tlsInsecure),
-              fingerprint: tlsFingerprint,
-              trustOnFirstUse: Boolean(opts.tlsTofu),
-              knownHostsPath: opts.tlsKnownHosts ? resolve(opts.tlsKnownHosts) : undefined
-            }
-          : undefined
+        tls,
+        streams: opts.streams,
+        atomic: Boolean(opts.atomic),
+        note
       });
 
       const transferredBytes = batch.results.reduce((sum, item) => sum + (item.ack.receivedBytes ?? 0), 0);
@@ -470,16 +1129,90 @@ program
           resumed: formatBytes(batch.resumedBytes)
         })
       );
+      emitIpc({
+        type: "sendDone",
+        files: batch.fileCount,
+        bytes: transferredBytes,
+        resumedBytes: batch.resumedBytes,
+        manifest: batch.results.map(({ entry, ack }) => ({
+          path: entry.relativePath,
+          bytes: entry.size,
+          resumedBytes: ack.resumedFrom ?? 0,
+          deduplicated: Boolean(ack.deduplicated),
+          savedPath: ack.savedPath
+        }))
+      });
 
       for (const item of batch.results) {
-        if (item.ack.savedPath) {
+        if (item.ack.deduplicated && item.ack.savedPath) {
+          console.log(t("send_already_have", { savedPath: item.ack.savedPath }));
+        } else if (item.ack.savedPath) {
           console.log(t("send_saved_path", { savedPath: item.ack.savedPath }));
         }
+        if (item.ack.savedPath) {
+          emitIpc({ type: "sendSaved", path: item.ack.savedPath, deduplicated: Boolean(item.ack.deduplicated) });
+        }
       }
     }
   );
 
+withRemoteTargetOptions(
+  program.command("shares").description(t("shares_command_desc")).argument("[path]", t("shares_path_arg"), "")
+)
+  .option("--json", t("shares_json_option"))
+  .action(async (remotePath: string, opts: RemoteTargetOptions & { json?: boolean }) => {
+    const { host, port, pairCode, tls } = await resolveRemoteTarget(opts);
+    const entries = await listRemoteShares({ host, port, pairCode, tls, remotePath });
+    if (opts.json) {
+      console.log(JSON.stringify(entries, null, 2));
+      return;
+    }
+    if (entries.length === 0) {
+      console.log(t("shares_none"));
+      return;
+    }
+    for (const entry of entries) {
+      console.log(entry.isDir ? `${entry.name}/` : `${entry.name}  ${formatBytes(entry.size)}`);
+    }
+  });
+
+withRemoteTargetOptions(
+  program.command("pull").description(t("pull_command_desc")).argument("<remote-path>", t("pull_path_arg"))
+)
+  .option("-o, --output <path>", t("pull_output_option"), ".")
+  .action(async (remotePath: string, opts: RemoteTargetOptions & { output: string }) => {
+    const { host, port, pairCode, tls } = await resolveRemoteTarget(opts);
+    const pulled = await pullFile({ host, port, pairCode, tls, remotePath, destination: resolve(opts.output) });
+    console.log(t("pull_done", { path: pulled.savedPath, bytes: formatBytes(pulled.bytes) }));
+    emitIpc({ type: "pullDone", path: pulled.savedPath, bytes: pulled.bytes, sha256: pulled.sha256 });
+  });
+
+withRemoteTargetOptions(
+  program.command("message").description(t("message_command_desc")).argument("<text>", t("message_text_arg"))
+)
+  .option("--from <name>", t("message_from_option"))
+  .action(async (text: string, opts: RemoteTargetOptions & { from?: string }) => {
+    const { host, port, pairCode, tls } = await resolveRemoteTarget(opts);
+    await sendTextMessage({ host, port, pairCode, tls, text, from: opts.from ?? hostname() });
+    console.log(t("message_delivered", { host, port }));
+  });
+
+withRemoteTargetOptions(program.command("clipboard").description(t("clipboard_command_desc"))).action(
+  async (opts: RemoteTargetOptions) => {
+    const chunks: Buffer[] = [];
+    for await (const chunk of process.stdin) {
+      chunks.push(Buffer.isBuffer(chunk) ? chunk : Buffer.from(chunk));
+    }
+    const { host, port, pairCode, tls } = await resolveRemoteTarget(opts);
+    await sendClipboardText({ host, port, pairCode, tls, text: Buffer.concat(chunks).toString("utf8") });
+    console.log(t("clipboard_delivered", { host, port }));
+  }
+);
+
+exitWithParent();
+
 program.parseAsync(process.argv).catch((err: Error) => {
+  emitIpc({ type: "error", message: err.message });
   console.error(t("error_prefix", { message: err.message }));
   process.exit(1);
 });
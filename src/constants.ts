@@ -5,3 +5,16 @@ export const DEFAULT_DISCOVERY_TIMEOUT_MS = 3000;
 export const HEADER_MAX_BYTES = 64 * 1024;
 export const UDP_DISCOVERY_PORT = 37374;
 export const UDP_DISCOVERY_MAGIC = "LOCAL_SENT_DISCOVER_V1";
+// Opt-in broadcast pings with a rendezvous reply port (see ping.ts).
+export const PING_PORT = 37375;
+export const PING_MAGIC = "LOCAL_SENT_PING_V1";
+// Optional features a listener announces, in the `caps` TXT record and the
+// `capabilities` field of UDP and ping replies:
+//   ping   answers broadcast pings on PING_PORT
+//   knock  stealth listener; connect only after a knock (see knock.ts)
+export const CAPABILITY_PING = "ping";
+export const CAPABILITY_KNOCK = "knock";
+export const KNOCK_MAGIC = "LOCAL_SENT_KNOCK_V1";
+export const CLI_VERSION = "0.1.0";
+// Newest `TransferHeader` version this build sends and accepts.
+export const PROTOCOL_VERSION = 5;
@@ -0,0 +1,140 @@
+import { promises as fsPromises } from "fs";
+import { isAbsolute, join, relative, resolve } from "path";
+
+export const CONTENT_INDEX_FILE = ".local-sent-index.json";
+
+export type ContentState = "partial" | "complete";
+
+interface ContentIndexEntry {
+  path: string;
+  size: number;
+  state: ContentState;
+  mtimeMs?: number;
+}
+
+interface ContentIndexData {
+  version: 1;
+  entries: Record<string, ContentIndexEntry>;
+}
+
+export interface ContentMatch {
+  state: ContentState;
+  absolutePath: string;
+}
+
+/**
+ * Receive-side index of partial and completed files keyed by SHA-256, stored
+ * in the output directory so it moves with the received files. Lets a renamed
+ * file resume from its old partial and lets an identical re-send finish
+ * without moving any bytes.
+ */
+export class ContentIndex {
+  private readonly indexPath: string;
+  private pending: Promise<unknown> = Promise.resolve();
+
+  constructor(private readonly outputDir: string) {
+    this.indexPath = join(outputDir, CONTENT_INDEX_FILE);
+  }
+
+  async lookup(sha256: string, size: number): Promise<ContentMatch | null> {
+    const key = sha256.toLowerCase();
+    const data = await this.serialize(() => this.load());
+    const entry = data.entries[key];
+    if (!entry || entry.size !== size) {
+      return null;
+    }
+
+    const absolutePath = this.resolveEntryPath(entry.path);
+    const current = absolutePath ? await statFile(absolutePath) : null;
+    const valid =
+      current !== null &&
+      (entry.state === "partial"
+        ? current.size <= size
+        : current.size === size && Math.trunc(current.mtimeMs) === entry.mtimeMs);
+    if (!absolutePath || !valid) {
+      await this.forget(key);
+      return null;
+    }
+    return { state: entry.state, absolutePath };
+  }
+
+  async markPartial(sha256: string, absolutePath: string, size: number): Promise<void> {
+    await this.update((data) => {
+      data.entries[sha256.toLowerCase()] = {
+        path: relative(this.outputDir, absolutePath),
+        size,
+        state: "partial"
+      };
+    });
+  }
+
+  async markComplete(sha256: string, absolutePath: string, size: number): Promise<void> {
+    const current = await statFile(absolutePath);
+    if (!current) {
+      return;
+    }
+    await this.update((data) => {
+      data.entries[sha256.toLowerCase()] = {
+        path: relative(this.outputDir, absolutePath),
+        size,
+        state: "complete",
+        mtimeMs: Math.trunc(current.mtimeMs)
+      };
+    });
+  }
+
+  async forget(sha256: string): Promise<void> {
+    await this.update((data) => {
+      delete data.entries[sha256.toLowerCase()];
+    });
+  }
+
+  // Entries come from a file on disk, so never follow one outside the output directory.
+  private resolveEntryPath(entryPath: string): string | null {
+    const absolutePath = resolve(this.outputDir, entryPath);
+    const rel = relative(this.outputDir, absolutePath);
+    if (!rel || rel.startsWith("..") || isAbsolute(rel)) {
+      return null;
+    }
+    return absolutePath;
+  }
+
+  private async load(): Promise<ContentIndexData> {
+    try {
+      const raw = await fsPromises.readFile(this.indexPath, "utf8");
+      const parsed = JSON.parse(raw) as Partial<ContentIndexData>;
+      if (parsed && typeof parsed.entries === "object" && parsed.entries !== null) {
+        return { version: 1, entries: parsed.entries };
+      }
+    } catch {
+      // Missing or corrupt index: start over, the files themselves are untouched.
+    }
+    return { version: 1, entries: {} };
+  }
+
+  private update(mutate: (data: ContentIndexData) => void): Promise<void> {
+    return this.serialize(async () => {
+      const data = await this.load();
+      mutate(data);
+      const tempPath = `${this.indexPath}.tmp`;
+      await fsPromises.writeFile(tempPath, `${JSON.stringify(data, null, 2)}\n`, "utf8");
+      await fsPromises.rename(tempPath, this.indexPath);
+    });
+  }
+
+  // Concurrent transfers share one index file, so reads and writes are queued.
+  private serialize<T>(task: () => Promise<T>): Promise<T> {
+    const run = this.pending.then(task, task);
+    this.pending = run.catch(() => undefined);
+    return run;
+  }
+}
+
+async function statFile(filePath: string): Promise<{ size: number; mtimeMs: number } | null> {
+  try {
+    const stat = await fsPromises.stat(filePath);
+    return stat.isFile() ? { size: stat.size, mtimeMs: stat.mtimeMs } : null;
+  } catch {
+    return null;
+  }
+}
@@ -2,21 +2,102 @@ import { Bonjour, Service } from "bonjour-service";
 import { createSocket, Socket as DgramSocket } from "dgram";
 import { networkInterfaces } from "os";
 import {
+  CAPABILITY_PING,
+  CLI_VERSION,
   DEFAULT_DISCOVERY_TIMEOUT_MS,
+  PROTOCOL_VERSION,
   SERVICE_PROTOCOL,
   SERVICE_TYPE,
   UDP_DISCOVERY_MAGIC,
   UDP_DISCOVERY_PORT
 } from "./constants";
+import { browseDnsSd, DnsSdPublication, DnsSdZone, publishDnsSd } from "./dnsSd";
+import { createPingResponder, pingListeners, PingSigner } from "./ping";
+import {
+  announcementFromSsdp,
+  parseSsdpMessage,
+  SSDP_ADDRESS,
+  SSDP_PORT,
+  ssdpSearchRequest,
+  startSsdpResponder
+} from "./ssdp";
 
 export interface DiscoveredDevice {
   name: string;
   host: string;
   port: number;
   addresses: string[];
+  // Announced by the listener, so one device seen on several interfaces is
+  // listed once. Absent for older listeners.
+  id?: string;
+  // SHA-256 of the listener's TLS certificate, when it serves TLS.
+  fingerprint?: string;
+  // What the listener supports, so a sender knows before connecting. All
+  // absent for listeners that predate announcing them.
+  version?: string;
+  protocolVersion?: number;
+  tls?: boolean;
+  pairCodeRequired?: boolean;
+  // Optional features, e.g. `CAPABILITY_PING`; absent when none.
+  capabilities?: string[];
+  // How the device shows itself, e.g. `laptop` with `#3b82f6` and an emoji.
+  deviceType?: string;
+  avatarColor?: string;
+  avatarEmoji?: string;
+  // Set when only the SSDP fallback found the device.
+  source?: "ssdp";
+}
+
+/** What a listener announces about itself besides its name and port. */
+export interface ServiceIdentity {
+  id: string;
+  fingerprint?: string;
+  tls?: boolean;
+  pairCodeRequired?: boolean;
+  capabilities?: string[];
+  deviceType?: string;
+  avatarColor?: string;
+  avatarEmoji?: string;
+}
+
+export const DEVICE_TYPES = ["laptop", "desktop", "server"];
+const MAX_AVATAR_EMOJI_CHARS = 8;
+
+type AnnouncedFields = Pick<
+  DiscoveredDevice,
+  | "id"
+  | "fingerprint"
+  | "version"
+  | "protocolVersion"
+  | "tls"
+  | "pairCodeRequired"
+  | "capabilities"
+  | "deviceType"
+  | "avatarColor"
+  | "avatarEmoji"
+>;
+
+// Newer listeners may announce types this version does not know.
+export function isDeviceType(value: unknown): value is string {
+  return typeof value === "string" && /^[a-z]{1,16}$/.test(value);
+}
+
+export function isAvatarColor(value: unknown): value is string {
+  return typeof value === "string" && /^#[0-9a-f]{6}$/i.test(value);
 }
 
-interface UdpDiscoveryReply {
+// Emoji sequences keep their joiners; other invisible and bidi characters,
+// which could disguise the name next to the avatar, are refused.
+export function isAvatarEmoji(value: unknown): value is string {
+  return (
+    typeof value === "string" &&
+    value.length > 0 &&
+    [...value].length <= MAX_AVATAR_EMOJI_CHARS &&
+    !/[\p{Cc}\s\u00ad\u061c\u180e\u200b\u200c\u200e\u200f\u202a-\u202e\u2060-\u2064\u2066-\u206f\ufeff]/u.test(value)
+  );
+}
+
+interface UdpDiscoveryReply extends AnnouncedFields {
   magic: string;
   name: string;
   port: number;
@@ -26,24 +107,53 @@ export interface DiscoverDevicesOptions {
   includeSelf?: boolean;
   includeLoopback?: boolean;
   onlyLanIpv4?: boolean;
+  // The id our own listener announces. Devices announcing an id are only
+  // treated as this machine when it matches; local addresses are the
+  // fallback for devices without one.
+  selfId?: string;
+  // Also browse this unicast DNS zone, for listeners mDNS cannot reach.
+  dnsSd?: DnsSdZone;
+  // Also ping listeners that answer broadcast pings, with replies sent to
+  // `replyPort` (any free port when unset).
+  ping?: { replyPort?: number };
 }
 
 interface ResolvedDiscoverDevicesOptions {
   includeSelf: boolean;
   includeLoopback: boolean;
   onlyLanIpv4: boolean;
+  selfId?: string;
+  dnsSd?: DnsSdZone;
+  ping?: { replyPort?: number };
+}
+
+/** Publishes to a unicast DNS zone alongside mDNS. */
+export interface DnsSdListenOptions extends DnsSdPublication {
+  // Publishing runs in the background; failures are reported here.
+  onError?: (error: Error) => void;
+}
+
+/** Announcement paths besides mDNS, UDP and SSDP, all off by default. */
+export interface PublishOptions {
+  dnsSd?: DnsSdListenOptions;
+  // Answer broadcast pings, signing replies with the TLS key when given.
+  answerPings?: boolean;
+  pingSigner?: PingSigner;
 }
 
 function resolveDiscoverOptions(options?: DiscoverDevicesOptions): ResolvedDiscoverDevicesOptions {
   return {
     includeSelf: options?.includeSelf ?? false,
     includeLoopback: options?.includeLoopback ?? false,
-    onlyLanIpv4: options?.onlyLanIpv4 ?? true
+    onlyLanIpv4: options?.onlyLanIpv4 ?? true,
+    selfId: options?.selfId?.trim() || undefined,
+    dnsSd: options?.dnsSd?.zone.trim() ? options.dnsSd : undefined,
+    ping: options?.ping
   };
 }
 
 function serviceToDevice(
-  service: Service,
+  service: Pick<Service, "name" | "port" | "addresses" | "txt">,
   options: ResolvedDiscoverDevicesOptions
 ): DiscoveredDevice | null {
   const addresses = normalizeAddresses(service.addresses ?? [], options);
@@ -51,14 +161,89 @@ function serviceToDevice(
   if (!host) {
     return null;
   }
+  // TXT values are strings; flags are "1" or "0".
+  const txt = (service.txt ?? {}) as Record<string, unknown>;
+  const flag = (value: unknown): boolean | undefined =>
+    value === "1" ? true : value === "0" ? false : undefined;
+  const decodeTxt = (value: unknown): string | undefined => {
+    try {
+      return typeof value === "string" ? decodeURIComponent(value) : undefined;
+    } catch {
+      return undefined;
+    }
+  };
   return {
     name: service.name,
     host,
     port: service.port,
-    addresses
+    addresses,
+    ...announcedFields({
+      id: txt.id,
+      fingerprint: txt.fp,
+      version: txt.v,
+      protocolVersion: typeof txt.pv === "string" ? Number.parseInt(txt.pv, 10) : undefined,
+      tls: flag(txt.tls),
+      pairCodeRequired: flag(txt.pair),
+      capabilities: typeof txt.caps === "string" ? txt.caps.split(",") : undefined,
+      deviceType: txt.dt,
+      avatarColor: txt.ac,
+      avatarEmoji: decodeTxt(txt.ae)
+    })
   };
 }
 
+/** Keeps the announced fields that are well formed and drops the rest. */
+function announcedFields(raw: Record<keyof AnnouncedFields, unknown>): AnnouncedFields {
+  const fields: AnnouncedFields = {};
+  if (typeof raw.id === "string" && raw.id.trim()) {
+    fields.id = raw.id.trim();
+  }
+  if (typeof raw.fingerprint === "string" && /^[0-9a-f]{64}$/.test(raw.fingerprint)) {
+    fields.fingerprint = raw.fingerprint;
+  }
+  if (typeof raw.version === "string" && raw.version.trim()) {
+    fields.version = raw.version.trim().slice(0, 32);
+  }
+  if (typeof raw.protocolVersion === "number" && Number.isInteger(raw.protocolVersion) && raw.protocolVersion > 0) {
+    fields.protocolVersion = raw.protocolVersion;
+  }
+  if (typeof raw.tls === "boolean") {
+    fields.tls = raw.tls;
+  }
+  if (typeof raw.pairCodeRequired === "boolean") {
+    fields.pairCodeRequired = raw.pairCodeRequired;
+  }
+  if (Array.isArray(raw.capabilities)) {
+    // Unknown flags are kept, so newer listeners' features still show.
+    const capabilities = raw.capabilities.filter(
+      (item): item is string => typeof item === "string" && /^[a-z0-9-]{1,32}$/.test(item)
+    );
+    if (capabilities.length > 0) {
+      fields.capabilities = [...new Set(capabilities)].slice(0, 16);
+    }
+  }
+  if (isDeviceType(raw.deviceType)) {
+    fields.deviceType = raw.deviceType;
+  }
+  if (isAvatarColor(raw.avatarColor)) {
+    fields.avatarColor = raw.avatarColor.toLowerCase();
+  }
+  if (isAvatarEmoji(raw.avatarEmoji)) {
+    fields.avatarEmoji = raw.avatarEmoji;
+  }
+  return fields;
+}
+
+function deviceKey(device: DiscoveredDevice): string {
+  if (device.id) {
+    return `id:${device.id}`;
+  }
+  if (device.fingerprint) {
+    return `fp:${device.fingerprint}`;
+  }
+  return `${device.host}:${device.port}`;
+}
+
 function chooseAddress(
   addresses: string[],
   options: ResolvedDiscoverDevicesOptions
@@ -162,7 +347,10 @@ function localIpv4Set(options: ResolvedDiscoverDevicesOptions): Set<string> {
   return result;
 }
 
-function isSelfDevice(device: DiscoveredDevice, localAddrs: Set<string>): boolean {
+function isSelfDevice(device: DiscoveredDevice, localAddrs: Set<string>, selfId?: string): boolean {
+  if (selfId && device.id) {
+    return device.id === selfId;
+  }
   if (localAddrs.has(device.host)) {
     return true;
   }
@@ -173,13 +361,17 @@ function mergeDevices(lists: DiscoveredDevice[][]): DiscoveredDevice[] {
   const merged = new Map<string, DiscoveredDevice>();
   for (const list of lists) {
     for (const item of list) {
-      const key = `${item.host}:${item.port}`;
+      const key = deviceKey(item);
       const existing = merged.get(key);
       if (!existing) {
         merged.set(key, item);
         continue;
       }
       existing.name = existing.name || item.name;
+      existing.fingerprint = existing.fingerprint ?? item.fingerprint;
+      if (existing.source && !item.source) {
+        delete existing.source;
+      }
       existing.addresses = [...new Set([...existing.addresses, ...item.addresses])];
     }
   }
@@ -254,7 +446,19 @@ async function discoverViaUdp(
           name: payload.name,
           host,
           port: payload.port,
-          addresses: [host]
+          addresses: [host],
+          ...announcedFields({
+            id: payload.id,
+            fingerprint: payload.fingerprint,
+            version: payload.version,
+            protocolVersion: payload.protocolVersion,
+            tls: payload.tls,
+            pairCodeRequired: payload.pairCodeRequired,
+            capabilities: payload.capabilities,
+            deviceType: payload.deviceType,
+            avatarColor: payload.avatarColor,
+            avatarEmoji: payload.avatarEmoji
+          })
         };
         const key = `${device.host}:${device.port}`;
         devices.set(key, device);
@@ -281,6 +485,108 @@ async function discoverViaUdp(
   });
 }
 
+async function discoverViaPing(
+  timeoutMs: number,
+  options: ResolvedDiscoverDevicesOptions
+): Promise<DiscoveredDevice[]> {
+  if (!options.ping) {
+    return [];
+  }
+  const answers = await pingListeners(timeoutMs, options.ping.replyPort, options.includeLoopback);
+  return answers.flatMap(({ reply, address, fingerprint }) => {
+    const host = normalizeIpv4(address);
+    if (!host || !isAllowedIpv4(host, options) || !reply.name || !Number.isInteger(reply.port) || reply.port <= 0) {
+      return [];
+    }
+    return [
+      {
+        name: String(reply.name),
+        host,
+        port: reply.port,
+        addresses: [host],
+        ...announcedFields({
+          id: reply.id,
+          // Only a signed reply proves the fingerprint.
+          fingerprint,
+          version: reply.version,
+          protocolVersion: reply.protocolVersion,
+          tls: reply.tls,
+          pairCodeRequired: reply.pairCodeRequired,
+          capabilities: reply.capabilities,
+          // Ping replies leave out the appearance to stay small.
+          deviceType: undefined,
+          avatarColor: undefined,
+          avatarEmoji: undefined
+        })
+      }
+    ];
+  });
+}
+
+async function discoverViaSsdp(
+  timeoutMs: number,
+  options: ResolvedDiscoverDevicesOptions
+): Promise<DiscoveredDevice[]> {
+  return new Promise((resolve) => {
+    const devices = new Map<string, DiscoveredDevice>();
+    const socket = createSocket({ type: "udp4" });
+    let finished = false;
+
+    const done = (): void => {
+      if (finished) {
+        return;
+      }
+      finished = true;
+      try {
+        socket.close();
+      } catch {
+        // Ignore close errors.
+      }
+      resolve([...devices.values()]);
+    };
+
+    socket.on("message", (raw, rinfo) => {
+      const message = parseSsdpMessage(raw);
+      const announcement = message ? announcementFromSsdp(message) : null;
+      if (!announcement) {
+        return;
+      }
+      const device = serviceToDevice({ ...announcement, addresses: [rinfo.address] }, options);
+      if (device) {
+        devices.set(`${device.host}:${device.port}`, { ...device, source: "ssdp" });
+      }
+    });
+
+    socket.on("error", () => done());
+    socket.bind(0, () => {
+      try {
+        // Responders wait up to MX seconds, so ask for less than we wait.
+        const mx = Math.max(1, Math.min(3, Math.floor(timeoutMs / 1000) - 1));
+        socket.setMulticastTTL(2);
+        socket.send(ssdpSearchRequest(mx), SSDP_PORT, SSDP_ADDRESS);
+      } catch {
+        done();
+        return;
+      }
+      setTimeout(done, timeoutMs);
+    });
+  });
+}
+
+async function discoverViaDnsSd(
+  timeoutMs: number,
+  options: ResolvedDiscoverDevicesOptions
+): Promise<DiscoveredDevice[]> {
+  if (!options.dnsSd) {
+    return [];
+  }
+  const instances = await browseDnsSd(options.dnsSd, timeoutMs);
+  return instances.flatMap((instance) => {
+    const device = serviceToDevice(instance, options);
+    return device ? [device] : [];
+  });
+}
+
 export async function discoverDevices(
   timeoutMs = DEFAULT_DISCOVERY_TIMEOUT_MS,
   options?: DiscoverDevicesOptions
@@ -288,7 +594,11 @@ export async function discoverDevices(
   const resolved = resolveDiscoverOptions(options);
   const results = await Promise.allSettled([
     discoverViaMdns(timeoutMs, resolved),
-    discoverViaUdp(timeoutMs, resolved)
+    discoverViaUdp(timeoutMs, resolved),
+    discoverViaDnsSd(timeoutMs, resolved),
+    discoverViaPing(timeoutMs, resolved),
+    // Last, so devices other mechanisms also found are not tagged.
+    discoverViaSsdp(timeoutMs, resolved)
   ]);
   const lists = results.flatMap((result) => (result.status === "fulfilled" ? [result.value] : []));
   const devices = mergeDevices(lists);
@@ -297,10 +607,10 @@ export async function discoverDevices(
   }
 
   const locals = localIpv4Set(resolved);
-  return devices.filter((device) => !isSelfDevice(device, locals));
+  return devices.filter((device) => !isSelfDevice(device, locals, resolved.selfId));
 }
 
-function createUdpResponder(name: string, port: number): DgramSocket {
+function createUdpResponder(name: string, port: number, identity?: ServiceIdentity): DgramSocket {
   const socket = createSocket({ type: "udp4", reuseAddr: true });
   socket.on("error", () => {
     // Keep running even if UDP discovery fails.
@@ -314,7 +624,17 @@ function createUdpResponder(name: string, port: number): DgramSocket {
     const payload: UdpDiscoveryReply = {
       magic: UDP_DISCOVERY_MAGIC,
       name,
-      port
+      port,
+      id: identity?.id,
+      fingerprint: identity?.fingerprint,
+      version: CLI_VERSION,
+      protocolVersion: PROTOCOL_VERSION,
+      tls: identity?.tls,
+      pairCodeRequired: identity?.pairCodeRequired,
+      capabilities: identity?.capabilities,
+      deviceType: identity?.deviceType,
+      avatarColor: identity?.avatarColor,
+      avatarEmoji: identity?.avatarEmoji
     };
 
     try {
@@ -328,23 +648,107 @@ function createUdpResponder(name: string, port: number): DgramSocket {
   return socket;
 }
 
-export function publishService(name: string, port: number): () => Promise<void> {
+function serviceTxt(identity?: ServiceIdentity): Record<string, string> {
+  return {
+    v: CLI_VERSION,
+    pv: String(PROTOCOL_VERSION),
+    ...(identity
+      ? {
+          id: identity.id,
+          ...(identity.fingerprint ? { fp: identity.fingerprint } : {}),
+          tls: identity.tls ? "1" : "0",
+          pair: identity.pairCodeRequired ? "1" : "0",
+          ...(identity.capabilities?.length ? { caps: identity.capabilities.join(",") } : {}),
+          ...(identity.deviceType ? { dt: identity.deviceType } : {}),
+          ...(identity.avatarColor ? { ac: identity.avatarColor } : {}),
+          // Percent-encoded so SSDP can carry it in a header.
+          ...(identity.avatarEmoji ? { ae: encodeURIComponent(identity.avatarEmoji) } : {})
+        }
+      : {})
+  };
+}
+
+export function publishService(
+  name: string,
+  port: number,
+  identity?: ServiceIdentity,
+  options?: PublishOptions
+): () => Promise<void> {
+  const dnsSd = options?.dnsSd;
+  if (options?.answerPings) {
+    identity = identity && {
+      ...identity,
+      capabilities: [...new Set([...(identity.capabilities ?? []), CAPABILITY_PING])]
+    };
+  }
   const bonjour = new Bonjour();
+  const txt = serviceTxt(identity);
   const service = bonjour.publish({
     name,
     type: SERVICE_TYPE,
     protocol: SERVICE_PROTOCOL,
-    port
+    port,
+    txt
   });
 
+  // Settles to the function removing the records, or null if publishing failed.
+  const unpublishDnsSd = dnsSd
+    ? publishDnsSd(dnsSd, { id: identity?.id ?? name, name, port, txt }).catch((err: unknown) => {
+        dnsSd.onError?.(err instanceof Error ? err : new Error(String(err)));
+        return null;
+      })
+    : Promise.resolve(null);
+
   let udpSocket: DgramSocket | null = null;
   try {
-    udpSocket = createUdpResponder(name, port);
+    udpSocket = createUdpResponder(name, port, identity);
   } catch {
     udpSocket = null;
   }
+  let pingSocket: DgramSocket | null = null;
+  if (options?.answerPings) {
+    try {
+      pingSocket = createPingResponder(
+        {
+          name,
+          port,
+          id: identity?.id,
+          tls: identity?.tls,
+          pairCodeRequired: identity?.pairCodeRequired,
+          capabilities: identity?.capabilities
+        },
+        options.pingSigner
+      );
+    } catch {
+      pingSocket = null;
+    }
+  }
+  let stopSsdp: (() => Promise<void>) | null = null;
+  try {
+    stopSsdp = startSsdpResponder({ name, port, txt });
+  } catch {
+    stopSsdp = null;
+  }
 
   return async () => {
+    const unpublish = await unpublishDnsSd;
+    if (unpublish) {
+      await unpublish().catch((err: unknown) => {
+        dnsSd?.onError?.(err instanceof Error ? err : new Error(String(err)));
+      });
+    }
+    if (stopSsdp) {
+      const stop = stopSsdp;
+      await runWithTimeout((done) => {
+        void stop().then(done);
+      }, 1000);
+    }
+    if (pingSocket) {
+      const socket = pingSocket;
+      await runWithTimeout((done) => {
+        socket.close(() => done());
+      }, 1000);
+    }
     if (udpSocket) {
       await runWithTimeout(
         (done) => {
@@ -0,0 +1,406 @@
+import { createHmac, randomInt } from "crypto";
+import { createSocket } from "dgram";
+import { lookup, Resolver } from "dns/promises";
+import { isIP } from "net";
+import { networkInterfaces } from "os";
+import { SERVICE_PROTOCOL, SERVICE_TYPE } from "./constants";
+
+/**
+ * Wide-area DNS-SD (RFC 6763 over unicast DNS) for networks that filter
+ * multicast: listeners add their PTR/SRV/TXT records to a zone the admin
+ * delegated, with a DNS UPDATE (RFC 2136) signed by a TSIG key when one is
+ * given, and senders browse the same zone with ordinary queries.
+ */
+export interface DnsSdZone {
+  // e.g. `sd.example.com`; records go under `_localsent._tcp.<zone>`.
+  zone: string;
+  // `host[:port]` of the server to query or update; the system resolver
+  // for queries and the zone's primary (SOA) for updates when unset.
+  server?: string;
+}
+
+export interface TsigKey {
+  name: string;
+  secret: Buffer;
+}
+
+export interface DnsSdPublication extends DnsSdZone {
+  key?: TsigKey;
+  // Addresses the host record lists; every non-internal one when empty.
+  addresses?: string[];
+}
+
+/** An instance found by `browseDnsSd`, before any field is checked. */
+export interface DnsSdInstance {
+  name: string;
+  port: number;
+  addresses: string[];
+  txt: Record<string, string>;
+}
+
+const RECORD_TTL_SECONDS = 120;
+const UPDATE_TIMEOUT_MS = 3000;
+const TSIG_ALGORITHM = "hmac-sha256";
+const TSIG_FUDGE_SECONDS = 300;
+
+const TYPE_A = 1;
+const TYPE_PTR = 12;
+const TYPE_TXT = 16;
+const TYPE_AAAA = 28;
+const TYPE_SRV = 33;
+const TYPE_SOA = 6;
+const TYPE_TSIG = 250;
+const TYPE_ANY = 255;
+const CLASS_IN = 1;
+const CLASS_NONE = 254;
+const CLASS_ANY = 255;
+
+const RCODE_NAMES: Record<number, string> = {
+  1: "FORMERR",
+  2: "SERVFAIL",
+  3: "NXDOMAIN",
+  4: "NOTIMP",
+  5: "REFUSED",
+  6: "YXDOMAIN",
+  7: "YXRRSET",
+  8: "NXRRSET",
+  9: "NOTAUTH",
+  10: "NOTZONE"
+};
+
+export function normalizeZone(raw: string): string {
+  const zone = raw.trim().replace(/\.+$/, "").toLowerCase();
+  if (!zone || zone.split(".").some((label) => !label || Buffer.byteLength(label) > 63)) {
+    throw new Error(`invalid DNS-SD zone: ${raw}`);
+  }
+  return zone;
+}
+
+/** `[hmac-sha256:]name:base64-secret`, as `nsupdate -y` takes it. */
+export function parseTsigKey(raw: string): TsigKey {
+  const parts = raw.trim().split(":");
+  if (parts.length === 3 && parts[0].toLowerCase() === TSIG_ALGORITHM) {
+    parts.shift();
+  }
+  if (parts.length !== 2 || !parts[0] || !parts[1]) {
+    throw new Error("TSIG key must be [hmac-sha256:]name:secret");
+  }
+  const secret = Buffer.from(parts[1], "base64");
+  if (secret.length === 0) {
+    throw new Error("TSIG secret must be base64");
+  }
+  return { name: parts[0].replace(/\.+$/, "").toLowerCase(), secret };
+}
+
+function splitServer(raw: string): { host: string; port: number } {
+  const value = raw.trim();
+  const bracketed = /^\[([^\]]+)\](?::(\d+))?$/.exec(value);
+  if (bracketed) {
+    return { host: bracketed[1], port: bracketed[2] ? Number(bracketed[2]) : 53 };
+  }
+  const colon = value.lastIndexOf(":");
+  if (colon > 0 && value.indexOf(":") === colon) {
+    return { host: value.slice(0, colon), port: Number(value.slice(colon + 1)) || 53 };
+  }
+  return { host: value, port: 53 };
+}
+
+async function serverAddress(raw: string): Promise<{ address: string; port: number }> {
+  const { host, port } = splitServer(raw);
+  const address = isIP(host) ? host : (await lookup(host)).address;
+  return { address, port };
+}
+
+/** The service type's domain in `zone`, e.g. `_localsent._tcp.sd.example.com`. */
+function serviceDomain(zone: string): string {
+  return `_${SERVICE_TYPE}._${SERVICE_PROTOCOL}.${zone}`;
+}
+
+/** Undoes the escaping resolvers apply to labels (`\.`, `\032`). */
+function unescapeLabel(label: string): string {
+  return label.replace(/\\(\d{3}|.)/g, (_, escaped: string) =>
+    escaped.length === 3 ? String.fromCharCode(Number(escaped)) : escaped
+  );
+}
+
+function parseTxt(records: string[][]): Record<string, string> {
+  const txt: Record<string, string> = {};
+  for (const chunk of records[0] ?? []) {
+    const equals = chunk.indexOf("=");
+    const key = (equals >= 0 ? chunk.slice(0, equals) : chunk).toLowerCase();
+    if (key && !(key in txt)) {
+      txt[key] = equals >= 0 ? chunk.slice(equals + 1) : "";
+    }
+  }
+  return txt;
+}
+
+/** Lists the instances registered in `zone`; failures of single instances are skipped. */
+export async function browseDnsSd(options: DnsSdZone, timeoutMs: number): Promise<DnsSdInstance[]> {
+  const zone = normalizeZone(options.zone);
+  const resolver = new Resolver({ timeout: Math.max(500, Math.min(timeoutMs, 5000)), tries: 1 });
+  if (options.server) {
+    const { address, port } = await serverAddress(options.server);
+    resolver.setServers([isIP(address) === 6 ? `[${address}]:${port}` : `${address}:${port}`]);
+  }
+  const domain = serviceDomain(zone);
+  const names = await resolver.resolvePtr(domain);
+  const instances = await Promise.allSettled(
+    names.map(async (instance): Promise<DnsSdInstance | null> => {
+      const [srv, txt] = await Promise.all([
+        resolver.resolveSrv(instance),
+        resolver.resolveTxt(instance).catch(() => [] as string[][])
+      ]);
+      const target = srv.sort((a, b) => a.priority - b.priority)[0];
+      if (!target) {
+        return null;
+      }
+      const addresses = isIP(target.name) ? [target.name] : await resolver.resolve4(target.name).catch(() => []);
+      const suffix = `.${domain}`;
+      const label = instance.toLowerCase().endsWith(suffix) ? instance.slice(0, -suffix.length) : instance;
+      return { name: unescapeLabel(label), port: target.port, addresses, txt: parseTxt(txt) };
+    })
+  );
+  return instances.flatMap((result) => (result.status === "fulfilled" && result.value ? [result.value] : []));
+}
+
+function encodeName(labels: string[]): Buffer {
+  const parts: Buffer[] = [];
+  for (const label of labels) {
+    const bytes = Buffer.from(label, "utf8");
+    if (bytes.length === 0 || bytes.length > 63) {
+      throw new Error(`invalid DNS label: ${label}`);
+    }
+    parts.push(Buffer.from([bytes.length]), bytes);
+  }
+  parts.push(Buffer.from([0]));
+  return Buffer.concat(parts);
+}
+
+function dottedName(name: string): Buffer {
+  return encodeName(name.split(".").filter(Boolean));
+}
+
+function u16(value: number): Buffer {
+  const buffer = Buffer.alloc(2);
+  buffer.writeUInt16BE(value);
+  return buffer;
+}
+
+function u32(value: number): Buffer {
+  const buffer = Buffer.alloc(4);
+  buffer.writeUInt32BE(value);
+  return buffer;
+}
+
+function u48(value: number): Buffer {
+  const buffer = Buffer.alloc(6);
+  buffer.writeUIntBE(value, 0, 6);
+  return buffer;
+}
+
+interface ResourceRecord {
+  owner: Buffer;
+  type: number;
+  klass: number;
+  ttl: number;
+  data: Buffer;
+}
+
+function encodeRecord(record: ResourceRecord): Buffer {
+  return Buffer.concat([
+    record.owner,
+    u16(record.type),
+    u16(record.klass),
+    u32(record.ttl),
+    u16(record.data.length),
+    record.data
+  ]);
+}
+
+/** Deletes every record of `type` (or all types) at `owner`. */
+function deleteRRset(owner: Buffer, type: number): ResourceRecord {
+  return { owner, type, klass: CLASS_ANY, ttl: 0, data: Buffer.alloc(0) };
+}
+
+function buildUpdate(id: number, zone: string, updates: ResourceRecord[]): Buffer {
+  const header = Buffer.concat([
+    u16(id),
+    u16(5 << 11), // opcode UPDATE
+    u16(1), // zone
+    u16(0), // prerequisites
+    u16(updates.length),
+    u16(0)
+  ]);
+  return Buffer.concat([header, dottedName(zone), u16(TYPE_SOA), u16(CLASS_IN), ...updates.map(encodeRecord)]);
+}
+
+/** Appends a TSIG record (RFC 8945) signing `message`. */
+function signTsig(message: Buffer, key: TsigKey, timeSigned = Math.floor(Date.now() / 1000)): Buffer {
+  const keyName = dottedName(key.name);
+  const algorithm = dottedName(TSIG_ALGORITHM);
+  const mac = createHmac("sha256", key.secret)
+    .update(message)
+    .update(
+      Buffer.concat([keyName, u16(CLASS_ANY), u32(0), algorithm, u48(timeSigned), u16(TSIG_FUDGE_SECONDS), u16(0), u16(0)])
+    )
+    .digest();
+  const data = Buffer.concat([
+    algorithm,
+    u48(timeSigned),
+    u16(TSIG_FUDGE_SECONDS),
+    u16(mac.length),
+    mac,
+    message.subarray(0, 2), // original id
+    u16(0),
+    u16(0)
+  ]);
+  const signed = Buffer.concat([
+    message,
+    encodeRecord({ owner: keyName, type: TYPE_TSIG, klass: CLASS_ANY, ttl: 0, data })
+  ]);
+  signed.writeUInt16BE(message.readUInt16BE(10) + 1, 10);
+  return signed;
+}
+
+async function sendUpdate(
+  server: { address: string; port: number },
+  zone: string,
+  updates: ResourceRecord[],
+  key?: TsigKey
+): Promise<void> {
+  const id = randomInt(0, 0x10000);
+  const unsigned = buildUpdate(id, zone, updates);
+  const message = key ? signTsig(unsigned, key) : unsigned;
+  const socket = createSocket(isIP(server.address) === 6 ? "udp6" : "udp4");
+  try {
+    const reply = await new Promise<Buffer>((resolve, reject) => {
+      const timer = setTimeout(() => reject(new Error(`no answer from ${server.address}`)), UPDATE_TIMEOUT_MS);
+      socket.on("error", (err) => {
+        clearTimeout(timer);
+        reject(err);
+      });
+      socket.on("message", (packet) => {
+        if (packet.length >= 4 && packet.readUInt16BE(0) === id) {
+          clearTimeout(timer);
+          resolve(packet);
+        }
+      });
+      socket.send(message, server.port, server.address);
+    });
+    const rcode = reply[3] & 0x0f;
+    if (rcode !== 0) {
+      throw new Error(`DNS update refused by ${server.address}: ${RCODE_NAMES[rcode] ?? `rcode ${rcode}`}`);
+    }
+  } finally {
+    socket.close();
+  }
+}
+
+/** The zone's primary server, named in its SOA record. */
+async function primaryServer(zone: string): Promise<{ address: string; port: number }> {
+  const soa = await new Resolver().resolveSoa(zone);
+  return serverAddress(soa.nsname);
+}
+
+function localAddresses(): string[] {
+  return Object.values(networkInterfaces())
+    .flatMap((list) => list ?? [])
+    .filter((item) => !item.internal && !item.address.toLowerCase().startsWith("fe80:"))
+    .map((item) => item.address);
+}
+
+/** A host label for the SRV target, from the listener's id. */
+function hostLabel(id: string): string {
+  const cleaned = id.toLowerCase().replace(/[^a-z0-9-]/g, "").slice(0, 40);
+  return `local-sent-${cleaned || "host"}`;
+}
+
+/** The instance label, cut to the 63 bytes a label may have. */
+function instanceLabel(name: string): string {
+  let label = name.trim() || "local-sent";
+  while (Buffer.byteLength(label) > 63) {
+    label = label.slice(0, -1);
+  }
+  return label;
+}
+
+interface DnsSdRecords {
+  zone: string;
+  add: ResourceRecord[];
+  remove: ResourceRecord[];
+}
+
+/** What `publishDnsSd` adds, and what removes it again. */
+function dnsSdRecords(
+  zoneName: string,
+  instance: { id: string; name: string; port: number; txt: Record<string, string>; addresses: string[] }
+): DnsSdRecords {
+  const zone = normalizeZone(zoneName);
+  const zoneLabels = zone.split(".");
+  const domain = dottedName(serviceDomain(zone));
+  const owner = encodeName([instanceLabel(instance.name), `_${SERVICE_TYPE}`, `_${SERVICE_PROTOCOL}`, ...zoneLabels]);
+  const host = encodeName([hostLabel(instance.id), ...zoneLabels]);
+  const txt = Buffer.concat(
+    Object.entries(instance.txt).map(([key, value]) => {
+      const entry = Buffer.from(`${key}=${value}`, "utf8").subarray(0, 255);
+      return Buffer.concat([Buffer.from([entry.length]), entry]);
+    })
+  );
+  const address = (ip: string): ResourceRecord | null => {
+    const family = isIP(ip);
+    if (family === 4) {
+      return { owner: host, type: TYPE_A, klass: CLASS_IN, ttl: RECORD_TTL_SECONDS, data: Buffer.from(ip.split(".").map(Number)) };
+    }
+    if (family === 6 && !ip.includes(".")) {
+      const groups = ip.split("%")[0].split("::");
+      const head = groups[0] ? groups[0].split(":") : [];
+      const tail = groups.length > 1 && groups[1] ? groups[1].split(":") : [];
+      const full = [...head, ...Array(8 - head.length - tail.length).fill("0"), ...tail];
+      return {
+        owner: host,
+        type: TYPE_AAAA,
+        klass: CLASS_IN,
+        ttl: RECORD_TTL_SECONDS,
+        data: Buffer.concat(full.map((group) => u16(Number.parseInt(group, 16))))
+      };
+    }
+    return null;
+  };
+  const ptr: ResourceRecord = { owner: domain, type: TYPE_PTR, klass: CLASS_IN, ttl: RECORD_TTL_SECONDS, data: owner };
+  return {
+    zone,
+    add: [
+      deleteRRset(owner, TYPE_SRV),
+      deleteRRset(owner, TYPE_TXT),
+      deleteRRset(host, TYPE_ANY),
+      ptr,
+      {
+        owner,
+        type: TYPE_SRV,
+        klass: CLASS_IN,
+        ttl: RECORD_TTL_SECONDS,
+        data: Buffer.concat([u16(0), u16(0), u16(instance.port), host])
+      },
+      { owner, type: TYPE_TXT, klass: CLASS_IN, ttl: RECORD_TTL_SECONDS, data: txt },
+      ...instance.addresses.map(address).filter((record): record is ResourceRecord => record !== null)
+    ],
+    remove: [{ ...ptr, klass: CLASS_NONE, ttl: 0 }, deleteRRset(owner, TYPE_ANY), deleteRRset(host, TYPE_ANY)]
+  };
+}
+
+/**
+ * Adds the listener's records to the zone. Resolves to a function that
+ * removes them again; records of a listener that never gets to call it
+ * stay until it next starts or an admin removes them.
+ */
+export async function publishDnsSd(
+  options: DnsSdPublication,
+  instance: { id: string; name: string; port: number; txt: Record<string, string> }
+): Promise<() => Promise<void>> {
+  const addresses = options.addresses?.length ? options.addresses : localAddresses();
+  const records = dnsSdRecords(options.zone, { ...instance, addresses });
+  const server = options.server ? await serverAddress(options.server) : await primaryServer(records.zone);
+  await sendUpdate(server, records.zone, records.add, options.key);
+  return () => sendUpdate(server, records.zone, records.remove, options.key);
+}
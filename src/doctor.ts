@@ -160,6 +160,26 @@ async function checkListenPort(port: number): Promise<CheckOutcome> {
   };
 }
 
+/**
+ * Whether this process may listen on `port`; `code` is the bind error when
+ * it may not, e.g. `EACCES` for a privileged port.
+ */
+export async function probeListenPort(port: number): Promise<{ available: boolean; code?: string }> {
+  const server = createServer();
+  try {
+    await new Promise<void>((resolve, reject) => {
+      server.once("error", reject);
+      server.listen(port, "0.0.0.0", () => resolve());
+    });
+  } catch (error) {
+    return { available: false, code: (error as NodeJS.ErrnoException).code };
+  }
+  await new Promise<void>((resolve) => {
+    server.close(() => resolve());
+  });
+  return { available: true };
+}
+
 async function checkDiscoveryLoopback(timeoutMs: number): Promise<CheckOutcome> {
   const workDir = await mkdtemp(join(tmpdir(), "local-sent-doctor-discovery-"));
   const serviceName = `local-sent-doctor-${process.pid}-${Date.now()}`;
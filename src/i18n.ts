@@ -2,13 +2,23 @@ export type SupportedLocale = "zh" | "en";
 
 type MessageKey =
   | "lang_option_desc"
+  | "ipc_option_desc"
+  | "err_ipc_format"
   | "app_description"
   | "discover_command_desc"
   | "doctor_command_desc"
+  | "check_port_command_desc"
+  | "check_port_arg"
   | "listen_command_desc"
   | "send_command_desc"
   | "discover_timeout_option"
   | "discover_json_option"
+  | "discover_self_id_option"
+  | "discover_include_self_option"
+  | "discover_dns_sd_zone_option"
+  | "discover_dns_sd_server_option"
+  | "discover_ping_option"
+  | "discover_ping_reply_port_option"
   | "doctor_port_option"
   | "doctor_output_option"
   | "doctor_timeout_option"
@@ -25,7 +35,53 @@ type MessageKey =
   | "listen_tls_cert_option"
   | "listen_tls_key_option"
   | "listen_confirm_each_option"
+  | "listen_keep_duplicates_option"
+  | "listen_preserve_metadata_option"
+  | "listen_preserve_xattrs_option"
+  | "listen_max_rate_option"
+  | "listen_share_option"
+  | "listen_share_allow_option"
+  | "listen_clipboard_from_option"
+  | "listen_bind_option"
+  | "listen_device_id_option"
+  | "listen_device_type_option"
+  | "listen_avatar_color_option"
+  | "listen_avatar_emoji_option"
+  | "listen_webdav_option"
+  | "listen_webdav_user_option"
+  | "listen_quick_share_option"
+  | "listen_dns_sd_zone_option"
+  | "listen_dns_sd_server_option"
+  | "listen_answer_pings_option"
+  | "listen_stealth_option"
+  | "listen_hidden_option"
+  | "shares_command_desc"
+  | "shares_path_arg"
+  | "shares_json_option"
+  | "pull_command_desc"
+  | "pull_path_arg"
+  | "pull_output_option"
+  | "message_command_desc"
+  | "message_text_arg"
+  | "message_from_option"
+  | "clipboard_command_desc"
   | "send_path_arg"
+  | "send_name_option"
+  | "send_streams_option"
+  | "err_streams_value"
+  | "send_symlinks_option"
+  | "send_hardlinks_option"
+  | "send_xattrs_option"
+  | "err_symlinks_value"
+  | "send_atomic_option"
+  | "send_note_option"
+  | "send_tag_option"
+  | "send_skip_from_option"
+  | "send_dry_run_option"
+  | "send_unicode_option"
+  | "err_unicode_value"
+  | "send_name_collisions_option"
+  | "err_name_collisions_value"
   | "send_host_option"
   | "send_port_option"
   | "send_device_option"
@@ -37,9 +93,11 @@ type MessageKey =
   | "send_tls_fingerprint_option"
   | "send_tls_tofu_option"
   | "send_tls_known_hosts_option"
+  | "send_knock_option"
   | "label_timeout"
   | "label_port"
   | "label_pair_ttl"
+  | "label_max_rate"
   | "err_positive_integer"
   | "err_pair_code_format"
   | "discover_none"
@@ -55,6 +113,7 @@ type MessageKey =
   | "err_pair_code_and_generate_conflict"
   | "err_pair_ttl_positive"
   | "err_pair_once_requirement"
+  | "err_knock_requires_secret"
   | "listen_pair_code_rotated"
   | "listen_service"
   | "listen_endpoint"
@@ -62,6 +121,24 @@ type MessageKey =
   | "listen_pair_code"
   | "listen_pair_ttl"
   | "listen_tls_enabled"
+  | "listen_webdav"
+  | "listen_quick_share"
+  | "listen_dns_sd"
+  | "listen_answer_pings"
+  | "listen_stealth"
+  | "listen_knock_secret_generated"
+  | "listen_dns_sd_failed"
+  | "listen_max_rate"
+  | "listen_share"
+  | "err_share_format"
+  | "err_share_duplicate"
+  | "err_share_allow_format"
+  | "err_share_allow_unknown"
+  | "shares_none"
+  | "pull_done"
+  | "message_delivered"
+  | "clipboard_delivered"
+  | "listen_control_ignored"
   | "listen_press_ctrl_c"
   | "listen_shutdown"
   | "err_tls_ca_requires_tls"
@@ -71,22 +148,46 @@ type MessageKey =
   | "err_tls_known_hosts_requires_tofu"
   | "err_tls_fingerprint_tofu_conflict"
   | "err_no_receiver_found"
+  | "err_stream_name_required"
+  | "err_device_type_value"
+  | "err_avatar_color_value"
+  | "err_avatar_emoji_value"
+  | "err_dry_run_stream"
+  | "err_note_too_long"
+  | "err_too_many_tags"
+  | "err_tag_format"
   | "discover_selected"
   | "send_done"
   | "send_saved_path"
+  | "send_already_have"
+  | "send_name_issue"
+  | "send_name_renamed"
+  | "send_skip_from_skipped"
+  | "send_dry_run_ok"
+  | "send_name_skipped"
   | "error_prefix";
 
 type Dictionary = Record<MessageKey, string>;
 
 const zh: Dictionary = {
   lang_option_desc: "界面语言（zh 或 en）",
+  ipc_option_desc: "机器可读输出（json：每行一个 JSON 消息，供桌面端使用）",
+  err_ipc_format: "--ipc 只支持 json",
   app_description: "跨平台局域网文件传输（TypeScript MVP）",
   discover_command_desc: "在局域网中发现 local-sent 接收端",
   doctor_command_desc: "运行本地环境诊断（网络/发现/TLS）",
+  check_port_command_desc: "检查本进程能否监听指定端口，以 JSON 输出结果",
+  check_port_arg: "要检查的端口",
   listen_command_desc: "作为接收端运行并通过 mDNS 广播",
   send_command_desc: "发送文件或目录到接收端",
   discover_timeout_option: "发现超时（毫秒）",
   discover_json_option: "以 JSON 输出发现结果",
+  discover_self_id_option: "本机接收端广播的设备 ID；广播了 ID 的设备只按 ID 判断是否为本机",
+  discover_include_self_option: "保留本机上的接收端（例如其他用户账户或容器中的接收端）",
+  discover_dns_sd_zone_option: "同时在这个单播 DNS 区域中查找接收端（广域 DNS-SD），用于组播被过滤的网络",
+  discover_dns_sd_server_option: "查询用的 DNS 服务器（host[:port]），默认使用系统解析器",
+  discover_ping_option: "同时广播 ping，找出开启了 --answer-pings 的接收端（其他方式都找不到设备时使用）",
+  discover_ping_reply_port_option: "让接收端把 ping 应答发到本机的这个端口（例如防火墙已放行的端口），默认任选空闲端口",
   doctor_port_option: "检查监听端口可用性",
   doctor_output_option: "检查输出目录写权限",
   doctor_timeout_option: "发现超时（毫秒）",
@@ -103,7 +204,54 @@ const zh: Dictionary = {
   listen_tls_cert_option: "TLS 证书文件路径（PEM）",
   listen_tls_key_option: "TLS 私钥文件路径（PEM）",
   listen_confirm_each_option: "每次接收前等待确认（通过 stdin 指令）",
-  send_path_arg: "文件或目录路径",
+  listen_keep_duplicates_option: "即使已收到相同内容也再保存一份",
+  listen_preserve_metadata_option: "按发送端保留文件权限和修改时间（平台支持时）",
+  listen_preserve_xattrs_option: "恢复同平台发送端的扩展属性（含 macOS 隔离标记和资源分支）",
+  listen_max_rate_option: "所有连接合计的接收速率上限（KiB/s）",
+  listen_share_option: "允许对端浏览并拉取的文件夹（名称=目录，可重复）",
+  listen_share_allow_option: "只允许这些地址看到该共享（名称=地址1,地址2，可重复）",
+  listen_clipboard_from_option: "接受这些对端地址同步过来的剪贴板文字（逗号分隔，默认不接受）",
+  listen_bind_option: "只在这个本机地址上监听（默认监听所有网卡）",
+  listen_device_id_option: "随服务广播的设备 ID，用于合并同一设备在多个网卡上的发现结果（默认每次启动随机生成）",
+  listen_device_type_option: "随服务广播的设备类型：laptop、desktop 或 server",
+  listen_avatar_color_option: "随服务广播的头像颜色（#rrggbb）",
+  listen_avatar_emoji_option: "随服务广播的头像 emoji（最多 8 个字符）",
+  listen_webdav_option: "把收到的文件直接上传到这个 WebDAV 文件夹（如 Nextcloud），不写本地磁盘",
+  listen_webdav_user_option: "WebDAV 用户名；密码从环境变量 LOCAL_SENT_WEBDAV_PASSWORD 读取",
+  listen_quick_share_option: "（实验性）同时接收 Google Quick Share 发送方的文件",
+  listen_dns_sd_zone_option:
+    "同时通过 DNS 动态更新把服务发布到这个单播 DNS 区域（广域 DNS-SD）；TSIG 密钥从环境变量 LOCAL_SENT_DNS_SD_KEY 读取",
+  listen_dns_sd_server_option: "接收动态更新的 DNS 服务器（host[:port]），默认使用区域 SOA 中的主服务器",
+  listen_answer_pings_option: "应答 discover --ping 的广播 ping（有频率限制）；启用 TLS 时应答用证书私钥签名",
+  listen_hidden_option: "启动时不广播，其他设备发现不到本机；可通过 stdin 发送 announce on 重新广播",
+  listen_stealth_option: "隐身监听：端口平时关闭，只对先用敲门密钥敲门（--knock）的地址开放；密钥取自 LOCAL_SENT_KNOCK_SECRET，未设置时随机生成",
+  shares_command_desc: "浏览远端设备共享的文件夹",
+  shares_path_arg: "共享内路径（共享名/子路径），留空列出所有共享",
+  shares_json_option: "以 JSON 输出",
+  pull_command_desc: "从远端设备的共享文件夹拉取文件",
+  pull_path_arg: "远端文件路径（共享名/子路径）",
+  pull_output_option: "保存位置（文件路径或已有目录）",
+  message_command_desc: "给接收端发一条简短消息（如“马上发，记得接收”）",
+  message_text_arg: "消息内容（最多 2000 字）",
+  message_from_option: "显示给对方的本机名称（默认主机名）",
+  clipboard_command_desc: "把标准输入中的文字推送到对方的剪贴板（对方需用 --clipboard-from 允许本机）",
+  send_path_arg: "文件或目录路径，- 表示从 stdin 读取",
+  send_name_option: "从 stdin 发送时接收端保存的文件名",
+  send_streams_option: "大文件拆分到多个并行连接：auto 或 1-16（默认 auto）",
+  err_streams_value: "--streams 必须是 auto 或 1-16 之间的整数",
+  send_symlinks_option: "目录中的符号链接：skip 跳过、follow 发送指向的内容、preserve 在接收端重建（默认 skip）",
+  send_hardlinks_option: "目录中的硬链接在接收端重建为硬链接，而不是重复发送内容",
+  send_xattrs_option: "随文件发送扩展属性（macOS 与 Linux）",
+  err_symlinks_value: "--symlinks 必须是 skip、follow 或 preserve",
+  send_atomic_option: "整批发送：接收端先暂存，全部校验通过后才放入目标目录，任一文件失败则全部回滚",
+  send_note_option: "附在这次发送上的备注，双方历史记录中可搜索",
+  send_tag_option: "给这次发送加标签（可重复）",
+  send_skip_from_option: "跳过该文件中列出的相对路径（每行一个），用于续传中断的批量发送",
+  send_dry_run_option: "只检查接收端是否接受配对码并有足够空间，不传输文件",
+  send_unicode_option: "文件名 Unicode 形式：nfc 转为组合形式（Linux/Windows 通用）、keep 保持原样（默认 nfc）",
+  err_unicode_value: "--unicode 必须是 nfc 或 keep",
+  send_name_collisions_option: "仅大小写或 Unicode 形式不同的路径：rename 加编号、skip 跳过、fail 终止（默认 rename）",
+  err_name_collisions_value: "--name-collisions 必须是 rename、skip 或 fail",
   send_host_option: "接收端主机；不传时自动发现首个匹配设备",
   send_port_option: "接收端端口",
   send_device_option: "自动发现时按设备名筛选",
@@ -115,9 +263,11 @@ const zh: Dictionary = {
   send_tls_fingerprint_option: "服务端证书 SHA-256 指纹",
   send_tls_tofu_option: "首次连接信任服务端证书指纹",
   send_tls_known_hosts_option: "--tls-tofu 的 known hosts 文件路径",
+  send_knock_option: "连接前先用 LOCAL_SENT_KNOCK_SECRET 中的敲门密钥向隐身接收端敲门（发现到的隐身接收端会自动敲门）",
   label_timeout: "timeout",
   label_port: "port",
   label_pair_ttl: "pair-ttl",
+  label_max_rate: "max-rate",
   err_positive_integer: "{label} 必须是正整数",
   err_pair_code_format: "配对码必须是 6 位数字",
   discover_none: "未发现接收端。请确认另一台设备已执行：local-sent listen",
@@ -133,6 +283,7 @@ const zh: Dictionary = {
   err_pair_code_and_generate_conflict: "--pair-code 与 --pair-generate 互斥",
   err_pair_ttl_positive: "--pair-ttl 必须是正整数",
   err_pair_once_requirement: "--pair-once 需要 --pair-code 或 --pair-generate 或 --pair-ttl",
+  err_knock_requires_secret: "接收端处于隐身模式，敲门需要设置 LOCAL_SENT_KNOCK_SECRET",
   listen_pair_code_rotated: "[listen] pair-code={code} (轮换:{reason}){ttlSuffix}",
   listen_service: "[listen] service={service}",
   listen_endpoint: "[listen] endpoint={host}:{port}",
@@ -140,6 +291,24 @@ const zh: Dictionary = {
   listen_pair_code: "[listen] pair-code={code}{ttlSuffix}",
   listen_pair_ttl: "[listen] pair-ttl={seconds}s",
   listen_tls_enabled: "[listen] tls=enabled",
+  listen_webdav: "[listen] webdav={url}",
+  listen_quick_share: "[listen] quick-share=enabled",
+  listen_dns_sd: "[listen] dns-sd={zone}",
+  listen_answer_pings: "[listen] answer-pings=enabled",
+  listen_stealth: "[listen] stealth=enabled",
+  listen_knock_secret_generated: "[listen] knock-secret={secret}（发送方需在 LOCAL_SENT_KNOCK_SECRET 中设置此密钥）",
+  listen_dns_sd_failed: "[listen] DNS-SD 发布失败：{error}",
+  listen_max_rate: "[listen] max-rate={rate}",
+  listen_share: "[listen] share={name} -> {dir}",
+  err_share_format: "--share 格式应为 名称=目录，名称不能包含斜杠",
+  err_share_duplicate: "共享名 {name} 重复",
+  err_share_allow_format: "--share-allow 格式应为 名称=地址1,地址2",
+  err_share_allow_unknown: "--share-allow 引用了未共享的名称 {name}",
+  shares_none: "[shares] 没有可浏览的内容",
+  pull_done: "[pull] 已保存 {path} ({bytes})",
+  message_delivered: "[message] 已送达 {host}:{port}",
+  clipboard_delivered: "[clipboard] 已同步到 {host}:{port}",
+  listen_control_ignored: "[listen] 忽略无效的控制指令：{line}",
   listen_press_ctrl_c: "[listen] 按 Ctrl+C 停止",
   listen_shutdown: "\n[listen] {signal}，正在关闭...",
   err_tls_ca_requires_tls: "--tls-ca 需要与 --tls 一起使用",
@@ -149,21 +318,45 @@ const zh: Dictionary = {
   err_tls_known_hosts_requires_tofu: "--tls-known-hosts 需要与 --tls-tofu 一起使用",
   err_tls_fingerprint_tofu_conflict: "--tls-fingerprint 与 --tls-tofu 互斥",
   err_no_receiver_found: "未发现接收端。请使用 --host + --port，或先执行 local-sent discover",
+  err_stream_name_required: "从 stdin 发送（-）时必须设置 --name",
+  err_device_type_value: "--device-type 必须是 laptop、desktop 或 server",
+  err_avatar_color_value: "--avatar-color 必须是 #rrggbb 格式的颜色",
+  err_avatar_emoji_value: "--avatar-emoji 必须是最多 8 个字符的 emoji，不能包含空白或控制字符",
+  err_dry_run_stream: "--dry-run 不能用于从 stdin 发送（-）",
+  err_note_too_long: "--note 最多 {max} 个字符",
+  err_too_many_tags: "--tag 最多 {max} 个",
+  err_tag_format: "--tag 不能为空，且最多 {max} 个字符",
   discover_selected: "[discover] 已选择 {name} ({host}:{port})",
   send_done: "[send] 完成: files={fileCount} bytes={bytes} resumed={resumed}",
   send_saved_path: "[send] 接收端保存路径: {savedPath}",
+  send_already_have: "[send] 接收端已有相同文件: {savedPath}",
+  send_name_issue: "[send] 以下路径在不区分大小写或规范化文件名的系统上会冲突（{kind}）: {paths}",
+  send_name_renamed: "[send] 已重命名 {from} -> {to}",
+  send_skip_from_skipped: "[send] 跳过 {count} 个已发送的文件",
+  send_dry_run_ok: "[send] 试运行通过: files={fileCount} bytes={bytes} free={free}",
+  send_name_skipped: "[send] 已跳过冲突路径 {path}",
   error_prefix: "[错误] {message}"
 };
 
 const en: Dictionary = {
   lang_option_desc: "language (zh or en)",
+  ipc_option_desc: "machine-readable output (json: one JSON message per line, for the desktop app)",
+  err_ipc_format: "--ipc only supports json",
   app_description: "Cross-platform LAN file transfer (TypeScript MVP)",
   discover_command_desc: "Discover local-sent receivers in LAN",
   doctor_command_desc: "Run local environment diagnostics (network/discovery/TLS)",
+  check_port_command_desc: "Check whether this process may listen on a port and print the result as JSON",
+  check_port_arg: "port to check",
   listen_command_desc: "Run as receiver and broadcast via mDNS",
   send_command_desc: "Send a file or directory to receiver",
   discover_timeout_option: "discovery timeout in milliseconds",
   discover_json_option: "output discovered devices as JSON",
+  discover_self_id_option: "device id our own listener announces; devices announcing an id are only hidden as this machine when it matches",
+  discover_include_self_option: "keep listeners on this machine, such as those of other user accounts or containers",
+  discover_dns_sd_zone_option: "also browse this unicast DNS zone (wide-area DNS-SD), for networks that filter multicast",
+  discover_dns_sd_server_option: "DNS server to query (host[:port]); the system resolver by default",
+  discover_ping_option: "also broadcast a ping that listeners started with --answer-pings reply to, for when nothing else finds them",
+  discover_ping_reply_port_option: "local port listeners send ping replies to (e.g. one the firewall lets in); any free port by default",
   doctor_port_option: "check listen port availability",
   doctor_output_option: "check output directory write access",
   doctor_timeout_option: "discovery timeout in milliseconds",
@@ -180,7 +373,54 @@ const en: Dictionary = {
   listen_tls_cert_option: "TLS cert file path (PEM)",
   listen_tls_key_option: "TLS private key file path (PEM)",
   listen_confirm_each_option: "require per-transfer confirmation via stdin commands",
-  send_path_arg: "file or directory path",
+  listen_keep_duplicates_option: "save another copy even when identical content was already received",
+  listen_preserve_metadata_option: "keep the sender's file permissions and modification times where the platform allows",
+  listen_preserve_xattrs_option: "restore extended attributes (incl. macOS quarantine and resource forks) from senders on the same platform",
+  listen_max_rate_option: "combined receive rate limit across all connections (KiB/s)",
+  listen_share_option: "folder peers may browse and pull from (name=dir, repeatable)",
+  listen_share_allow_option: "only these peer addresses see the share (name=addr1,addr2, repeatable)",
+  listen_clipboard_from_option: "accept clipboard text from these peer addresses (comma-separated; none by default)",
+  listen_bind_option: "listen on this local address only (all interfaces by default)",
+  listen_device_id_option: "device id announced with the service so peers merge sightings on several interfaces (random per run by default)",
+  listen_device_type_option: "device type announced with the service: laptop, desktop or server",
+  listen_avatar_color_option: "avatar color announced with the service (#rrggbb)",
+  listen_avatar_emoji_option: "avatar emoji announced with the service (at most 8 characters)",
+  listen_webdav_option: "upload received files straight to this WebDAV folder (e.g. Nextcloud) instead of local disk",
+  listen_webdav_user_option: "WebDAV user; the password is read from LOCAL_SENT_WEBDAV_PASSWORD",
+  listen_quick_share_option: "(experimental) also receive files from Google Quick Share senders",
+  listen_dns_sd_zone_option:
+    "also publish the service to this unicast DNS zone with dynamic updates (wide-area DNS-SD); the TSIG key is read from LOCAL_SENT_DNS_SD_KEY",
+  listen_dns_sd_server_option: "DNS server that takes the updates (host[:port]); the zone's primary from its SOA by default",
+  listen_answer_pings_option: "answer discover --ping broadcasts (rate-limited); with TLS, replies are signed with the certificate key",
+  listen_hidden_option: "start without announcing the service, so peers cannot discover it; send announce on over stdin to show it",
+  listen_stealth_option: "stealth listening: keep the port closed except to addresses that first knock with the knock secret (--knock); the secret comes from LOCAL_SENT_KNOCK_SECRET and is generated when unset",
+  shares_command_desc: "browse the folders a remote device shares",
+  shares_path_arg: "path inside a share (share/sub/path); empty lists the shares",
+  shares_json_option: "print JSON",
+  pull_command_desc: "pull a file from a remote device's shared folder",
+  pull_path_arg: "remote file path (share/sub/path)",
+  pull_output_option: "where to save (a file path or an existing folder)",
+  message_command_desc: "send a short text message to a receiver (e.g. \"sending now, accept it\")",
+  message_text_arg: "message text (up to 2000 characters)",
+  message_from_option: "name shown to the receiver (defaults to the hostname)",
+  clipboard_command_desc: "push text from stdin to a receiver's clipboard (it must allow this device with --clipboard-from)",
+  send_path_arg: "file or directory path, or - to read from stdin",
+  send_name_option: "file name the receiver saves stdin content as",
+  send_streams_option: "split large files across parallel connections: auto or 1-16 (default auto)",
+  err_streams_value: "--streams must be auto or an integer between 1 and 16",
+  send_symlinks_option: "symlinks in directories: skip, follow (send what they point to) or preserve (recreate them; default skip)",
+  send_hardlinks_option: "recreate hard-linked files in directories as hard links instead of sending their content again",
+  send_xattrs_option: "send extended attributes with each file (macOS and Linux)",
+  err_symlinks_value: "--symlinks must be skip, follow or preserve",
+  send_atomic_option: "all or nothing: the receiver stages the files and moves them into place only when every one verified; any failure rolls the batch back",
+  send_note_option: "note attached to this send, searchable in both sides' history",
+  send_tag_option: "tag this send (repeatable)",
+  send_skip_from_option: "leave out the relative paths listed in this file, one per line, to resume an interrupted batch",
+  send_dry_run_option: "only check that the receiver accepts the pair code and has room, without sending",
+  send_unicode_option: "Unicode form of file names: nfc (composed, what Linux and Windows expect) or keep (default nfc)",
+  err_unicode_value: "--unicode must be nfc or keep",
+  send_name_collisions_option: "paths differing only in case or Unicode form: rename (numbered), skip or fail (default rename)",
+  err_name_collisions_value: "--name-collisions must be rename, skip or fail",
   send_host_option: "receiver host; if omitted, auto-discover first match",
   send_port_option: "receiver port",
   send_device_option: "receiver name filter when auto-discovering",
@@ -192,9 +432,11 @@ const en: Dictionary = {
   send_tls_fingerprint_option: "expected server cert SHA-256 fingerprint",
   send_tls_tofu_option: "trust server certificate fingerprint on first use",
   send_tls_known_hosts_option: "known hosts file path for --tls-tofu",
+  send_knock_option: "knock with the secret in LOCAL_SENT_KNOCK_SECRET before connecting to a stealth listener (automatic for discovered ones)",
   label_timeout: "timeout",
   label_port: "port",
   label_pair_ttl: "pair-ttl",
+  label_max_rate: "max-rate",
   err_positive_integer: "{label} must be a positive integer",
   err_pair_code_format: "pair code must be exactly 6 digits",
   discover_none: "No receiver found. Make sure another device runs: local-sent listen",
@@ -210,6 +452,7 @@ const en: Dictionary = {
   err_pair_code_and_generate_conflict: "--pair-code and --pair-generate are mutually exclusive",
   err_pair_ttl_positive: "--pair-ttl must be a positive integer",
   err_pair_once_requirement: "--pair-once requires --pair-code or --pair-generate or --pair-ttl",
+  err_knock_requires_secret: "the receiver is in stealth mode; knocking requires LOCAL_SENT_KNOCK_SECRET",
   listen_pair_code_rotated: "[listen] pair-code={code} (rotated:{reason}){ttlSuffix}",
   listen_service: "[listen] service={service}",
   listen_endpoint: "[listen] endpoint={host}:{port}",
@@ -217,6 +460,24 @@ const en: Dictionary = {
   listen_pair_code: "[listen] pair-code={code}{ttlSuffix}",
   listen_pair_ttl: "[listen] pair-ttl={seconds}s",
   listen_tls_enabled: "[listen] tls=enabled",
+  listen_webdav: "[listen] webdav={url}",
+  listen_quick_share: "[listen] quick-share=enabled",
+  listen_dns_sd: "[listen] dns-sd={zone}",
+  listen_answer_pings: "[listen] answer-pings=enabled",
+  listen_stealth: "[listen] stealth=enabled",
+  listen_knock_secret_generated: "[listen] knock-secret={secret} (senders set it in LOCAL_SENT_KNOCK_SECRET)",
+  listen_dns_sd_failed: "[listen] DNS-SD publishing failed: {error}",
+  listen_max_rate: "[listen] max-rate={rate}",
+  listen_share: "[listen] share={name} -> {dir}",
+  err_share_format: "--share expects name=dir, and the name cannot contain slashes",
+  err_share_duplicate: "share name {name} is used twice",
+  err_share_allow_format: "--share-allow expects name=addr1,addr2",
+  err_share_allow_unknown: "--share-allow names {name}, which is not shared",
+  shares_none: "[shares] nothing to browse",
+  pull_done: "[pull] saved {path} ({bytes})",
+  message_delivered: "[message] delivered to {host}:{port}",
+  clipboard_delivered: "[clipboard] synced to {host}:{port}",
+  listen_control_ignored: "[listen] ignored invalid control line: {line}",
   listen_press_ctrl_c: "[listen] Press Ctrl+C to stop.",
   listen_shutdown: "\n[listen] {signal}, shutting down...",
   err_tls_ca_requires_tls: "--tls-ca requires --tls",
@@ -226,9 +487,23 @@ const en: Dictionary = {
   err_tls_known_hosts_requires_tofu: "--tls-known-hosts requires --tls-tofu",
   err_tls_fingerprint_tofu_conflict: "--tls-fingerprint and --tls-tofu are mutually exclusive",
   err_no_receiver_found: "no receiver found. Use --host + --port or run local-sent discover first",
+  err_stream_name_required: "--name is required when sending from stdin (-)",
+  err_device_type_value: "--device-type must be laptop, desktop or server",
+  err_avatar_color_value: "--avatar-color must be a #rrggbb color",
+  err_avatar_emoji_value: "--avatar-emoji must be an emoji of at most 8 characters without spaces or control characters",
+  err_dry_run_stream: "--dry-run cannot be used when sending from stdin (-)",
+  err_note_too_long: "--note is limited to {max} characters",
+  err_too_many_tags: "at most {max} --tag values are allowed",
+  err_tag_format: "--tag must not be empty and is limited to {max} characters",
   discover_selected: "[discover] selected {name} ({host}:{port})",
   send_done: "[send] done: files={fileCount} bytes={bytes} resumed={resumed}",
   send_saved_path: "[send] receiver saved path: {savedPath}",
+  send_already_have: "[send] receiver already has it: {savedPath}",
+  send_name_issue: "[send] these paths collide on case-insensitive or normalizing filesystems ({kind}): {paths}",
+  send_name_renamed: "[send] renamed {from} -> {to}",
+  send_skip_from_skipped: "[send] skipping {count} file(s) already sent",
+  send_dry_run_ok: "[send] dry run passed: files={fileCount} bytes={bytes} free={free}",
+  send_name_skipped: "[send] skipped colliding path {path}",
   error_prefix: "[error] {message}"
 };
 
@@ -0,0 +1,115 @@
+import { ThroughputRegime } from "./progressAnalytics";
+import { TransferNote } from "./protocol";
+
+/**
+ * Machine-readable output for the desktop app (`--ipc json`). Every stdout
+ * line becomes one JSON object carrying the protocol version `v` and a
+ * `type`; free text printed anywhere in the CLI is wrapped as `log`, so
+ * callers never have to parse the human-oriented output.
+ */
+export const IPC_VERSION = 1;
+
+export type IpcMessage =
+  | { type: "hello"; command: string; cliVersion: string }
+  | { type: "log"; line: string }
+  | {
+      type: "progress";
+      direction: "send" | "recv";
+      path: string;
+      bytes: number;
+      // Null while streaming content of unknown length.
+      total: number | null;
+      // From `ProgressAnalytics`: a smoothed rate, and no ETA while stalled.
+      bytesPerSecond: number;
+      etaSeconds: number | null;
+      stalled: boolean;
+      regime: ThroughputRegime;
+      // Files of a multi-file send: this file's 1-based index and the bytes
+      // of the whole batch, for an overall percentage.
+      batch?: { index: number; count: number; bytes: number; total: number };
+    }
+  | {
+      type: "confirmRequest";
+      id: number;
+      from: string;
+      path: string;
+      size: number;
+      sha256: string;
+      streaming: boolean;
+      // An unpaired Quick Share sender; never auto-accepted.
+      quickShare?: boolean;
+    }
+  | { type: "listening"; service: string; host: string; port: number; output: string; tls: boolean }
+  // Only for files in their final place: staged batch files are reported on commit.
+  // `quickShare` marks files from an unpaired Quick Share sender.
+  | { type: "saved"; path: string; alreadyHad: boolean; from: string; note?: TransferNote; quickShare?: boolean }
+  | { type: "receiveFailed"; message: string }
+  // A text message from a peer; `from` is its address, `name` what it calls itself.
+  | { type: "message"; from: string; name: string; text: string }
+  // Clipboard text from a peer allowed by `listen --clipboard-from`.
+  | { type: "clipboard"; from: string; text: string }
+  // A peer browsed or pulled from a shared folder (`listen --share`).
+  | { type: "shareAccess"; from: string; action: "list" | "fetch"; path: string; allowed: boolean; bytes?: number }
+  | { type: "sendSaved"; path: string; deduplicated: boolean }
+  // The files a send is about to transfer, before the first one starts.
+  | { type: "sendPlan"; files: Array<{ path: string; size: number }> }
+  // One file of a send is safely on the receiver; `source` is the local file.
+  | { type: "fileSent"; path: string; source: string; sha256?: string }
+  | { type: "sendDone"; files: number; bytes: number; resumedBytes: number; manifest: SentFileRecord[] }
+  | { type: "pullDone"; path: string; bytes: number; sha256: string }
+  // `send --dry-run` passed: the receiver accepts the pair code and has room.
+  | { type: "dryRun"; files: number; bytes: number; freeBytes?: number }
+  | { type: "error"; message: string }
+  // Answer to a control request read from stdin, matched by `id`.
+  | { type: "response"; id: number; ok: boolean; result?: unknown; error?: string };
+
+/** One file of a finished send, in the order they were sent. */
+export interface SentFileRecord {
+  path: string;
+  bytes: number;
+  resumedBytes: number;
+  // The receiver already had the content and kept its copy.
+  deduplicated: boolean;
+  savedPath?: string;
+}
+
+let rawWrite: ((line: string) => void) | null = null;
+
+export function ipcEnabled(): boolean {
+  return rawWrite !== null;
+}
+
+/** No-op unless `--ipc json` is active. */
+export function emitIpc(message: IpcMessage): void {
+  rawWrite?.(`${JSON.stringify({ v: IPC_VERSION, ...message })}\n`);
+}
+
+/**
+ * Switches stdout to JSON lines. Text written afterwards is split on line
+ * breaks and carriage returns and re-emitted as `log` messages.
+ */
+export function enableIpc(): void {
+  if (rawWrite) {
+    return;
+  }
+  const stdout = process.stdout;
+  const write = stdout.write.bind(stdout) as (chunk: string) => boolean;
+  rawWrite = (line) => {
+    write(line);
+  };
+  let pending = "";
+  stdout.write = ((chunk: string | Uint8Array, ...rest: unknown[]): boolean => {
+    pending += typeof chunk === "string" ? chunk : Buffer.from(chunk).toString("utf8");
+    const parts = pending.split(/\r|\n/);
+    pending = parts.pop() ?? "";
+    for (const part of parts) {
+      const line = part.trim();
+      if (line) {
+        emitIpc({ type: "log", line });
+      }
+    }
+    const callback = rest.find((item) => typeof item === "function") as (() => void) | undefined;
+    callback?.();
+    return true;
+  }) as typeof stdout.write;
+}
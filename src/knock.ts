@@ -0,0 +1,222 @@
+import { createHmac, randomBytes, timingSafeEqual } from "crypto";
+import { createSocket, Socket as DgramSocket } from "dgram";
+import { isIP } from "net";
+import { KNOCK_MAGIC } from "./constants";
+
+/**
+ * Stealth listening: the TCP port stays closed until a peer knocks with a
+ * UDP packet on the same port number, keyed with a shared knock secret.
+ * The listener then accepts connections from that address only, for a
+ * window that each connection renews, and closes the port again once every
+ * window has lapsed, so a port scan of a shared network finds nothing.
+ *
+ * The secret is separate from the pair code on purpose: a knock travels in
+ * the clear, and a 6-digit code behind one sniffed HMAC falls to an offline
+ * search in moments. The secret must be long enough that it does not.
+ */
+export interface KnockPacket {
+  magic: string;
+  // Sender clock in ms; knocks more than KNOCK_MAX_SKEW_MS off are dropped.
+  ts: number;
+  nonce: string;
+  mac: string;
+}
+
+export interface KnockGateOptions {
+  port: number;
+  host?: string;
+  secret: string;
+  // Called with `true` on every accepted knock, so a port that failed to
+  // reopen is retried, and with `false` once the last peer lapses.
+  onOpenChange: (open: boolean) => void;
+}
+
+export const KNOCK_SECRET_ENV = "LOCAL_SENT_KNOCK_SECRET";
+export const KNOCK_WINDOW_MS = 30_000;
+const MIN_SECRET_LENGTH = 16;
+const GENERATED_SECRET_BYTES = 18;
+const KNOCK_MAX_SKEW_MS = 60_000;
+const NONCE_BYTES = 16;
+const MAX_REMEMBERED_NONCES = 4096;
+// Time for the listener to open its port before the sender connects.
+const KNOCK_SETTLE_MS = 250;
+
+/** Checks a knock secret; returns it trimmed. */
+export function validateKnockSecret(secret: string): string {
+  const trimmed = secret.trim();
+  if (trimmed.length < MIN_SECRET_LENGTH) {
+    throw new Error(`knock secret must be at least ${MIN_SECRET_LENGTH} characters`);
+  }
+  return trimmed;
+}
+
+/** The knock secret from `LOCAL_SENT_KNOCK_SECRET`, if set. */
+export function knockSecretFromEnv(): string | undefined {
+  const secret = process.env[KNOCK_SECRET_ENV];
+  return secret ? validateKnockSecret(secret) : undefined;
+}
+
+export function generateKnockSecret(): string {
+  return randomBytes(GENERATED_SECRET_BYTES).toString("base64url");
+}
+
+function knockMac(secret: string, port: number, ts: number, nonce: string): Buffer {
+  return createHmac("sha256", secret).update(`${KNOCK_MAGIC}\n${port}\n${ts}\n${nonce}`).digest();
+}
+
+export function buildKnock(secret: string, port: number, now = Date.now()): KnockPacket {
+  const nonce = randomBytes(NONCE_BYTES).toString("base64");
+  return { magic: KNOCK_MAGIC, ts: now, nonce, mac: knockMac(secret, port, now, nonce).toString("hex") };
+}
+
+/** Whether `packet` is a fresh knock keyed with `secret`. */
+export function isValidKnock(packet: KnockPacket, port: number, secret: string, now = Date.now()): boolean {
+  if (
+    packet.magic !== KNOCK_MAGIC ||
+    !Number.isSafeInteger(packet.ts) ||
+    Math.abs(now - packet.ts) > KNOCK_MAX_SKEW_MS ||
+    typeof packet.nonce !== "string" ||
+    Buffer.from(packet.nonce, "base64").length !== NONCE_BYTES ||
+    typeof packet.mac !== "string" ||
+    !/^[0-9a-f]{64}$/.test(packet.mac)
+  ) {
+    return false;
+  }
+  return timingSafeEqual(knockMac(secret, port, packet.ts, packet.nonce), Buffer.from(packet.mac, "hex"));
+}
+
+/** Sends a knock for `host:port` and gives the listener a moment to open. */
+export async function knock(host: string, port: number, secret: string): Promise<void> {
+  const socket = createSocket(isIP(host) === 6 ? "udp6" : "udp4");
+  try {
+    await new Promise<void>((resolve, reject) => {
+      socket.once("error", reject);
+      socket.send(Buffer.from(JSON.stringify(buildKnock(secret, port)), "utf8"), port, host, (err) =>
+        err ? reject(err) : resolve()
+      );
+    });
+  } finally {
+    socket.close();
+  }
+  await new Promise((resolve) => setTimeout(resolve, KNOCK_SETTLE_MS));
+}
+
+function normalizeAddress(address: string | undefined): string {
+  return (address ?? "").replace(/^::ffff:/i, "");
+}
+
+/** Tracks which addresses have knocked and opens or closes the port to match. */
+export class KnockGate {
+  // Window end and open connections per admitted address.
+  private readonly admitted = new Map<string, { until: number; active: number }>();
+  private readonly seenNonces = new Map<string, number>();
+  private socket: DgramSocket | null = null;
+  private timer: NodeJS.Timeout | null = null;
+  private open = false;
+
+  constructor(private readonly options: KnockGateOptions) {}
+
+  async start(): Promise<void> {
+    const socket = createSocket({ type: this.options.host && isIP(this.options.host) === 6 ? "udp6" : "udp4" });
+    socket.on("message", (message, rinfo) => this.handleKnock(message, rinfo.address));
+    await new Promise<void>((resolve, reject) => {
+      socket.once("error", reject);
+      socket.bind(this.options.port, this.options.host, () => {
+        socket.removeListener("error", reject);
+        resolve();
+      });
+    });
+    socket.on("error", () => {
+      // Keep running; peers that cannot knock just cannot connect.
+    });
+    this.socket = socket;
+  }
+
+  /**
+   * Whether a connection from `address` may proceed. Admitted addresses
+   * stay admitted while they have connections open; call `release` when
+   * one closes.
+   */
+  admit(address: string | undefined): boolean {
+    const entry = this.admitted.get(normalizeAddress(address));
+    if (!entry || (entry.active === 0 && entry.until < Date.now())) {
+      return false;
+    }
+    entry.active += 1;
+    return true;
+  }
+
+  /** Starts a fresh window for `address` once a connection closes. */
+  release(address: string | undefined): void {
+    const entry = this.admitted.get(normalizeAddress(address));
+    if (entry) {
+      entry.active = Math.max(0, entry.active - 1);
+      entry.until = Date.now() + KNOCK_WINDOW_MS;
+      this.update();
+    }
+  }
+
+  close(): void {
+    if (this.timer) {
+      clearTimeout(this.timer);
+      this.timer = null;
+    }
+    this.socket?.close();
+    this.socket = null;
+    this.admitted.clear();
+  }
+
+  private handleKnock(message: Buffer, address: string): void {
+    let packet: KnockPacket;
+    try {
+      packet = JSON.parse(message.toString("utf8")) as KnockPacket;
+    } catch {
+      return;
+    }
+    if (!packet || typeof packet !== "object") {
+      return;
+    }
+    const now = Date.now();
+    for (const [nonce, expiresAt] of this.seenNonces) {
+      if (expiresAt < now) {
+        this.seenNonces.delete(nonce);
+      }
+    }
+    if (this.seenNonces.has(packet.nonce) || this.seenNonces.size >= MAX_REMEMBERED_NONCES) {
+      return;
+    }
+    if (!isValidKnock(packet, this.options.port, this.options.secret, now)) {
+      return;
+    }
+    this.seenNonces.set(packet.nonce, now + 2 * KNOCK_MAX_SKEW_MS);
+    const key = normalizeAddress(address);
+    this.admitted.set(key, { until: now + KNOCK_WINDOW_MS, active: this.admitted.get(key)?.active ?? 0 });
+    this.open = true;
+    this.options.onOpenChange(true);
+    this.update();
+  }
+
+  private update(): void {
+    const now = Date.now();
+    for (const [address, entry] of this.admitted) {
+      if (entry.active === 0 && entry.until < now) {
+        this.admitted.delete(address);
+      }
+    }
+    const open = this.admitted.size > 0;
+    if (open !== this.open) {
+      this.open = open;
+      this.options.onOpenChange(open);
+    }
+    if (this.timer) {
+      clearTimeout(this.timer);
+      this.timer = null;
+    }
+    const idle = [...this.admitted.values()].filter((entry) => entry.active === 0);
+    if (idle.length > 0) {
+      const next = Math.min(...idle.map((entry) => entry.until));
+      this.timer = setTimeout(() => this.update(), Math.max(0, next - now) + 50);
+      this.timer.unref();
+    }
+  }
+}
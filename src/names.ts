@@ -0,0 +1,102 @@
+import { posix } from "path";
+import { TransferEntry } from "./utils";
+
+/**
+ * `nfc` rewrites names to the composed form Linux and Windows tools expect
+ * (macOS often hands out decomposed names); `keep` sends them unchanged.
+ */
+export type UnicodeForm = "nfc" | "keep";
+
+/**
+ * What to do with paths that only differ in case or Unicode form, which
+ * land on the same file on case-insensitive or normalizing filesystems.
+ */
+export type CollisionStrategy = "rename" | "skip" | "fail";
+
+export interface NameSafetyOptions {
+  unicode: UnicodeForm;
+  collisions: CollisionStrategy;
+}
+
+export interface NameIssue {
+  kind: "case" | "unicode";
+  paths: string[];
+}
+
+export interface NameSafetyResult {
+  entries: TransferEntry[];
+  issues: NameIssue[];
+  renamed: Array<{ from: string; to: string }>;
+  skipped: string[];
+}
+
+function collisionKey(path: string): string {
+  return path.normalize("NFC").toLowerCase();
+}
+
+/** Groups paths that would end up as the same file on another system. */
+export function findNameIssues(paths: string[]): NameIssue[] {
+  const groups = new Map<string, string[]>();
+  for (const path of paths) {
+    const key = collisionKey(path);
+    groups.set(key, [...(groups.get(key) ?? []), path]);
+  }
+  const issues: NameIssue[] = [];
+  for (const group of groups.values()) {
+    if (group.length < 2) {
+      continue;
+    }
+    const composed = new Set(group.map((path) => path.normalize("NFC")));
+    issues.push({ kind: composed.size < group.length ? "unicode" : "case", paths: group });
+  }
+  return issues;
+}
+
+function withSuffix(path: string, index: number): string {
+  const ext = posix.extname(path);
+  const base = ext ? path.slice(0, -ext.length) : path;
+  return `${base}(${index})${ext}`;
+}
+
+/**
+ * Applies the Unicode form and resolves collisions before anything is sent,
+ * so no entry silently replaces another on the receiver.
+ */
+export function applyNameSafety(entries: TransferEntry[], options: NameSafetyOptions): NameSafetyResult {
+  const issues = findNameIssues(entries.map((entry) => entry.relativePath));
+  if (options.collisions === "fail" && issues.length > 0) {
+    throw new Error(`name collision: ${issues[0].paths.join(", ")}`);
+  }
+
+  const taken = new Set<string>();
+  const renamed: Array<{ from: string; to: string }> = [];
+  const skipped: string[] = [];
+  const finalPaths = new Map<string, string>();
+  const result: TransferEntry[] = [];
+  for (const entry of entries) {
+    let relativePath = options.unicode === "nfc" ? entry.relativePath.normalize("NFC") : entry.relativePath;
+    if (taken.has(collisionKey(relativePath))) {
+      if (options.collisions === "skip") {
+        skipped.push(entry.relativePath);
+        continue;
+      }
+      let index = 1;
+      while (taken.has(collisionKey(withSuffix(relativePath, index)))) {
+        index += 1;
+      }
+      relativePath = withSuffix(relativePath, index);
+      renamed.push({ from: entry.relativePath, to: relativePath });
+    }
+    taken.add(collisionKey(relativePath));
+    finalPaths.set(entry.relativePath, relativePath);
+    result.push({ ...entry, relativePath });
+  }
+
+  // Hard links name earlier entries by path; follow their renames.
+  for (const entry of result) {
+    if (entry.link?.type === "hardlink") {
+      entry.link = { ...entry.link, target: finalPaths.get(entry.link.target) ?? entry.link.target };
+    }
+  }
+  return { entries: result, issues, renamed, skipped };
+}
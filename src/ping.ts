@@ -0,0 +1,237 @@
+import { createHash, KeyObject, randomBytes, sign, verify, X509Certificate } from "crypto";
+import { createSocket, Socket as DgramSocket } from "dgram";
+import { CLI_VERSION, PING_MAGIC, PING_PORT, PROTOCOL_VERSION } from "./constants";
+
+/**
+ * "Ping all listeners": a last-resort discovery path for networks where
+ * neither mDNS nor the regular UDP probe gets an answer through, e.g.
+ * because replies to the sender's ephemeral port are dropped. The sender
+ * broadcasts a nonce and a rendezvous port it has opened; listeners that
+ * opted in answer there. Listeners serving TLS sign the reply with their
+ * certificate key, so the fingerprint a reply carries is proven rather
+ * than claimed. Both sides are opt-in and listeners answer each source
+ * only a few times per window.
+ */
+export interface PingProbe {
+  magic: string;
+  nonce: string;
+  replyPort: number;
+}
+
+export interface PingReply {
+  magic: string;
+  nonce: string;
+  name: string;
+  port: number;
+  id?: string;
+  version?: string;
+  protocolVersion?: number;
+  tls?: boolean;
+  pairCodeRequired?: boolean;
+  capabilities?: string[];
+  // Base64 DER certificate and signature over `signedPart`, when signed.
+  cert?: string;
+  signature?: string;
+}
+
+/** What a verified reply tells the sender. */
+export interface PingAnswer {
+  reply: PingReply;
+  address: string;
+  // Set only when the reply was signed by this certificate.
+  fingerprint?: string;
+}
+
+export interface PingSigner {
+  cert: Buffer;
+  key: KeyObject;
+}
+
+const NONCE_BYTES = 16;
+const RATE_WINDOW_MS = 10_000;
+const MAX_REPLIES_PER_SOURCE = 3;
+const MAX_REPLIES_PER_WINDOW = 30;
+
+/** The reply fields the signature covers, in a fixed order. */
+function signedPart(reply: PingReply): Buffer {
+  return Buffer.from(
+    JSON.stringify([
+      reply.magic,
+      reply.nonce,
+      reply.name,
+      reply.port,
+      reply.id ?? null,
+      reply.tls ?? null,
+      reply.pairCodeRequired ?? null,
+      reply.capabilities ?? []
+    ]),
+    "utf8"
+  );
+}
+
+// Ed25519 and Ed448 keys sign the message itself; the rest sign a SHA-256 digest.
+function digestFor(key: KeyObject): string | null {
+  return key.asymmetricKeyType === "ed25519" || key.asymmetricKeyType === "ed448" ? null : "sha256";
+}
+
+export function signPingReply(reply: PingReply, signer: PingSigner): PingReply {
+  return {
+    ...reply,
+    cert: signer.cert.toString("base64"),
+    signature: sign(digestFor(signer.key), signedPart(reply), signer.key).toString("base64")
+  };
+}
+
+/**
+ * Checks a reply against the probe's nonce. Resolves to the certificate
+ * fingerprint for signed replies, `undefined` for unsigned ones and
+ * `null` for replies to drop.
+ */
+export function verifyPingReply(reply: PingReply, nonce: string): string | undefined | null {
+  if (reply.magic !== PING_MAGIC || reply.nonce !== nonce) {
+    return null;
+  }
+  if (!reply.cert && !reply.signature) {
+    return undefined;
+  }
+  try {
+    const raw = Buffer.from(reply.cert ?? "", "base64");
+    const cert = new X509Certificate(raw);
+    const signature = Buffer.from(reply.signature ?? "", "base64");
+    if (!verify(digestFor(cert.publicKey), signedPart(reply), cert.publicKey, signature)) {
+      return null;
+    }
+    return createHash("sha256").update(raw).digest("hex");
+  } catch {
+    return null;
+  }
+}
+
+/** Answers at most a few pings per source, and a few dozen overall, per window. */
+export class PingRateLimiter {
+  private windowStartedAt = 0;
+  private total = 0;
+  private readonly perSource = new Map<string, number>();
+
+  allow(address: string, now = Date.now()): boolean {
+    if (now - this.windowStartedAt >= RATE_WINDOW_MS) {
+      this.windowStartedAt = now;
+      this.total = 0;
+      this.perSource.clear();
+    }
+    const count = this.perSource.get(address) ?? 0;
+    if (count >= MAX_REPLIES_PER_SOURCE || this.total >= MAX_REPLIES_PER_WINDOW) {
+      return false;
+    }
+    this.perSource.set(address, count + 1);
+    this.total += 1;
+    return true;
+  }
+}
+
+function parseProbe(message: Buffer): PingProbe | null {
+  try {
+    const probe = JSON.parse(message.toString("utf8")) as PingProbe;
+    const valid =
+      probe.magic === PING_MAGIC &&
+      typeof probe.nonce === "string" &&
+      Buffer.from(probe.nonce, "base64").length === NONCE_BYTES &&
+      Number.isInteger(probe.replyPort) &&
+      probe.replyPort > 0 &&
+      probe.replyPort <= 65535;
+    return valid ? probe : null;
+  } catch {
+    return null;
+  }
+}
+
+/** Listens for pings until the socket is closed. */
+export function createPingResponder(
+  announcement: Omit<PingReply, "magic" | "nonce" | "version" | "protocolVersion" | "cert" | "signature">,
+  signer?: PingSigner
+): DgramSocket {
+  const socket = createSocket({ type: "udp4", reuseAddr: true });
+  const limiter = new PingRateLimiter();
+  socket.on("error", () => {
+    // Keep running even if pings cannot be answered.
+  });
+
+  socket.on("message", (message, rinfo) => {
+    const probe = parseProbe(message);
+    if (!probe || !limiter.allow(rinfo.address)) {
+      return;
+    }
+    const reply: PingReply = {
+      magic: PING_MAGIC,
+      nonce: probe.nonce,
+      ...announcement,
+      version: CLI_VERSION,
+      protocolVersion: PROTOCOL_VERSION
+    };
+    try {
+      const payload = JSON.stringify(signer ? signPingReply(reply, signer) : reply);
+      socket.send(Buffer.from(payload, "utf8"), probe.replyPort, rinfo.address);
+    } catch {
+      // Ignore reply send failures.
+    }
+  });
+
+  socket.bind(PING_PORT);
+  return socket;
+}
+
+/**
+ * Broadcasts one ping and collects the answers that arrive at the
+ * rendezvous port within `timeoutMs`. `replyPort` 0 lets the system pick.
+ */
+export async function pingListeners(timeoutMs: number, replyPort = 0, includeLoopback = false): Promise<PingAnswer[]> {
+  const nonce = randomBytes(NONCE_BYTES).toString("base64");
+  const answers = new Map<string, PingAnswer>();
+  const socket = createSocket({ type: "udp4", reuseAddr: true });
+  return new Promise((resolve) => {
+    let finished = false;
+    const done = (): void => {
+      if (finished) {
+        return;
+      }
+      finished = true;
+      try {
+        socket.close();
+      } catch {
+        // Ignore close errors.
+      }
+      resolve([...answers.values()]);
+    };
+
+    socket.on("message", (message, rinfo) => {
+      let reply: PingReply;
+      try {
+        reply = JSON.parse(message.toString("utf8")) as PingReply;
+      } catch {
+        return;
+      }
+      const fingerprint = verifyPingReply(reply, nonce);
+      if (fingerprint === null) {
+        return;
+      }
+      answers.set(`${rinfo.address}:${reply.port}`, { reply, address: rinfo.address, fingerprint });
+    });
+
+    socket.on("error", () => done());
+    socket.bind(replyPort, () => {
+      try {
+        socket.setBroadcast(true);
+        const probe: PingProbe = { magic: PING_MAGIC, nonce, replyPort: socket.address().port };
+        const packet = Buffer.from(JSON.stringify(probe), "utf8");
+        socket.send(packet, PING_PORT, "255.255.255.255");
+        if (includeLoopback) {
+          socket.send(packet, PING_PORT, "127.0.0.1");
+        }
+      } catch {
+        done();
+        return;
+      }
+      setTimeout(done, timeoutMs);
+    });
+  });
+}
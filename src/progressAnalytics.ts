@@ -0,0 +1,99 @@
+/**
+ * Throughput figures shared by every progress report, sent or received, so
+ * the terminal line, the IPC message and the desktop app agree. The rate is
+ * an exponentially weighted moving average of short samples, which keeps
+ * the ETA from swinging with every burst; the desktop app's own senders
+ * use the same constants in `progress_stats.rs`.
+ */
+
+/** Weight of the newest sample in the moving average. */
+const RATE_SMOOTHING = 0.3;
+/** Bytes arriving closer together than this are pooled into one sample. */
+const MIN_SAMPLE_MS = 250;
+/** No new bytes for this long counts as a stall. */
+export const STALL_AFTER_MS = 5_000;
+/** Before this, the average is too young to classify. */
+const WARMUP_MS = 2_000;
+/** A smoothed deviation above this share of the rate is bursty. */
+const BURSTY_DEVIATION = 0.5;
+/** Below this share of the best rate seen, the transfer has degraded. */
+const DEGRADED_SHARE = 0.25;
+
+export type ThroughputRegime = "starting" | "steady" | "bursty" | "degraded" | "stalled";
+
+export interface ProgressSnapshot {
+  bytesPerSecond: number;
+  // Null while the rate is unknown or the transfer is stalled.
+  etaSeconds: number | null;
+  stalled: boolean;
+  regime: ThroughputRegime;
+}
+
+export class ProgressAnalytics {
+  private bytes = 0;
+  private sampleAt: number;
+  private sampleBytes = 0;
+  private lastAdvanceAt: number;
+  private rate: number | null = null;
+  private deviation = 0;
+  private peakRate = 0;
+
+  constructor(private readonly startedAt: number) {
+    this.sampleAt = startedAt;
+    this.lastAdvanceAt = startedAt;
+  }
+
+  observe(bytes: number, now = Date.now()): void {
+    if (bytes > this.bytes) {
+      this.lastAdvanceAt = now;
+    }
+    this.bytes = bytes;
+    const elapsedMs = now - this.sampleAt;
+    if (elapsedMs < MIN_SAMPLE_MS) {
+      return;
+    }
+    const sample = (Math.max(0, bytes - this.sampleBytes) * 1000) / elapsedMs;
+    if (this.rate === null) {
+      this.rate = sample;
+    } else {
+      this.deviation = RATE_SMOOTHING * Math.abs(sample - this.rate) + (1 - RATE_SMOOTHING) * this.deviation;
+      this.rate = RATE_SMOOTHING * sample + (1 - RATE_SMOOTHING) * this.rate;
+    }
+    this.peakRate = Math.max(this.peakRate, this.rate);
+    this.sampleAt = now;
+    this.sampleBytes = bytes;
+  }
+
+  /** Milliseconds since the byte count last went up. */
+  idleFor(now = Date.now()): number {
+    return now - this.lastAdvanceAt;
+  }
+
+  /** `total` is null for content of unknown length. */
+  snapshot(total: number | null, now = Date.now()): ProgressSnapshot {
+    const stalled = this.idleFor(now) >= STALL_AFTER_MS && (total === null || this.bytes < total);
+    // Until the first sample, the plain average is all there is.
+    const rate = this.rate ?? this.bytes / Math.max(1, (now - this.startedAt) / 1000);
+    const etaSeconds =
+      total === null || stalled || rate <= 0 ? null : Math.ceil(Math.max(0, total - this.bytes) / rate);
+    return { bytesPerSecond: Math.round(rate), etaSeconds, stalled, regime: this.regime(stalled, rate, now) };
+  }
+
+  private regime(stalled: boolean, rate: number, now: number): ThroughputRegime {
+    if (stalled) {
+      return "stalled";
+    }
+    if (this.rate === null || now - this.startedAt < WARMUP_MS) {
+      return "starting";
+    }
+    if (rate < this.peakRate * DEGRADED_SHARE) {
+      return "degraded";
+    }
+    return this.deviation > rate * BURSTY_DEVIATION ? "bursty" : "steady";
+  }
+}
+
+/** Appended to a progress line; steady transfers need no note. */
+export function describeRegime(snapshot: ProgressSnapshot): string {
+  return snapshot.regime === "steady" ? "" : ` ${snapshot.regime}`;
+}
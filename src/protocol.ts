@@ -1,12 +1,175 @@
+/**
+ * Version 1 announces size and SHA-256 up front. Version 2 streams content of
+ * unknown length: `fileSize` is `STREAM_FILE_SIZE`, `sha256` is empty, the
+ * payload ends when the sender half-closes, and the sender checks the digest
+ * returned in the ack.
+ *
+ * Version 3 carries one byte range of a large file that is split across
+ * several connections. The receiver reassembles all ranges of a `sessionId`
+ * into one file and checks `sha256` over the whole file once every range
+ * arrived; each connection then gets the same ack.
+ *
+ * Version 4 carries no payload: it recreates `link` at `relativePath`.
+ * `fileSize` is 0 and `sha256` is empty. The receiver answers with a ready
+ * message followed directly by the ack.
+ *
+ * Version 5 sends a sparse file: `extents` lists its data regions as flat
+ * `[start, length, ...]` pairs, everything else is a hole. The payload is
+ * the regions back to back; the receiver recreates the holes and checks
+ * `sha256` over the whole file. Like streams, it cannot be resumed.
+ *
+ * Any version may carry `metadata`; receivers that do not preserve it, or
+ * predate it, ignore the field. `note` works the same way. `batch` puts the
+ * entry into an atomic batch opened with a `BatchControl` message.
+ */
 export interface TransferHeader {
   type: "header";
-  version: 1;
+  version: 1 | 2 | 3 | 4 | 5;
   relativePath: string;
   fileSize: number;
   sha256: string;
   pairCode?: string;
+  range?: ParallelRange;
+  link?: EntryLink;
+  extents?: number[];
+  metadata?: EntryMetadata;
+  batch?: string;
+  note?: TransferNote;
 }
 
+/**
+ * Atomic batches: the sender opens one with `begin`, tags every entry header
+ * with its `batchId`, then sends `commit` or `abort` on a fresh connection.
+ * The receiver stages entries in a hidden folder and moves them into place
+ * only on commit. Receivers that predate batches reject `begin` with a
+ * protocol error, so nothing is ever sent non-atomically by accident. The
+ * reply is an `AckMessage`.
+ */
+export interface BatchControl {
+  type: "batch";
+  action: "begin" | "commit" | "abort";
+  batchId: string;
+  pairCode?: string;
+  reason?: string;
+}
+
+/**
+ * Pull mode: the peer asks the receiver for files instead of sending them.
+ * Receivers serve only folders shared explicitly with `listen --share`.
+ * `path` is `<share>/<relative path>`; an empty `path` lists the shares.
+ * `list` is answered with one `ShareReply` carrying `entries`. `fetch` is
+ * answered with a `ShareReply` carrying `fileSize` and `sha256`, followed by
+ * the file's bytes until the receiver closes the connection.
+ */
+export interface ShareRequest {
+  type: "share";
+  action: "list" | "fetch";
+  path: string;
+  pairCode?: string;
+}
+
+export interface ShareEntry {
+  name: string;
+  isDir: boolean;
+  // 0 for folders.
+  size: number;
+  mtimeMs: number;
+}
+
+export interface ShareReply {
+  type: "shares";
+  ok: boolean;
+  message?: string;
+  entries?: ShareEntry[];
+  fileSize?: number;
+  sha256?: string;
+}
+
+/**
+ * A short note for the person at the receiver, e.g. "sending now, accept
+ * it". Sent on the transfer port and answered with an `AckMessage`; it is
+ * shown, never saved as a file. Receivers that predate it answer with a
+ * protocol error.
+ */
+export interface TextMessage {
+  type: "message";
+  text: string;
+  // Display name of the sending device.
+  from?: string;
+  pairCode?: string;
+}
+
+export const MAX_MESSAGE_CHARS = 2000;
+
+/**
+ * Clipboard sync: the sender's clipboard text, pushed when it changes.
+ * Receivers take it only from peers they listed with `--clipboard-from`
+ * and answer with an `AckMessage`.
+ */
+export interface ClipboardMessage {
+  type: "clipboard";
+  text: string;
+  pairCode?: string;
+}
+
+// UTF-8 bytes; larger clipboards are not synced.
+export const MAX_CLIPBOARD_BYTES = 256 * 1024;
+
+/**
+ * `send --dry-run`: what a send would transfer. The receiver checks the
+ * pair code and its free space, writes nothing, and answers with an
+ * `AckMessage` carrying `freeBytes`. Receivers that predate it answer with
+ * a protocol error.
+ */
+export interface ProbeMessage {
+  type: "probe";
+  files: number;
+  totalBytes: number;
+  pairCode?: string;
+}
+
+/**
+ * What the sender wrote about a transfer, e.g. "signed contract" with the
+ * tag "work". Every entry of a send carries the same note; it only ends up
+ * in both sides' history.
+ */
+export interface TransferNote {
+  text?: string;
+  tags?: string[];
+}
+
+export const MAX_NOTE_CHARS = 500;
+export const MAX_NOTE_TAGS = 10;
+export const MAX_TAG_CHARS = 32;
+
+export interface EntryLink {
+  type: "symlink" | "hardlink";
+  // Symlinks: the link text, relative to the link's own directory.
+  // Hard links: the transfer path of an earlier entry of the same batch.
+  target: string;
+}
+
+export interface EntryMetadata {
+  // Permission bits (`0o777`); setuid, setgid and sticky bits are never sent.
+  mode?: number;
+  mtimeMs?: number;
+  // Extended attributes, base64 values keyed by name. Only restored by a
+  // receiver on the same `platform`, where the names mean the same thing.
+  xattrs?: Record<string, string>;
+  platform?: string;
+}
+
+export interface ParallelRange {
+  sessionId: string;
+  index: number;
+  count: number;
+  // Byte offsets within the file; `end` is exclusive.
+  start: number;
+  end: number;
+}
+
+export const STREAM_FILE_SIZE = -1;
+
 export interface ReadyMessage {
   type: "ready";
   ok: boolean;
@@ -23,7 +186,11 @@ export interface AckMessage {
   receivedBytes?: number;
   savedPath?: string;
   resumedFrom?: number;
+  deduplicated?: boolean;
+  streams?: number;
   nextPairCode?: string;
+  // Answer to a `ProbeMessage`; absent when the receiver cannot tell.
+  freeBytes?: number;
 }
 
 export function encodeJsonLine(value: unknown): Buffer {
@@ -0,0 +1,631 @@
+import { Bonjour } from "bonjour-service";
+import {
+  createCipheriv,
+  createDecipheriv,
+  createECDH,
+  createHash,
+  createHmac,
+  hkdfSync,
+  randomBytes,
+  randomInt,
+  timingSafeEqual
+} from "crypto";
+import { Socket } from "net";
+
+/*
+ * Experimental receiver for Google Quick Share (formerly Nearby Share) over
+ * the LAN, pieced together from public reimplementations of the protocol.
+ * Senders find it over mDNS, run a UKEY2 handshake, and then exchange
+ * AES-CBC + HMAC protected protobuf frames. Only the Wi-Fi LAN medium and
+ * file attachments are handled; text, links and Wi-Fi credentials are
+ * declined.
+ */
+
+const SERVICE_TYPE = "FC9F5ED42C8A";
+const MAX_FRAME_BYTES = 5 * 1024 * 1024;
+const NEXT_PROTOCOL = "AES_256_CBC-HMAC_SHA256";
+const P256_SHA512 = 100n;
+// SHA-256 of "D2D" and of "SecureMessage".
+const D2D_SALT = Buffer.from("82aa55a0d397f88346ca1cee8d3909b95f13fa7deb1d4ab38376b8256da85510", "hex");
+const SECURE_MESSAGE_SALT = Buffer.from("bf9d2a53c63616d75db0a7165b91c1ef73e537f2427405fa23610a4be657642e", "hex");
+
+// Offline (connections layer) frame types.
+const CONNECTION_REQUEST = 1n;
+const CONNECTION_RESPONSE = 2n;
+const PAYLOAD_TRANSFER = 3n;
+const KEEP_ALIVE = 5n;
+const DISCONNECTION = 6n;
+// Payload types and chunk flags.
+const PAYLOAD_BYTES = 1n;
+const PAYLOAD_FILE = 2n;
+const LAST_CHUNK = 1n;
+// Sharing frame types.
+const SHARING_INTRODUCTION = 1n;
+const SHARING_RESPONSE = 2n;
+const SHARING_PAIRED_KEY_ENCRYPTION = 3n;
+const SHARING_PAIRED_KEY_RESULT = 4n;
+const SHARING_CANCEL = 6n;
+// Ukey2 message types.
+const UKEY2_CLIENT_INIT = 2n;
+const UKEY2_SERVER_INIT = 3n;
+const UKEY2_CLIENT_FINISH = 4n;
+
+type ProtoValue = bigint | Buffer;
+type ProtoFields = Map<number, ProtoValue[]>;
+type ProtoInput = number | bigint | boolean | string | Buffer | undefined;
+
+function encodeVarint(value: number | bigint): Buffer {
+  let remaining = BigInt.asUintN(64, BigInt(value));
+  const bytes: number[] = [];
+  do {
+    let byte = Number(remaining & 0x7fn);
+    remaining >>= 7n;
+    if (remaining > 0n) {
+      byte |= 0x80;
+    }
+    bytes.push(byte);
+  } while (remaining > 0n);
+  return Buffer.from(bytes);
+}
+
+/** Protobuf encoding of `fields`, in order; `undefined` values are left out. */
+function encodeMessage(fields: Array<[number, ProtoInput]>): Buffer {
+  const parts: Buffer[] = [];
+  for (const [field, value] of fields) {
+    if (value === undefined) {
+      continue;
+    }
+    if (typeof value === "string" || Buffer.isBuffer(value)) {
+      const bytes = typeof value === "string" ? Buffer.from(value, "utf8") : value;
+      parts.push(encodeVarint((field << 3) | 2), encodeVarint(bytes.length), bytes);
+    } else {
+      parts.push(encodeVarint(field << 3), encodeVarint(typeof value === "boolean" ? Number(value) : value));
+    }
+  }
+  return Buffer.concat(parts);
+}
+
+function decodeMessage(buffer: Buffer): ProtoFields {
+  const fields: ProtoFields = new Map();
+  let offset = 0;
+  const readVarint = (): bigint => {
+    let result = 0n;
+    for (let shift = 0n; shift < 70n; shift += 7n) {
+      if (offset >= buffer.length) {
+        throw new Error("truncated protobuf varint");
+      }
+      const byte = buffer[offset++];
+      result |= BigInt(byte & 0x7f) << shift;
+      if ((byte & 0x80) === 0) {
+        return result;
+      }
+    }
+    throw new Error("protobuf varint too long");
+  };
+  const take = (length: number): Buffer => {
+    if (offset + length > buffer.length) {
+      throw new Error("truncated protobuf field");
+    }
+    const bytes = buffer.subarray(offset, offset + length);
+    offset += length;
+    return bytes;
+  };
+  while (offset < buffer.length) {
+    const key = readVarint();
+    const field = Number(key >> 3n);
+    let value: ProtoValue;
+    switch (Number(key & 7n)) {
+      case 0:
+        value = readVarint();
+        break;
+      case 1:
+        value = take(8);
+        break;
+      case 2:
+        value = take(Number(readVarint()));
+        break;
+      case 5:
+        value = take(4);
+        break;
+      default:
+        throw new Error("unsupported protobuf wire type");
+    }
+    fields.set(field, [...(fields.get(field) ?? []), value]);
+  }
+  return fields;
+}
+
+function bytesField(fields: ProtoFields, field: number): Buffer | undefined {
+  const value = fields.get(field)?.[0];
+  return Buffer.isBuffer(value) ? value : undefined;
+}
+
+function numberField(fields: ProtoFields, field: number): bigint | undefined {
+  const value = fields.get(field)?.[0];
+  return typeof value === "bigint" ? value : undefined;
+}
+
+function messageField(fields: ProtoFields, field: number): ProtoFields {
+  return decodeMessage(bytesField(fields, field) ?? Buffer.alloc(0));
+}
+
+/**
+ * Offline (connections layer) and sharing frames share this shape: version
+ * 1, then a v1 frame holding its type and the field for that type.
+ */
+function v1Frame(type: bigint, field: number, body: Buffer): Buffer {
+  return encodeMessage([
+    [1, 1],
+    [2, encodeMessage([[1, type], [field, body]])]
+  ]);
+}
+
+function writeFrame(socket: Socket, frame: Buffer): void {
+  const length = Buffer.alloc(4);
+  length.writeUInt32BE(frame.length);
+  socket.write(Buffer.concat([length, frame]));
+}
+
+function base64Url(bytes: Buffer): string {
+  return bytes.toString("base64url");
+}
+
+/** Public key coordinates are big-endian two's complement. */
+function signedCoordinate(bytes: Buffer): Buffer {
+  return bytes[0] >= 0x80 ? Buffer.concat([Buffer.from([0]), bytes]) : bytes;
+}
+
+function unsignedCoordinate(bytes: Buffer): Buffer {
+  const trimmed = bytes.subarray(Math.max(0, bytes.length - 32));
+  return Buffer.concat([Buffer.alloc(32 - trimmed.length), trimmed]);
+}
+
+function hkdf(key: Buffer, salt: Buffer | string, info: Buffer | string): Buffer {
+  return Buffer.from(hkdfSync("sha256", key, salt, info, 32));
+}
+
+/** The four digits both sides show; the sender displays them too. */
+export function quickSharePin(authKey: Buffer): string {
+  let hash = 0;
+  let multiplier = 1;
+  for (const byte of authKey) {
+    const signed = byte > 127 ? byte - 256 : byte;
+    hash = (hash + signed * multiplier) % 9973;
+    multiplier = (multiplier * 31) % 9973;
+  }
+  return String(Math.abs(hash)).padStart(4, "0");
+}
+
+/**
+ * Advertises `name` to Quick Share senders on `port`. The instance name
+ * encodes a random endpoint id; the `n` record carries the device type
+ * (a laptop) and name.
+ */
+export function publishQuickShare(name: string, port: number): () => Promise<void> {
+  const alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+  const endpointId = Array.from({ length: 4 }, () => alphabet[randomInt(alphabet.length)]).join("");
+  const instance = Buffer.concat([
+    Buffer.from([0x23]),
+    Buffer.from(endpointId, "ascii"),
+    Buffer.from([0xfc, 0x9f, 0x5e, 0x00, 0x00])
+  ]);
+  const nameBytes = Buffer.from(name, "utf8").subarray(0, 255);
+  const endpointInfo = Buffer.concat([Buffer.from([3 << 1]), randomBytes(16), Buffer.from([nameBytes.length]), nameBytes]);
+  const bonjour = new Bonjour();
+  const service = bonjour.publish({
+    name: base64Url(instance),
+    type: SERVICE_TYPE,
+    protocol: "tcp",
+    port,
+    txt: { n: base64Url(endpointInfo) }
+  });
+  return async () => {
+    await new Promise<void>((resolve) => {
+      const timer = setTimeout(resolve, 1500);
+      service.stop?.(() => {
+        clearTimeout(timer);
+        resolve();
+      });
+    });
+    bonjour.destroy();
+  };
+}
+
+class FrameReader {
+  private buffer = Buffer.alloc(0);
+  private readonly chunks: AsyncIterator<Buffer>;
+
+  constructor(socket: Socket) {
+    this.chunks = socket[Symbol.asyncIterator]();
+  }
+
+  async read(): Promise<Buffer> {
+    while (true) {
+      if (this.buffer.length >= 4) {
+        const length = this.buffer.readUInt32BE(0);
+        if (length > MAX_FRAME_BYTES) {
+          throw new Error("Quick Share frame too large");
+        }
+        if (this.buffer.length >= 4 + length) {
+          const frame = this.buffer.subarray(4, 4 + length);
+          this.buffer = this.buffer.subarray(4 + length);
+          return frame;
+        }
+      }
+      const next = await this.chunks.next();
+      if (next.done) {
+        throw new Error("Quick Share sender closed the connection");
+      }
+      this.buffer = this.buffer.length === 0 ? next.value : Buffer.concat([this.buffer, next.value]);
+    }
+  }
+}
+
+export interface QuickShareFile {
+  payloadId: bigint;
+  name: string;
+  size: number;
+}
+
+export interface QuickShareChunk {
+  payloadId: bigint;
+  offset: number;
+  body: Buffer;
+  last: boolean;
+}
+
+interface SessionKeys {
+  decrypt: Buffer;
+  receiveHmac: Buffer;
+  encrypt: Buffer;
+  sendHmac: Buffer;
+}
+
+/**
+ * One inbound Quick Share connection, from the handshake to the file data.
+ * `accept` completes the handshake; `introduction`, `respond` and
+ * `nextChunk` then follow the sender's side of the share.
+ */
+export class QuickShareSession {
+  private sendSequence = 0;
+  private receiveSequence = 0;
+  private readonly bytePayloads = new Map<bigint, Buffer[]>();
+
+  private constructor(
+    private readonly socket: Socket,
+    private readonly reader: FrameReader,
+    private readonly keys: SessionKeys,
+    readonly senderName: string,
+    readonly pin: string
+  ) {}
+
+  static async accept(socket: Socket): Promise<QuickShareSession> {
+    const reader = new FrameReader(socket);
+
+    const request = messageField(decodeMessage(await reader.read()), 2);
+    if (numberField(request, 1) !== CONNECTION_REQUEST) {
+      throw new Error("expected a Quick Share connection request");
+    }
+    const connection = messageField(request, 2);
+    const endpointInfo = bytesField(connection, 6) ?? Buffer.alloc(0);
+    const nameLength = endpointInfo[17] ?? 0;
+    const announcedName =
+      endpointInfo.length >= 18 + nameLength && nameLength > 0
+        ? endpointInfo.subarray(18, 18 + nameLength).toString("utf8")
+        : (bytesField(connection, 2)?.toString("utf8") ?? "");
+    // Names are for display only; strip anything that could fake a log line.
+    const senderName =
+      announcedName
+        .replace(/[\r\n]+/g, " ")
+        .trim()
+        .slice(0, 64) || "Quick Share";
+
+    const clientInitRaw = await reader.read();
+    const clientInitMessage = decodeMessage(clientInitRaw);
+    if (numberField(clientInitMessage, 1) !== UKEY2_CLIENT_INIT) {
+      throw new Error("expected a UKEY2 client init");
+    }
+    const clientInit = messageField(clientInitMessage, 2);
+    const commitment = (clientInit.get(3) ?? [])
+      .filter(Buffer.isBuffer)
+      .map((raw) => decodeMessage(raw))
+      .find((cipher) => numberField(cipher, 1) === P256_SHA512);
+    const expectedCommitment = commitment ? bytesField(commitment, 2) : undefined;
+    if (numberField(clientInit, 1) !== 1n || !expectedCommitment) {
+      throw new Error("sender does not offer a supported UKEY2 cipher");
+    }
+    if (bytesField(clientInit, 4)?.toString("utf8") !== NEXT_PROTOCOL) {
+      throw new Error("sender does not offer a supported channel cipher");
+    }
+
+    const ecdh = createECDH("prime256v1");
+    const publicKey = ecdh.generateKeys();
+    const serverInitRaw = encodeMessage([
+      [1, UKEY2_SERVER_INIT],
+      [
+        2,
+        encodeMessage([
+          [1, 1],
+          [2, randomBytes(32)],
+          [3, P256_SHA512],
+          [
+            4,
+            encodeMessage([
+              [1, 1],
+              [
+                2,
+                encodeMessage([
+                  [1, signedCoordinate(publicKey.subarray(1, 33))],
+                  [2, signedCoordinate(publicKey.subarray(33, 65))]
+                ])
+              ]
+            ])
+          ]
+        ])
+      ]
+    ]);
+    writeFrame(socket, serverInitRaw);
+
+    const clientFinishRaw = await reader.read();
+    const actualCommitment = createHash("sha512").update(clientFinishRaw).digest();
+    if (actualCommitment.length !== expectedCommitment.length || !timingSafeEqual(actualCommitment, expectedCommitment)) {
+      throw new Error("UKEY2 commitment mismatch");
+    }
+    const clientFinish = decodeMessage(clientFinishRaw);
+    if (numberField(clientFinish, 1) !== UKEY2_CLIENT_FINISH) {
+      throw new Error("expected a UKEY2 client finish");
+    }
+    const senderKey = messageField(messageField(messageField(clientFinish, 2), 1), 2);
+    const x = bytesField(senderKey, 1);
+    const y = bytesField(senderKey, 2);
+    if (!x || !y) {
+      throw new Error("sender sent an unsupported public key");
+    }
+    const shared = ecdh.computeSecret(Buffer.concat([Buffer.from([4]), unsignedCoordinate(x), unsignedCoordinate(y)]));
+
+    const secret = createHash("sha256").update(shared).digest();
+    const ukeyInfo = Buffer.concat([clientInitRaw, serverInitRaw]);
+    const authKey = hkdf(secret, "UKEY2 v1 auth", ukeyInfo);
+    const nextSecret = hkdf(secret, "UKEY2 v1 next", ukeyInfo);
+    const clientKey = hkdf(nextSecret, D2D_SALT, "client");
+    const serverKey = hkdf(nextSecret, D2D_SALT, "server");
+    const keys: SessionKeys = {
+      decrypt: hkdf(clientKey, SECURE_MESSAGE_SALT, "ENC:2"),
+      receiveHmac: hkdf(clientKey, SECURE_MESSAGE_SALT, "SIG:1"),
+      encrypt: hkdf(serverKey, SECURE_MESSAGE_SALT, "ENC:2"),
+      sendHmac: hkdf(serverKey, SECURE_MESSAGE_SALT, "SIG:1")
+    };
+
+    const response = messageField(decodeMessage(await reader.read()), 2);
+    if (numberField(response, 1) !== CONNECTION_RESPONSE || numberField(messageField(response, 3), 3) === 2n) {
+      throw new Error("sender did not accept the connection");
+    }
+    const osType = process.platform === "win32" ? 200 : process.platform === "darwin" ? 300 : 100;
+    writeFrame(
+      socket,
+      v1Frame(
+        CONNECTION_RESPONSE,
+        3,
+        encodeMessage([
+          [1, 0],
+          [3, 1],
+          [4, encodeMessage([[1, osType]])]
+        ])
+      )
+    );
+
+    const session = new QuickShareSession(socket, reader, keys, senderName, quickSharePin(authKey));
+    // Paired keys are only meaningful between signed-in contacts; offer
+    // random ones so the sender carries on with an unknown receiver.
+    session.sendSharing(
+      v1Frame(
+        SHARING_PAIRED_KEY_ENCRYPTION,
+        4,
+        encodeMessage([
+          [1, randomBytes(72)],
+          [2, randomBytes(6)]
+        ])
+      )
+    );
+    return session;
+  }
+
+  /** Files the sender offers; declines shares that carry anything else. */
+  async introduction(): Promise<QuickShareFile[]> {
+    while (true) {
+      const frame = await this.nextSharingFrame();
+      const type = numberField(frame, 1);
+      if (type === SHARING_PAIRED_KEY_ENCRYPTION) {
+        // 3: unable to verify.
+        this.sendSharing(v1Frame(SHARING_PAIRED_KEY_RESULT, 5, encodeMessage([[1, 3]])));
+        continue;
+      }
+      if (type !== SHARING_INTRODUCTION) {
+        continue;
+      }
+      const introduction = messageField(frame, 2);
+      const files = (introduction.get(1) ?? []).filter(Buffer.isBuffer).map((raw): QuickShareFile => {
+        const metadata = decodeMessage(raw);
+        return {
+          payloadId: BigInt.asIntN(64, numberField(metadata, 3) ?? 0n),
+          name: bytesField(metadata, 1)?.toString("utf8") ?? "",
+          size: Number(numberField(metadata, 4) ?? 0n)
+        };
+      });
+      const others = [2, 4].some((field) => (introduction.get(field) ?? []).length > 0);
+      if (files.length === 0 || others) {
+        // 4: unsupported attachment type.
+        await this.respond(false, 4);
+        throw new Error("only files can be received from Quick Share");
+      }
+      return files;
+    }
+  }
+
+  /** Answers the introduction; a rejection ends the share. */
+  async respond(accept: boolean, status = accept ? 1 : 2): Promise<void> {
+    this.sendSharing(v1Frame(SHARING_RESPONSE, 3, encodeMessage([[1, status]])));
+    if (!accept) {
+      await this.close();
+    }
+  }
+
+  /** The next piece of an accepted file's data. */
+  async nextChunk(): Promise<QuickShareChunk> {
+    while (true) {
+      const frame = await this.readOffline();
+      const transfer = messageField(frame, 4);
+      const header = messageField(transfer, 2);
+      const chunk = messageField(transfer, 3);
+      const payloadId = BigInt.asIntN(64, numberField(header, 1) ?? 0n);
+      const last = ((numberField(chunk, 1) ?? 0n) & LAST_CHUNK) !== 0n;
+      const body = bytesField(chunk, 3) ?? Buffer.alloc(0);
+      if (numberField(header, 2) === PAYLOAD_FILE) {
+        return { payloadId, offset: Number(numberField(chunk, 2) ?? 0n), body, last };
+      }
+      const sharing = this.collectBytes(payloadId, body, last);
+      if (sharing && numberField(sharing, 1) === SHARING_CANCEL) {
+        throw new Error("the sender cancelled the share");
+      }
+    }
+  }
+
+  async close(): Promise<void> {
+    if (this.socket.destroyed) {
+      return;
+    }
+    try {
+      this.sendOffline(v1Frame(DISCONNECTION, 7, Buffer.alloc(0)));
+    } finally {
+      this.socket.end();
+    }
+  }
+
+  private sendOffline(frame: Buffer): void {
+    this.sendSequence += 1;
+    const message = encodeMessage([
+      [1, frame],
+      [2, this.sendSequence]
+    ]);
+    const iv = randomBytes(16);
+    const cipher = createCipheriv("aes-256-cbc", this.keys.encrypt, iv);
+    const body = Buffer.concat([cipher.update(message), cipher.final()]);
+    const header = encodeMessage([
+      [1, 1],
+      [2, 2],
+      [5, iv],
+      [6, encodeMessage([[1, 13], [2, 1]])]
+    ]);
+    const headerAndBody = encodeMessage([
+      [1, header],
+      [2, body]
+    ]);
+    const signature = createHmac("sha256", this.keys.sendHmac).update(headerAndBody).digest();
+    writeFrame(
+      this.socket,
+      encodeMessage([
+        [1, headerAndBody],
+        [2, signature]
+      ])
+    );
+  }
+
+  /** Sharing frames travel as byte payloads: the data, then an empty last chunk. */
+  private sendSharing(frame: Buffer): void {
+    const payloadId = BigInt.asIntN(64, randomBytes(8).readBigUInt64BE());
+    const header = encodeMessage([
+      [1, payloadId],
+      [2, PAYLOAD_BYTES],
+      [3, frame.length]
+    ]);
+    for (const [offset, flags, body] of [
+      [0, 0n, frame],
+      [frame.length, LAST_CHUNK, Buffer.alloc(0)]
+    ] as const) {
+      this.sendOffline(
+        v1Frame(
+          PAYLOAD_TRANSFER,
+          4,
+          encodeMessage([
+            [1, 1],
+            [2, header],
+            [3, encodeMessage([[1, flags], [2, offset], [3, body]])]
+          ])
+        )
+      );
+    }
+  }
+
+  /** Decrypts the next frame, answering keep-alives on the way. */
+  private async readOffline(): Promise<ProtoFields> {
+    while (true) {
+      const secure = decodeMessage(await this.reader.read());
+      const headerAndBody = bytesField(secure, 1);
+      const signature = bytesField(secure, 2);
+      if (!headerAndBody || !signature) {
+        throw new Error("malformed Quick Share secure message");
+      }
+      const expected = createHmac("sha256", this.keys.receiveHmac).update(headerAndBody).digest();
+      if (signature.length !== expected.length || !timingSafeEqual(signature, expected)) {
+        throw new Error("Quick Share message signature mismatch");
+      }
+      const parts = decodeMessage(headerAndBody);
+      const iv = bytesField(messageField(parts, 1), 5);
+      if (!iv || iv.length !== 16) {
+        throw new Error("malformed Quick Share secure message");
+      }
+      const decipher = createDecipheriv("aes-256-cbc", this.keys.decrypt, iv);
+      const message = decodeMessage(Buffer.concat([decipher.update(bytesField(parts, 2) ?? Buffer.alloc(0)), decipher.final()]));
+      this.receiveSequence += 1;
+      if (Number(numberField(message, 2) ?? 0n) !== this.receiveSequence) {
+        throw new Error("Quick Share message out of sequence");
+      }
+      const frame = messageField(decodeMessage(bytesField(message, 1) ?? Buffer.alloc(0)), 2);
+      const type = numberField(frame, 1);
+      if (type === KEEP_ALIVE) {
+        this.sendOffline(v1Frame(KEEP_ALIVE, 6, encodeMessage([[1, true]])));
+        continue;
+      }
+      if (type === DISCONNECTION) {
+        throw new Error("the sender disconnected");
+      }
+      if (type === PAYLOAD_TRANSFER) {
+        return frame;
+      }
+    }
+  }
+
+  private async nextSharingFrame(): Promise<ProtoFields> {
+    while (true) {
+      const transfer = messageField(await this.readOffline(), 4);
+      const header = messageField(transfer, 2);
+      if (numberField(header, 2) !== PAYLOAD_BYTES) {
+        continue;
+      }
+      const chunk = messageField(transfer, 3);
+      const sharing = this.collectBytes(
+        BigInt.asIntN(64, numberField(header, 1) ?? 0n),
+        bytesField(chunk, 3) ?? Buffer.alloc(0),
+        ((numberField(chunk, 1) ?? 0n) & LAST_CHUNK) !== 0n
+      );
+      if (sharing && numberField(sharing, 1) === SHARING_CANCEL) {
+        throw new Error("the sender cancelled the share");
+      }
+      if (sharing) {
+        return sharing;
+      }
+    }
+  }
+
+  /** Gathers a byte payload; returns its sharing frame once complete. */
+  private collectBytes(payloadId: bigint, body: Buffer, last: boolean): ProtoFields | null {
+    const parts = this.bytePayloads.get(payloadId) ?? [];
+    parts.push(body);
+    if (!last) {
+      this.bytePayloads.set(payloadId, parts);
+      return null;
+    }
+    this.bytePayloads.delete(payloadId);
+    return messageField(decodeMessage(Buffer.concat(parts)), 2);
+  }
+}
@@ -0,0 +1,151 @@
+import { Socket } from "net";
+
+/**
+ * Caps the combined receive rate of every connection of a listener. A
+ * socket that overdraws the budget is paused until it has refilled, which
+ * backs the sender off through TCP flow control.
+ */
+export class ReceiveRateLimiter {
+  private bytesPerSecond = 0;
+  private available = 0;
+  private refilledAt = Date.now();
+
+  constructor(bytesPerSecond = 0) {
+    this.setLimit(bytesPerSecond);
+  }
+
+  /** 0 removes the limit; connections already paused resume on schedule. */
+  setLimit(bytesPerSecond: number): void {
+    this.bytesPerSecond = Math.max(0, Math.floor(bytesPerSecond));
+    this.available = this.bytesPerSecond;
+    this.refilledAt = Date.now();
+  }
+
+  get limit(): number {
+    return this.bytesPerSecond;
+  }
+
+  attach(socket: Socket): void {
+    socket.on("data", (chunk: Buffer) => {
+      const delayMs = this.take(chunk.length);
+      if (delayMs <= 0 || socket.isPaused()) {
+        return;
+      }
+      socket.pause();
+      setTimeout(() => {
+        if (!socket.destroyed) {
+          socket.resume();
+        }
+      }, delayMs);
+    });
+  }
+
+  /** Milliseconds until the budget is back above zero after taking `bytes`. */
+  private take(bytes: number): number {
+    if (this.bytesPerSecond <= 0) {
+      return 0;
+    }
+    const now = Date.now();
+    const refill = ((now - this.refilledAt) / 1000) * this.bytesPerSecond;
+    this.available = Math.min(this.bytesPerSecond, this.available + refill) - bytes;
+    this.refilledAt = now;
+    return this.available >= 0 ? 0 : Math.ceil((-this.available / this.bytesPerSecond) * 1000);
+  }
+}
+
+export interface PeerGuardOptions {
+  /** Connections one address may hold open at once. */
+  maxConnections?: number;
+  /** Pair code mismatches an address may make before it is locked out. */
+  maxPairCodeFailures?: number;
+  /** First lockout; each further one doubles, up to `maxLockoutMs`. */
+  lockoutMs?: number;
+  maxLockoutMs?: number;
+}
+
+interface PairCodeFailures {
+  count: number;
+  lockouts: number;
+  lockedUntil: number;
+}
+
+/**
+ * Per-address limits of a listener: open connections, and pair code
+ * guesses. Every failed pair code counts; past the limit the address is
+ * locked out, for twice as long on each repeat, and its connections are
+ * reset on arrival.
+ */
+export class PeerGuard {
+  private readonly connections = new Map<string, number>();
+  private readonly failures = new Map<string, PairCodeFailures>();
+  private readonly maxConnections: number;
+  private readonly maxPairCodeFailures: number;
+  private readonly lockoutMs: number;
+  private readonly maxLockoutMs: number;
+
+  constructor(options: PeerGuardOptions = {}) {
+    this.maxConnections = options.maxConnections ?? 128;
+    this.maxPairCodeFailures = options.maxPairCodeFailures ?? 5;
+    this.lockoutMs = options.lockoutMs ?? 30_000;
+    this.maxLockoutMs = options.maxLockoutMs ?? 15 * 60_000;
+  }
+
+  /** Counts a new connection from `address`; false when it must be refused. */
+  admit(address: string, now = Date.now()): boolean {
+    if (this.isLockedOut(address, now)) {
+      return false;
+    }
+    const open = this.connections.get(address) ?? 0;
+    if (open >= this.maxConnections) {
+      return false;
+    }
+    this.connections.set(address, open + 1);
+    return true;
+  }
+
+  /** Pairs with a successful `admit` once the connection closes. */
+  release(address: string): void {
+    const open = (this.connections.get(address) ?? 0) - 1;
+    if (open > 0) {
+      this.connections.set(address, open);
+    } else {
+      this.connections.delete(address);
+    }
+  }
+
+  isLockedOut(address: string, now = Date.now()): boolean {
+    return (this.failures.get(address)?.lockedUntil ?? 0) > now;
+  }
+
+  /** Returns the lockout this failure started in milliseconds, or 0. */
+  pairCodeFailed(address: string, now = Date.now()): number {
+    this.prune(now);
+    const failures = this.failures.get(address) ?? { count: 0, lockouts: 0, lockedUntil: 0 };
+    this.failures.set(address, failures);
+    failures.count += 1;
+    if (failures.count < this.maxPairCodeFailures) {
+      return 0;
+    }
+    const lockoutMs = Math.min(this.maxLockoutMs, this.lockoutMs * 2 ** failures.lockouts);
+    failures.count = 0;
+    failures.lockouts += 1;
+    failures.lockedUntil = now + lockoutMs;
+    return lockoutMs;
+  }
+
+  pairCodeAccepted(address: string): void {
+    this.failures.delete(address);
+  }
+
+  /** Forgets addresses that are neither locked out nor recently failing. */
+  private prune(now: number): void {
+    if (this.failures.size < 4096) {
+      return;
+    }
+    for (const [address, failures] of this.failures) {
+      if (failures.lockedUntil + this.maxLockoutMs <= now) {
+        this.failures.delete(address);
+      }
+    }
+  }
+}
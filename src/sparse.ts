@@ -0,0 +1,122 @@
+import { createHash } from "crypto";
+import { promises as fsPromises, Stats } from "fs";
+
+/** Holes are detected at this granularity; smaller zero runs are sent. */
+export const SPARSE_BLOCK_BYTES = 64 * 1024;
+/** Keeps the extent list well inside the header size limit. */
+export const MAX_SPARSE_EXTENTS = 1024;
+const SPARSE_MIN_BYTES = 16 * 1024 * 1024;
+const READ_BUFFER_BYTES = 4 * 1024 * 1024;
+const ZERO_BLOCK = Buffer.alloc(SPARSE_BLOCK_BYTES);
+
+export interface SparseExtent {
+  start: number;
+  length: number;
+}
+
+export interface SparseScan {
+  sha256: string;
+  extents: SparseExtent[];
+  dataBytes: number;
+}
+
+/**
+ * Files that occupy much less disk than their apparent size, e.g. VM disk
+ * images. Platforms that do not report allocated blocks never qualify.
+ */
+export function looksSparse(stat: Stats): boolean {
+  return stat.size >= SPARSE_MIN_BYTES && stat.blocks > 0 && stat.blocks * 512 < stat.size * 0.75;
+}
+
+/**
+ * Hashes the file and records which blocks hold data, in one pass. Node has
+ * no `SEEK_DATA`, so holes are found by content; the filesystem fills
+ * them with zeros without any disk I/O.
+ */
+export async function scanSparseFile(filePath: string, fileSize: number): Promise<SparseScan> {
+  const hash = createHash("sha256");
+  const extents: SparseExtent[] = [];
+  const handle = await fsPromises.open(filePath, "r");
+  try {
+    const buffer = Buffer.allocUnsafe(READ_BUFFER_BYTES);
+    let position = 0;
+    while (position < fileSize) {
+      const { bytesRead } = await handle.read(buffer, 0, Math.min(buffer.length, fileSize - position), position);
+      if (bytesRead === 0) {
+        throw new Error("file shrank while scanning");
+      }
+      const data = buffer.subarray(0, bytesRead);
+      hash.update(data);
+      for (let offset = 0; offset < bytesRead; offset += SPARSE_BLOCK_BYTES) {
+        const block = data.subarray(offset, Math.min(offset + SPARSE_BLOCK_BYTES, bytesRead));
+        if (block.equals(ZERO_BLOCK.subarray(0, block.length))) {
+          continue;
+        }
+        const start = position + offset;
+        const last = extents[extents.length - 1];
+        if (last && last.start + last.length === start) {
+          last.length += block.length;
+        } else {
+          extents.push({ start, length: block.length });
+        }
+      }
+      position += bytesRead;
+    }
+  } finally {
+    await handle.close();
+  }
+
+  const merged = coalesceExtents(extents, MAX_SPARSE_EXTENTS);
+  return {
+    sha256: hash.digest("hex"),
+    extents: merged,
+    dataBytes: merged.reduce((sum, extent) => sum + extent.length, 0)
+  };
+}
+
+/** Fills the smallest holes until at most `max` extents remain. */
+function coalesceExtents(extents: SparseExtent[], max: number): SparseExtent[] {
+  let result = extents;
+  for (let minGap = SPARSE_BLOCK_BYTES * 2; result.length > max; minGap *= 2) {
+    const next: SparseExtent[] = [];
+    for (const extent of result) {
+      const last = next[next.length - 1];
+      if (last && extent.start - (last.start + last.length) < minGap) {
+        last.length = extent.start + extent.length - last.start;
+      } else {
+        next.push({ ...extent });
+      }
+    }
+    result = next;
+  }
+  return result;
+}
+
+/** Extents travel as flat `[start, length, start, length, ...]` pairs. */
+export function encodeExtents(extents: SparseExtent[]): number[] {
+  return extents.flatMap((extent) => [extent.start, extent.length]);
+}
+
+/**
+ * Returns the extents if they are sorted, non-overlapping, non-empty and
+ * inside the file, otherwise null.
+ */
+export function decodeExtents(flat: unknown, fileSize: number): SparseExtent[] | null {
+  if (!Array.isArray(flat) || flat.length % 2 !== 0 || flat.length / 2 > MAX_SPARSE_EXTENTS) {
+    return null;
+  }
+  const extents: SparseExtent[] = [];
+  let end = 0;
+  for (let index = 0; index < flat.length; index += 2) {
+    const [start, length] = [flat[index], flat[index + 1]];
+    if (!Number.isSafeInteger(start) || !Number.isSafeInteger(length) || length <= 0 || start < end) {
+      return null;
+    }
+    end = start + length;
+    if (end > fileSize) {
+      return null;
+    }
+    extents.push({ start, length });
+  }
+  return extents;
+}
@@ -0,0 +1,211 @@
+import { createSocket, Socket as DgramSocket } from "dgram";
+import { networkInterfaces, platform, release } from "os";
+import { CLI_VERSION } from "./constants";
+
+/**
+ * SSDP (the discovery half of UPnP) as a fallback for networks that filter
+ * mDNS but pass SSDP, as some consumer routers do. Listeners answer
+ * M-SEARCH requests for our search target and announce themselves with
+ * NOTIFY; what mDNS carries in TXT records travels in `X-LOCALSENT-*`
+ * headers, keyed the same way.
+ */
+export const SSDP_ADDRESS = "239.255.255.250";
+export const SSDP_PORT = 1900;
+export const SSDP_SEARCH_TARGET = "urn:local-sent:service:localsent:1";
+
+const MAX_AGE_SECONDS = 1800;
+// Re-announce well within max-age so caches never let us lapse.
+const NOTIFY_INTERVAL_MS = (MAX_AGE_SECONDS / 3) * 1000;
+// Cap on the random delay before answering, whatever MX a searcher asks for.
+const MAX_RESPONSE_DELAY_SECONDS = 3;
+const HEADER_PREFIX = "x-localsent-";
+
+export interface SsdpMessage {
+  // `M-SEARCH * HTTP/1.1`, `NOTIFY * HTTP/1.1` or `HTTP/1.1 200 OK`.
+  startLine: string;
+  // Lower-cased names; the first of repeated headers wins.
+  headers: Record<string, string>;
+}
+
+export interface SsdpAnnouncement {
+  name: string;
+  port: number;
+  // TXT-style fields (`id`, `fp`, `v`, `pv`, `tls`, `pair`).
+  txt: Record<string, string>;
+}
+
+export function parseSsdpMessage(raw: Buffer): SsdpMessage | null {
+  const lines = raw.toString("utf8").split(/\r?\n/);
+  const startLine = lines.shift()?.trim();
+  if (!startLine) {
+    return null;
+  }
+  const headers: Record<string, string> = {};
+  for (const line of lines) {
+    const colon = line.indexOf(":");
+    if (colon <= 0) {
+      continue;
+    }
+    const name = line.slice(0, colon).trim().toLowerCase();
+    if (!(name in headers)) {
+      headers[name] = line.slice(colon + 1).trim();
+    }
+  }
+  return { startLine, headers };
+}
+
+function formatMessage(startLine: string, headers: Array<[string, string]>): Buffer {
+  return Buffer.from([startLine, ...headers.map(([name, value]) => `${name}: ${value}`), "", ""].join("\r\n"), "utf8");
+}
+
+export function ssdpSearchRequest(mxSeconds: number): Buffer {
+  return formatMessage("M-SEARCH * HTTP/1.1", [
+    ["HOST", `${SSDP_ADDRESS}:${SSDP_PORT}`],
+    ["MAN", '"ssdp:discover"'],
+    ["MX", String(mxSeconds)],
+    ["ST", SSDP_SEARCH_TARGET],
+    ["USER-AGENT", serverHeader()]
+  ]);
+}
+
+/** The announced name, port and fields of an SSDP response or NOTIFY for our service. */
+export function announcementFromSsdp(message: SsdpMessage): SsdpAnnouncement | null {
+  const target = message.headers.st ?? message.headers.nt;
+  if (target !== SSDP_SEARCH_TARGET || message.headers.nts === "ssdp:byebye") {
+    return null;
+  }
+  const port = Number.parseInt(message.headers[`${HEADER_PREFIX}port`] ?? "", 10);
+  let name: string;
+  try {
+    name = decodeURIComponent(message.headers[`${HEADER_PREFIX}name`] ?? "").trim();
+  } catch {
+    return null;
+  }
+  if (!name || !Number.isInteger(port) || port <= 0 || port > 65535) {
+    return null;
+  }
+  const txt: Record<string, string> = {};
+  for (const [header, value] of Object.entries(message.headers)) {
+    const key = header.startsWith(HEADER_PREFIX) ? header.slice(HEADER_PREFIX.length) : "";
+    if (key && key !== "name" && key !== "port") {
+      txt[key] = value;
+    }
+  }
+  return { name, port, txt };
+}
+
+function serverHeader(): string {
+  return `${platform()}/${release()} UPnP/1.1 LocalSent/${CLI_VERSION}`;
+}
+
+function announcementHeaders(announcement: SsdpAnnouncement): Array<[string, string]> {
+  const id = announcement.txt.id || announcement.name;
+  return [
+    ["CACHE-CONTROL", `max-age=${MAX_AGE_SECONDS}`],
+    ["SERVER", serverHeader()],
+    ["USN", `uuid:${encodeURIComponent(id)}::${SSDP_SEARCH_TARGET}`],
+    ["X-LOCALSENT-NAME", encodeURIComponent(announcement.name)],
+    ["X-LOCALSENT-PORT", String(announcement.port)],
+    ...Object.entries(announcement.txt).map(([key, value]): [string, string] => [
+      `X-LOCALSENT-${key.toUpperCase()}`,
+      value
+    ])
+  ];
+}
+
+function notify(socket: DgramSocket, announcement: SsdpAnnouncement, nts: "ssdp:alive" | "ssdp:byebye"): Promise<void> {
+  const message = formatMessage("NOTIFY * HTTP/1.1", [
+    ["HOST", `${SSDP_ADDRESS}:${SSDP_PORT}`],
+    ["NT", SSDP_SEARCH_TARGET],
+    ["NTS", nts],
+    ...announcementHeaders(announcement)
+  ]);
+  return new Promise((resolve) => {
+    try {
+      socket.send(message, SSDP_PORT, SSDP_ADDRESS, () => resolve());
+    } catch {
+      resolve();
+    }
+  });
+}
+
+/**
+ * Answers searches for our service and announces it until the returned
+ * function is called, which says goodbye first. Binding SSDP's port can
+ * fail where another stack holds it exclusively; the listener then just
+ * is not found this way.
+ */
+export function startSsdpResponder(announcement: SsdpAnnouncement): () => Promise<void> {
+  const socket = createSocket({ type: "udp4", reuseAddr: true });
+  const pending = new Set<NodeJS.Timeout>();
+  let bound = false;
+  socket.on("error", () => {
+    // Keep running even if SSDP fails.
+  });
+
+  socket.on("message", (raw, rinfo) => {
+    const message = parseSsdpMessage(raw);
+    if (!message?.startLine.toUpperCase().startsWith("M-SEARCH")) {
+      return;
+    }
+    const target = message.headers.st;
+    if (target !== SSDP_SEARCH_TARGET && target !== "ssdp:all") {
+      return;
+    }
+    const mx = Number.parseInt(message.headers.mx ?? "1", 10);
+    const delayMs = Math.random() * Math.min(Number.isFinite(mx) && mx > 0 ? mx : 1, MAX_RESPONSE_DELAY_SECONDS) * 1000;
+    const reply = formatMessage("HTTP/1.1 200 OK", [
+      ["EXT", ""],
+      ["ST", SSDP_SEARCH_TARGET],
+      ...announcementHeaders(announcement)
+    ]);
+    const timer = setTimeout(() => {
+      pending.delete(timer);
+      try {
+        socket.send(reply, rinfo.port, rinfo.address);
+      } catch {
+        // Ignore response send failures.
+      }
+    }, delayMs);
+    pending.add(timer);
+  });
+
+  let notifyTimer: NodeJS.Timeout | null = null;
+  socket.bind(SSDP_PORT, () => {
+    bound = true;
+    for (const list of Object.values(networkInterfaces())) {
+      for (const item of list ?? []) {
+        if (item.family !== "IPv4" || item.internal) {
+          continue;
+        }
+        try {
+          socket.addMembership(SSDP_ADDRESS, item.address);
+        } catch {
+          // Already joined through another address, or no multicast here.
+        }
+      }
+    }
+    void notify(socket, announcement, "ssdp:alive");
+    notifyTimer = setInterval(() => void notify(socket, announcement, "ssdp:alive"), NOTIFY_INTERVAL_MS);
+    notifyTimer.unref();
+  });
+
+  return async () => {
+    if (notifyTimer) {
+      clearInterval(notifyTimer);
+    }
+    for (const timer of pending) {
+      clearTimeout(timer);
+    }
+    if (bound) {
+      await notify(socket, announcement, "ssdp:byebye");
+    }
+    await new Promise<void>((resolve) => {
+      try {
+        socket.close(() => resolve());
+      } catch {
+        resolve();
+      }
+    });
+  };
+}
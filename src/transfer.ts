@@ -1,27 +1,77 @@
-import { createHash } from "crypto";
+import { createHash, createPrivateKey, randomUUID, X509Certificate } from "crypto";
 import { createReadStream, createWriteStream, promises as fsPromises, readFileSync, WriteStream } from "fs";
-import { createConnection, createServer, Server, Socket } from "net";
-import { basename, dirname, extname, resolve } from "path";
+import { FileHandle } from "fs/promises";
+import { Readable } from "stream";
+import { AddressInfo, createConnection, createServer, Server, Socket } from "net";
+import { basename, dirname, extname, isAbsolute, join, relative, resolve, sep } from "path";
 import { once } from "events";
-import { finished } from "stream/promises";
+import { finished, pipeline } from "stream/promises";
 import { connect as tlsConnect, createServer as createTlsServer, Server as TlsServer, TLSSocket } from "tls";
-import { HEADER_MAX_BYTES } from "./constants";
-import { publishService } from "./discovery";
-import { AckMessage, decodeJsonLine, encodeJsonLine, ReadyMessage, TransferHeader } from "./protocol";
-import { verifyTlsPeer } from "./tlsTrust";
+import { CAPABILITY_KNOCK, HEADER_MAX_BYTES } from "./constants";
+import { ContentIndex } from "./contentIndex";
+import { DnsSdListenOptions, publishService, ServiceIdentity } from "./discovery";
+import { KnockGate } from "./knock";
+import { PingSigner } from "./ping";
+import { emitIpc, ipcEnabled } from "./ipc";
 import {
+  AckMessage,
+  BatchControl,
+  ClipboardMessage,
+  decodeJsonLine,
+  encodeJsonLine,
+  EntryLink,
+  EntryMetadata,
+  MAX_CLIPBOARD_BYTES,
+  MAX_MESSAGE_CHARS,
+  MAX_NOTE_CHARS,
+  MAX_NOTE_TAGS,
+  MAX_TAG_CHARS,
+  ParallelRange,
+  ProbeMessage,
+  ReadyMessage,
+  ShareEntry,
+  ShareReply,
+  ShareRequest,
+  STREAM_FILE_SIZE,
+  TextMessage,
+  TransferHeader,
+  TransferNote
+} from "./protocol";
+import { ProgressAnalytics, STALL_AFTER_MS, ThroughputRegime } from "./progressAnalytics";
+import { publishQuickShare, QuickShareSession } from "./quickShare";
+import { PeerGuard, ReceiveRateLimiter } from "./rateLimit";
+import { decodeExtents, encodeExtents, looksSparse, scanSparseFile, SparseScan } from "./sparse";
+import { normalizeFingerprint, verifyTlsPeer } from "./tlsTrust";
+import { renderTuning, TransferTuner, TransferTuning } from "./tuning";
+import {
+  formatBytes,
   normalizeTransferPath,
   renderProgress,
+  renderStreamProgress,
+  renderStreamStats,
   resolveOutputPath,
   sha256File,
   TransferEntry,
   updateHashFromFilePrefix
 } from "./utils";
+import {
+  deleteWebDavResource,
+  ensureWebDavFolders,
+  WebDavTarget,
+  WebDavUpload,
+  webDavResourceUrl
+} from "./webdav";
+import { stripLongPathPrefix } from "./winPaths";
+import { writeXattrs } from "./xattrs";
 
 export interface TransferConfirmRequest {
   from: string;
   relativePath: string;
   fileSize: number;
+  sha256: string;
+  streaming: boolean;
+  // Set for Quick Share senders, which are never paired.
+  quickShare?: boolean;
 }
 
 export interface TransferConfirmDecision {
@@ -31,12 +81,28 @@ export interface TransferConfirmDecision {
 
 export interface ListenOptions {
   port: number;
+  // Local address to accept connections on; every interface when unset.
+  host?: string;
   outputDir: string;
   serviceName: string;
+  // Announced with the service so peers can merge sightings of this
+  // listener on several interfaces; a fresh id per run when unset.
+  deviceId?: string;
+  // Announced so peers can show this device with its icon and avatar.
+  deviceType?: string;
+  avatarColor?: string;
+  avatarEmoji?: string;
   pairCode?: string;
   rotatePairCodePerTransfer?: boolean;
   pairCodeTtlSeconds?: number;
   generatePairCode?: () => string;
+  keepDuplicates?: boolean;
+  // Apply permission bits and mtimes sent with each entry where possible.
+  preserveMetadata?: boolean;
+  // Restore extended attributes sent from a machine on the same platform.
+  preserveXattrs?: boolean;
+  // Combined cap for all incoming connections; 0 or unset is unlimited.
+  maxReceiveBytesPerSecond?: number;
   onPairCodeChange?: (nextCode: string | null, reason: "once" | "ttl") => void;
   confirmTransfer?: (
     request: TransferConfirmRequest
@@ -45,8 +111,74 @@ export interface ListenOptions {
     certPath: string;
     keyPath: string;
   };
+  /**
+   * Folders peers may browse and pull from, keyed by the name they see.
+   * Nothing is served unless a folder is listed here.
+   */
+  shares?: Record<string, string>;
+  // Peer addresses allowed to see a share; shares not listed are open to
+  // every peer that passes pairing.
+  shareAllow?: Record<string, string[]>;
+  // Peer addresses whose clipboard text is accepted; none by default.
+  clipboardFrom?: string[];
+  // Upload received files here instead of `outputDir`. Only single-stream
+  // file transfers are accepted; senders fall back to them.
+  webdav?: WebDavTarget;
+  // Also receive from Google Quick Share senders (experimental) on a port
+  // of its own, announced over mDNS like the main service.
+  quickShare?: boolean;
+  // Also publish the service to a unicast DNS zone (wide-area DNS-SD) for
+  // senders on networks that filter multicast.
+  dnsSd?: DnsSdListenOptions;
+  // Answer "ping all listeners" broadcasts (see ping.ts); replies are
+  // signed with the TLS key when TLS is on.
+  answerPings?: boolean;
+  // Keep the port closed except to peers that knocked with `secret`
+  // (see knock.ts).
+  stealth?: { secret: string };
+  // Start without advertising the service; `update({ announce: true })`
+  // shows it.
+  hidden?: boolean;
+}
+
+/** Settings a running receiver can change without dropping connections. */
+export interface ReceiverConfigUpdate {
+  outputDir?: string;
+  // `null` turns pairing off.
+  pairCode?: string | null;
+  maxReceiveBytesPerSecond?: number;
+  // Stop or resume advertising the service for discovery.
+  announce?: boolean;
+  // Replace the shared folders and their allow lists.
+  shares?: Record<string, string>;
+  shareAllow?: Record<string, string[]>;
+  // Replace the peers clipboard text is accepted from; empty turns it off.
+  clipboardFrom?: string[];
+}
+
+/** A receive in progress, as reported by `ReceiverHandle.transfers`. */
+export interface ActiveTransfer {
+  path: string;
+  from: string;
+  bytes: number;
+  // Null while streaming content of unknown length.
+  total: number | null;
+  startedAt: number;
+  bytesPerSecond: number;
+  etaSeconds: number | null;
+  stalled: boolean;
+  regime: ThroughputRegime;
 }
 
+/**
+ * Stops the receiver when called. `update` applies to connections accepted
+ * afterwards; transfers already running finish with the old settings.
+ */
+export type ReceiverHandle = (() => Promise<void>) & {
+  update(changes: ReceiverConfigUpdate): Promise<void>;
+  transfers(): ActiveTransfer[];
+};
+
 export interface SendFileOptions {
   filePath: string;
   relativePath: string;
@@ -61,6 +193,80 @@ export interface SendFileOptions {
     trustOnFirstUse?: boolean;
     knownHostsPath?: string;
   };
+  /**
+   * Connections to split the file across. `"auto"` only splits files of at
+   * least `PARALLEL_AUTO_MIN_BYTES` and picks the count from the round trip
+   * time of the first connection; a number always splits (capped by size).
+   */
+  streams?: number | "auto";
+  metadata?: EntryMetadata;
+  // Atomic batch opened by `sendEntries`; the receiver stages the file.
+  batchId?: string;
+  // Where the file sits in a multi-file `sendEntries`, for nested progress.
+  position?: BatchPosition;
+  note?: TransferNote;
+}
+
+export interface BatchPosition {
+  // 1-based, like the `[send] 2/5` line.
+  index: number;
+  count: number;
+  // Sizes of the files before this one, of this one, and of all of them.
+  doneBytes: number;
+  fileBytes: number;
+  totalBytes: number;
+}
+
+type ClientConnectOptions = Pick<SendFileOptions, "host" | "port" | "tls">;
+
+export interface SendLinkOptions extends ClientConnectOptions {
+  relativePath: string;
+  link: EntryLink;
+  pairCode?: string;
+  metadata?: EntryMetadata;
+  batchId?: string;
+  note?: TransferNote;
+}
+
+export interface SendStreamOptions extends ClientConnectOptions {
+  input: Readable;
+  relativePath: string;
+  pairCode?: string;
+  note?: TransferNote;
+}
+
+export interface ShareClientOptions extends ClientConnectOptions {
+  pairCode?: string;
+}
+
+export interface SendMessageOptions extends ClientConnectOptions {
+  text: string;
+  from?: string;
+  pairCode?: string;
+}
+
+export interface SendClipboardOptions extends ClientConnectOptions {
+  text: string;
+  pairCode?: string;
+}
+
+export interface ProbeReceiverOptions extends ClientConnectOptions {
+  files: number;
+  totalBytes: number;
+  pairCode?: string;
+}
+
+export interface PullFileOptions extends ShareClientOptions {
+  // `<share>/<relative path>` on the remote device.
+  remotePath: string;
+  // A file path, or an existing folder to save into under the remote name.
+  destination: string;
+}
+
+export interface PullFileResult {
+  savedPath: string;
+  bytes: number;
+  sha256: string;
 }
 
 export interface SendBatchOptions {
@@ -76,6 +282,14 @@ export interface SendBatchOptions {
     trustOnFirstUse?: boolean;
     knownHostsPath?: string;
   };
+  streams?: number | "auto";
+  /**
+   * All or nothing: the receiver stages every entry and only moves them into
+   * place once all verified. Any failure rolls the whole batch back.
+   */
+  atomic?: boolean;
+  // Attached to every entry.
+  note?: TransferNote;
 }
 
 export interface SendBatchResult {
@@ -96,11 +310,23 @@ interface PairingState {
 }
 
 interface ProgressEmitState {
+  direction: "send" | "recv";
+  path: string;
   prefix: string;
+  // Negative while streaming content of unknown length.
   totalBytes: number;
   startedAt: number;
   lastEmitAt: number;
   lastPercent: number;
+  // Latest figure passed to `emitProgress`, throttled or not.
+  bytes: number;
+  updatedAt: number;
+  from?: string;
+  // Appended to each progress line, e.g. per-connection throughput.
+  detail?: () => string;
+  analytics: ProgressAnalytics;
+  stallTimer?: NodeJS.Timeout;
+  batch?: BatchPosition;
 }
 
 interface ReceivePathSelection {
@@ -108,6 +334,63 @@ interface ReceivePathSelection {
   tempPath: string;
 }
 
+interface ParallelSessionSetup {
+  handle: FileHandle | null;
+  finalPath: string;
+  tempPath: string;
+  alreadyHavePath: string | null;
+  requiredPairCode: string | null;
+}
+
+interface StreamCounter {
+  bytes: number;
+  startedAt: number;
+}
+
+/** All connections of one parallel (version 3) transfer on the receiver. */
+interface ParallelSession {
+  header: TransferHeader;
+  remoteAddress: string;
+  setup: Promise<ParallelSessionSetup>;
+  result: Promise<AckMessage>;
+  settle: (outcome: AckMessage | Error) => void;
+  settled: boolean;
+  failure: string | null;
+  arrived: Set<number>;
+  completed: Set<number>;
+  received: number;
+  counters: Map<number, StreamCounter>;
+  progressState: ProgressEmitState;
+}
+
+/** An atomic batch on the receiver; its entries wait in `stagingDir`. */
+interface ReceiveBatch {
+  id: string;
+  stagingDir: string;
+  // Address of the peer that opened the batch.
+  from: string;
+  note?: TransferNote;
+  // Rolls the batch back when the sender goes quiet.
+  timer?: NodeJS.Timeout;
+}
+
+interface ReceiverContext {
+  outputDir: string;
+  pairingState: PairingState;
+  contentIndex: ContentIndex;
+  listenOptions: ListenOptions;
+  parallelSessions: Map<string, ParallelSession>;
+  batches: Map<string, ReceiveBatch>;
+  peerGuard: PeerGuard;
+  // Progress of every receive that has started writing data.
+  activeReceives: Set<ProgressEmitState>;
+  // Set for entries of an atomic batch; `outputDir` is then its staging folder.
+  batch?: ReceiveBatch;
+  shares: Record<string, string>;
+  shareAllow: Record<string, string[]>;
+  clipboardFrom: string[];
+}
+
 class SocketReader {
   private buffer = Buffer.alloc(0);
   private ended = false;
@@ -215,16 +498,63 @@ class SocketReader {
   }
 }
 
-export async function startReceiver(options: ListenOptions): Promise<() => Promise<void>> {
+/** Peer addresses can be spoofed on the LAN; `shareAllow` alone proves nothing. */
+const SHARE_ALLOW_NEEDS_PAIR_CODE = "shares restricted to addresses need a pair code as well";
+
+export async function startReceiver(options: ListenOptions): Promise<ReceiverHandle> {
+  if (options.quickShare && options.webdav) {
+    throw new Error("Quick Share cannot be received into WebDAV");
+  }
+  if (options.quickShare && options.stealth) {
+    throw new Error("Quick Share cannot be combined with stealth listening");
+  }
+  if (Object.keys(options.shareAllow ?? {}).length > 0 && !options.pairCode) {
+    throw new Error(SHARE_ALLOW_NEEDS_PAIR_CODE);
+  }
   await fsPromises.mkdir(options.outputDir, { recursive: true });
-  const stopPublish = publishService(options.serviceName, options.port);
+  const identity: ServiceIdentity = {
+    id: options.deviceId ?? randomUUID(),
+    fingerprint: options.tls
+      ? normalizeFingerprint(new X509Certificate(readFileSync(options.tls.certPath)).fingerprint256)
+      : undefined,
+    tls: Boolean(options.tls),
+    capabilities: options.stealth ? [CAPABILITY_KNOCK] : undefined,
+    deviceType: options.deviceType,
+    avatarColor: options.avatarColor,
+    avatarEmoji: options.avatarEmoji
+  };
+  const pingSigner: PingSigner | undefined =
+    options.answerPings && options.tls
+      ? {
+          cert: new X509Certificate(readFileSync(options.tls.certPath)).raw,
+          key: createPrivateKey(readFileSync(options.tls.keyPath))
+        }
+      : undefined;
   const activeSockets = new Set<Socket>();
+  const contentIndex = new ContentIndex(options.outputDir);
+  const parallelSessions = new Map<string, ParallelSession>();
+  const batches = new Map<string, ReceiveBatch>();
+  const rateLimiter = new ReceiveRateLimiter(options.maxReceiveBytesPerSecond ?? 0);
+  const peerGuard = new PeerGuard();
   const pairingState: PairingState = {
     currentCode: options.pairCode ?? null,
     previousCode: null,
     previousCodeValidUntilMs: 0,
     activeTransfers: 0
   };
+  const receiverContext: ReceiverContext = {
+    outputDir: options.outputDir,
+    pairingState,
+    contentIndex,
+    listenOptions: options,
+    parallelSessions,
+    batches,
+    peerGuard,
+    activeReceives: new Set(),
+    shares: options.shares ?? {},
+    shareAllow: options.shareAllow ?? {},
+    clipboardFrom: options.clipboardFrom ?? []
+  };
 
   const needsGenerator = options.rotatePairCodePerTransfer || Boolean(options.pairCodeTtlSeconds);
   if (needsGenerator && !options.generatePairCode) {
@@ -236,6 +566,17 @@ export async function startReceiver(options: ListenOptions): Promise<() => Promi
   if ((options.rotatePairCodePerTransfer || options.pairCodeTtlSeconds) && !pairingState.currentCode && options.generatePairCode) {
     pairingState.currentCode = options.generatePairCode();
   }
+  const publish = (): (() => Promise<void>) =>
+    publishService(
+      options.serviceName,
+      options.port,
+      {
+        ...identity,
+        pairCodeRequired: Boolean(pairingState.currentCode)
+      },
+      { dnsSd: options.dnsSd, answerPings: options.answerPings, pingSigner }
+    );
+  let stopPublish: (() => Promise<void>) | null = options.hidden ? null : publish();
 
   const ttlMs = options.pairCodeTtlSeconds ? options.pairCodeTtlSeconds * 1000 : null;
   const rotatePairCode = (reason: "once" | "ttl"): string | undefined => {
@@ -271,6 +612,27 @@ export async function startReceiver(options: ListenOptions): Promise<() => Promi
         }, ttlMs)
       : null;
 
+  let knockGate: KnockGate | null = null;
+  // Resets connections from locked-out or flooding peers, and from peers
+  // that have not knocked, before any TLS handshake or byte of the protocol.
+  const gateConnection = (socket: Socket): void => {
+    const peer = normalizeRemoteAddress(socket.remoteAddress);
+    if (!peerGuard.admit(peer)) {
+      socket.resetAndDestroy();
+      return;
+    }
+    socket.once("close", () => peerGuard.release(peer));
+    if (!knockGate) {
+      return;
+    }
+    const address = socket.remoteAddress;
+    if (!knockGate.admit(address)) {
+      socket.resetAndDestroy();
+      return;
+    }
+    socket.once("close", () => knockGate?.release(address));
+  };
+
   const server = options.tls
     ? createTlsServer(
         {
@@ -284,23 +646,123 @@ export async function startReceiver(options: ListenOptions): Promise<() => Promi
           socket.once("close", () => {
             activeSockets.delete(socket);
           });
-          void handleIncomingSocket(socket, options.outputDir, pairingState, options);
+          rateLimiter.attach(socket);
+          void handleIncomingSocket(socket, receiverContext);
         }
       )
     : createServer({ allowHalfOpen: true }, (socket) => {
+        if (socket.destroyed) {
+          return;
+        }
         activeSockets.add(socket);
         socket.once("close", () => {
           activeSockets.delete(socket);
         });
-        void handleIncomingSocket(socket, options.outputDir, pairingState, options);
+        rateLimiter.attach(socket);
+        void handleIncomingSocket(socket, receiverContext);
       });
+  server.prependListener("connection", gateConnection);
 
   await new Promise<void>((resolve, reject) => {
     server.once("error", reject);
-    server.listen(options.port, () => resolve());
+    server.listen(options.port, options.host, () => resolve());
   });
+  if (options.stealth) {
+    const listenPort = (server.address() as AddressInfo).port;
+    await closeServer(server);
+    server.on("error", () => {
+      // The port may have been taken while closed; the next knock retries.
+    });
+    knockGate = new KnockGate({
+      port: listenPort,
+      host: options.host,
+      secret: options.stealth.secret,
+      onOpenChange: (open) => {
+        if (open && !server.listening) {
+          server.listen(listenPort, options.host);
+        } else if (!open && server.listening) {
+          server.close();
+        }
+      }
+    });
+    await knockGate.start();
+  }
+
+  const quickShareServer = options.quickShare
+    ? createServer((socket) => {
+        activeSockets.add(socket);
+        socket.once("close", () => {
+          activeSockets.delete(socket);
+        });
+        rateLimiter.attach(socket);
+        void receiveQuickShare(socket, receiverContext);
+      })
+    : null;
+  if (quickShareServer) {
+    await new Promise<void>((resolve, reject) => {
+      quickShareServer.once("error", reject);
+      quickShareServer.listen(0, options.host, () => resolve());
+    });
+  }
+  const publishQuickShareService = (): (() => Promise<void>) | null =>
+    quickShareServer
+      ? publishQuickShare(options.serviceName, (quickShareServer.address() as AddressInfo).port)
+      : null;
+  let stopQuickSharePublish = options.hidden ? null : publishQuickShareService();
+
+  const update = async (changes: ReceiverConfigUpdate): Promise<void> => {
+    const nextPairCode = changes.pairCode !== undefined ? changes.pairCode : pairingState.currentCode;
+    const nextShareAllow = changes.shares !== undefined ? (changes.shareAllow ?? {}) : receiverContext.shareAllow;
+    if (!nextPairCode && Object.keys(nextShareAllow).length > 0) {
+      // Checked before anything changes, like the rest of the update.
+      throw new Error(SHARE_ALLOW_NEEDS_PAIR_CODE);
+    }
+    if (changes.outputDir !== undefined) {
+      await fsPromises.mkdir(changes.outputDir, { recursive: true });
+      receiverContext.outputDir = changes.outputDir;
+      receiverContext.contentIndex = new ContentIndex(changes.outputDir);
+    }
+    let pairingToggled = false;
+    if (changes.pairCode !== undefined) {
+      pairingToggled = Boolean(pairingState.currentCode) !== Boolean(changes.pairCode);
+      pairingState.currentCode = changes.pairCode;
+      pairingState.previousCode = null;
+      pairingState.previousCodeValidUntilMs = 0;
+    }
+    if (changes.maxReceiveBytesPerSecond !== undefined) {
+      rateLimiter.setLimit(changes.maxReceiveBytesPerSecond);
+    }
+    if (changes.shares !== undefined) {
+      receiverContext.shares = changes.shares;
+      receiverContext.shareAllow = changes.shareAllow ?? {};
+    }
+    if (changes.clipboardFrom !== undefined) {
+      receiverContext.clipboardFrom = changes.clipboardFrom;
+    }
+    if (pairingToggled && stopPublish && changes.announce !== false) {
+      // Peers learn whether a pair code is needed from the announcement.
+      const unpublish = stopPublish;
+      stopPublish = null;
+      await unpublish();
+      stopPublish = publish();
+    }
+    if (changes.announce === false && stopPublish) {
+      const unpublish = stopPublish;
+      stopPublish = null;
+      await unpublish();
+    } else if (changes.announce === true && !stopPublish) {
+      stopPublish = publish();
+    }
+    if (changes.announce === false && stopQuickSharePublish) {
+      const unpublish = stopQuickSharePublish;
+      stopQuickSharePublish = null;
+      await unpublish();
+    } else if (changes.announce === true && !stopQuickSharePublish) {
+      stopQuickSharePublish = publishQuickShareService();
+    }
+  };
 
-  return async () => {
+  const stop = async (): Promise<void> => {
     if (ttlTimer) {
       clearInterval(ttlTimer);
     }
@@ -309,9 +771,44 @@ export async function startReceiver(options: ListenOptions): Promise<() => Promi
         socket.destroy();
       }
     }
-    await stopPublish();
+    for (const batch of [...batches.values()]) {
+      await rollbackBatch(batches, batch, "receiver stopped");
+    }
+    await stopPublish?.();
+    await stopQuickSharePublish?.();
+    knockGate?.close();
     await closeServer(server);
+    if (quickShareServer) {
+      await closeServer(quickShareServer);
+    }
+  };
+  const transfers = (): ActiveTransfer[] => {
+    const now = Date.now();
+    const active: ActiveTransfer[] = [];
+    for (const state of receiverContext.activeReceives) {
+      const done = state.totalBytes >= 0 && state.bytes >= state.totalBytes;
+      // Failed receives stop reporting progress; they drop out once idle.
+      if (done || now - state.updatedAt > ACTIVE_TRANSFER_IDLE_MS) {
+        receiverContext.activeReceives.delete(state);
+        continue;
+      }
+      const total = state.totalBytes >= 0 ? state.totalBytes : null;
+      active.push({
+        path: state.path,
+        from: state.from ?? "unknown",
+        bytes: state.bytes,
+        total,
+        startedAt: state.startedAt,
+        ...state.analytics.snapshot(total, now)
+      });
+    }
+    return active;
   };
+  return Object.assign(stop, { update, transfers });
+}
+
+function emitFileSent(entry: TransferEntry, ack: AckMessage): void {
+  emitIpc({ type: "fileSent", path: entry.relativePath, source: entry.absolutePath, sha256: ack.sha256 });
 }
 
 export async function sendEntries(options: SendBatchOptions): Promise<SendBatchResult> {
@@ -319,23 +816,63 @@ export async function sendEntries(options: SendBatchOptions): Promise<SendBatchR
   let totalBytes = 0;
   let resumedBytes = 0;
   let activePairCode = options.pairCode;
+  // Where the receiver saved each entry, so hard links can point at it.
+  const savedPaths = new Map<string, string>();
+  const batchId = options.atomic ? randomUUID() : undefined;
+  if (batchId) {
+    await sendBatchControl(options, { type: "batch", action: "begin", batchId, pairCode: activePairCode });
+  }
 
-  for (const [index, entry] of options.entries.entries()) {
-    totalBytes += entry.size;
-    process.stdout.write(`\n[send] ${index + 1}/${options.entries.length} ${entry.relativePath}\n`);
+  const batchTotalBytes = options.entries.reduce((sum, entry) => sum + entry.size, 0);
+  emitIpc({
+    type: "sendPlan",
+    files: options.entries.map((entry) => ({ path: entry.relativePath, size: entry.size }))
+  });
+  try {
+    for (const [index, entry] of options.entries.entries()) {
+      const position: BatchPosition | undefined =
+        options.entries.length > 1
+          ? {
+              index: index + 1,
+              count: options.entries.length,
+              doneBytes: totalBytes,
+              fileBytes: entry.size,
+              totalBytes: batchTotalBytes
+            }
+          : undefined;
+      totalBytes += entry.size;
+      process.stdout.write(`\n[send] ${index + 1}/${options.entries.length} ${entry.relativePath}\n`);
+
+      const ack = await sendEntry(entry, { ...options, pairCode: activePairCode }, savedPaths, batchId, position);
+      resumedBytes += ack.resumedFrom ?? 0;
+      results.push({ entry, ack });
+      // Staged files of an atomic batch only count once it commits.
+      if (!batchId) {
+        emitFileSent(entry, ack);
+      }
+      if (ack.savedPath) {
+        savedPaths.set(entry.relativePath, ack.savedPath);
+      }
+      if (ack.nextPairCode) {
+        activePairCode = ack.nextPairCode;
+      }
+    }
+  } catch (err) {
+    if (!batchId) {
+      throw err;
+    }
+    const reason = (err as Error).message;
+    await sendBatchControl(options, { type: "batch", action: "abort", batchId, pairCode: activePairCode, reason }).catch(
+      () => undefined
+    );
+    throw new Error(`batch rolled back: ${reason}`);
+  }
 
-    const ack = await sendFile({
-      filePath: entry.absolutePath,
-      relativePath: entry.relativePath,
-      host: options.host,
-      port: options.port,
-      pairCode: activePairCode,
-      tls: options.tls
-    });
-    resumedBytes += ack.resumedFrom ?? 0;
-    results.push({ entry, ack });
-    if (ack.nextPairCode) {
-      activePairCode = ack.nextPairCode;
+  if (batchId) {
+    const ack = await sendBatchControl(options, { type: "batch", action: "commit", batchId, pairCode: activePairCode });
+    process.stdout.write(`[send] batch ${ack.message ?? "committed"}\n`);
+    for (const result of results) {
+      emitFileSent(result.entry, result.ack);
     }
   }
 
@@ -347,21 +884,283 @@ export async function sendEntries(options: SendBatchOptions): Promise<SendBatchR
   };
 }
 
-export async function sendFile(options: SendFileOptions): Promise<AckMessage> {
-  const absolutePath = resolve(options.filePath);
-  const stat = await fsPromises.stat(absolutePath);
-  if (!stat.isFile()) {
-    throw new Error("only regular file is supported");
+async function sendEntry(
+  entry: TransferEntry,
+  options: SendBatchOptions,
+  savedPaths: Map<string, string>,
+  batchId: string | undefined,
+  position: BatchPosition | undefined
+): Promise<AckMessage> {
+  const linkOptions = {
+    relativePath: entry.relativePath,
+    host: options.host,
+    port: options.port,
+    pairCode: options.pairCode,
+    tls: options.tls,
+    metadata: entry.metadata,
+    batchId,
+    note: options.note
+  };
+  if (entry.link?.type === "symlink") {
+    return sendLink({ ...linkOptions, link: entry.link });
+  }
+  const linkedPath = entry.link?.type === "hardlink" ? savedPaths.get(entry.link.target) : undefined;
+  if (linkedPath) {
+    try {
+      return await sendLink({ ...linkOptions, link: { type: "hardlink", target: linkedPath } });
+    } catch (err) {
+      process.stdout.write(`[send ${entry.relativePath}] cannot link on receiver (${(err as Error).message}), sending content\n`);
+    }
+  }
+  return sendFile({
+    filePath: entry.absolutePath,
+    relativePath: entry.relativePath,
+    host: options.host,
+    port: options.port,
+    pairCode: options.pairCode,
+    tls: options.tls,
+    streams: options.streams,
+    metadata: entry.metadata,
+    batchId,
+    position,
+    note: options.note
+  });
+}
+
+/**
+ * Sends one `BatchControl` message on its own connection. Receivers that
+ * predate batches answer `begin` with a protocol error.
+ */
+async function sendBatchControl(options: ClientConnectOptions, control: BatchControl): Promise<AckMessage> {
+  const socket = createClientSocket(options);
+  const reader = new SocketReader(socket);
+  try {
+    await waitConnected(socket);
+    await verifyTlsPeerIfNeeded(socket, options);
+    socket.write(encodeJsonLine(control));
+    const reply = await reader.readLineMessage<AckMessage | ReadyMessage>("batch reply");
+    if (!reply.ok) {
+      throw new Error(
+        reply.message === "protocol error: expected header"
+          ? "receiver does not support atomic batches"
+          : (reply.message ?? "receiver rejected batch")
+      );
+    }
+    if (reply.type !== "ack") {
+      throw new Error("protocol error: expected ack message");
+    }
+    return reply;
+  } finally {
+    reader.dispose();
+    if (!socket.destroyed) {
+      socket.destroy();
+    }
+  }
+}
+
+async function openShareConnection(
+  options: ShareClientOptions,
+  request: ShareRequest
+): Promise<{ socket: Socket; reader: SocketReader; reply: ShareReply }> {
+  const socket = createClientSocket(options);
+  const reader = new SocketReader(socket);
+  try {
+    await waitConnected(socket);
+    await verifyTlsPeerIfNeeded(socket, options);
+    socket.write(encodeJsonLine(request));
+    const reply = await reader.readLineMessage<ShareReply | ReadyMessage>("share reply");
+    if (!reply.ok) {
+      throw new Error(
+        reply.message === "protocol error: expected header"
+          ? "receiver does not support shared folders"
+          : (reply.message ?? "receiver rejected share request")
+      );
+    }
+    if (reply.type !== "shares") {
+      throw new Error("protocol error: expected share reply");
+    }
+    return { socket, reader, reply };
+  } catch (err) {
+    reader.dispose();
+    socket.destroy();
+    throw err;
+  }
+}
+
+/**
+ * Lists a folder the remote device shares, or the shares themselves when
+ * `remotePath` is empty.
+ */
+export async function listRemoteShares(options: ShareClientOptions & { remotePath?: string }): Promise<ShareEntry[]> {
+  const { socket, reader, reply } = await openShareConnection(options, {
+    type: "share",
+    action: "list",
+    path: options.remotePath ?? "",
+    pairCode: options.pairCode
+  });
+  reader.dispose();
+  socket.destroy();
+  return reply.entries ?? [];
+}
+
+/**
+ * Downloads one file from a remote share. The file is written next to its
+ * destination and only renamed into place once size and SHA-256 match.
+ */
+export async function pullFile(options: PullFileOptions): Promise<PullFileResult> {
+  const remotePath = normalizeTransferPath(options.remotePath);
+  let savedPath = resolve(options.destination);
+  const destinationStat = await fsPromises.stat(savedPath).catch(() => null);
+  if (destinationStat?.isDirectory()) {
+    savedPath = join(savedPath, basename(remotePath));
+  }
+  await fsPromises.mkdir(dirname(savedPath), { recursive: true });
+
+  const { socket, reader, reply } = await openShareConnection(options, {
+    type: "share",
+    action: "fetch",
+    path: remotePath,
+    pairCode: options.pairCode
+  });
+  const fileSize = reply.fileSize ?? 0;
+  const tempPath = `${savedPath}.${randomUUID()}.part`;
+  const hasher = createHash("sha256");
+  const output = createWriteStream(tempPath);
+  const progressState = createProgressEmitState("recv", remotePath, fileSize, Date.now());
+  let received = 0;
+  try {
+    while (received < fileSize) {
+      const chunk = await reader.readChunk();
+      if (!chunk) {
+        break;
+      }
+      const piece = chunk.subarray(0, fileSize - received);
+      hasher.update(piece);
+      received += piece.length;
+      if (!output.write(piece)) {
+        await once(output, "drain");
+      }
+      emitProgress(progressState, received);
+    }
+    emitProgress(progressState, received, true);
+    if (!ipcEnabled()) {
+      process.stdout.write("\n");
+    }
+    output.end();
+    await finished(output);
+    if (received !== fileSize) {
+      throw new Error(`connection closed after ${received} of ${fileSize} bytes`);
+    }
+    const digest = hasher.digest("hex");
+    if (digest !== reply.sha256) {
+      throw new Error("sha256 mismatch");
+    }
+    await fsPromises.rename(tempPath, savedPath);
+    return { savedPath, bytes: received, sha256: digest };
+  } catch (err) {
+    output.destroy();
+    await fsPromises.rm(tempPath, { force: true }).catch(() => undefined);
+    throw err;
+  } finally {
+    reader.dispose();
+    socket.destroy();
+  }
+}
+
+/** Delivers a short text message; resolves once the receiver has shown it. */
+export async function sendTextMessage(options: SendMessageOptions): Promise<void> {
+  const text = options.text.trim();
+  if (!text) {
+    throw new Error("message is empty");
+  }
+  if (text.length > MAX_MESSAGE_CHARS) {
+    throw new Error(`message is longer than ${MAX_MESSAGE_CHARS} characters`);
+  }
+  await deliverMessage(
+    options,
+    { type: "message", text, from: options.from, pairCode: options.pairCode },
+    "receiver does not support messages"
+  );
+}
+
+/**
+ * Pushes clipboard text to a receiver that syncs clipboards with this
+ * device. Unlike messages, the text is sent exactly as copied.
+ */
+export async function sendClipboardText(options: SendClipboardOptions): Promise<void> {
+  if (!options.text) {
+    throw new Error("clipboard is empty");
+  }
+  if (Buffer.byteLength(options.text, "utf8") > MAX_CLIPBOARD_BYTES) {
+    throw new Error(`clipboard text is larger than ${MAX_CLIPBOARD_BYTES} bytes`);
+  }
+  await deliverMessage(
+    options,
+    { type: "clipboard", text: options.text, pairCode: options.pairCode },
+    "receiver does not support clipboard sync"
+  );
+}
+
+/**
+ * Asks a receiver whether a send of `files` files and `totalBytes` bytes
+ * would be accepted, without transferring anything. Rejects with the
+ * receiver's reason, e.g. a pair code mismatch or too little free space.
+ */
+export async function probeReceiver(options: ProbeReceiverOptions): Promise<{ freeBytes?: number }> {
+  const ack = await deliverMessage(
+    options,
+    { type: "probe", files: options.files, totalBytes: options.totalBytes, pairCode: options.pairCode },
+    "receiver does not support dry runs"
+  );
+  return { freeBytes: ack.freeBytes };
+}
+
+/** Sends one message that the receiver answers with an ack. */
+async function deliverMessage(
+  options: ClientConnectOptions,
+  message: TextMessage | ClipboardMessage | ProbeMessage,
+  unsupported: string
+): Promise<AckMessage> {
+  const socket = createClientSocket(options);
+  const reader = new SocketReader(socket);
+  try {
+    await waitConnected(socket);
+    await verifyTlsPeerIfNeeded(socket, options);
+    socket.write(encodeJsonLine(message));
+    const reply = await reader.readLineMessage<AckMessage | ReadyMessage>(`${message.type} reply`);
+    if (!reply.ok) {
+      throw new Error(
+        reply.message === "protocol error: expected header"
+          ? unsupported
+          : (reply.message ?? `receiver rejected ${message.type}`)
+      );
+    }
+    return reply as AckMessage;
+  } finally {
+    reader.dispose();
+    if (!socket.destroyed) {
+      socket.destroy();
+    }
   }
+}
 
-  const digest = await sha256File(absolutePath);
+/**
+ * Recreates a symlink or hard link on the receiver. Hard link targets are the
+ * receiver's `savedPath` of an earlier entry; the receiver only links to
+ * files inside its output directory.
+ */
+export async function sendLink(options: SendLinkOptions): Promise<AckMessage> {
   const header: TransferHeader = {
     type: "header",
-    version: 1,
+    version: 4,
     relativePath: normalizeTransferPath(options.relativePath),
-    fileSize: stat.size,
-    sha256: digest,
-    pairCode: options.pairCode
+    fileSize: 0,
+    sha256: "",
+    pairCode: options.pairCode,
+    link: options.link,
+    metadata: options.metadata,
+    batch: options.batchId,
+    note: options.note
   };
 
   const socket = createClientSocket(options);
@@ -378,26 +1177,11 @@ export async function sendFile(options: SendFileOptions): Promise<AckMessage> {
       throw new Error("protocol error: expected ready message");
     }
     if (!ready.ok) {
-      throw new Error(ready.message ?? "receiver rejected transfer");
-    }
-
-    const offset = ready.offset;
-    if (offset < 0 || offset > stat.size) {
-      throw new Error(`invalid resume offset: ${offset}`);
-    }
-
-    if (offset < stat.size) {
-      await streamFileRange({
-        socket,
-        filePath: absolutePath,
-        startOffset: offset,
-        totalBytes: stat.size,
-        label: header.relativePath
-      });
-      socket.end();
-    } else {
-      process.stdout.write(`[send ${header.relativePath}] already complete on receiver, waiting ack\n`);
-      socket.end();
+      throw new Error(
+        ready.message === "invalid header fields"
+          ? "receiver does not support links"
+          : (ready.message ?? "receiver rejected transfer")
+      );
     }
 
     const ack = await reader.readLineMessage<AckMessage>("ack");
@@ -407,6 +1191,7 @@ export async function sendFile(options: SendFileOptions): Promise<AckMessage> {
     if (!ack.ok) {
       throw new Error(ack.message ?? "receiver rejected transfer");
     }
+    process.stdout.write(`[send ${header.relativePath}] linked to ${options.link.target}\n`);
     completed = true;
     return ack;
   } finally {
@@ -417,18 +1202,444 @@ export async function sendFile(options: SendFileOptions): Promise<AckMessage> {
   }
 }
 
-async function closeServer(server: CloseableServer): Promise<void> {
-  if (!server.listening) {
-    return;
+export async function sendFile(options: SendFileOptions): Promise<AckMessage> {
+  const absolutePath = resolve(options.filePath);
+  const stat = await fsPromises.stat(absolutePath);
+  if (!stat.isFile()) {
+    throw new Error("only regular file is supported");
   }
 
-  await new Promise<void>((resolve) => {
-    let finished = false;
-    const settle = (): void => {
-      if (finished) {
-        return;
-      }
-      finished = true;
+  const sparse = looksSparse(stat) ? await scanSparseFile(absolutePath, stat.size) : null;
+  const digest = sparse?.sha256 ?? (await sha256File(absolutePath));
+  if (sparse && sparse.dataBytes <= stat.size * SPARSE_MAX_DATA_RATIO) {
+    const ack = await sendSparseFile(options, absolutePath, stat.size, sparse);
+    if (ack) {
+      return ack;
+    }
+    process.stdout.write(`[send ${options.relativePath}] receiver does not support sparse files, sending all bytes\n`);
+  }
+  if (wantsParallel(options.streams, stat.size)) {
+    const ack = await sendFileParallel(options, absolutePath, stat.size, digest);
+    if (ack) {
+      return ack;
+    }
+    process.stdout.write(`[send ${options.relativePath}] receiver does not support parallel streams, using one\n`);
+  }
+
+  const header: TransferHeader = {
+    type: "header",
+    version: 1,
+    relativePath: normalizeTransferPath(options.relativePath),
+    fileSize: stat.size,
+    sha256: digest,
+    pairCode: options.pairCode,
+    metadata: options.metadata,
+    batch: options.batchId,
+    note: options.note
+  };
+
+  const socket = createClientSocket(options);
+  const reader = new SocketReader(socket);
+  let completed = false;
+
+  try {
+    const connectStartedAt = Date.now();
+    await waitConnected(socket);
+    const rttMs = Date.now() - connectStartedAt;
+    await verifyTlsPeerIfNeeded(socket, options);
+    socket.write(encodeJsonLine(header));
+
+    const ready = await reader.readLineMessage<ReadyMessage>("ready");
+    if (ready.type !== "ready") {
+      throw new Error("protocol error: expected ready message");
+    }
+    if (!ready.ok) {
+      throw new Error(ready.message ?? "receiver rejected transfer");
+    }
+
+    const offset = ready.offset;
+    if (offset < 0 || offset > stat.size) {
+      throw new Error(`invalid resume offset: ${offset}`);
+    }
+
+    if (offset < stat.size) {
+      await streamFileRange({
+        socket,
+        filePath: absolutePath,
+        startOffset: offset,
+        totalBytes: stat.size,
+        label: header.relativePath,
+        rttMs,
+        position: options.position
+      });
+      socket.end();
+    } else {
+      process.stdout.write(`[send ${header.relativePath}] already complete on receiver, waiting ack\n`);
+      socket.end();
+    }
+
+    const ack = await reader.readLineMessage<AckMessage>("ack");
+    if (ack.type !== "ack") {
+      throw new Error("protocol error: expected ack message");
+    }
+    if (!ack.ok) {
+      throw new Error(ack.message ?? "receiver rejected transfer");
+    }
+    completed = true;
+    return ack;
+  } finally {
+    reader.dispose();
+    if (!completed && !socket.destroyed) {
+      socket.destroy();
+    }
+  }
+}
+
+/**
+ * Sends content of unknown length (e.g. stdin) without staging it in a temp
+ * file. Nothing can be resumed, and integrity is checked by comparing the
+ * digest the receiver reports with the one hashed while sending.
+ */
+export async function sendStream(options: SendStreamOptions): Promise<AckMessage> {
+  const header: TransferHeader = {
+    type: "header",
+    version: 2,
+    relativePath: normalizeTransferPath(options.relativePath),
+    fileSize: STREAM_FILE_SIZE,
+    sha256: "",
+    pairCode: options.pairCode,
+    note: options.note
+  };
+
+  const socket = createClientSocket(options);
+  const reader = new SocketReader(socket);
+  let completed = false;
+
+  try {
+    await waitConnected(socket);
+    await verifyTlsPeerIfNeeded(socket, options);
+    socket.write(encodeJsonLine(header));
+
+    const ready = await reader.readLineMessage<ReadyMessage>("ready");
+    if (ready.type !== "ready") {
+      throw new Error("protocol error: expected ready message");
+    }
+    if (!ready.ok) {
+      throw new Error(ready.message ?? "receiver rejected transfer");
+    }
+    if (ready.offset !== 0) {
+      throw new Error(`invalid stream offset: ${ready.offset}`);
+    }
+
+    const { sentBytes, digest } = await streamInput({
+      socket,
+      input: options.input,
+      label: header.relativePath
+    });
+    socket.end();
+
+    const ack = await reader.readLineMessage<AckMessage>("ack");
+    if (ack.type !== "ack") {
+      throw new Error("protocol error: expected ack message");
+    }
+    if (!ack.ok) {
+      throw new Error(ack.message ?? "receiver rejected transfer");
+    }
+    if (ack.receivedBytes !== sentBytes || ack.sha256 !== digest) {
+      throw new Error(
+        `stream verification failed: sent ${sentBytes} bytes sha256=${digest}, receiver got ${ack.receivedBytes} bytes sha256=${ack.sha256}`
+      );
+    }
+    completed = true;
+    return ack;
+  } finally {
+    reader.dispose();
+    if (!completed && !socket.destroyed) {
+      socket.destroy();
+    }
+  }
+}
+
+/** Sparse sends only pay off when a good part of the file is holes. */
+const SPARSE_MAX_DATA_RATIO = 0.75;
+
+/**
+ * Sends only the data regions of a sparse file. Returns null when the
+ * receiver predates sparse transfers so the caller can send all bytes.
+ */
+async function sendSparseFile(
+  options: SendFileOptions,
+  absolutePath: string,
+  fileSize: number,
+  sparse: SparseScan
+): Promise<AckMessage | null> {
+  const header: TransferHeader = {
+    type: "header",
+    version: 5,
+    relativePath: normalizeTransferPath(options.relativePath),
+    fileSize,
+    sha256: sparse.sha256,
+    pairCode: options.pairCode,
+    extents: encodeExtents(sparse.extents),
+    metadata: options.metadata,
+    batch: options.batchId,
+    note: options.note
+  };
+
+  const socket = createClientSocket(options);
+  const reader = new SocketReader(socket);
+  let completed = false;
+
+  try {
+    const connectStartedAt = Date.now();
+    await waitConnected(socket);
+    const rttMs = Date.now() - connectStartedAt;
+    await verifyTlsPeerIfNeeded(socket, options);
+    socket.write(encodeJsonLine(header));
+
+    const ready = await reader.readLineMessage<ReadyMessage>("ready");
+    if (ready.type !== "ready") {
+      throw new Error("protocol error: expected ready message");
+    }
+    if (!ready.ok && ready.message === "invalid header fields") {
+      return null;
+    }
+    if (!ready.ok) {
+      throw new Error(ready.message ?? "receiver rejected transfer");
+    }
+    if (ready.offset !== 0 && ready.offset !== fileSize) {
+      throw new Error(`invalid sparse offset: ${ready.offset}`);
+    }
+
+    if (ready.offset === 0) {
+      process.stdout.write(
+        `[send ${header.relativePath}] sparse: ${formatBytes(sparse.dataBytes)} of data in ${sparse.extents.length} extent(s), ${formatBytes(fileSize)} apparent\n`
+      );
+      const tuner = new TransferTuner(rttMs);
+      const progressState = createProgressEmitState(
+        "send",
+        header.relativePath,
+        sparse.dataBytes,
+        Date.now(),
+        options.position
+      );
+      let sent = 0;
+      for (const extent of sparse.extents) {
+        await writeFileRange({
+          socket,
+          filePath: absolutePath,
+          start: extent.start,
+          end: extent.start + extent.length,
+          tuner,
+          onBytes: (bytes) => {
+            sent += bytes;
+            emitProgress(progressState, sent);
+          }
+        });
+      }
+      emitProgress(progressState, sent, true);
+      process.stdout.write("\n");
+    }
+    socket.end();
+
+    const ack = await reader.readLineMessage<AckMessage>("ack");
+    if (ack.type !== "ack") {
+      throw new Error("protocol error: expected ack message");
+    }
+    if (!ack.ok) {
+      throw new Error(ack.message ?? "receiver rejected transfer");
+    }
+    completed = true;
+    return ack;
+  } finally {
+    reader.dispose();
+    if (!completed && !socket.destroyed) {
+      socket.destroy();
+    }
+  }
+}
+
+/** Below this size `streams: "auto"` keeps using a single connection. */
+const PARALLEL_AUTO_MIN_BYTES = 256 * 1024 * 1024;
+const PARALLEL_MIN_RANGE_BYTES = 32 * 1024 * 1024;
+
+function wantsParallel(streams: SendFileOptions["streams"], fileSize: number): boolean {
+  if (streams === "auto") {
+    return fileSize >= PARALLEL_AUTO_MIN_BYTES;
+  }
+  return typeof streams === "number" && streams > 1 && fileSize >= 2;
+}
+
+/**
+ * One TCP connection needs about bandwidth x RTT bytes in flight, so longer
+ * round trips get more connections; on a quiet LAN a few are enough to spread
+ * the work across cores and NIC queues.
+ */
+function autoStreamCount(rttMs: number, fileSize: number): number {
+  const byRtt = rttMs < 2 ? 4 : rttMs < 10 ? 6 : 8;
+  return Math.max(2, Math.min(byRtt, Math.floor(fileSize / PARALLEL_MIN_RANGE_BYTES)));
+}
+
+/**
+ * Sends one file over several connections, each carrying a contiguous byte
+ * range. Returns null when the receiver does not understand version 3
+ * headers so the caller can fall back to one connection. Parallel transfers
+ * always start from scratch; resume stays a single-connection feature.
+ */
+async function sendFileParallel(
+  options: SendFileOptions,
+  absolutePath: string,
+  fileSize: number,
+  digest: string
+): Promise<AckMessage | null> {
+  const relativePath = normalizeTransferPath(options.relativePath);
+  const sessionId = randomUUID();
+  const connections: Array<{ socket: Socket; reader: SocketReader }> = [];
+  let completed = false;
+
+  const connect = async (): Promise<{ socket: Socket; reader: SocketReader }> => {
+    const socket = createClientSocket(options);
+    const connection = { socket, reader: new SocketReader(socket) };
+    connections.push(connection);
+    await waitConnected(socket);
+    return connection;
+  };
+  const handshake = async (
+    connection: { socket: Socket; reader: SocketReader },
+    range: ParallelRange
+  ): Promise<ReadyMessage> => {
+    await verifyTlsPeerIfNeeded(connection.socket, options);
+    connection.socket.write(
+      encodeJsonLine({
+        type: "header",
+        version: 3,
+        relativePath,
+        fileSize,
+        sha256: digest,
+        pairCode: options.pairCode,
+        range,
+        metadata: options.metadata,
+        batch: options.batchId,
+        note: options.note
+      } satisfies TransferHeader)
+    );
+    const ready = await connection.reader.readLineMessage<ReadyMessage>("ready");
+    if (ready.type !== "ready") {
+      throw new Error("protocol error: expected ready message");
+    }
+    return ready;
+  };
+  const checkReady = (ready: ReadyMessage, range: ParallelRange): void => {
+    if (!ready.ok) {
+      throw new Error(ready.message ?? "receiver rejected transfer");
+    }
+    if (ready.offset !== 0 && ready.offset !== range.end - range.start) {
+      throw new Error(`invalid range offset: ${ready.offset}`);
+    }
+  };
+
+  try {
+    const connectStartedAt = Date.now();
+    const first = await connect();
+    const rttMs = Date.now() - connectStartedAt;
+    const count =
+      options.streams === "auto"
+        ? autoStreamCount(rttMs, fileSize)
+        : Math.min(options.streams ?? 2, MAX_PARALLEL_STREAMS, fileSize);
+    const ranges: ParallelRange[] = splitRanges(fileSize, count).map((range, index) => ({
+      sessionId,
+      index,
+      count,
+      ...range
+    }));
+
+    const firstReady = await handshake(first, ranges[0]);
+    if (!firstReady.ok && firstReady.message === "invalid header fields") {
+      return null;
+    }
+    checkReady(firstReady, ranges[0]);
+    // The first connection went through confirmation; the rest join it.
+    const others = await Promise.all(
+      ranges.slice(1).map(async (range) => {
+        const connection = await connect();
+        const ready = await handshake(connection, range);
+        checkReady(ready, range);
+        return { connection, ready };
+      })
+    );
+    const links = [{ connection: first, ready: firstReady }, ...others];
+
+    const startedAt = Date.now();
+    const counters: StreamCounter[] = ranges.map(() => ({ bytes: 0, startedAt }));
+    const tuners = ranges.map(() => new TransferTuner(rttMs));
+    const progressState = createProgressEmitState("send", relativePath, fileSize, startedAt, options.position);
+    progressState.detail = () => renderStreamStats(counters);
+    let sent = 0;
+    await Promise.all(
+      links.map(async ({ connection, ready }, index) => {
+        const range = ranges[index];
+        if (ready.offset === range.end - range.start) {
+          // The receiver already has this content.
+          sent += ready.offset;
+        } else {
+          await writeFileRange({
+            socket: connection.socket,
+            filePath: absolutePath,
+            start: range.start,
+            end: range.end,
+            tuner: tuners[index],
+            onBytes: (bytes) => {
+              counters[index].bytes += bytes;
+              sent += bytes;
+              emitProgress(progressState, sent);
+            }
+          });
+        }
+        connection.socket.end();
+      })
+    );
+    emitProgress(progressState, sent, true);
+    process.stdout.write("\n");
+    process.stdout.write(
+      `[send ${relativePath}] tuning: ${tuners.map((tuner) => renderTuning(tuner.stats())).join(" | ")}\n`
+    );
+
+    const acks = await Promise.all(
+      links.map(async ({ connection }) => {
+        const ack = await connection.reader.readLineMessage<AckMessage>("ack");
+        if (ack.type !== "ack") {
+          throw new Error("protocol error: expected ack message");
+        }
+        if (!ack.ok) {
+          throw new Error(ack.message ?? "receiver rejected transfer");
+        }
+        return ack;
+      })
+    );
+    completed = true;
+    return acks[0];
+  } finally {
+    for (const { socket, reader } of connections) {
+      reader.dispose();
+      if (!completed && !socket.destroyed) {
+        socket.destroy();
+      }
+    }
+  }
+}
+
+async function closeServer(server: CloseableServer): Promise<void> {
+  if (!server.listening) {
+    return;
+  }
+
+  await new Promise<void>((resolve) => {
+    let finished = false;
+    const settle = (): void => {
+      if (finished) {
+        return;
+      }
+      finished = true;
       clearTimeout(timer);
       resolve();
     };
@@ -457,7 +1668,9 @@ async function closeServer(server: CloseableServer): Promise<void> {
   });
 }
 
-async function handleIncomingSocket(socket: Socket, outputDir: string, pairingState: PairingState, listenOptions: ListenOptions): Promise<void> {
+async function handleIncomingSocket(socket: Socket, receiverContext: ReceiverContext): Promise<void> {
+  let context = receiverContext;
+  const { pairingState, contentIndex, listenOptions } = context;
   socket.on("error", () => {
     // Keep receiver process alive if peer resets after we reply with an error.
   });
@@ -476,194 +1689,1618 @@ async function handleIncomingSocket(socket: Socket, outputDir: string, pairingSt
   const startedAt = Date.now();
   const remoteAddress = normalizeRemoteAddress(socket.remoteAddress);
 
-  const fail = async (message: string, cleanup = false): Promise<void> => {
-    if (failed) {
-      return;
+  const fail = async (message: string, cleanup = false): Promise<void> => {
+    if (failed) {
+      return;
+    }
+    failed = true;
+    reportReceiveFailure(context, message);
+
+    if (fileStream) {
+      fileStream.destroy();
+    }
+
+    if (cleanup && tempPath) {
+      try {
+        await fsPromises.rm(tempPath, { force: true });
+      } catch {
+        // Ignore cleanup failures.
+      }
+    }
+
+    endWithFailure(socket, phase, message);
+  };
+
+  try {
+    const message = await reader.readLineMessage<
+      TransferHeader | BatchControl | ShareRequest | TextMessage | ClipboardMessage | ProbeMessage
+    >("header");
+    if (message.type === "batch") {
+      await receiveBatchControl(socket, message, context, remoteAddress);
+      return;
+    }
+    if (message.type === "share") {
+      await serveShareRequest(socket, message, context, remoteAddress);
+      return;
+    }
+    if (message.type === "message") {
+      receiveTextMessage(socket, message, context, remoteAddress);
+      return;
+    }
+    if (message.type === "clipboard") {
+      receiveClipboard(socket, message, context, remoteAddress);
+      return;
+    }
+    if (message.type === "probe") {
+      await answerProbe(socket, message, context, remoteAddress);
+      return;
+    }
+    header = message;
+    if (header.type !== "header") {
+      await fail("protocol error: expected header");
+      return;
+    }
+    if (header.batch !== undefined) {
+      const batch = context.batches.get(header.batch);
+      if (!batch) {
+        await fail("unknown batch");
+        return;
+      }
+      armBatchTimer(context.batches, batch);
+      batch.note ??= sanitizeTransferNote(header.note);
+      context = { ...context, outputDir: batch.stagingDir, batch };
+    }
+    if (listenOptions.webdav) {
+      if (header.version === 4) {
+        await fail("links cannot be saved to WebDAV");
+      } else if (header.version === 3 || header.version === 5) {
+        // Makes the sender fall back to one plain connection.
+        endWithFailure(socket, phase, "invalid header fields");
+      } else {
+        await receiveToWebDav(socket, reader, header, remoteAddress, context, listenOptions.webdav);
+      }
+      return;
+    }
+    if (header.version === 3 || header.version === 4 || header.version === 5) {
+      if (header.version === 3) {
+        await receiveParallelRange(socket, reader, header, remoteAddress, context);
+      } else if (header.version === 4) {
+        await receiveLink(socket, header, remoteAddress, context);
+      } else {
+        await receiveSparse(socket, reader, header, remoteAddress, context);
+      }
+      return;
+    }
+
+    const streaming = header.version === 2;
+    const validHeader = streaming
+      ? header.fileSize === STREAM_FILE_SIZE
+      : header.version === 1 && header.fileSize >= 0 && Boolean(header.sha256);
+    if (!validHeader) {
+      await fail("invalid header fields");
+      return;
+    }
+
+    requiredPairCodeForThisTransfer = pairingState.currentCode;
+    if (!checkPairCode(context, remoteAddress, header.pairCode)) {
+      await fail("pair code mismatch");
+      return;
+    }
+
+    const receivePathSelection = streaming
+      ? await selectStreamReceivePaths(context.outputDir, header.relativePath)
+      : await selectReceivePaths({
+          outputDir: context.outputDir,
+          relativePath: header.relativePath,
+          expectedSha256: header.sha256,
+          expectedSize: header.fileSize
+        });
+    targetPath = receivePathSelection.finalPath;
+    tempPath = receivePathSelection.tempPath;
+    await prepareOutputParent(context.outputDir, targetPath);
+
+    if (listenOptions.confirmTransfer) {
+      const decision = await listenOptions.confirmTransfer({
+        from: remoteAddress,
+        relativePath: header.relativePath,
+        fileSize: streaming ? 0 : header.fileSize,
+        sha256: header.sha256,
+        streaming
+      });
+      const rejection = rejectionMessage(decision);
+      if (rejection) {
+        await fail(rejection);
+        return;
+      }
+    }
+
+    // The content index finds data received under another name: a partial is
+    // adopted as this transfer's temp file, a complete copy is reported back
+    // instead of receiving the same bytes again.
+    const known = streaming ? null : await contentIndex.lookup(header.sha256, header.fileSize);
+    if (known?.state === "partial" && known.absolutePath !== tempPath && !(await pathExists(tempPath))) {
+      try {
+        await fsPromises.rename(known.absolutePath, tempPath);
+      } catch {
+        // Fall back to a fresh receive.
+      }
+    }
+    const alreadyHavePath =
+      known?.state === "complete" && !listenOptions.keepDuplicates
+        ? await adoptKnownCopy(context, known.absolutePath, targetPath)
+        : null;
+
+    if (alreadyHavePath) {
+      resumedFrom = header.fileSize;
+    } else if (!streaming) {
+      resumedFrom = await decideResumeOffset({
+        targetPath: tempPath,
+        expectedSha256: header.sha256,
+        expectedSize: header.fileSize,
+        hasher
+      });
+      await contentIndex.markPartial(header.sha256, tempPath, header.fileSize);
+    }
+    received = resumedFrom;
+    const recvProgressState = trackReceive(
+      context,
+      createProgressEmitState("recv", header.relativePath, header.fileSize, startedAt),
+      remoteAddress
+    );
+
+    if (streaming || resumedFrom < header.fileSize) {
+      fileStream = createWriteStream(tempPath, resumedFrom > 0 ? { flags: "r+", start: resumedFrom } : { flags: "w" });
+      fileStream.on("error", () => {
+        void fail("cannot write target file");
+      });
+    }
+
+    socket.write(
+      encodeJsonLine({
+        type: "ready",
+        ok: true,
+        offset: resumedFrom,
+        savedPath: alreadyHavePath ?? targetPath
+      } satisfies ReadyMessage)
+    );
+    phase = "receiving";
+
+    const maxBytes = streaming ? Number.MAX_SAFE_INTEGER : header.fileSize;
+    while (streaming || received < header.fileSize) {
+      const chunk = await reader.readChunk();
+      if (chunk === null) {
+        break;
+      }
+
+      await writePayload({
+        payload: chunk,
+        maxBytes,
+        fileStream,
+        hasher,
+        progressState: recvProgressState,
+        receivedRef: {
+          get: () => received,
+          set: (v: number) => {
+            received = v;
+          }
+        }
+      });
+    }
+
+    if (!streaming && received !== header.fileSize) {
+      await fail(`size mismatch: expected ${header.fileSize}, got ${received}`);
+      return;
+    }
+
+    if (fileStream) {
+      fileStream.end();
+      await finished(fileStream);
+    }
+
+    const digest = alreadyHavePath ? header.sha256 : hasher.digest("hex");
+    if (!streaming && digest !== header.sha256) {
+      await contentIndex.forget(header.sha256);
+      await fail("sha256 mismatch", true);
+      return;
+    }
+    const savedPath = alreadyHavePath ?? (await promoteReceivedFile(tempPath, targetPath));
+    if (!alreadyHavePath) {
+      await contentIndex.markComplete(digest, savedPath, received);
+      await applyEntryMetadata(savedPath, header.metadata, listenOptions);
+    }
+
+    phase = "done";
+    const nextPairCode = nextPairCodeAfterTransfer(requiredPairCodeForThisTransfer, pairingState, listenOptions);
+
+    if (streaming) {
+      recvProgressState.totalBytes = received;
+    }
+    emitProgress(recvProgressState, received, true);
+    process.stdout.write("\n");
+    socket.end(
+      encodeJsonLine({
+        type: "ack",
+        ok: true,
+        sha256: digest,
+        receivedBytes: received,
+        savedPath,
+        resumedFrom,
+        nextPairCode,
+        deduplicated: alreadyHavePath ? true : undefined,
+        message: alreadyHavePath ? "already have it" : undefined
+      } satisfies AckMessage)
+    );
+    reportSaved(context, savedPath, Boolean(alreadyHavePath), remoteAddress, header.note);
+  } catch (err) {
+    await fail((err as Error).message);
+  } finally {
+    reader.dispose();
+    pairingState.activeTransfers = Math.max(0, pairingState.activeTransfers - 1);
+  }
+}
+
+const MAX_PARALLEL_STREAMS = 16;
+
+function rejectionMessage(decision: TransferConfirmDecision | boolean): string | null {
+  if (typeof decision === "boolean") {
+    return decision ? null : "receiver rejected transfer";
+  }
+  return decision.accept ? null : decision.message?.trim() || "receiver rejected transfer";
+}
+
+function nextPairCodeAfterTransfer(
+  requiredPairCode: string | null,
+  pairingState: PairingState,
+  listenOptions: ListenOptions
+): string | undefined {
+  if (!requiredPairCode) {
+    return undefined;
+  }
+  if (listenOptions.rotatePairCodePerTransfer && listenOptions.generatePairCode) {
+    const nextPairCode = rotatePairCodeOnce(pairingState, listenOptions.generatePairCode);
+    if (nextPairCode) {
+      listenOptions.onPairCodeChange?.(nextPairCode, "once");
+    }
+    return nextPairCode;
+  }
+  return pairingState.currentCode ?? undefined;
+}
+
+function endWithFailure(socket: Socket, phase: ReceiverPhase, message: string): void {
+  const messageBody =
+    phase === "before-ready"
+      ? ({
+          type: "ready",
+          ok: false,
+          offset: 0,
+          message
+        } satisfies ReadyMessage)
+      : ({
+          type: "ack",
+          ok: false,
+          message
+        } satisfies AckMessage);
+
+  if (socket.writable) {
+    try {
+      socket.end(encodeJsonLine(messageBody));
+      setImmediate(() => {
+        if (!socket.destroyed) {
+          socket.destroy();
+        }
+      });
+    } catch {
+      socket.destroy();
+    }
+  } else {
+    socket.destroy();
+  }
+}
+
+function splitRanges(fileSize: number, count: number): Array<{ start: number; end: number }> {
+  return Array.from({ length: count }, (_, index) => ({
+    start: Math.floor((index * fileSize) / count),
+    end: Math.floor(((index + 1) * fileSize) / count)
+  }));
+}
+
+/**
+ * Ranges must be exactly the ones `splitRanges` produces, so once every index
+ * arrived the whole file is covered without gaps or overlaps.
+ */
+function isValidParallelHeader(header: TransferHeader): header is TransferHeader & { range: ParallelRange } {
+  const range = header.range;
+  if (!range || !Number.isSafeInteger(header.fileSize) || header.fileSize < 0 || !header.sha256) {
+    return false;
+  }
+  if (typeof range.sessionId !== "string" || !/^[A-Za-z0-9-]{1,64}$/.test(range.sessionId)) {
+    return false;
+  }
+  if (!Number.isInteger(range.count) || range.count < 2 || range.count > MAX_PARALLEL_STREAMS) {
+    return false;
+  }
+  if (!Number.isInteger(range.index) || range.index < 0 || range.index >= range.count) {
+    return false;
+  }
+  const expected = splitRanges(header.fileSize, range.count)[range.index];
+  return range.start === expected.start && range.end === expected.end;
+}
+
+function sameParallelFile(a: TransferHeader, b: TransferHeader): boolean {
+  return (
+    a.relativePath === b.relativePath &&
+    a.fileSize === b.fileSize &&
+    a.sha256 === b.sha256 &&
+    a.pairCode === b.pairCode &&
+    a.range?.count === b.range?.count
+  );
+}
+
+async function prepareParallelSession(
+  header: TransferHeader,
+  remoteAddress: string,
+  context: ReceiverContext
+): Promise<ParallelSessionSetup> {
+  const { outputDir, pairingState, contentIndex, listenOptions } = context;
+  const requiredPairCode = pairingState.currentCode;
+  const paths = await selectStreamReceivePaths(outputDir, header.relativePath);
+  await prepareOutputParent(outputDir, paths.finalPath);
+
+  if (listenOptions.confirmTransfer) {
+    const decision = await listenOptions.confirmTransfer({
+      from: remoteAddress,
+      relativePath: header.relativePath,
+      fileSize: header.fileSize,
+      sha256: header.sha256,
+      streaming: false
+    });
+    const rejection = rejectionMessage(decision);
+    if (rejection) {
+      throw new Error(rejection);
+    }
+  }
+
+  const known = await contentIndex.lookup(header.sha256, header.fileSize);
+  const alreadyHavePath =
+    known?.state === "complete" && !listenOptions.keepDuplicates
+      ? await adoptKnownCopy(context, known.absolutePath, paths.finalPath)
+      : null;
+  let handle: FileHandle | null = null;
+  if (!alreadyHavePath) {
+    handle = await fsPromises.open(paths.tempPath, "w");
+    // Sizing the file up front lets every range write at its own offset.
+    await handle.truncate(header.fileSize);
+  }
+  return {
+    handle,
+    finalPath: paths.finalPath,
+    tempPath: paths.tempPath,
+    alreadyHavePath,
+    requiredPairCode
+  };
+}
+
+function openParallelSession(header: TransferHeader, remoteAddress: string, context: ReceiverContext): ParallelSession {
+  let settle: (outcome: AckMessage | Error) => void = () => undefined;
+  const result = new Promise<AckMessage>((resolve, reject) => {
+    settle = (outcome) => (outcome instanceof Error ? reject(outcome) : resolve(outcome));
+  });
+  const setup = prepareParallelSession(header, remoteAddress, context);
+  // Every connection of the session awaits these; failures are reported there.
+  result.catch(() => undefined);
+  setup.catch(() => undefined);
+
+  const counters = new Map<number, StreamCounter>();
+  const progressState = trackReceive(
+    context,
+    createProgressEmitState("recv", header.relativePath, header.fileSize, Date.now()),
+    remoteAddress
+  );
+  progressState.detail = () => renderStreamStats([...counters.values()]);
+  const session: ParallelSession = {
+    header,
+    remoteAddress,
+    setup,
+    result,
+    settle,
+    settled: false,
+    failure: null,
+    arrived: new Set(),
+    completed: new Set(),
+    received: 0,
+    counters,
+    progressState
+  };
+  context.parallelSessions.set(header.range!.sessionId, session);
+  return session;
+}
+
+function failParallelSession(session: ParallelSession, context: ReceiverContext, message: string): void {
+  if (session.settled) {
+    return;
+  }
+  session.settled = true;
+  session.failure = message;
+  context.parallelSessions.delete(session.header.range!.sessionId);
+  reportReceiveFailure(context, message);
+  session.settle(new Error(message));
+  void session.setup
+    .then(async (setup) => {
+      const handle = setup.handle;
+      setup.handle = null;
+      await handle?.close();
+      if (!setup.alreadyHavePath) {
+        await fsPromises.rm(setup.tempPath, { force: true });
+      }
+    })
+    .catch(() => {
+      // Ignore cleanup failures.
+    });
+}
+
+async function finishParallelSession(session: ParallelSession, context: ReceiverContext): Promise<void> {
+  const { header } = session;
+  try {
+    const setup = await session.setup;
+    let savedPath = setup.alreadyHavePath;
+    if (!savedPath) {
+      const handle = setup.handle;
+      setup.handle = null;
+      await handle?.close();
+      const digest = await sha256File(setup.tempPath);
+      if (digest !== header.sha256) {
+        throw new Error("sha256 mismatch");
+      }
+      savedPath = await promoteReceivedFile(setup.tempPath, setup.finalPath);
+      await context.contentIndex.markComplete(digest, savedPath, header.fileSize);
+      await applyEntryMetadata(savedPath, header.metadata, context.listenOptions);
+    }
+    if (session.settled) {
+      return;
+    }
+
+    session.settled = true;
+    context.parallelSessions.delete(header.range!.sessionId);
+    const nextPairCode = nextPairCodeAfterTransfer(setup.requiredPairCode, context.pairingState, context.listenOptions);
+    emitProgress(session.progressState, header.fileSize, true);
+    process.stdout.write("\n");
+    session.settle({
+      type: "ack",
+      ok: true,
+      sha256: header.sha256,
+      receivedBytes: header.fileSize,
+      savedPath,
+      resumedFrom: setup.alreadyHavePath ? header.fileSize : 0,
+      streams: header.range!.count,
+      nextPairCode,
+      deduplicated: setup.alreadyHavePath ? true : undefined,
+      message: setup.alreadyHavePath ? "already have it" : undefined
+    });
+    reportSaved(context, savedPath, Boolean(setup.alreadyHavePath), session.remoteAddress, header.note);
+  } catch (err) {
+    failParallelSession(session, context, (err as Error).message);
+  }
+}
+
+/**
+ * Receives one range of a parallel transfer. The first connection of a
+ * session checks the pair code, asks for confirmation and opens the temp
+ * file; the others wait for that and then write their range in place. The
+ * connection that completes the last range verifies and promotes the file,
+ * and every connection answers with the same ack.
+ */
+async function receiveParallelRange(
+  socket: Socket,
+  reader: SocketReader,
+  header: TransferHeader,
+  remoteAddress: string,
+  context: ReceiverContext
+): Promise<void> {
+  const refuse = (message: string): void => {
+    reportReceiveFailure(context, message);
+    endWithFailure(socket, "before-ready", message);
+  };
+  if (!isValidParallelHeader(header)) {
+    refuse("invalid header fields");
+    return;
+  }
+  const range = header.range;
+  let session = context.parallelSessions.get(range.sessionId);
+  if (!session) {
+    if (!checkPairCode(context, remoteAddress, header.pairCode)) {
+      refuse("pair code mismatch");
+      return;
+    }
+    session = openParallelSession(header, remoteAddress, context);
+  } else if (
+    !sameParallelFile(session.header, header) ||
+    session.remoteAddress !== remoteAddress ||
+    session.arrived.has(range.index)
+  ) {
+    refuse("parallel session mismatch");
+    return;
+  }
+
+  const active = session;
+  active.arrived.add(range.index);
+  let phase: ReceiverPhase = "before-ready";
+  const onClose = (): void => {
+    failParallelSession(active, context, "connection closed before the transfer finished");
+  };
+  socket.once("close", onClose);
+
+  try {
+    const setup = await active.setup;
+    const length = range.end - range.start;
+    const counter: StreamCounter = { bytes: 0, startedAt: Date.now() };
+    active.counters.set(range.index, counter);
+    let written = setup.alreadyHavePath ? length : 0;
+    socket.write(
+      encodeJsonLine({
+        type: "ready",
+        ok: true,
+        offset: written,
+        savedPath: setup.alreadyHavePath ?? setup.finalPath
+      } satisfies ReadyMessage)
+    );
+    phase = "receiving";
+
+    while (written < length) {
+      const chunk = await reader.readChunk();
+      if (chunk === null) {
+        break;
+      }
+      if (active.failure) {
+        throw new Error(active.failure);
+      }
+      if (written + chunk.length > length) {
+        throw new Error("payload exceeds declared range");
+      }
+      if (!setup.handle) {
+        throw new Error("unexpected payload for already complete file");
+      }
+      await setup.handle.write(chunk, 0, chunk.length, range.start + written);
+      written += chunk.length;
+      counter.bytes += chunk.length;
+      active.received += chunk.length;
+      emitProgress(active.progressState, active.received);
+    }
+    if (written !== length) {
+      throw new Error(`size mismatch in range ${range.index}: expected ${length}, got ${written}`);
+    }
+
+    active.completed.add(range.index);
+    if (active.completed.size === range.count) {
+      void finishParallelSession(active, context);
+    }
+    const ack = await active.result;
+    phase = "done";
+    socket.end(encodeJsonLine(ack));
+  } catch (err) {
+    const message = active.failure ?? (err as Error).message;
+    failParallelSession(active, context, message);
+    endWithFailure(socket, phase, message);
+  } finally {
+    socket.removeListener("close", onClose);
+  }
+}
+
+/**
+ * Receives the data regions of a sparse file into a temp file truncated to
+ * the full size, so the filesystem keeps the gaps as holes where it can.
+ */
+async function receiveSparse(
+  socket: Socket,
+  reader: SocketReader,
+  header: TransferHeader,
+  remoteAddress: string,
+  context: ReceiverContext
+): Promise<void> {
+  const { contentIndex, listenOptions } = context;
+  let phase: ReceiverPhase = "before-ready";
+  let tempPath = "";
+  let handle: FileHandle | null = null;
+  const fail = async (message: string): Promise<void> => {
+    reportReceiveFailure(context, message);
+    await handle?.close().catch(() => undefined);
+    handle = null;
+    if (tempPath) {
+      await fsPromises.rm(tempPath, { force: true }).catch(() => undefined);
+    }
+    endWithFailure(socket, phase, message);
+  };
+
+  const extents =
+    Number.isSafeInteger(header.fileSize) && header.fileSize >= 0 && header.sha256
+      ? decodeExtents(header.extents, header.fileSize)
+      : null;
+  if (!extents) {
+    await fail("invalid header fields");
+    return;
+  }
+  const requiredPairCode = context.pairingState.currentCode;
+  if (!checkPairCode(context, remoteAddress, header.pairCode)) {
+    await fail("pair code mismatch");
+    return;
+  }
+
+  try {
+    const paths = await selectStreamReceivePaths(context.outputDir, header.relativePath);
+    await prepareOutputParent(context.outputDir, paths.finalPath);
+    if (listenOptions.confirmTransfer) {
+      const decision = await listenOptions.confirmTransfer({
+        from: remoteAddress,
+        relativePath: header.relativePath,
+        fileSize: header.fileSize,
+        sha256: header.sha256,
+        streaming: false
+      });
+      const rejection = rejectionMessage(decision);
+      if (rejection) {
+        await fail(rejection);
+        return;
+      }
+    }
+
+    const known = await contentIndex.lookup(header.sha256, header.fileSize);
+    const alreadyHavePath =
+      known?.state === "complete" && !listenOptions.keepDuplicates
+        ? await adoptKnownCopy(context, known.absolutePath, paths.finalPath)
+        : null;
+    const dataBytes = extents.reduce((sum, extent) => sum + extent.length, 0);
+    if (!alreadyHavePath) {
+      tempPath = paths.tempPath;
+      handle = await fsPromises.open(tempPath, "w");
+      await handle.truncate(header.fileSize);
+    }
+    socket.write(
+      encodeJsonLine({
+        type: "ready",
+        ok: true,
+        offset: alreadyHavePath ? header.fileSize : 0,
+        savedPath: alreadyHavePath ?? paths.finalPath
+      } satisfies ReadyMessage)
+    );
+    phase = "receiving";
+
+    const progressState = trackReceive(
+      context,
+      createProgressEmitState("recv", header.relativePath, dataBytes, Date.now()),
+      remoteAddress
+    );
+    let received = 0;
+    let extentIndex = 0;
+    let extentOffset = 0;
+    while (handle && received < dataBytes) {
+      const chunk = await reader.readChunk();
+      if (chunk === null) {
+        break;
+      }
+      if (received + chunk.length > dataBytes) {
+        throw new Error("payload exceeds declared extents");
+      }
+      let consumed = 0;
+      while (consumed < chunk.length) {
+        const extent = extents[extentIndex];
+        const length = Math.min(extent.length - extentOffset, chunk.length - consumed);
+        await handle.write(chunk, consumed, length, extent.start + extentOffset);
+        consumed += length;
+        extentOffset += length;
+        if (extentOffset === extent.length) {
+          extentIndex += 1;
+          extentOffset = 0;
+        }
+      }
+      received += chunk.length;
+      emitProgress(progressState, received);
+    }
+
+    let savedPath = alreadyHavePath;
+    if (!savedPath) {
+      if (received !== dataBytes) {
+        throw new Error(`size mismatch: expected ${dataBytes}, got ${received}`);
+      }
+      await handle!.close();
+      handle = null;
+      if ((await sha256File(tempPath)) !== header.sha256) {
+        throw new Error("sha256 mismatch");
+      }
+      savedPath = await promoteReceivedFile(tempPath, paths.finalPath);
+      tempPath = "";
+      await contentIndex.markComplete(header.sha256, savedPath, header.fileSize);
+      await applyEntryMetadata(savedPath, header.metadata, listenOptions);
+    }
+
+    phase = "done";
+    emitProgress(progressState, received, true);
+    process.stdout.write("\n");
+    socket.end(
+      encodeJsonLine({
+        type: "ack",
+        ok: true,
+        sha256: header.sha256,
+        receivedBytes: received,
+        savedPath,
+        resumedFrom: alreadyHavePath ? header.fileSize : 0,
+        nextPairCode: nextPairCodeAfterTransfer(requiredPairCode, context.pairingState, listenOptions),
+        deduplicated: alreadyHavePath ? true : undefined,
+        message: alreadyHavePath ? "already have it" : undefined
+      } satisfies AckMessage)
+    );
+    reportSaved(context, savedPath, Boolean(alreadyHavePath), remoteAddress, header.note, " (sparse)");
+  } catch (err) {
+    await fail((err as Error).message);
+  }
+}
+
+/**
+ * Receives a version 1 or 2 transfer straight into a WebDAV upload. Nothing
+ * touches local disk, so resume and duplicate detection are not available;
+ * a file whose digest does not match is deleted again.
+ */
+async function receiveToWebDav(
+  socket: Socket,
+  reader: SocketReader,
+  header: TransferHeader,
+  remoteAddress: string,
+  context: ReceiverContext,
+  target: WebDavTarget
+): Promise<void> {
+  const { listenOptions } = context;
+  const streaming = header.version === 2;
+  let phase: ReceiverPhase = "before-ready";
+  let upload: WebDavUpload | null = null;
+  const fail = (message: string): void => {
+    reportReceiveFailure(context, message);
+    upload?.abort();
+    endWithFailure(socket, phase, message);
+  };
+
+  const validHeader = streaming
+    ? header.fileSize === STREAM_FILE_SIZE
+    : header.version === 1 && header.fileSize >= 0 && Boolean(header.sha256);
+  if (!validHeader) {
+    fail("invalid header fields");
+    return;
+  }
+  const requiredPairCode = context.pairingState.currentCode;
+  if (!checkPairCode(context, remoteAddress, header.pairCode)) {
+    fail("pair code mismatch");
+    return;
+  }
+
+  try {
+    const relativePath = normalizeTransferPath(header.relativePath);
+    if (listenOptions.confirmTransfer) {
+      const decision = await listenOptions.confirmTransfer({
+        from: remoteAddress,
+        relativePath: header.relativePath,
+        fileSize: streaming ? 0 : header.fileSize,
+        sha256: header.sha256,
+        streaming
+      });
+      const rejection = rejectionMessage(decision);
+      if (rejection) {
+        fail(rejection);
+        return;
+      }
+    }
+    await ensureWebDavFolders(target, relativePath);
+    upload = new WebDavUpload(target, webDavResourceUrl(target, relativePath), streaming ? null : header.fileSize);
+    const savedPath = upload.url.href;
+    socket.write(encodeJsonLine({ type: "ready", ok: true, offset: 0, savedPath } satisfies ReadyMessage));
+    phase = "receiving";
+
+    const hasher = createHash("sha256");
+    const progressState = trackReceive(
+      context,
+      createProgressEmitState("recv", header.relativePath, header.fileSize, Date.now()),
+      remoteAddress
+    );
+    const maxBytes = streaming ? Number.MAX_SAFE_INTEGER : header.fileSize;
+    let received = 0;
+    while (streaming || received < header.fileSize) {
+      const chunk = await reader.readChunk();
+      if (chunk === null) {
+        break;
+      }
+      received += chunk.length;
+      if (received > maxBytes) {
+        throw new Error("payload exceeds declared file size");
+      }
+      hasher.update(chunk);
+      await upload.write(chunk);
+      emitProgress(progressState, received);
+    }
+    if (!streaming && received !== header.fileSize) {
+      fail(`size mismatch: expected ${header.fileSize}, got ${received}`);
+      return;
+    }
+    await upload.finish();
+    const digest = hasher.digest("hex");
+    if (!streaming && digest !== header.sha256) {
+      await deleteWebDavResource(target, upload.url);
+      fail("sha256 mismatch");
+      return;
+    }
+
+    phase = "done";
+    if (streaming) {
+      progressState.totalBytes = received;
+    }
+    emitProgress(progressState, received, true);
+    process.stdout.write("\n");
+    socket.end(
+      encodeJsonLine({
+        type: "ack",
+        ok: true,
+        sha256: digest,
+        receivedBytes: received,
+        savedPath,
+        resumedFrom: 0,
+        nextPairCode: nextPairCodeAfterTransfer(requiredPairCode, context.pairingState, listenOptions)
+      } satisfies AckMessage)
+    );
+    reportSaved(context, savedPath, false, remoteAddress, header.note);
+  } catch (err) {
+    fail((err as Error).message);
+  }
+}
+
+// The share plugin sends bare names; everything lands in one folder.
+function quickShareFileName(name: string): string {
+  return normalizeTransferPath(basename(name.replace(/\\/g, "/")));
+}
+
+interface QuickShareReceive {
+  finalPath: string;
+  tempPath: string;
+  stream: WriteStream;
+  received: number;
+  progress: ProgressEmitState;
+}
+
+/**
+ * A share from a Quick Share sender. Shares are accepted or declined as a
+ * whole, so every file goes through `confirmTransfer` before any data
+ * flows. Senders cannot enter a pair code; while one is set, shares are
+ * only taken when they are confirmed.
+ */
+async function receiveQuickShare(socket: Socket, context: ReceiverContext): Promise<void> {
+  const { listenOptions } = context;
+  const remoteAddress = normalizeRemoteAddress(socket.remoteAddress);
+  const receiving = new Map<bigint, QuickShareReceive>();
+  try {
+    const session = await QuickShareSession.accept(socket);
+    const files = await session.introduction();
+    process.stdout.write(
+      `[receive] quick share from ${session.senderName} (${remoteAddress}), pin ${session.pin}, files=${files.length}\n`
+    );
+    if (context.pairingState.currentCode && !listenOptions.confirmTransfer) {
+      await session.respond(false);
+      reportReceiveFailure(context, "quick share: pair code required");
+      return;
+    }
+    if (listenOptions.confirmTransfer) {
+      for (const file of files) {
+        const decision = await listenOptions.confirmTransfer({
+          from: remoteAddress,
+          relativePath: quickShareFileName(file.name),
+          fileSize: file.size,
+          sha256: "",
+          streaming: false,
+          quickShare: true
+        });
+        const rejection = rejectionMessage(decision);
+        if (rejection) {
+          await session.respond(false);
+          reportReceiveFailure(context, `quick share: ${rejection}`);
+          return;
+        }
+      }
+    }
+    await session.respond(true);
+
+    const pending = new Map(files.map((file) => [file.payloadId, file]));
+    while (pending.size > 0) {
+      const chunk = await session.nextChunk();
+      const file = pending.get(chunk.payloadId);
+      if (!file) {
+        continue;
+      }
+      let entry = receiving.get(chunk.payloadId);
+      if (!entry) {
+        const relativePath = quickShareFileName(file.name);
+        const paths = await selectStreamReceivePaths(context.outputDir, relativePath);
+        await prepareOutputParent(context.outputDir, paths.finalPath);
+        entry = {
+          ...paths,
+          stream: createWriteStream(paths.tempPath),
+          received: 0,
+          progress: trackReceive(
+            context,
+            createProgressEmitState("recv", relativePath, file.size, Date.now()),
+            remoteAddress
+          )
+        };
+        receiving.set(chunk.payloadId, entry);
+      }
+      if (chunk.offset !== entry.received) {
+        throw new Error("chunk out of order");
+      }
+      entry.received += chunk.body.length;
+      if (entry.received > file.size) {
+        throw new Error("payload exceeds declared file size");
+      }
+      if (chunk.body.length > 0 && !entry.stream.write(chunk.body)) {
+        await once(entry.stream, "drain");
+      }
+      emitProgress(entry.progress, entry.received);
+      if (!chunk.last) {
+        continue;
+      }
+      if (entry.received !== file.size) {
+        throw new Error(`size mismatch: expected ${file.size}, got ${entry.received}`);
+      }
+      entry.stream.end();
+      await finished(entry.stream);
+      receiving.delete(chunk.payloadId);
+      pending.delete(chunk.payloadId);
+      const savedPath = await promoteReceivedFile(entry.tempPath, entry.finalPath);
+      emitProgress(entry.progress, entry.received, true);
+      process.stdout.write("\n");
+      reportSaved(context, savedPath, false, remoteAddress, undefined, "", true);
+    }
+    await session.close();
+  } catch (err) {
+    for (const entry of receiving.values()) {
+      entry.stream.destroy();
+      await fsPromises.rm(entry.tempPath, { force: true });
+    }
+    reportReceiveFailure(context, `quick share: ${(err as Error).message}`);
+    socket.destroy();
+  }
+}
+
+const BATCH_DIR_PREFIX = ".local-sent-batch-";
+const BATCH_IDLE_TIMEOUT_MS = 10 * 60 * 1000;
+
+function savedVerb(context: ReceiverContext): string {
+  return context.batch ? "staged" : "saved";
+}
+
+/**
+ * The sender's note, trimmed to the protocol limits; `undefined` when
+ * nothing usable is left. Line breaks are dropped so a note cannot fake log
+ * lines.
+ */
+export function sanitizeTransferNote(note: TransferNote | undefined): TransferNote | undefined {
+  if (!note || typeof note !== "object") {
+    return undefined;
+  }
+  const text =
+    typeof note.text === "string" ? note.text.replace(/[\r\n]+/g, " ").trim().slice(0, MAX_NOTE_CHARS) : "";
+  const tags = Array.isArray(note.tags)
+    ? [
+        ...new Set(
+          note.tags
+            .filter((tag): tag is string => typeof tag === "string")
+            .map((tag) => tag.replace(/\s+/g, " ").trim().slice(0, MAX_TAG_CHARS))
+            .filter(Boolean)
+        )
+      ].slice(0, MAX_NOTE_TAGS)
+    : [];
+  if (!text && tags.length === 0) {
+    return undefined;
+  }
+  return { ...(text ? { text } : {}), ...(tags.length > 0 ? { tags } : {}) };
+}
+
+function formatTransferNote(note: TransferNote | undefined): string {
+  if (!note) {
+    return "";
+  }
+  const parts = [note.text ? `"${note.text}"` : "", ...(note.tags ?? []).map((tag) => `#${tag}`)];
+  return ` — ${parts.filter(Boolean).join(" ")}`;
+}
+
+function reportSaved(
+  context: ReceiverContext,
+  savedPath: string,
+  alreadyHad: boolean,
+  from: string,
+  rawNote: TransferNote | undefined,
+  suffix = "",
+  quickShare = false
+): void {
+  const note = sanitizeTransferNote(rawNote);
+  process.stdout.write(
+    alreadyHad
+      ? `[receive] already have ${savedPath}${formatTransferNote(note)}\n`
+      : `[receive] ${savedVerb(context)} ${savedPath}${suffix}${formatTransferNote(note)}\n`
+  );
+  if (!context.batch) {
+    emitIpc({ type: "saved", path: savedPath, alreadyHad, from, note, ...(quickShare ? { quickShare } : {}) });
+  }
+}
+
+/** Inside an atomic batch only the rollback reports a failure. */
+function reportReceiveFailure(context: ReceiverContext, message: string): void {
+  process.stdout.write(
+    context.batch ? `\n[receive] batch ${context.batch.id}: ${message}\n` : `\n[receive] failed: ${message}\n`
+  );
+  if (!context.batch) {
+    emitIpc({ type: "receiveFailed", message });
+  }
+}
+
+/**
+ * A complete copy the receiver already has stands in for the transfer. An
+ * atomic batch copies it into the staging folder so the committed tree is
+ * whole.
+ */
+async function adoptKnownCopy(context: ReceiverContext, knownPath: string, finalPath: string): Promise<string> {
+  if (!context.batch) {
+    return knownPath;
+  }
+  await prepareOutputParent(context.outputDir, finalPath);
+  await fsPromises.copyFile(knownPath, finalPath);
+  return finalPath;
+}
+
+function armBatchTimer(batches: Map<string, ReceiveBatch>, batch: ReceiveBatch): void {
+  clearTimeout(batch.timer);
+  batch.timer = setTimeout(() => {
+    void rollbackBatch(batches, batch, "sender stopped responding");
+  }, BATCH_IDLE_TIMEOUT_MS);
+}
+
+async function rollbackBatch(batches: Map<string, ReceiveBatch>, batch: ReceiveBatch, reason: string): Promise<void> {
+  clearTimeout(batch.timer);
+  if (!batches.delete(batch.id)) {
+    return;
+  }
+  await fsPromises.rm(batch.stagingDir, { recursive: true, force: true }).catch(() => undefined);
+  process.stdout.write(`\n[receive] failed: batch ${batch.id} rolled back: ${reason}\n`);
+  emitIpc({ type: "receiveFailed", message: `batch ${batch.id} rolled back: ${reason}` });
+}
+
+/** Relative paths of everything staged below `dir`, files and links alike. */
+async function listStaged(dir: string, prefix = ""): Promise<Array<{ relativePath: string; isFile: boolean }>> {
+  const items: Array<{ relativePath: string; isFile: boolean }> = [];
+  for (const entry of await fsPromises.readdir(dir, { withFileTypes: true })) {
+    const relativePath = prefix ? `${prefix}/${entry.name}` : entry.name;
+    if (entry.isDirectory()) {
+      items.push(...(await listStaged(join(dir, entry.name), relativePath)));
+    } else if (!entry.name.endsWith(TEMP_SUFFIX)) {
+      items.push({ relativePath, isFile: entry.isFile() });
+    }
+  }
+  return items;
+}
+
+/**
+ * Moves every staged entry into the output directory. A failed move puts
+ * the ones already moved back, so the batch still lands all or nothing.
+ */
+async function commitBatch(batch: ReceiveBatch, context: ReceiverContext): Promise<number> {
+  clearTimeout(batch.timer);
+  const moved: Array<{ from: string; to: string; isFile: boolean }> = [];
+  try {
+    for (const item of await listStaged(batch.stagingDir)) {
+      const from = resolve(batch.stagingDir, ...item.relativePath.split("/"));
+      const target = resolveOutputPath(context.outputDir, item.relativePath);
+      let to = target;
+      for (let index = 1; await pathExists(to); index += 1) {
+        if (index >= MAX_DUPLICATE_SUFFIX_ATTEMPTS) {
+          throw new Error("failed to allocate receive target path");
+        }
+        to = duplicatePathWithIndex(target, index);
+      }
+      await prepareOutputParent(context.outputDir, to);
+      await fsPromises.rename(from, to);
+      moved.push({ from, to, isFile: item.isFile });
+    }
+  } catch (err) {
+    for (const move of moved.reverse()) {
+      await fsPromises.rename(move.to, move.from).catch(() => undefined);
+    }
+    await rollbackBatch(context.batches, batch, `commit failed: ${(err as Error).message}`);
+    throw err;
+  }
+
+  context.batches.delete(batch.id);
+  await fsPromises.rm(batch.stagingDir, { recursive: true, force: true }).catch(() => undefined);
+  for (const move of moved) {
+    if (move.isFile) {
+      process.stdout.write(`[receive] saved ${move.to}${formatTransferNote(batch.note)}\n`);
+      emitIpc({ type: "saved", path: move.to, alreadyHad: false, from: batch.from, note: batch.note });
+    }
+  }
+  process.stdout.write(`[receive] batch ${batch.id} committed: ${moved.length} file(s)\n`);
+  return moved.length;
+}
+
+async function receiveBatchControl(
+  socket: Socket,
+  control: BatchControl,
+  context: ReceiverContext,
+  remoteAddress: string
+): Promise<void> {
+  const reply = (ack: Omit<AckMessage, "type">): void => {
+    socket.end(encodeJsonLine({ type: "ack", ...ack } satisfies AckMessage));
+  };
+  if (typeof control.batchId !== "string" || !/^[A-Za-z0-9-]{1,64}$/.test(control.batchId)) {
+    reply({ ok: false, message: "invalid batch id" });
+    return;
+  }
+  if (!checkPairCode(context, remoteAddress, control.pairCode)) {
+    reply({ ok: false, message: "pair code mismatch" });
+    return;
+  }
+
+  const batch = context.batches.get(control.batchId);
+  try {
+    if (control.action === "begin") {
+      if (context.listenOptions.webdav) {
+        throw new Error("atomic batches cannot be saved to WebDAV");
+      }
+      if (batch) {
+        throw new Error("batch already open");
+      }
+      const opened: ReceiveBatch = {
+        id: control.batchId,
+        stagingDir: join(context.outputDir, `${BATCH_DIR_PREFIX}${control.batchId}`),
+        from: remoteAddress
+      };
+      await fsPromises.mkdir(opened.stagingDir, { recursive: true });
+      context.batches.set(opened.id, opened);
+      armBatchTimer(context.batches, opened);
+      process.stdout.write(`[receive] batch ${opened.id} opened\n`);
+      reply({ ok: true });
+    } else if (control.action === "commit") {
+      if (!batch) {
+        throw new Error("unknown batch");
+      }
+      const files = await commitBatch(batch, context);
+      reply({ ok: true, message: `committed ${files} file(s)` });
+    } else if (control.action === "abort") {
+      if (batch) {
+        await rollbackBatch(context.batches, batch, control.reason?.trim() || "aborted by sender");
+      }
+      reply({ ok: true });
+    } else {
+      throw new Error("invalid batch action");
+    }
+  } catch (err) {
+    reply({ ok: false, message: (err as Error).message });
+  }
+}
+
+/**
+ * Maps `<share>/<relative path>` onto a shared folder. Symlinks inside the
+ * share are followed only while they stay within it.
+ */
+async function resolveSharedPath(shares: Record<string, string>, requestPath: string): Promise<string> {
+  const [shareName, ...rest] = normalizeTransferPath(requestPath).split("/");
+  const shareRoot = Object.prototype.hasOwnProperty.call(shares, shareName) ? shares[shareName] : undefined;
+  if (!shareRoot) {
+    throw new Error(`no shared folder named ${shareName}`);
+  }
+  const root = await fsPromises.realpath(shareRoot);
+  const target = await fsPromises.realpath(join(root, ...rest)).catch(() => {
+    throw new Error("not found");
+  });
+  if (!isWithinDirectory(root, target)) {
+    throw new Error("not found");
+  }
+  return target;
+}
+
+async function listSharedFolder(folder: string): Promise<ShareEntry[]> {
+  const entries: ShareEntry[] = [];
+  for (const dirent of await fsPromises.readdir(folder, { withFileTypes: true })) {
+    // Links are left out rather than resolved; `fetch` re-checks anything it serves.
+    if (!dirent.isFile() && !dirent.isDirectory()) {
+      continue;
+    }
+    const stat = await fsPromises.stat(join(folder, dirent.name)).catch(() => null);
+    if (!stat) {
+      continue;
+    }
+    entries.push({
+      name: dirent.name,
+      isDir: stat.isDirectory(),
+      size: stat.isDirectory() ? 0 : stat.size,
+      mtimeMs: Math.round(stat.mtimeMs)
+    });
+  }
+  return entries.sort((a, b) => Number(b.isDir) - Number(a.isDir) || a.name.localeCompare(b.name));
+}
+
+function receiveTextMessage(
+  socket: Socket,
+  message: TextMessage,
+  context: ReceiverContext,
+  remoteAddress: string
+): void {
+  const reply = (ack: Omit<AckMessage, "type">): void => {
+    socket.end(encodeJsonLine({ type: "ack", ...ack } satisfies AckMessage));
+  };
+  if (!checkPairCode(context, remoteAddress, message.pairCode)) {
+    reply({ ok: false, message: "pair code mismatch" });
+    return;
+  }
+  const text = typeof message.text === "string" ? message.text.trim() : "";
+  if (!text || text.length > MAX_MESSAGE_CHARS) {
+    reply({ ok: false, message: "invalid message" });
+    return;
+  }
+  // Names are for display only; strip anything that could fake a log line.
+  const name = (typeof message.from === "string" ? message.from : "")
+    .replace(/[\r\n]+/g, " ")
+    .trim()
+    .slice(0, 64);
+  process.stdout.write(`[message] ${name || remoteAddress} (${remoteAddress}): ${text.replace(/\r?\n/g, " / ")}\n`);
+  emitIpc({ type: "message", from: remoteAddress, name: name || remoteAddress, text });
+  reply({ ok: true });
+}
+
+/**
+ * Clipboard text is handed to the desktop app to apply; the CLI on its own
+ * prints it. Peers not listed in `clipboardFrom` are turned away.
+ */
+function receiveClipboard(
+  socket: Socket,
+  message: ClipboardMessage,
+  context: ReceiverContext,
+  remoteAddress: string
+): void {
+  const reply = (ack: Omit<AckMessage, "type">): void => {
+    socket.end(encodeJsonLine({ type: "ack", ...ack } satisfies AckMessage));
+  };
+  if (!checkPairCode(context, remoteAddress, message.pairCode)) {
+    reply({ ok: false, message: "pair code mismatch" });
+    return;
+  }
+  if (!context.clipboardFrom.includes(remoteAddress)) {
+    reply({ ok: false, message: "clipboard sync is off" });
+    return;
+  }
+  const text = typeof message.text === "string" ? message.text : "";
+  if (!text || Buffer.byteLength(text, "utf8") > MAX_CLIPBOARD_BYTES) {
+    reply({ ok: false, message: "invalid clipboard text" });
+    return;
+  }
+  if (ipcEnabled()) {
+    emitIpc({ type: "clipboard", from: remoteAddress, text });
+  } else {
+    process.stdout.write(`[clipboard] ${remoteAddress}: ${text.replace(/\r?\n/g, " / ")}\n`);
+  }
+  reply({ ok: true });
+}
+
+/**
+ * Answers `send --dry-run` with the checks a real send would meet before
+ * its first byte: the pair code, then room for the announced bytes in the
+ * output folder. WebDAV receivers cannot tell their free space.
+ */
+async function answerProbe(
+  socket: Socket,
+  message: ProbeMessage,
+  context: ReceiverContext,
+  remoteAddress: string
+): Promise<void> {
+  const reply = (ack: Omit<AckMessage, "type">): void => {
+    socket.end(encodeJsonLine({ type: "ack", ...ack } satisfies AckMessage));
+  };
+  if (!checkPairCode(context, remoteAddress, message.pairCode)) {
+    reply({ ok: false, message: "pair code mismatch" });
+    return;
+  }
+  const totalBytes = Number(message.totalBytes);
+  if (!Number.isSafeInteger(totalBytes) || totalBytes < 0) {
+    reply({ ok: false, message: "invalid dry run" });
+    return;
+  }
+  if (context.listenOptions.webdav) {
+    reply({ ok: true });
+    return;
+  }
+  let freeBytes: number;
+  try {
+    const stats = await fsPromises.statfs(context.outputDir);
+    freeBytes = stats.bavail * stats.bsize;
+  } catch {
+    reply({ ok: true });
+    return;
+  }
+  if (totalBytes > freeBytes) {
+    reply({
+      ok: false,
+      message: `not enough free space: ${formatBytes(totalBytes)} needed, ${formatBytes(freeBytes)} free`,
+      freeBytes
+    });
+    return;
+  }
+  reply({ ok: true, freeBytes });
+}
+
+/** The shares `remoteAddress` may see: open ones plus those allowing it. */
+function visibleShares(context: ReceiverContext, remoteAddress: string): Record<string, string> {
+  const visible: Record<string, string> = {};
+  for (const [name, dir] of Object.entries(context.shares)) {
+    const allowed = context.shareAllow[name];
+    if (!allowed || allowed.includes(remoteAddress)) {
+      visible[name] = dir;
     }
-    failed = true;
-    process.stdout.write(`\n[receive] failed: ${message}\n`);
+  }
+  return visible;
+}
 
-    if (fileStream) {
-      fileStream.destroy();
-    }
+/**
+ * Every share request that passed pairing ends up in the receive log;
+ * `allowed` is false when the peer asked for a share it may not see.
+ */
+function reportShareAccess(
+  from: string,
+  action: ShareRequest["action"],
+  path: string,
+  allowed: boolean,
+  bytes?: number
+): void {
+  const label = path || "(shares)";
+  process.stdout.write(
+    allowed
+      ? `[share] ${from} ${action === "fetch" ? "sending" : "listed"} ${label}${bytes === undefined ? "" : ` (${formatBytes(bytes)})`}\n`
+      : `[share] denied ${from}: ${label}\n`
+  );
+  emitIpc({ type: "shareAccess", from, action, path, allowed, bytes });
+}
 
-    if (cleanup && tempPath) {
-      try {
-        await fsPromises.rm(tempPath, { force: true });
-      } catch {
-        // Ignore cleanup failures.
+async function serveShareRequest(
+  socket: Socket,
+  request: ShareRequest,
+  context: ReceiverContext,
+  remoteAddress: string
+): Promise<void> {
+  const reply = (body: Omit<ShareReply, "type">): void => {
+    socket.end(encodeJsonLine({ type: "shares", ...body } satisfies ShareReply));
+  };
+  if (Object.keys(context.shares).length === 0) {
+    reply({ ok: false, message: "receiver shares no folders" });
+    return;
+  }
+  if (!checkPairCode(context, remoteAddress, request.pairCode)) {
+    reply({ ok: false, message: "pair code mismatch" });
+    return;
+  }
+  const shares = visibleShares(context, remoteAddress);
+
+  let headerSent = false;
+  try {
+    if (request.action === "list" && !request.path) {
+      const entries: ShareEntry[] = [];
+      for (const name of Object.keys(shares).sort()) {
+        const stat = await fsPromises.stat(shares[name]).catch(() => null);
+        entries.push({ name, isDir: true, size: 0, mtimeMs: Math.round(stat?.mtimeMs ?? 0) });
       }
+      reportShareAccess(remoteAddress, "list", "", true);
+      reply({ ok: true, entries });
+      return;
     }
 
-    const messageBody =
-      phase === "before-ready"
-        ? ({
-            type: "ready",
-            ok: false,
-            offset: 0,
-            message
-          } satisfies ReadyMessage)
-        : ({
-            type: "ack",
-            ok: false,
-            message
-          } satisfies AckMessage);
-
-    if (socket.writable) {
-      try {
-        socket.end(encodeJsonLine(messageBody));
-        setImmediate(() => {
-          if (!socket.destroyed) {
-            socket.destroy();
-          }
-        });
-      } catch {
-        socket.destroy();
+    const shareName = normalizeTransferPath(request.path).split("/")[0];
+    if (Object.prototype.hasOwnProperty.call(context.shares, shareName) && !(shareName in shares)) {
+      // Answered like a missing share, so restricted shares stay hidden.
+      reportShareAccess(remoteAddress, request.action, request.path, false);
+    }
+    const target = await resolveSharedPath(shares, request.path);
+    const stat = await fsPromises.stat(target);
+    if (request.action === "list") {
+      if (!stat.isDirectory()) {
+        throw new Error("not a folder");
+      }
+      reportShareAccess(remoteAddress, "list", request.path, true);
+      reply({ ok: true, entries: await listSharedFolder(target) });
+    } else if (request.action === "fetch") {
+      if (!stat.isFile()) {
+        throw new Error("not a file");
+      }
+      const sha256 = await sha256File(target);
+      reportShareAccess(remoteAddress, "fetch", request.path, true, stat.size);
+      headerSent = true;
+      socket.write(encodeJsonLine({ type: "shares", ok: true, fileSize: stat.size, sha256 } satisfies ShareReply));
+      if (stat.size === 0) {
+        socket.end();
+        return;
       }
+      // Bounded so a file growing meanwhile still matches the announced size.
+      await pipeline(createReadStream(target, { start: 0, end: stat.size - 1 }), socket);
     } else {
+      throw new Error("invalid share action");
+    }
+  } catch (err) {
+    if (headerSent) {
+      // The peer notices the short read and discards the partial file.
       socket.destroy();
+    } else if (socket.writable) {
+      reply({ ok: false, message: (err as Error).message });
     }
-  };
+  }
+}
+
+function isWithinDirectory(root: string, candidate: string): boolean {
+  const rel = relative(resolve(stripLongPathPrefix(root)), resolve(stripLongPathPrefix(candidate)));
+  return !rel.startsWith("..") && !isAbsolute(rel);
+}
 
+/** Hard links must name a regular file the receiver already saved. */
+async function checkLinkSource(outputDir: string, link: EntryLink): Promise<string | null> {
+  if (link.type === "symlink") {
+    return isAbsolute(link.target) || /^[A-Za-z]:/.test(link.target) ? "symlink target must be relative" : null;
+  }
+  let real: string;
   try {
-    header = await reader.readLineMessage<TransferHeader>("header");
-    if (header.type !== "header") {
-      await fail("protocol error: expected header");
-      return;
-    }
+    real = await fsPromises.realpath(link.target);
+  } catch {
+    return "hard link target is missing";
+  }
+  if (!isWithinDirectory(await fsPromises.realpath(outputDir), real)) {
+    return "hard link target is outside the output directory";
+  }
+  const stat = await fsPromises.stat(real);
+  return stat.isFile() ? null : "hard link target is not a regular file";
+}
 
-    if (header.version !== 1 || header.fileSize < 0 || !header.sha256) {
-      await fail("invalid header fields");
-      return;
-    }
+/**
+ * Symlinks may only resolve inside the output directory, otherwise a later
+ * entry written through the link could land anywhere on disk. Checked
+ * before the link exists, against the real folder it goes into; targets
+ * that exist are checked by their real path too, so links through other
+ * links count.
+ */
+async function symlinkStaysInside(outputDir: string, linkPath: string, target: string): Promise<boolean> {
+  const root = await fsPromises.realpath(outputDir);
+  const resolved = resolve(await fsPromises.realpath(dirname(linkPath)), target.split("/").join(sep));
+  if (!isWithinDirectory(root, resolved)) {
+    return false;
+  }
+  try {
+    return isWithinDirectory(root, await fsPromises.realpath(resolved));
+  } catch {
+    return true;
+  }
+}
 
-    requiredPairCodeForThisTransfer = pairingState.currentCode;
-    if (!isPairCodeAccepted(pairingState, header.pairCode)) {
-      await fail("pair code mismatch");
-      return;
-    }
+/**
+ * Creates the folder `filePath` goes into. `resolveOutputPath` only checks
+ * the path text, so the real folder is checked as well: a symlink received
+ * earlier must not redirect a write outside `outputDir`.
+ */
+async function prepareOutputParent(outputDir: string, filePath: string): Promise<void> {
+  const root = await fsPromises.realpath(outputDir);
+  const parent = dirname(filePath);
+  let existing = parent;
+  while (!(await pathExists(existing)) && dirname(existing) !== existing) {
+    existing = dirname(existing);
+  }
+  if (!isWithinDirectory(root, await fsPromises.realpath(existing))) {
+    throw new Error("path escapes output directory");
+  }
+  await fsPromises.mkdir(parent, { recursive: true });
+  if (!isWithinDirectory(root, await fsPromises.realpath(parent))) {
+    throw new Error("path escapes output directory");
+  }
+}
 
-    const receivePathSelection = await selectReceivePaths({
-      outputDir,
-      relativePath: header.relativePath,
-      expectedSha256: header.sha256,
-      expectedSize: header.fileSize
-    });
-    targetPath = receivePathSelection.finalPath;
-    tempPath = receivePathSelection.tempPath;
-    await fsPromises.mkdir(dirname(targetPath), { recursive: true });
+async function receiveLink(
+  socket: Socket,
+  header: TransferHeader,
+  remoteAddress: string,
+  context: ReceiverContext
+): Promise<void> {
+  const refuse = (message: string): void => {
+    reportReceiveFailure(context, message);
+    endWithFailure(socket, "before-ready", message);
+  };
+  const link = header.link;
+  const validLink =
+    (link?.type === "symlink" || link?.type === "hardlink") && typeof link.target === "string" && link.target !== "";
+  if (!link || !validLink) {
+    refuse("invalid header fields");
+    return;
+  }
+  const requiredPairCode = context.pairingState.currentCode;
+  if (!checkPairCode(context, remoteAddress, header.pairCode)) {
+    refuse("pair code mismatch");
+    return;
+  }
 
-    if (listenOptions.confirmTransfer) {
-      const decision = await listenOptions.confirmTransfer({
+  let savedPath: string;
+  try {
+    savedPath = (await selectStreamReceivePaths(context.outputDir, header.relativePath)).finalPath;
+    const problem = await checkLinkSource(context.outputDir, link);
+    if (problem) {
+      refuse(problem);
+      return;
+    }
+    if (context.listenOptions.confirmTransfer) {
+      const decision = await context.listenOptions.confirmTransfer({
         from: remoteAddress,
         relativePath: header.relativePath,
-        fileSize: header.fileSize
+        fileSize: 0,
+        sha256: "",
+        streaming: false
       });
-      const accepted = typeof decision === "boolean" ? decision : decision.accept;
-      if (!accepted) {
-        const message =
-          typeof decision === "boolean"
-            ? "receiver rejected transfer"
-            : decision.message?.trim() || "receiver rejected transfer";
-        await fail(message);
+      const rejection = rejectionMessage(decision);
+      if (rejection) {
+        refuse(rejection);
         return;
       }
     }
-
-    resumedFrom = await decideResumeOffset({
-      targetPath: tempPath,
-      expectedSha256: header.sha256,
-      expectedSize: header.fileSize,
-      hasher
-    });
-    received = resumedFrom;
-    const recvProgressState = createProgressEmitState(`[recv ${header.relativePath}]`, header.fileSize, startedAt);
-
-    if (resumedFrom < header.fileSize) {
-      fileStream = createWriteStream(tempPath, resumedFrom > 0 ? { flags: "r+", start: resumedFrom } : { flags: "w" });
-      fileStream.on("error", () => {
-        void fail("cannot write target file");
-      });
-    }
-
-    socket.write(
-      encodeJsonLine({
-        type: "ready",
-        ok: true,
-        offset: resumedFrom,
-        savedPath: targetPath
-      } satisfies ReadyMessage)
-    );
-    phase = "receiving";
-
-    while (received < header.fileSize) {
-      const chunk = await reader.readChunk();
-      if (chunk === null) {
-        break;
+    await prepareOutputParent(context.outputDir, savedPath);
+    if (link.type === "symlink") {
+      if (!(await symlinkStaysInside(context.outputDir, savedPath, link.target))) {
+        refuse("symlink target escapes output directory");
+        return;
       }
-
-      await writePayload({
-        payload: chunk,
-        header,
-        fileStream,
-        hasher,
-        progressState: recvProgressState,
-        receivedRef: {
-          get: () => received,
-          set: (v: number) => {
-            received = v;
-          }
-        }
-      });
+      await fsPromises.symlink(link.target.split("/").join(sep), savedPath);
+    } else {
+      await fsPromises.link(link.target, savedPath);
     }
+    await applyEntryMetadata(savedPath, header.metadata, context.listenOptions, link.type === "symlink");
+  } catch (err) {
+    refuse((err as Error).message);
+    return;
+  }
 
-    if (received !== header.fileSize) {
-      await fail(`size mismatch: expected ${header.fileSize}, got ${received}`);
-      return;
-    }
+  socket.write(encodeJsonLine({ type: "ready", ok: true, offset: 0, savedPath } satisfies ReadyMessage));
+  socket.end(
+    encodeJsonLine({
+      type: "ack",
+      ok: true,
+      receivedBytes: 0,
+      savedPath,
+      nextPairCode: nextPairCodeAfterTransfer(requiredPairCode, context.pairingState, context.listenOptions)
+    } satisfies AckMessage)
+  );
+  process.stdout.write(`[receive] linked ${savedPath} -> ${link.target}\n`);
+}
 
-    if (fileStream) {
-      fileStream.end();
-      await finished(fileStream);
+/** Best effort: not every platform or filesystem keeps modes and times. */
+async function applyEntryMetadata(
+  filePath: string,
+  metadata: EntryMetadata | undefined,
+  listenOptions: ListenOptions,
+  isSymlink = false
+): Promise<void> {
+  if (!metadata) {
+    return;
+  }
+  if (listenOptions.preserveXattrs && metadata.xattrs && !isSymlink) {
+    if (metadata.platform !== process.platform) {
+      process.stdout.write(`[receive] ${filePath}: extended attributes from ${metadata.platform} skipped\n`);
+    } else {
+      const failed = await writeXattrs(filePath, metadata.xattrs);
+      if (failed.length > 0) {
+        process.stdout.write(`[receive] ${filePath}: could not restore ${failed.join(", ")}\n`);
+      }
     }
-
-    const digest = hasher.digest("hex");
-    if (digest !== header.sha256) {
-      await fail("sha256 mismatch", true);
-      return;
+  }
+  if (!listenOptions.preserveMetadata) {
+    return;
+  }
+  try {
+    if (!isSymlink && Number.isInteger(metadata.mode)) {
+      await fsPromises.chmod(filePath, metadata.mode! & 0o777);
     }
-    const savedPath = await promoteReceivedFile(tempPath, targetPath);
-
-    phase = "done";
-    let nextPairCode: string | undefined;
-    if (requiredPairCodeForThisTransfer && listenOptions.rotatePairCodePerTransfer && listenOptions.generatePairCode) {
-      nextPairCode = rotatePairCodeOnce(pairingState, listenOptions.generatePairCode);
-      if (nextPairCode) {
-        listenOptions.onPairCodeChange?.(nextPairCode, "once");
-      }
-    } else if (requiredPairCodeForThisTransfer) {
-      nextPairCode = pairingState.currentCode ?? undefined;
+    if (Number.isFinite(metadata.mtimeMs)) {
+      const mtime = new Date(metadata.mtimeMs!);
+      await (isSymlink ? fsPromises.lutimes(filePath, mtime, mtime) : fsPromises.utimes(filePath, mtime, mtime));
     }
-
-    emitProgress(recvProgressState, received, true);
-    process.stdout.write("\n");
-    socket.end(
-      encodeJsonLine({
-        type: "ack",
-        ok: true,
-        sha256: digest,
-        receivedBytes: received,
-        savedPath,
-        resumedFrom,
-        nextPairCode
-      } satisfies AckMessage)
-    );
-    process.stdout.write(`[receive] saved ${savedPath}\n`);
-  } catch (err) {
-    await fail((err as Error).message);
-  } finally {
-    reader.dispose();
-    pairingState.activeTransfers = Math.max(0, pairingState.activeTransfers - 1);
+  } catch {
+    // Keep the received content even if its metadata cannot be applied.
   }
 }
 
@@ -754,6 +3391,24 @@ async function selectReceivePaths(args: {
   throw new Error("failed to allocate receive target path");
 }
 
+async function selectStreamReceivePaths(outputDir: string, relativePath: string): Promise<ReceivePathSelection> {
+  const basePath = resolveOutputPath(outputDir, relativePath);
+  // Streams and parallel transfers cannot resume, so every one gets its own
+  // temp file next to the first free final name.
+  const tag = `stream-${process.pid}-${Date.now().toString(36)}-${Math.random().toString(36).slice(2, 8)}`;
+  for (let index = 0; index < MAX_DUPLICATE_SUFFIX_ATTEMPTS; index += 1) {
+    const candidateFinalPath = duplicatePathWithIndex(basePath, index);
+    if (!(await pathExists(candidateFinalPath))) {
+      return {
+        finalPath: candidateFinalPath,
+        tempPath: `${candidateFinalPath}.${tag}${TEMP_SUFFIX}`
+      };
+    }
+  }
+
+  throw new Error("failed to allocate receive target path");
+}
+
 async function promoteReceivedFile(tempPath: string, preferredFinalPath: string): Promise<string> {
   if (tempPath === preferredFinalPath) {
     return preferredFinalPath;
@@ -861,20 +3516,127 @@ async function decideResumeOffset(args: {
 }
 
 const PROGRESS_EMIT_INTERVAL_MS = 80;
+const ACTIVE_TRANSFER_IDLE_MS = 30_000;
 const PROGRESS_EMIT_DELTA_PERCENT = 0.35;
 
-function createProgressEmitState(prefix: string, totalBytes: number, startedAt: number): ProgressEmitState {
+function createProgressEmitState(
+  direction: "send" | "recv",
+  path: string,
+  totalBytes: number,
+  startedAt: number,
+  batch?: BatchPosition
+): ProgressEmitState {
   return {
-    prefix,
+    batch,
+    direction,
+    path,
+    prefix: `[${direction} ${path}]`,
     totalBytes,
     startedAt,
     lastEmitAt: 0,
-    lastPercent: -1
+    lastPercent: -1,
+    bytes: 0,
+    updatedAt: startedAt,
+    analytics: new ProgressAnalytics(startedAt)
+  };
+}
+
+/** Starts listing a receive in `ReceiverHandle.transfers`. */
+function trackReceive(context: ReceiverContext, state: ProgressEmitState, from: string): ProgressEmitState {
+  state.from = from;
+  context.activeReceives.add(state);
+  return state;
+}
+
+/**
+ * Bytes of the whole batch done so far. The file's share is scaled to its
+ * size, since a sparse file's progress counts only its data.
+ */
+function batchBytes(batch: BatchPosition, transferredBytes: number, total: number): number {
+  const fraction = total === 0 ? 1 : Math.min(1, transferredBytes / total);
+  return batch.doneBytes + Math.round(fraction * batch.fileBytes);
+}
+
+function renderBatchPosition(batch: BatchPosition, transferredBytes: number, total: number): string {
+  const overall = batch.totalBytes === 0 ? 100 : (batchBytes(batch, transferredBytes, total) / batch.totalBytes) * 100;
+  return ` [${batch.index}/${batch.count} files, ${overall.toFixed(1)}% overall]`;
+}
+
+function emitIpcProgress(state: ProgressEmitState, transferredBytes: number, total: number | null): void {
+  const batch = state.batch;
+  emitIpc({
+    type: "progress",
+    direction: state.direction,
+    path: state.path,
+    bytes: transferredBytes,
+    total,
+    ...state.analytics.snapshot(total),
+    ...(batch && total !== null
+      ? {
+          batch: {
+            index: batch.index,
+            count: batch.count,
+            bytes: batchBytes(batch, transferredBytes, total),
+            total: batch.totalBytes
+          }
+        }
+      : {})
+  });
+}
+
+/**
+ * Progress is only reported as bytes arrive, so without this a stalled
+ * transfer would go quiet instead of saying so. Reports a stall once; the
+ * next advance re-arms the watch.
+ */
+function watchForStall(state: ProgressEmitState): void {
+  if (state.stallTimer) {
+    return;
+  }
+  const check = (): void => {
+    const idleMs = state.analytics.idleFor();
+    if (idleMs < STALL_AFTER_MS) {
+      state.stallTimer = setTimeout(check, STALL_AFTER_MS - idleMs).unref();
+      return;
+    }
+    state.stallTimer = undefined;
+    state.analytics.observe(state.bytes);
+    reportProgress(state, state.bytes, true);
   };
+  state.stallTimer = setTimeout(check, STALL_AFTER_MS).unref();
 }
 
+/** `force` marks the last report of a transfer, which ends the stall watch. */
 function emitProgress(state: ProgressEmitState, transferredBytes: number, force = false): void {
   const now = Date.now();
+  const advanced = transferredBytes > state.bytes;
+  state.bytes = transferredBytes;
+  state.updatedAt = now;
+  state.analytics.observe(transferredBytes, now);
+  if (force) {
+    clearTimeout(state.stallTimer);
+    state.stallTimer = undefined;
+  } else if (advanced) {
+    watchForStall(state);
+  }
+  reportProgress(state, transferredBytes, force);
+}
+
+function reportProgress(state: ProgressEmitState, transferredBytes: number, force: boolean): void {
+  const now = Date.now();
+  if (state.totalBytes < 0) {
+    if (force || now - state.lastEmitAt >= PROGRESS_EMIT_INTERVAL_MS) {
+      state.lastEmitAt = now;
+      if (ipcEnabled()) {
+        emitIpcProgress(state, transferredBytes, null);
+        return;
+      }
+      process.stdout.write(
+        `\r${renderStreamProgress(state.prefix, transferredBytes, state.analytics.snapshot(null, now))}`
+      );
+    }
+    return;
+  }
   const total = Math.max(0, state.totalBytes);
   const ratio = total === 0 ? 100 : (transferredBytes / total) * 100;
   const safePercent = Math.max(0, Math.min(100, ratio));
@@ -886,7 +3648,15 @@ function emitProgress(state: ProgressEmitState, transferredBytes: number, force
 
   state.lastEmitAt = now;
   state.lastPercent = safePercent;
-  process.stdout.write(`\r${renderProgress(state.prefix, transferredBytes, total, state.startedAt)}`);
+  if (ipcEnabled()) {
+    emitIpcProgress(state, transferredBytes, total);
+    return;
+  }
+  process.stdout.write(
+    `\r${renderProgress(state.prefix, transferredBytes, total, state.analytics.snapshot(total, now))}${
+      state.batch ? renderBatchPosition(state.batch, transferredBytes, total) : ""
+    }${state.detail?.() ?? ""}`
+  );
 }
 
 async function streamFileRange(args: {
@@ -895,12 +3665,110 @@ async function streamFileRange(args: {
   startOffset: number;
   totalBytes: number;
   label: string;
-}): Promise<void> {
-  const { socket, filePath, startOffset, totalBytes, label } = args;
+  rttMs: number;
+  position?: BatchPosition;
+}): Promise<TransferTuning> {
+  const { socket, filePath, startOffset, totalBytes, label, rttMs } = args;
   const startedAt = Date.now();
   let sent = startOffset;
-  const progressState = createProgressEmitState(`[send ${label}]`, totalBytes, startedAt);
-  const stream = createReadStream(filePath, { start: startOffset });
+  const tuner = new TransferTuner(rttMs);
+  const progressState = createProgressEmitState("send", label, totalBytes, startedAt, args.position);
+
+  await writeFileRange({
+    socket,
+    filePath,
+    start: startOffset,
+    end: totalBytes,
+    tuner,
+    onBytes: (bytes) => {
+      sent += bytes;
+      emitProgress(progressState, sent);
+    }
+  });
+  emitProgress(progressState, sent, true);
+  process.stdout.write("\n");
+  const tuning = tuner.stats();
+  process.stdout.write(`[send ${label}] tuning: ${renderTuning(tuning)}\n`);
+  return tuning;
+}
+
+/**
+ * Copies `[start, end)` of a file to the socket, reading `tuner.chunkBytes`
+ * at a time and letting up to `tuner.windowBytes` queue on the socket before
+ * waiting for it to drain.
+ */
+async function writeFileRange(args: {
+  socket: Socket;
+  filePath: string;
+  start: number;
+  end: number;
+  tuner: TransferTuner;
+  onBytes: (bytes: number) => void;
+}): Promise<void> {
+  const { socket, filePath, start, end, tuner, onBytes } = args;
+  const handle = await fsPromises.open(filePath, "r");
+  try {
+    let position = start;
+    while (position < end) {
+      if (socket.destroyed) {
+        throw new Error("connection closed while sending");
+      }
+      const length = Math.min(tuner.chunkBytes, end - position);
+      const buffer = Buffer.allocUnsafe(length);
+      const { bytesRead } = await handle.read(buffer, 0, length, position);
+      if (bytesRead === 0) {
+        throw new Error("file shrank while sending");
+      }
+      position += bytesRead;
+      socket.write(bytesRead === length ? buffer : buffer.subarray(0, bytesRead));
+      onBytes(bytesRead);
+      tuner.record(bytesRead);
+      if (socket.writableLength >= tuner.windowBytes) {
+        await waitDrain(socket);
+      }
+    }
+  } catch (err) {
+    socket.destroy();
+    throw err;
+  } finally {
+    await handle.close();
+  }
+}
+
+function waitDrain(socket: Socket): Promise<void> {
+  return new Promise((resolve, reject) => {
+    const cleanup = (): void => {
+      socket.removeListener("drain", onDrain);
+      socket.removeListener("close", onClose);
+      socket.removeListener("error", onError);
+    };
+    const onDrain = (): void => {
+      cleanup();
+      resolve();
+    };
+    const onClose = (): void => {
+      cleanup();
+      reject(new Error("connection closed while sending"));
+    };
+    const onError = (err: Error): void => {
+      cleanup();
+      reject(err);
+    };
+    socket.once("drain", onDrain);
+    socket.once("close", onClose);
+    socket.once("error", onError);
+  });
+}
+
+async function streamInput(args: {
+  socket: Socket;
+  input: Readable;
+  label: string;
+}): Promise<{ sentBytes: number; digest: string }> {
+  const { socket, input, label } = args;
+  const hasher = createHash("sha256");
+  let sent = 0;
+  const progressState = createProgressEmitState("send", label, STREAM_FILE_SIZE, Date.now());
 
   await new Promise<void>((resolve, reject) => {
     const onError = (err: Error): void => {
@@ -909,41 +3777,46 @@ async function streamFileRange(args: {
     };
 
     socket.on("error", onError);
-    stream.on("error", onError);
-    stream.on("data", (chunk: Buffer) => {
-      sent += chunk.length;
+    input.on("error", onError);
+    input.on("data", (chunk: Buffer | string) => {
+      const bytes = typeof chunk === "string" ? Buffer.from(chunk) : chunk;
+      sent += bytes.length;
+      hasher.update(bytes);
       emitProgress(progressState, sent);
 
-      const writable = socket.write(chunk);
+      const writable = socket.write(bytes);
       if (!writable) {
-        stream.pause();
-        socket.once("drain", () => stream.resume());
+        input.pause();
+        socket.once("drain", () => input.resume());
       }
     });
-    stream.on("end", () => {
+    input.on("end", () => {
+      progressState.totalBytes = sent;
       emitProgress(progressState, sent, true);
       process.stdout.write("\n");
       socket.removeListener("error", onError);
       resolve();
     });
   });
+
+  return { sentBytes: sent, digest: hasher.digest("hex") };
 }
 
 async function writePayload(args: {
   payload: Buffer;
-  header: TransferHeader;
+  maxBytes: number;
   fileStream: WriteStream | null;
   hasher: ReturnType<typeof createHash>;
   progressState: ProgressEmitState;
   receivedRef: { get: () => number; set: (next: number) => void };
 }): Promise<void> {
-  const { payload, header, fileStream, hasher, progressState, receivedRef } = args;
+  const { payload, maxBytes, fileStream, hasher, progressState, receivedRef } = args;
   if (payload.length === 0) {
     return;
   }
 
   const nextReceived = receivedRef.get() + payload.length;
-  if (nextReceived > header.fileSize) {
+  if (nextReceived > maxBytes) {
     throw new Error("payload exceeds declared file size");
   }
 
@@ -1001,7 +3874,7 @@ function waitConnected(socket: Socket): Promise<void> {
   });
 }
 
-function createClientSocket(options: SendFileOptions): Socket {
+function createClientSocket(options: ClientConnectOptions): Socket {
   if (!options.tls?.enabled) {
     return createConnection({ host: options.host, port: options.port });
   }
@@ -1017,7 +3890,7 @@ function createClientSocket(options: SendFileOptions): Socket {
   });
 }
 
-async function verifyTlsPeerIfNeeded(socket: Socket, options: SendFileOptions): Promise<void> {
+async function verifyTlsPeerIfNeeded(socket: Socket, options: ClientConnectOptions): Promise<void> {
   if (!options.tls?.enabled) {
     return;
   }
@@ -1033,6 +3906,25 @@ async function verifyTlsPeerIfNeeded(socket: Socket, options: SendFileOptions):
   });
 }
 
+/** Checks a peer's pair code, counting a mismatch towards its lockout. */
+function checkPairCode(context: ReceiverContext, remoteAddress: string, incomingCode: string | undefined): boolean {
+  const { peerGuard, pairingState } = context;
+  if (peerGuard.isLockedOut(remoteAddress)) {
+    return false;
+  }
+  if (isPairCodeAccepted(pairingState, incomingCode)) {
+    peerGuard.pairCodeAccepted(remoteAddress);
+    return true;
+  }
+  const lockoutMs = peerGuard.pairCodeFailed(remoteAddress);
+  if (lockoutMs > 0) {
+    process.stdout.write(
+      `[receive] ${remoteAddress} locked out for ${Math.ceil(lockoutMs / 1000)}s after repeated pair code mismatches\n`
+    );
+  }
+  return false;
+}
+
 function isPairCodeAccepted(pairingState: PairingState, incomingCode: string | undefined): boolean {
   if (!pairingState.currentCode) {
     return true;
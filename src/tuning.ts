@@ -0,0 +1,90 @@
+import { formatBytes } from "./utils";
+
+const MIN_CHUNK_BYTES = 64 * 1024;
+const MAX_CHUNK_BYTES = 4 * 1024 * 1024;
+const MIN_WINDOW_BYTES = 256 * 1024;
+const MAX_WINDOW_BYTES = 64 * 1024 * 1024;
+const SAMPLE_INTERVAL_MS = 250;
+// Roughly one gigabit per millisecond of round trip.
+const INITIAL_WINDOW_BYTES_PER_RTT_MS = 128 * 1024;
+
+export interface TransferTuning {
+  chunkBytes: number;
+  windowBytes: number;
+  rttMs: number;
+  throughputBytesPerSec: number;
+  adjustments: number;
+}
+
+function clamp(value: number, min: number, max: number): number {
+  return Math.max(min, Math.min(max, Math.round(value)));
+}
+
+/**
+ * Picks the read size and the number of bytes allowed to queue on the socket
+ * from what the link actually delivers. The window starts at an estimate of
+ * the bandwidth-delay product and follows measured throughput: while it keeps
+ * up, reads and window grow; a sustained drop (congestion or loss on the path)
+ * shrinks them again so a lossy VPN link is not flooded with queued data.
+ */
+export class TransferTuner {
+  chunkBytes = MIN_CHUNK_BYTES;
+  windowBytes: number;
+  private adjustments = 0;
+  private bestBytesPerSec = 0;
+  private lastBytesPerSec = 0;
+  private sampleStartedAt = Date.now();
+  private sampleBytes = 0;
+
+  constructor(private readonly rttMs: number) {
+    this.windowBytes = clamp(Math.max(1, rttMs) * INITIAL_WINDOW_BYTES_PER_RTT_MS, MIN_WINDOW_BYTES, MAX_WINDOW_BYTES);
+  }
+
+  record(bytes: number): void {
+    this.sampleBytes += bytes;
+    const now = Date.now();
+    const elapsedMs = now - this.sampleStartedAt;
+    if (elapsedMs < SAMPLE_INTERVAL_MS) {
+      return;
+    }
+
+    const bytesPerSec = (this.sampleBytes * 1000) / elapsedMs;
+    this.sampleStartedAt = now;
+    this.sampleBytes = 0;
+    this.lastBytesPerSec = bytesPerSec;
+
+    if (bytesPerSec >= this.bestBytesPerSec * 0.95) {
+      this.bestBytesPerSec = Math.max(this.bestBytesPerSec, bytesPerSec);
+      // Keep two bandwidth-delay products queued so the pipe never drains.
+      const bdpBytes = (bytesPerSec * Math.max(1, this.rttMs)) / 1000;
+      this.adjust(this.chunkBytes * 2, Math.max(this.windowBytes, bdpBytes * 2, this.chunkBytes * 4));
+    } else if (bytesPerSec < this.bestBytesPerSec * 0.7) {
+      this.bestBytesPerSec *= 0.9;
+      this.adjust(this.chunkBytes / 2, this.windowBytes * 0.75);
+    }
+  }
+
+  stats(): TransferTuning {
+    return {
+      chunkBytes: this.chunkBytes,
+      windowBytes: this.windowBytes,
+      rttMs: this.rttMs,
+      throughputBytesPerSec: Math.round(this.lastBytesPerSec),
+      adjustments: this.adjustments
+    };
+  }
+
+  private adjust(chunkBytes: number, windowBytes: number): void {
+    const nextChunk = clamp(chunkBytes, MIN_CHUNK_BYTES, MAX_CHUNK_BYTES);
+    const nextWindow = clamp(windowBytes, Math.max(MIN_WINDOW_BYTES, nextChunk), MAX_WINDOW_BYTES);
+    if (nextChunk !== this.chunkBytes || nextWindow !== this.windowBytes) {
+      this.adjustments += 1;
+    }
+    this.chunkBytes = nextChunk;
+    this.windowBytes = nextWindow;
+  }
+}
+
+export function renderTuning(tuning: TransferTuning): string {
+  return `chunk=${formatBytes(tuning.chunkBytes)} window=${formatBytes(tuning.windowBytes)} rtt=${tuning.rttMs}ms adjustments=${tuning.adjustments}`;
+}
@@ -1,12 +1,32 @@
 import { createHash, Hash } from "crypto";
-import { createReadStream } from "fs";
+import { createReadStream, Stats } from "fs";
 import { promises as fsPromises } from "fs";
-import { basename, join, normalize, posix, relative, resolve, sep } from "path";
+import { basename, dirname, isAbsolute, join, normalize, posix, relative, resolve, sep } from "path";
+import { describeRegime, ProgressSnapshot } from "./progressAnalytics";
+import { EntryLink, EntryMetadata } from "./protocol";
+import { stripLongPathPrefix, toLongPath, windowsSafeRelativePath } from "./winPaths";
+import { readXattrs, xattrsSupported } from "./xattrs";
 
 export interface TransferEntry {
   absolutePath: string;
   relativePath: string;
   size: number;
+  link?: EntryLink;
+  metadata?: EntryMetadata;
+}
+
+/**
+ * `skip` leaves symlinks out (the default), `follow` sends what they point
+ * to and `preserve` recreates them on the receiver.
+ */
+export type SymlinkMode = "skip" | "follow" | "preserve";
+
+export interface BuildEntriesOptions {
+  symlinks?: SymlinkMode;
+  // Send later paths of a hard-linked file as links to the first one.
+  hardlinks?: boolean;
+  // Capture extended attributes (and macOS resource forks) of each file.
+  xattrs?: boolean;
 }
 
 export async function sha256File(filePath: string): Promise<string> {
@@ -47,12 +67,26 @@ export function formatBytes(bytes: number): string {
   return `${value.toFixed(1)} ${units[i]}`;
 }
 
-export function renderProgress(prefix: string, sent: number, total: number, startedAt: number): string {
+export function renderProgress(prefix: string, sent: number, total: number, stats: ProgressSnapshot): string {
   const pct = total === 0 ? 100 : (sent / total) * 100;
-  const elapsedSec = Math.max(1, (Date.now() - startedAt) / 1000);
-  const speed = sent / elapsedSec;
-  const remainingSec = speed <= 0 ? 0 : Math.max(0, (total - sent) / speed);
-  return `${prefix} ${pct.toFixed(1)}% (${formatBytes(sent)}/${formatBytes(total)}) ${formatBytes(speed)}/s ETA ${Math.ceil(remainingSec)}s`;
+  const eta = stats.etaSeconds === null ? "" : ` ETA ${stats.etaSeconds}s`;
+  return `${prefix} ${pct.toFixed(1)}% (${formatBytes(sent)}/${formatBytes(total)}) ${formatBytes(stats.bytesPerSecond)}/s${eta}${describeRegime(stats)}`;
+}
+
+export function renderStreamProgress(prefix: string, sent: number, stats: ProgressSnapshot): string {
+  return `${prefix} streaming ${formatBytes(sent)} ${formatBytes(stats.bytesPerSecond)}/s${describeRegime(stats)}`;
+}
+
+/** Per-connection throughput of a parallel transfer, appended to its progress line. */
+export function renderStreamStats(streams: Array<{ bytes: number; startedAt: number }>): string {
+  if (streams.length === 0) {
+    return "";
+  }
+  const now = Date.now();
+  const speeds = streams.map(
+    (stream) => `${formatBytes(stream.bytes / Math.max(1, (now - stream.startedAt) / 1000))}/s`
+  );
+  return ` [${streams.length} streams: ${speeds.join(", ")}]`;
 }
 
 export function normalizeTransferPath(input: string): string {
@@ -66,30 +100,50 @@ export function normalizeTransferPath(input: string): string {
   return normalized;
 }
 
+/**
+ * Maps a received relative path into `outputDir`. On Windows, names the
+ * filesystem cannot hold (`CON`, `a:b`, trailing dots) are rewritten and
+ * long results get the `\\?\` prefix so deep trees do not fail halfway.
+ */
 export function resolveOutputPath(outputDir: string, relativePath: string): string {
-  const normalized = normalizeTransferPath(relativePath);
+  let normalized = normalizeTransferPath(relativePath);
+  if (process.platform === "win32") {
+    normalized = windowsSafeRelativePath(normalized);
+  }
   const localRelative = normalized.split("/").join(sep);
-  const absoluteRoot = resolve(outputDir);
+  const absoluteRoot = resolve(stripLongPathPrefix(outputDir));
   const absoluteTarget = resolve(absoluteRoot, localRelative);
   const rel = relative(absoluteRoot, absoluteTarget);
   if (rel === "" || rel === "." || (!rel.startsWith("..") && !normalize(rel).startsWith(`..${sep}`))) {
-    return absoluteTarget;
+    return toLongPath(absoluteTarget);
   }
   throw new Error("path escapes output directory");
 }
 
-export async function buildTransferEntries(inputPath: string): Promise<TransferEntry[]> {
+export function entryMetadata(stat: { mode: number; mtimeMs: number }): EntryMetadata {
+  return { mode: stat.mode & 0o777, mtimeMs: Math.round(stat.mtimeMs) };
+}
+
+export async function buildTransferEntries(
+  inputPath: string,
+  options: BuildEntriesOptions = {}
+): Promise<TransferEntry[]> {
   const absoluteInput = resolve(inputPath);
   const stat = await fsPromises.stat(absoluteInput);
 
   if (stat.isFile()) {
-    return [
+    const entries: TransferEntry[] = [
       {
         absolutePath: absoluteInput,
         relativePath: basename(absoluteInput),
-        size: stat.size
+        size: stat.size,
+        metadata: entryMetadata(stat)
       }
     ];
+    if (options.xattrs) {
+      await attachXattrs(entries);
+    }
+    return entries;
   }
 
   if (!stat.isDirectory()) {
@@ -98,41 +152,134 @@ export async function buildTransferEntries(inputPath: string): Promise<TransferE
 
   const rootName = basename(absoluteInput);
   const entries: TransferEntry[] = [];
+  const symlinks = options.symlinks ?? "skip";
+  // First transfer path seen for each (device, inode) of a multiply linked file.
+  const firstLinkPaths = new Map<string, string>();
 
-  await walkDirectory(absoluteInput, async (filePath, fileSize) => {
-    const rel = relative(absoluteInput, filePath).split(sep).join("/");
+  const visitedDirs = new Set([await fsPromises.realpath(absoluteInput)]);
+  await walkDirectory(absoluteInput, symlinks, visitedDirs, async (item) => {
+    const rel = relative(absoluteInput, item.absolutePath).split(sep).join("/");
+    const relativePath = `${rootName}/${rel}`;
+    if (item.linkTarget !== undefined) {
+      const resolvedTarget = resolve(dirname(item.absolutePath), item.linkTarget);
+      const targetRel = relative(absoluteInput, resolvedTarget);
+      if (isAbsolute(item.linkTarget) || targetRel.startsWith("..") || isAbsolute(targetRel)) {
+        process.stdout.write(`[send] skipping symlink ${relativePath}: target is outside the sent folder\n`);
+        return;
+      }
+      entries.push({
+        absolutePath: item.absolutePath,
+        relativePath,
+        size: 0,
+        link: { type: "symlink", target: item.linkTarget.split(sep).join("/") },
+        metadata: { mtimeMs: Math.round(item.stat.mtimeMs) }
+      });
+      return;
+    }
+
+    const inode = `${item.stat.dev}:${item.stat.ino}`;
+    const firstPath = options.hardlinks && item.stat.nlink > 1 ? firstLinkPaths.get(inode) : undefined;
+    if (options.hardlinks && item.stat.nlink > 1 && !firstPath) {
+      firstLinkPaths.set(inode, relativePath);
+    }
     entries.push({
-      absolutePath: filePath,
-      relativePath: `${rootName}/${rel}`,
-      size: fileSize
+      absolutePath: item.absolutePath,
+      relativePath,
+      size: item.stat.size,
+      link: firstPath ? { type: "hardlink", target: firstPath } : undefined,
+      metadata: entryMetadata(item.stat)
     });
   });
 
   if (entries.length === 0) {
     throw new Error("directory has no files");
   }
+  if (options.xattrs) {
+    await attachXattrs(entries);
+  }
 
   entries.sort((a, b) => a.relativePath.localeCompare(b.relativePath));
+  // Hard links must follow the entry they point at.
+  const order = (entry: TransferEntry): number => (entry.link?.type === "hardlink" ? 1 : 0);
+  entries.sort((a, b) => order(a) - order(b));
   return entries;
 }
 
+async function attachXattrs(entries: TransferEntry[]): Promise<void> {
+  if (!xattrsSupported()) {
+    process.stdout.write(`[send] extended attributes are not supported on ${process.platform}\n`);
+    return;
+  }
+  for (const entry of entries) {
+    if (entry.link) {
+      continue;
+    }
+    const xattrs = await readXattrs(entry.absolutePath, (name) => {
+      process.stdout.write(`[send] ${entry.relativePath}: extended attribute ${name} is too large, skipped\n`);
+    });
+    if (Object.keys(xattrs).length > 0) {
+      entry.metadata = { ...entry.metadata, xattrs, platform: process.platform };
+    }
+  }
+}
+
+interface WalkedItem {
+  absolutePath: string;
+  stat: Stats;
+  // Set for symlinks that are preserved rather than followed.
+  linkTarget?: string;
+}
+
 async function walkDirectory(
   dirPath: string,
-  onFile: (absolutePath: string, fileSize: number) => Promise<void>
+  symlinks: SymlinkMode,
+  visitedDirs: Set<string>,
+  onFile: (item: WalkedItem) => Promise<void>
 ): Promise<void> {
   const items = await fsPromises.readdir(dirPath, { withFileTypes: true });
   items.sort((a, b) => a.name.localeCompare(b.name));
 
   for (const item of items) {
     const absolute = join(dirPath, item.name);
+    if (item.isSymbolicLink()) {
+      if (symlinks === "preserve") {
+        await onFile({
+          absolutePath: absolute,
+          stat: await fsPromises.lstat(absolute),
+          linkTarget: await fsPromises.readlink(absolute)
+        });
+        continue;
+      }
+      if (symlinks === "skip") {
+        continue;
+      }
+      let stat: Stats;
+      try {
+        stat = await fsPromises.stat(absolute);
+      } catch {
+        // Dangling link.
+        continue;
+      }
+      if (stat.isDirectory()) {
+        // Guard against link cycles.
+        const real = await fsPromises.realpath(absolute);
+        if (!visitedDirs.has(real)) {
+          visitedDirs.add(real);
+          await walkDirectory(absolute, symlinks, visitedDirs, onFile);
+        }
+      } else if (stat.isFile()) {
+        await onFile({ absolutePath: absolute, stat });
+      }
+      continue;
+    }
+
     if (item.isDirectory()) {
-      await walkDirectory(absolute, onFile);
+      await walkDirectory(absolute, symlinks, visitedDirs, onFile);
       continue;
     }
 
     if (item.isFile()) {
-      const stat = await fsPromises.stat(absolute);
-      await onFile(absolute, stat.size);
+      await onFile({ absolutePath: absolute, stat: await fsPromises.stat(absolute) });
     }
   }
 }
@@ -0,0 +1,132 @@
+import { ClientRequest, IncomingMessage, request as httpRequest } from "http";
+import { request as httpsRequest } from "https";
+import { once } from "events";
+
+/** A WebDAV folder (e.g. a Nextcloud `remote.php/dav/files/<user>/...`). */
+export interface WebDavTarget {
+  url: URL;
+  username?: string;
+  password?: string;
+}
+
+const REQUEST_TIMEOUT_MS = 30_000;
+
+export function parseWebDavTarget(raw: string, username?: string, password?: string): WebDavTarget {
+  let url: URL;
+  try {
+    url = new URL(raw.trim());
+  } catch {
+    throw new Error("WebDAV URL is invalid");
+  }
+  if (url.protocol !== "http:" && url.protocol !== "https:") {
+    throw new Error("WebDAV URL must start with http:// or https://");
+  }
+  if (url.username || url.password) {
+    throw new Error("WebDAV credentials must not be part of the URL");
+  }
+  if (!url.pathname.endsWith("/")) {
+    url.pathname += "/";
+  }
+  return { url, username: username || undefined, password: password || undefined };
+}
+
+/** Where `relativePath` (with `/` separators) lands under the target. */
+export function webDavResourceUrl(target: WebDavTarget, relativePath: string): URL {
+  const encoded = relativePath
+    .split("/")
+    .filter((part) => part.length > 0)
+    .map(encodeURIComponent)
+    .join("/");
+  return new URL(encoded, target.url);
+}
+
+function open(target: WebDavTarget, method: string, url: URL, headers: Record<string, string> = {}): ClientRequest {
+  if (target.username !== undefined) {
+    const credentials = Buffer.from(`${target.username}:${target.password ?? ""}`).toString("base64");
+    headers.Authorization = `Basic ${credentials}`;
+  }
+  const send = url.protocol === "https:" ? httpsRequest : httpRequest;
+  const request = send(url, { method, headers });
+  request.setTimeout(REQUEST_TIMEOUT_MS, () => {
+    request.destroy(new Error(`WebDAV ${method} timed out`));
+  });
+  return request;
+}
+
+async function statusOf(request: ClientRequest): Promise<number> {
+  const [response] = (await once(request, "response")) as [IncomingMessage];
+  response.resume();
+  return response.statusCode ?? 0;
+}
+
+async function simpleRequest(target: WebDavTarget, method: string, url: URL): Promise<number> {
+  const request = open(target, method, url);
+  const status = statusOf(request);
+  request.end();
+  return status;
+}
+
+function describeStatus(status: number): string {
+  return status === 401 || status === 403 ? `HTTP ${status} (check the WebDAV user and password)` : `HTTP ${status}`;
+}
+
+/** Creates the folders above `relativePath`; existing ones are fine. */
+export async function ensureWebDavFolders(target: WebDavTarget, relativePath: string): Promise<void> {
+  const parts = relativePath.split("/").filter((part) => part.length > 0);
+  for (let depth = 1; depth < parts.length; depth += 1) {
+    const url = webDavResourceUrl(target, `${parts.slice(0, depth).join("/")}/`);
+    const status = await simpleRequest(target, "MKCOL", url);
+    // 405: the folder already exists.
+    if (status !== 201 && status !== 405) {
+      throw new Error(`cannot create WebDAV folder ${url.pathname}: ${describeStatus(status)}`);
+    }
+  }
+}
+
+export async function deleteWebDavResource(target: WebDavTarget, url: URL): Promise<void> {
+  await simpleRequest(target, "DELETE", url).catch(() => undefined);
+}
+
+/**
+ * One PUT whose body is written as data arrives, so nothing is staged on
+ * local disk. `size` is sent as Content-Length when known; streams of unknown
+ * length use chunked encoding.
+ */
+export class WebDavUpload {
+  private readonly request: ClientRequest;
+  private readonly status: Promise<number>;
+
+  constructor(
+    target: WebDavTarget,
+    readonly url: URL,
+    size: number | null
+  ) {
+    this.request = open(
+      target,
+      "PUT",
+      url,
+      size === null ? { "Content-Type": "application/octet-stream" } : { "Content-Type": "application/octet-stream", "Content-Length": String(size) }
+    );
+    this.status = statusOf(this.request);
+    // Surfaced by `finish`; keeps an early server error from going unhandled.
+    this.status.catch(() => undefined);
+  }
+
+  async write(chunk: Buffer): Promise<void> {
+    if (!this.request.write(chunk)) {
+      await Promise.race([once(this.request, "drain"), this.status.then(() => undefined)]);
+    }
+  }
+
+  async finish(): Promise<void> {
+    this.request.end();
+    const status = await this.status;
+    if (status < 200 || status >= 300) {
+      throw new Error(`WebDAV upload failed: ${describeStatus(status)}`);
+    }
+  }
+
+  abort(): void {
+    this.request.destroy();
+  }
+}
@@ -0,0 +1,54 @@
+import { win32 } from "path";
+
+/**
+ * Win32 APIs reject longer paths unless they carry the `\\?\` prefix.
+ * Directories need room for an 8.3 file name, hence the margin.
+ */
+export const WINDOWS_MAX_PATH = 260 - 12;
+
+const RESERVED_NAME = /^(con|prn|aux|nul|conin\$|conout\$|com[0-9¹²³]|lpt[0-9¹²³])(\..*)?$/i;
+const INVALID_CHARS = /[<>:"|?*\u0000-\u001f]/g;
+
+/**
+ * Device names such as `CON` or `nul.txt` open the device instead of a
+ * file on Windows, whatever the extension.
+ */
+export function isReservedWindowsName(segment: string): boolean {
+  return RESERVED_NAME.test(segment.replace(/[. ]+$/, ""));
+}
+
+/**
+ * Rewrites one path segment into a name Windows can create: invalid
+ * characters become `_`, trailing dots and spaces (which Windows drops)
+ * are replaced and device names get a `_` prefix.
+ */
+export function windowsSafeSegment(segment: string): string {
+  let safe = segment.replace(INVALID_CHARS, "_").replace(/[. ]+$/, (tail) => "_".repeat(tail.length));
+  if (isReservedWindowsName(safe)) {
+    safe = `_${safe}`;
+  }
+  return safe;
+}
+
+/** Applies `windowsSafeSegment` to every segment of a `/`-separated path. */
+export function windowsSafeRelativePath(relativePath: string): string {
+  return relativePath.split("/").map(windowsSafeSegment).join("/");
+}
+
+/**
+ * Adds the `\\?\` (or `\\?\UNC\`) prefix to absolute Windows paths that
+ * are too long for the classic APIs; other paths are returned unchanged.
+ */
+export function toLongPath(absolutePath: string, platform: NodeJS.Platform = process.platform): string {
+  if (platform !== "win32" || absolutePath.length < WINDOWS_MAX_PATH || absolutePath.startsWith("\\\\?\\")) {
+    return absolutePath;
+  }
+  return win32.toNamespacedPath(absolutePath);
+}
+
+export function stripLongPathPrefix(path: string): string {
+  if (path.startsWith("\\\\?\\UNC\\")) {
+    return `\\\\${path.slice(8)}`;
+  }
+  return path.startsWith("\\\\?\\") ? path.slice(4) : path;
+}
@@ -0,0 +1,92 @@
+import { execFile } from "child_process";
+import { promisify } from "util";
+
+const run = promisify(execFile);
+
+/** Attributes beyond this budget are left out so the header stays small. */
+const MAX_XATTR_BYTES = 32 * 1024;
+
+/**
+ * Extended attributes keyed by name, values base64-encoded. On macOS this
+ * includes Finder info, quarantine flags and small resource forks. Linux
+ * only carries the `user.` namespace; the others need privileges.
+ */
+export type Xattrs = Record<string, string>;
+
+export function xattrsSupported(): boolean {
+  return process.platform === "darwin" || process.platform === "linux";
+}
+
+/**
+ * Reads the extended attributes of `filePath` with the platform tools
+ * (`xattr` on macOS, `getfattr` on Linux). Returns an empty set when the
+ * tools are missing or the file has none; `onSkip` hears about attributes
+ * left out because they do not fit.
+ */
+export async function readXattrs(filePath: string, onSkip?: (name: string) => void): Promise<Xattrs> {
+  let pairs: Array<[string, Buffer]>;
+  try {
+    pairs = process.platform === "darwin" ? await readMacXattrs(filePath) : await readLinuxXattrs(filePath);
+  } catch {
+    return {};
+  }
+  const result: Xattrs = {};
+  let budget = MAX_XATTR_BYTES;
+  for (const [name, value] of pairs) {
+    const encoded = value.toString("base64");
+    if (encoded.length + name.length > budget) {
+      onSkip?.(name);
+      continue;
+    }
+    budget -= encoded.length + name.length;
+    result[name] = encoded;
+  }
+  return result;
+}
+
+async function readMacXattrs(filePath: string): Promise<Array<[string, Buffer]>> {
+  const { stdout } = await run("xattr", [filePath]);
+  const names = stdout.split("\n").filter((name) => name.trim() !== "");
+  const pairs: Array<[string, Buffer]> = [];
+  for (const name of names) {
+    const { stdout: hex } = await run("xattr", ["-px", name, filePath], { maxBuffer: 64 * 1024 * 1024 });
+    pairs.push([name, Buffer.from(hex.replace(/\s+/g, ""), "hex")]);
+  }
+  return pairs;
+}
+
+async function readLinuxXattrs(filePath: string): Promise<Array<[string, Buffer]>> {
+  const { stdout } = await run("getfattr", ["--absolute-names", "-d", "-m", "^user\\.", "-e", "base64", filePath]);
+  const pairs: Array<[string, Buffer]> = [];
+  for (const line of stdout.split("\n")) {
+    const match = /^([^#=][^=]*)=0s(.*)$/.exec(line.trim());
+    if (match) {
+      pairs.push([match[1], Buffer.from(match[2], "base64")]);
+    }
+  }
+  return pairs;
+}
+
+/**
+ * Best effort: attributes the filesystem or platform refuses are skipped
+ * and their names returned.
+ */
+export async function writeXattrs(filePath: string, xattrs: Xattrs): Promise<string[]> {
+  const failed: string[] = [];
+  for (const [name, encoded] of Object.entries(xattrs)) {
+    const value = Buffer.from(encoded, "base64");
+    try {
+      if (process.platform === "darwin") {
+        await run("xattr", ["-wx", name, value.toString("hex"), filePath]);
+      } else {
+        if (!name.startsWith("user.")) {
+          throw new Error("unsupported namespace");
+        }
+        await run("setfattr", ["-n", name, "-v", `0s${encoded}`, filePath]);
+      }
+    } catch {
+      failed.push(name);
+    }
+  }
+  return failed;
+}
@@ -1,20 +1,63 @@
 import assert from "node:assert/strict";
-import { X509Certificate } from "node:crypto";
-import { mkdir, mkdtemp, readFile, rm, stat, writeFile } from "node:fs/promises";
+import { createPrivateKey, X509Certificate } from "node:crypto";
+import { createSocket } from "node:dgram";
+import {
+  chmod,
+  link,
+  lstat,
+  mkdir,
+  mkdtemp,
+  open,
+  readdir,
+  readFile,
+  readlink,
+  rm,
+  stat,
+  symlink,
+  utimes,
+  writeFile
+} from "node:fs/promises";
 import { createServer } from "node:net";
 import { tmpdir } from "node:os";
 import { dirname, join, resolve } from "node:path";
+import { Readable } from "node:stream";
 import test from "node:test";
-import { ListenOptions, sendEntries, sendFile, startReceiver } from "../src/transfer";
+import { PING_MAGIC } from "../src/constants";
+import { CONTENT_INDEX_FILE } from "../src/contentIndex";
+import { buildKnock, generateKnockSecret, isValidKnock, KnockGate, validateKnockSecret } from "../src/knock";
+import {
+  listRemoteShares,
+  ListenOptions,
+  probeReceiver,
+  pullFile,
+  ReceiverHandle,
+  sanitizeTransferNote,
+  sendEntries,
+  sendFile,
+  sendClipboardText,
+  sendStream,
+  sendTextMessage,
+  startReceiver
+} from "../src/transfer";
+import { ProgressAnalytics } from "../src/progressAnalytics";
+import { PingRateLimiter, PingReply, signPingReply, verifyPingReply } from "../src/ping";
+import { PeerGuard } from "../src/rateLimit";
 import { normalizeFingerprint } from "../src/tlsTrust";
 import { buildTransferEntries, sha256File } from "../src/utils";
+import {
+  isReservedWindowsName,
+  stripLongPathPrefix,
+  toLongPath,
+  windowsSafeRelativePath,
+  windowsSafeSegment
+} from "../src/winPaths";
 
 interface TestContext {
   rootDir: string;
   sourceDir: string;
   receiveDir: string;
   port: number;
-  stop: () => Promise<void>;
+  stop: ReceiverHandle;
 }
 
 type ReceiverOverrides = Omit<Partial<ListenOptions>, "port" | "outputDir" | "serviceName">;
@@ -66,7 +109,7 @@ async function setupReceiver(overrides: ReceiverOverrides = {}): Promise<TestCon
   await mkdir(receiveDir, { recursive: true });
   const port = await getFreePort();
 
-  let stop: (() => Promise<void>) | null = null;
+  let stop: ReceiverHandle | null = null;
   try {
     stop = await startReceiver({
       port,
@@ -178,6 +221,209 @@ test(
   }
 );
 
+test(
+  "e2e: resume partial file after sender renamed it",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const sourcePath = join(context.sourceDir, "renamed.bin");
+      await createSampleFile(sourcePath, 400 * 1024 + 9);
+      const digest = await sha256File(sourcePath);
+
+      const partialSize = 150 * 1024 + 5;
+      const partialName = `original.bin.${digest.slice(0, 16)}.local-sent.part`;
+      const sourceData = await readFile(sourcePath);
+      await writeFile(join(context.receiveDir, partialName), sourceData.subarray(0, partialSize));
+      await writeFile(
+        join(context.receiveDir, CONTENT_INDEX_FILE),
+        JSON.stringify({
+          version: 1,
+          entries: { [digest]: { path: partialName, size: sourceData.length, state: "partial" } }
+        })
+      );
+
+      const ack = await sendFile({
+        filePath: sourcePath,
+        relativePath: "renamed.bin",
+        host: "127.0.0.1",
+        port: context.port
+      });
+
+      assert.equal(ack.ok, true);
+      assert.equal(ack.resumedFrom, partialSize);
+      const receivedPath = join(context.receiveDir, "renamed.bin");
+      assert.equal(await sha256File(receivedPath), digest);
+      await assert.rejects(stat(join(context.receiveDir, partialName)));
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: identical re-send completes with already-have-it ack",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const sourcePath = join(context.sourceDir, "first.bin");
+      await createSampleFile(sourcePath, 200 * 1024 + 1);
+
+      const firstAck = await sendFile({
+        filePath: sourcePath,
+        relativePath: "first.bin",
+        host: "127.0.0.1",
+        port: context.port
+      });
+      assert.equal(firstAck.ok, true);
+      assert.equal(firstAck.deduplicated, undefined);
+
+      const secondAck = await sendFile({
+        filePath: sourcePath,
+        relativePath: "copy/second.bin",
+        host: "127.0.0.1",
+        port: context.port
+      });
+      assert.equal(secondAck.ok, true);
+      assert.equal(secondAck.deduplicated, true);
+      assert.equal(secondAck.resumedFrom, 200 * 1024 + 1);
+      assert.equal(secondAck.savedPath, firstAck.savedPath);
+      await assert.rejects(stat(join(context.receiveDir, "copy", "second.bin")));
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: stream content of unknown length",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const chunks = [Buffer.alloc(64 * 1024, 1), Buffer.alloc(3, 2), Buffer.alloc(128 * 1024 + 5, 3)];
+      const expected = Buffer.concat(chunks);
+
+      const ack = await sendStream({
+        input: Readable.from(chunks),
+        relativePath: "streamed/backup.tar",
+        host: "127.0.0.1",
+        port: context.port
+      });
+
+      assert.equal(ack.ok, true);
+      assert.equal(ack.receivedBytes, expected.length);
+      const receivedPath = join(context.receiveDir, "streamed", "backup.tar");
+      assert.deepEqual(await readFile(receivedPath), expected);
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: parallel streams reassemble one file",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver({ pairCode: "246810" });
+    try {
+      const sourcePath = join(context.sourceDir, "large.bin");
+      await createSampleFile(sourcePath, 3 * 1024 * 1024 + 7);
+
+      const ack = await sendFile({
+        filePath: sourcePath,
+        relativePath: "large.bin",
+        host: "127.0.0.1",
+        port: context.port,
+        pairCode: "246810",
+        streams: 3
+      });
+
+      assert.equal(ack.ok, true);
+      assert.equal(ack.streams, 3);
+      const receivedPath = join(context.receiveDir, "large.bin");
+      assert.equal(await sha256File(receivedPath), await sha256File(sourcePath));
+      const leftovers = (await readdir(context.receiveDir)).filter((name) => name.endsWith(".part"));
+      assert.deepEqual(leftovers, []);
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: links and metadata are recreated when preserved",
+  { concurrency: false, timeout: 20_000, skip: process.platform === "win32" },
+  async () => {
+    const context = await setupReceiver({ preserveMetadata: true });
+    try {
+      const folderPath = join(context.sourceDir, "linked");
+      await mkdir(folderPath, { recursive: true });
+      const scriptPath = join(folderPath, "run.sh");
+      await writeFile(scriptPath, "#!/bin/sh\necho hi\n");
+      await chmod(scriptPath, 0o750);
+      const mtime = new Date("2020-01-02T03:04:05Z");
+      await utimes(scriptPath, mtime, mtime);
+      await link(scriptPath, join(folderPath, "zz-copy.sh"));
+      await symlink("run.sh", join(folderPath, "latest"));
+      await symlink("../../outside", join(folderPath, "escape"));
+
+      const entries = await buildTransferEntries(folderPath, { symlinks: "preserve", hardlinks: true });
+      assert.deepEqual(
+        entries.map((entry) => [entry.relativePath, entry.link?.type ?? "file"]),
+        [
+          ["linked/latest", "symlink"],
+          ["linked/run.sh", "file"],
+          ["linked/zz-copy.sh", "hardlink"]
+        ]
+      );
+      await sendEntries({ entries, host: "127.0.0.1", port: context.port });
+
+      const receivedDir = join(context.receiveDir, "linked");
+      const received = await stat(join(receivedDir, "run.sh"));
+      assert.equal(received.mode & 0o777, 0o750);
+      assert.equal(received.mtimeMs, mtime.getTime());
+      assert.equal((await stat(join(receivedDir, "zz-copy.sh"))).ino, received.ino);
+      assert.equal((await lstat(join(receivedDir, "latest"))).isSymbolicLink(), true);
+      assert.equal(await readlink(join(receivedDir, "latest")), "run.sh");
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: sparse files send only their data",
+  { concurrency: false, timeout: 20_000, skip: process.platform === "win32" },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const sourcePath = join(context.sourceDir, "disk.img");
+      const handle = await open(sourcePath, "w");
+      await handle.truncate(32 * 1024 * 1024);
+      await handle.write(Buffer.alloc(64 * 1024, 7), 0, 64 * 1024, 0);
+      await handle.write(Buffer.alloc(64 * 1024, 9), 0, 64 * 1024, 20 * 1024 * 1024);
+      await handle.close();
+
+      const ack = await sendFile({
+        filePath: sourcePath,
+        relativePath: "disk.img",
+        host: "127.0.0.1",
+        port: context.port
+      });
+
+      assert.equal(ack.ok, true);
+      assert.equal(ack.receivedBytes, 128 * 1024);
+      const receivedPath = join(context.receiveDir, "disk.img");
+      assert.equal((await stat(receivedPath)).size, 32 * 1024 * 1024);
+      assert.equal(await sha256File(receivedPath), await sha256File(sourcePath));
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
 test(
   "e2e: pair-once rotation keeps batch transfer alive",
   { concurrency: false, timeout: 20_000 },
@@ -424,3 +670,415 @@ test(
     }
   }
 );
+
+test(
+  "e2e: atomic batches land all or nothing",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const folderPath = join(context.sourceDir, "project");
+      await mkdir(join(folderPath, "src"), { recursive: true });
+      await writeFile(join(folderPath, "package.json"), "{}\n");
+      await createSampleFile(join(folderPath, "src", "main.bin"), 64 * 1024 + 3);
+
+      const entries = await buildTransferEntries(folderPath);
+      await sendEntries({ entries, host: "127.0.0.1", port: context.port, atomic: true });
+      assert.equal(
+        await sha256File(join(context.receiveDir, "project", "src", "main.bin")),
+        await sha256File(join(folderPath, "src", "main.bin"))
+      );
+      assert.deepEqual(
+        (await readdir(context.receiveDir)).filter((name) => name.startsWith(".local-sent-batch-")),
+        []
+      );
+
+      // The second entry vanishes before it is sent, so the first must not stay.
+      const broken = entries.map((entry) => ({
+        ...entry,
+        relativePath: entry.relativePath.replace("project", "broken")
+      }));
+      broken[1] = { ...broken[1], absolutePath: join(folderPath, "missing.bin") };
+      await assert.rejects(
+        sendEntries({ entries: broken, host: "127.0.0.1", port: context.port, atomic: true }),
+        /batch rolled back/
+      );
+      const leftovers = (await readdir(context.receiveDir)).filter(
+        (name) => name === "broken" || name.startsWith(".local-sent-batch-")
+      );
+      assert.deepEqual(leftovers, []);
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: pull files only from explicitly shared folders",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const sharedDir = await mkdtemp(join(tmpdir(), "local-sent-share-"));
+    const context = await setupReceiver({ shares: { photos: sharedDir } });
+    const closed = await setupReceiver();
+    try {
+      await createSampleFile(join(sharedDir, "2024", "a.bin"), 96 * 1024 + 5);
+      const target = { host: "127.0.0.1", port: context.port };
+
+      const shares = await listRemoteShares(target);
+      assert.deepEqual(shares.map((entry) => entry.name), ["photos"]);
+      const listing = await listRemoteShares({ ...target, remotePath: "photos/2024" });
+      assert.deepEqual(
+        listing.map((entry) => [entry.name, entry.isDir, entry.size]),
+        [["a.bin", false, 96 * 1024 + 5]]
+      );
+
+      const pulled = await pullFile({ ...target, remotePath: "photos/2024/a.bin", destination: context.sourceDir });
+      assert.equal(pulled.savedPath, join(context.sourceDir, "a.bin"));
+      assert.equal(await sha256File(pulled.savedPath), await sha256File(join(sharedDir, "2024", "a.bin")));
+
+      await assert.rejects(
+        pullFile({ ...target, remotePath: "photos/../../etc/passwd", destination: context.sourceDir }),
+        /invalid relative path/
+      );
+      await assert.rejects(
+        listRemoteShares({ host: "127.0.0.1", port: closed.port }),
+        /receiver shares no folders/
+      );
+    } finally {
+      await teardown(closed);
+      await teardown(context);
+      await rm(sharedDir, { recursive: true, force: true });
+    }
+  }
+);
+
+test(
+  "e2e: restricted shares are hidden from other peers",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const sharedDir = await mkdtemp(join(tmpdir(), "local-sent-share-"));
+    await assert.rejects(
+      setupReceiver({ shares: { family: sharedDir }, shareAllow: { family: ["192.0.2.7"] } }),
+      /need a pair code/
+    );
+    const context = await setupReceiver({
+      pairCode: "135790",
+      shares: { open: sharedDir, family: sharedDir },
+      shareAllow: { family: ["192.0.2.7"] }
+    });
+    try {
+      await writeFile(join(sharedDir, "note.txt"), "hello\n");
+      const target = { host: "127.0.0.1", port: context.port, pairCode: "135790" };
+      const shares = await listRemoteShares(target);
+      assert.deepEqual(shares.map((entry) => entry.name), ["open"]);
+      await assert.rejects(
+        pullFile({ ...target, remotePath: "family/note.txt", destination: context.sourceDir }),
+        /no shared folder named family/
+      );
+      await assert.rejects(context.stop.update({ pairCode: null }), /need a pair code/);
+
+      await context.stop.update({ shares: { family: sharedDir }, shareAllow: {} });
+      const pulled = await pullFile({ ...target, remotePath: "family/note.txt", destination: context.sourceDir });
+      assert.equal(await readFile(pulled.savedPath, "utf8"), "hello\n");
+    } finally {
+      await teardown(context);
+      await rm(sharedDir, { recursive: true, force: true });
+    }
+  }
+);
+
+test(
+  "e2e: text messages need the pair code and leave no files",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver({ pairCode: "246810" });
+    try {
+      const target = { host: "127.0.0.1", port: context.port, text: "sending now", from: "laptop" };
+      await assert.rejects(sendTextMessage(target), /pair code mismatch/);
+      await sendTextMessage({ ...target, pairCode: "246810" });
+      await assert.rejects(sendTextMessage({ ...target, text: "  " }), /message is empty/);
+      assert.deepEqual(await readdir(context.receiveDir), []);
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: dry runs check the pair code and free space without writing",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver({ pairCode: "246810" });
+    try {
+      const target = { host: "127.0.0.1", port: context.port, files: 2, totalBytes: 1024 };
+      await assert.rejects(probeReceiver(target), /pair code mismatch/);
+      const { freeBytes } = await probeReceiver({ ...target, pairCode: "246810" });
+      assert.ok(typeof freeBytes === "number" && freeBytes >= 1024);
+      await assert.rejects(
+        probeReceiver({ ...target, pairCode: "246810", totalBytes: Number.MAX_SAFE_INTEGER }),
+        /not enough free space/
+      );
+      assert.deepEqual(await readdir(context.receiveDir), []);
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: repeated pair code mismatches lock the peer out",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver({ pairCode: "246810" });
+    try {
+      const target = { host: "127.0.0.1", port: context.port, text: "guess", from: "laptop" };
+      for (let attempt = 0; attempt < 5; attempt += 1) {
+        await assert.rejects(sendTextMessage({ ...target, pairCode: String(100000 + attempt) }), /pair code mismatch/);
+      }
+      // Even the right code is refused until the lockout ends.
+      await assert.rejects(sendTextMessage({ ...target, pairCode: "246810" }));
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: clipboard text is only accepted from listed peers",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const target = { host: "127.0.0.1", port: context.port, text: "copied\ntext" };
+      await assert.rejects(sendClipboardText(target), /clipboard sync is off/);
+      await context.stop.update({ clipboardFrom: ["127.0.0.1"] });
+      await sendClipboardText(target);
+      await assert.rejects(
+        sendClipboardText({ ...target, text: "x".repeat(256 * 1024 + 1) }),
+        /larger than/
+      );
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: a transfer note arrives with the file and is cleaned up",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const filePath = join(context.sourceDir, "contract.pdf");
+      await createSampleFile(filePath, 16 * 1024);
+      await sendFile({
+        filePath,
+        relativePath: "contract.pdf",
+        host: "127.0.0.1",
+        port: context.port,
+        note: { text: "signed contract", tags: ["work", "legal"] }
+      });
+      assert.equal(await sha256File(join(context.receiveDir, "contract.pdf")), await sha256File(filePath));
+
+      assert.deepEqual(sanitizeTransferNote({ text: " line one\nline two ", tags: ["a", " a ", "", "b"] }), {
+        text: "line one line two",
+        tags: ["a", "b"]
+      });
+      assert.equal(sanitizeTransferNote({ text: "  ", tags: [] }), undefined);
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test(
+  "e2e: a running receiver picks up a new output folder and pair code",
+  { concurrency: false, timeout: 20_000 },
+  async () => {
+    const context = await setupReceiver();
+    try {
+      const filePath = join(context.sourceDir, "first.bin");
+      await createSampleFile(filePath, 32 * 1024);
+      const movedDir = join(context.rootDir, "moved");
+      await context.stop.update({ outputDir: movedDir, pairCode: "123456", maxReceiveBytesPerSecond: 1024 * 1024 });
+
+      await assert.rejects(
+        sendFile({ filePath, relativePath: "first.bin", host: "127.0.0.1", port: context.port })
+      );
+      await sendFile({
+        filePath,
+        relativePath: "first.bin",
+        host: "127.0.0.1",
+        port: context.port,
+        pairCode: "123456"
+      });
+      assert.equal(await sha256File(join(movedDir, "first.bin")), await sha256File(filePath));
+      assert.deepEqual(
+        (await readdir(context.receiveDir)).filter((name) => name !== CONTENT_INDEX_FILE),
+        []
+      );
+
+      await context.stop.update({ pairCode: null, maxReceiveBytesPerSecond: 0 });
+      await sendFile({ filePath, relativePath: "second.bin", host: "127.0.0.1", port: context.port });
+      assert.equal(await sha256File(join(movedDir, "second.bin")), await sha256File(filePath));
+    } finally {
+      await teardown(context);
+    }
+  }
+);
+
+test("windows paths: reserved and invalid names are rewritten", () => {
+  assert.equal(isReservedWindowsName("CON"), true);
+  assert.equal(isReservedWindowsName("nul.txt"), true);
+  assert.equal(isReservedWindowsName("com1.tar.gz"), true);
+  assert.equal(isReservedWindowsName("console.log"), false);
+  assert.equal(windowsSafeSegment("aux"), "_aux");
+  assert.equal(windowsSafeSegment("a:b?.txt"), "a_b_.txt");
+  assert.equal(windowsSafeSegment("notes. "), "notes__");
+  assert.equal(windowsSafeRelativePath("node_modules/con/index.js"), "node_modules/_con/index.js");
+});
+
+test("windows paths: long paths get the namespace prefix", () => {
+  const short = "C:\\Users\\me\\received\\a.txt";
+  assert.equal(toLongPath(short, "win32"), short);
+
+  const deep = `C:\\Users\\me\\received\\${"node_modules\\pkg\\".repeat(20)}index.js`;
+  assert.equal(toLongPath(deep, "win32"), `\\\\?\\${deep}`);
+  assert.equal(toLongPath(toLongPath(deep, "win32"), "win32"), `\\\\?\\${deep}`);
+  assert.equal(toLongPath(deep, "linux"), deep);
+
+  const unc = `\\\\nas\\share\\${"x".repeat(300)}`;
+  assert.equal(toLongPath(unc, "win32"), `\\\\?\\UNC\\nas\\share\\${"x".repeat(300)}`);
+  assert.equal(stripLongPathPrefix(toLongPath(unc, "win32")), unc);
+  assert.equal(stripLongPathPrefix(toLongPath(deep, "win32")), deep);
+});
+
+test("progress analytics: smoothed ETA, stalls and regimes", () => {
+  const analytics = new ProgressAnalytics(0);
+  analytics.observe(1000, 250);
+  analytics.observe(2000, 500);
+  assert.deepEqual(analytics.snapshot(10_000, 500), {
+    bytesPerSecond: 4000,
+    etaSeconds: 2,
+    stalled: false,
+    regime: "starting"
+  });
+
+  // One slow sample moves the rate only part of the way.
+  analytics.observe(3000, 2500);
+  const slowed = analytics.snapshot(10_000, 2500);
+  assert.equal(slowed.bytesPerSecond, 2950);
+  assert.equal(slowed.etaSeconds, 3);
+  assert.equal(slowed.regime, "steady");
+
+  const stalled = analytics.snapshot(10_000, 7500);
+  assert.equal(stalled.stalled, true);
+  assert.equal(stalled.etaSeconds, null);
+  assert.equal(stalled.regime, "stalled");
+  assert.equal(analytics.snapshot(null, 7500).etaSeconds, null);
+});
+
+test("knock: stale, foreign and replayed knocks are refused", async () => {
+  const secret = generateKnockSecret();
+  const now = Date.now();
+  const packet = buildKnock(secret, 4000, now);
+  assert.equal(isValidKnock(packet, 4000, secret, now), true);
+  assert.equal(isValidKnock(packet, 4001, secret, now), false);
+  assert.equal(isValidKnock(packet, 4000, generateKnockSecret(), now), false);
+  assert.equal(isValidKnock(packet, 4000, secret, now + 61_000), false);
+  assert.equal(isValidKnock(packet, 4000, secret, now - 61_000), false);
+  assert.equal(isValidKnock({ ...packet, ts: now + 1 }, 4000, secret, now), false);
+  assert.throws(() => validateKnockSecret("123456"));
+
+  const probe = createSocket("udp4");
+  await new Promise<void>((resolve) => probe.bind(0, "127.0.0.1", () => resolve()));
+  const port = probe.address().port;
+  await new Promise<void>((resolve) => probe.close(() => resolve()));
+
+  const opened: boolean[] = [];
+  const gate = new KnockGate({ port, host: "127.0.0.1", secret, onOpenChange: (open) => opened.push(open) });
+  await gate.start();
+  const sender = createSocket("udp4");
+  try {
+    assert.equal(gate.admit("127.0.0.1"), false);
+    const knock = Buffer.from(JSON.stringify(buildKnock(secret, port)), "utf8");
+    for (let attempt = 0; attempt < 2; attempt += 1) {
+      await new Promise<void>((resolve, reject) =>
+        sender.send(knock, port, "127.0.0.1", (err) => (err ? reject(err) : resolve()))
+      );
+    }
+    await new Promise((resolve) => setTimeout(resolve, 200));
+    // The replayed copy is dropped, so the port opens once.
+    assert.deepEqual(opened, [true]);
+    assert.equal(gate.admit("127.0.0.1"), true);
+    assert.equal(gate.admit("127.0.0.2"), false);
+  } finally {
+    sender.close();
+    gate.close();
+  }
+});
+
+test("peer guard: lockouts double and connections are capped per address", () => {
+  const guard = new PeerGuard({ maxConnections: 2, maxPairCodeFailures: 3, lockoutMs: 1000, maxLockoutMs: 3000 });
+  const now = 1_000_000;
+  assert.equal(guard.admit("10.0.0.1", now), true);
+  assert.equal(guard.admit("10.0.0.1", now), true);
+  assert.equal(guard.admit("10.0.0.1", now), false);
+  assert.equal(guard.admit("10.0.0.2", now), true);
+  guard.release("10.0.0.1");
+  assert.equal(guard.admit("10.0.0.1", now), true);
+
+  assert.equal(guard.pairCodeFailed("10.0.0.3", now), 0);
+  assert.equal(guard.pairCodeFailed("10.0.0.3", now), 0);
+  assert.equal(guard.pairCodeFailed("10.0.0.3", now), 1000);
+  assert.equal(guard.isLockedOut("10.0.0.3", now + 999), true);
+  assert.equal(guard.admit("10.0.0.3", now + 999), false);
+  assert.equal(guard.isLockedOut("10.0.0.3", now + 1000), false);
+  for (let attempt = 0; attempt < 2; attempt += 1) {
+    guard.pairCodeFailed("10.0.0.3", now + 1000);
+  }
+  assert.equal(guard.pairCodeFailed("10.0.0.3", now + 1000), 2000);
+  for (let attempt = 0; attempt < 2; attempt += 1) {
+    guard.pairCodeFailed("10.0.0.3", now + 3000);
+  }
+  assert.equal(guard.pairCodeFailed("10.0.0.3", now + 3000), 3000);
+  guard.pairCodeAccepted("10.0.0.3");
+  assert.equal(guard.isLockedOut("10.0.0.3", now + 3000), false);
+});
+
+test("ping: replies with a wrong nonce, tampered fields or a foreign certificate are dropped", async () => {
+  const signer = {
+    cert: new X509Certificate(await readFile(TLS_A_CERT)).raw,
+    key: createPrivateKey(await readFile(TLS_A_KEY))
+  };
+  const nonce = Buffer.alloc(16, 7).toString("base64");
+  const reply: PingReply = { magic: PING_MAGIC, nonce, name: "desk", port: 37373, tls: true };
+  const signed = signPingReply(reply, signer);
+
+  assert.equal(verifyPingReply(reply, nonce), undefined);
+  assert.equal(verifyPingReply(signed, nonce), await readCertFingerprint(TLS_A_CERT));
+  assert.equal(verifyPingReply(signed, Buffer.alloc(16, 8).toString("base64")), null);
+  assert.equal(verifyPingReply({ ...signed, magic: "OTHER" }, nonce), null);
+  assert.equal(verifyPingReply({ ...signed, port: 37374 }, nonce), null);
+  assert.equal(verifyPingReply({ ...signed, name: "laptop" }, nonce), null);
+  assert.equal(verifyPingReply({ ...signed, signature: undefined }, nonce), null);
+
+  // Signed by A but claiming B's certificate.
+  const foreignCert = new X509Certificate(await readFile(TLS_B_CERT)).raw.toString("base64");
+  assert.equal(verifyPingReply({ ...signed, cert: foreignCert }, nonce), null);
+});
+
+test("ping: the rate limiter caps replies per source and per window", () => {
+  const limiter = new PingRateLimiter();
+  const now = 1_000_000;
+  for (let attempt = 0; attempt < 3; attempt += 1) {
+    assert.equal(limiter.allow("10.0.0.1", now), true);
+  }
+  assert.equal(limiter.allow("10.0.0.1", now), false);
+  for (let source = 2; source <= 28; source += 1) {
+    assert.equal(limiter.allow(`10.0.0.${source}`, now + 1), true);
+  }
+  assert.equal(limiter.allow("10.0.0.99", now + 9_999), false);
+  assert.equal(limiter.allow("10.0.0.1", now + 10_000), true);
+  assert.equal(limiter.allow("10.0.0.99", now + 10_000), true);
+});